@@ -0,0 +1,35 @@
+//! Benchmarks of the operators over the bundled datasets, run with `cargo bench` from the root of
+//! the repository so that the `data` directory is found.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use toy_query_engine::bench::{run_plan, warm_context, BENCH_PLANS};
+
+/// Benchmarks the [`BENCH_PLANS`] of the `group`, against datasets that are already loaded.
+fn bench_group(c: &mut Criterion, group: &str) {
+    let mut context = warm_context();
+    let mut benchmarks = c.benchmark_group(group);
+    for plan in BENCH_PLANS.iter().filter(|plan| plan.group == group) {
+        let operator = plan.operator();
+        benchmarks.bench_function(plan.name, |b| {
+            b.iter(|| black_box(run_plan(black_box(&operator), &mut context)))
+        });
+    }
+    benchmarks.finish();
+}
+
+fn bench_join(c: &mut Criterion) {
+    bench_group(c, "join");
+}
+
+fn bench_sort(c: &mut Criterion) {
+    bench_group(c, "sort");
+}
+
+fn bench_countby(c: &mut Criterion) {
+    bench_group(c, "countby");
+}
+
+criterion_group!(benches, bench_join, bench_sort, bench_countby);
+criterion_main!(benches);
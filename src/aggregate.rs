@@ -0,0 +1,316 @@
+use std::fmt::Display;
+
+use crate::table::Cell;
+
+/// The column COUNT is given to count the rows of its input rather than the values of a column,
+/// e.g. `FROM city.csv COUNT *`, producing the column `COUNT(*)`.
+pub const ALL_ROWS: &str = "*";
+
+/// Functions that combine all the values of a column into a single value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFunction {
+    /// The sum of the values.
+    Sum,
+    /// The arithmetic mean of the values.
+    Avg,
+    /// The smallest value.
+    Min,
+    /// The largest value.
+    Max,
+    /// The number of values that aren't missing.
+    Count,
+}
+
+impl Display for AggregateFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl AggregateFunction {
+    /// Returns the keyword used to invoke the aggregate function, e.g. "SUM".
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+            AggregateFunction::Count => "COUNT",
+        }
+    }
+
+    /// Looks up an aggregate function by the keyword used to invoke it, e.g. "SUM".
+    pub fn from_name(name: &str) -> Option<AggregateFunction> {
+        match name {
+            "SUM" => Some(AggregateFunction::Sum),
+            "AVG" => Some(AggregateFunction::Avg),
+            "MIN" => Some(AggregateFunction::Min),
+            "MAX" => Some(AggregateFunction::Max),
+            "COUNT" => Some(AggregateFunction::Count),
+            _ => None,
+        }
+    }
+
+    /// Combines the `values` into a single value. Missing values are ignored.
+    ///
+    /// # Arguments
+    /// `values` : The values to combine. Must all be numbers or missing, except for COUNT, which
+    /// counts values of any type.
+    ///
+    /// # Returns
+    /// On success: The combined value. The sum, minimum, and maximum of integers are a
+    /// [`Cell::Int64`]; if any value is a decimal number, or for the average, the result is a
+    /// [`Cell::Float64`]. If there are no values to combine, a missing value ([`Cell::OptInt64`]
+    /// of `None`) is returned, except for COUNT, which returns 0.
+    /// On failure: A message describing why the values can't be combined.
+    pub fn apply<'a, I: Iterator<Item = &'a Cell>>(&self, values: I) -> Result<Cell, String> {
+        if *self == AggregateFunction::Count {
+            let count = values.filter(|value| !value.is_null()).count();
+            return Ok(Cell::Int64(count as i64));
+        }
+
+        let mut integers = Vec::new();
+        let mut decimals = Vec::new();
+        for value in values {
+            match value {
+                Cell::Int64(val) | Cell::OptInt64(Some(val)) => integers.push(*val),
+                Cell::Float64(val) => decimals.push(*val),
+                Cell::OptInt64(None) | Cell::Null => (),
+                Cell::String(val) => {
+                    return Err(format!("{} is not a number.", val));
+                }
+            }
+        }
+        if integers.is_empty() && decimals.is_empty() {
+            return Ok(Cell::OptInt64(None));
+        }
+
+        // Any decimal number makes the whole computation decimal.
+        if !decimals.is_empty() {
+            decimals.extend(integers.iter().map(|val| *val as f64));
+            let sum = decimals.iter().sum::<f64>();
+            return Ok(Cell::Float64(match self {
+                AggregateFunction::Sum => sum,
+                AggregateFunction::Avg => sum / decimals.len() as f64,
+                AggregateFunction::Min => decimals.iter().copied().min_by(f64::total_cmp).unwrap(),
+                AggregateFunction::Max => decimals.iter().copied().max_by(f64::total_cmp).unwrap(),
+                AggregateFunction::Count => unreachable!(),
+            }));
+        }
+
+        let sum = || {
+            integers
+                .iter()
+                .try_fold(0i64, |sum, val| sum.checked_add(*val))
+                .ok_or_else(|| "The sum is too large to be represented.".to_string())
+        };
+        Ok(match self {
+            AggregateFunction::Sum => Cell::Int64(sum()?),
+            AggregateFunction::Avg => Cell::Float64(
+                integers.iter().map(|val| *val as f64).sum::<f64>() / integers.len() as f64,
+            ),
+            AggregateFunction::Min => Cell::Int64(*integers.iter().min().unwrap()),
+            AggregateFunction::Max => Cell::Int64(*integers.iter().max().unwrap()),
+            AggregateFunction::Count => unreachable!(),
+        })
+    }
+}
+
+/// An aggregate function applied to a column, e.g. `SUM(CityPop)`. Computed per group by the
+/// GROUPBY operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    /// How the values are combined.
+    pub function: AggregateFunction,
+    /// The name of the column whose values are combined.
+    pub column: String,
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}({})", self.function, self.column))
+    }
+}
+
+/// Parses a comma separated list of aggregates, e.g. `SUM(CityPop),COUNT(CityID)`.
+///
+/// # Arguments
+/// `text` : The text to parse.
+///
+/// # Returns
+/// The parsed [`Aggregate`]s, or an error message describing why `text` is malformed.
+pub fn parse_aggregate_list(text: &str) -> Result<Vec<Aggregate>, String> {
+    text.split(',')
+        .map(|aggregate| {
+            let parsed = aggregate.split_once('(').and_then(|(name, rest)| {
+                let column = rest.strip_suffix(')')?;
+                let function = AggregateFunction::from_name(name)?;
+                (!column.is_empty() && !column.contains(['(', ')'])).then(|| Aggregate {
+                    function,
+                    column: column.to_string(),
+                })
+            });
+            parsed.ok_or(format!(
+                "Invalid aggregate: {}. Must be one of SUM, AVG, MIN, MAX, or COUNT applied to a column, e.g. SUM(CityPop).",
+                aggregate
+            ))
+        })
+        .collect()
+}
+
+#[test]
+fn test_aggregate_function_from_name() {
+    assert_eq!(
+        AggregateFunction::from_name("SUM"),
+        Some(AggregateFunction::Sum)
+    );
+    assert_eq!(
+        AggregateFunction::from_name("MAX"),
+        Some(AggregateFunction::Max)
+    );
+    assert_eq!(
+        AggregateFunction::from_name("COUNT"),
+        Some(AggregateFunction::Count)
+    );
+    assert_eq!(AggregateFunction::from_name("sum"), None);
+}
+
+#[test]
+fn test_aggregate_function_apply() {
+    let values = [
+        Cell::Int64(4),
+        Cell::OptInt64(None),
+        Cell::OptInt64(Some(-1)),
+        Cell::Int64(3),
+    ];
+    assert_eq!(
+        AggregateFunction::Sum.apply(values.iter()),
+        Ok(Cell::Int64(6))
+    );
+    assert_eq!(
+        AggregateFunction::Avg.apply(values.iter()),
+        Ok(Cell::Float64(2.0))
+    );
+    assert_eq!(
+        AggregateFunction::Min.apply(values.iter()),
+        Ok(Cell::Int64(-1))
+    );
+    assert_eq!(
+        AggregateFunction::Max.apply(values.iter()),
+        Ok(Cell::Int64(4))
+    );
+    assert_eq!(
+        AggregateFunction::Avg.apply([Cell::Int64(1), Cell::Int64(2)].iter()),
+        Ok(Cell::Float64(1.5))
+    );
+}
+
+#[test]
+fn test_aggregate_function_apply_decimals() {
+    let values = [
+        Cell::Float64(1.5),
+        Cell::Null,
+        Cell::Int64(3),
+        Cell::Float64(-0.25),
+    ];
+    assert_eq!(
+        AggregateFunction::Sum.apply(values.iter()),
+        Ok(Cell::Float64(4.25))
+    );
+    assert_eq!(
+        AggregateFunction::Avg.apply(values.iter()),
+        Ok(Cell::Float64(4.25 / 3.0))
+    );
+    assert_eq!(
+        AggregateFunction::Min.apply(values.iter()),
+        Ok(Cell::Float64(-0.25))
+    );
+    assert_eq!(
+        AggregateFunction::Max.apply(values.iter()),
+        Ok(Cell::Float64(3.0))
+    );
+    assert_eq!(
+        AggregateFunction::Count.apply(values.iter()),
+        Ok(Cell::Int64(3))
+    );
+}
+
+#[test]
+fn test_aggregate_function_apply_no_values() {
+    assert_eq!(
+        AggregateFunction::Sum.apply([].iter()),
+        Ok(Cell::OptInt64(None))
+    );
+    assert_eq!(
+        AggregateFunction::Max.apply([Cell::OptInt64(None)].iter()),
+        Ok(Cell::OptInt64(None))
+    );
+}
+
+#[test]
+fn test_aggregate_function_apply_errors() {
+    assert_eq!(
+        AggregateFunction::Sum.apply([Cell::Int64(1), Cell::String("Kabul".to_string())].iter()),
+        Err("Kabul is not a number.".to_string())
+    );
+    assert_eq!(
+        AggregateFunction::Sum.apply([Cell::Int64(i64::MAX), Cell::Int64(1)].iter()),
+        Err("The sum is too large to be represented.".to_string())
+    );
+}
+
+#[test]
+fn test_aggregate_function_apply_count() {
+    let values = [
+        Cell::String("Kabul".to_string()),
+        Cell::OptInt64(None),
+        Cell::Int64(3),
+        Cell::Null,
+    ];
+    assert_eq!(
+        AggregateFunction::Count.apply(values.iter()),
+        Ok(Cell::Int64(2))
+    );
+    assert_eq!(
+        AggregateFunction::Count.apply([].iter()),
+        Ok(Cell::Int64(0))
+    );
+}
+
+#[test]
+fn test_parse_aggregate_list() {
+    assert_eq!(
+        parse_aggregate_list("SUM(CityPop),COUNT(CityID)"),
+        Ok(vec![
+            Aggregate {
+                function: AggregateFunction::Sum,
+                column: "CityPop".to_string(),
+            },
+            Aggregate {
+                function: AggregateFunction::Count,
+                column: "CityID".to_string(),
+            },
+        ])
+    );
+    assert_eq!(
+        parse_aggregate_list("MAX(CityPop)").unwrap()[0].to_string(),
+        "MAX(CityPop)"
+    );
+}
+
+#[test]
+fn test_parse_aggregate_list_malformed() {
+    for text in [
+        "SUM",
+        "SUM()",
+        "TOTAL(CityPop)",
+        "SUM(CityPop",
+        "SUM(MAX(CityPop))",
+        "",
+    ] {
+        assert_eq!(
+            parse_aggregate_list(text),
+            Err(format!("Invalid aggregate: {}. Must be one of SUM, AVG, MIN, MAX, or COUNT applied to a column, e.g. SUM(CityPop).", text))
+        );
+    }
+}
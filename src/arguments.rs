@@ -0,0 +1,385 @@
+use encoding_rs::Encoding;
+use toy_query_engine::data::parse_encoding;
+use toy_query_engine::settings::OutputFormat;
+
+/// Options passed to the toy-query-engine on the command line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Arguments {
+    /// `--profile-out <path>`: Write the execution statistics of every query in the session to
+    /// `path` as JSON.
+    pub profile_out: Option<String>,
+    /// `--script <path>`: Load the user-defined functions in the rhai script at `path` on
+    /// startup, so they can be called from SELECT expressions.
+    pub script: Option<String>,
+    /// `-c <query>` or `--command <query>`: Run the query, print its results, and exit instead of
+    /// starting the interactive prompt.
+    pub command: Option<String>,
+    /// `--file <path>`: Run the queries in the file at `path`, one per line, and exit instead of
+    /// starting the interactive prompt.
+    pub file: Option<String>,
+    /// `--encoding <label>`: Read the CSV files in the encoding named by `label`, e.g. `latin1`,
+    /// instead of UTF-8. The bundled datasets, and the files loaded with an ENCODING of their own,
+    /// aren't affected.
+    pub encoding: Option<&'static Encoding>,
+    /// `-q` or `--quiet`: Don't print the welcome banner and the goodbye message, even in a
+    /// terminal. They are never printed when the queries are piped in.
+    pub quiet: bool,
+    /// `--format <name>`: Print the results as `table`, `csv`, `jsonl`, or `markdown`, like
+    /// `\format` does.
+    pub format: Option<OutputFormat>,
+    /// `--serve <address>`: Answer the queries sent to `POST /query` at `address`, e.g.
+    /// `0.0.0.0:8080`, instead of starting the interactive prompt.
+    pub serve: Option<String>,
+    /// `--allow-origin <origin>`: Let the web pages served from `origin`, e.g.
+    /// `https://dashboard.example.com`, send queries to the `--serve` server and read its
+    /// responses. May be given several times. Browsers can't query the server from any other
+    /// origin.
+    pub allow_origins: Vec<String>,
+    /// `--listen <address>`: Accept REPL sessions from any number of clients connecting to
+    /// `address`, e.g. `0.0.0.0:9000`, each with its own saved results, datasets, and settings,
+    /// instead of starting the interactive prompt.
+    pub listen: Option<String>,
+}
+
+/// The usage message printed when the command line arguments are malformed.
+pub const C_USAGE_MESSAGE: &str =
+    "Usage: toy-query-engine [--profile-out <path>] [--script <path>] [--encoding <label>] [--format <name>] [--quiet] [-c <query> | --file <path> | --serve <address> [--allow-origin <origin>...] | --listen <address>]";
+
+/// Parses the command line arguments into [`Arguments`].
+///
+/// # Arguments
+/// `args` : The command line arguments, excluding the name of the program.
+///
+/// # Returns
+/// The parsed [`Arguments`], or an error message describing why the arguments are invalid.
+pub fn parse_arguments<I: Iterator<Item = String>>(args: I) -> Result<Arguments, String> {
+    let mut arguments = Arguments::default();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--profile-out" => match args.next() {
+                Some(path) => arguments.profile_out = Some(path),
+                None => return Err("--profile-out must be followed by a file path.".to_string()),
+            },
+            "--script" => match args.next() {
+                Some(path) => arguments.script = Some(path),
+                None => return Err("--script must be followed by a file path.".to_string()),
+            },
+            "-c" | "--command" => match args.next() {
+                Some(query) => arguments.command = Some(query),
+                None => return Err(format!("{} must be followed by a query.", arg)),
+            },
+            "--file" => match args.next() {
+                Some(path) => arguments.file = Some(path),
+                None => return Err("--file must be followed by a file path.".to_string()),
+            },
+            "--encoding" => match args.next() {
+                Some(label) => arguments.encoding = Some(parse_encoding(&label)?),
+                None => {
+                    return Err(
+                        "--encoding must be followed by the name of an encoding, e.g. latin1."
+                            .to_string(),
+                    )
+                }
+            },
+            "--format" => match args.next() {
+                Some(name) => arguments.format = Some(OutputFormat::parse(&name)?),
+                None => {
+                    return Err(
+                        "--format must be followed by table, csv, jsonl, or markdown.".to_string(),
+                    )
+                }
+            },
+            "--serve" => {
+                match args.next() {
+                    Some(address) => arguments.serve = Some(address),
+                    None => return Err(
+                        "--serve must be followed by the address to listen on, e.g. 0.0.0.0:8080."
+                            .to_string(),
+                    ),
+                }
+            }
+            "--allow-origin" => match args.next() {
+                Some(origin) => arguments.allow_origins.push(origin),
+                None => {
+                    return Err(
+                        "--allow-origin must be followed by an origin, e.g. https://dashboard.example.com."
+                            .to_string(),
+                    )
+                }
+            },
+            "--listen" => {
+                match args.next() {
+                    Some(address) => arguments.listen = Some(address),
+                    None => return Err(
+                        "--listen must be followed by the address to listen on, e.g. 0.0.0.0:9000."
+                            .to_string(),
+                    ),
+                }
+            }
+            "-q" | "--quiet" => arguments.quiet = true,
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+    if arguments.command.is_some() && arguments.file.is_some() {
+        return Err("--command and --file can't be used together.".to_string());
+    }
+    if arguments.serve.is_some() && (arguments.command.is_some() || arguments.file.is_some()) {
+        return Err("--serve can't be used together with --command or --file.".to_string());
+    }
+    if !arguments.allow_origins.is_empty() && arguments.serve.is_none() {
+        return Err("--allow-origin can only be used together with --serve.".to_string());
+    }
+    if arguments.listen.is_some()
+        && (arguments.command.is_some() || arguments.file.is_some() || arguments.serve.is_some())
+    {
+        return Err(
+            "--listen can't be used together with --command, --file, or --serve.".to_string(),
+        );
+    }
+    Ok(arguments)
+}
+
+#[test]
+fn test_parse_arguments_empty() {
+    assert_eq!(
+        parse_arguments(Vec::<String>::new().into_iter()),
+        Ok(Arguments::default())
+    );
+}
+
+#[test]
+fn test_parse_arguments_profile_out() {
+    assert_eq!(
+        parse_arguments(vec!["--profile-out".to_string(), "profile.json".to_string()].into_iter()),
+        Ok(Arguments {
+            profile_out: Some("profile.json".to_string()),
+            ..Arguments::default()
+        })
+    );
+}
+
+#[test]
+fn test_parse_arguments_script() {
+    assert_eq!(
+        parse_arguments(vec!["--script".to_string(), "functions.rhai".to_string()].into_iter()),
+        Ok(Arguments {
+            script: Some("functions.rhai".to_string()),
+            ..Arguments::default()
+        })
+    );
+}
+
+#[test]
+fn test_parse_arguments_command() {
+    for flag in ["-c", "--command"] {
+        assert_eq!(
+            parse_arguments(vec![flag.to_string(), "FROM city TAKE 5".to_string()].into_iter()),
+            Ok(Arguments {
+                command: Some("FROM city TAKE 5".to_string()),
+                ..Arguments::default()
+            })
+        );
+    }
+}
+
+#[test]
+fn test_parse_arguments_file() {
+    assert_eq!(
+        parse_arguments(vec!["--file".to_string(), "queries.txt".to_string()].into_iter()),
+        Ok(Arguments {
+            file: Some("queries.txt".to_string()),
+            ..Arguments::default()
+        })
+    );
+}
+
+#[test]
+fn test_parse_arguments_encoding() {
+    assert_eq!(
+        parse_arguments(vec!["--encoding".to_string(), "latin1".to_string()].into_iter()),
+        Ok(Arguments {
+            encoding: Some(encoding_rs::WINDOWS_1252),
+            ..Arguments::default()
+        })
+    );
+    assert_eq!(
+        parse_arguments(vec!["--encoding".to_string(), "ebcdic".to_string()].into_iter()),
+        Err(
+            "Unknown encoding: ebcdic. Use a label such as utf-8, latin1, or windows-1252."
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn test_parse_arguments_serve() {
+    assert_eq!(
+        parse_arguments(vec!["--serve".to_string(), "0.0.0.0:8080".to_string()].into_iter()),
+        Ok(Arguments {
+            serve: Some("0.0.0.0:8080".to_string()),
+            ..Arguments::default()
+        })
+    );
+    assert_eq!(
+        parse_arguments(vec!["--serve".to_string()].into_iter()),
+        Err("--serve must be followed by the address to listen on, e.g. 0.0.0.0:8080.".to_string())
+    );
+    assert_eq!(
+        parse_arguments(
+            ["--serve", "localhost:8080", "-c", "FROM city"]
+                .map(str::to_string)
+                .into_iter()
+        ),
+        Err("--serve can't be used together with --command or --file.".to_string())
+    );
+}
+
+#[test]
+fn test_parse_arguments_allow_origin() {
+    assert_eq!(
+        parse_arguments(
+            [
+                "--allow-origin",
+                "https://a.example.com",
+                "--serve",
+                "0.0.0.0:8080",
+                "--allow-origin",
+                "http://localhost:3000"
+            ]
+            .map(str::to_string)
+            .into_iter()
+        ),
+        Ok(Arguments {
+            serve: Some("0.0.0.0:8080".to_string()),
+            allow_origins: vec![
+                "https://a.example.com".to_string(),
+                "http://localhost:3000".to_string()
+            ],
+            ..Arguments::default()
+        })
+    );
+    assert_eq!(
+        parse_arguments(
+            ["--serve", "0.0.0.0:8080", "--allow-origin"]
+                .map(str::to_string)
+                .into_iter()
+        ),
+        Err(
+            "--allow-origin must be followed by an origin, e.g. https://dashboard.example.com."
+                .to_string()
+        )
+    );
+    assert_eq!(
+        parse_arguments(
+            ["--allow-origin", "https://a.example.com"]
+                .map(str::to_string)
+                .into_iter()
+        ),
+        Err("--allow-origin can only be used together with --serve.".to_string())
+    );
+}
+
+#[test]
+fn test_parse_arguments_listen() {
+    assert_eq!(
+        parse_arguments(
+            ["--script", "functions.rhai", "--listen", "0.0.0.0:9000"]
+                .map(str::to_string)
+                .into_iter()
+        ),
+        Ok(Arguments {
+            script: Some("functions.rhai".to_string()),
+            listen: Some("0.0.0.0:9000".to_string()),
+            ..Arguments::default()
+        })
+    );
+    assert_eq!(
+        parse_arguments(vec!["--listen".to_string()].into_iter()),
+        Err(
+            "--listen must be followed by the address to listen on, e.g. 0.0.0.0:9000.".to_string()
+        )
+    );
+    assert_eq!(
+        parse_arguments(
+            ["--listen", "localhost:9000", "--serve", "localhost:8080"]
+                .map(str::to_string)
+                .into_iter()
+        ),
+        Err("--listen can't be used together with --command, --file, or --serve.".to_string())
+    );
+}
+
+#[test]
+fn test_parse_arguments_format() {
+    assert_eq!(
+        parse_arguments(vec!["--format".to_string(), "jsonl".to_string()].into_iter()),
+        Ok(Arguments {
+            format: Some(OutputFormat::Jsonl),
+            ..Arguments::default()
+        })
+    );
+    assert_eq!(
+        parse_arguments(vec!["--format".to_string(), "xml".to_string()].into_iter()),
+        Err(
+            "Unknown output format: xml. Must be 'table', 'csv', 'jsonl', or 'markdown'."
+                .to_string()
+        )
+    );
+    assert_eq!(
+        parse_arguments(vec!["--format".to_string()].into_iter()),
+        Err("--format must be followed by table, csv, jsonl, or markdown.".to_string())
+    );
+}
+
+#[test]
+fn test_parse_arguments_quiet() {
+    for flag in ["-q", "--quiet"] {
+        assert_eq!(
+            parse_arguments(
+                vec![flag.to_string(), "-c".to_string(), "exit".to_string()].into_iter()
+            ),
+            Ok(Arguments {
+                command: Some("exit".to_string()),
+                quiet: true,
+                ..Arguments::default()
+            })
+        );
+    }
+}
+
+#[test]
+fn test_parse_arguments_malformed() {
+    assert_eq!(
+        parse_arguments(vec!["--profile-out".to_string()].into_iter()),
+        Err("--profile-out must be followed by a file path.".to_string())
+    );
+    assert_eq!(
+        parse_arguments(vec!["--script".to_string()].into_iter()),
+        Err("--script must be followed by a file path.".to_string())
+    );
+    assert_eq!(
+        parse_arguments(vec!["-c".to_string()].into_iter()),
+        Err("-c must be followed by a query.".to_string())
+    );
+    assert_eq!(
+        parse_arguments(vec!["--file".to_string()].into_iter()),
+        Err("--file must be followed by a file path.".to_string())
+    );
+    assert_eq!(
+        parse_arguments(vec!["--encoding".to_string()].into_iter()),
+        Err("--encoding must be followed by the name of an encoding, e.g. latin1.".to_string())
+    );
+    assert_eq!(
+        parse_arguments(
+            vec!["-c", "FROM city", "--file", "queries.txt"]
+                .into_iter()
+                .map(String::from)
+        ),
+        Err("--command and --file can't be used together.".to_string())
+    );
+    assert_eq!(
+        parse_arguments(vec!["--verbose".to_string()].into_iter()),
+        Err("Unknown argument: --verbose".to_string())
+    );
+}
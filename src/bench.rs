@@ -0,0 +1,120 @@
+use crate::commands::{parse_command, Command};
+use crate::data::Dataset;
+use crate::operators::{process_operator, ExecutionContext, Operator};
+
+/// A canned query exercising one of the operators, run by the benchmarks in `benches/`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchPlan {
+    /// The group the benchmark is reported under, i.e., the operator it measures, e.g. "join".
+    pub group: &'static str,
+    /// The name of the benchmark within its group.
+    pub name: &'static str,
+    /// The query to run, over the bundled datasets.
+    pub query: &'static str,
+}
+
+impl BenchPlan {
+    /// Parses the query of the plan into its [`Operator`] chain.
+    ///
+    /// # Panics
+    /// If the query is malformed, which the tests below rule out for the [`BENCH_PLANS`].
+    pub fn operator(&self) -> Operator {
+        match parse_command(&format!("{}\n", self.query)) {
+            Command::Operator(operator) => operator,
+            command => panic!("Not a query: {} ({:?})", self.query, command),
+        }
+    }
+}
+
+/// The queries measured by the benchmarks, over the bundled datasets.
+pub const BENCH_PLANS: &[BenchPlan] = &[
+    BenchPlan {
+        group: "join",
+        name: "hash",
+        query: "FROM city.csv JOIN country.csv CountryCode",
+    },
+    BenchPlan {
+        group: "join",
+        name: "merge",
+        query: "FROM city.csv JOIN country.csv CountryCode USING MERGE",
+    },
+    BenchPlan {
+        group: "join",
+        name: "chained",
+        query: "FROM city.csv JOIN country.csv CountryCode JOIN language.csv CountryCode",
+    },
+    BenchPlan {
+        group: "join",
+        name: "outer",
+        query: "FROM country.csv OUTERJOIN city.csv ON Capital=CityID",
+    },
+    BenchPlan {
+        group: "sort",
+        name: "integers",
+        query: "FROM city.csv ORDERBY CityPop",
+    },
+    BenchPlan {
+        group: "sort",
+        name: "joined",
+        query: "FROM city.csv JOIN country.csv CountryCode ORDERBY CountryPop",
+    },
+    BenchPlan {
+        group: "sort",
+        name: "take",
+        query: "FROM city.csv ORDERBY CityPop TAKE 10",
+    },
+    BenchPlan {
+        group: "countby",
+        name: "few_values",
+        query: "FROM country.csv COUNTBY Continent",
+    },
+    BenchPlan {
+        group: "countby",
+        name: "many_values",
+        query: "FROM language.csv COUNTBY Language",
+    },
+];
+
+/// Creates a context with the bundled datasets already loaded, so that the benchmarks measure the
+/// operators rather than reading the CSV files.
+pub fn warm_context() -> ExecutionContext {
+    let mut context = ExecutionContext::default();
+    for dataset in Dataset::all() {
+        process_operator(&Operator::From(dataset.clone()), &mut context)
+            .expect("The bundled datasets are loaded from the data directory.");
+    }
+    context
+}
+
+/// Runs the `operator` chain of a benchmark in the `context`.
+///
+/// # Returns
+/// The number of rows produced, which the benchmarks pass to [`std::hint::black_box`] so that the
+/// work isn't optimized away.
+///
+/// # Panics
+/// If processing the `operator` fails.
+pub fn run_plan(operator: &Operator, context: &mut ExecutionContext) -> usize {
+    match process_operator(operator, context) {
+        Ok(table) => table.rows.len(),
+        Err(error) => panic!("Failed to run {}: {}", operator, error),
+    }
+}
+
+#[test]
+fn test_bench_plans() {
+    let mut context = warm_context();
+    for plan in BENCH_PLANS {
+        assert!(
+            run_plan(&plan.operator(), &mut context) > 0,
+            "{}",
+            plan.query
+        );
+    }
+    // The names are unique within each group.
+    for (index, plan) in BENCH_PLANS.iter().enumerate() {
+        assert!(!BENCH_PLANS[..index]
+            .iter()
+            .any(|other| other.group == plan.group && other.name == plan.name));
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::data::Dataset;
+use crate::table::Table;
+
+/// The number of query results kept by a [`ResultCache`].
+pub const C_RESULT_CACHE_SIZE: usize = 16;
+
+/// The result of a query kept by the [`ResultCache`].
+#[derive(Debug, Clone)]
+pub struct CachedResult {
+    /// The result of the query.
+    pub table: Arc<Table>,
+    /// The datasets the query read, and the tables they held at the time. The result is only
+    /// reused while every dataset still holds the same table, i.e., it wasn't reloaded, modified,
+    /// or saved again in the meantime.
+    pub inputs: Vec<(Dataset, Arc<Table>)>,
+}
+
+/// The results of the latest queries, so that running the same query again, e.g. after looking at
+/// its output, returns the previous result instead of processing it again. Only the
+/// [`C_RESULT_CACHE_SIZE`] most recently used results are kept.
+#[derive(Debug, Default)]
+pub struct ResultCache {
+    /// The cached results by the normalized query they are the result of, least recently used
+    /// first.
+    entries: VecDeque<(String, CachedResult)>,
+}
+
+impl ResultCache {
+    /// Removes the result of the `query` from the cache and returns it, if it is cached. Put it
+    /// back with [`ResultCache::insert`] once it is known to be current, which also marks it as
+    /// the most recently used.
+    pub fn take(&mut self, query: &str) -> Option<CachedResult> {
+        let index = self.entries.iter().position(|(key, _)| key == query)?;
+        self.entries.remove(index).map(|(_, result)| result)
+    }
+
+    /// Caches the `result` of the `query` as the most recently used, dropping the least recently
+    /// used result if the cache is full.
+    pub fn insert(&mut self, query: &str, result: CachedResult) {
+        self.entries.retain(|(key, _)| key != query);
+        if self.entries.len() == C_RESULT_CACHE_SIZE {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((query.to_string(), result));
+    }
+
+    /// Returns the number of cached results.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no results are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops all the cached results.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[test]
+fn test_result_cache() {
+    let result = |rows: usize| CachedResult {
+        table: Arc::new(Table::new(
+            vec!["n".to_string()],
+            vec![
+                crate::table::Row {
+                    cells: vec![crate::table::Cell::Int64(0)]
+                };
+                rows
+            ],
+        )),
+        inputs: vec![],
+    };
+    let mut cache = ResultCache::default();
+    assert!(cache.is_empty());
+    for index in 0..C_RESULT_CACHE_SIZE {
+        cache.insert(&format!("query {}", index), result(index));
+    }
+    assert_eq!(cache.len(), C_RESULT_CACHE_SIZE);
+
+    // Taking a result removes it until it is put back as the most recently used.
+    let first = cache.take("query 0").unwrap();
+    assert_eq!(first.table.rows.len(), 0);
+    assert!(cache.take("query 0").is_none());
+    cache.insert("query 0", first);
+
+    // The least recently used result is dropped to make room.
+    cache.insert("another query", result(1));
+    assert_eq!(cache.len(), C_RESULT_CACHE_SIZE);
+    assert!(cache.take("query 1").is_none());
+    assert!(cache.take("query 0").is_some());
+
+    // Caching a query again replaces its result.
+    cache.insert("another query", result(2));
+    assert_eq!(cache.take("another query").unwrap().table.rows.len(), 2);
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
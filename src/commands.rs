@@ -1,17 +1,40 @@
-use crate::data::Dataset;
-use crate::operators::Operator;
+use std::error::Error;
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::num::ParseIntError;
+use std::slice::Iter;
+
+use encoding_rs::Encoding;
+
+use crate::aggregate::{parse_aggregate_list, AggregateFunction, ALL_ROWS};
+use crate::data::{is_identifier, is_parquet_file, parse_encoding, Dataset, DatasetRegistry};
+#[cfg(test)]
+use crate::expression::Comparison;
+use crate::expression::{
+    parse_constant, parse_extend_list, parse_predicate, parse_select_list, parse_values,
+    ArithmeticOperator, Expression, Predicate,
+};
+use crate::lexer::{split_statements, tokenize, unquote};
+use crate::operators::{
+    find_operator_descriptor, CastType, CountByOrder, ExportFormat, JoinKind, JoinStrategy,
+    NullsOrder, Operator, SampleSize, SortOrder, OPERATORS,
+};
+use crate::settings::{parse_setting, Setting};
+use crate::table::Cell;
 
 /// Commands parsed from user input.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     /// The user entered the `exit` command.
     Exit,
-    /// The user entered the `help` command.
-    Help,
+    /// The user entered the `help` command, optionally followed by the name of the operator to
+    /// describe in detail.
+    /// Example: 'help take' will parse to `Command::Help(Some("TAKE".to_string()))`.
+    Help(Option<String>),
     /// The chain of operators processed from the input.
     /// Example:
     /// 'FROM city.csv SELECT CityID TAKE 5' will parse to
-    /// ```
+    /// ```text
     /// Command::Operator(
     ///   Operator::Take(
     ///     Box(Operator::Select(
@@ -25,68 +48,758 @@ pub enum Command {
     /// )
     /// ```
     Operator(Operator),
+    /// The user entered `EXPLAIN` followed by a query, to describe how the query would be
+    /// evaluated without running it.
+    /// Example: 'EXPLAIN FROM city.csv TAKE 5' will parse to
+    /// `Command::Explain(Operator::Take(Box(Operator::From(Dataset::City)), 5))`.
+    Explain(Operator),
+    /// The user entered `EXPLAIN ANALYZE` followed by a query, to run the query and describe how
+    /// many rows each operator produced and how long it took.
+    /// Example: 'EXPLAIN ANALYZE FROM city.csv TAKE 5' will parse to
+    /// `Command::ExplainAnalyze(Operator::Take(Box(Operator::From(Dataset::City)), 5))`.
+    ExplainAnalyze(Operator),
+    /// The user entered `WATCH` followed by a query, to run the query again each time a file it
+    /// reads is modified, e.g. a log that keeps growing, until Ctrl-C is pressed.
+    /// Example: 'WATCH FROM log.csv COUNTBY Status' will parse to
+    /// `Command::Watch(Operator::CountBy(Box(Operator::From(Dataset::Csv("log.csv"))), "Status"))`.
+    Watch(Operator),
+    /// The user entered `LOAD <name> FROM <path> [ENCODING <label>]`, to give the CSV or Parquet
+    /// file at `path` a name that FROM and JOIN can refer to. See [`DatasetRegistry`].
+    Load {
+        /// The name to give the dataset.
+        name: String,
+        /// The path of the CSV or Parquet file.
+        path: String,
+        /// The encoding the CSV file is read in, if one is given with ENCODING, e.g. `latin1`.
+        encoding: Option<&'static Encoding>,
+    },
+    /// The user entered `ATTACH <path>`, to make the tables of the SQLite database at `path`
+    /// datasets that FROM and JOIN can refer to by name. See [`DatasetRegistry::attach`].
+    /// Example: 'ATTACH mydb.sqlite' will parse to `Command::Attach("mydb.sqlite")`.
+    Attach(String),
+    /// The user entered `SET <name> = <query>`, to run the query and save its result, so that
+    /// FROM and JOIN in later queries can refer to it as `@<name>` (i.e., [`Dataset::Variable`]).
+    /// Example: 'SET big = FROM city.csv TAKE 5' will parse to
+    /// `Command::Set { name: "big", operator: Operator::Take(Box(Operator::From(Dataset::City)), 5) }`.
+    Set {
+        /// The name to save the result under, without the leading `@`.
+        name: String,
+        /// The query whose result is saved.
+        operator: Operator,
+    },
+    /// The user entered `INSERT INTO <dataset> VALUES (<value>, ...), ...`, to append rows to the
+    /// copy of the dataset kept in memory. The file on disk is left untouched.
+    /// Example: 'INSERT INTO language.csv VALUES (ATL, Atlantean)' will parse to
+    /// `Command::Insert { dataset: Dataset::Language, rows: vec![vec![Cell::String("ATL"), Cell::String("Atlantean")]] }`.
+    Insert {
+        /// The dataset to append the rows to.
+        dataset: Dataset,
+        /// The values of each row, one per column of the dataset.
+        rows: Vec<Vec<Cell>>,
+    },
+    /// The user entered `DELETE FROM <dataset> WHERE <condition>`, to remove the rows satisfying
+    /// the condition from the copy of the dataset kept in memory. The file on disk is left
+    /// untouched.
+    /// Example: 'DELETE FROM city.csv WHERE CityPop < 1000' will parse to
+    /// `Command::Delete { dataset: Dataset::City, predicate: <CityPop < 1000> }`.
+    Delete {
+        /// The dataset to remove the rows from.
+        dataset: Dataset,
+        /// The condition the rows to remove satisfy.
+        predicate: Predicate,
+    },
+    /// The user entered `DESCRIBE <dataset>`, to list the type, number of missing and distinct
+    /// values, range, and sample values of each column of the dataset. See
+    /// [`crate::describe::describe_table`].
+    /// Example: 'DESCRIBE city.csv' will parse to `Command::Describe(Dataset::City)`.
+    Describe(Dataset),
+    /// The user entered `SHOW TABLES`, to list the datasets FROM and JOIN can refer to, and the
+    /// results saved with SET. See [`crate::describe::list_tables`].
+    ShowTables,
+    /// The user entered `SHOW COLUMNS <dataset>`, to list the names and types of the columns of
+    /// the dataset. See [`crate::describe::list_columns`].
+    /// Example: 'SHOW COLUMNS city' will parse to `Command::ShowColumns(Dataset::City)`.
+    ShowColumns(Dataset),
+    /// The user entered `SAVE <dataset> [<path>]`, to write the copy of the dataset kept in
+    /// memory, including the edits made with INSERT and DELETE, to a CSV file. Without a path,
+    /// the dataset's own file is overwritten.
+    /// Example: 'SAVE language.csv edited.csv' will parse to
+    /// `Command::Save { dataset: Dataset::Language, path: "edited.csv" }`.
+    Save {
+        /// The dataset to write.
+        dataset: Dataset,
+        /// The path of the CSV file to write.
+        path: String,
+    },
+    /// The user changed one of the session [`crate::settings::Settings`] with a `\<setting>`
+    /// command.
+    /// Example: '\limit 50' will parse to `Command::Setting(Setting::DefaultLimit(Some(50)))`.
+    Setting(Setting),
+    /// The user entered `\history`, to list the queries entered earlier in the session, numbered
+    /// from 1.
+    History,
+    /// The user entered `\rerun <number>`, to run again the query listed under that number by
+    /// `\history`.
+    /// Example: '\rerun 3' will parse to `Command::Rerun(3)`.
+    Rerun(usize),
+    /// The user entered `\save <path>`, to write the settings, registered datasets, and saved
+    /// results of the session to a TOML file.
+    SaveSession(String),
+    /// The user entered `\load <path>`, to restore a session written by `\save`.
+    LoadSession(String),
     /// The user's input is erroneous.
-    InputError(String),
+    InputError(ParseError),
     /// The user didn't enter anything so do nothing.
     NoInput,
 }
 
+/// The position of a token in the line passed to [`parse_command`], as a range of byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// The offset of the first byte of the token.
+    pub start: usize,
+    /// The offset of the byte following the token.
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the span of `token` in `input`. The tokens are slices of the input, so their
+    /// position is found from their address. Tokens that aren't part of the input span all of it.
+    pub(crate) fn of(token: &str, input: &str) -> Span {
+        let start = (token.as_ptr() as usize).wrapping_sub(input.as_ptr() as usize);
+        if start + token.len() <= input.len() {
+            Span {
+                start,
+                end: start + token.len(),
+            }
+        } else {
+            Span {
+                start: 0,
+                end: input.len(),
+            }
+        }
+    }
+
+    /// Returns the span from the start of the `first` token to the end of the `last` one.
+    pub(crate) fn between(first: &str, last: &str, input: &str) -> Span {
+        Span {
+            start: Span::of(first, input).start,
+            end: Span::of(last, input).end,
+        }
+    }
+}
+
+/// The set of errors that can be returned in a [`Command::InputError`]. Each error holds the
+/// [`Span`] of the token that caused it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The token isn't a command or operator.
+    UnknownKeyword { keyword: String, span: Span },
+    /// The token follows a complete command, e.g. a second operator name after `help`.
+    UnexpectedToken { token: String, span: Span },
+    /// The command or operator isn't followed by all of its arguments. Holds a description of
+    /// what was `expected`.
+    MissingArgument {
+        keyword: String,
+        expected: String,
+        span: Span,
+    },
+    /// The dataset passed to FROM or JOIN is neither a built-in, registered, or saved dataset,
+    /// nor a sub-query defined by WITH.
+    InvalidDataset {
+        keyword: String,
+        name: String,
+        span: Span,
+    },
+    /// The count passed to TAKE, SKIP, or TAIL isn't a positive integer.
+    InvalidNumber {
+        keyword: String,
+        value: String,
+        error: ParseIntError,
+        span: Span,
+    },
+    /// The operator is the first of the query, instead of FROM.
+    MissingFrom { keyword: String, span: Span },
+    /// FROM follows another operator.
+    MisplacedFrom { span: Span },
+    /// The operator, e.g. INTO, is followed by other operators.
+    NotLast { keyword: String, span: Span },
+    /// The parenthesis opened before a sub-query isn't closed. Holds a description of the
+    /// sub-query it was opened `after`.
+    MissingParenthesis { after: String, span: Span },
+    /// There is no query between the parentheses of a sub-query. Holds a description of the
+    /// `subquery`, e.g. "sub-query to JOIN on".
+    EmptySubquery { subquery: String, span: Span },
+    /// The sub-query is defined more than once in the WITH clause.
+    DuplicateName { name: String, span: Span },
+    /// The name given to a dataset with LOAD is the keyword of an operator.
+    ReservedName { name: String, span: Span },
+    /// The name given to a result with SET isn't an identifier.
+    InvalidVariableName { name: String, span: Span },
+    /// The expression, predicate, or aggregate is malformed. Holds the message describing why.
+    InvalidExpression { message: String, span: Span },
+    /// The setting is unknown or its value is malformed, or the arguments of `\history` or
+    /// `\rerun` are. Holds the message describing why.
+    InvalidSetting { message: String, span: Span },
+    /// A quoted string has no closing quote. Holds the `text` from its opening quote on.
+    UnterminatedString { text: String, span: Span },
+    /// `help` was given the name of something that isn't an operator.
+    NoHelp { name: String, span: Span },
+    /// The encoding given to LOAD with ENCODING is unknown. Holds the message describing why.
+    InvalidEncoding { message: String, span: Span },
+}
+
+impl ParseError {
+    /// Returns the [`Span`] of the token that caused the error.
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnknownKeyword { span, .. }
+            | ParseError::UnexpectedToken { span, .. }
+            | ParseError::MissingArgument { span, .. }
+            | ParseError::InvalidDataset { span, .. }
+            | ParseError::InvalidNumber { span, .. }
+            | ParseError::MissingFrom { span, .. }
+            | ParseError::MisplacedFrom { span }
+            | ParseError::NotLast { span, .. }
+            | ParseError::MissingParenthesis { span, .. }
+            | ParseError::EmptySubquery { span, .. }
+            | ParseError::DuplicateName { span, .. }
+            | ParseError::ReservedName { span, .. }
+            | ParseError::InvalidVariableName { span, .. }
+            | ParseError::InvalidExpression { span, .. }
+            | ParseError::InvalidSetting { span, .. }
+            | ParseError::UnterminatedString { span, .. }
+            | ParseError::NoHelp { span, .. }
+            | ParseError::InvalidEncoding { span, .. } => *span,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnknownKeyword { keyword, .. } => f.write_fmt(format_args!(
+                "Invalid Input: {} is not an operator.",
+                keyword
+            )),
+            ParseError::UnexpectedToken { token, .. } => {
+                f.write_fmt(format_args!("Invalid Input: unexpected {}.", token))
+            }
+            ParseError::MissingArgument {
+                keyword, expected, ..
+            } => f.write_fmt(format_args!("{} must be followed by {}.", keyword, expected)),
+            ParseError::InvalidDataset { keyword, name, .. } => f.write_fmt(format_args!(
+                "Invalid dataset passed to {}: {}",
+                keyword, name
+            )),
+            ParseError::InvalidNumber {
+                keyword,
+                value,
+                error,
+                ..
+            } => f.write_fmt(format_args!(
+                "Invalid value passed to {} operator: {}. Must be a positive integer.\n Full error message: {}",
+                keyword, value, error
+            )),
+            ParseError::MissingFrom { keyword, .. } => f.write_fmt(format_args!(
+                "{} can't be the first command; It must be preceded by at least a FROM.",
+                keyword
+            )),
+            ParseError::MisplacedFrom { .. } => {
+                f.write_str("FROM must always be the first operator.")
+            }
+            ParseError::NotLast { keyword, .. } => f.write_fmt(format_args!(
+                "{} must be the last operator of the query.",
+                keyword
+            )),
+            ParseError::MissingParenthesis { after, .. } => {
+                f.write_fmt(format_args!("Missing ')' after {}.", after))
+            }
+            ParseError::EmptySubquery { subquery, .. } => {
+                f.write_fmt(format_args!("The {} is empty.", subquery))
+            }
+            ParseError::DuplicateName { name, .. } => f.write_fmt(format_args!(
+                "{} is defined more than once in the WITH clause.",
+                name
+            )),
+            ParseError::ReservedName { name, .. } => f.write_fmt(format_args!(
+                "{} is a keyword, so it can't be the name of a dataset.",
+                name
+            )),
+            ParseError::InvalidVariableName { name, .. } => f.write_fmt(format_args!(
+                "Invalid variable name: {}. It must consist of letters, digits, and underscores.",
+                name
+            )),
+            ParseError::InvalidExpression { message, .. }
+            | ParseError::InvalidSetting { message, .. }
+            | ParseError::InvalidEncoding { message, .. } => f.write_str(message),
+            ParseError::UnterminatedString { text, .. } => f.write_fmt(format_args!(
+                "Missing closing quote: {}",
+                text
+            )),
+            ParseError::NoHelp { name, .. } => {
+                f.write_fmt(format_args!("No help available for: {}", name))
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
 /// Helper function to parse the token stream of the user input from the CLI into an [`Operator`]
 /// chain.
 ///
 /// # Arguments
 /// `tokens` : The input string tokenized into a vector of strings to be processed.
+/// `input` : The line the `tokens` were taken from, which the spans of errors are relative to.
+/// `datasets` : The datasets registered with `LOAD`, which FROM and JOIN can refer to by name.
 ///
 /// # Usage: This function only processes the input tokens into a chain of [`Operator`]s.
 /// The 'exit' and 'help' commands must be handled separetely. Use [`parse_command`] instead.
 ///
 /// # Returns
-/// A [`Command::Operator`] chain on successfully parsinig the tokens into [`Operator`]s or
-/// [`Command::InputError`] in all other cases.
-fn parse_operators(tokens: &Vec<&str>) -> Result<Operator, String> {
-    let mut token_iter = tokens.into_iter();
+/// The [`Operator`] chain on successfully parsinig the tokens into [`Operator`]s or the
+/// [`ParseError`] describing why the input is malformed.
+fn parse_operators(
+    tokens: &[&str],
+    input: &str,
+    datasets: &DatasetRegistry,
+) -> Result<Operator, ParseError> {
+    match tokens.first() {
+        Some(&"WITH") => parse_with(tokens, input, datasets),
+        _ => parse_chain(tokens, input, &[], datasets),
+    }
+}
+
+/// Helper function to parse a query that starts with a WITH clause, e.g.
+/// `WITH euro AS (FROM country.csv FILTER Continent = Europe) FROM city.csv JOIN euro CountryCode`.
+/// Each sub-query is registered in the query-scoped catalog, so that the sub-queries defined after
+/// it and the main query can refer to it with FROM and JOIN.
+///
+/// # Arguments
+/// `tokens` : The tokens of the query, starting with the WITH keyword.
+/// `input` : The line the `tokens` were taken from, which the spans of errors are relative to.
+/// `datasets` : The datasets registered with `LOAD`.
+///
+/// # Returns
+/// An [`Operator::With`] on success, or the [`ParseError`] describing why the input is malformed.
+fn parse_with(
+    tokens: &[&str],
+    input: &str,
+    datasets: &DatasetRegistry,
+) -> Result<Operator, ParseError> {
+    let malformed = || ParseError::MissingArgument {
+        keyword: "WITH".to_string(),
+        expected: "<name> AS (<query>) and then the query to run".to_string(),
+        span: Span::of(tokens[0], input),
+    };
+    let mut catalog: Vec<String> = Vec::new();
+    let mut ctes = Vec::new();
+    let mut rest = &tokens[1..];
+    loop {
+        let (name, definition) = match rest {
+            [name, as_keyword, definition @ ..]
+                if as_keyword.eq_ignore_ascii_case("AS")
+                    && definition
+                        .first()
+                        .is_some_and(|token| token.starts_with('(')) =>
+            {
+                (name, definition)
+            }
+            _ => return Err(malformed()),
+        };
+        if catalog.iter().any(|cte| cte == name) {
+            return Err(ParseError::DuplicateName {
+                name: name.to_string(),
+                span: Span::of(name, input),
+            });
+        }
+
+        // Find the token that closes the parenthesis opened before the definition. Parentheses
+        // may also appear inside the definition, e.g. in function calls.
+        let mut depth = 0;
+        let end = definition
+            .iter()
+            .position(|token| {
+                depth += token.matches('(').count() as i64 - token.matches(')').count() as i64;
+                depth <= 0
+            })
+            .ok_or_else(|| ParseError::MissingParenthesis {
+                after: format!("the definition of {} in the WITH clause", name),
+                span: Span::of(definition[0], input),
+            })?;
+
+        // Another definition may follow, separated by a comma.
+        let (last, mut more) = match definition[end].strip_suffix(',') {
+            Some(last) => (last, true),
+            None => (definition[end], false),
+        };
+        let mut body = definition[..end].to_vec();
+        body.push(last.strip_suffix(')').ok_or_else(malformed)?);
+        body[0] = &body[0][1..];
+        body.retain(|token| !token.is_empty());
+        if body.is_empty() {
+            return Err(malformed());
+        }
+        ctes.push((
+            name.to_string(),
+            parse_chain(&body, input, &catalog, datasets)?,
+        ));
+        catalog.push(name.to_string());
+
+        rest = &definition[end + 1..];
+        if let Some((&",", after)) = rest.split_first() {
+            rest = after;
+            more = true;
+        }
+        if !more {
+            break;
+        }
+    }
+
+    if rest.is_empty() {
+        return Err(malformed());
+    }
+    Ok(Operator::With {
+        ctes,
+        query: Box::new(parse_chain(rest, input, &catalog, datasets)?),
+    })
+}
+
+/// Helper function to resolve the name of the dataset passed to FROM or JOIN. The query-scoped
+/// `catalog` of sub-queries defined by WITH is consulted before the registered `datasets` and the
+/// datasets on disk. Names starting with `@` refer to results saved with SET; whether they exist
+/// is only checked when the query is processed.
+pub(crate) fn resolve_dataset(
+    name: &str,
+    catalog: &[String],
+    datasets: &DatasetRegistry,
+) -> Option<Dataset> {
+    if let Some(variable) = name.strip_prefix('@') {
+        is_identifier(variable).then(|| Dataset::Variable(variable.to_string()))
+    } else if catalog.iter().any(|cte| cte == name) {
+        Some(Dataset::Cte(name.to_string()))
+    } else {
+        datasets.resolve(name)
+    }
+}
+
+/// Returns `true` if `token` is the keyword of an operator, including aliases such as WHERE.
+fn is_operator_keyword(token: &str) -> bool {
+    matches!(token, "WHERE" | "EXPORT") || OPERATORS.iter().any(|operator| operator.name == token)
+}
+
+/// Helper function to check whether the `text` of an expression opens more parentheses than it
+/// closes outside of quoted strings, i.e., whether the expression continues in the next token,
+/// e.g. `COALESCE(Capital,` followed by `0)`.
+fn has_unclosed_parenthesis(text: &str) -> bool {
+    let (mut depth, mut quote, mut escaped) = (0, None, false);
+    for c in text.chars() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => (),
+            None => match c {
+                '\'' | '"' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => (),
+            },
+        }
+    }
+    depth > 0
+}
+
+#[test]
+fn test_has_unclosed_parenthesis() {
+    assert!(has_unclosed_parenthesis("COALESCE(Capital,"));
+    assert!(has_unclosed_parenthesis("(CityPop"));
+    assert!(!has_unclosed_parenthesis("COALESCE(Capital,0)"));
+    assert!(!has_unclosed_parenthesis("CityName"));
+    // Parentheses in quoted strings don't count.
+    assert!(!has_unclosed_parenthesis("'(n/a'"));
+    assert!(has_unclosed_parenthesis("COALESCE(Name,'it\\'s )'"));
+}
+
+/// Helper function to parse the condition of FILTER and HAVING, i.e.,
+/// `<expression> <comparison> <value>` or `<expression> IS [NOT] NULL`.
+///
+/// # Arguments
+/// `[left, comparison, value]` : The first three tokens of the condition.
+/// `token_iter` : The tokens that follow, from which the `NULL` of `IS NOT NULL` is taken.
+/// `input` : The line the tokens were taken from, which the spans of errors are relative to.
+///
+/// # Returns
+/// The parsed [`Predicate`], or [`ParseError::InvalidExpression`] if the condition is malformed.
+fn parse_condition<'a>(
+    [left, comparison, value]: [&'a str; 3],
+    token_iter: &mut Peekable<Iter<'_, &'a str>>,
+    input: &str,
+) -> Result<Predicate, ParseError> {
+    let null = (comparison == "IS" && value == "NOT").then(|| token_iter.next());
+    match null {
+        Some(null) => parse_predicate(left, "IS NOT", null.unwrap_or(&"")),
+        None => parse_predicate(left, comparison, value),
+    }
+    .map_err(|message| ParseError::InvalidExpression {
+        message,
+        span: Span::between(left, null.flatten().unwrap_or(&value), input),
+    })
+}
+
+/// Helper function to parse the optional `HAVING <condition>` of COUNTBY and GROUPBY.
+///
+/// # Arguments
+/// `keyword` : The keyword of the operator the condition belongs to.
+/// `token_iter` : The tokens following the arguments of the operator.
+/// `input` : The line the tokens were taken from, which the spans of errors are relative to.
+///
+/// # Returns
+/// The parsed condition, `None` if the next token isn't HAVING, or the [`ParseError`] describing
+/// why the condition is malformed.
+fn parse_having(
+    keyword: &str,
+    token_iter: &mut Peekable<Iter<'_, &str>>,
+    input: &str,
+) -> Result<Option<Predicate>, ParseError> {
+    let Some(having) = token_iter.next_if(|token| **token == "HAVING") else {
+        return Ok(None);
+    };
+    match (token_iter.next(), token_iter.next(), token_iter.next()) {
+        (Some(left), Some(comparison), Some(value)) => {
+            parse_condition([left, comparison, value], token_iter, input).map(Some)
+        }
+        _ => Err(ParseError::MissingArgument {
+            keyword: format!("{} ... HAVING", keyword),
+            expected: "a column name, a comparison, and a value, e.g. count > 10".to_string(),
+            span: Span::of(having, input),
+        }),
+    }
+}
+
+/// Helper function to parse a parenthesized sub-query, e.g. the `(FROM country.csv TAKE 5)` of
+/// `JOIN (FROM country.csv TAKE 5) CountryCode`.
+///
+/// # Arguments
+/// `first` : The token starting with the opening parenthesis.
+/// `token_iter` : The tokens that follow, of which those up to the one closing the parenthesis
+/// are taken.
+/// `subquery` : Describes the sub-query in the errors, e.g. "sub-query to JOIN on".
+/// `input`, `catalog`, `datasets` : As passed to [`parse_chain`].
+///
+/// # Returns
+/// The [`Operator`] chain of the sub-query, or the [`ParseError`] describing why it is malformed.
+fn parse_subquery<'a>(
+    first: &'a str,
+    token_iter: &mut Peekable<Iter<'_, &'a str>>,
+    subquery: &str,
+    input: &str,
+    catalog: &[String],
+    datasets: &DatasetRegistry,
+) -> Result<Operator, ParseError> {
+    // Collect the tokens up to the one that closes the parenthesis, which may also appear inside
+    // the sub-query, e.g. in function calls.
+    let missing_parenthesis = || ParseError::MissingParenthesis {
+        after: format!("the {}", subquery),
+        span: Span::of(first, input),
+    };
+    let mut body = vec![first];
+    let mut depth = first.matches('(').count() as i64 - first.matches(')').count() as i64;
+    while depth > 0 {
+        let next = token_iter.next().ok_or_else(missing_parenthesis)?;
+        depth += next.matches('(').count() as i64 - next.matches(')').count() as i64;
+        body.push(next);
+    }
+    let last = body.len() - 1;
+    body[last] = body[last]
+        .strip_suffix(')')
+        .ok_or_else(missing_parenthesis)?;
+    body[0] = &body[0][1..];
+    body.retain(|token| !token.is_empty());
+    if body.is_empty() {
+        return Err(ParseError::EmptySubquery {
+            subquery: subquery.to_string(),
+            span: Span::of(first, input),
+        });
+    }
+    parse_chain(&body, input, catalog, datasets)
+}
+
+/// Helper function to parse a chain of [`Operator`]s starting with FROM.
+///
+/// # Arguments
+/// `tokens` : The tokens to be processed.
+/// `input` : The line the `tokens` were taken from, which the spans of errors are relative to.
+/// `catalog` : The names of the sub-queries defined by the WITH clause of the query, if any.
+/// `datasets` : The datasets registered with `LOAD`.
+///
+/// # Returns
+/// The [`Operator`] chain, or the [`ParseError`] describing why the input is malformed.
+fn parse_chain(
+    tokens: &[&str],
+    input: &str,
+    catalog: &[String],
+    datasets: &DatasetRegistry,
+) -> Result<Operator, ParseError> {
+    let mut token_iter = tokens.iter().peekable();
 
     // This needs to be mutable as we will keep chaining operators onto the preceeding chain.
     let mut chain = None;
 
     while let Some(token) = token_iter.next() {
+        // Helpers for the errors about the operator itself, which point at its keyword.
+        let missing_from = || ParseError::MissingFrom {
+            keyword: token.to_string(),
+            span: Span::of(token, input),
+        };
+        let missing_argument = |expected: &str| ParseError::MissingArgument {
+            keyword: token.to_string(),
+            expected: expected.to_string(),
+            span: Span::of(token, input),
+        };
+        let invalid_dataset = |name: &str| ParseError::InvalidDataset {
+            keyword: token.to_string(),
+            name: name.to_string(),
+            span: Span::of(name, input),
+        };
+
         chain = match *token {
-            // Expected: FROM <["language.csv", "city.csv", "country.csv"]>
+            // Expected: FROM <dataset>
             "FROM" => {
                 // FROM must always be the first command.
                 if chain.is_some() {
-                    return Err("FROM must always be the first operator.".to_string());
+                    return Err(ParseError::MisplacedFrom {
+                        span: Span::of(token, input),
+                    });
                 } else {
-                    // The token following FROM must be one of
-                    // ["language.csv", "city.csv", "country.csv"]
+                    // The token following FROM must name a known dataset, with or without the
+                    // `.csv` suffix.
                     match token_iter.next() {
-                        Some(&"language.csv") => Some(Operator::From(Dataset::Language)),
-                        Some(&"city.csv") => Some(Operator::From(Dataset::City)),
-                        Some(&"country.csv") => Some(Operator::From(Dataset::Country)),
-                        other => {
-                            return Err(format!("Invalid argument to FROM: {:?}", other));
-                        }
+                        Some(name) => match resolve_dataset(name, catalog, datasets) {
+                            Some(dataset) => Some(Operator::From(dataset)),
+                            None => return Err(invalid_dataset(name)),
+                        },
+                        None => return Err(missing_argument("the dataset to load")),
                     }
                 }
             }
-            // Expected: ... SELECT <comma_seperated_column_names>
+            // Expected: ... SELECT <comma_separated_column_names_or_expressions>
             "SELECT" => match token_iter.next() {
+                // Expected: ... SELECT * [EXCEPT <column_name>[,<column_name>...]]
+                Some(&"*") => {
+                    if chain.is_none() {
+                        return Err(missing_from());
+                    }
+                    let except = match token_iter.next_if(|token| **token == "EXCEPT") {
+                        Some(_) => match token_iter.next() {
+                            Some(columns) => columns
+                                .split(',')
+                                .filter(|column| !column.is_empty())
+                                .map(str::to_string)
+                                .collect(),
+                            None => {
+                                return Err(missing_argument(
+                                    "the columns to select, or * EXCEPT and the columns to drop, e.g. SELECT * EXCEPT Capital",
+                                ));
+                            }
+                        },
+                        None => vec![],
+                    };
+                    Some(Operator::SelectAll {
+                        chain: Box::new(chain.unwrap()),
+                        except,
+                    })
+                }
                 Some(columns) => {
                     if chain.is_none() {
-                        return Err("SELECT can't be the first command; It must be preceded by at least a FROM.".to_string());
+                        return Err(missing_from());
                     }
 
+                    // Aliases, spaces around arithmetic operators, and spaces between the arguments
+                    // of functions split the columns into several tokens, e.g.
+                    // `CityName AS Name,CountryPop - CityPop,COALESCE(Capital, 0)`.
+                    let is_operator = |c: char| ArithmeticOperator::from_symbol(c).is_some();
+                    let (first, mut last) = (*columns, *columns);
+                    let mut columns = columns.to_string();
+                    loop {
+                        if let Some(as_keyword) = token_iter.next_if(|token| **token == "AS") {
+                            columns.push_str(" AS");
+                            last = as_keyword;
+                        } else if !columns.ends_with(is_operator)
+                            && !token_iter
+                                .peek()
+                                .is_some_and(|token| token.starts_with(is_operator))
+                            && !has_unclosed_parenthesis(&columns)
+                        {
+                            break;
+                        }
+                        match token_iter.next() {
+                            Some(token) => {
+                                columns.push(' ');
+                                columns.push_str(token);
+                                last = token;
+                            }
+                            None => break,
+                        }
+                    }
                     Some(Operator::Select {
                         chain: Box::new(chain.unwrap()),
-                        column_names: columns
-                            .split(",")
-                            .filter(|s| !s.is_empty())
-                            .map(|s| s.to_string())
-                            .collect::<Vec<String>>(),
+                        columns: parse_select_list(&columns).map_err(|message| {
+                            ParseError::InvalidExpression {
+                                message,
+                                span: Span::between(first, last, input),
+                            }
+                        })?,
+                    })
+                }
+                None => {
+                    return Err(missing_argument("the columns to select"));
+                }
+            },
+            // Expected: ... EXTEND <name> = <expression>[,<name> = <expression>...]
+            "EXTEND" => match token_iter.next() {
+                Some(columns) => {
+                    if chain.is_none() {
+                        return Err(missing_from());
+                    }
+
+                    // Spaces around `=` and the arithmetic operators, and between the arguments of
+                    // functions, split the columns into several tokens, e.g.
+                    // `PopDensity = CityPop / 1000`.
+                    let is_operator =
+                        |c: char| c == '=' || ArithmeticOperator::from_symbol(c).is_some();
+                    let (first, mut last) = (*columns, *columns);
+                    let mut columns = columns.to_string();
+                    while columns.ends_with(is_operator)
+                        || token_iter
+                            .peek()
+                            .is_some_and(|token| token.starts_with(is_operator))
+                        || has_unclosed_parenthesis(&columns)
+                    {
+                        match token_iter.next() {
+                            Some(token) => {
+                                columns.push(' ');
+                                columns.push_str(token);
+                                last = token;
+                            }
+                            None => break,
+                        }
+                    }
+                    Some(Operator::Extend {
+                        chain: Box::new(chain.unwrap()),
+                        columns: parse_extend_list(&columns).map_err(|message| {
+                            ParseError::InvalidExpression {
+                                message,
+                                span: Span::between(first, last, input),
+                            }
+                        })?,
                     })
                 }
                 None => {
-                    return Err("SELECT takes at least one column name to select on.".to_string());
+                    return Err(missing_argument(
+                        "the columns to add, e.g. EXTEND PopDensity = CityPop / 1000",
+                    ));
                 }
             },
             // Expected: ... TAKE <+ve number>
@@ -94,393 +807,3110 @@ fn parse_operators(tokens: &Vec<&str>) -> Result<Operator, String> {
                 Some(count) => {
                     if chain.is_none() {
                         // Early termination.
-                        return Err("TAKE can't be the first command; It must be preceded by at least a FROM.".to_string());
+                        return Err(missing_from());
                     }
                     Some(Operator::Take {
                         chain: Box::new(chain.unwrap()),
                         count: match str::parse::<usize>(count) {
                             Ok(count) => count,
-                            Err(e) => {
-                                return Err(format!(
-                                    "Invalid value passed to TAKE operator: {}. Must be a positive integer.\n Full error message: {}",
-                                    count, e.to_string()
-                                ));
+                            Err(error) => {
+                                return Err(ParseError::InvalidNumber {
+                                    keyword: token.to_string(),
+                                    value: count.to_string(),
+                                    error,
+                                    span: Span::of(count, input),
+                                });
+                            }
+                        },
+                    })
+                }
+                None => {
+                    return Err(missing_argument("the number of rows to take"));
+                }
+            },
+            // Expected: ... SKIP <+ve number>
+            "SKIP" => match token_iter.next() {
+                Some(count) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Skip {
+                        chain: Box::new(chain.unwrap()),
+                        count: match str::parse::<usize>(count) {
+                            Ok(count) => count,
+                            Err(error) => {
+                                return Err(ParseError::InvalidNumber {
+                                    keyword: token.to_string(),
+                                    value: count.to_string(),
+                                    error,
+                                    span: Span::of(count, input),
+                                });
+                            }
+                        },
+                    })
+                }
+                None => {
+                    return Err(missing_argument("the number of rows to skip"));
+                }
+            },
+            // Expected: ... TAIL <+ve number>
+            "TAIL" => match token_iter.next() {
+                Some(count) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Tail {
+                        chain: Box::new(chain.unwrap()),
+                        count: match str::parse::<usize>(count) {
+                            Ok(count) => count,
+                            Err(error) => {
+                                return Err(ParseError::InvalidNumber {
+                                    keyword: token.to_string(),
+                                    value: count.to_string(),
+                                    error,
+                                    span: Span::of(count, input),
+                                });
+                            }
+                        },
+                    })
+                }
+                None => {
+                    return Err(missing_argument("the number of rows to keep from the end"));
+                }
+            },
+            // Expected: ... SAMPLE <+ve number>|<percentage>% [SEED <+ve number>]
+            "SAMPLE" => match token_iter.next() {
+                Some(size) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    let size = match SampleSize::parse(size) {
+                        Some(size) => size,
+                        None => {
+                            return Err(missing_argument(
+                                "the number of rows to sample, or a percentage of them, e.g. 10%",
+                            ));
+                        }
+                    };
+                    // The seed is optional; without it, different rows are sampled every time.
+                    let seed = match token_iter.next_if(|token| **token == "SEED") {
+                        Some(seed_keyword) => match token_iter.next() {
+                            Some(seed) => match str::parse::<u64>(seed) {
+                                Ok(seed) => Some(seed),
+                                Err(error) => {
+                                    return Err(ParseError::InvalidNumber {
+                                        keyword: "SAMPLE ... SEED".to_string(),
+                                        value: seed.to_string(),
+                                        error,
+                                        span: Span::of(seed, input),
+                                    });
+                                }
+                            },
+                            None => {
+                                return Err(ParseError::MissingArgument {
+                                    keyword: "SAMPLE ... SEED".to_string(),
+                                    expected: "the seed of the random number generator, e.g. 42"
+                                        .to_string(),
+                                    span: Span::of(seed_keyword, input),
+                                });
                             }
                         },
+                        None => None,
+                    };
+                    Some(Operator::Sample {
+                        chain: Box::new(chain.unwrap()),
+                        size,
+                        seed,
                     })
                 }
                 None => {
-                    return Err("TAKE must be followed by the number of rows to take.".to_string());
+                    return Err(missing_argument(
+                        "the number of rows to sample, or a percentage of them, e.g. 10%",
+                    ));
                 }
             },
-            // Expected: ... ORDERBY <column_name>
+            // Expected: ... ORDERBY <column_name> [ASC|DESC] [NULLS FIRST|LAST]
             "ORDERBY" => match token_iter.next() {
                 Some(column_name) => {
                     if chain.is_none() {
                         // Early termination.
-                        return Err("ORDERBY can't be the first command; It must be preceded by at least a FROM.".to_string());
+                        return Err(missing_from());
                     }
+                    // The order is optional, and defaults to descending.
+                    let order = match token_iter.next_if(|token| matches!(**token, "ASC" | "DESC"))
+                    {
+                        Some(&"ASC") => SortOrder::Ascending,
+                        _ => SortOrder::Descending,
+                    };
+                    // Rows with a missing value come last unless asked otherwise.
+                    let nulls = match token_iter.next_if(|token| **token == "NULLS") {
+                        Some(nulls_keyword) => match token_iter.next() {
+                            Some(&"FIRST") => NullsOrder::First,
+                            Some(&"LAST") => NullsOrder::Last,
+                            _ => {
+                                return Err(ParseError::MissingArgument {
+                                    keyword: "ORDERBY ... NULLS".to_string(),
+                                    expected: "FIRST or LAST".to_string(),
+                                    span: Span::of(nulls_keyword, input),
+                                });
+                            }
+                        },
+                        None => NullsOrder::Last,
+                    };
                     Some(Operator::OrderBy {
                         chain: Box::new(chain.unwrap()),
                         column: column_name.to_string(),
+                        order,
+                        nulls,
                     })
                 }
                 None => {
-                    return Err(
-                        "ORDERBY must be followed by the name of the column to order by."
-                            .to_string(),
-                    );
+                    return Err(missing_argument("the name of the column to order by"));
                 }
             },
-            // Expected: ... COUNTBY <column_name>
+            // Expected: ... COUNTBY <column_name> [ORDER count|value]
             "COUNTBY" => match token_iter.next() {
                 Some(column_name) => {
                     if chain.is_none() {
                         // Early termination.
-                        return Err("COUNTBY can't be the first command; It must be preceded by at least a FROM.".to_string());
+                        return Err(missing_from());
                     }
+                    // The order is optional, and defaults to the count.
+                    let order = match token_iter.next_if(|token| **token == "ORDER") {
+                        Some(order_keyword) => match token_iter
+                            .next()
+                            .and_then(|name| CountByOrder::from_name(name))
+                        {
+                            Some(order) => order,
+                            None => {
+                                return Err(ParseError::MissingArgument {
+                                    keyword: "COUNTBY ... ORDER".to_string(),
+                                    expected: "count or value".to_string(),
+                                    span: Span::of(order_keyword, input),
+                                });
+                            }
+                        },
+                        None => CountByOrder::Count,
+                    };
                     Some(Operator::CountBy {
                         chain: Box::new(chain.unwrap()),
                         column: column_name.to_string(),
+                        order,
+                        bars: token_iter.next_if(|token| **token == "BARS").is_some(),
+                        having: parse_having(token, &mut token_iter, input)?,
                     })
                 }
                 None => {
-                    return Err(
-                        "COUNTBY must be followed by the name of the column to count.".to_string(),
-                    );
+                    return Err(missing_argument("the name of the column to count"));
                 }
             },
-            // Expected: ... JOIN <["language.csv", "city.csv", "country.csv"]> <column_name>
-            "JOIN" => {
-                if chain.is_some() {
-                    let dataset = match token_iter.next() {
-                        Some(&"language.csv") => Dataset::Language,
-                        Some(&"city.csv") => Dataset::City,
-                        Some(&"country.csv") => Dataset::Country,
-                        Some(str) => {
-                            return Err(format!("Invalid dataset to JOIN on: {}", str));
+            // Expected: ... COUNT [*], to count the rows of the input.
+            "COUNT"
+                if token_iter
+                    .peek()
+                    .is_none_or(|next| **next == ALL_ROWS || is_operator_keyword(next)) =>
+            {
+                if chain.is_none() {
+                    // Early termination.
+                    return Err(missing_from());
+                }
+                token_iter.next_if(|next| **next == ALL_ROWS);
+                Some(Operator::Aggregate {
+                    chain: Box::new(chain.unwrap()),
+                    function: AggregateFunction::Count,
+                    column: ALL_ROWS.to_string(),
+                })
+            }
+            // Expected: ... SUM|AVG|MIN|MAX|COUNT <column_name>
+            "SUM" | "AVG" | "MIN" | "MAX" | "COUNT" => match token_iter.next() {
+                Some(column_name) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Aggregate {
+                        chain: Box::new(chain.unwrap()),
+                        function: AggregateFunction::from_name(token).unwrap(),
+                        column: column_name.to_string(),
+                    })
+                }
+                None => {
+                    return Err(missing_argument("the name of the column to aggregate"));
+                }
+            },
+            // Expected: ... GROUPBY <column_name> <AGG(column_name)>[,<AGG(column_name)>...]
+            "GROUPBY" => match (token_iter.next(), token_iter.next()) {
+                (Some(column_name), Some(aggregates)) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    let aggregates = parse_aggregate_list(aggregates).map_err(|message| {
+                        ParseError::InvalidExpression {
+                            message,
+                            span: Span::of(aggregates, input),
+                        }
+                    })?;
+                    // The condition reads an aggregate by its name, e.g. `SUM(CityPop) > 10`,
+                    // which would otherwise be parsed as a call to a function.
+                    let having = parse_having(token, &mut token_iter, input)?.map(|mut having| {
+                        if let Ok([aggregate]) =
+                            parse_aggregate_list(&having.left.to_string()).as_deref()
+                        {
+                            if aggregates.contains(aggregate) {
+                                having.left = Expression::Column(aggregate.to_string());
+                            }
+                        }
+                        having
+                    });
+                    Some(Operator::GroupBy {
+                        chain: Box::new(chain.unwrap()),
+                        column: column_name.to_string(),
+                        aggregates,
+                        having,
+                    })
+                }
+                _ => {
+                    return Err(missing_argument("the name of the column to group by and the aggregates to compute, e.g. SUM(CityPop)"));
+                }
+            },
+            // Expected: ... PIVOT <column_name> <column_name> <AGG(column_name)>
+            "PIVOT" => match (token_iter.next(), token_iter.next(), token_iter.next()) {
+                (Some(row), Some(column), Some(aggregate)) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    let invalid = |message: String| ParseError::InvalidExpression {
+                        message,
+                        span: Span::of(aggregate, input),
+                    };
+                    let aggregate = match parse_aggregate_list(aggregate).map_err(invalid)? {
+                        aggregates if aggregates.len() == 1 => aggregates[0].clone(),
+                        _ => {
+                            return Err(invalid(format!(
+                                "PIVOT computes a single aggregate, not {}.",
+                                aggregate
+                            )));
                         }
+                    };
+                    Some(Operator::Pivot {
+                        chain: Box::new(chain.unwrap()),
+                        row: row.to_string(),
+                        column: column.to_string(),
+                        aggregate,
+                    })
+                }
+                _ => {
+                    return Err(missing_argument("the column whose values become the rows, the column whose values become the columns, and an aggregate, e.g. PIVOT Language Continent COUNT(CountryCode)"));
+                }
+            },
+            // Expected: ... JOIN|LEFTJOIN|RIGHTJOIN|OUTERJOIN <dataset> <column_name>
+            //        or: ... JOIN|LEFTJOIN|RIGHTJOIN|OUTERJOIN <dataset> ON <column_name>=<column_name>
+            // where <dataset> may also be a parenthesized sub-query, e.g. (FROM country.csv ...).
+            "JOIN" | "LEFTJOIN" | "RIGHTJOIN" | "OUTERJOIN" => {
+                if let Some(chain) = chain {
+                    let right = match token_iter.next() {
+                        Some(first) if first.starts_with('(') => Box::new(parse_subquery(
+                            first,
+                            &mut token_iter,
+                            &format!("sub-query to {} on", token),
+                            input,
+                            catalog,
+                            datasets,
+                        )?),
+                        Some(name) => match resolve_dataset(name, catalog, datasets) {
+                            Some(dataset) => Box::new(Operator::From(dataset)),
+                            None => return Err(invalid_dataset(name)),
+                        },
                         None => {
-                            return Err(
-                                "JOIN must be followed by the dataset and the name of the column to join on."
-                                    .to_string(),
-                            );
+                            return Err(missing_argument(
+                                "the dataset and the name of the column to join on",
+                            ));
                         }
                     };
-                    let column_name = match token_iter.next() {
-                        Some(column_name) => column_name,
+                    let (column_name, right_column) = match token_iter.next() {
+                        Some(on @ &"ON") => {
+                            match token_iter.next().and_then(|pair| pair.split_once('=')) {
+                                Some((left, right)) if !left.is_empty() && !right.is_empty() => {
+                                    // Joining on the same name is the same as not using ON.
+                                    (left, (left != right).then(|| right.to_string()))
+                                }
+                                _ => {
+                                    return Err(ParseError::MissingArgument {
+                                        keyword: format!("{} ON", token),
+                                        expected: "the columns to join on, separated by =, e.g. Capital=CityID".to_string(),
+                                        span: Span::of(on, input),
+                                    });
+                                }
+                            }
+                        }
+                        Some(column_name) => (*column_name, None),
                         None => {
-                            return Err(
-                                "JOIN must be followed by the dataset and the name of the column to join on."
-                                    .to_string(),
-                            );
+                            return Err(missing_argument(
+                                "the dataset and the name of the column to join on",
+                            ));
                         }
                     };
+                    // The join strategy can be picked with a hint, e.g. USING MERGE.
+                    let strategy = match token_iter.next_if(|t| **t == "USING") {
+                        Some(using) => match token_iter
+                            .next()
+                            .and_then(|t| JoinStrategy::from_keyword(t))
+                        {
+                            Some(strategy) => Some(strategy),
+                            None => {
+                                return Err(ParseError::MissingArgument {
+                                    keyword: format!("{} ... USING", token),
+                                    expected: "the join strategy, HASH or MERGE".to_string(),
+                                    span: Span::of(using, input),
+                                });
+                            }
+                        },
+                        None => None,
+                    };
                     Some(Operator::Join {
-                        chain: Box::new(chain.unwrap()),
-                        right: dataset,
+                        chain: Box::new(chain),
+                        right,
                         column: column_name.to_string(),
+                        right_column,
+                        kind: JoinKind::from_keyword(token).unwrap(),
+                        strategy,
                     })
                 } else {
                     // Early termination.
-                    return Err(
-                        "JOIN can't be the first command; It must be preceded by at least a FROM."
-                            .to_string(),
-                    );
+                    return Err(missing_from());
                 }
             }
-            _ => {
-                // Early termination.
-                return Err(format!("Invalid Input: {}", tokens.join(" ")));
-            }
-        };
-    }
-
-    if chain.is_some() {
-        Ok(chain.unwrap())
-    } else {
-        Err(format!("Invalid Input: {}", tokens.join(" ")))
-    }
-}
-
-/// Parses the command entered on the CLI into a [`Command`].
-///
-/// # Arguments
-/// `input` : the input string to be processed.
-///
-/// # Returns
-/// A [`Command`] that represents the parsed input.
-pub fn parse_command(input: &str) -> Command {
-    // Remove the trailing new line.
-    match input.strip_suffix("\n") {
-        Some(val) => match val {
-            "help" => Command::Help,
-            "exit" => Command::Exit,
-            _ => {
-                // Use split_whitespace to get rid of excess whitespace in the input.
-                let tokens: Vec<&str> = val.split_whitespace().collect();
-                if tokens.is_empty() {
-                    Command::NoInput
+            // Expected: ... UNION [ALL] (<query>) ...
+            // where the operators after the parenthesized query apply to the combined rows.
+            "UNION" => {
+                if let Some(chain) = chain {
+                    let all = token_iter.next_if(|token| **token == "ALL");
+                    let keyword = if all.is_some() { "UNION ALL" } else { "UNION" };
+                    // Without the parentheses, it wouldn't be clear whether the operators after
+                    // the query apply to it or to the combined rows.
+                    let expected = "the query to combine with, in parentheses, e.g. (FROM language.csv SELECT CountryCode)";
+                    let right = match token_iter.next() {
+                        Some(first) if first.starts_with('(') => parse_subquery(
+                            first,
+                            &mut token_iter,
+                            &format!("sub-query to {} with", keyword),
+                            input,
+                            catalog,
+                            datasets,
+                        )?,
+                        Some(first) => {
+                            return Err(ParseError::MissingArgument {
+                                keyword: keyword.to_string(),
+                                expected: expected.to_string(),
+                                span: Span::of(first, input),
+                            });
+                        }
+                        None => {
+                            return Err(ParseError::MissingArgument {
+                                keyword: keyword.to_string(),
+                                expected: expected.to_string(),
+                                span: Span::between(token, all.unwrap_or(token), input),
+                            });
+                        }
+                    };
+                    Some(Operator::Union {
+                        chain: Box::new(chain),
+                        right: Box::new(right),
+                        all: all.is_some(),
+                    })
                 } else {
-                    match parse_operators(&tokens) {
-                        Ok(operator) => Command::Operator(operator),
-                        Err(str) => Command::InputError(str),
-                    }
+                    // Early termination.
+                    return Err(missing_from());
                 }
             }
-        },
-        None => Command::NoInput,
-    }
-}
-
-/// Test for NULL input
-#[test]
-fn test_parse_command_no_input() {
-    assert_eq!(parse_command("\n"), Command::NoInput);
-}
-
+            // Expected: ... FILTER <expression> <comparison> <value>
+            // or:       ... FILTER <expression> IS [NOT] NULL
+            "FILTER" | "WHERE" => match (token_iter.next(), token_iter.next(), token_iter.next()) {
+                (Some(left), Some(comparison), Some(value)) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Filter {
+                        chain: Box::new(chain.unwrap()),
+                        predicate: parse_condition(
+                            [left, comparison, value],
+                            &mut token_iter,
+                            input,
+                        )?,
+                    })
+                }
+                _ => {
+                    return Err(missing_argument(
+                        "a column name, a comparison, and a value, e.g. CityPop > 1000000",
+                    ));
+                }
+            },
+            // Expected: ... MAP <script>
+            "MAP" => match token_iter.next() {
+                Some(script) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Map {
+                        chain: Box::new(chain.unwrap()),
+                        script: script.to_string(),
+                    })
+                }
+                None => {
+                    return Err(missing_argument("the path of the script to run"));
+                }
+            },
+            // Expected: ... DISTINCT [<comma_separated_column_names>]
+            "DISTINCT" => {
+                if chain.is_none() {
+                    // Early termination.
+                    return Err(missing_from());
+                }
+                // The columns are optional, so the next token is only taken if it isn't the next
+                // operator.
+                let columns = match token_iter.next_if(|token| !is_operator_keyword(token)) {
+                    Some(columns) => columns
+                        .split(',')
+                        .filter(|column| !column.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    None => vec![],
+                };
+                Some(Operator::Distinct {
+                    chain: Box::new(chain.unwrap()),
+                    columns,
+                })
+            }
+            // Expected: ... RENAME <column_name> <new_column_name>
+            "RENAME" => match (token_iter.next(), token_iter.next()) {
+                (Some(column), Some(new_name)) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Rename {
+                        chain: Box::new(chain.unwrap()),
+                        column: column.to_string(),
+                        new_name: new_name.to_string(),
+                    })
+                }
+                _ => {
+                    return Err(missing_argument("the name of the column to rename and its new name, e.g. RENAME CityPop Population"));
+                }
+            },
+            // Expected: ... REORDER <comma_separated_column_names>
+            "REORDER" => match token_iter.next() {
+                Some(columns) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Reorder {
+                        chain: Box::new(chain.unwrap()),
+                        columns: columns
+                            .split(',')
+                            .filter(|column| !column.is_empty())
+                            .map(str::to_string)
+                            .collect(),
+                    })
+                }
+                None => {
+                    return Err(missing_argument("the names of the columns to move to the front, e.g. REORDER CityName,CityPop"));
+                }
+            },
+            // Expected: ... CAST <column_name> AS INT|FLOAT|STRING
+            // Expected: ... FILLNA <column_name> <value>
+            "FILLNA" => match (token_iter.next(), token_iter.next()) {
+                (Some(column), Some(value)) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::FillNa {
+                        chain: Box::new(chain.unwrap()),
+                        column: column.to_string(),
+                        value: parse_constant(value),
+                    })
+                }
+                _ => {
+                    return Err(missing_argument(
+                        "the name of the column and the value replacing its missing values, e.g. FILLNA Capital 0",
+                    ));
+                }
+            },
+            "CAST" => match (token_iter.next(), token_iter.next(), token_iter.next()) {
+                (Some(column), Some(&"AS"), Some(to)) if CastType::from_name(to).is_some() => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    Some(Operator::Cast {
+                        chain: Box::new(chain.unwrap()),
+                        column: column.to_string(),
+                        to: CastType::from_name(to).unwrap(),
+                    })
+                }
+                _ => {
+                    return Err(missing_argument(
+                        "the name of the column to convert, AS, and INT, FLOAT, or STRING, e.g. CAST Zip AS INT",
+                    ));
+                }
+            },
+            // Expected: ... TOPN <+ve number> BY <column_name> ORDER <column_name> [ASC|DESC]
+            "TOPN" => match (
+                token_iter.next(),
+                token_iter.next(),
+                token_iter.next(),
+                token_iter.next(),
+                token_iter.next(),
+            ) {
+                (Some(count), Some(&"BY"), Some(group), Some(&"ORDER"), Some(column)) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    let count = match str::parse::<usize>(count) {
+                        Ok(count) => count,
+                        Err(error) => {
+                            return Err(ParseError::InvalidNumber {
+                                keyword: token.to_string(),
+                                value: count.to_string(),
+                                error,
+                                span: Span::of(count, input),
+                            });
+                        }
+                    };
+                    // The order is optional, and defaults to descending.
+                    let order = match token_iter.next_if(|token| matches!(**token, "ASC" | "DESC"))
+                    {
+                        Some(&"ASC") => SortOrder::Ascending,
+                        _ => SortOrder::Descending,
+                    };
+                    Some(Operator::TopN {
+                        chain: Box::new(chain.unwrap()),
+                        count,
+                        group: group.to_string(),
+                        column: column.to_string(),
+                        order,
+                    })
+                }
+                _ => {
+                    return Err(missing_argument(
+                        "the number of rows to keep, BY and the column to group by, and ORDER and the column to rank by, e.g. TOPN 3 BY CountryCode ORDER CityPop",
+                    ));
+                }
+            },
+            // Expected: ... RANK BY <column_name> [PER <column_name>]
+            "RANK" => match (token_iter.next(), token_iter.next()) {
+                (Some(&"BY"), Some(column)) => {
+                    if chain.is_none() {
+                        // Early termination.
+                        return Err(missing_from());
+                    }
+                    // The partition is optional, and the rows are ranked together without one.
+                    let partition = match token_iter.next_if(|token| **token == "PER") {
+                        Some(_) => match token_iter.next() {
+                            Some(partition) => Some(partition.to_string()),
+                            None => {
+                                return Err(missing_argument(
+                                    "BY and the column to rank the rows by, and optionally PER and the column to rank them within, e.g. RANK BY CityPop PER CountryCode",
+                                ));
+                            }
+                        },
+                        None => None,
+                    };
+                    Some(Operator::Rank {
+                        chain: Box::new(chain.unwrap()),
+                        column: column.to_string(),
+                        partition,
+                    })
+                }
+                _ => {
+                    return Err(missing_argument(
+                        "BY and the column to rank the rows by, and optionally PER and the column to rank them within, e.g. RANK BY CityPop PER CountryCode",
+                    ));
+                }
+            },
+            // Expected: ... INTO|EXPORT [CSV|ARROW] <path>
+            "INTO" | "EXPORT" => {
+                let format = match token_iter.peek() {
+                    Some(keyword) if *token == "EXPORT" => ExportFormat::from_keyword(keyword),
+                    _ => None,
+                };
+                if format.is_some() {
+                    token_iter.next();
+                }
+                match token_iter.next() {
+                    Some(path) => {
+                        if chain.is_none() {
+                            // Early termination.
+                            return Err(missing_from());
+                        }
+                        if let Some(next) = token_iter.peek() {
+                            return Err(ParseError::NotLast {
+                                keyword: token.to_string(),
+                                span: Span::of(next, input),
+                            });
+                        }
+                        Some(Operator::Into {
+                            chain: Box::new(chain.unwrap()),
+                            path: path.to_string(),
+                            format: format.unwrap_or_default(),
+                        })
+                    }
+                    None => {
+                        return Err(missing_argument("the path of the file to write"));
+                    }
+                }
+            }
+            _ => {
+                // Early termination.
+                return Err(ParseError::UnknownKeyword {
+                    keyword: token.to_string(),
+                    span: Span::of(token, input),
+                });
+            }
+        };
+    }
+
+    match chain {
+        Some(chain) => Ok(chain),
+        // Only reached if there are no tokens at all.
+        None => Err(ParseError::UnknownKeyword {
+            keyword: tokens.join(" "),
+            span: Span {
+                start: 0,
+                end: input.len(),
+            },
+        }),
+    }
+}
+
+/// Parses the command entered on the CLI into a [`Command`]. Only the built-in datasets and CSV
+/// files can be referred to; see [`parse_command_with`] to also refer to registered datasets.
+///
+/// # Arguments
+/// `input` : the input string to be processed.
+///
+/// # Returns
+/// A [`Command`] that represents the parsed input.
+pub fn parse_command(input: &str) -> Command {
+    parse_command_with(input, &DatasetRegistry::default())
+}
+
+/// Parses the command entered on the CLI into a [`Command`], resolving the names of the datasets
+/// passed to FROM and JOIN in the `datasets` registered with `LOAD`.
+///
+/// # Arguments
+/// `input` : the input string to be processed.
+/// `datasets` : The datasets registered so far.
+///
+/// # Returns
+/// A [`Command`] that represents the parsed input.
+pub fn parse_command_with(input: &str, datasets: &DatasetRegistry) -> Command {
+    // Remove the trailing new line.
+    match input.strip_suffix("\n") {
+        Some(val) => match val {
+            "help" => Command::Help(None),
+            _ if val.starts_with("help ") => {
+                let tokens: Vec<&str> = val.split_whitespace().collect();
+                match tokens[1..] {
+                    [name] => match find_operator_descriptor(name) {
+                        Some(operator) => Command::Help(Some(operator.name.to_string())),
+                        None => Command::InputError(ParseError::NoHelp {
+                            name: name.to_string(),
+                            span: Span::of(name, val),
+                        }),
+                    },
+                    _ => Command::InputError(ParseError::UnexpectedToken {
+                        token: tokens[2].to_string(),
+                        span: Span::of(tokens[2], val),
+                    }),
+                }
+            }
+            "exit" => Command::Exit,
+            _ if val.starts_with('\\') => {
+                let tokens: Vec<&str> = val[1..].split_whitespace().collect();
+                match tokens.split_first() {
+                    Some((name, args)) if SESSION_COMMANDS.contains(name) => {
+                        match parse_session_command(name, args) {
+                            Ok(command) => command,
+                            Err(message) => Command::InputError(ParseError::InvalidSetting {
+                                message,
+                                span: Span::between(name, tokens[tokens.len() - 1], val),
+                            }),
+                        }
+                    }
+                    Some((name, args)) => match parse_setting(name, args) {
+                        Ok(setting) => Command::Setting(setting),
+                        Err(message) => Command::InputError(ParseError::InvalidSetting {
+                            message,
+                            span: Span::between(name, tokens[tokens.len() - 1], val),
+                        }),
+                    },
+                    None => Command::InputError(ParseError::MissingArgument {
+                        keyword: "\\".to_string(),
+                        expected: "the name of a setting".to_string(),
+                        span: Span::of(val, val),
+                    }),
+                }
+            }
+            _ => {
+                // Quoted strings, e.g. 'New York', are single tokens even if they contain spaces.
+                let tokens = match tokenize(val) {
+                    Ok(tokens) => tokens,
+                    Err(text) => {
+                        return Command::InputError(ParseError::UnterminatedString {
+                            text: text.to_string(),
+                            span: Span::of(text, val),
+                        })
+                    }
+                };
+                let missing_argument = |keyword: &str, expected: &str| {
+                    Command::InputError(ParseError::MissingArgument {
+                        keyword: keyword.to_string(),
+                        expected: expected.to_string(),
+                        span: Span::of(tokens[0], val),
+                    })
+                };
+                let missing_delete_condition = || {
+                    missing_argument(
+                        "DELETE",
+                        "FROM, the dataset, WHERE, and a condition, e.g. DELETE FROM city.csv WHERE CityPop < 1000",
+                    )
+                };
+                match tokens.split_first() {
+                    None => Command::NoInput,
+                    Some((&"EXPLAIN", [])) => missing_argument("EXPLAIN", "the query to explain"),
+                    Some((&"EXPLAIN", [analyze])) if *analyze == "ANALYZE" => {
+                        missing_argument("EXPLAIN ANALYZE", "the query to run")
+                    }
+                    Some((&"EXPLAIN", [analyze, query @ ..])) if *analyze == "ANALYZE" => {
+                        match parse_operators(query, val, datasets) {
+                            Ok(operator) => Command::ExplainAnalyze(operator),
+                            Err(error) => Command::InputError(error),
+                        }
+                    }
+                    Some((&"EXPLAIN", query)) => match parse_operators(query, val, datasets) {
+                        Ok(operator) => Command::Explain(operator),
+                        Err(error) => Command::InputError(error),
+                    },
+                    Some((&"WATCH", [])) => missing_argument("WATCH", "the query to watch"),
+                    Some((&"WATCH", query)) => match parse_operators(query, val, datasets) {
+                        Ok(operator) => Command::Watch(operator),
+                        Err(error) => Command::InputError(error),
+                    },
+                    // Expected: LOAD <name> FROM <path> [ENCODING <label>]
+                    Some((&"LOAD", [_, from_keyword, .., encoding_keyword]))
+                        if *from_keyword == "FROM" && *encoding_keyword == "ENCODING" =>
+                    {
+                        missing_argument("ENCODING", "the name of an encoding, e.g. latin1")
+                    }
+                    Some((&"LOAD", [name, from_keyword, path @ ..]))
+                        if *from_keyword == "FROM" && !path.is_empty() =>
+                    {
+                        let (path, encoding) = match path {
+                            [path @ .., keyword, label]
+                                if *keyword == "ENCODING" && !path.is_empty() =>
+                            {
+                                (path, Some(label))
+                            }
+                            _ => (path, None),
+                        };
+                        let encoding = encoding
+                            .map(|label| {
+                                parse_encoding(label).map_err(|message| {
+                                    ParseError::InvalidEncoding {
+                                        message,
+                                        span: Span::of(label, val),
+                                    }
+                                })
+                            })
+                            .transpose();
+                        match encoding {
+                            _ if is_operator_keyword(name) => {
+                                Command::InputError(ParseError::ReservedName {
+                                    name: name.to_string(),
+                                    span: Span::of(name, val),
+                                })
+                            }
+                            Err(error) => Command::InputError(error),
+                            Ok(encoding) => Command::Load {
+                                name: name.to_string(),
+                                // A path containing spaces can be quoted.
+                                path: match path {
+                                    [path] => unquote(path).unwrap_or(path.to_string()),
+                                    _ => path.join(" "),
+                                },
+                                encoding,
+                            },
+                        }
+                    }
+                    // Expected: ATTACH <path>
+                    Some((&"ATTACH", path @ [_, ..])) => Command::Attach(match path {
+                        // A path containing spaces can be quoted.
+                        [path] => unquote(path).unwrap_or(path.to_string()),
+                        _ => path.join(" "),
+                    }),
+                    // Expected: SET <name> = <query>
+                    Some((&"SET", [name, equals, query @ ..]))
+                        if *equals == "=" && !query.is_empty() =>
+                    {
+                        let name = name.strip_prefix('@').unwrap_or(name);
+                        if !is_identifier(name) {
+                            Command::InputError(ParseError::InvalidVariableName {
+                                name: name.to_string(),
+                                span: Span::of(name, val),
+                            })
+                        } else {
+                            match parse_operators(query, val, datasets) {
+                                Ok(operator) => Command::Set {
+                                    name: name.to_string(),
+                                    operator,
+                                },
+                                Err(error) => Command::InputError(error),
+                            }
+                        }
+                    }
+                    // Expected: INSERT INTO <dataset> VALUES (<value>, ...), ...
+                    Some((&"INSERT", [into, name, values_keyword, values @ ..]))
+                        if *into == "INTO" && *values_keyword == "VALUES" && !values.is_empty() =>
+                    {
+                        match resolve_dataset(name, &[], datasets) {
+                            None => Command::InputError(ParseError::InvalidDataset {
+                                keyword: "INSERT".to_string(),
+                                name: name.to_string(),
+                                span: Span::of(name, val),
+                            }),
+                            // The values are parsed from the rest of the line, as the spaces
+                            // between them don't matter.
+                            Some(dataset) => {
+                                let span = Span::between(values[0], values[values.len() - 1], val);
+                                match parse_values(&val[span.start..span.end]) {
+                                    Ok(rows) => Command::Insert { dataset, rows },
+                                    Err(message) => {
+                                        Command::InputError(ParseError::InvalidExpression {
+                                            message,
+                                            span,
+                                        })
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    // Expected: DELETE FROM <dataset> WHERE <condition>, which is parsed like the
+                    // query FROM <dataset> WHERE <condition>.
+                    Some((&"DELETE", [from_keyword, ..])) if *from_keyword == "FROM" => {
+                        match parse_chain(&tokens[1..], val, &[], datasets) {
+                            Ok(Operator::Filter { chain, predicate }) => match *chain {
+                                Operator::From(dataset) => Command::Delete { dataset, predicate },
+                                _ => missing_delete_condition(),
+                            },
+                            Ok(_) => missing_delete_condition(),
+                            Err(error) => Command::InputError(error),
+                        }
+                    }
+                    // Expected: DESCRIBE <dataset>
+                    Some((&"DESCRIBE", [name, rest @ ..])) => {
+                        match (resolve_dataset(name, &[], datasets), rest.first()) {
+                            (None, _) => Command::InputError(ParseError::InvalidDataset {
+                                keyword: "DESCRIBE".to_string(),
+                                name: name.to_string(),
+                                span: Span::of(name, val),
+                            }),
+                            (Some(_), Some(token)) => {
+                                Command::InputError(ParseError::UnexpectedToken {
+                                    token: token.to_string(),
+                                    span: Span::of(token, val),
+                                })
+                            }
+                            (Some(dataset), None) => Command::Describe(dataset),
+                        }
+                    }
+                    Some((&"DESCRIBE", [])) => missing_argument("DESCRIBE", "the dataset to describe"),
+                    // Expected: SHOW TABLES | SHOW COLUMNS <dataset>
+                    Some((&"SHOW", [tables, rest @ ..])) if *tables == "TABLES" => {
+                        match rest.first() {
+                            Some(token) => Command::InputError(ParseError::UnexpectedToken {
+                                token: token.to_string(),
+                                span: Span::of(token, val),
+                            }),
+                            None => Command::ShowTables,
+                        }
+                    }
+                    Some((&"SHOW", [columns, name, rest @ ..])) if *columns == "COLUMNS" => {
+                        match (resolve_dataset(name, &[], datasets), rest.first()) {
+                            (None, _) => Command::InputError(ParseError::InvalidDataset {
+                                keyword: "SHOW COLUMNS".to_string(),
+                                name: name.to_string(),
+                                span: Span::of(name, val),
+                            }),
+                            (Some(_), Some(token)) => {
+                                Command::InputError(ParseError::UnexpectedToken {
+                                    token: token.to_string(),
+                                    span: Span::of(token, val),
+                                })
+                            }
+                            (Some(dataset), None) => Command::ShowColumns(dataset),
+                        }
+                    }
+                    Some((&"SHOW", _)) => missing_argument(
+                        "SHOW",
+                        "TABLES, or COLUMNS and the dataset to list the columns of",
+                    ),
+                    // Expected: SAVE <dataset> [<path>]
+                    Some((&"SAVE", [name, path @ ..])) => {
+                        match resolve_dataset(name, &[], datasets) {
+                            None => Command::InputError(ParseError::InvalidDataset {
+                                keyword: "SAVE".to_string(),
+                                name: name.to_string(),
+                                span: Span::of(name, val),
+                            }),
+                            Some(dataset) => match (path, dataset.path()) {
+                                // Only datasets read from a CSV file can be written back to it.
+                                ([], Some(own_path))
+                                    if !is_parquet_file(own_path)
+                                        && !matches!(dataset, Dataset::Sqlite { .. }) =>
+                                {
+                                    Command::Save {
+                                        path: own_path.to_string(),
+                                        dataset,
+                                    }
+                                }
+                                ([], _) => Command::InputError(ParseError::MissingArgument {
+                                    keyword: format!("SAVE {}", name),
+                                    expected: "the path of the CSV file to write".to_string(),
+                                    span: Span::of(name, val),
+                                }),
+                                // A path containing spaces can be quoted.
+                                ([path], _) => Command::Save {
+                                    dataset,
+                                    path: unquote(path).unwrap_or(path.to_string()),
+                                },
+                                (path, _) => Command::Save {
+                                    dataset,
+                                    path: path.join(" "),
+                                },
+                            },
+                        }
+                    }
+                    Some((&"SAVE", [])) => missing_argument(
+                        "SAVE",
+                        "the dataset to write, and optionally the path of the CSV file to write it to",
+                    ),
+                    Some((&"INSERT", _)) => missing_argument(
+                        "INSERT",
+                        "INTO, the dataset, VALUES, and the rows of values, e.g. INSERT INTO language.csv VALUES (ATL, Atlantean)",
+                    ),
+                    Some((&"DELETE", _)) => missing_delete_condition(),
+                    Some((&"SET", _)) => missing_argument(
+                        "SET",
+                        "the name to save the result under, =, and the query",
+                    ),
+                    Some((&"ATTACH", [])) => {
+                        missing_argument("ATTACH", "the path of the SQLite database")
+                    }
+                    Some((&"LOAD", _)) => missing_argument(
+                        "LOAD",
+                        "the name of the dataset, FROM, and the path of the CSV or Parquet file",
+                    ),
+                    Some(_) => match parse_operators(&tokens, val, datasets) {
+                        Ok(operator) => Command::Operator(operator),
+                        Err(error) => Command::InputError(error),
+                    },
+                }
+            }
+        },
+        None => Command::NoInput,
+    }
+}
+
+/// The names of the backslash commands that act on the session rather than change a setting.
+pub const SESSION_COMMANDS: &[&str] = &["history", "rerun", "save", "load"];
+
+/// Helper function to parse a `\history`, `\rerun`, `\save`, or `\load` command.
+///
+/// # Arguments
+/// `name` : One of the [`SESSION_COMMANDS`], without the leading backslash.
+/// `args` : The whitespace separated arguments that followed the name.
+///
+/// # Returns
+/// The parsed [`Command`], or an error message describing why the input is invalid.
+fn parse_session_command(name: &str, args: &[&str]) -> Result<Command, String> {
+    match (name, args) {
+        ("history", []) => Ok(Command::History),
+        ("history", _) => Err("\\history doesn't take any arguments.".to_string()),
+        ("rerun", [number]) => match str::parse::<usize>(number) {
+            Ok(number) if number > 0 => Ok(Command::Rerun(number)),
+            _ => Err(format!(
+                "Invalid value passed to \\rerun: {}. Must be the number of a query listed by \\history.",
+                number
+            )),
+        },
+        ("rerun", _) => Err("\\rerun must be followed by the number of a query listed by \\history.".to_string()),
+        ("save", [path]) => Ok(Command::SaveSession(path.to_string())),
+        ("load", [path]) => Ok(Command::LoadSession(path.to_string())),
+        _ => Err(format!(
+            "\\{} must be followed by the path of the session file, e.g. session.toml.",
+            name
+        )),
+    }
+}
+
+/// Test the `\history` and `\rerun` commands as input
+#[test]
+fn test_parse_command_session() {
+    assert_eq!(parse_command("\\history\n"), Command::History);
+    assert_eq!(parse_command("\\rerun 3 \n"), Command::Rerun(3));
+    assert_eq!(
+        parse_error("\\history 3\n").to_string(),
+        "\\history doesn't take any arguments."
+    );
+    assert_eq!(
+        parse_error("\\rerun\n").to_string(),
+        "\\rerun must be followed by the number of a query listed by \\history."
+    );
+    for number in ["0", "-1", "last"] {
+        assert_eq!(
+            parse_error(&format!("\\rerun {}\n", number)).to_string(),
+            format!(
+                "Invalid value passed to \\rerun: {}. Must be the number of a query listed by \\history.",
+                number
+            )
+        );
+    }
+    assert_eq!(
+        parse_command("\\save session.toml\n"),
+        Command::SaveSession("session.toml".to_string())
+    );
+    assert_eq!(
+        parse_command("\\load session.toml\n"),
+        Command::LoadSession("session.toml".to_string())
+    );
+    assert_eq!(
+        parse_error("\\load\n").to_string(),
+        "\\load must be followed by the path of the session file, e.g. session.toml."
+    );
+    assert_eq!(
+        parse_error("\\rerun 1 2\n").span(),
+        Span { start: 1, end: 10 }
+    );
+}
+
+/// Returns the `line` without its trailing backslash if it ends with one, i.e., if the command
+/// continues on the next line. A backslash escaped by another one doesn't continue the line.
+pub fn strip_continuation(line: &str) -> Option<&str> {
+    let line = line.trim_end();
+    let backslashes = line.chars().rev().take_while(|c| *c == '\\').count();
+    (backslashes % 2 == 1).then(|| &line[..line.len() - 1])
+}
+
+#[test]
+fn test_strip_continuation() {
+    assert_eq!(
+        strip_continuation("FROM city.csv \\"),
+        Some("FROM city.csv ")
+    );
+    assert_eq!(
+        strip_continuation("ORDERBY CityPop\\  "),
+        Some("ORDERBY CityPop")
+    );
+    assert_eq!(strip_continuation("FROM city.csv"), None);
+    assert_eq!(strip_continuation("FILTER Path = C:\\\\"), None);
+    assert_eq!(strip_continuation(""), None);
+}
+
+/// Returns `true` if the query `input` is cut short, so that the REPL waits for the rest of it on
+/// the next line rather than reporting an error: a quoted string or a sub-query isn't closed, or
+/// the input ends with a keyword missing its arguments, e.g. `FROM city.csv ORDERBY`. Settings are
+/// never incomplete. Of several statements separated by semicolons, only the last one can be, unless
+/// it is ended by a semicolon too.
+///
+/// # Arguments
+/// `input` : The lines entered so far, without the trailing new line.
+/// `datasets` : The datasets registered with `LOAD`.
+pub fn is_incomplete(input: &str, datasets: &DatasetRegistry) -> bool {
+    let input = input.trim_end();
+    let input = match split_statements(input).pop() {
+        Some(statement) if input.ends_with(statement) => statement.trim_start(),
+        _ => return false,
+    };
+    if input.starts_with('\\') {
+        return false;
+    }
+    match parse_command_with(&format!("{}\n", input), datasets) {
+        Command::InputError(
+            ParseError::UnterminatedString { .. } | ParseError::MissingParenthesis { .. },
+        ) => true,
+        Command::InputError(error @ ParseError::MissingArgument { .. }) => {
+            error.span().end == input.len()
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn test_is_incomplete() {
+    let datasets = DatasetRegistry::default();
+    for incomplete in [
+        "FROM",
+        "FROM city.csv ORDERBY",
+        "FROM city.csv JOIN country.csv CountryCode\nFILTER",
+        "FROM city.csv FILTER CityName = 'New",
+        "FROM city.csv JOIN (FROM country.csv",
+        "FROM city.csv UNION ALL",
+        "EXPLAIN",
+        "WATCH",
+        "DELETE",
+        "FROM city.csv TAKE 1; FROM country.csv ORDERBY",
+    ] {
+        assert!(is_incomplete(incomplete, &datasets), "{}", incomplete);
+    }
+    for complete in [
+        "",
+        "FROM city.csv TAKE 5",
+        "FROM city.csv\nTAKE 5",
+        "help",
+        "\\limit",
+        // Malformed rather than cut short.
+        "FROM city.csv TAKE many",
+        "FROM city.csv SAMPLE ten",
+        "FRM city.csv",
+        // Ended by a semicolon.
+        "FROM city.csv ORDERBY;",
+        "FROM city.csv ORDERBY; FROM city.csv TAKE 1",
+    ] {
+        assert!(!is_incomplete(complete, &datasets), "{}", complete);
+    }
+}
+
+/// Helper function to parse the `input`, which must be malformed, into its [`ParseError`].
+#[cfg(test)]
+fn parse_error(input: &str) -> ParseError {
+    match parse_command(input) {
+        Command::InputError(error) => error,
+        command => panic!("Not malformed: {:?}", command),
+    }
+}
+
+/// Test for NULL input
+#[test]
+fn test_parse_command_no_input() {
+    assert_eq!(parse_command("\n"), Command::NoInput);
+}
+
+/// Test 'EXPLAIN' command as input
+#[test]
+fn test_parse_command_explain() {
+    assert_eq!(
+        parse_command("EXPLAIN FROM city.csv TAKE 5\n"),
+        Command::Explain(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 5,
+        })
+    );
+    assert_eq!(
+        parse_error("EXPLAIN\n").to_string(),
+        "EXPLAIN must be followed by the query to explain."
+    );
+    // Malformed queries are rejected with the same message as when they are run, pointing at
+    // the offending token in the whole line.
+    assert_eq!(
+        parse_error("EXPLAIN FROM city.csv TAKE\n"),
+        ParseError::MissingArgument {
+            keyword: "TAKE".to_string(),
+            expected: "the number of rows to take".to_string(),
+            span: Span { start: 22, end: 26 },
+        }
+    );
+    assert_eq!(
+        parse_error("EXPLAIN FROM city.csv TAKE\n").to_string(),
+        parse_error("FROM city.csv TAKE\n").to_string()
+    );
+}
+
+/// Test 'WATCH' command as input
+#[test]
+fn test_parse_command_watch() {
+    assert_eq!(
+        parse_command("WATCH FROM city.csv COUNTBY CountryCode\n"),
+        Command::Watch(Operator::CountBy {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CountryCode".to_string(),
+            order: CountByOrder::Count,
+            bars: false,
+            having: None,
+        })
+    );
+    assert_eq!(
+        parse_error("WATCH\n").to_string(),
+        "WATCH must be followed by the query to watch."
+    );
+    assert_eq!(
+        parse_error("WATCH FROM city.csv TAKE\n").to_string(),
+        parse_error("FROM city.csv TAKE\n").to_string()
+    );
+}
+
+/// Test 'EXPLAIN ANALYZE' command as input
+#[test]
+fn test_parse_command_explain_analyze() {
+    assert_eq!(
+        parse_command("EXPLAIN ANALYZE FROM city.csv TAKE 5\n"),
+        Command::ExplainAnalyze(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 5,
+        })
+    );
+    assert_eq!(
+        parse_error("EXPLAIN ANALYZE\n").to_string(),
+        "EXPLAIN ANALYZE must be followed by the query to run."
+    );
+    assert_eq!(
+        parse_error("EXPLAIN ANALYZE FROM city.csv TAKE\n").to_string(),
+        parse_error("FROM city.csv TAKE\n").to_string()
+    );
+}
+
+/// Test 'ATTACH' command as input
+#[test]
+fn test_parse_command_attach() {
+    assert_eq!(
+        parse_command("ATTACH mydb.sqlite\n"),
+        Command::Attach("mydb.sqlite".to_string())
+    );
+    assert_eq!(
+        parse_command("ATTACH 'my data/mydb.sqlite'\n"),
+        Command::Attach("my data/mydb.sqlite".to_string())
+    );
+    assert_eq!(
+        parse_error("ATTACH\n").to_string(),
+        "ATTACH must be followed by the path of the SQLite database."
+    );
+
+    // The tables of an attached database can't be written back to it.
+    let path = std::env::temp_dir().join("toy-query-engine-parse-attach.sqlite");
+    crate::data::write_planets_sqlite(&path);
+    let mut datasets = DatasetRegistry::default();
+    datasets.attach(&path.to_string_lossy()).unwrap();
+    assert_eq!(
+        parse_command_with("SAVE planets\n", &datasets),
+        Command::InputError(ParseError::MissingArgument {
+            keyword: "SAVE planets".to_string(),
+            expected: "the path of the CSV file to write".to_string(),
+            span: Span { start: 5, end: 12 },
+        })
+    );
+}
+
+/// Test 'LOAD' command as input
+#[test]
+fn test_parse_command_load() {
+    assert_eq!(
+        parse_command("LOAD cities FROM ./data/city.csv\n"),
+        Command::Load {
+            name: "cities".to_string(),
+            path: "./data/city.csv".to_string(),
+            encoding: None,
+        }
+    );
+    assert_eq!(
+        parse_command("LOAD scores FROM 'my data/scores.csv' ENCODING latin1\n"),
+        Command::Load {
+            name: "scores".to_string(),
+            path: "my data/scores.csv".to_string(),
+            encoding: Some(encoding_rs::WINDOWS_1252),
+        }
+    );
+    let error = parse_error("LOAD scores FROM scores.csv ENCODING klingon\n");
+    assert_eq!(
+        error.to_string(),
+        "Unknown encoding: klingon. Use a label such as utf-8, latin1, or windows-1252."
+    );
+    assert_eq!(error.span(), Span { start: 37, end: 44 });
+    assert_eq!(
+        parse_error("LOAD scores FROM scores.csv ENCODING\n").to_string(),
+        "ENCODING must be followed by the name of an encoding, e.g. latin1."
+    );
+    for malformed in ["LOAD\n", "LOAD cities\n", "LOAD cities ./data/city.csv\n"] {
+        assert_eq!(parse_error(malformed).to_string(), "LOAD must be followed by the name of the dataset, FROM, and the path of the CSV or Parquet file.");
+    }
+    assert_eq!(
+        parse_error("LOAD TAKE FROM ./data/city.csv\n").to_string(),
+        "TAKE is a keyword, so it can't be the name of a dataset."
+    );
+}
+
+/// Test 'SET' command as input
+#[test]
+fn test_parse_command_set() {
+    let big = Operator::Filter {
+        chain: Box::new(Operator::From(Dataset::City)),
+        predicate: parse_predicate("CityPop", ">", "1000000").unwrap(),
+    };
+    for input in [
+        "SET big = FROM city.csv WHERE CityPop > 1000000\n",
+        "SET @big = FROM city.csv FILTER CityPop > 1000000\n",
+    ] {
+        assert_eq!(
+            parse_command(input),
+            Command::Set {
+                name: "big".to_string(),
+                operator: big.clone(),
+            }
+        );
+    }
+    for malformed in [
+        "SET\n",
+        "SET big\n",
+        "SET big =\n",
+        "SET big FROM city.csv\n",
+    ] {
+        assert_eq!(
+            parse_error(malformed).to_string(),
+            "SET must be followed by the name to save the result under, =, and the query."
+        );
+    }
+    assert_eq!(
+        parse_error("SET big-cities = FROM city.csv\n").to_string(),
+        "Invalid variable name: big-cities. It must consist of letters, digits, and underscores."
+    );
+    assert_eq!(
+        parse_error("SET big = FRM city.csv\n").to_string(),
+        "Invalid Input: FRM is not an operator."
+    );
+}
+
+/// Test 'INSERT' command as input
+#[test]
+fn test_parse_command_insert() {
+    assert_eq!(
+        parse_command("INSERT INTO language.csv VALUES (ATL, 'Old Atlantean'),(ATL,Atlantean)\n"),
+        Command::Insert {
+            dataset: Dataset::Language,
+            rows: vec![
+                vec![
+                    Cell::String("ATL".to_string()),
+                    Cell::String("Old Atlantean".to_string())
+                ],
+                vec![
+                    Cell::String("ATL".to_string()),
+                    Cell::String("Atlantean".to_string())
+                ],
+            ],
+        }
+    );
+    assert_eq!(
+        parse_command("INSERT INTO @big VALUES (1, NULL)\n"),
+        Command::Insert {
+            dataset: Dataset::Variable("big".to_string()),
+            rows: vec![vec![Cell::Int64(1), Cell::Null]],
+        }
+    );
+    for malformed in [
+        "INSERT\n",
+        "INSERT INTO city.csv\n",
+        "INSERT INTO city.csv VALUES\n",
+        "INSERT city.csv VALUES (1)\n",
+    ] {
+        assert_eq!(
+            parse_error(malformed).to_string(),
+            "INSERT must be followed by INTO, the dataset, VALUES, and the rows of values, e.g. INSERT INTO language.csv VALUES (ATL, Atlantean)."
+        );
+    }
+    assert_eq!(
+        parse_error("INSERT INTO cities VALUES (1)\n").to_string(),
+        "Invalid dataset passed to INSERT: cities"
+    );
+    let error = parse_error("INSERT INTO city.csv VALUES (1, 2) (3)\n");
+    assert_eq!(
+        error.to_string(),
+        "Expected ',' between the rows in: (1, 2) (3)"
+    );
+    assert_eq!(error.span(), Span { start: 28, end: 38 });
+}
+
+/// Test 'DESCRIBE' command as input
+#[test]
+fn test_parse_command_describe() {
+    assert_eq!(
+        parse_command("DESCRIBE city.csv\n"),
+        Command::Describe(Dataset::City)
+    );
+    assert_eq!(
+        parse_command("DESCRIBE @big\n"),
+        Command::Describe(Dataset::Variable("big".to_string()))
+    );
+    assert_eq!(
+        parse_error("DESCRIBE\n").to_string(),
+        "DESCRIBE must be followed by the dataset to describe."
+    );
+    assert_eq!(
+        parse_error("DESCRIBE cities\n").to_string(),
+        "Invalid dataset passed to DESCRIBE: cities"
+    );
+    assert_eq!(
+        parse_error("DESCRIBE city.csv TAKE 5\n"),
+        ParseError::UnexpectedToken {
+            token: "TAKE".to_string(),
+            span: Span { start: 18, end: 22 },
+        }
+    );
+}
+
+/// Test 'SHOW TABLES' and 'SHOW COLUMNS' commands as input
+#[test]
+fn test_parse_command_show() {
+    assert_eq!(parse_command("SHOW TABLES\n"), Command::ShowTables);
+    assert_eq!(
+        parse_command("SHOW COLUMNS city\n"),
+        Command::ShowColumns(Dataset::City)
+    );
+    assert_eq!(
+        parse_command("SHOW COLUMNS @big\n"),
+        Command::ShowColumns(Dataset::Variable("big".to_string()))
+    );
+    for input in ["SHOW\n", "SHOW COLUMNS\n", "SHOW DATASETS\n"] {
+        assert_eq!(
+            parse_error(input).to_string(),
+            "SHOW must be followed by TABLES, or COLUMNS and the dataset to list the columns of."
+        );
+    }
+    assert_eq!(
+        parse_error("SHOW COLUMNS cities\n").to_string(),
+        "Invalid dataset passed to SHOW COLUMNS: cities"
+    );
+    assert_eq!(
+        parse_error("SHOW TABLES city\n"),
+        ParseError::UnexpectedToken {
+            token: "city".to_string(),
+            span: Span { start: 12, end: 16 },
+        }
+    );
+}
+
+/// Test 'SAVE' command as input
+#[test]
+fn test_parse_command_save() {
+    assert_eq!(
+        parse_command("SAVE language.csv\n"),
+        Command::Save {
+            dataset: Dataset::Language,
+            path: "data/language.csv".to_string(),
+        }
+    );
+    assert_eq!(
+        parse_command("SAVE @big 'big cities.csv'\n"),
+        Command::Save {
+            dataset: Dataset::Variable("big".to_string()),
+            path: "big cities.csv".to_string(),
+        }
+    );
+    assert_eq!(
+        parse_error("SAVE\n").to_string(),
+        "SAVE must be followed by the dataset to write, and optionally the path of the CSV file to write it to."
+    );
+    assert_eq!(
+        parse_error("SAVE @big\n").to_string(),
+        "SAVE @big must be followed by the path of the CSV file to write."
+    );
+    assert_eq!(
+        parse_error("SAVE cities out.csv\n").to_string(),
+        "Invalid dataset passed to SAVE: cities"
+    );
+}
+
+/// Test 'DELETE' command as input
+#[test]
+fn test_parse_command_delete() {
+    for input in [
+        "DELETE FROM city.csv WHERE CityPop < 1000\n",
+        "DELETE FROM city.csv FILTER CityPop < 1000\n",
+    ] {
+        assert_eq!(
+            parse_command(input),
+            Command::Delete {
+                dataset: Dataset::City,
+                predicate: parse_predicate("CityPop", "<", "1000").unwrap(),
+            }
+        );
+    }
+    for malformed in [
+        "DELETE\n",
+        "DELETE city.csv WHERE CityPop < 1000\n",
+        "DELETE FROM city.csv\n",
+        "DELETE FROM city.csv WHERE CityPop < 1000 TAKE 5\n",
+    ] {
+        assert_eq!(
+            parse_error(malformed).to_string(),
+            "DELETE must be followed by FROM, the dataset, WHERE, and a condition, e.g. DELETE FROM city.csv WHERE CityPop < 1000."
+        );
+    }
+    assert_eq!(
+        parse_error("DELETE FROM city.csv WHERE CityPop <\n").to_string(),
+        "WHERE must be followed by a column name, a comparison, and a value, e.g. CityPop > 1000000."
+    );
+}
+
+/// Test FROM and JOIN with results saved by SET
+#[test]
+fn test_parse_command_variables() {
+    assert_eq!(
+        parse_command("FROM @big JOIN @countries CountryCode\n"),
+        Command::Operator(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::Variable("big".to_string()))),
+            right: Box::new(Operator::From(Dataset::Variable("countries".to_string()))),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        })
+    );
+    assert_eq!(
+        parse_error("FROM @\n").to_string(),
+        "Invalid dataset passed to FROM: @"
+    );
+}
+
+/// Test FROM and JOIN with registered datasets
+#[test]
+fn test_parse_command_with_registered_datasets() {
+    let mut datasets = DatasetRegistry::default();
+    let cities = datasets.register("cities", "data/city.csv").unwrap();
+    assert_eq!(
+        parse_command_with("FROM country JOIN cities CountryCode\n", &datasets),
+        Command::Operator(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            right: Box::new(Operator::From(cities.clone())),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        })
+    );
+    assert_eq!(
+        parse_command_with("EXPLAIN FROM cities\n", &datasets),
+        Command::Explain(Operator::From(cities))
+    );
+    // Sub-queries defined by WITH take precedence.
+    assert_eq!(
+        parse_command_with("WITH cities AS (FROM city) FROM cities\n", &datasets),
+        Command::Operator(Operator::With {
+            ctes: vec![("cities".to_string(), Operator::From(Dataset::City))],
+            query: Box::new(Operator::From(Dataset::Cte("cities".to_string()))),
+        })
+    );
+    assert_eq!(
+        parse_error("FROM cities\n").to_string(),
+        "Invalid dataset passed to FROM: cities"
+    );
+}
+
 /// Test 'exit' command as input
 #[test]
-fn test_parse_command_exit() {
-    assert_eq!(parse_command("exit\n"), Command::Exit);
+fn test_parse_command_exit() {
+    assert_eq!(parse_command("exit\n"), Command::Exit);
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed1() {
+    assert_eq!(
+        parse_error("FRM language.csv\n"),
+        ParseError::UnknownKeyword {
+            keyword: "FRM".to_string(),
+            span: Span { start: 0, end: 3 },
+        }
+    );
+    assert_eq!(
+        parse_error("FRM language.csv\n").to_string(),
+        "Invalid Input: FRM is not an operator."
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed2() {
+    assert_eq!(
+        parse_error("TAKE language.csv\n").to_string(),
+        "TAKE can't be the first command; It must be preceded by at least a FROM."
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed3() {
+    assert_eq!(
+        parse_error("language.csv\n").to_string(),
+        "Invalid Input: language.csv is not an operator."
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed4() {
+    assert_eq!(
+        parse_error("help FROM language.csv\n").to_string(),
+        "Invalid Input: unexpected language.csv."
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed5() {
+    assert_eq!(
+        parse_error("FROM ORDERBY CityPop TAKE 7 SELECT CityName,CityPop\n").to_string(),
+        "Invalid dataset passed to FROM: ORDERBY"
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed6() {
+    assert_eq!(
+        parse_error("FROM city.csv ORDERBY TAKE 7 SELECT CityName,CityPop\n").to_string(),
+        "Invalid Input: 7 is not an operator."
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed7() {
+    assert_eq!(
+        parse_error("FROM city.csv TAKE many\n"),
+        ParseError::InvalidNumber {
+            keyword: "TAKE".to_string(),
+            value: "many".to_string(),
+            error: "many".parse::<usize>().unwrap_err(),
+            span: Span { start: 19, end: 23 },
+        }
+    );
+    assert_eq!(parse_error("FROM city.csv ORDERBY CityPop TAKE SELECT CityName,CityPop\n").to_string(), "Invalid value passed to TAKE operator: SELECT. Must be a positive integer.\n Full error message: invalid digit found in string");
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed8() {
+    assert_eq!(
+        parse_error("FROM city.csv ORDERBY CityPop TAKE 7 SELECT\n").to_string(),
+        "SELECT must be followed by the columns to select."
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed9() {
+    assert_eq!(parse_error("FROM city.csv TAKE -2\n").to_string(), "Invalid value passed to TAKE operator: -2. Must be a positive integer.\n Full error message: invalid digit found in string");
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed10() {
+    assert_eq!(parse_error("FROM city.csv TAKE CityID\n").to_string(), "Invalid value passed to TAKE operator: CityID. Must be a positive integer.\n Full error message: invalid digit found in string");
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed11() {
+    assert_eq!(
+        parse_error("FROM city.cv\n").to_string(),
+        "Invalid dataset passed to FROM: city.cv"
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed12() {
+    assert_eq!(
+        parse_error("FROM cit.csv\n").to_string(),
+        "Invalid dataset passed to FROM: cit.csv"
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed13() {
+    assert_eq!(
+        parse_error("FROM lungage.csv\n").to_string(),
+        "Invalid dataset passed to FROM: lungage.csv"
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed14() {
+    assert_eq!(
+        parse_error("FROM contry.csv\n").to_string(),
+        "Invalid dataset passed to FROM: contry.csv"
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed15() {
+    assert_eq!(
+        parse_error("FROM city.csv JOIN country.csv\n").to_string(),
+        "JOIN must be followed by the dataset and the name of the column to join on."
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed16() {
+    assert_eq!(
+        parse_error("FROM city.csv JOIN CountryCode\n"),
+        ParseError::InvalidDataset {
+            keyword: "JOIN".to_string(),
+            name: "CountryCode".to_string(),
+            span: Span { start: 19, end: 30 },
+        }
+    );
+    assert_eq!(
+        parse_error("FROM city.csv JOIN CountryCode\n").to_string(),
+        "Invalid dataset passed to JOIN: CountryCode"
+    );
+}
+
+/// Test malformed command as input
+#[test]
+fn test_parse_command_malformed17() {
+    assert_eq!(
+        parse_error("FROM city.csv JOIN country.csv CountryCode JOIN lnguage.csv CountryCode\n")
+            .to_string(),
+        "Invalid dataset passed to JOIN: lnguage.csv"
+    );
+}
+
+/// Test a setting command as input
+#[test]
+fn test_parse_command_setting() {
+    assert_eq!(
+        parse_command("\\limit 50\n"),
+        Command::Setting(Setting::DefaultLimit(Some(50)))
+    );
+    assert_eq!(
+        parse_command("\\limit off\n"),
+        Command::Setting(Setting::DefaultLimit(None))
+    );
+}
+
+/// Test malformed setting commands as input
+#[test]
+fn test_parse_command_setting_malformed() {
+    assert_eq!(
+        parse_error("\\\n").to_string(),
+        "\\ must be followed by the name of a setting."
+    );
+    assert_eq!(
+        parse_error("\\limit\n").to_string(),
+        "\\limit must be followed by the number of rows or 'off'."
+    );
+    // The span covers the setting and its arguments.
+    assert_eq!(
+        parse_error("\\format  html\n").span(),
+        Span { start: 1, end: 13 }
+    );
+}
+
+/// Test the spans of the errors, which point at the offending token of the line.
+#[test]
+fn test_parse_error_span() {
+    let span = |input: &str| {
+        let span = parse_error(&format!("{}\n", input)).span();
+        input[span.start..span.end].to_string()
+    };
+    assert_eq!(span("FROM city.csv  ORDERBY"), "ORDERBY");
+    assert_eq!(span("FROM city.csv TAKE 5 FROM country.csv"), "FROM");
+    assert_eq!(span("TAKE 5"), "TAKE");
+    assert_eq!(span("FROM city.csv INTO out.csv TAKE 5"), "TAKE");
+    assert_eq!(span("FROM city.csv SELECT CityName AS"), "CityName AS");
+    assert_eq!(
+        span("FROM city.csv FILTER CityPop IS NOT 5"),
+        "CityPop IS NOT 5"
+    );
+    assert_eq!(span("FROM city.csv GROUPBY CountryCode SUM(x"), "SUM(x");
+    assert_eq!(
+        span("FROM city.csv COUNTBY CountryCode ORDER size"),
+        "ORDER"
+    );
+    assert_eq!(span("FROM city.csv JOIN country.csv ON Capital"), "ON");
+    assert_eq!(span("FROM city.csv JOIN () CountryCode"), "()");
+    assert_eq!(span("SET big-cities = FROM city.csv"), "big-cities");
+    assert_eq!(span("LOAD TAKE FROM data/city.csv"), "TAKE");
+    assert_eq!(span("help SORT"), "SORT");
+    assert_eq!(
+        span("WITH a AS (FROM city.csv), a AS (FROM country.csv) FROM a"),
+        "a"
+    );
+    // The spans of errors within a sub-query are relative to the whole line too.
+    assert_eq!(
+        parse_error("FROM city.csv JOIN (FROM country.csv TAKE x) CountryCode\n").span(),
+        Span { start: 42, end: 43 }
+    );
+    assert_eq!(
+        parse_error("FROM city.csv ORDERBY CityPop UP\n").to_string(),
+        "Invalid Input: UP is not an operator."
+    );
+}
+
+/// Test 'help'command as input
+#[test]
+fn test_parse_command_help() {
+    assert_eq!(parse_command("help\n"), Command::Help(None));
+}
+
+/// Test 'help <OPERATOR>' command as input
+#[test]
+fn test_parse_command_help_operator() {
+    assert_eq!(
+        parse_command("help ORDERBY\n"),
+        Command::Help(Some("ORDERBY".to_string()))
+    );
+    assert_eq!(
+        parse_command("help countby\n"),
+        Command::Help(Some("COUNTBY".to_string()))
+    );
+    assert_eq!(
+        parse_error("help SORT\n").to_string(),
+        "No help available for: SORT"
+    );
+}
+
+/// Test well-formed input: `FROM language.csv`.
+#[test]
+fn test_parse_command_from_language() {
+    assert_eq!(
+        parse_command("FROM language.csv\n"),
+        Command::Operator(Operator::From(Dataset::Language))
+    );
+}
+
+/// Test well-formed input: `FROM city.csv`.
+#[test]
+fn test_parse_command_from_city() {
+    assert_eq!(
+        parse_command("FROM city.csv\n"),
+        Command::Operator(Operator::From(Dataset::City))
+    );
+}
+
+/// Test well-formed input: `FROM country.csv`.
+#[test]
+fn test_parse_command_from_country() {
+    assert_eq!(
+        parse_command("FROM country.csv\n"),
+        Command::Operator(Operator::From(Dataset::Country))
+    );
+}
+/// Test well-formed input using the short dataset names: "FROM city JOIN country CountryCode\n"
+#[test]
+fn test_parse_command_short_dataset_names() {
+    assert_eq!(
+        parse_command("FROM city JOIN country CountryCode\n"),
+        Command::Operator(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::City)),
+            right: Box::new(Operator::From(Dataset::Country)),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        }),
+    );
+}
+
+/// Test well-formed input: "FROM city.csv ORDERBY CityPop TAKE 7 SELECT CityName,CityPop\n"
+#[test]
+fn test_parse_command_complex1() {
+    assert_eq!(
+        parse_command("FROM city.csv ORDERBY CityPop TAKE 7 SELECT CityName,CityPop\n"),
+        Command::Operator(Operator::Select {
+            chain: Box::new(Operator::Take {
+                chain: Box::new(Operator::OrderBy {
+                    chain: Box::new(Operator::From(Dataset::City)),
+                    column: "CityPop".to_string(),
+                    order: SortOrder::Descending,
+                    nulls: NullsOrder::Last,
+                }),
+                count: 7
+            }),
+            columns: vec![
+                Expression::Column("CityName".to_string()).into(),
+                Expression::Column("CityPop".to_string()).into()
+            ]
+        }),
+    );
+}
+/// Test well-formed input: "FROM city.csv SELECT CityName\n"
+#[test]
+fn test_parse_command_complex2() {
+    assert_eq!(
+        parse_command("FROM city.csv SELECT CityName\n"),
+        Command::Operator(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![Expression::Column("CityName".to_string()).into()]
+        })
+    );
+}
+
+/// Test well-formed input: "FROM country.csv SELECT CountryCode,Continent,CountryPop\n"
+#[test]
+fn test_parse_command_complex3() {
+    assert_eq!(
+        parse_command("FROM country.csv SELECT CountryCode,Continent,CountryPop\n"),
+        Command::Operator(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            columns: vec![
+                Expression::Column("CountryCode".to_string()).into(),
+                Expression::Column("Continent".to_string()).into(),
+                Expression::Column("CountryPop".to_string()).into()
+            ]
+        }),
+    );
+}
+/// Test well-formed input: "FROM city.csv SELECT slug(CityName),CityPop\n"
+#[test]
+fn test_parse_command_select_function() {
+    assert_eq!(
+        parse_command("FROM city.csv SELECT slug(CityName),CityPop\n"),
+        Command::Operator(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![
+                Expression::Call {
+                    function: "slug".to_string(),
+                    arguments: vec![Expression::Column("CityName".to_string())]
+                }
+                .into(),
+                Expression::Column("CityPop".to_string()).into()
+            ]
+        }),
+    );
 }
 
-/// Test malformed command as input
+/// Test malformed input: "FROM city.csv SELECT slug(CityName\n"
 #[test]
-fn test_parse_command_malformed1() {
+fn test_parse_command_select_malformed_function() {
     assert_eq!(
-        parse_command("FRM language.csv\n"),
-        Command::InputError("Invalid Input: FRM language.csv".to_string())
+        parse_error("FROM city.csv SELECT slug(CityName\n"),
+        ParseError::InvalidExpression {
+            message: "Expected ',' or ')' after the arguments to slug in: slug(CityName"
+                .to_string(),
+            span: Span { start: 21, end: 34 },
+        }
     );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM city.csv SELECT CityName AS Name,CityPop AS Pop ORDERBY Pop\n"
 #[test]
-fn test_parse_command_malformed2() {
+fn test_parse_command_select_alias() {
     assert_eq!(
-        parse_command("TAKE language.csv\n"),
-        Command::InputError(
-            "TAKE can't be the first command; It must be preceded by at least a FROM.".to_string()
+        parse_command("FROM city.csv SELECT CityName AS Name,CityPop AS Pop ORDERBY Pop\n"),
+        Command::Operator(Operator::OrderBy {
+            chain: Box::new(Operator::Select {
+                chain: Box::new(Operator::From(Dataset::City)),
+                columns: parse_select_list("CityName AS Name,CityPop AS Pop").unwrap(),
+            }),
+            column: "Pop".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::Last,
+        }),
+    );
+    assert_eq!(
+        parse_error("FROM city.csv SELECT CityName AS\n").to_string(),
+        "Unexpected character ' ' in expression: CityName AS"
+    );
+}
+
+/// Test well-formed input: "FROM country.csv SELECT CountryName,CountryPop - Capital AS Diff TAKE 1\n"
+#[test]
+fn test_parse_command_select_arithmetic() {
+    let select = |columns| Operator::Take {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            columns: parse_select_list(columns).unwrap(),
+        }),
+        count: 1,
+    };
+    assert_eq!(
+        parse_command("FROM country.csv SELECT CountryName,CountryPop - Capital AS Diff TAKE 1\n"),
+        Command::Operator(select("CountryName,CountryPop-Capital AS Diff"))
+    );
+    assert_eq!(
+        parse_command("FROM country.csv SELECT CountryPop/ 1000,Capital *2 TAKE 1\n"),
+        Command::Operator(select("CountryPop/1000,Capital*2"))
+    );
+    assert_eq!(
+        parse_error("FROM country.csv SELECT CountryPop -\n").to_string(),
+        "Incomplete expression: CountryPop -"
+    );
+}
+
+/// Test well-formed input: "FROM city.csv EXTEND PopDensity = CityPop / 1000,Name=UPPER(CityName) TAKE 1\n"
+#[test]
+fn test_parse_command_extend() {
+    assert_eq!(
+        parse_command(
+            "FROM city.csv EXTEND PopDensity = CityPop / 1000,Name=UPPER(CityName) TAKE 1\n"
+        ),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Extend {
+                chain: Box::new(Operator::From(Dataset::City)),
+                columns: parse_extend_list("PopDensity=CityPop/1000,Name=UPPER(CityName)").unwrap(),
+            }),
+            count: 1,
+        }),
+    );
+    assert_eq!(
+        parse_error("FROM city.csv EXTEND\n").to_string(),
+        "EXTEND must be followed by the columns to add, e.g. EXTEND PopDensity = CityPop / 1000."
+    );
+    assert_eq!(
+        parse_error("FROM city.csv EXTEND PopDensity CityPop\n").to_string(),
+        "Expected '=' after PopDensity in: PopDensity"
+    );
+    assert_eq!(
+        parse_error("EXTEND PopDensity = 1\n").to_string(),
+        "EXTEND can't be the first command; It must be preceded by at least a FROM."
+    );
+}
+
+/// Test well-formed input: "FROM city.csv SKIP 20 TAKE 10\n"
+#[test]
+fn test_parse_command_skip() {
+    assert_eq!(
+        parse_command("FROM city.csv SKIP 20 TAKE 10\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Skip {
+                chain: Box::new(Operator::From(Dataset::City)),
+                count: 20,
+            }),
+            count: 10,
+        }),
+    );
+    assert_eq!(
+        parse_error("FROM city.csv SKIP\n").to_string(),
+        "SKIP must be followed by the number of rows to skip."
+    );
+    assert_eq!(
+        parse_error("SKIP 2\n").to_string(),
+        "SKIP can't be the first command; It must be preceded by at least a FROM."
+    );
+    assert_eq!(parse_error("FROM city.csv SKIP -2\n").to_string(), "Invalid value passed to SKIP operator: -2. Must be a positive integer.\n Full error message: invalid digit found in string");
+}
+
+/// Test well-formed input: "FROM city.csv ORDERBY CityPop TAIL 5\n"
+#[test]
+fn test_parse_command_tail() {
+    assert_eq!(
+        parse_command("FROM city.csv ORDERBY CityPop TAIL 5\n"),
+        Command::Operator(Operator::Tail {
+            chain: Box::new(Operator::OrderBy {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
+            }),
+            count: 5,
+        }),
+    );
+    assert_eq!(
+        parse_error("FROM city.csv TAIL\n").to_string(),
+        "TAIL must be followed by the number of rows to keep from the end."
+    );
+    assert_eq!(
+        parse_error("TAIL 2\n").to_string(),
+        "TAIL can't be the first command; It must be preceded by at least a FROM."
+    );
+    assert_eq!(parse_error("FROM city.csv TAIL -2\n").to_string(), "Invalid value passed to TAIL operator: -2. Must be a positive integer.\n Full error message: invalid digit found in string");
+}
+
+/// Test well-formed input: "FROM city.csv SAMPLE 10 SEED 42\n" and friends
+#[test]
+fn test_parse_command_sample() {
+    let sample = |size, seed| {
+        Command::Operator(Operator::Sample {
+            chain: Box::new(Operator::From(Dataset::City)),
+            size,
+            seed,
+        })
+    };
+    assert_eq!(
+        parse_command("FROM city.csv SAMPLE 10\n"),
+        sample(SampleSize::Rows(10), None)
+    );
+    assert_eq!(
+        parse_command("FROM city.csv SAMPLE 10 SEED 42\n"),
+        sample(SampleSize::Rows(10), Some(42))
+    );
+    assert_eq!(
+        parse_command("FROM city.csv SAMPLE 2.5%\n"),
+        sample(SampleSize::Percent(2.5), None)
+    );
+    assert_eq!(
+        parse_command("FROM city.csv SAMPLE 5% SEED 7 TAKE 3\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Sample {
+                chain: Box::new(Operator::From(Dataset::City)),
+                size: SampleSize::Percent(5.0),
+                seed: Some(7),
+            }),
+            count: 3,
+        })
+    );
+}
+
+/// Test malformed input: "FROM city.csv SAMPLE\n" and friends
+#[test]
+fn test_parse_command_sample_malformed() {
+    for malformed in ["", "ten", "-1", "150%"] {
+        assert_eq!(
+            parse_error(&format!("FROM city.csv SAMPLE {}\n", malformed)).to_string(),
+            "SAMPLE must be followed by the number of rows to sample, or a percentage of them, e.g. 10%."
+        );
+    }
+    assert_eq!(
+        parse_error("SAMPLE 2\n").to_string(),
+        "SAMPLE can't be the first command; It must be preceded by at least a FROM."
+    );
+    assert_eq!(
+        parse_error("FROM city.csv SAMPLE 2 SEED\n").to_string(),
+        "SAMPLE ... SEED must be followed by the seed of the random number generator, e.g. 42."
+    );
+    assert_eq!(parse_error("FROM city.csv SAMPLE 2 SEED x\n").to_string(), "Invalid value passed to SAMPLE ... SEED operator: x. Must be a positive integer.\n Full error message: invalid digit found in string");
+}
+
+/// Test well-formed input: "FROM city.csv ORDERBY CityPop ASC TAKE 3\n"
+#[test]
+fn test_parse_command_orderby_order() {
+    assert_eq!(
+        parse_command("FROM city.csv ORDERBY CityPop ASC TAKE 3\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::OrderBy {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                order: SortOrder::Ascending,
+                nulls: NullsOrder::Last,
+            }),
+            count: 3
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM city.csv ORDERBY CityPop DESC\n"),
+        parse_command("FROM city.csv ORDERBY CityPop\n"),
+    );
+    assert_eq!(
+        parse_error("FROM city.csv ORDERBY CityPop UP\n").to_string(),
+        "Invalid Input: UP is not an operator."
+    );
+    assert_eq!(
+        parse_command("FROM country.csv ORDERBY Capital NULLS FIRST\n"),
+        Command::Operator(Operator::OrderBy {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            column: "Capital".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::First,
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM country.csv ORDERBY Capital ASC NULLS LAST\n"),
+        parse_command("FROM country.csv ORDERBY Capital ASC\n"),
+    );
+    assert_eq!(
+        parse_error("FROM country.csv ORDERBY Capital ASC NULLS\n").to_string(),
+        "ORDERBY ... NULLS must be followed by FIRST or LAST."
+    );
+}
+
+/// Test well-formed input: "FROM city.csv SUM CityPop\n"
+#[test]
+fn test_parse_command_aggregate() {
+    assert_eq!(
+        parse_command("FROM city.csv SUM CityPop\n"),
+        Command::Operator(Operator::Aggregate {
+            chain: Box::new(Operator::From(Dataset::City)),
+            function: AggregateFunction::Sum,
+            column: "CityPop".to_string(),
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM city.csv TAKE 5 AVG CityPop\n"),
+        Command::Operator(Operator::Aggregate {
+            chain: Box::new(Operator::Take {
+                chain: Box::new(Operator::From(Dataset::City)),
+                count: 5,
+            }),
+            function: AggregateFunction::Avg,
+            column: "CityPop".to_string(),
+        }),
+    );
+}
+
+/// Test 'COUNT' without a column, which counts the rows of its input.
+#[test]
+fn test_parse_command_count_rows() {
+    let count_rows = |chain| {
+        Command::Operator(Operator::Aggregate {
+            chain: Box::new(chain),
+            function: AggregateFunction::Count,
+            column: "*".to_string(),
+        })
+    };
+    assert_eq!(
+        parse_command("FROM city.csv COUNT\n"),
+        count_rows(Operator::From(Dataset::City))
+    );
+    assert_eq!(
+        parse_command("FROM city.csv COUNT *\n"),
+        count_rows(Operator::From(Dataset::City))
+    );
+    assert_eq!(
+        parse_command("FROM city.csv COUNT INTO count.csv\n"),
+        Command::Operator(Operator::Into {
+            chain: Box::new(Operator::Aggregate {
+                chain: Box::new(Operator::From(Dataset::City)),
+                function: AggregateFunction::Count,
+                column: "*".to_string(),
+            }),
+            path: "count.csv".to_string(),
+            format: ExportFormat::Csv,
+        })
+    );
+    assert_eq!(
+        parse_error("COUNT\n").to_string(),
+        "COUNT can't be the first command; It must be preceded by at least a FROM."
+    );
+}
+
+/// Test malformed input: "FROM city.csv MAX\n" and "MIN CityPop\n"
+#[test]
+fn test_parse_command_aggregate_malformed() {
+    assert_eq!(
+        parse_error("FROM city.csv MAX\n").to_string(),
+        "MAX must be followed by the name of the column to aggregate."
+    );
+    assert_eq!(
+        parse_error("MIN CityPop\n").to_string(),
+        "MIN can't be the first command; It must be preceded by at least a FROM."
+    );
+}
+
+/// Test well-formed input: "FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID)\n"
+#[test]
+fn test_parse_command_groupby() {
+    assert_eq!(
+        parse_command("FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID)\n"),
+        Command::Operator(Operator::GroupBy {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CountryCode".to_string(),
+            aggregates: parse_aggregate_list("SUM(CityPop),COUNT(CityID)").unwrap(),
+            having: None,
+        }),
+    );
+}
+
+/// Test malformed input: "FROM city.csv GROUPBY CountryCode\n" and friends
+#[test]
+fn test_parse_command_groupby_malformed() {
+    assert_eq!(parse_error("FROM city.csv GROUPBY CountryCode\n").to_string(), "GROUPBY must be followed by the name of the column to group by and the aggregates to compute, e.g. SUM(CityPop).");
+    assert_eq!(parse_error("FROM city.csv GROUPBY CountryCode TOTAL(CityPop)\n").to_string(), "Invalid aggregate: TOTAL(CityPop). Must be one of SUM, AVG, MIN, MAX, or COUNT applied to a column, e.g. SUM(CityPop).");
+    assert_eq!(
+        parse_error("GROUPBY CountryCode SUM(CityPop)\n").to_string(),
+        "GROUPBY can't be the first command; It must be preceded by at least a FROM."
+    );
+}
+
+/// Test well-formed input: "FROM country.csv LEFTJOIN city.csv CountryCode\n" and the other kinds
+/// of join.
+#[test]
+fn test_parse_command_join_kinds() {
+    for (keyword, kind) in [
+        ("LEFTJOIN", JoinKind::Left),
+        ("RIGHTJOIN", JoinKind::Right),
+        ("OUTERJOIN", JoinKind::Full),
+    ] {
+        assert_eq!(
+            parse_command(&format!(
+                "FROM country.csv {} city.csv CountryCode\n",
+                keyword
+            )),
+            Command::Operator(Operator::Join {
+                chain: Box::new(Operator::From(Dataset::Country)),
+                right: Box::new(Operator::From(Dataset::City)),
+                column: "CountryCode".to_string(),
+                right_column: None,
+                kind,
+                strategy: None,
+            }),
+        );
+    }
+    assert_eq!(
+        parse_error("FROM country.csv LEFTJOIN city.csv\n").to_string(),
+        "LEFTJOIN must be followed by the dataset and the name of the column to join on."
+    );
+}
+
+/// Test join strategy hints: "FROM city.csv JOIN country.csv CountryCode USING MERGE\n"
+#[test]
+fn test_parse_command_join_using() {
+    for (hint, strategy) in [("MERGE", JoinStrategy::Merge), ("HASH", JoinStrategy::Hash)] {
+        let command = parse_command(&format!(
+            "FROM country.csv JOIN city.csv ON Capital=CityID USING {} TAKE 1\n",
+            hint
+        ));
+        assert_eq!(
+            command,
+            Command::Operator(Operator::Take {
+                chain: Box::new(Operator::Join {
+                    chain: Box::new(Operator::From(Dataset::Country)),
+                    right: Box::new(Operator::From(Dataset::City)),
+                    column: "Capital".to_string(),
+                    right_column: Some("CityID".to_string()),
+                    kind: JoinKind::Inner,
+                    strategy: Some(strategy),
+                }),
+                count: 1,
+            })
+        );
+    }
+    for malformed in [
+        "FROM city.csv JOIN country.csv CountryCode USING\n",
+        "FROM city.csv JOIN country.csv CountryCode USING NESTED\n",
+    ] {
+        assert_eq!(
+            parse_error(malformed).to_string(),
+            "JOIN ... USING must be followed by the join strategy, HASH or MERGE."
+        );
+    }
+}
+
+/// Test well-formed input: "FROM country.csv JOIN city.csv ON Capital=CityID\n"
+#[test]
+fn test_parse_command_join_subquery() {
+    assert_eq!(
+        parse_command(
+            "FROM city.csv JOIN (FROM country.csv SELECT CountryCode,Continent) CountryCode\n"
+        ),
+        Command::Operator(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::City)),
+            right: Box::new(Operator::Select {
+                chain: Box::new(Operator::From(Dataset::Country)),
+                columns: vec![
+                    Expression::Column("CountryCode".to_string()).into(),
+                    Expression::Column("Continent".to_string()).into()
+                ],
+            }),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        }),
+    );
+    // Parentheses inside the sub-query don't end it.
+    assert_eq!(
+        parse_command(
+            "FROM city.csv LEFTJOIN ( FROM country.csv SELECT LENGTH(CountryName) ) ON CityID=CountryCode\n"
+        ),
+        parse_command(
+            "FROM city.csv LEFTJOIN (FROM country.csv SELECT LENGTH(CountryName)) ON CityID=CountryCode\n"
         )
     );
+    assert_eq!(
+        parse_error("FROM city.csv JOIN (FROM country.csv CountryCode\n"),
+        ParseError::MissingParenthesis {
+            after: "the sub-query to JOIN on".to_string(),
+            span: Span { start: 19, end: 24 },
+        }
+    );
+    assert_eq!(
+        parse_error("FROM city.csv OUTERJOIN () CountryCode\n").to_string(),
+        "The sub-query to OUTERJOIN on is empty."
+    );
+    assert_eq!(
+        parse_error("FROM city.csv JOIN (TAKE 5) CountryCode\n").to_string(),
+        "TAKE can't be the first command; It must be preceded by at least a FROM."
+    );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM country.csv SELECT CountryCode UNION (FROM language.csv SELECT
+/// CountryCode)\n", and its variants.
 #[test]
-fn test_parse_command_malformed3() {
+fn test_parse_command_union() {
+    let codes = |dataset: Dataset| Operator::Select {
+        chain: Box::new(Operator::From(dataset)),
+        columns: vec![Expression::Column("CountryCode".to_string()).into()],
+    };
+    let union = |all: bool| Operator::Union {
+        chain: Box::new(codes(Dataset::Country)),
+        right: Box::new(codes(Dataset::Language)),
+        all,
+    };
+    assert_eq!(
+        parse_command(
+            "FROM country.csv SELECT CountryCode UNION (FROM language.csv SELECT CountryCode)\n"
+        ),
+        Command::Operator(union(false))
+    );
+    // The operators after a parenthesized query apply to the combined rows.
+    let take = Operator::Take {
+        chain: Box::new(union(true)),
+        count: 5,
+    };
+    assert_eq!(
+        parse_command(
+            "FROM country.csv SELECT CountryCode UNION ALL (FROM language.csv SELECT CountryCode) TAKE 5\n"
+        ),
+        Command::Operator(take.clone())
+    );
+    // The label reads back as the same operator.
+    assert_eq!(
+        parse_command(&format!("{}\n", take)),
+        Command::Operator(take)
+    );
+    assert_eq!(
+        parse_command(
+            "FROM country.csv SELECT CountryCode UNION (FROM language.csv SELECT CountryCode) INTO codes.csv\n"
+        ),
+        Command::Operator(Operator::Into {
+            chain: Box::new(union(false)),
+            path: "codes.csv".to_string(),
+            format: ExportFormat::Csv,
+        })
+    );
+    // Each UNION applies to the rows combined by the ones before it.
+    assert_eq!(
+        parse_command("FROM city.csv UNION (FROM city.csv) UNION ALL (FROM city.csv)\n"),
+        Command::Operator(Operator::Union {
+            chain: Box::new(Operator::Union {
+                chain: Box::new(Operator::From(Dataset::City)),
+                right: Box::new(Operator::From(Dataset::City)),
+                all: false,
+            }),
+            right: Box::new(Operator::From(Dataset::City)),
+            all: true,
+        })
+    );
+}
+
+/// Test malformed UNIONs as input
+#[test]
+fn test_parse_command_union_malformed() {
+    assert_eq!(
+        parse_error("FROM city.csv UNION\n").to_string(),
+        "UNION must be followed by the query to combine with, in parentheses, e.g. (FROM language.csv SELECT CountryCode)."
+    );
+    // The query must be parenthesized, so that the operators after it clearly apply to the
+    // combined rows.
+    assert_eq!(
+        parse_error("FROM city.csv UNION FROM country.csv TAKE 2\n"),
+        ParseError::MissingArgument {
+            keyword: "UNION".to_string(),
+            expected: "the query to combine with, in parentheses, e.g. (FROM language.csv SELECT CountryCode)".to_string(),
+            span: Span { start: 20, end: 24 },
+        }
+    );
+    assert_eq!(
+        parse_error("FROM city.csv UNION ALL\n").span(),
+        Span { start: 14, end: 23 }
+    );
+    assert_eq!(
+        parse_error("UNION FROM city.csv\n").to_string(),
+        "UNION can't be the first command; It must be preceded by at least a FROM."
+    );
+    assert_eq!(
+        parse_error("FROM city.csv UNION (FROM country.csv\n").to_string(),
+        "Missing ')' after the sub-query to UNION with."
+    );
+    assert_eq!(
+        parse_error("FROM city.csv UNION ALL ()\n").to_string(),
+        "The sub-query to UNION ALL with is empty."
+    );
+}
+
+#[test]
+fn test_parse_command_join_on() {
+    assert_eq!(
+        parse_command("FROM country.csv JOIN city.csv ON Capital=CityID\n"),
+        Command::Operator(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            right: Box::new(Operator::From(Dataset::City)),
+            column: "Capital".to_string(),
+            right_column: Some("CityID".to_string()),
+            kind: JoinKind::Inner,
+            strategy: None,
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM city.csv LEFTJOIN country.csv ON CountryCode=CountryCode\n"),
+        parse_command("FROM city.csv LEFTJOIN country.csv CountryCode\n")
+    );
+    for malformed in ["ON", "ON Capital", "ON Capital=", "ON =CityID"] {
+        assert_eq!(parse_error(&format!("FROM country.csv JOIN city.csv {}\n", malformed)).to_string(), "JOIN ON must be followed by the columns to join on, separated by =, e.g. Capital=CityID.");
+    }
+}
+
+/// Test well-formed input: "FROM language.csv DISTINCT\n" with and without columns.
+#[test]
+fn test_parse_command_distinct() {
+    assert_eq!(
+        parse_command("FROM language.csv DISTINCT TAKE 5\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Distinct {
+                chain: Box::new(Operator::From(Dataset::Language)),
+                columns: vec![],
+            }),
+            count: 5,
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM city.csv DISTINCT CountryCode,CityPop\n"),
+        Command::Operator(Operator::Distinct {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec!["CountryCode".to_string(), "CityPop".to_string()],
+        }),
+    );
     assert_eq!(
-        parse_command("language.csv\n"),
-        Command::InputError("Invalid Input: language.csv".to_string())
+        parse_error("DISTINCT\n").to_string(),
+        "DISTINCT can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM city.csv RENAME CityPop Population\n"
 #[test]
-fn test_parse_command_malformed4() {
+fn test_parse_command_rename() {
+    assert_eq!(
+        parse_command("FROM city.csv RENAME CityPop Population ORDERBY Population\n"),
+        Command::Operator(Operator::OrderBy {
+            chain: Box::new(Operator::Rename {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                new_name: "Population".to_string(),
+            }),
+            column: "Population".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::Last,
+        }),
+    );
+    assert_eq!(parse_error("FROM city.csv RENAME CityPop\n").to_string(), "RENAME must be followed by the name of the column to rename and its new name, e.g. RENAME CityPop Population.");
     assert_eq!(
-        parse_command("help FROM language.csv\n"),
-        Command::InputError("Invalid Input: help FROM language.csv".to_string())
+        parse_error("RENAME CityPop Population\n").to_string(),
+        "RENAME can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
 #[test]
-fn test_parse_command_malformed5() {
+fn test_parse_command_reorder() {
+    assert_eq!(
+        parse_command("FROM city.csv REORDER CityPop,CityName TAKE 1\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Reorder {
+                chain: Box::new(Operator::From(Dataset::City)),
+                columns: vec!["CityPop".to_string(), "CityName".to_string()],
+            }),
+            count: 1,
+        }),
+    );
+    assert_eq!(parse_error("FROM city.csv REORDER\n").to_string(), "REORDER must be followed by the names of the columns to move to the front, e.g. REORDER CityName,CityPop.");
     assert_eq!(
-        parse_command("FROM ORDERBY CityPop TAKE 7 SELECT CityName,CityPop\n"),
-        Command::InputError("Invalid argument to FROM: Some(\"ORDERBY\")".to_string())
+        parse_error("REORDER CityPop\n").to_string(),
+        "REORDER can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
 #[test]
-fn test_parse_command_malformed6() {
+fn test_parse_command_cast() {
     assert_eq!(
-        parse_command("FROM city.csv ORDERBY TAKE 7 SELECT CityName,CityPop\n"),
-        Command::InputError(
-            "Invalid Input: FROM city.csv ORDERBY TAKE 7 SELECT CityName,CityPop".to_string()
-        )
+        parse_command("FROM city.csv CAST CityPop AS string TAKE 1\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Cast {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                to: CastType::String,
+            }),
+            count: 1,
+        }),
+    );
+    for input in [
+        "FROM city.csv CAST CityPop\n",
+        "FROM city.csv CAST CityPop INT\n",
+        "FROM city.csv CAST CityPop AS DATE\n",
+    ] {
+        assert_eq!(parse_error(input).to_string(), "CAST must be followed by the name of the column to convert, AS, and INT, FLOAT, or STRING, e.g. CAST Zip AS INT.", "{}", input);
+    }
+    assert_eq!(
+        parse_error("CAST CityPop AS INT\n").to_string(),
+        "CAST can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM country.csv FILLNA Capital 0 FILLNA CountryName 'Not named'\n"
 #[test]
-fn test_parse_command_malformed7() {
+fn test_parse_command_fillna() {
+    assert_eq!(
+        parse_command("FROM country.csv FILLNA Capital 0 FILLNA CountryName 'Not named'\n"),
+        Command::Operator(Operator::FillNa {
+            chain: Box::new(Operator::FillNa {
+                chain: Box::new(Operator::From(Dataset::Country)),
+                column: "Capital".to_string(),
+                value: Cell::Int64(0),
+            }),
+            column: "CountryName".to_string(),
+            value: Cell::String("Not named".to_string()),
+        }),
+    );
     assert_eq!(
-        parse_command("FROM city.csv ORDERBY CityPop TAKE SELECT CityName,CityPop\n"),
-        Command::InputError("Invalid value passed to TAKE operator: SELECT. Must be a positive integer.\n Full error message: invalid digit found in string".to_string())
+        parse_error("FROM country.csv FILLNA Capital\n").to_string(),
+        "FILLNA must be followed by the name of the column and the value replacing its missing values, e.g. FILLNA Capital 0."
+    );
+    assert_eq!(
+        parse_error("FILLNA Capital 0\n").to_string(),
+        "FILLNA can't be the first command; It must be preceded by at least a FROM."
+    );
+    // The arguments of a function may be separated by spaces.
+    assert_eq!(
+        parse_command("FROM country.csv SELECT COALESCE(Capital, 0) AS Capital\n"),
+        Command::Operator(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            columns: parse_select_list("COALESCE(Capital,0) AS Capital").unwrap(),
+        }),
     );
 }
 
-/// Test malformed command as input
 #[test]
-fn test_parse_command_malformed8() {
+fn test_parse_command_topn() {
+    assert_eq!(
+        parse_command("FROM city.csv TOPN 3 BY CountryCode ORDER CityPop TAKE 5\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::TopN {
+                chain: Box::new(Operator::From(Dataset::City)),
+                count: 3,
+                group: "CountryCode".to_string(),
+                column: "CityPop".to_string(),
+                order: SortOrder::Descending,
+            }),
+            count: 5,
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM city.csv TOPN 1 BY CountryCode ORDER CityPop ASC\n"),
+        Command::Operator(Operator::TopN {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 1,
+            group: "CountryCode".to_string(),
+            column: "CityPop".to_string(),
+            order: SortOrder::Ascending,
+        }),
+    );
+    for input in [
+        "FROM city.csv TOPN 3\n",
+        "FROM city.csv TOPN 3 BY CountryCode\n",
+        "FROM city.csv TOPN 3 CountryCode ORDER CityPop\n",
+    ] {
+        assert_eq!(parse_error(input).to_string(), "TOPN must be followed by the number of rows to keep, BY and the column to group by, and ORDER and the column to rank by, e.g. TOPN 3 BY CountryCode ORDER CityPop.", "{}", input);
+    }
     assert_eq!(
-        parse_command("FROM city.csv ORDERBY CityPop TAKE 7 SELECT\n"),
-        Command::InputError("SELECT takes at least one column name to select on.".to_string())
+        parse_error("FROM city.csv TOPN three BY CountryCode ORDER CityPop\n").to_string(),
+        parse_error("FROM city.csv TAKE three\n")
+            .to_string()
+            .replace("TAKE", "TOPN")
+    );
+    assert_eq!(
+        parse_error("TOPN 3 BY CountryCode ORDER CityPop\n").to_string(),
+        "TOPN can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
 #[test]
-fn test_parse_command_malformed9() {
+fn test_parse_command_rank() {
+    assert_eq!(
+        parse_command("FROM city.csv RANK BY CityPop TAKE 5\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Rank {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                partition: None,
+            }),
+            count: 5,
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM city.csv RANK BY CityPop PER CountryCode\n"),
+        Command::Operator(Operator::Rank {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CityPop".to_string(),
+            partition: Some("CountryCode".to_string()),
+        }),
+    );
+    for input in [
+        "FROM city.csv RANK\n",
+        "FROM city.csv RANK CityPop\n",
+        "FROM city.csv RANK BY CityPop PER\n",
+    ] {
+        assert_eq!(parse_error(input).to_string(), "RANK must be followed by BY and the column to rank the rows by, and optionally PER and the column to rank them within, e.g. RANK BY CityPop PER CountryCode.", "{}", input);
+    }
     assert_eq!(
-        parse_command("FROM city.csv TAKE -2\n"),
-        Command::InputError("Invalid value passed to TAKE operator: -2. Must be a positive integer.\n Full error message: invalid digit found in string".to_string())
+        parse_error("RANK BY CityPop\n").to_string(),
+        "RANK can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
 #[test]
-fn test_parse_command_malformed10() {
+fn test_parse_command_pivot() {
+    assert_eq!(
+        parse_command("FROM language.csv PIVOT Language CountryCode COUNT(CountryCode)\n"),
+        Command::Operator(Operator::Pivot {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            row: "Language".to_string(),
+            column: "CountryCode".to_string(),
+            aggregate: parse_aggregate_list("COUNT(CountryCode)").unwrap()[0].clone(),
+        }),
+    );
+    assert_eq!(parse_error("FROM language.csv PIVOT Language CountryCode\n").to_string(), "PIVOT must be followed by the column whose values become the rows, the column whose values become the columns, and an aggregate, e.g. PIVOT Language Continent COUNT(CountryCode).");
+    assert_eq!(parse_error("FROM city.csv PIVOT CountryCode CityID TOTAL(CityPop)\n").to_string(), "Invalid aggregate: TOTAL(CityPop). Must be one of SUM, AVG, MIN, MAX, or COUNT applied to a column, e.g. SUM(CityPop).");
     assert_eq!(
-        parse_command("FROM city.csv TAKE CityID\n"),
-        Command::InputError("Invalid value passed to TAKE operator: CityID. Must be a positive integer.\n Full error message: invalid digit found in string".to_string())
+        parse_error("FROM city.csv PIVOT CountryCode CityID SUM(CityPop),COUNT(CityID)\n")
+            .to_string(),
+        "PIVOT computes a single aggregate, not SUM(CityPop),COUNT(CityID)."
+    );
+    assert_eq!(
+        parse_error("PIVOT Language CountryCode COUNT(CountryCode)\n").to_string(),
+        "PIVOT can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
 #[test]
-fn test_parse_command_malformed11() {
+fn test_parse_command_select_all() {
+    assert_eq!(
+        parse_command("FROM country.csv SELECT * TAKE 5\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::SelectAll {
+                chain: Box::new(Operator::From(Dataset::Country)),
+                except: vec![],
+            }),
+            count: 5,
+        }),
+    );
+    assert_eq!(
+        parse_command("FROM country.csv SELECT * EXCEPT Capital,CountryPop\n"),
+        Command::Operator(Operator::SelectAll {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            except: vec!["Capital".to_string(), "CountryPop".to_string()],
+        }),
+    );
+    assert_eq!(
+        parse_error("FROM country.csv SELECT * EXCEPT\n").to_string(),
+        "SELECT must be followed by the columns to select, or * EXCEPT and the columns to drop, e.g. SELECT * EXCEPT Capital."
+    );
     assert_eq!(
-        parse_command("FROM city.cv\n"),
-        Command::InputError("Invalid argument to FROM: Some(\"city.cv\")".to_string())
+        parse_error("SELECT *\n").to_string(),
+        "SELECT can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM city.csv TAKE 5 INTO results.csv\n"
 #[test]
-fn test_parse_command_malformed12() {
+fn test_parse_command_into() {
+    let expected = Command::Operator(Operator::Into {
+        chain: Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 5,
+        }),
+        path: "results.csv".to_string(),
+        format: ExportFormat::Csv,
+    });
+    assert_eq!(
+        parse_command("FROM city.csv TAKE 5 INTO results.csv\n"),
+        expected
+    );
+    assert_eq!(
+        parse_command("FROM city.csv TAKE 5 EXPORT results.csv\n"),
+        expected
+    );
     assert_eq!(
-        parse_command("FROM cit.csv\n"),
-        Command::InputError("Invalid argument to FROM: Some(\"cit.csv\")".to_string())
+        parse_command("FROM city.csv TAKE 5 EXPORT CSV results.csv\n"),
+        expected
     );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM city.csv TAKE 5 EXPORT ARROW results.arrow\n"
 #[test]
-fn test_parse_command_malformed13() {
+fn test_parse_command_export_arrow() {
+    let expected = Operator::Into {
+        chain: Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 5,
+        }),
+        path: "results.arrow".to_string(),
+        format: ExportFormat::Arrow,
+    };
+    assert_eq!(
+        parse_command("FROM city.csv TAKE 5 EXPORT ARROW results.arrow\n"),
+        Command::Operator(expected.clone())
+    );
+    assert_eq!(
+        expected.to_string(),
+        "FROM city.csv TAKE 5 EXPORT ARROW results.arrow"
+    );
+    // After INTO, ARROW is the name of the file.
+    assert_eq!(
+        parse_command("FROM city.csv INTO ARROW\n"),
+        Command::Operator(Operator::Into {
+            chain: Box::new(Operator::From(Dataset::City)),
+            path: "ARROW".to_string(),
+            format: ExportFormat::Csv,
+        })
+    );
     assert_eq!(
-        parse_command("FROM lungage.csv\n"),
-        Command::InputError("Invalid argument to FROM: Some(\"lungage.csv\")".to_string())
+        parse_error("FROM city.csv EXPORT ARROW\n").to_string(),
+        "EXPORT must be followed by the path of the file to write."
     );
 }
 
-/// Test malformed command as input
+/// Test malformed input: INTO without a path, not at the end of the query, or first.
 #[test]
-fn test_parse_command_malformed14() {
+fn test_parse_command_into_malformed() {
+    assert_eq!(
+        parse_error("FROM city.csv INTO\n").to_string(),
+        "INTO must be followed by the path of the file to write."
+    );
+    assert_eq!(
+        parse_error("FROM city.csv INTO results.csv TAKE 5\n").to_string(),
+        "INTO must be the last operator of the query."
+    );
     assert_eq!(
-        parse_command("FROM contry.csv\n"),
-        Command::InputError("Invalid argument to FROM: Some(\"contry.csv\")".to_string())
+        parse_error("EXPORT results.csv\n").to_string(),
+        "EXPORT can't be the first command; It must be preceded by at least a FROM."
     );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM country.csv FILTER Continent = Europe\n"
 #[test]
-fn test_parse_command_malformed15() {
+fn test_parse_command_filter() {
+    let expected = Command::Operator(Operator::Filter {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        predicate: parse_predicate("Continent", "=", "Europe").unwrap(),
+    });
     assert_eq!(
-        parse_command("FROM city.csv JOIN country.csv\n"),
-        Command::InputError(
-            "JOIN must be followed by the dataset and the name of the column to join on."
-                .to_string()
-        )
+        parse_command("FROM country.csv FILTER Continent = Europe\n"),
+        expected
+    );
+    assert_eq!(
+        parse_command("FROM country.csv WHERE Continent = Europe\n"),
+        expected
     );
 }
 
-/// Test malformed command as input
+/// Test well-formed input: "FROM country.csv WHERE Capital IS NOT NULL\n"
 #[test]
-fn test_parse_command_malformed16() {
+fn test_parse_command_filter_null() {
+    assert_eq!(
+        parse_command("FROM country.csv WHERE Capital IS NULL\n"),
+        Command::Operator(Operator::Filter {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            predicate: parse_predicate("Capital", "IS", "NULL").unwrap(),
+        })
+    );
+    assert_eq!(
+        parse_command("FROM country.csv WHERE Capital IS NOT NULL TAKE 1\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Filter {
+                chain: Box::new(Operator::From(Dataset::Country)),
+                predicate: parse_predicate("Capital", "IS NOT", "NULL").unwrap(),
+            }),
+            count: 1,
+        })
+    );
     assert_eq!(
-        parse_command("FROM city.csv JOIN CountryCode\n"),
-        Command::InputError("Invalid dataset to JOIN on: CountryCode".to_string())
+        parse_error("FROM country.csv WHERE Capital IS NOT\n").to_string(),
+        "IS NOT must be followed by NULL, e.g. Capital IS NULL."
     );
 }
 
-/// Test malformed command as input
+/// Test quoted values: "FROM city.csv FILTER CityName = 'New York'\n"
 #[test]
-fn test_parse_command_malformed17() {
+fn test_parse_command_quoted() {
+    let filter = |value: &str| {
+        Command::Operator(Operator::Filter {
+            chain: Box::new(Operator::From(Dataset::City)),
+            predicate: Predicate {
+                left: Expression::Column("CityName".to_string()),
+                comparison: Comparison::Equal,
+                value: Cell::String(value.to_string()),
+            },
+        })
+    };
+    assert_eq!(
+        parse_command("FROM city.csv FILTER CityName = 'New York'\n"),
+        filter("New York")
+    );
+    assert_eq!(
+        parse_command("FROM city.csv FILTER CityName = \"Washington, D.C.\"\n"),
+        filter("Washington, D.C.")
+    );
+    assert_eq!(
+        parse_command("FROM city.csv FILTER CityName = 'O\\'Hare'\n"),
+        filter("O'Hare")
+    );
+    assert_eq!(
+        parse_command("FROM city.csv FILTER CityName = New\\ York\n"),
+        filter("New York")
+    );
+    // Quoted strings in expressions may contain spaces and commas too.
+    assert_eq!(
+        parse_command("FROM city.csv SELECT CityName,'Hello, world' AS Greeting\n"),
+        Command::Operator(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: parse_select_list("CityName,'Hello, world' AS Greeting").unwrap(),
+        })
+    );
     assert_eq!(
-        parse_command("FROM city.csv JOIN country.csv CountryCode JOIN lnguage.csv CountryCode\n"),
-        Command::InputError("Invalid dataset to JOIN on: lnguage.csv".to_string())
+        parse_command("LOAD trips FROM 'my data/trips.csv'\n"),
+        Command::Load {
+            name: "trips".to_string(),
+            path: "my data/trips.csv".to_string(),
+            encoding: None,
+        }
     );
+    let error = parse_error("FROM city.csv FILTER CityName = 'New York\n");
+    assert_eq!(error.to_string(), "Missing closing quote: 'New York");
+    assert_eq!(error.span(), Span { start: 32, end: 41 });
 }
 
-/// Test 'help'command as input
+/// Test malformed input: "FROM country.csv FILTER Continent =\n"
 #[test]
-fn test_parse_command_help() {
-    assert_eq!(parse_command("help\n"), Command::Help);
+fn test_parse_command_filter_malformed() {
+    assert_eq!(parse_error("FROM country.csv FILTER Continent =\n").to_string(), "FILTER must be followed by a column name, a comparison, and a value, e.g. CityPop > 1000000.");
+    assert_eq!(
+        parse_error("FROM country.csv WHERE Continent ~ Europe\n").to_string(),
+        "Invalid comparison: ~. Must be one of =, !=, <, <=, >, >=, IS NULL, IS NOT NULL, LIKE, CONTAINS, STARTSWITH, ENDSWITH, or MATCHES, optionally prefixed with I to ignore case, e.g. ILIKE."
+    );
 }
 
-/// Test well-formed input: `FROM language.csv`.
+/// Test well-formed input:
+/// "WITH euro AS (FROM country.csv FILTER Continent = Europe) FROM city.csv JOIN euro CountryCode\n"
 #[test]
-fn test_parse_command_from_language() {
+fn test_parse_command_with() {
+    let euro = Operator::Filter {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        predicate: parse_predicate("Continent", "=", "Europe").unwrap(),
+    };
     assert_eq!(
-        parse_command("FROM language.csv\n"),
-        Command::Operator(Operator::From(Dataset::Language))
+        parse_command(
+            "WITH euro AS (FROM country.csv FILTER Continent = Europe) FROM city.csv JOIN euro CountryCode\n"
+        ),
+        Command::Operator(Operator::With {
+            ctes: vec![("euro".to_string(), euro)],
+            query: Box::new(Operator::Join {
+                chain: Box::new(Operator::From(Dataset::City)),
+                right: Box::new(Operator::From(Dataset::Cte("euro".to_string()))),
+                column: "CountryCode".to_string(),
+                right_column: None,
+                kind: JoinKind::Inner,
+                strategy: None,
+            }),
+        })
     );
 }
 
-/// Test well-formed input: `FROM city.csv`.
+/// Test well-formed input with several sub-queries, where the second one refers to the first and
+/// contains parentheses of its own.
 #[test]
-fn test_parse_command_from_city() {
+fn test_parse_command_with_multiple() {
+    let command = parse_command(
+        "WITH big AS (FROM city.csv TAKE 5), names AS ( FROM big SELECT slug(CityName) ) FROM names\n",
+    );
     assert_eq!(
-        parse_command("FROM city.csv\n"),
-        Command::Operator(Operator::From(Dataset::City))
+        command,
+        Command::Operator(Operator::With {
+            ctes: vec![
+                (
+                    "big".to_string(),
+                    Operator::Take {
+                        chain: Box::new(Operator::From(Dataset::City)),
+                        count: 5,
+                    }
+                ),
+                (
+                    "names".to_string(),
+                    Operator::Select {
+                        chain: Box::new(Operator::From(Dataset::Cte("big".to_string()))),
+                        columns: parse_select_list("slug(CityName)").unwrap(),
+                    }
+                ),
+            ],
+            query: Box::new(Operator::From(Dataset::Cte("names".to_string()))),
+        })
     );
+    if let Command::Operator(operator) = command {
+        assert_eq!(
+            operator.to_string(),
+            "WITH big AS (FROM city.csv TAKE 5), names AS (FROM big SELECT slug(CityName)) FROM names"
+        );
+    }
 }
 
-/// Test well-formed input: `FROM country.csv`.
+/// Test malformed WITH clauses.
 #[test]
-fn test_parse_command_from_country() {
+fn test_parse_command_with_malformed() {
+    for malformed in [
+        "WITH euro\n",
+        "WITH euro AS FROM country.csv\n",
+        "WITH euro AS (FROM country.csv TAKE 5)\n",
+        "WITH euro AS () FROM euro\n",
+    ] {
+        assert_eq!(
+            parse_error(malformed).to_string(),
+            "WITH must be followed by <name> AS (<query>) and then the query to run."
+        );
+    }
     assert_eq!(
-        parse_command("FROM country.csv\n"),
-        Command::Operator(Operator::From(Dataset::Country))
+        parse_error("WITH euro AS (FROM country.csv TAKE 5 FROM euro\n").to_string(),
+        "Missing ')' after the definition of euro in the WITH clause."
+    );
+    assert_eq!(
+        parse_error("WITH a AS (FROM city.csv), a AS (FROM country.csv) FROM a\n").to_string(),
+        "a is defined more than once in the WITH clause."
+    );
+    // The names are only valid within the query that defines them.
+    assert_eq!(
+        parse_error("FROM euro\n").to_string(),
+        "Invalid dataset passed to FROM: euro"
     );
 }
-/// Test well-formed input: "FROM city.csv ORDERBY CityPop TAKE 7 SELECT CityName,CityPop\n"
+
+/// Test well-formed input: "FROM city.csv MAP clean.rhai TAKE 2\n"
 #[test]
-fn test_parse_command_complex1() {
+fn test_parse_command_map() {
     assert_eq!(
-        parse_command("FROM city.csv ORDERBY CityPop TAKE 7 SELECT CityName,CityPop\n"),
-        Command::Operator(Operator::Select {
-            chain: Box::new(Operator::Take {
-                chain: Box::new(Operator::OrderBy {
-                    chain: Box::new(Operator::From(Dataset::City)),
-                    column: "CityPop".to_string()
-                }),
-                count: 7
+        parse_command("FROM city.csv MAP clean.rhai TAKE 2\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::Map {
+                chain: Box::new(Operator::From(Dataset::City)),
+                script: "clean.rhai".to_string(),
             }),
-            column_names: vec!["CityName".to_string(), "CityPop".to_string()]
+            count: 2
         }),
     );
 }
-/// Test well-formed input: "FROM city.csv SELECT CityName\n"
+
+/// Test malformed input: "FROM city.csv MAP\n" and "MAP clean.rhai\n"
 #[test]
-fn test_parse_command_complex2() {
+fn test_parse_command_map_malformed() {
     assert_eq!(
-        parse_command("FROM city.csv SELECT CityName\n"),
-        Command::Operator(Operator::Select {
-            chain: Box::new(Operator::From(Dataset::City)),
-            column_names: vec!["CityName".to_string()]
-        })
+        parse_error("FROM city.csv MAP\n").to_string(),
+        "MAP must be followed by the path of the script to run."
     );
-}
-
-/// Test well-formed input: "FROM country.csv SELECT CountryCode,Continent,CountryPop\n"
-#[test]
-fn test_parse_command_complex3() {
     assert_eq!(
-        parse_command("FROM country.csv SELECT CountryCode,Continent,CountryPop\n"),
-        Command::Operator(Operator::Select {
-            chain: Box::new(Operator::From(Dataset::Country)),
-            column_names: vec![
-                "CountryCode".to_string(),
-                "Continent".to_string(),
-                "CountryPop".to_string()
-            ]
-        }),
+        parse_error("MAP clean.rhai\n").to_string(),
+        "MAP can't be the first command; It must be preceded by at least a FROM."
     );
 }
+
 /// Test well-formed input: "FROM city.csv TAKE 2\n"
 #[test]
 fn test_parse_command_complex4() {
@@ -500,7 +3930,9 @@ fn test_parse_command_complex5() {
         Command::Operator(Operator::Take {
             chain: Box::new(Operator::OrderBy {
                 chain: Box::new(Operator::From(Dataset::City)),
-                column: "CityPop".to_string()
+                column: "CityPop".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
             }),
             count: 10
         }),
@@ -513,8 +3945,11 @@ fn test_parse_command_complex6() {
         parse_command("FROM city.csv JOIN country.csv CountryCode\n"),
         Command::Operator(Operator::Join {
             chain: Box::new(Operator::From(Dataset::City)),
-            right: Dataset::Country,
-            column: "CountryCode".to_string()
+            right: Box::new(Operator::From(Dataset::Country)),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
         }),
     );
 }
@@ -527,14 +3962,167 @@ fn test_parse_command_complex7() {
         Command::Operator(Operator::Join {
             chain: Box::new(Operator::Join {
                 chain: Box::new(Operator::From(Dataset::City)),
-                right: Dataset::Country,
-                column: "CountryCode".to_string()
+                right: Box::new(Operator::From(Dataset::Country)),
+                column: "CountryCode".to_string(),
+                right_column: None,
+                kind: JoinKind::Inner,
+                strategy: None,
             }),
-            right: Dataset::Language,
-            column: "CountryCode".to_string()
+            right: Box::new(Operator::From(Dataset::Language)),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
         }),
     );
 }
+/// Test well-formed input: "FROM city.csv COUNTBY CountryCode ORDER value [BARS]\n"
+#[test]
+fn test_parse_command_countby_order() {
+    let countby = |order| {
+        Command::Operator(Operator::CountBy {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CountryCode".to_string(),
+            order,
+            bars: false,
+            having: None,
+        })
+    };
+    assert_eq!(
+        parse_command("FROM city.csv COUNTBY CountryCode ORDER value\n"),
+        countby(CountByOrder::Value)
+    );
+    assert_eq!(
+        parse_command("FROM city.csv COUNTBY CountryCode ORDER count\n"),
+        countby(CountByOrder::Count)
+    );
+    assert_eq!(
+        parse_command("FROM city.csv COUNTBY CountryCode ORDER value BARS\n"),
+        Command::Operator(Operator::CountBy {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CountryCode".to_string(),
+            order: CountByOrder::Value,
+            bars: true,
+            having: None,
+        })
+    );
+    assert_eq!(
+        parse_command("FROM city.csv COUNTBY CountryCode BARS TAKE 3\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::CountBy {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CountryCode".to_string(),
+                order: CountByOrder::Count,
+                bars: true,
+                having: None,
+            }),
+            count: 3,
+        })
+    );
+    for malformed in ["ORDER", "ORDER name"] {
+        assert_eq!(
+            parse_error(&format!(
+                "FROM city.csv COUNTBY CountryCode {}\n",
+                malformed
+            ))
+            .to_string(),
+            "COUNTBY ... ORDER must be followed by count or value."
+        );
+    }
+}
+
+/// Test well-formed input: "FROM language.csv COUNTBY Language HAVING count > 10\n" and the
+/// HAVING of GROUPBY.
+#[test]
+fn test_parse_command_having() {
+    let countby = Operator::CountBy {
+        chain: Box::new(Operator::From(Dataset::Language)),
+        column: "Language".to_string(),
+        order: CountByOrder::Value,
+        bars: true,
+        having: Some(parse_predicate("count", ">", "10").unwrap()),
+    };
+    assert_eq!(
+        parse_command("FROM language.csv COUNTBY Language ORDER value BARS HAVING count > 10\n"),
+        Command::Operator(countby.clone())
+    );
+    // The label reads back as the same operator.
+    assert_eq!(
+        parse_command(&format!("{}\n", countby)),
+        Command::Operator(countby)
+    );
+    assert_eq!(
+        parse_command("FROM language.csv COUNTBY Language HAVING Language IS NOT NULL TAKE 3\n"),
+        Command::Operator(Operator::Take {
+            chain: Box::new(Operator::CountBy {
+                chain: Box::new(Operator::From(Dataset::Language)),
+                column: "Language".to_string(),
+                order: CountByOrder::Count,
+                bars: false,
+                having: Some(parse_predicate("Language", "IS NOT", "NULL").unwrap()),
+            }),
+            count: 3,
+        })
+    );
+    // The aggregates are read by their name.
+    let groupby = |having| Operator::GroupBy {
+        chain: Box::new(Operator::From(Dataset::City)),
+        column: "CountryCode".to_string(),
+        aggregates: parse_aggregate_list("SUM(CityPop),COUNT(CityID)").unwrap(),
+        having: Some(having),
+    };
+    let sum = Predicate {
+        left: Expression::Column("SUM(CityPop)".to_string()),
+        ..parse_predicate("total", ">", "1000000").unwrap()
+    };
+    assert_eq!(
+        parse_command(
+            "FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID) HAVING SUM(CityPop) > 1000000\n"
+        ),
+        Command::Operator(groupby(sum.clone()))
+    );
+    assert_eq!(
+        parse_command(&format!("{}\n", groupby(sum.clone()))),
+        Command::Operator(groupby(sum))
+    );
+    assert_eq!(
+        parse_command(
+            "FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID) HAVING CountryCode = USA\n"
+        ),
+        Command::Operator(groupby(
+            parse_predicate("CountryCode", "=", "USA").unwrap()
+        ))
+    );
+    // Other calls are left as is.
+    assert_eq!(
+        parse_command(
+            "FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID) HAVING MAX(CityPop) > 5\n"
+        ),
+        Command::Operator(groupby(parse_predicate("MAX(CityPop)", ">", "5").unwrap()))
+    );
+}
+
+/// Test malformed input: "FROM language.csv COUNTBY Language HAVING count\n" and friends
+#[test]
+fn test_parse_command_having_malformed() {
+    for malformed in ["HAVING", "HAVING count", "HAVING count >"] {
+        assert_eq!(
+            parse_error(&format!("FROM language.csv COUNTBY Language {}\n", malformed))
+                .to_string(),
+            "COUNTBY ... HAVING must be followed by a column name, a comparison, and a value, e.g. count > 10."
+        );
+    }
+    assert_eq!(
+        parse_error("FROM city.csv GROUPBY CountryCode SUM(CityPop) HAVING SUM(CityPop) ~ 5\n")
+            .to_string(),
+        "Invalid comparison: ~. Must be one of =, !=, <, <=, >, >=, IS NULL, IS NOT NULL, LIKE, CONTAINS, STARTSWITH, ENDSWITH, or MATCHES, optionally prefixed with I to ignore case, e.g. ILIKE."
+    );
+    assert_eq!(
+        parse_error("FROM city.csv GROUPBY CountryCode SUM(CityPop) HAVING\n").span(),
+        Span { start: 47, end: 53 }
+    );
+}
+
 /// Test well-formed input: "FROM language.csv COUNTBY Language ORDERBY count TAKE 7\n"
 #[test]
 fn test_parse_command_complex8() {
@@ -544,9 +4132,14 @@ fn test_parse_command_complex8() {
             chain: Box::new(Operator::OrderBy {
                 chain: Box::new(Operator::CountBy {
                     chain: Box::new(Operator::From(Dataset::Language)),
-                    column: "Language".to_string()
+                    column: "Language".to_string(),
+                    order: CountByOrder::Count,
+                    bars: false,
+                    having: None,
                 }),
-                column: "count".to_string()
+                column: "count".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
             }),
             count: 7
         }),
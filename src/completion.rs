@@ -0,0 +1,331 @@
+use crate::commands::SESSION_COMMANDS;
+use crate::data::{peek_columns, Dataset, DatasetRegistry};
+use crate::operators::OPERATORS;
+use crate::settings::SETTINGS;
+
+/// The commands that can start a line, in addition to FROM.
+const COMMANDS: &[&str] = &[
+    "WITH", "EXPLAIN", "WATCH", "LOAD", "ATTACH", "SET", "INSERT", "DELETE", "SAVE", "DESCRIBE",
+    "SHOW", "help", "exit",
+];
+
+/// The operators followed by the name of a column, or a list of them separated by commas.
+const COLUMN_KEYWORDS: &[&str] = &[
+    "SELECT", "ORDERBY", "COUNTBY", "SUM", "AVG", "MIN", "MAX", "COUNT", "GROUPBY", "FILTER",
+    "WHERE", "DISTINCT", "RENAME", "REORDER", "CAST", "FILLNA", "BY", "PER", "PIVOT", "EXCEPT",
+];
+
+/// The operators and commands followed by the name of a dataset.
+const DATASET_KEYWORDS: &[&str] = &[
+    "FROM",
+    "JOIN",
+    "LEFTJOIN",
+    "RIGHTJOIN",
+    "OUTERJOIN",
+    "SAVE",
+    "DESCRIBE",
+    "COLUMNS",
+];
+
+/// Helper function to return the `candidates` that start with the `prefix`, ignoring case, in
+/// their original order and without duplicates.
+fn matching(candidates: Vec<String>, prefix: &str) -> Vec<String> {
+    let prefix = prefix.to_lowercase();
+    let mut matches: Vec<String> = Vec::new();
+    for candidate in candidates {
+        if candidate.to_lowercase().starts_with(&prefix) && !matches.contains(&candidate) {
+            matches.push(candidate);
+        }
+    }
+    matches
+}
+
+/// Completes the word being typed at the cursor in the REPL.
+///
+/// # Arguments
+/// `line` : The line being edited.
+/// `pos` : The position of the cursor in the `line`, as a byte offset.
+/// `datasets` : The datasets registered with `LOAD`, whose names are completed after FROM and
+/// JOIN.
+///
+/// # Returns
+/// The byte offset the completed word starts at, and the words it may be completed to:
+/// - The name of a dataset after FROM and JOIN, e.g. `FROM ci` to `city.csv`.
+/// - The name of a column after SELECT, ORDERBY, COUNTBY and the other operators that take one,
+///   among the columns of the datasets passed to FROM and JOIN earlier in the line. In a list of
+///   columns, only the one after the last comma is completed.
+/// - The name of a setting, or of `\history` and `\rerun`, after a backslash at the start of the
+///   line, e.g. `\li` to `\limit`.
+/// - A command or operator keyword otherwise, e.g. `FROM city SEL` to `SELECT`.
+///
+/// Case is ignored when matching the word.
+pub fn complete(line: &str, pos: usize, datasets: &DatasetRegistry) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(index, c)| index + c.len_utf8());
+    let previous: Vec<&str> = before[..start].split_whitespace().collect();
+    let word = &before[start..];
+
+    if previous.is_empty() {
+        if let Some(name) = word.strip_prefix('\\') {
+            let settings = SETTINGS
+                .iter()
+                .map(|setting| setting.name)
+                .chain(SESSION_COMMANDS.iter().copied())
+                .map(|name| format!("\\{}", name))
+                .collect();
+            return (start, matching(settings, &format!("\\{}", name)));
+        }
+    }
+
+    // A sub-query to JOIN on starts with a parenthesis, e.g. `(FROM country`.
+    let keyword = previous
+        .last()
+        .map(|keyword| keyword.trim_start_matches('('));
+    if keyword.is_some_and(|keyword| DATASET_KEYWORDS.contains(&keyword)) {
+        let names = Dataset::all()
+            .iter()
+            .chain(datasets.registered().iter())
+            .map(|dataset| match dataset {
+                Dataset::Registered { name, .. } => name.clone(),
+                dataset => dataset.to_string(),
+            })
+            .collect();
+        return (start, matching(names, word));
+    }
+
+    // The aggregates of GROUPBY follow the column to group by, e.g. `GROUPBY Continent SUM(`.
+    let groupby_aggregates = previous.len() >= 2 && previous[previous.len() - 2] == "GROUPBY";
+    // TOPN is followed by the count, BY and a column, and ORDER and a column, e.g.
+    // `TOPN 3 BY CountryCode ORDER CityPop`.
+    let topn = previous.iter().rev().position(|token| *token == "TOPN");
+    let topn_order = topn == Some(4) && keyword == Some("ORDER");
+    if keyword.is_some_and(|keyword| COLUMN_KEYWORDS.contains(&keyword))
+        || groupby_aggregates
+        || topn_order
+    {
+        // Only the last of a list of columns, or the argument of a function, is completed.
+        let column_start = start + word.rfind([',', '(']).map_or(0, |index| index + 1);
+        let columns = previous
+            .windows(2)
+            .filter(|pair| DATASET_KEYWORDS.contains(&pair[0].trim_start_matches('(')))
+            .filter_map(|pair| datasets.resolve(pair[1]))
+            .flat_map(|dataset| peek_columns(&dataset))
+            .collect();
+        return (column_start, matching(columns, &line[column_start..pos]));
+    }
+
+    // The column converted by CAST is followed by AS and the type, e.g. `CAST Zip AS INT`.
+    let cast = previous.iter().rev().position(|token| *token == "CAST");
+    let keywords: Vec<&str> = match previous.last() {
+        Some(_) if cast == Some(1) => vec!["AS"],
+        Some(_) if topn == Some(1) => vec!["BY"],
+        Some(&"RANK") => vec!["BY"],
+        Some(&"EXPORT") => vec!["ARROW", "CSV"],
+        Some(&"SHOW") => vec!["TABLES", "COLUMNS"],
+        Some(_) if topn == Some(3) => vec!["ORDER"],
+        Some(&"AS") if cast == Some(2) => vec!["INT", "FLOAT", "STRING"],
+        None => COMMANDS.iter().copied().chain(["FROM"]).collect(),
+        Some(&"EXPLAIN") => vec!["ANALYZE", "FROM", "WITH"],
+        Some(&"WATCH") => vec!["FROM", "WITH"],
+        // The query combined with by UNION is parenthesized, and starts with FROM too.
+        Some(&"UNION") => vec!["ALL", "(FROM"],
+        Some(&"ALL") => vec!["(FROM"],
+        Some(_) => OPERATORS
+            .iter()
+            .map(|operator| operator.name)
+            .filter(|name| *name != "FROM" && *name != "WITH")
+            .chain(["WHERE", "EXPORT"])
+            .collect(),
+    };
+    (
+        start,
+        matching(keywords.into_iter().map(str::to_string).collect(), word),
+    )
+}
+
+#[test]
+fn test_complete_keywords() {
+    let datasets = DatasetRegistry::default();
+    // Only the commands that can start a line are completed there.
+    assert_eq!(complete("SE", 2, &datasets), (0, vec!["SET".to_string()]));
+    assert_eq!(complete("f", 1, &datasets), (0, vec!["FROM".to_string()]));
+    assert_eq!(
+        complete("ins", 3, &datasets),
+        (0, vec!["INSERT".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv SEL", 17, &datasets),
+        (14, vec!["SELECT".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv take", 18, &datasets),
+        (14, vec!["TAKE".to_string()])
+    );
+    assert_eq!(
+        complete("EXPLAIN AN", 10, &datasets),
+        (8, vec!["ANALYZE".to_string()])
+    );
+    assert_eq!(complete("FROM city.csv SLUG", 18, &datasets), (14, vec![]));
+    // Only the word before the cursor is completed.
+    assert_eq!(
+        complete("FROM city.csv ORD TAKE 5", 17, &datasets),
+        (14, vec!["ORDERBY".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv UNION (F", 22, &datasets),
+        (20, vec!["(FROM".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv UNION A", 21, &datasets),
+        (20, vec!["ALL".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv CAST CityPop A", 28, &datasets),
+        (27, vec!["AS".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv CAST CityPop AS f", 31, &datasets),
+        (30, vec!["FLOAT".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv TOPN 3 B", 22, &datasets),
+        (21, vec!["BY".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv TOPN 3 BY CountryCode O", 37, &datasets),
+        (36, vec!["ORDER".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv RANK B", 20, &datasets),
+        (19, vec!["BY".to_string()])
+    );
+    assert_eq!(
+        complete("SHOW c", 6, &datasets),
+        (5, vec!["COLUMNS".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv EXPORT a", 22, &datasets),
+        (21, vec!["ARROW".to_string()])
+    );
+    // Past the type, the next operator is completed.
+    assert_eq!(
+        complete("FROM city.csv CAST CityPop AS INT TAK", 37, &datasets),
+        (34, vec!["TAKE".to_string()])
+    );
+}
+
+#[test]
+fn test_complete_settings() {
+    let datasets = DatasetRegistry::default();
+    assert_eq!(
+        complete("\\li", 3, &datasets),
+        (0, vec!["\\limit".to_string()])
+    );
+    assert_eq!(
+        complete("\\", 1, &datasets).1.len(),
+        SETTINGS.len() + SESSION_COMMANDS.len()
+    );
+    assert_eq!(
+        complete("\\hi", 3, &datasets),
+        (0, vec!["\\history".to_string()])
+    );
+}
+
+#[test]
+fn test_complete_datasets() {
+    let mut datasets = DatasetRegistry::default();
+    assert_eq!(
+        complete("FROM ci", 7, &datasets),
+        (5, vec!["city.csv".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv JOIN (FROM co", 27, &datasets),
+        (25, vec!["country.csv".to_string()])
+    );
+    assert_eq!(
+        complete("SAVE la", 7, &datasets),
+        (5, vec!["language.csv".to_string()])
+    );
+    assert_eq!(
+        complete("DESCRIBE co", 11, &datasets),
+        (9, vec!["country.csv".to_string()])
+    );
+    datasets.register("cities", "data/city.csv").unwrap();
+    assert_eq!(
+        complete("FROM city.csv LEFTJOIN C", 24, &datasets),
+        (
+            23,
+            vec![
+                "city.csv".to_string(),
+                "country.csv".to_string(),
+                "cities".to_string()
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_complete_columns() {
+    let mut datasets = DatasetRegistry::default();
+    assert_eq!(
+        complete("FROM city.csv SELECT CityName,CityP", 35, &datasets),
+        (30, vec!["CityPop".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city ORDERBY cityp", 23, &datasets),
+        (18, vec!["CityPop".to_string()])
+    );
+    assert_eq!(
+        complete("FROM country.csv SELECT * EXCEPT Cap", 36, &datasets),
+        (33, vec!["Capital".to_string()])
+    );
+    assert_eq!(
+        complete("FROM city.csv TOPN 3 BY Coun", 28, &datasets),
+        (24, vec!["CountryCode".to_string()])
+    );
+    assert_eq!(
+        complete(
+            "FROM city.csv TOPN 3 BY CountryCode ORDER CityP",
+            47,
+            &datasets
+        ),
+        (42, vec!["CityPop".to_string()])
+    );
+    // The columns of joined datasets are included, once each.
+    assert_eq!(
+        complete(
+            "FROM city.csv JOIN country.csv CountryCode COUNTBY C",
+            52,
+            &datasets
+        ),
+        (
+            51,
+            vec![
+                "CityID".to_string(),
+                "CityName".to_string(),
+                "CountryCode".to_string(),
+                "CityPop".to_string(),
+                "CountryName".to_string(),
+                "Continent".to_string(),
+                "CountryPop".to_string(),
+                "Capital".to_string()
+            ]
+        )
+    );
+    assert_eq!(
+        complete("FROM country GROUPBY Continent SUM(CountryP", 43, &datasets),
+        (35, vec!["CountryPop".to_string()])
+    );
+    // The columns of CSV files are read from their header row.
+    datasets.register("cities", "data/city.csv").unwrap();
+    assert_eq!(
+        complete("FROM cities SELECT CityP", 24, &datasets),
+        (19, vec!["CityPop".to_string()])
+    );
+    // Without a dataset earlier in the line, no columns are known.
+    assert_eq!(complete("SELECT Ci", 9, &datasets), (7, vec![]));
+}
@@ -1,295 +1,1296 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt::Display;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
 
-use serde::Deserialize;
+use encoding_rs::{Encoding, UTF_8};
+use flate2::read::GzDecoder;
+use icu_normalizer::ComposingNormalizerBorrowed;
+use parquet::errors::ParquetError;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::Field;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
 
-use crate::table::{Cell, Row};
+use crate::table::{Cell, Row, Table};
 
-/// In-memory representation of each record in the `country.csv` dataset.
-/// This is represented as a struct so we can use the [`serde`] and [`csv`] crates to generate
-/// the deserialization code.
-///
-/// Example record:
-/// CountryCode, CountryName, Continent,        CountryPop, Capital
-/// ABW,         Aruba,       North_America,    103000,     129
-#[derive(Clone, Debug, Deserialize, PartialEq)]
-// This is necessary as the header in the dataset (`country.csv`) is in CamelCase. `serde` and `csv`
-// rely on these names being the same as those in the header row in the dataset.
-#[allow(non_snake_case)]
-pub struct Country {
-    /// "ABW" in the example above.
-    pub CountryCode: String,
-    /// "Aruba" in the example above.
-    pub CountryName: String,
-    /// "North_America" in the example above.
-    pub Continent: String,
-    /// 103000 in the example above.
-    pub CountryPop: i64,
-    /// 129 in the example above.
-    pub Capital: Option<i64>,
-}
-
-impl Country {
-    /// Returns the names of the columns in the City dataset.
-    pub fn column_names() -> Vec<String> {
-        vec![
-            "CountryCode".to_string(),
-            "CountryName".to_string(),
-            "Continent".to_string(),
-            "CountryPop".to_string(),
-            "Capital".to_string(),
-        ]
+/// The types a column of an arbitrary CSV or Parquet file can be inferred to have. See
+/// [`load_csv`] and [`load_parquet`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    /// Every value is an integer. Loaded as [`Cell::Int64`].
+    Int64,
+    /// Every value is an integer or empty. Loaded as [`Cell::OptInt64`].
+    OptInt64,
+    /// Every value is a number, and at least one is a decimal number. Loaded as [`Cell::Float64`].
+    Float64,
+    /// Every value is a number or empty, and at least one is a decimal number. Loaded as
+    /// [`Cell::Float64`], with [`Cell::Null`] for the empty values.
+    OptFloat64,
+    /// Anything else. Loaded as [`Cell::String`].
+    String,
+}
+
+/// Helper function to parse a decimal number, e.g. `3.75`. Unlike [`str::parse`], words such as
+/// `inf` or `NaN` aren't numbers.
+pub(crate) fn parse_decimal(value: &str) -> Option<f64> {
+    if value.chars().any(|c| c.is_ascii_digit()) {
+        str::parse::<f64>(value).ok().filter(|val| val.is_finite())
+    } else {
+        None
+    }
+}
+
+/// Helper function to infer the [`ColumnType`] of a column from its `values`. A column without any
+/// non-empty values is a string column.
+fn infer_column_type<'a, I: Iterator<Item = &'a str>>(values: I) -> ColumnType {
+    let mut has_integers = false;
+    let mut has_decimals = false;
+    let mut has_empty_values = false;
+    for value in values {
+        if value.is_empty() {
+            has_empty_values = true;
+        } else if str::parse::<i64>(value).is_ok() {
+            has_integers = true;
+        } else if parse_decimal(value).is_some() {
+            has_decimals = true;
+        } else {
+            return ColumnType::String;
+        }
+    }
+    match (has_integers, has_decimals, has_empty_values) {
+        (false, false, _) => ColumnType::String,
+        (true, false, false) => ColumnType::Int64,
+        (true, false, true) => ColumnType::OptInt64,
+        (_, true, false) => ColumnType::Float64,
+        (_, true, true) => ColumnType::OptFloat64,
     }
+}
+
+#[test]
+fn test_infer_column_type() {
+    assert_eq!(
+        infer_column_type(["1", "-2", "3"].into_iter()),
+        ColumnType::Int64
+    );
+    assert_eq!(
+        infer_column_type(["1", "", "3"].into_iter()),
+        ColumnType::OptInt64
+    );
+    assert_eq!(
+        infer_column_type(["", "1"].into_iter()),
+        ColumnType::OptInt64
+    );
+    assert_eq!(
+        infer_column_type(["1", "two", "3"].into_iter()),
+        ColumnType::String
+    );
+    assert_eq!(
+        infer_column_type(["1", "2.5", "-0.75"].into_iter()),
+        ColumnType::Float64
+    );
+    assert_eq!(
+        infer_column_type(["1.5", ""].into_iter()),
+        ColumnType::OptFloat64
+    );
+    assert_eq!(
+        infer_column_type(["1.5", "inf", "NaN"].into_iter()),
+        ColumnType::String
+    );
+    assert_eq!(infer_column_type(["", ""].into_iter()), ColumnType::String);
+    assert_eq!(infer_column_type([].into_iter()), ColumnType::String);
+}
+
+/// How rows of a CSV file holding fewer or more values than its header names are handled while
+/// loading it. Selected with `\badrows`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BadRows {
+    /// Fail to load the file.
+    #[default]
+    Error,
+    /// Leave the rows out of the loaded table.
+    Skip,
+    /// Keep the rows, with their missing values missing and their extra values dropped.
+    Null,
+}
 
-    /// Returns the names of only those columns whose values are numeric.
-    pub fn numeric_columns() -> Vec<String> {
-        vec!["CountryPop".to_string()]
+impl BadRows {
+    /// Looks up a way of handling bad rows by the name used to select it, e.g. "skip".
+    pub fn from_name(name: &str) -> Option<BadRows> {
+        match name {
+            "error" => Some(BadRows::Error),
+            "skip" => Some(BadRows::Skip),
+            "null" => Some(BadRows::Null),
+            _ => None,
+        }
     }
 }
 
-impl Display for Country {
+impl Display for BadRows {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let capital = if self.Capital.is_some() {
-            self.Capital.unwrap().to_string()
-        } else {
-            String::new()
-        };
-        f.write_fmt(format_args!(
-            "{},{},{},{},{}",
-            self.CountryCode, self.CountryName, self.Continent, self.CountryPop, capital,
-        ))
+        match self {
+            BadRows::Error => f.write_str("error"),
+            BadRows::Skip => f.write_str("skip"),
+            BadRows::Null => f.write_str("null"),
+        }
     }
 }
 
-/// Trait to make it easy to convert the Country struct in a [`Row`].
-impl Into<Row> for Country {
-    fn into(self) -> Row {
-        Row {
-            cells: vec![
-                Cell::String(self.CountryCode),
-                Cell::String(self.CountryName),
-                Cell::String(self.Continent),
-                Cell::Int64(self.CountryPop),
-                Cell::OptInt64(self.Capital),
-            ],
+/// How the values of a CSV file are cleaned up while loading it, before the types of its columns
+/// are inferred, so that values written differently compare equal, e.g. when FILTER compares them,
+/// JOIN matches them, or COUNTBY groups them. Selected with `\normalize`. Nothing is changed by
+/// default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Normalization {
+    /// Remove the whitespace around the values, e.g. ` 42 ` becomes `42`, which is a number.
+    pub trim: bool,
+    /// Replace the underscores with spaces, e.g. `North_America` becomes `North America`.
+    pub underscores: bool,
+    /// Convert the values to the Unicode Normalization Form C, so that e.g. an `e` followed by a
+    /// combining acute accent becomes the single character `é`.
+    pub nfc: bool,
+}
+
+impl Normalization {
+    /// Looks up the steps of a normalization by the names used to select them, i.e., any of
+    /// "trim", "underscores", and "nfc", each name possibly holding several separated by commas.
+    /// "off" alone selects none.
+    ///
+    /// # Returns
+    /// The normalization, or `None` if a name is unknown or no name is given.
+    pub fn from_names(names: &[&str]) -> Option<Normalization> {
+        if names == ["off"] {
+            return Some(Normalization::default());
+        }
+        let mut normalization = Normalization::default();
+        for name in names.iter().flat_map(|names| names.split(',')) {
+            match name {
+                "trim" => normalization.trim = true,
+                "underscores" => normalization.underscores = true,
+                "nfc" => normalization.nfc = true,
+                _ => return None,
+            }
         }
+        (normalization != Normalization::default()).then_some(normalization)
+    }
+
+    /// Normalizes a single `value`.
+    ///
+    /// # Returns
+    /// The normalized value, borrowed from `value` if it is unchanged or only trimmed.
+    pub fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        let mut value = Cow::Borrowed(match self.trim {
+            true => value.trim(),
+            false => value,
+        });
+        if self.underscores && value.contains('_') {
+            value = Cow::Owned(value.replace('_', " "));
+        }
+        if self.nfc {
+            if let Cow::Owned(composed) = ComposingNormalizerBorrowed::new_nfc().normalize(&value) {
+                value = Cow::Owned(composed);
+            }
+        }
+        value
     }
 }
 
-/// Helper function to deserialize the `country.csv` dataset.
-///
-/// Returns
-/// A vector of all the rows in the dataset represented as a [`Country`], or
-/// an error propagated from the csv and serde deserialization code.
-pub fn load_countries() -> Result<Vec<Country>, Box<dyn Error>> {
-    let mut countries: Vec<Country> = Vec::new();
-    let mut csv_reader = csv::Reader::from_path("data/country.csv")?;
-    for record in csv_reader.deserialize() {
-        let country: Country = record?;
-        countries.push(country);
+impl Display for Normalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = [
+            (self.trim, "trim"),
+            (self.underscores, "underscores"),
+            (self.nfc, "nfc"),
+        ]
+        .into_iter()
+        .filter_map(|(selected, name)| selected.then_some(name))
+        .collect();
+        match names.is_empty() {
+            true => f.write_str("off"),
+            false => f.write_str(&names.join(",")),
+        }
     }
-    Ok(countries)
 }
 
 #[test]
-fn test_load_countries() {
-    let countries = load_countries();
-    assert!(countries.is_ok());
-    let countries = countries.unwrap();
-    assert!(countries.len() > 0);
-    let first = countries.first().unwrap().to_owned();
-    assert_eq!(
-        first,
-        Country {
-            CountryCode: "ABW".to_string(),
-            CountryName: "Aruba".to_string(),
-            Continent: "North_America".to_string(),
-            CountryPop: 103000,
-            Capital: Some(129),
-        }
+fn test_normalization() {
+    assert_eq!(
+        Normalization::from_names(&["trim,nfc"]),
+        Some(Normalization {
+            trim: true,
+            underscores: false,
+            nfc: true,
+        })
+    );
+    let all = Normalization::from_names(&["trim", "underscores,nfc"]).unwrap();
+    assert_eq!(all.to_string(), "trim,underscores,nfc");
+    assert_eq!(
+        Normalization::from_names(&["off"]),
+        Some(Normalization::default())
     );
+    assert_eq!(Normalization::default().to_string(), "off");
+    assert_eq!(Normalization::from_names(&["trim", "off"]), None);
+    assert_eq!(Normalization::from_names(&["lowercase"]), None);
+    assert_eq!(Normalization::from_names(&[]), None);
+
+    assert_eq!(all.normalize("  North_America "), "North America");
+    assert_eq!(all.normalize("Cafe\u{301}"), "Caf\u{e9}");
+    assert!(matches!(all.normalize(" Aruba "), Cow::Borrowed("Aruba")));
+    assert_eq!(Normalization::default().normalize(" A_b "), " A_b ");
 }
 
-/// In-memory representation of each record in the `city.csv` dataset.
-/// This is represented as a struct so we can use the [`serde`] and [`csv`] crates to generate
-/// the deserialization code.
-///
-/// Example record:
-/// CityID, CityName,   CountryCode,    CityPop
-/// 1,      Kabul,      AFG,            1780000
-#[derive(Clone, Debug, Deserialize, PartialEq)]
-// This is necessary as the header row in the dataset (`city.csv`) is in CamelCase. `serde` and
-// `csv` rely on these names being the same as those in the header row in the dataset.
-#[allow(non_snake_case)]
-pub struct City {
-    /// 1 in the example above.
-    pub CityID: i64,
-    /// "Kabul" in the example above.
-    pub CityName: String,
-    /// "AFG" in the example above.
-    pub CountryCode: String,
-    /// 1780000 in the example above.
-    pub CityPop: i64,
-}
-
-impl Display for City {
+/// The number of line numbers listed by a [`BadRowsReport`] before the rest are left out.
+const C_REPORTED_LINES: usize = 10;
+
+/// The bad rows met while loading a dataset, which were skipped or filled in as requested by
+/// [`BadRows`], to report them to the user after the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BadRowsReport {
+    /// The dataset the rows belong to.
+    pub dataset: Dataset,
+    /// How the rows were handled; never [`BadRows::Error`].
+    pub handling: BadRows,
+    /// The line numbers of the rows in the file, counting the header as line 1.
+    pub lines: Vec<u64>,
+}
+
+impl Display for BadRowsReport {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut lines: Vec<String> = self
+            .lines
+            .iter()
+            .take(C_REPORTED_LINES)
+            .map(u64::to_string)
+            .collect();
+        if self.lines.len() > C_REPORTED_LINES {
+            lines.push("...".to_string());
+        }
+        let (rows, numbers) = match self.lines.len() {
+            1 => ("row", "number"),
+            _ => ("rows", "numbers"),
+        };
         f.write_fmt(format_args!(
-            "{},{},{},{}",
-            self.CityID, self.CityName, self.CountryCode, self.CityPop,
+            "{} {} {} (line {} {}) in {}.",
+            self.lines.len(),
+            rows,
+            match self.handling {
+                BadRows::Skip => "skipped",
+                _ => "filled with missing values",
+            },
+            numbers,
+            lines.join(", "),
+            self.dataset
         ))
     }
 }
 
-/// Trait to make it easy to convert the [`City`] struct in a [`Row`].
-impl Into<Row> for City {
-    fn into(self) -> Row {
-        Row {
-            cells: vec![
-                Cell::Int64(self.CityID),
-                Cell::String(self.CityName),
-                Cell::String(self.CountryCode),
-                Cell::Int64(self.CityPop),
-            ],
+#[test]
+fn test_bad_rows_report() {
+    let report = BadRowsReport {
+        dataset: Dataset::Csv("trips.csv".to_string()),
+        handling: BadRows::Skip,
+        lines: vec![3, 7],
+    };
+    assert_eq!(
+        report.to_string(),
+        "2 rows skipped (line numbers 3, 7) in trips.csv."
+    );
+    let report = BadRowsReport {
+        lines: vec![3],
+        ..report
+    };
+    assert_eq!(
+        report.to_string(),
+        "1 row skipped (line number 3) in trips.csv."
+    );
+    let report = BadRowsReport {
+        handling: BadRows::Null,
+        lines: (2..14).collect(),
+        ..report
+    };
+    assert_eq!(
+        report.to_string(),
+        "12 rows filled with missing values (line numbers 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, ...) in trips.csv."
+    );
+    assert_eq!(BadRows::from_name("null"), Some(BadRows::Null));
+    assert_eq!(BadRows::from_name("fill"), None);
+}
+
+/// Loads an arbitrary CSV file into a [`Table`]. The names of the columns are read from the
+/// header row, and the type of each column is inferred from its values: columns containing only
+/// integers or decimal numbers are numeric, columns that also contain empty values hold optional
+/// numbers (like the `Capital` column of `country.csv`), and all other columns hold strings.
+/// Files compressed with gzip, e.g. `trips.csv.gz`, are decompressed as they are read.
+///
+/// # Arguments
+/// `path` : The path of the CSV file, which must be UTF-8 text. See [`load_csv_with_encoding`]
+/// for files in other encodings.
+///
+/// # Returns
+/// The loaded [`Table`], or an error propagated from the [`csv`] crate if the file can't be read
+/// or is malformed.
+pub fn load_csv(path: &str) -> Result<Table, Box<dyn Error>> {
+    load_csv_with_encoding(path, UTF_8)
+}
+
+/// Loads an arbitrary CSV file in the given text `encoding` into a [`Table`], like [`load_csv`].
+/// A byte order mark at the start of the file takes precedence over the `encoding`.
+///
+/// # Arguments
+/// `path` : The path of the CSV file.
+/// `encoding` : The encoding the file is written in, e.g. windows-1252 for a file saved by an
+/// older spreadsheet. See [`parse_encoding`].
+///
+/// # Returns
+/// The loaded [`Table`], an error if the file isn't valid text in the `encoding`, or an error
+/// propagated from the [`csv`] crate if the file can't be read or is malformed.
+pub fn load_csv_with_encoding(
+    path: &str,
+    encoding: &'static Encoding,
+) -> Result<Table, Box<dyn Error>> {
+    load_csv_with_options(path, encoding, BadRows::Error, Normalization::default())
+        .map(|(table, _)| table)
+}
+
+/// Loads an arbitrary CSV file in the given text `encoding` into a [`Table`], like
+/// [`load_csv_with_encoding`], handling its bad rows as requested.
+///
+/// # Arguments
+/// `path` : The path of the CSV file.
+/// `encoding` : The encoding the file is written in.
+/// `bad_rows` : How rows holding fewer or more values than the header names are handled. The
+/// values missing from a row filled in with [`BadRows::Null`] are missing, whatever the type of
+/// their column.
+/// `normalization` : How the values are cleaned up before the types of the columns are inferred.
+/// The names of the columns are left as they are.
+///
+/// # Returns
+/// The loaded [`Table`] and the line numbers of the bad rows that were skipped or filled in, or
+/// the same errors as [`load_csv_with_encoding`].
+pub fn load_csv_with_options(
+    path: &str,
+    encoding: &'static Encoding,
+    bad_rows: BadRows,
+    normalization: Normalization,
+) -> Result<(Table, Vec<u64>), Box<dyn Error>> {
+    let bytes = read_csv_file(path)?;
+    // Valid UTF-8 is borrowed as is, so the common case doesn't copy the file.
+    let (text, encoding, malformed) = encoding.decode(&bytes);
+    if malformed {
+        return Err(format!(
+            "{} is not valid {} text. Use --encoding to read it in another encoding.",
+            path,
+            encoding.name()
+        )
+        .into());
+    }
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .flexible(bad_rows != BadRows::Error)
+        .from_reader(text.as_bytes());
+    let header: Vec<String> = csv_reader.headers()?.iter().map(String::from).collect();
+    let mut records = Vec::new();
+    let mut bad_lines = Vec::new();
+    for record in csv_reader.records() {
+        let record = record.map_err(|error| -> Box<dyn Error> {
+            match error.kind() {
+                csv::ErrorKind::UnequalLengths { .. } => format!(
+                    "{}. Use '\\badrows skip' or '\\badrows null' to load the other rows.",
+                    error
+                )
+                .into(),
+                _ => error.into(),
+            }
+        })?;
+        if record.len() != header.len() {
+            bad_lines.push(record.position().map_or(0, |position| position.line()));
+            if bad_rows == BadRows::Skip {
+                continue;
+            }
+        }
+        match normalization == Normalization::default() {
+            true => records.push(record),
+            false => records.push(
+                record
+                    .iter()
+                    .map(|value| normalization.normalize(value))
+                    .collect(),
+            ),
         }
     }
+
+    let column_types: Vec<ColumnType> = (0..header.len())
+        .map(|index| {
+            infer_column_type(
+                records
+                    .iter()
+                    .map(|record| record.get(index).unwrap_or_default()),
+            )
+        })
+        .collect();
+    let rows = records
+        .iter()
+        .map(|record| Row {
+            cells: column_types
+                .iter()
+                .enumerate()
+                .map(
+                    |(index, column_type)| match (record.get(index), column_type) {
+                        // The values missing from a bad row are missing, whatever their column.
+                        (None, ColumnType::OptInt64) => Cell::OptInt64(None),
+                        (None, _) => Cell::Null,
+                        (Some(value), ColumnType::Int64) => {
+                            Cell::Int64(str::parse::<i64>(value).unwrap())
+                        }
+                        (Some(value), ColumnType::OptInt64) => {
+                            Cell::OptInt64(str::parse::<i64>(value).ok())
+                        }
+                        (Some(value), ColumnType::Float64) => {
+                            Cell::Float64(parse_decimal(value).unwrap())
+                        }
+                        (Some(value), ColumnType::OptFloat64) => {
+                            parse_decimal(value).map_or(Cell::Null, Cell::Float64)
+                        }
+                        (Some(value), ColumnType::String) => Cell::String(value.to_string()),
+                    },
+                )
+                .collect(),
+        })
+        .collect();
+    // Like the `Capital` column of `country.csv`, columns with missing values are nullable, and
+    // so aren't numeric.
+    Ok((Table::new(header, rows), bad_lines))
 }
 
-impl City {
-    /// Returns the names of the columns in the City dataset.
-    pub fn column_names() -> Vec<String> {
+#[test]
+fn test_load_csv() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv.csv");
+    std::fs::write(
+        &path,
+        "Name,Population,Capital,Code\nAruba,103000,129,1\nAntarctica,0,,A2\n",
+    )
+    .unwrap();
+    let table = load_csv(&path.to_string_lossy()).unwrap();
+    assert_eq!(table.header, vec!["Name", "Population", "Capital", "Code"]);
+    assert_eq!(table.numeric_columns(), vec!["Population"]);
+    assert_eq!(
+        table.rows[1].cells,
         vec![
-            "CityID".to_string(),
-            "CityName".to_string(),
-            "CountryCode".to_string(),
-            "CityPop".to_string(),
+            Cell::String("Antarctica".to_string()),
+            Cell::Int64(0),
+            Cell::OptInt64(None),
+            Cell::String("A2".to_string()),
         ]
-    }
+    );
+}
 
-    /// Returns the names of only those columns whose values are numeric.
-    pub fn numeric_columns() -> Vec<String> {
-        vec!["CityID".to_string(), "CityPop".to_string()]
+#[test]
+fn test_load_csv_with_options() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-bad-rows.csv");
+    std::fs::write(
+        &path,
+        "Name,Pop,Code\nAruba,103000,ABW\nAnguilla\nAngola,12878000,AGO,x\n",
+    )
+    .unwrap();
+    let path = path.to_string_lossy();
+    let error =
+        load_csv_with_options(&path, UTF_8, BadRows::Error, Normalization::default()).unwrap_err();
+    assert_eq!(error.to_string(), "CSV error: record 2 (line: 3, byte: 31): found record with 1 fields, but the previous record has 3 fields. Use '\\badrows skip' or '\\badrows null' to load the other rows.");
+
+    let (table, lines) =
+        load_csv_with_options(&path, UTF_8, BadRows::Skip, Normalization::default()).unwrap();
+    assert_eq!(lines, vec![3, 4]);
+    assert_eq!(table.rows.len(), 1);
+    assert_eq!(table.numeric_columns(), vec!["Pop"]);
+
+    let (table, lines) =
+        load_csv_with_options(&path, UTF_8, BadRows::Null, Normalization::default()).unwrap();
+    assert_eq!(lines, vec![3, 4]);
+    assert_eq!(
+        table.rows[1].cells,
+        vec![
+            Cell::String("Anguilla".to_string()),
+            Cell::OptInt64(None),
+            Cell::Null,
+        ]
+    );
+    assert_eq!(table.rows[2].join(), "Angola,12878000,AGO");
+    // The missing values make the column hold optional numbers.
+    assert!(table.numeric_columns().is_empty());
+}
+
+#[test]
+fn test_load_csv_normalized() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-normalized.csv");
+    std::fs::write(
+        &path,
+        "Name,Pop,Continent\n Aruba ,103000,North_America\nAnguilla, 8000 ,North America\n",
+    )
+    .unwrap();
+    let path = path.to_string_lossy();
+    let (table, _) =
+        load_csv_with_options(&path, UTF_8, BadRows::Error, Normalization::default()).unwrap();
+    assert!(table.numeric_columns().is_empty());
+
+    let normalization = Normalization::from_names(&["trim,underscores"]).unwrap();
+    let (table, _) = load_csv_with_options(&path, UTF_8, BadRows::Error, normalization).unwrap();
+    // The values are trimmed before the types are inferred, so the column is numeric.
+    assert_eq!(table.numeric_columns(), vec!["Pop"]);
+    assert_eq!(table.rows[0].join(), "Aruba,103000,North America");
+    assert_eq!(table.rows[1].join(), "Anguilla,8000,North America");
+}
+
+#[test]
+fn test_load_csv_decimals() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-decimals.csv");
+    std::fs::write(
+        &path,
+        "Country,GdpPerCapita,Growth\nNLD,57025.5,1.2\nAFG,364,\n",
+    )
+    .unwrap();
+    let table = load_csv(&path.to_string_lossy()).unwrap();
+    assert_eq!(table.numeric_columns(), vec!["GdpPerCapita"]);
+    assert_eq!(
+        table.rows[1].cells,
+        vec![
+            Cell::String("AFG".to_string()),
+            Cell::Float64(364.0),
+            Cell::Null,
+        ]
+    );
+}
+
+#[test]
+fn test_load_csv_with_encoding() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-latin1.csv");
+    // "São_Paulo" and "Zürich", with the accented letters written as single latin1 bytes.
+    std::fs::write(&path, b"City,Code\nS\xe3o_Paulo,BRA\nZ\xfcrich,CHE\n").unwrap();
+    let path = path.to_string_lossy();
+    let latin1 = parse_encoding("latin1").unwrap();
+    let table = load_csv_with_encoding(&path, latin1).unwrap();
+    assert_eq!(table.rows[0].join(), "São_Paulo,BRA");
+    assert_eq!(table.rows[1].join(), "Zürich,CHE");
+    assert_eq!(
+        load_csv(&path).unwrap_err().to_string(),
+        format!(
+            "{} is not valid UTF-8 text. Use --encoding to read it in another encoding.",
+            path
+        )
+    );
+
+    // UTF-8 files are read as is, whatever the encoding, as long as they start with a BOM.
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-bom.csv");
+    std::fs::write(&path, "\u{feff}City,Code\nZürich,CHE\n").unwrap();
+    let table = load_csv_with_encoding(&path.to_string_lossy(), latin1).unwrap();
+    assert_eq!(table.header, vec!["City", "Code"]);
+    assert_eq!(table.rows[0].join(), "Zürich,CHE");
+}
+
+#[test]
+fn test_load_csv_malformed() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-malformed.csv");
+    std::fs::write(&path, "Name,Population\nAruba,103000,129\n").unwrap();
+    assert!(load_csv(&path.to_string_lossy()).is_err());
+    assert!(load_csv("no/such/file.csv").is_err());
+}
+
+#[test]
+fn test_load_csv_builtin_datasets() {
+    // The types inferred for the columns of the built-in datasets match their known schemas.
+    for dataset in Dataset::all() {
+        let table = load_csv(dataset.path().unwrap()).unwrap();
+        assert_eq!(table.header, dataset.column_names());
+        assert_eq!(table.numeric_columns(), dataset.numeric_columns());
+        let sortable: Vec<String> = table
+            .schema
+            .iter()
+            .filter(|column| column.is_sortable())
+            .map(|column| column.name.clone())
+            .collect();
+        assert_eq!(sortable, dataset.sortable_columns());
     }
+
+    let cities = load_csv(Dataset::City.path().unwrap()).unwrap();
+    assert_eq!(
+        cities.rows[0].cells,
+        vec![
+            Cell::Int64(1),
+            Cell::String("Kabul".to_string()),
+            Cell::String("AFG".to_string()),
+            Cell::Int64(1780000),
+        ]
+    );
+    let countries = load_csv(Dataset::Country.path().unwrap()).unwrap();
+    assert_eq!(
+        countries.rows[0].cells,
+        vec![
+            Cell::String("ABW".to_string()),
+            Cell::String("Aruba".to_string()),
+            Cell::String("North_America".to_string()),
+            Cell::Int64(103000),
+            Cell::OptInt64(Some(129)),
+        ]
+    );
+    assert!(countries
+        .rows
+        .iter()
+        .any(|row| row.cells[4] == Cell::OptInt64(None)));
+    let languages = load_csv(Dataset::Language.path().unwrap()).unwrap();
+    assert_eq!(
+        languages.rows[0].cells,
+        vec![
+            Cell::String("ABW".to_string()),
+            Cell::String("Dutch".to_string()),
+        ]
+    );
 }
 
-/// Helper function to deserialize the `city.csv` dataset.
+/// Looks up the text encoding with the given `label`, e.g. `utf-8`, `latin1`, or `shift_jis`,
+/// ignoring case. The labels are the ones web browsers understand, so `latin1` and `iso-8859-1`
+/// name windows-1252, the superset of latin1 that files labelled latin1 are usually written in.
 ///
-/// Returns
-/// A vector of all the rows in the dataset represented as a [`City`], or
-/// an error propagated from the csv and serde deserialization code.
-pub fn load_cities() -> Result<Vec<City>, Box<dyn Error>> {
-    let mut cities: Vec<City> = Vec::new();
-    let mut csv_reader = csv::Reader::from_path("data/city.csv")?;
-    for record in csv_reader.deserialize() {
-        let city: City = record?;
-        cities.push(city);
-    }
-    Ok(cities)
+/// # Returns
+/// The encoding, or an error message if no encoding has that `label`.
+pub fn parse_encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.as_bytes()).ok_or(format!(
+        "Unknown encoding: {}. Use a label such as utf-8, latin1, or windows-1252.",
+        label
+    ))
 }
 
 #[test]
-fn test_load_cities() {
-    let cities = load_cities();
-    assert!(cities.is_ok());
-    let cities = cities.unwrap();
-    assert!(cities.len() > 0);
-    let first = cities.first().unwrap().to_owned();
-    assert_eq!(
-        first,
-        City {
-            CityID: 1,
-            CityName: "Kabul".to_string(),
-            CountryCode: "AFG".to_string(),
-            CityPop: 1780000,
-        }
+fn test_parse_encoding() {
+    assert_eq!(parse_encoding("UTF-8"), Ok(UTF_8));
+    assert_eq!(parse_encoding("latin1").unwrap().name(), "windows-1252");
+    assert_eq!(parse_encoding("Shift_JIS").unwrap().name(), "Shift_JIS");
+    assert_eq!(
+        parse_encoding("klingon"),
+        Err(
+            "Unknown encoding: klingon. Use a label such as utf-8, latin1, or windows-1252."
+                .to_string()
+        )
     );
 }
 
-/// In-memory representation of each record in the `city.csv` dataset.
-/// This is represented as a struct so we can use the [`serde`] and [`csv`] crates to generate
-/// the deserialization code.
-///
-/// Example record:
-/// CountryCode,    Language
-/// ABW,            Dutch
-#[derive(Clone, Debug, Deserialize, PartialEq)]
-// This is necessary as the header row in the dataset (`city.csv`) is in CamelCase. `serde` and
-// `csv` rely on these names being the same as those in the header row in the dataset.
-#[allow(non_snake_case)]
-pub struct Language {
-    /// "ABW" in the example above.
-    pub CountryCode: String,
-    /// "Dutch" in the example above.
-    pub Language: String,
-}
-
-impl Display for Language {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{},{}", self.CountryCode, self.Language,))
-    }
+/// Helper function to convert a `field` read from a Parquet file into a [`Cell`], or `None` if the
+/// value is missing. Integers and floating point numbers stay numbers; every other value, e.g. a
+/// date or a boolean, is converted to its text representation.
+fn parquet_field_to_cell(field: &Field) -> Option<Cell> {
+    Some(match field {
+        Field::Null => return None,
+        Field::Byte(value) => Cell::Int64(i64::from(*value)),
+        Field::Short(value) => Cell::Int64(i64::from(*value)),
+        Field::Int(value) => Cell::Int64(i64::from(*value)),
+        Field::Long(value) => Cell::Int64(*value),
+        Field::UByte(value) => Cell::Int64(i64::from(*value)),
+        Field::UShort(value) => Cell::Int64(i64::from(*value)),
+        Field::UInt(value) => Cell::Int64(i64::from(*value)),
+        Field::ULong(value) => match i64::try_from(*value) {
+            Ok(value) => Cell::Int64(value),
+            Err(_) => Cell::Float64(*value as f64),
+        },
+        Field::Float16(value) => Cell::Float64(f64::from(*value)),
+        Field::Float(value) => Cell::Float64(f64::from(*value)),
+        Field::Double(value) => Cell::Float64(*value),
+        Field::Decimal(_) => parse_decimal(&field.to_string())
+            .map_or_else(|| Cell::String(field.to_string()), Cell::Float64),
+        Field::Str(value) => Cell::String(value.clone()),
+        _ => Cell::String(field.to_string()),
+    })
 }
 
-impl Language {
-    /// Returns the names of the columns in the Language dataset.
-    pub fn column_names() -> Vec<String> {
-        vec!["CountryCode".to_string(), "Language".to_string()]
+/// Helper function to infer the [`ColumnType`] of a column of a Parquet file from its `values`,
+/// like [`infer_column_type`] does for a column of a CSV file.
+fn infer_parquet_column_type<'a, I: Iterator<Item = &'a Option<Cell>>>(values: I) -> ColumnType {
+    let mut has_integers = false;
+    let mut has_decimals = false;
+    let mut has_empty_values = false;
+    for value in values {
+        match value {
+            None => has_empty_values = true,
+            Some(Cell::Int64(_)) => has_integers = true,
+            Some(Cell::Float64(_)) => has_decimals = true,
+            Some(_) => return ColumnType::String,
+        }
     }
-
-    /// Returns the names of only those columns whose values are numeric.
-    pub fn numeric_columns() -> Vec<String> {
-        vec![]
+    match (has_integers, has_decimals, has_empty_values) {
+        (false, false, _) => ColumnType::String,
+        (true, false, false) => ColumnType::Int64,
+        (true, false, true) => ColumnType::OptInt64,
+        (_, true, false) => ColumnType::Float64,
+        (_, true, true) => ColumnType::OptFloat64,
     }
 }
 
-/// Trait to make it easy to convert the [`Language`] struct in a [`Row`].
-impl Into<Row> for Language {
-    fn into(self) -> Row {
-        Row {
-            cells: vec![Cell::String(self.CountryCode), Cell::String(self.Language)],
+/// Helper function to open the SQLite database at `path` for reading. Unlike
+/// [`Connection::open`], it doesn't create the database if it doesn't exist.
+fn open_sqlite(path: &str) -> rusqlite::Result<Connection> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+}
+
+/// Helper function to convert a `value` read from a SQLite table into a [`Cell`], or `None` if the
+/// value is NULL. Integers and real numbers stay numbers; text and blobs become strings.
+fn sqlite_value_to_cell(value: ValueRef) -> Option<Cell> {
+    Some(match value {
+        ValueRef::Null => return None,
+        ValueRef::Integer(value) => Cell::Int64(value),
+        ValueRef::Real(value) => Cell::Float64(value),
+        ValueRef::Text(text) | ValueRef::Blob(text) => {
+            Cell::String(String::from_utf8_lossy(text).to_string())
         }
-    }
+    })
 }
 
-/// Helper function to deserialize the `language.csv` dataset.
+/// Helper function to quote the name of a SQLite table, so that it can't be mistaken for SQL.
+fn quote_sqlite_name(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Lists the tables and views of the SQLite database at `path`, ordered by name, leaving out the
+/// internal tables of SQLite.
 ///
-/// Returns
-/// A vector of all the rows in the dataset represented as a [`Language`], or
-/// an error propagated from the csv and serde deserialization code.
-pub fn load_languages() -> Result<Vec<Language>, Box<dyn Error>> {
-    let mut languages: Vec<Language> = Vec::new();
-    let mut csv_reader = csv::Reader::from_path("data/language.csv")?;
-    for record in csv_reader.deserialize() {
-        let language: Language = record?;
-        languages.push(language);
+/// # Returns
+/// The names of the tables, or the error returned from [`rusqlite`] if the file isn't a SQLite
+/// database.
+pub fn sqlite_tables(path: &str) -> rusqlite::Result<Vec<String>> {
+    open_sqlite(path)?
+        .prepare(
+            "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') \
+             AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\' ORDER BY name",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect()
+}
+
+/// Returns the names of the columns of the `table` of the SQLite database at `path`, without
+/// reading its rows.
+pub fn sqlite_columns(path: &str, table: &str) -> rusqlite::Result<Vec<String>> {
+    let connection = open_sqlite(path)?;
+    let statement = connection.prepare(&format!("SELECT * FROM {}", quote_sqlite_name(table)))?;
+    Ok(statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Looks up the names of the columns of the `dataset` without loading it: the
+/// built-in datasets know theirs, CSV files name theirs in their header row, and SQLite databases
+/// in their schema. The columns of other datasets, e.g. Parquet files, are unknown until the query
+/// runs, so none are returned for them. Used to complete the names of columns and to list them in
+/// the help message.
+pub fn peek_columns(dataset: &Dataset) -> Vec<String> {
+    if dataset.is_builtin() {
+        return dataset.column_names();
     }
-    Ok(languages)
+    if let Dataset::Sqlite { path, table } = dataset {
+        return sqlite_columns(path, table).unwrap_or_default();
+    }
+    match dataset.path() {
+        Some(path) if !is_parquet_file(path) => open_csv_file(path)
+            .map(csv::Reader::from_reader)
+            .and_then(|mut reader| Ok(reader.headers().cloned()?))
+            .map(|headers| headers.iter().map(str::to_string).collect())
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+/// Loads the `table` of the SQLite database at `path` into a [`Table`]. Like in [`load_parquet`],
+/// columns of INTEGER and REAL values are numeric, columns with NULL values hold optional numbers,
+/// and all other columns hold strings, with [`Cell::Null`] for the NULL values.
+///
+/// # Arguments
+/// `path` : The path of the database file.
+/// `table` : The name of the table or view to load.
+///
+/// # Returns
+/// The loaded [`Table`], or the error returned from [`rusqlite`] if the database can't be read
+/// or has no such table.
+pub fn load_sqlite(path: &str, table: &str) -> Result<Table, Box<dyn Error>> {
+    let connection = open_sqlite(path)?;
+    let mut statement =
+        connection.prepare(&format!("SELECT * FROM {}", quote_sqlite_name(table)))?;
+    let header: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let records = statement
+        .query_map([], |row| {
+            (0..header.len())
+                .map(|index| row.get_ref(index).map(sqlite_value_to_cell))
+                .collect()
+        })?
+        .collect::<rusqlite::Result<Vec<Vec<Option<Cell>>>>>()?;
+    Ok(typed_table(header, records))
+}
+
+/// Helper function to write a SQLite database holding the table `planets`, with the columns
+/// `Planet` (text), `Moons` (integers), `Radius` (real numbers, with a NULL), and `Notes` (mixed
+/// values), and the view `big_planets`.
+#[cfg(test)]
+pub(crate) fn write_planets_sqlite(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    Connection::open(path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE planets (Planet TEXT, Moons INTEGER, Radius REAL, Notes);
+             INSERT INTO planets VALUES ('Mercury', 0, 2439.7, 'closest');
+             INSERT INTO planets VALUES ('Earth', 1, NULL, 3);
+             INSERT INTO planets VALUES ('Saturn', 146, 58232.0, NULL);
+             CREATE VIEW big_planets AS SELECT * FROM planets WHERE Moons > 0;",
+        )
+        .unwrap();
 }
 
 #[test]
-fn test_load_languages() {
-    let languages = load_languages();
-    assert!(languages.is_ok());
-    let languages = languages.unwrap();
-    assert!(languages.len() > 0);
-    let first = languages.first().unwrap().to_owned();
-    assert_eq!(
-        first,
-        Language {
-            CountryCode: "ABW".to_string(),
-            Language: "Dutch".to_string(),
-        }
+fn test_load_sqlite() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-sqlite.sqlite");
+    write_planets_sqlite(&path);
+    let path = path.to_string_lossy();
+    assert_eq!(
+        sqlite_tables(&path).unwrap(),
+        vec!["big_planets", "planets"]
+    );
+    assert_eq!(
+        sqlite_columns(&path, "planets").unwrap(),
+        vec!["Planet", "Moons", "Radius", "Notes"]
+    );
+
+    let table = load_sqlite(&path, "planets").unwrap();
+    assert_eq!(table.header, vec!["Planet", "Moons", "Radius", "Notes"]);
+    assert_eq!(table.numeric_columns(), vec!["Moons"]);
+    assert_eq!(
+        table.rows[0].cells,
+        vec![
+            Cell::String("Mercury".to_string()),
+            Cell::Int64(0),
+            Cell::Float64(2439.7),
+            Cell::String("closest".to_string()),
+        ]
+    );
+    // Missing numbers are missing, and the numbers in a column of strings are strings.
+    assert_eq!(
+        table.rows[1].cells,
+        vec![
+            Cell::String("Earth".to_string()),
+            Cell::Int64(1),
+            Cell::Null,
+            Cell::String("3".to_string()),
+        ]
+    );
+    assert_eq!(table.rows[2].cells[3], Cell::Null);
+    assert_eq!(load_sqlite(&path, "big_planets").unwrap().rows.len(), 2);
+
+    assert_eq!(
+        load_sqlite(&path, "moons").unwrap_err().to_string(),
+        "no such table: moons"
+    );
+    assert_eq!(
+        sqlite_tables("data/city.csv").unwrap_err().to_string(),
+        "file is not a database"
+    );
+}
+
+/// Returns `true` if `name` is an HTTP or HTTPS URL, e.g. `https://example.com/cities.csv`.
+pub fn is_url(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("http://") || name.starts_with("https://")
+}
+
+/// Downloads the file published at `url` into the temporary directory, so that it can be loaded
+/// like a local file. The downloaded file keeps the name the URL ends in, e.g. `cities.csv.gz`,
+/// so that [`is_gzip_file`] and [`is_parquet_file`] recognize it.
+///
+/// # Returns
+/// The path of the downloaded file, or the error returned from [`reqwest`] if the file can't be
+/// fetched, including for HTTP error statuses such as 404 Not Found.
+pub fn fetch_url(url: &str) -> Result<String, Box<dyn Error>> {
+    let response = reqwest::blocking::get(url)?.error_for_status()?;
+    let bytes = response.bytes()?;
+    let file_name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|url| url.rsplit('/').next())
+        .filter(|name| !name.is_empty() && !name.contains(':'))
+        .unwrap_or("download.csv");
+    // Different URLs may end in the same file name, so the name is prefixed with a hash of the URL.
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let path = std::env::temp_dir().join(format!(
+        "toy-query-engine-{:016x}-{}",
+        hasher.finish(),
+        file_name
+    ));
+    std::fs::write(&path, bytes)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Helper function for the tests to serve `body` from a local HTTP server, once per request.
+///
+/// # Returns
+/// The URL of `path` on the server, e.g. `http://127.0.0.1:4242/cities.csv`.
+#[cfg(test)]
+pub(crate) fn serve_once(path: &str, status: &'static str, body: &'static str) -> String {
+    use std::io::Write;
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/{}", listener.local_addr().unwrap(), path);
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0; 4096];
+        let _ = stream.read(&mut request);
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+    });
+    url
+}
+
+#[test]
+fn test_fetch_url() {
+    assert!(is_url("https://example.com/cities.csv"));
+    assert!(is_url("HTTP://example.com/cities.csv"));
+    assert!(!is_url("./data/city.csv"));
+
+    let url = serve_once("planets.csv?version=2", "200 OK", "Planet,Moons\nEarth,1\n");
+    let path = fetch_url(&url).unwrap();
+    assert!(path.ends_with("-planets.csv"), "{}", path);
+    let table = load_csv(&path).unwrap();
+    assert_eq!(table.header, vec!["Planet", "Moons"]);
+    assert_eq!(table.rows[0].join(), "Earth,1");
+
+    let url = serve_once("missing.csv", "404 Not Found", "");
+    assert_eq!(
+        fetch_url(&url).unwrap_err().to_string(),
+        format!("HTTP status client error (404 Not Found) for url ({})", url)
     );
 }
 
+/// Returns `true` if the file at `path` is compressed with gzip, judging by its `.gz` extension.
+pub fn is_gzip_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+}
+
+/// Returns `true` if `path` names a CSV file, i.e., it ends in `.csv` or, for a file compressed
+/// with gzip, `.csv.gz`.
+pub fn is_csv_file(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".csv") || path.ends_with(".csv.gz")
+}
+
+/// Opens the CSV file at `path` for reading, decompressing it if it is compressed with gzip.
+pub fn open_csv_file(path: &str) -> std::io::Result<Box<dyn Read>> {
+    let file = std::fs::File::open(path)?;
+    Ok(match is_gzip_file(path) {
+        true => Box::new(GzDecoder::new(file)),
+        false => Box::new(file),
+    })
+}
+
+/// Helper function to read the whole CSV file at `path`, decompressing it if it is compressed
+/// with gzip.
+fn read_csv_file(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    open_csv_file(path)?
+        .read_to_end(&mut bytes)
+        .map_err(|error| match is_gzip_file(path) {
+            true => format!("{} is not a valid gzip file: {}", path, error).into(),
+            false => Box::<dyn Error>::from(error),
+        })?;
+    Ok(bytes)
+}
+
+#[test]
+fn test_load_csv_gzip() {
+    use std::io::Write;
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-gzip.csv.gz");
+    let mut encoder = flate2::write::GzEncoder::new(
+        std::fs::File::create(&path).unwrap(),
+        flate2::Compression::default(),
+    );
+    encoder
+        .write_all(b"Name,Population\nAruba,103000\nAnguilla,8000\n")
+        .unwrap();
+    encoder.finish().unwrap();
+    let path = path.to_string_lossy();
+    let table = load_csv(&path).unwrap();
+    assert_eq!(table.header, vec!["Name", "Population"]);
+    assert_eq!(table.numeric_columns(), vec!["Population"]);
+    assert_eq!(table.rows[1].join(), "Anguilla,8000");
+
+    // A file that isn't compressed despite its extension can't be read.
+    let path = std::env::temp_dir().join("toy-query-engine-load-csv-not-gzip.csv.gz");
+    std::fs::write(&path, "Name,Population\nAruba,103000\n").unwrap();
+    let path = path.to_string_lossy();
+    assert_eq!(
+        load_csv(&path).unwrap_err().to_string(),
+        format!("{} is not a valid gzip file: invalid gzip header", path)
+    );
+}
+
+#[test]
+fn test_is_csv_file() {
+    assert!(is_csv_file("data/city.csv"));
+    assert!(is_csv_file("trips.csv.gz"));
+    assert!(is_csv_file("TRIPS.CSV.GZ"));
+    assert!(is_gzip_file("trips.csv.gz"));
+    assert!(!is_gzip_file("data/city.csv"));
+    assert!(!is_csv_file("trips.gz"));
+    assert!(!is_csv_file("planets.parquet"));
+}
+
+/// Returns `true` if the file at `path` is a Parquet file, judging by its `.parquet` extension.
+pub fn is_parquet_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("parquet"))
+}
+
+/// Loads a Parquet file into a [`Table`]. The names of the columns are read from the schema of
+/// the file. Integer and floating point columns are numeric; like in [`load_csv`], columns with
+/// missing values hold optional numbers, and all other columns (e.g. strings, booleans, and
+/// dates) hold strings, with [`Cell::Null`] for the missing values.
+///
+/// # Arguments
+/// `path` : The path of the Parquet file.
+///
+/// # Returns
+/// The loaded [`Table`], or an error propagated from the [`parquet`] crate if the file can't be
+/// read or is malformed.
+pub fn load_parquet(path: &str) -> Result<Table, Box<dyn Error>> {
+    let reader = SerializedFileReader::new(std::fs::File::open(path)?)?;
+    let header: Vec<String> = reader
+        .metadata()
+        .file_metadata()
+        .schema()
+        .get_fields()
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+    let records = reader
+        .get_row_iter(None)?
+        .map(|row| {
+            row.map(|row| {
+                row.get_column_iter()
+                    .map(|(_, field)| parquet_field_to_cell(field))
+                    .collect::<Vec<Option<Cell>>>()
+            })
+        })
+        .collect::<Result<Vec<Vec<Option<Cell>>>, ParquetError>>()?;
+
+    Ok(typed_table(header, records))
+}
+
+/// Helper function to build a [`Table`] from the `records` read from a Parquet file or a SQLite
+/// table, inferring the [`ColumnType`] of each column from its values. The numbers in a column
+/// that also holds strings, which SQLite allows, are converted to strings.
+pub(crate) fn typed_table(header: Vec<String>, records: Vec<Vec<Option<Cell>>>) -> Table {
+    let column_types: Vec<ColumnType> = (0..header.len())
+        .map(|index| infer_parquet_column_type(records.iter().map(|record| &record[index])))
+        .collect();
+    let rows = records
+        .into_iter()
+        .map(|record| Row {
+            cells: record
+                .into_iter()
+                .zip(column_types.iter())
+                .map(|(value, column_type)| match (column_type, value) {
+                    (ColumnType::OptInt64, Some(Cell::Int64(value))) => Cell::OptInt64(Some(value)),
+                    (ColumnType::OptInt64, _) => Cell::OptInt64(None),
+                    (ColumnType::Float64 | ColumnType::OptFloat64, Some(Cell::Int64(value))) => {
+                        Cell::Float64(value as f64)
+                    }
+                    (ColumnType::String, Some(cell @ (Cell::Int64(_) | Cell::Float64(_)))) => {
+                        Cell::String(cell.to_string())
+                    }
+                    (_, Some(cell)) => cell,
+                    (_, None) => Cell::Null,
+                })
+                .collect(),
+        })
+        .collect();
+    Table::new(header, rows)
+}
+
+/// Helper function to write a Parquet file with the columns `Planet` (a required string),
+/// `Moons` (a required integer), `Radius` (an optional double), and `Rings` (an optional
+/// integer), holding Mercury, Earth, and Saturn.
+#[cfg(test)]
+pub(crate) fn write_planets_parquet(path: &Path) {
+    use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    let schema = parse_message_type(
+        "message planets {
+            REQUIRED BYTE_ARRAY Planet (UTF8);
+            REQUIRED INT64 Moons;
+            OPTIONAL DOUBLE Radius;
+            OPTIONAL INT64 Rings;
+        }",
+    )
+    .unwrap();
+    let mut writer = SerializedFileWriter::new(
+        std::fs::File::create(path).unwrap(),
+        std::sync::Arc::new(schema),
+        std::sync::Arc::new(WriterProperties::builder().build()),
+    )
+    .unwrap();
+    let mut row_group = writer.next_row_group().unwrap();
+
+    let mut column = row_group.next_column().unwrap().unwrap();
+    let planets = ["Mercury", "Earth", "Saturn"].map(ByteArray::from);
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&planets, None, None)
+        .unwrap();
+    column.close().unwrap();
+    let mut column = row_group.next_column().unwrap().unwrap();
+    column
+        .typed::<Int64Type>()
+        .write_batch(&[0, 1, 146], None, None)
+        .unwrap();
+    column.close().unwrap();
+    let mut column = row_group.next_column().unwrap().unwrap();
+    column
+        .typed::<DoubleType>()
+        .write_batch(&[2439.7, 6371.0, 58232.0], Some(&[1, 1, 1]), None)
+        .unwrap();
+    column.close().unwrap();
+    let mut column = row_group.next_column().unwrap().unwrap();
+    column
+        .typed::<Int64Type>()
+        .write_batch(&[7], Some(&[0, 0, 1]), None)
+        .unwrap();
+    column.close().unwrap();
+
+    row_group.close().unwrap();
+    writer.close().unwrap();
+}
+
+#[test]
+fn test_load_parquet() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-parquet.parquet");
+    write_planets_parquet(&path);
+    let table = load_parquet(&path.to_string_lossy()).unwrap();
+    assert_eq!(table.header, vec!["Planet", "Moons", "Radius", "Rings"]);
+    // Radius is declared optional, but no values are missing.
+    assert_eq!(table.numeric_columns(), vec!["Moons", "Radius"]);
+    assert_eq!(
+        table.rows[0].cells,
+        vec![
+            Cell::String("Mercury".to_string()),
+            Cell::Int64(0),
+            Cell::Float64(2439.7),
+            Cell::OptInt64(None),
+        ]
+    );
+    assert_eq!(table.rows[2].join(), "Saturn,146,58232,7");
+}
+
+#[test]
+fn test_parquet_field_to_cell() {
+    assert_eq!(parquet_field_to_cell(&Field::Null), None);
+    assert_eq!(
+        parquet_field_to_cell(&Field::Int(-3)),
+        Some(Cell::Int64(-3))
+    );
+    assert_eq!(
+        parquet_field_to_cell(&Field::ULong(u64::MAX)),
+        Some(Cell::Float64(u64::MAX as f64))
+    );
+    assert_eq!(
+        parquet_field_to_cell(&Field::Float(1.5)),
+        Some(Cell::Float64(1.5))
+    );
+    assert_eq!(
+        parquet_field_to_cell(&Field::Str("Earth".to_string())),
+        Some(Cell::String("Earth".to_string()))
+    );
+    assert_eq!(
+        parquet_field_to_cell(&Field::Bool(true)),
+        Some(Cell::String("true".to_string()))
+    );
+    assert_eq!(
+        parquet_field_to_cell(&Field::Date(0)),
+        Some(Cell::String("1970-01-01".to_string()))
+    );
+}
+
+#[test]
+fn test_load_parquet_malformed() {
+    let path = std::env::temp_dir().join("toy-query-engine-load-parquet-malformed.parquet");
+    std::fs::write(&path, "Planet,Moons\nEarth,1\n").unwrap();
+    assert!(load_parquet(&path.to_string_lossy()).is_err());
+    assert!(load_parquet("no/such/file.parquet").is_err());
+}
+
+#[test]
+fn test_is_parquet_file() {
+    assert!(is_parquet_file("data/planets.parquet"));
+    assert!(is_parquet_file("PLANETS.PARQUET"));
+    assert!(!is_parquet_file("data/city.csv"));
+    assert!(!is_parquet_file("parquet"));
+}
+
 /// The datasets known to the toy-query-engine.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Dataset {
-    /// city.csv
+    /// city.csv, e.g. `1,Kabul,AFG,1780000` for the columns `CityID,CityName,CountryCode,CityPop`.
     City,
-    /// country.csv
+    /// country.csv, e.g. `ABW,Aruba,North_America,103000,129` for the columns
+    /// `CountryCode,CountryName,Continent,CountryPop,Capital`. Countries without a capital leave
+    /// the `Capital` column empty.
     Country,
-    /// language.csv
+    /// language.csv, e.g. `ABW,Dutch` for the columns `CountryCode,Language`.
     Language,
+    /// Any other CSV file, identified by its path. Its columns are only known once it is loaded.
+    /// See [`load_csv`].
+    Csv(String),
+    /// A Parquet file, identified by its path. Its columns are only known once it is loaded.
+    /// See [`load_parquet`].
+    Parquet(String),
+    /// A CSV or Parquet file published at an HTTP or HTTPS URL, e.g.
+    /// `https://example.com/cities.csv`. It is downloaded with [`fetch_url`] the first time a
+    /// query uses it, and then loaded like a local file.
+    Url(String),
+    /// A table defined by a `WITH <name> AS (<query>)` clause earlier in the same query.
+    /// The table is looked up by name in the query-scoped catalog when the query is processed.
+    Cte(String),
+    /// A result saved earlier in the session with `SET <name> = <query>`, referred to as `@<name>`.
+    /// The table is looked up by name in the session's saved results when the query is processed.
+    Variable(String),
+    /// A CSV or Parquet file given a name with `LOAD <name> FROM <path>`. See [`DatasetRegistry`].
+    Registered {
+        /// The name queries refer to the dataset by.
+        name: String,
+        /// The path of the file. It is loaded like a [`Dataset::Parquet`] if it has the `.parquet`
+        /// extension (see [`is_parquet_file`]), and like a [`Dataset::Csv`] otherwise.
+        path: String,
+        /// The encoding of the CSV file, given with `LOAD <name> FROM <path> ENCODING <label>`.
+        /// The CSV files without one are read in the encoding of the session, e.g. `--encoding`.
+        encoding: Option<&'static Encoding>,
+    },
+    /// A table of a SQLite database attached with `ATTACH <path>`. See [`DatasetRegistry::attach`]
+    /// and [`load_sqlite`].
+    Sqlite {
+        /// The path of the database file.
+        path: String,
+        /// The name of the table, which queries refer to the dataset by.
+        table: String,
+    },
 }
 
 impl Display for Dataset {
@@ -298,6 +1299,574 @@ impl Display for Dataset {
             Dataset::City => f.write_str("city.csv"),
             Dataset::Country => f.write_str("country.csv"),
             Dataset::Language => f.write_str("language.csv"),
+            Dataset::Csv(path) | Dataset::Parquet(path) | Dataset::Url(path) => f.write_str(path),
+            Dataset::Cte(name) | Dataset::Registered { name, .. } => f.write_str(name),
+            Dataset::Sqlite { table, .. } => f.write_str(table),
+            Dataset::Variable(name) => f.write_fmt(format_args!("@{}", name)),
+        }
+    }
+}
+
+impl Dataset {
+    /// Returns all the datasets stored on disk that are known to the toy-query-engine.
+    pub fn all() -> Vec<Dataset> {
+        vec![Dataset::City, Dataset::Country, Dataset::Language]
+    }
+
+    /// Returns the short name of the dataset, i.e., its file name without the `.csv` suffix.
+    pub fn name(&self) -> &str {
+        match self {
+            Dataset::City => "city",
+            Dataset::Country => "country",
+            Dataset::Language => "language",
+            Dataset::Csv(path) | Dataset::Parquet(path) | Dataset::Url(path) => path,
+            Dataset::Cte(name) | Dataset::Variable(name) | Dataset::Registered { name, .. } => name,
+            Dataset::Sqlite { table, .. } => table,
+        }
+    }
+
+    /// Returns the qualified names of the columns of the dataset, i.e., their names prefixed with
+    /// the name of the dataset, e.g. `city.CityName`. Files are named by their file name without
+    /// the directory and the extension, e.g. `planets.PlanetName` for `data/planets.csv.gz`.
+    ///
+    /// # Arguments
+    /// `header` : The names of the columns of the dataset.
+    pub fn qualified_names(&self, header: &[String]) -> Vec<Option<String>> {
+        let qualifier = match self {
+            Dataset::Csv(path) | Dataset::Parquet(path) | Dataset::Url(path) => {
+                let file_name = path.rsplit('/').next().unwrap_or(path);
+                [".csv.gz", ".csv", ".parquet"]
+                    .iter()
+                    .find_map(|extension| file_name.strip_suffix(extension))
+                    .unwrap_or(file_name)
+            }
+            _ => self.name(),
+        };
+        header
+            .iter()
+            .map(|name| Some(format!("{}.{}", qualifier, name)))
+            .collect()
+    }
+
+    /// Returns the path of the file the dataset is loaded from, or `None` for a [`Dataset::Cte`] or
+    /// a [`Dataset::Variable`], which only exist in memory, and for a [`Dataset::Url`], which is
+    /// only downloaded when it is loaded.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Dataset::City => Some("data/city.csv"),
+            Dataset::Country => Some("data/country.csv"),
+            Dataset::Language => Some("data/language.csv"),
+            Dataset::Csv(path)
+            | Dataset::Parquet(path)
+            | Dataset::Registered { path, .. }
+            | Dataset::Sqlite { path, .. } => Some(path),
+            Dataset::Cte(_) | Dataset::Variable(_) | Dataset::Url(_) => None,
+        }
+    }
+
+    /// Returns `true` for the datasets bundled with the toy-query-engine, whose columns are known
+    /// without loading them.
+    pub fn is_builtin(&self) -> bool {
+        matches!(self, Dataset::City | Dataset::Country | Dataset::Language)
+    }
+
+    /// Looks up a dataset by its short name (`city`) or its file name (`city.csv`). Any other name
+    /// ending in `.csv`, `.csv.gz`, or `.parquet` is treated as the path of a CSV or Parquet file,
+    /// as long as the file exists. Names starting with `http://` or `https://` are URLs, which are
+    /// only fetched when the dataset is loaded.
+    ///
+    /// # Returns
+    /// The matching [`Dataset`], or `None` if `name` doesn't refer to a known dataset.
+    pub fn from_name(name: &str) -> Option<Dataset> {
+        let short_name = name.strip_suffix(".csv").unwrap_or(name);
+        Dataset::all()
+            .into_iter()
+            .find(|dataset| dataset.name() == short_name)
+            .or_else(|| {
+                if is_url(name) {
+                    Some(Dataset::Url(name.to_string()))
+                } else if !Path::new(name).is_file() {
+                    None
+                } else if is_csv_file(name) {
+                    Some(Dataset::Csv(name.to_string()))
+                } else if is_parquet_file(name) {
+                    Some(Dataset::Parquet(name.to_string()))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Returns the names of the columns in the dataset. The columns of the datasets that aren't
+    /// built in, e.g. a [`Dataset::Csv`] or a [`Dataset::Sqlite`], are only known once it is
+    /// loaded, so none are returned for them.
+    pub fn column_names(&self) -> Vec<String> {
+        match self {
+            Dataset::City => vec![
+                "CityID".to_string(),
+                "CityName".to_string(),
+                "CountryCode".to_string(),
+                "CityPop".to_string(),
+            ],
+            Dataset::Country => vec![
+                "CountryCode".to_string(),
+                "CountryName".to_string(),
+                "Continent".to_string(),
+                "CountryPop".to_string(),
+                "Capital".to_string(),
+            ],
+            Dataset::Language => vec!["CountryCode".to_string(), "Language".to_string()],
+            Dataset::Csv(_)
+            | Dataset::Parquet(_)
+            | Dataset::Url(_)
+            | Dataset::Cte(_)
+            | Dataset::Variable(_)
+            | Dataset::Registered { .. }
+            | Dataset::Sqlite { .. } => vec![],
+        }
+    }
+
+    /// Returns the names of only those columns in the dataset whose values are numeric.
+    pub fn numeric_columns(&self) -> Vec<String> {
+        match self {
+            Dataset::City => vec!["CityID".to_string(), "CityPop".to_string()],
+            Dataset::Country => vec!["CountryPop".to_string()],
+            Dataset::Language => vec![],
+            Dataset::Csv(_)
+            | Dataset::Parquet(_)
+            | Dataset::Url(_)
+            | Dataset::Cte(_)
+            | Dataset::Variable(_)
+            | Dataset::Registered { .. }
+            | Dataset::Sqlite { .. } => vec![],
+        }
+    }
+
+    /// Returns the names of the columns of the dataset that ORDERBY can sort on, i.e., those
+    /// holding numbers. Unlike [`Dataset::numeric_columns`], these include the columns with missing
+    /// values, like `Capital`.
+    pub fn sortable_columns(&self) -> Vec<String> {
+        match self {
+            Dataset::Country => vec!["CountryPop".to_string(), "Capital".to_string()],
+            _ => self.numeric_columns(),
         }
     }
+
+    /// Returns the names of the columns whose values uniquely identify a row in the dataset.
+    pub fn key_columns(&self) -> Vec<String> {
+        match self {
+            Dataset::City => vec!["CityID".to_string()],
+            Dataset::Country => vec!["CountryCode".to_string()],
+            // Languages are only unique per (CountryCode, Language) pair, so no single column is a key.
+            Dataset::Language => vec![],
+            Dataset::Csv(_)
+            | Dataset::Parquet(_)
+            | Dataset::Url(_)
+            | Dataset::Cte(_)
+            | Dataset::Variable(_)
+            | Dataset::Registered { .. }
+            | Dataset::Sqlite { .. } => vec![],
+        }
+    }
+}
+
+#[test]
+fn test_dataset_from_name() {
+    assert_eq!(Dataset::from_name("city"), Some(Dataset::City));
+    assert_eq!(Dataset::from_name("city.csv"), Some(Dataset::City));
+    assert_eq!(Dataset::from_name("country"), Some(Dataset::Country));
+    assert_eq!(Dataset::from_name("language.csv"), Some(Dataset::Language));
+    assert_eq!(Dataset::from_name("city.cv"), None);
+    assert_eq!(Dataset::from_name("cities"), None);
+    assert_eq!(Dataset::from_name(".csv"), None);
+    assert_eq!(
+        Dataset::from_name("./data/city.csv"),
+        Some(Dataset::Csv("./data/city.csv".to_string()))
+    );
+    assert_eq!(Dataset::from_name("./data/cities.csv"), None);
+    assert_eq!(Dataset::from_name("./data"), None);
+
+    let path = std::env::temp_dir().join("toy-query-engine-dataset-from-name.parquet");
+    std::fs::write(&path, "").unwrap();
+    let path = path.to_string_lossy().to_string();
+    assert_eq!(
+        Dataset::from_name(&path),
+        Some(Dataset::Parquet(path.clone()))
+    );
+    assert_eq!(Dataset::from_name("./data/city.parquet"), None);
+
+    // URLs aren't fetched until the dataset is loaded.
+    let url = "https://example.com/cities.csv";
+    assert_eq!(Dataset::from_name(url), Some(Dataset::Url(url.to_string())));
+    assert_eq!(Dataset::Url(url.to_string()).to_string(), url);
+    assert_eq!(Dataset::Url(url.to_string()).path(), None);
+}
+
+#[test]
+fn test_dataset_path() {
+    assert_eq!(Dataset::City.path(), Some("data/city.csv"));
+    assert_eq!(
+        Dataset::Csv("./planets.csv".to_string()).path(),
+        Some("./planets.csv")
+    );
+    assert_eq!(Dataset::Cte("euro".to_string()).path(), None);
+    let variable = Dataset::Variable("big_cities".to_string());
+    assert_eq!(variable.path(), None);
+    assert_eq!(variable.to_string(), "@big_cities");
+    assert_eq!(variable.name(), "big_cities");
+    let registered = Dataset::Registered {
+        name: "planets".to_string(),
+        path: "./planets.csv".to_string(),
+        encoding: None,
+    };
+    assert_eq!(registered.path(), Some("./planets.csv"));
+    assert_eq!(registered.to_string(), "planets");
+}
+
+#[test]
+fn test_dataset_qualified_names() {
+    let header = vec!["CityID".to_string(), "CityName".to_string()];
+    assert_eq!(
+        Dataset::City.qualified_names(&header),
+        vec![
+            Some("city.CityID".to_string()),
+            Some("city.CityName".to_string())
+        ]
+    );
+    for path in ["data/planets.csv.gz", "./planets.parquet", "planets.csv"] {
+        assert_eq!(
+            Dataset::Csv(path.to_string()).qualified_names(&header[..1]),
+            vec![Some("planets.CityID".to_string())]
+        );
+    }
+    assert_eq!(
+        Dataset::Variable("big_cities".to_string()).qualified_names(&header[1..]),
+        vec![Some("big_cities.CityName".to_string())]
+    );
+}
+
+/// Returns `true` if `name` can name a dataset, i.e., it consists of letters, digits, and
+/// underscores.
+pub fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The datasets given a name with `LOAD <name> FROM <path>`, or attached with `ATTACH <path>`,
+/// during a session, in addition to the built-in ones. FROM and JOIN look up the names of datasets
+/// here, see [`DatasetRegistry::resolve`].
+#[derive(Debug, Clone, Default)]
+pub struct DatasetRegistry {
+    /// Each registered [`Dataset::Registered`] or attached [`Dataset::Sqlite`], by name.
+    datasets: BTreeMap<String, Dataset>,
+}
+
+impl DatasetRegistry {
+    /// Gives the CSV or Parquet file at `path` the `name`, so later queries can refer to it by that name.
+    /// Registering a name again replaces the dataset it refers to.
+    ///
+    /// # Arguments
+    /// `name` : The name to give the dataset. Must consist of letters, digits, and underscores, and
+    /// not be the name of a built-in dataset.
+    /// `path` : The path of the CSV or Parquet file. It is only checked to exist here; it is loaded the first
+    /// time a query uses it.
+    ///
+    /// # Returns
+    /// The registered [`Dataset::Registered`], or an error message describing why it can't be
+    /// registered.
+    pub fn register(&mut self, name: &str, path: &str) -> Result<Dataset, String> {
+        self.register_with_encoding(name, path, None)
+    }
+
+    /// Gives the CSV or Parquet file at `path` the `name`, like [`DatasetRegistry::register`],
+    /// reading a CSV file in the given text `encoding` rather than in the encoding of the session.
+    ///
+    /// # Arguments
+    /// `encoding` : The encoding the CSV file is written in, e.g. windows-1252. See
+    /// [`parse_encoding`]. The encoding of the session is used when `None`.
+    pub fn register_with_encoding(
+        &mut self,
+        name: &str,
+        path: &str,
+        encoding: Option<&'static Encoding>,
+    ) -> Result<Dataset, String> {
+        if !is_identifier(name) {
+            return Err(format!(
+                "Invalid dataset name: {}. It must consist of letters, digits, and underscores.",
+                name
+            ));
+        }
+        if Dataset::all().iter().any(|dataset| dataset.name() == name) {
+            return Err(format!("{} is the name of a built-in dataset.", name));
+        }
+        if !Path::new(path).is_file() {
+            return Err(format!("No such file: {}", path));
+        }
+        let dataset = Dataset::Registered {
+            name: name.to_string(),
+            path: path.to_string(),
+            encoding,
+        };
+        self.datasets.insert(name.to_string(), dataset.clone());
+        Ok(dataset)
+    }
+
+    /// Attaches the SQLite database at `path`, so later queries can refer to each of its tables
+    /// and views by name. A table named like a built-in dataset, or with characters other than
+    /// letters, digits, and underscores, can't be referred to, so it is left out. A table replaces
+    /// any dataset registered earlier under the same name.
+    ///
+    /// # Returns
+    /// The attached [`Dataset::Sqlite`] tables, ordered by name, or an error message describing
+    /// why the database can't be read.
+    pub fn attach(&mut self, path: &str) -> Result<Vec<Dataset>, String> {
+        if !Path::new(path).is_file() {
+            return Err(format!("No such file: {}", path));
+        }
+        let tables = sqlite_tables(path)
+            .map_err(|error| format!("Failed to read the SQLite database {}: {}", path, error))?;
+        Ok(tables
+            .into_iter()
+            .filter(|table| {
+                is_identifier(table)
+                    && !Dataset::all().iter().any(|dataset| dataset.name() == table)
+            })
+            .map(|table| {
+                let dataset = Dataset::Sqlite {
+                    path: path.to_string(),
+                    table: table.clone(),
+                };
+                self.datasets.insert(table, dataset.clone());
+                dataset
+            })
+            .collect())
+    }
+
+    /// Looks up a dataset by name: first among the registered datasets, then as described by
+    /// [`Dataset::from_name`].
+    ///
+    /// # Returns
+    /// The matching [`Dataset`], or `None` if `name` doesn't refer to a known dataset.
+    pub fn resolve(&self, name: &str) -> Option<Dataset> {
+        self.datasets
+            .get(name)
+            .cloned()
+            .or_else(|| Dataset::from_name(name))
+    }
+
+    /// Returns the registered and attached datasets, ordered by name.
+    pub fn registered(&self) -> Vec<Dataset> {
+        self.datasets.values().cloned().collect()
+    }
+
+    /// Returns every dataset FROM and JOIN can refer to by name: the built-in ones, followed by
+    /// the registered and attached ones, ordered by name.
+    pub fn catalog(&self) -> Vec<Dataset> {
+        Dataset::all()
+            .into_iter()
+            .chain(self.registered())
+            .collect()
+    }
+}
+
+#[test]
+fn test_dataset_registry() {
+    let path = std::env::temp_dir().join("toy-query-engine-dataset-registry.csv");
+    std::fs::write(&path, "Planet,Moons\nEarth,1\n").unwrap();
+    let path = path.to_string_lossy().to_string();
+    let planets = Dataset::Registered {
+        name: "planets".to_string(),
+        path: path.clone(),
+        encoding: None,
+    };
+
+    let mut registry = DatasetRegistry::default();
+    assert_eq!(registry.resolve("planets"), None);
+    assert_eq!(registry.register("planets", &path), Ok(planets.clone()));
+    assert_eq!(registry.resolve("planets"), Some(planets.clone()));
+    // The built-in datasets are still found.
+    assert_eq!(registry.resolve("city"), Some(Dataset::City));
+    assert_eq!(registry.registered(), vec![planets.clone()]);
+    assert_eq!(
+        registry.catalog(),
+        vec![Dataset::City, Dataset::Country, Dataset::Language, planets]
+    );
+}
+
+#[test]
+fn test_dataset_registry_invalid() {
+    let mut registry = DatasetRegistry::default();
+    assert_eq!(
+        registry.register("city", "data/country.csv"),
+        Err("city is the name of a built-in dataset.".to_string())
+    );
+    assert_eq!(
+        registry.register("my-data", "data/country.csv"),
+        Err(
+            "Invalid dataset name: my-data. It must consist of letters, digits, and underscores."
+                .to_string()
+        )
+    );
+    assert_eq!(
+        registry.register("planets", "no/such/file.csv"),
+        Err("No such file: no/such/file.csv".to_string())
+    );
+    assert!(registry.registered().is_empty());
+}
+
+#[test]
+fn test_dataset_registry_attach() {
+    let path = std::env::temp_dir().join("toy-query-engine-dataset-registry-attach.sqlite");
+    write_planets_sqlite(&path);
+    let path = path.to_string_lossy().to_string();
+    let planets = Dataset::Sqlite {
+        path: path.clone(),
+        table: "planets".to_string(),
+    };
+
+    let mut registry = DatasetRegistry::default();
+    registry.register("planets", "data/city.csv").unwrap();
+    let attached = registry.attach(&path).unwrap();
+    assert_eq!(attached.len(), 2);
+    assert_eq!(attached[1], planets);
+    // The attached table replaces the dataset registered under its name.
+    assert_eq!(registry.resolve("planets"), Some(planets.clone()));
+    assert_eq!(planets.to_string(), "planets");
+    assert_eq!(planets.path(), Some(path.as_str()));
+    assert_eq!(registry.registered(), attached);
+
+    assert_eq!(
+        registry.attach("no/such/file.sqlite"),
+        Err("No such file: no/such/file.sqlite".to_string())
+    );
+    assert_eq!(
+        registry.attach("data/city.csv"),
+        Err("Failed to read the SQLite database data/city.csv: file is not a database".to_string())
+    );
+}
+
+/// A [`Table`] held by the [`DatasetCache`], along with the modification time of the file it was
+/// loaded from.
+#[derive(Debug)]
+struct CachedTable {
+    table: Arc<Table>,
+    /// When the file was last modified, or `None` for a [`Dataset::Url`], which is only
+    /// downloaded once per session.
+    modified: Option<SystemTime>,
+}
+
+/// Tables loaded from disk, kept in memory so that queries using the same [`Dataset`] don't have
+/// to read and parse its file again. A table is loaded the first time it is requested, and
+/// reloaded if its file has been modified since. Tables downloaded from a URL are kept until the
+/// cache is cleared.
+#[derive(Debug, Default)]
+pub struct DatasetCache {
+    tables: HashMap<Dataset, CachedTable>,
+}
+
+impl DatasetCache {
+    /// Returns the table for the `dataset`, calling `load` to read it from disk if it isn't
+    /// cached or its file has changed since it was cached.
+    ///
+    /// # Arguments
+    /// `dataset` : The dataset to look up. Datasets without a file (i.e., [`Dataset::Cte`] and
+    /// [`Dataset::Variable`]) are never cached.
+    /// `load` : Reads the dataset from disk.
+    ///
+    /// # Returns
+    /// The cached table, shared with the cache rather than copied, or the error returned by
+    /// `load`.
+    pub fn get_or_load<F>(
+        &mut self,
+        dataset: &Dataset,
+        load: F,
+    ) -> Result<Arc<Table>, Box<dyn Error>>
+    where
+        F: FnOnce(&Dataset) -> Result<Table, Box<dyn Error>>,
+    {
+        let modified = match dataset {
+            Dataset::Url(_) => None,
+            _ => {
+                let modified = dataset
+                    .path()
+                    .and_then(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok());
+                if modified.is_none() {
+                    // The file can't be inspected, so there is no way to tell if a cached copy is
+                    // stale.
+                    self.tables.remove(dataset);
+                    return load(dataset).map(Arc::new);
+                }
+                modified
+            }
+        };
+        if let Some(cached) = self.tables.get(dataset) {
+            if cached.modified == modified {
+                return Ok(cached.table.clone());
+            }
+        }
+        let table = Arc::new(load(dataset)?);
+        self.tables.insert(
+            dataset.clone(),
+            CachedTable {
+                table: table.clone(),
+                modified,
+            },
+        );
+        Ok(table)
+    }
+
+    /// Returns the number of cached tables.
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Returns `true` if no tables are cached.
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Drops all the cached tables.
+    pub fn clear(&mut self) {
+        self.tables.clear();
+    }
+}
+
+#[test]
+fn test_dataset_cache_get_or_load() {
+    let path = std::env::temp_dir().join("toy-query-engine-dataset-cache.csv");
+    std::fs::write(&path, "Planet,Moons\nEarth,1\n").unwrap();
+    let dataset = Dataset::Csv(path.to_string_lossy().to_string());
+    let mut cache = DatasetCache::default();
+    let mut loads = 0;
+    let mut load = |dataset: &Dataset| {
+        loads += 1;
+        load_csv(dataset.path().unwrap())
+    };
+
+    let first = cache.get_or_load(&dataset, &mut load).unwrap();
+    let second = cache.get_or_load(&dataset, &mut load).unwrap();
+    assert_eq!(first.rows.len(), 1);
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(cache.len(), 1);
+
+    // Modifying the file invalidates the cached table.
+    std::fs::write(&path, "Planet,Moons\nEarth,1\nMars,2\n").unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+    let third = cache.get_or_load(&dataset, &mut load).unwrap();
+    assert_eq!(third.rows.len(), 2);
+    assert_eq!(loads, 2);
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_dataset_cache_missing_file() {
+    let mut cache = DatasetCache::default();
+    let dataset = Dataset::Csv("no/such/file.csv".to_string());
+    assert!(cache
+        .get_or_load(&dataset, |dataset| load_csv(dataset.path().unwrap()))
+        .is_err());
+    assert!(cache.is_empty());
 }
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+
+use crate::data::Dataset;
+use crate::table::{Cell, Row, Table};
+
+/// The number of distinct values listed as samples of each column.
+const C_SAMPLE_COUNT: usize = 3;
+
+/// Describes each column of the `table`, e.g. to get to know an unfamiliar dataset with
+/// `DESCRIBE <dataset>`.
+///
+/// # Arguments
+/// `table` : The table to describe.
+///
+/// # Returns
+/// A [`Table`] with one row per column of the `table`, in order, and the columns:
+/// - `column`: The name of the column.
+/// - `type`: The [`crate::table::DataType`] of its values, i.e., "integer", "decimal", "string",
+///   "mixed", or "empty".
+/// - `nulls`: The number of missing values.
+/// - `distinct`: The number of distinct values, not counting the missing values.
+/// - `min` and `max`: The smallest and largest values of a column of numbers, and empty
+///   otherwise.
+/// - `samples`: The first few distinct values, separated by commas.
+pub fn describe_table(table: &Table) -> Table {
+    let rows = table
+        .schema
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let values: Vec<&Cell> = table
+                .rows
+                .iter()
+                .map(|row| &row.cells[index])
+                .filter(|value| !value.is_null())
+                .collect();
+            let (min, max) = match column.data_type.is_numeric() {
+                true => (
+                    values
+                        .iter()
+                        .copied()
+                        .min_by(|left, right| left.cmp_values(right)),
+                    values
+                        .iter()
+                        .copied()
+                        .max_by(|left, right| left.cmp_values(right)),
+                ),
+                false => (None, None),
+            };
+            let mut seen = HashSet::new();
+            let distinct: Vec<&Cell> = values
+                .iter()
+                .copied()
+                .filter(|value| seen.insert(*value))
+                .collect();
+            Row {
+                cells: vec![
+                    Cell::String(column.name.clone()),
+                    Cell::String(column.data_type.to_string()),
+                    Cell::Int64((table.rows.len() - values.len()) as i64),
+                    Cell::Int64(distinct.len() as i64),
+                    min.cloned().unwrap_or(Cell::Null),
+                    max.cloned().unwrap_or(Cell::Null),
+                    Cell::String(
+                        distinct
+                            .iter()
+                            .take(C_SAMPLE_COUNT)
+                            .map(|value| value.to_string())
+                            .collect::<Vec<String>>()
+                            .join(", "),
+                    ),
+                ],
+            }
+        })
+        .collect();
+    Table::new(
+        [
+            "column", "type", "nulls", "distinct", "min", "max", "samples",
+        ]
+        .map(str::to_string)
+        .to_vec(),
+        rows,
+    )
+}
+
+/// Lists the `datasets`, e.g. those FROM and JOIN can refer to, for `SHOW TABLES`.
+///
+/// # Arguments
+/// `datasets` : The datasets to list, in order.
+///
+/// # Returns
+/// A [`Table`] with one row per dataset, and the columns:
+/// - `name`: The name FROM and JOIN refer to the dataset by, e.g. `city` or `@big`.
+/// - `kind`: Where the dataset comes from, i.e., "built-in", "file", "sqlite", "url", or
+///   "variable" for a result saved with SET.
+/// - `source`: The path of the file the dataset is loaded from, or its URL, and empty for a
+///   saved result.
+pub fn list_tables(datasets: &[Dataset]) -> Table {
+    let rows = datasets
+        .iter()
+        .map(|dataset| {
+            let (name, kind, source) = match dataset {
+                Dataset::City | Dataset::Country | Dataset::Language => {
+                    (dataset.name().to_string(), "built-in", dataset.path())
+                }
+                Dataset::Csv(_) | Dataset::Parquet(_) | Dataset::Registered { .. } => {
+                    (dataset.name().to_string(), "file", dataset.path())
+                }
+                Dataset::Sqlite { .. } => (dataset.name().to_string(), "sqlite", dataset.path()),
+                Dataset::Url(url) => (url.clone(), "url", Some(url.as_str())),
+                Dataset::Cte(name) => (name.clone(), "cte", None),
+                Dataset::Variable(_) => (dataset.to_string(), "variable", None),
+            };
+            Row {
+                cells: vec![
+                    Cell::String(name),
+                    Cell::String(kind.to_string()),
+                    source.map_or(Cell::Null, |source| Cell::String(source.to_string())),
+                ],
+            }
+        })
+        .collect();
+    Table::new(
+        ["name", "kind", "source"].map(str::to_string).to_vec(),
+        rows,
+    )
+}
+
+/// Lists the columns of the `table`, e.g. a loaded dataset for `SHOW COLUMNS`. Unlike
+/// [`describe_table`], only the names and types are listed, so it is quick to read.
+///
+/// # Arguments
+/// `table` : The table whose columns to list.
+///
+/// # Returns
+/// A [`Table`] with one row per column of the `table`, in order, and the columns:
+/// - `column`: The name of the column.
+/// - `type`: The [`crate::table::DataType`] of its values, i.e., "integer", "decimal", "string",
+///   "mixed", or "empty".
+/// - `numeric`: "yes" if the column can be sorted by ORDERBY and aggregated, and "no" otherwise,
+///   e.g. for a column of integers with missing values.
+pub fn list_columns(table: &Table) -> Table {
+    let rows = table
+        .schema
+        .iter()
+        .map(|column| Row {
+            cells: vec![
+                Cell::String(column.name.clone()),
+                Cell::String(column.data_type.to_string()),
+                Cell::String(
+                    match column.is_numeric() {
+                        true => "yes",
+                        false => "no",
+                    }
+                    .to_string(),
+                ),
+            ],
+        })
+        .collect();
+    Table::new(
+        ["column", "type", "numeric"].map(str::to_string).to_vec(),
+        rows,
+    )
+}
+
+#[test]
+fn test_describe_table() {
+    let table = crate::data::load_csv("data/country.csv").unwrap();
+    let description = describe_table(&table);
+    assert_eq!(
+        description.header,
+        vec!["column", "type", "nulls", "distinct", "min", "max", "samples"]
+    );
+    assert_eq!(description.rows.len(), table.header.len());
+    assert_eq!(
+        description.rows[2].join(),
+        "Continent,string,0,7,,,North_America, Asia, Africa"
+    );
+    assert_eq!(
+        description.rows[3].join(),
+        "CountryPop,integer,0,226,0,1277558000,103000, 22720000, 12878000"
+    );
+    // The countries without a capital are counted as missing values.
+    assert_eq!(
+        description.rows[4].join(),
+        "Capital,integer,7,232,1,4074,129, 1, 56"
+    );
+}
+
+#[test]
+fn test_list_tables() {
+    let datasets = vec![
+        Dataset::City,
+        Dataset::Registered {
+            name: "planets".to_string(),
+            path: "data/planets.csv".to_string(),
+            encoding: None,
+        },
+        Dataset::Sqlite {
+            path: "my.db".to_string(),
+            table: "moons".to_string(),
+        },
+        Dataset::Variable("big".to_string()),
+    ];
+    let tables = list_tables(&datasets);
+    assert_eq!(tables.header, vec!["name", "kind", "source"]);
+    assert_eq!(
+        tables
+            .rows
+            .iter()
+            .map(|row| row.join())
+            .collect::<Vec<String>>(),
+        vec![
+            "city,built-in,data/city.csv",
+            "planets,file,data/planets.csv",
+            "moons,sqlite,my.db",
+            "@big,variable,",
+        ]
+    );
+}
+
+#[test]
+fn test_list_columns() {
+    let table = crate::data::load_csv("data/country.csv").unwrap();
+    let columns = list_columns(&table);
+    assert_eq!(columns.header, vec!["column", "type", "numeric"]);
+    assert_eq!(
+        columns
+            .rows
+            .iter()
+            .map(|row| row.join())
+            .collect::<Vec<String>>(),
+        vec![
+            "CountryCode,string,no",
+            "CountryName,string,no",
+            "Continent,string,no",
+            "CountryPop,integer,yes",
+            "Capital,integer,no",
+        ]
+    );
+}
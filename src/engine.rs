@@ -0,0 +1,386 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use encoding_rs::Encoding;
+
+use crate::commands::{parse_command, Command, ParseError};
+use crate::data::Dataset;
+use crate::describe::{describe_table, list_columns, list_tables};
+use crate::lint::{lint_operator, LintWarning};
+use crate::operators::{
+    process_cached, process_operator, ExecutionContext, Operator, OperatorError, ResultLimits,
+};
+use crate::optimizer::optimize;
+use crate::script::ScriptFunctions;
+use crate::table::Table;
+
+/// The set of errors that can be returned by [`QueryEngine::execute`].
+#[derive(Debug)]
+pub enum EngineError {
+    /// The query is malformed. Holds the [`ParseError`] describing why.
+    ParseError(ParseError),
+    /// The input is a REPL command (e.g. `help`, `exit`, or a `\<setting>`) or empty, rather than
+    /// a query.
+    NotAQuery(String),
+    /// The query was parsed, but processing it failed.
+    OperatorError(OperatorError),
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::ParseError(error) => {
+                f.write_fmt(format_args!("Malformed input. {}", error))
+            }
+            EngineError::NotAQuery(input) => f.write_fmt(format_args!("Not a query: {:?}", input)),
+            EngineError::OperatorError(error) => f.write_fmt(format_args!("{}", error)),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+impl From<OperatorError> for EngineError {
+    fn from(error: OperatorError) -> Self {
+        EngineError::OperatorError(error)
+    }
+}
+
+/// The result of a query run by a [`QueryEngine`], with metadata about how it ran, e.g. to show
+/// next to the results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    /// The table produced by the query.
+    pub table: Table,
+    /// The number of rows read from the datasets, and the saved results, to produce the `table`.
+    /// A dataset cut short by TAKE counts only the rows it produced, and a result reused from an
+    /// earlier query counts none.
+    pub rows_scanned: usize,
+    /// The time taken to run the query, not counting parsing it.
+    pub elapsed: Duration,
+    /// The suspicious, but legal, patterns found in the query, e.g. a JOIN that may multiply the
+    /// size of the result. They never stop it from running.
+    pub warnings: Vec<LintWarning>,
+}
+
+/// High-level entry point for embedding the toy-query-engine in another application.
+/// Parses queries written in the same language as the REPL and runs them. State that lives across
+/// queries, such as the user-defined functions, is kept in the engine.
+///
+/// Example:
+/// ```
+/// use toy_query_engine::QueryEngine;
+///
+/// let mut engine = QueryEngine::new();
+/// let result = engine.execute("FROM city.csv ORDERBY CityPop TAKE 1").unwrap();
+/// assert_eq!(result.table.rows[0].join(), "1024,Mumbai_(Bombay),IND,10500000");
+/// ```
+#[derive(Debug, Default)]
+pub struct QueryEngine {
+    /// The context every query is processed in.
+    context: ExecutionContext,
+}
+
+impl QueryEngine {
+    /// Creates an engine without any user-defined functions.
+    pub fn new() -> Self {
+        QueryEngine::default()
+    }
+
+    /// Creates an engine whose queries can call the user-defined `functions`.
+    pub fn with_functions(functions: ScriptFunctions) -> Self {
+        let mut engine = QueryEngine::default();
+        engine.context.functions = Some(functions);
+        engine
+    }
+
+    /// Reads the CSV files in the given `encoding`, e.g. one returned by
+    /// [`crate::data::parse_encoding`], instead of UTF-8. The bundled datasets are always read as
+    /// UTF-8, and the files registered with an encoding of their own in it. The datasets already
+    /// loaded by earlier queries aren't read again, so set it before running any.
+    pub fn set_encoding(&mut self, encoding: &'static Encoding) {
+        self.context.encoding = Some(encoding);
+    }
+
+    /// Limits the size of the tables held in memory while a query is processed, e.g. for a server
+    /// shared by several clients. No limits apply by default.
+    pub fn set_limits(&mut self, limits: ResultLimits) {
+        self.context.limits = limits;
+    }
+
+    /// Parses and runs a single `query`, e.g. `FROM city.csv ORDERBY CityPop TAKE 10`.
+    ///
+    /// # Returns
+    /// On success: The [`QueryResult`] holding the [`Table`] produced by the query. Unlike the
+    /// REPL, no default row limit is applied. For `SET <name> = <query>`, the result is also
+    /// saved, so later queries can refer to it as `@<name>`. For `INSERT` and `DELETE`, the
+    /// in-memory copy of the dataset is modified and returned, and for `SAVE`, the saved dataset
+    /// is returned. For `DESCRIBE`, the description of the dataset's columns is returned, and for
+    /// `SHOW TABLES` and `SHOW COLUMNS`, the list of datasets or columns.
+    /// On failure: [`EngineError::ParseError`] or [`EngineError::NotAQuery`] if `query` isn't a
+    /// well-formed query, or [`EngineError::OperatorError`] if processing it fails.
+    pub fn execute(&mut self, query: &str) -> Result<QueryResult, EngineError> {
+        // parse_command expects a line as read from the REPL.
+        let input = format!("{}\n", query.trim_end_matches('\n'));
+        self.execute_command(parse_command(&input), query)
+    }
+
+    /// Runs a `command` already parsed, e.g. with [`crate::commands::parse_command_with`] to
+    /// resolve the datasets registered with `LOAD`, or with [`crate::sql::parse_sql_command`].
+    ///
+    /// # Arguments
+    /// `command` : The command to run.
+    /// `query` : The text the `command` was parsed from, held by [`EngineError::NotAQuery`].
+    ///
+    /// # Returns
+    /// The same as [`QueryEngine::execute`].
+    pub fn execute_command(
+        &mut self,
+        command: Command,
+        query: &str,
+    ) -> Result<QueryResult, EngineError> {
+        let start = Instant::now();
+        // Rows read by an earlier query that failed midway don't count.
+        self.context.take_rows_scanned();
+        let (table, warnings) = match command {
+            Command::Operator(operator) => {
+                return Ok(run_operator(&operator, &mut self.context, false)?)
+            }
+            Command::Set { name, operator } => {
+                let table = process_operator(&optimize(&operator), &mut self.context)?;
+                self.context.set_variable(&name, table.clone());
+                (table, lint_operator(&operator))
+            }
+            Command::Insert { dataset, rows } => {
+                self.context.insert_rows(&dataset, rows)?;
+                let table = process_operator(&Operator::From(dataset), &mut self.context)?;
+                (table, Vec::new())
+            }
+            Command::Delete { dataset, predicate } => {
+                self.context.delete_rows(&dataset, &predicate)?;
+                let table = process_operator(&Operator::From(dataset), &mut self.context)?;
+                (table, Vec::new())
+            }
+            Command::Describe(dataset) => {
+                let table = process_operator(&Operator::From(dataset), &mut self.context)?;
+                (describe_table(&table), Vec::new())
+            }
+            Command::ShowTables => {
+                let datasets: Vec<Dataset> = Dataset::all()
+                    .into_iter()
+                    .chain(self.context.variables())
+                    .collect();
+                (list_tables(&datasets), Vec::new())
+            }
+            Command::ShowColumns(dataset) => {
+                let table = process_operator(&Operator::From(dataset), &mut self.context)?;
+                (list_columns(&table), Vec::new())
+            }
+            Command::Save { dataset, path } => {
+                self.context.save_dataset(&dataset, &path)?;
+                let table = process_operator(&Operator::From(dataset), &mut self.context)?;
+                (table, Vec::new())
+            }
+            Command::InputError(error) => return Err(EngineError::ParseError(error)),
+            _ => return Err(EngineError::NotAQuery(query.to_string())),
+        };
+        Ok(QueryResult {
+            table,
+            rows_scanned: self.context.take_rows_scanned(),
+            elapsed: start.elapsed(),
+            warnings,
+        })
+    }
+}
+
+/// Runs the `operator` chain of a query in the `context`, as [`QueryEngine::execute`] does, e.g.
+/// for the REPL, which keeps its own [`ExecutionContext`].
+///
+/// # Arguments
+/// `operator` : The operator chain to run, as parsed. It is optimized before it runs.
+/// `context` : The [`ExecutionContext`] the query is processed in.
+/// `cached` : Whether to return the previous result of the same query while the datasets it read
+/// are unchanged. See [`process_cached`].
+///
+/// # Returns
+/// On success: The [`QueryResult`], whose `elapsed` time doesn't count printing the results.
+/// On failure: The [`OperatorError`] that stopped the query.
+pub fn run_operator(
+    operator: &Operator,
+    context: &mut ExecutionContext,
+    cached: bool,
+) -> Result<QueryResult, OperatorError> {
+    let warnings = lint_operator(operator);
+    context.take_rows_scanned();
+    let start = Instant::now();
+    let table = match cached {
+        true => process_cached(&optimize(operator), context)?,
+        false => process_operator(&optimize(operator), context)?,
+    };
+    Ok(QueryResult {
+        table,
+        rows_scanned: context.take_rows_scanned(),
+        elapsed: start.elapsed(),
+        warnings,
+    })
+}
+
+#[test]
+fn test_query_engine_execute() {
+    let mut engine = QueryEngine::new();
+    let table = engine.execute("FROM language.csv TAKE 2").unwrap().table;
+    assert_eq!(table.header, vec!["CountryCode", "Language"]);
+    assert_eq!(table.rows.len(), 2);
+    // Trailing newlines are accepted.
+    assert!(engine.execute("FROM language.csv TAKE 2\n").is_ok());
+}
+
+#[test]
+fn test_query_engine_execute_errors() {
+    let mut engine = QueryEngine::new();
+    assert_eq!(
+        engine.execute("FRM city.csv").unwrap_err().to_string(),
+        "Malformed input. Invalid Input: FRM is not an operator."
+    );
+    // The parse errors can be told apart without looking at their message.
+    match engine.execute("FROM city.csv TAKE ten").unwrap_err() {
+        EngineError::ParseError(ParseError::InvalidNumber { value, span, .. }) => {
+            assert_eq!(value, "ten");
+            assert_eq!((span.start, span.end), (19, 22));
+        }
+        error => panic!("Unexpected error: {}", error),
+    }
+    assert_eq!(
+        engine.execute("help").unwrap_err().to_string(),
+        "Not a query: \"help\""
+    );
+    assert_eq!(
+        engine
+            .execute("FROM city.csv ORDERBY CityName")
+            .unwrap_err()
+            .to_string(),
+        "You attempted to ORDERBY the CityName column whose type is not numeric."
+    );
+}
+
+#[test]
+fn test_query_engine_insert_delete() {
+    let mut engine = QueryEngine::new();
+    let table = engine
+        .execute("INSERT INTO language.csv VALUES (ATL, Atlantean), (ATL, 'Old Atlantean')")
+        .unwrap()
+        .table;
+    assert_eq!(table.rows.len(), 986);
+    assert_eq!(table.rows[985].join(), "ATL,Old Atlantean");
+    let table = engine
+        .execute("DELETE FROM language.csv WHERE CountryCode = ABW")
+        .unwrap()
+        .table;
+    assert_eq!(table.rows.len(), 982);
+    let table = engine
+        .execute("FROM language.csv FILTER CountryCode = ATL")
+        .unwrap()
+        .table;
+    assert_eq!(table.rows.len(), 2);
+}
+
+#[test]
+fn test_query_engine_set() {
+    let mut engine = QueryEngine::new();
+    let big = engine
+        .execute("SET big = FROM city.csv WHERE CityPop > 9000000")
+        .unwrap()
+        .table;
+    assert_eq!(big.rows.len(), 6);
+    let table = engine
+        .execute("FROM @big JOIN country.csv CountryCode SELECT CityName,CountryName TAKE 1")
+        .unwrap()
+        .table;
+    assert!(table.rows[0].join().ends_with(",Brazil"));
+    assert_eq!(
+        QueryEngine::new()
+            .execute("FROM @big")
+            .unwrap_err()
+            .to_string(),
+        "No result is saved as @big. Use SET big = <query> to save one."
+    );
+}
+
+#[test]
+fn test_query_engine_show() {
+    let mut engine = QueryEngine::new();
+    engine.execute("SET big = FROM city.csv TAKE 1").unwrap();
+    let tables = engine.execute("SHOW TABLES").unwrap().table;
+    assert_eq!(
+        tables.rows.last().unwrap().join(),
+        "@big,variable,".to_string()
+    );
+    assert_eq!(tables.rows.len(), Dataset::all().len() + 1);
+    let columns = engine.execute("SHOW COLUMNS @big").unwrap().table;
+    assert_eq!(columns.rows[0].join(), "CityID,integer,yes");
+}
+
+#[test]
+fn test_query_engine_execute_command() {
+    let mut engine = QueryEngine::new();
+    let command = crate::sql::parse_sql_command(
+        "SELECT CityName FROM city LIMIT 1\n",
+        &crate::data::DatasetRegistry::default(),
+    );
+    let table = engine.execute_command(command, "SELECT").unwrap().table;
+    assert_eq!(table.rows[0].join(), "Kabul");
+    assert_eq!(
+        engine
+            .execute_command(Command::Exit, "exit")
+            .unwrap_err()
+            .to_string(),
+        "Not a query: \"exit\""
+    );
+    engine.set_limits(ResultLimits {
+        max_rows: Some(10),
+        max_bytes: None,
+    });
+    assert!(engine.execute("FROM city").is_err());
+}
+
+#[test]
+fn test_query_engine_send_sync() {
+    // Each client of a `--listen` server has its own engine, running on its own thread.
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<QueryEngine>();
+}
+
+#[test]
+fn test_query_engine_with_functions() {
+    let functions = ScriptFunctions::from_source("fn twice(n) { n * 2 }").unwrap();
+    let mut engine = QueryEngine::with_functions(functions);
+    let table = engine
+        .execute("FROM country.csv SELECT twice(CountryPop) TAKE 1")
+        .unwrap()
+        .table;
+    assert_eq!(table.rows[0].join(), "206000");
+}
+
+#[test]
+fn test_run_operator() {
+    let mut context = ExecutionContext::default();
+    let operator = Operator::Filter {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        predicate: crate::expression::parse_predicate("Continent", "=", "Antarctica").unwrap(),
+    };
+    let result = run_operator(&operator, &mut context, true).unwrap();
+    assert_eq!(result.table.rows.len(), 5);
+    // FILTER reads every row of the dataset.
+    assert_eq!(result.rows_scanned, 239);
+    // A result reused from the cache reads none.
+    let cached = run_operator(&operator, &mut context, true).unwrap();
+    assert_eq!(cached.table, result.table);
+    assert_eq!(cached.rows_scanned, 0);
+    assert_eq!(
+        run_operator(&operator, &mut context, false)
+            .unwrap()
+            .rows_scanned,
+        239
+    );
+}
@@ -0,0 +1,382 @@
+use crate::operators::{process_operator, ExecutionContext, Operator, OperatorError};
+use crate::profile::OperatorProfile;
+
+/// Returns the text describing a single `operator` (not its chain) in the plan. WITH is described
+/// by the names of its sub-queries, and a JOIN against a sub-query and a UNION by "(subquery)", as
+/// their definitions are shown below them.
+fn plan_label(operator: &Operator) -> String {
+    match operator {
+        Operator::Join {
+            column,
+            right_column,
+            kind,
+            strategy,
+            ..
+        } if operator.subquery().is_some() => {
+            let label = match right_column {
+                None => format!("{} (subquery) {}", kind, column),
+                Some(right_column) => {
+                    format!("{} (subquery) ON {}={}", kind, column, right_column)
+                }
+            };
+            match strategy {
+                Some(strategy) => format!("{} USING {}", label, strategy),
+                None => label,
+            }
+        }
+        Operator::Union { all: true, .. } => "UNION ALL (subquery)".to_string(),
+        Operator::Union { .. } => "UNION (subquery)".to_string(),
+        Operator::With { ctes, .. } => format!(
+            "WITH {}",
+            ctes.iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+        _ => operator.label(),
+    }
+}
+
+/// Helper function to append the `operator` and its chain to the `tree`, one operator per line,
+/// each indented one level deeper than the operator it feeds. Each line ends with the text
+/// returned by `annotate` for its operator.
+fn write_tree(
+    operator: &Operator,
+    depth: usize,
+    tree: &mut String,
+    annotate: &dyn Fn(&Operator) -> String,
+) {
+    let indent = "  ".repeat(depth);
+    tree.push_str(&format!(
+        "{}{}{}\n",
+        indent,
+        plan_label(operator),
+        annotate(operator)
+    ));
+    if let Operator::With { ctes, .. } = operator {
+        for (name, cte) in ctes {
+            tree.push_str(&format!("{}  {} AS\n", indent, name));
+            write_tree(cte, depth + 2, tree, annotate);
+        }
+    }
+    if let Some(subquery) = operator.subquery() {
+        tree.push_str(&format!("{}  (subquery)\n", indent));
+        write_tree(subquery, depth + 2, tree, annotate);
+    }
+    if let Some(chain) = operator.chain() {
+        write_tree(chain, depth + 1, tree, annotate);
+    }
+}
+
+/// Helper function to append the operators of the `operator` chain to `order` in the order they
+/// are evaluated in: every operator after the operators it chains, the sub-queries of a WITH
+/// before its query, and the sub-query of a JOIN or a UNION after the chain it is combined with.
+fn evaluation_order<'a>(operator: &'a Operator, order: &mut Vec<&'a Operator>) {
+    if let Operator::With { ctes, .. } = operator {
+        for (_, cte) in ctes {
+            evaluation_order(cte, order);
+        }
+    }
+    if let Some(chain) = operator.chain() {
+        evaluation_order(chain, order);
+    }
+    if let Some(subquery) = operator.subquery() {
+        evaluation_order(subquery, order);
+    }
+    order.push(operator);
+}
+
+/// Describes how the `operator` chain would be evaluated, without evaluating it.
+///
+/// # Arguments
+/// `operator` : The operator chain to describe.
+///
+/// # Returns
+/// The operator tree, with the last operator of the chain first and each operator indented below
+/// the operator it feeds, followed by the numbered list of the operators in the order they are
+/// evaluated in and whether each one streams its rows or processes its whole input at once.
+pub fn explain_operator(operator: &Operator) -> String {
+    let mut plan = String::from("Operator tree:\n");
+    write_tree(operator, 1, &mut plan, &|_| String::new());
+
+    plan.push_str("Evaluation order:\n");
+    let mut order = Vec::new();
+    evaluation_order(operator, &mut order);
+    for (step, operator) in order.iter().enumerate() {
+        plan.push_str(&format!(
+            "  {}. {} - {}\n",
+            step + 1,
+            plan_label(operator),
+            if operator.is_streaming() {
+                "streams its rows"
+            } else {
+                "processes its whole input at once"
+            }
+        ));
+    }
+    plan
+}
+
+/// Helper function to format a duration as a number of milliseconds, e.g. "1.250ms".
+fn format_millis(duration: std::time::Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Runs the `operator` chain and describes how it was evaluated.
+///
+/// # Arguments
+/// `operator` : The operator chain to run.
+/// `context` : The [`ExecutionContext`] to run it in. Its collected profile, if any, is left
+/// untouched.
+///
+/// # Returns
+/// On success: The operator tree, like [`explain_operator`], with each operator annotated with
+/// the number of rows it produced, the time spent in it and the operators it chains, and the time
+/// spent in it alone. The rows of the result themselves are discarded.
+/// On failure: The [`OperatorError`] that stopped the query.
+pub fn explain_analyze(
+    operator: &Operator,
+    context: &mut ExecutionContext,
+) -> Result<String, OperatorError> {
+    let previous = context.profile.replace(Vec::new());
+    let result = process_operator(operator, context);
+    let profile = std::mem::replace(&mut context.profile, previous).unwrap_or_default();
+    let result = result?;
+
+    // Every operator's profile is recorded once it has been opened, i.e., after the operators it
+    // chains, so the profile follows the evaluation order.
+    let mut order = Vec::new();
+    evaluation_order(operator, &mut order);
+    let profiles: Vec<(&Operator, &OperatorProfile)> = order.into_iter().zip(&profile).collect();
+    let annotate = |operator: &Operator| match profiles
+        .iter()
+        .find(|(profiled, _)| std::ptr::eq(*profiled, operator))
+    {
+        Some((_, profile)) => format!(
+            " ({} {}, {} total, {} self)",
+            profile.rows,
+            if profile.rows == 1 { "row" } else { "rows" },
+            format_millis(profile.elapsed),
+            format_millis(profile.self_elapsed)
+        ),
+        None => " (not run)".to_string(),
+    };
+
+    let mut plan = String::from("Operator tree:\n");
+    write_tree(operator, 1, &mut plan, &annotate);
+    plan.push_str(&format!(
+        "Produced {} {} in {}.\n",
+        result.rows.len(),
+        if result.rows.len() == 1 {
+            "row"
+        } else {
+            "rows"
+        },
+        format_millis(
+            profile
+                .last()
+                .map_or_else(Default::default, |last| last.elapsed)
+        )
+    ));
+    Ok(plan)
+}
+
+#[cfg(test)]
+fn parse_query(query: &str) -> Operator {
+    match crate::commands::parse_command(&format!("{}\n", query)) {
+        crate::commands::Command::Operator(operator) => operator,
+        command => panic!("Not a query: {:?}", command),
+    }
+}
+
+#[test]
+fn test_explain_operator() {
+    assert_eq!(
+        explain_operator(&parse_query(
+            "FROM city.csv FILTER CityPop > 1000000 ORDERBY CityPop TAKE 5"
+        )),
+        "Operator tree:
+  TAKE 5
+    ORDERBY CityPop
+      FILTER CityPop > 1000000
+        FROM city.csv
+Evaluation order:
+  1. FROM city.csv - streams its rows
+  2. FILTER CityPop > 1000000 - streams its rows
+  3. ORDERBY CityPop - processes its whole input at once
+  4. TAKE 5 - streams its rows
+"
+    );
+}
+
+#[test]
+fn test_explain_operator_from() {
+    assert_eq!(
+        explain_operator(&parse_query("FROM language.csv")),
+        "Operator tree:\n  FROM language.csv\nEvaluation order:\n  1. FROM language.csv - streams its rows\n"
+    );
+}
+
+#[test]
+fn test_explain_operator_with() {
+    assert_eq!(
+        explain_operator(&parse_query(
+            "WITH euro AS (FROM country.csv FILTER Continent = Europe) FROM city.csv JOIN euro CountryCode"
+        )),
+        "Operator tree:
+  WITH euro
+    euro AS
+      FILTER Continent = Europe
+        FROM country.csv
+    JOIN euro CountryCode
+      FROM city.csv
+Evaluation order:
+  1. FROM country.csv - streams its rows
+  2. FILTER Continent = Europe - streams its rows
+  3. FROM city.csv - streams its rows
+  4. JOIN euro CountryCode - processes its whole input at once
+  5. WITH euro - streams its rows
+"
+    );
+}
+
+#[test]
+fn test_explain_operator_subquery() {
+    assert_eq!(
+        explain_operator(&parse_query(
+            "FROM city.csv JOIN (FROM country.csv FILTER Continent = Europe) ON CountryCode=CountryCode TAKE 3"
+        )),
+        "Operator tree:
+  TAKE 3
+    JOIN (subquery) CountryCode
+      (subquery)
+        FILTER Continent = Europe
+          FROM country.csv
+      FROM city.csv
+Evaluation order:
+  1. FROM city.csv - streams its rows
+  2. FROM country.csv - streams its rows
+  3. FILTER Continent = Europe - streams its rows
+  4. JOIN (subquery) CountryCode - processes its whole input at once
+  5. TAKE 3 - streams its rows
+"
+    );
+}
+
+#[test]
+fn test_explain_operator_union() {
+    assert_eq!(
+        explain_operator(&parse_query(
+            "FROM country.csv SELECT CountryCode UNION ALL (FROM language.csv SELECT CountryCode) DISTINCT"
+        )),
+        "Operator tree:
+  DISTINCT
+    UNION ALL (subquery)
+      (subquery)
+        SELECT CountryCode
+          FROM language.csv
+      SELECT CountryCode
+        FROM country.csv
+Evaluation order:
+  1. FROM country.csv - streams its rows
+  2. SELECT CountryCode - streams its rows
+  3. FROM language.csv - streams its rows
+  4. SELECT CountryCode - streams its rows
+  5. UNION ALL (subquery) - streams its rows
+  6. DISTINCT - streams its rows
+"
+    );
+}
+
+/// Helper function to replace the timings in the output of [`explain_analyze`] with `?ms`.
+#[cfg(test)]
+fn strip_timings(plan: &str) -> String {
+    plan.split(' ')
+        .map(|word| match word.find("ms") {
+            Some(end) if word[..end].parse::<f64>().is_ok() => format!("?{}", &word[end..]),
+            _ => word.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[test]
+fn test_explain_analyze() {
+    let mut context = ExecutionContext::default();
+    let plan = explain_analyze(
+        &parse_query("FROM city.csv FILTER CityPop > 9000000 ORDERBY CityPop TAKE 2"),
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(
+        strip_timings(&plan),
+        "Operator tree:
+  TAKE 2 (2 rows, ?ms total, ?ms self)
+    ORDERBY CityPop (2 rows, ?ms total, ?ms self)
+      FILTER CityPop > 9000000 (6 rows, ?ms total, ?ms self)
+        FROM city.csv (4079 rows, ?ms total, ?ms self)
+Produced 2 rows in ?ms.
+"
+    );
+    // Profiling is only enabled while the query runs.
+    assert_eq!(context.profile, None);
+}
+
+#[test]
+fn test_explain_analyze_with() {
+    let mut context = ExecutionContext::with_profiling();
+    let plan = explain_analyze(
+        &parse_query("WITH euro AS (FROM country.csv FILTER Continent = Europe) FROM euro TAKE 1"),
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(
+        strip_timings(&plan),
+        "Operator tree:
+  WITH euro (1 row, ?ms total, ?ms self)
+    euro AS
+      FILTER Continent = Europe (46 rows, ?ms total, ?ms self)
+        FROM country.csv (239 rows, ?ms total, ?ms self)
+    TAKE 1 (1 row, ?ms total, ?ms self)
+      FROM euro (1 row, ?ms total, ?ms self)
+Produced 1 row in ?ms.
+"
+    );
+    // The profile collected for the session isn't affected.
+    assert_eq!(context.take_profile(), Some(vec![]));
+}
+
+#[test]
+fn test_explain_analyze_subquery() {
+    let mut context = ExecutionContext::default();
+    let plan = explain_analyze(
+        &parse_query(
+            "FROM city.csv TAKE 10 JOIN (FROM country.csv FILTER Continent = Europe) CountryCode",
+        ),
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(
+        strip_timings(&plan),
+        "Operator tree:
+  JOIN (subquery) CountryCode (6 rows, ?ms total, ?ms self)
+    (subquery)
+      FILTER Continent = Europe (46 rows, ?ms total, ?ms self)
+        FROM country.csv (239 rows, ?ms total, ?ms self)
+    TAKE 10 (10 rows, ?ms total, ?ms self)
+      FROM city.csv (10 rows, ?ms total, ?ms self)
+Produced 6 rows in ?ms.
+"
+    );
+}
+
+#[test]
+fn test_explain_analyze_error() {
+    let mut context = ExecutionContext::default();
+    let result = explain_analyze(&parse_query("FROM city.csv ORDERBY CityName"), &mut context);
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "You attempted to ORDERBY the CityName column whose type is not numeric."
+    );
+    assert_eq!(context.profile, None);
+}
@@ -0,0 +1,1719 @@
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+use crate::function::ScalarFunction;
+use crate::lexer::{quote, unescape, unquote};
+use crate::operators::{ExecutionContext, OperatorError};
+use crate::table::{Cell, Row};
+
+/// The arithmetic operators that combine the values of two [`Expression`]s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithmeticOperator {
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+}
+
+impl Display for ArithmeticOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", self.symbol()))
+    }
+}
+
+impl ArithmeticOperator {
+    /// Returns the character the operator is written as, e.g. `+`.
+    pub fn symbol(&self) -> char {
+        match self {
+            ArithmeticOperator::Add => '+',
+            ArithmeticOperator::Subtract => '-',
+            ArithmeticOperator::Multiply => '*',
+            ArithmeticOperator::Divide => '/',
+        }
+    }
+
+    /// Looks up an operator by the character it is written as, e.g. `+`.
+    pub fn from_symbol(symbol: char) -> Option<ArithmeticOperator> {
+        match symbol {
+            '+' => Some(ArithmeticOperator::Add),
+            '-' => Some(ArithmeticOperator::Subtract),
+            '*' => Some(ArithmeticOperator::Multiply),
+            '/' => Some(ArithmeticOperator::Divide),
+            _ => None,
+        }
+    }
+
+    /// Returns how tightly the operator binds its operands: `*` and `/` are applied before `+`
+    /// and `-`.
+    fn precedence(&self) -> u8 {
+        match self {
+            ArithmeticOperator::Add | ArithmeticOperator::Subtract => 1,
+            ArithmeticOperator::Multiply | ArithmeticOperator::Divide => 2,
+        }
+    }
+
+    /// Combines the `left` and `right` values with the operator.
+    ///
+    /// # Returns
+    /// On success: A [`Cell::Int64`] when adding, subtracting, or multiplying two integers, and a
+    /// [`Cell::Float64`] otherwise; division always produces a decimal number. If either value is
+    /// missing, or when dividing by zero, the result is missing ([`Cell::Null`]).
+    /// On failure: A message describing why the values can't be combined, e.g. because one of
+    /// them is a string or the result doesn't fit in an integer.
+    pub fn apply(&self, left: &Cell, right: &Cell) -> Result<Cell, String> {
+        if left.is_null() || right.is_null() {
+            return Ok(Cell::Null);
+        }
+        let (left_value, right_value) = match (left.as_f64(), right.as_f64()) {
+            (Some(left_value), Some(right_value)) => (left_value, right_value),
+            (None, _) => return Err(format!("{} is not a number.", left)),
+            (_, None) => return Err(format!("{} is not a number.", right)),
+        };
+        let too_large = || format!("The result of {} {} {} is too large.", left, self, right);
+        match (left, right) {
+            (
+                Cell::Int64(left) | Cell::OptInt64(Some(left)),
+                Cell::Int64(right) | Cell::OptInt64(Some(right)),
+            ) if *self != ArithmeticOperator::Divide => {
+                let result = match self {
+                    ArithmeticOperator::Add => left.checked_add(*right),
+                    ArithmeticOperator::Subtract => left.checked_sub(*right),
+                    // Division always produces a decimal number, see below.
+                    _ => left.checked_mul(*right),
+                };
+                result.map(Cell::Int64).ok_or_else(too_large)
+            }
+            _ => {
+                let result = match self {
+                    ArithmeticOperator::Add => left_value + right_value,
+                    ArithmeticOperator::Subtract => left_value - right_value,
+                    ArithmeticOperator::Multiply => left_value * right_value,
+                    ArithmeticOperator::Divide if right_value == 0.0 => return Ok(Cell::Null),
+                    ArithmeticOperator::Divide => left_value / right_value,
+                };
+                if result.is_finite() {
+                    Ok(Cell::Float64(result))
+                } else {
+                    Err(too_large())
+                }
+            }
+        }
+    }
+}
+
+/// An expression computing a value from the [`Cell`]s of a [`Row`].
+/// These are parsed from the arguments of the SELECT operator.
+/// Example:
+/// 'slug(CityName)' will parse to
+/// ```text
+/// Expression::Call {
+///   function: "slug",
+///   arguments: vec![Expression::Column("CityName")],
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// The value of the named column.
+    Column(String),
+    /// A constant value, i.e., a number or a quoted string.
+    Literal(Cell),
+    /// The value returned by calling a function on the values of the `arguments`: either a
+    /// user-defined one (see [`crate::script::ScriptFunctions`]) or a built-in one (see
+    /// [`ScalarFunction`]).
+    Call {
+        /// The name of the function to call.
+        function: String,
+        /// The expressions whose values are passed to the function.
+        arguments: Vec<Expression>,
+    },
+    /// The values of two expressions combined with an arithmetic operator, e.g. `CityPop/1000`.
+    Arithmetic {
+        /// The operator combining the values.
+        operator: ArithmeticOperator,
+        /// The expression on the left hand side of the operator.
+        left: Box<Expression>,
+        /// The expression on the right hand side of the operator.
+        right: Box<Expression>,
+    },
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Column(name) => f.write_str(name),
+            Expression::Literal(Cell::String(val)) => f.write_str(&quote(val)),
+            Expression::Literal(cell) => f.write_fmt(format_args!("{}", cell)),
+            Expression::Call {
+                function,
+                arguments,
+            } => f.write_fmt(format_args!(
+                "{}({})",
+                function,
+                arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            )),
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => {
+                // Operands are parenthesized where needed to keep the order of evaluation, e.g.
+                // `(A+B)*C` or `A-(B-C)`.
+                let precedence = |operand: &Expression| match operand {
+                    Expression::Arithmetic { operator, .. } => operator.precedence(),
+                    _ => u8::MAX,
+                };
+                let write_operand = |f: &mut std::fmt::Formatter<'_>, operand, parenthesize| {
+                    if parenthesize {
+                        f.write_fmt(format_args!("({})", operand))
+                    } else {
+                        f.write_fmt(format_args!("{}", operand))
+                    }
+                };
+                write_operand(f, left, precedence(left) < operator.precedence())?;
+                f.write_fmt(format_args!("{}", operator))?;
+                write_operand(f, right, precedence(right) <= operator.precedence())
+            }
+        }
+    }
+}
+
+impl Expression {
+    /// Returns the names of all the columns referenced by the expression.
+    pub fn column_names(&self) -> Vec<&str> {
+        match self {
+            Expression::Column(name) => vec![name],
+            Expression::Literal(_) => vec![],
+            Expression::Call { arguments, .. } => arguments
+                .iter()
+                .flat_map(|argument| argument.column_names())
+                .collect(),
+            Expression::Arithmetic { left, right, .. } => {
+                let mut names = left.column_names();
+                names.append(&mut right.column_names());
+                names
+            }
+        }
+    }
+
+    /// Returns a copy of the expression with each referenced column renamed by `rename`, e.g. to
+    /// refer to `city.CityName` by the name of the column in the header.
+    pub fn rename_columns(&self, rename: &impl Fn(&str) -> String) -> Expression {
+        match self {
+            Expression::Column(name) => Expression::Column(rename(name)),
+            Expression::Literal(cell) => Expression::Literal(cell.clone()),
+            Expression::Call {
+                function,
+                arguments,
+            } => Expression::Call {
+                function: function.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| argument.rename_columns(rename))
+                    .collect(),
+            },
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => Expression::Arithmetic {
+                operator: *operator,
+                left: Box::new(left.rename_columns(rename)),
+                right: Box::new(right.rename_columns(rename)),
+            },
+        }
+    }
+
+    /// Computes the value of the expression for a `row`.
+    ///
+    /// # Arguments
+    /// `header` : The names of the columns of the table the `row` belongs to.
+    /// `row` : The row to compute the value for.
+    /// `context` : The [`ExecutionContext`] holding the user-defined functions. Built-in functions,
+    /// e.g. `UPPER`, can be called even without any user-defined ones.
+    ///
+    /// # Usage Note: The caller must guarantee that the columns referenced by the expression exist
+    /// in the `header`.
+    ///
+    /// # Returns
+    /// On success: The computed value.
+    /// On failure: [`OperatorError::FunctionError`] if a function doesn't exist or fails, or
+    /// [`OperatorError::ArithmeticError`] if the values of an arithmetic expression can't be
+    /// combined.
+    pub fn evaluate(
+        &self,
+        header: &[String],
+        row: &Row,
+        context: &ExecutionContext,
+    ) -> Result<Cell, OperatorError> {
+        match self {
+            Expression::Column(name) => {
+                let index = header.iter().position(|column| column == name).unwrap();
+                Ok(row.cells[index].clone())
+            }
+            Expression::Literal(cell) => Ok(cell.clone()),
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                let values = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(header, row, context))
+                    .collect::<Result<Vec<Cell>, OperatorError>>()?;
+                // Functions defined in the script take precedence over the built-in ones.
+                let result = match (&context.functions, ScalarFunction::from_name(function)) {
+                    (Some(functions), _) if functions.has_function(function, values.len()) => {
+                        functions.call(function, values)
+                    }
+                    (_, Some(builtin)) => builtin.apply(&values),
+                    _ => Err(format!(
+                        "No function called {} taking {} argument(s) is defined.",
+                        function,
+                        values.len()
+                    )),
+                };
+                result.map_err(|message| OperatorError::FunctionError {
+                    function: function.clone(),
+                    message,
+                })
+            }
+            Expression::Arithmetic {
+                operator,
+                left,
+                right,
+            } => {
+                let left_value = left.evaluate(header, row, context)?;
+                let right_value = right.evaluate(header, row, context)?;
+                operator
+                    .apply(&left_value, &right_value)
+                    .map_err(|message| OperatorError::ArithmeticError {
+                        expression: self.to_string(),
+                        message,
+                    })
+            }
+        }
+    }
+}
+
+/// A column produced by the SELECT operator: an expression, optionally renamed with `AS`.
+/// Example:
+/// 'CityName AS Name' will parse to
+/// ```text
+/// SelectColumn {
+///   expression: Expression::Column("CityName"),
+///   alias: Some("Name"),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectColumn {
+    /// The expression computing the values of the column.
+    pub expression: Expression,
+    /// The name given to the column with `AS`, if any.
+    pub alias: Option<String>,
+}
+
+impl Display for SelectColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.alias {
+            Some(alias) => f.write_fmt(format_args!("{} AS {}", self.expression, alias)),
+            None => f.write_fmt(format_args!("{}", self.expression)),
+        }
+    }
+}
+
+impl From<Expression> for SelectColumn {
+    fn from(expression: Expression) -> Self {
+        SelectColumn {
+            expression,
+            alias: None,
+        }
+    }
+}
+
+impl SelectColumn {
+    /// Returns the name of the column in the output of SELECT: the alias if there is one, and
+    /// the text of the expression otherwise.
+    pub fn name(&self) -> String {
+        match &self.alias {
+            Some(alias) => alias.clone(),
+            None => self.expression.to_string(),
+        }
+    }
+}
+
+/// Helper to walk over the characters of the expression text while parsing it.
+struct Parser<'a> {
+    /// The text being parsed.
+    text: &'a str,
+    /// The byte offset of the next character to parse.
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the next character without consuming it.
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    /// Consumes characters while `predicate` holds and returns them.
+    fn take_while<P: FnMut(char) -> bool>(&mut self, mut predicate: P) -> &'a str {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if !predicate(c) {
+                break;
+            }
+            self.position += c.len_utf8();
+        }
+        &self.text[start..self.position]
+    }
+
+    /// Consumes the next character if it is `expected`.
+    fn consume(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the arithmetic operator following the next character, ignoring any spaces around
+    /// it, along with the position of the operand that follows it.
+    fn peek_operator(&self) -> Option<(ArithmeticOperator, usize)> {
+        let rest = self.text[self.position..].trim_start_matches(' ');
+        let operator = ArithmeticOperator::from_symbol(rest.chars().next()?)?;
+        let operand = rest[1..].trim_start_matches(' ');
+        Some((operator, self.text.len() - operand.len()))
+    }
+
+    /// Parses a single expression: operands combined with arithmetic operators, e.g.
+    /// `CountryPop - CityPop*2`.
+    fn parse_expression(&mut self) -> Result<Expression, String> {
+        self.parse_arithmetic(0)
+    }
+
+    /// Parses operands combined with arithmetic operators binding at least as tightly as
+    /// `min_precedence`. Operators of the same precedence are applied from left to right.
+    fn parse_arithmetic(&mut self, min_precedence: u8) -> Result<Expression, String> {
+        let mut left = self.parse_operand()?;
+        while let Some((operator, operand)) = self.peek_operator() {
+            if operator.precedence() < min_precedence {
+                break;
+            }
+            self.position = operand;
+            let right = self.parse_arithmetic(operator.precedence() + 1)?;
+            left = Expression::Arithmetic {
+                operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    /// Parses a single operand: a quoted string, a number, a column name, a function call, or an
+    /// expression in parentheses.
+    fn parse_operand(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some('(') => {
+                self.consume('(');
+                self.take_while(|c| c == ' ');
+                let expression = self.parse_expression()?;
+                self.take_while(|c| c == ' ');
+                if !self.consume(')') {
+                    return Err(format!("Expected ')' in expression: {}", self.text));
+                }
+                Ok(expression)
+            }
+            Some(quote) if quote == '\'' || quote == '"' => {
+                self.consume(quote);
+                // A quote escaped with a backslash doesn't end the string.
+                let mut escaped = false;
+                let val = self.take_while(|c| {
+                    let end = !escaped && c == quote;
+                    escaped = !escaped && c == '\\';
+                    !end
+                });
+                if !self.consume(quote) {
+                    return Err(format!("Unterminated string in expression: {}", self.text));
+                }
+                Ok(Expression::Literal(Cell::String(unescape(val))))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.position;
+                self.consume('-');
+                self.take_while(|c| c.is_ascii_digit());
+                let is_decimal = self.consume('.');
+                if is_decimal {
+                    self.take_while(|c| c.is_ascii_digit());
+                }
+                let number = &self.text[start..self.position];
+                let literal = if is_decimal {
+                    str::parse::<f64>(number)
+                        .map(Cell::Float64)
+                        .map_err(|e| e.to_string())
+                } else {
+                    str::parse::<i64>(number)
+                        .map(Cell::Int64)
+                        .map_err(|e| e.to_string())
+                };
+                match literal {
+                    Ok(literal) => Ok(Expression::Literal(literal)),
+                    Err(e) => Err(format!(
+                        "Invalid number in expression: {}. Full error message: {}",
+                        number, e
+                    )),
+                }
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '.');
+                if !self.consume('(') {
+                    return Ok(Expression::Column(name.to_string()));
+                }
+                let mut arguments = Vec::new();
+                if !self.consume(')') {
+                    // The arguments may be separated by spaces, e.g. `COALESCE(Capital, 0)`.
+                    loop {
+                        self.take_while(|c| c == ' ');
+                        arguments.push(self.parse_expression()?);
+                        self.take_while(|c| c == ' ');
+                        if self.consume(')') {
+                            break;
+                        }
+                        if !self.consume(',') {
+                            return Err(format!(
+                                "Expected ',' or ')' after the arguments to {} in: {}",
+                                name, self.text
+                            ));
+                        }
+                    }
+                }
+                Ok(Expression::Call {
+                    function: name.to_string(),
+                    arguments,
+                })
+            }
+            Some(c) => Err(format!(
+                "Unexpected character '{}' in expression: {}",
+                c, self.text
+            )),
+            None => Err(format!("Incomplete expression: {}", self.text)),
+        }
+    }
+
+    /// Parses an expression optionally followed by ` AS <name>`.
+    fn parse_select_column(&mut self) -> Result<SelectColumn, String> {
+        let expression = self.parse_expression()?;
+        if !self.text[self.position..].starts_with(" AS ") {
+            return Ok(expression.into());
+        }
+        self.position += " AS ".len();
+        let alias = self.take_while(|c| c.is_alphanumeric() || c == '_');
+        if alias.is_empty() {
+            return Err(format!("Expected a column name after AS in: {}", self.text));
+        }
+        Ok(SelectColumn {
+            expression,
+            alias: Some(alias.to_string()),
+        })
+    }
+
+    /// Parses the name of a column followed by `=` and the expression computing its values, e.g.
+    /// `PopDensity = CityPop / 1000`. The `=` may be surrounded by spaces.
+    fn parse_named_expression(&mut self) -> Result<(String, Expression), String> {
+        let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+        if name.is_empty() {
+            return Err(format!("Expected a column name in: {}", self.text));
+        }
+        self.take_while(|c| c == ' ');
+        if !self.consume('=') {
+            return Err(format!("Expected '=' after {} in: {}", name, self.text));
+        }
+        self.take_while(|c| c == ' ');
+        Ok((name.to_string(), self.parse_expression()?))
+    }
+
+    /// Parses a comma separated list of items with `parse_item`, ignoring empty entries.
+    fn parse_list<T, F: Fn(&mut Self) -> Result<T, String>>(
+        &mut self,
+        parse_item: F,
+    ) -> Result<Vec<T>, String> {
+        let mut items = Vec::new();
+        loop {
+            while self.consume(',') {}
+            if self.peek().is_none() {
+                return Ok(items);
+            }
+            items.push(parse_item(self)?);
+            match self.peek() {
+                Some(',') | None => (),
+                Some(c) => {
+                    return Err(format!(
+                        "Unexpected character '{}' in expression: {}",
+                        c, self.text
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Parses a comma separated list of expressions, e.g. the argument to SELECT. Empty entries in the
+/// list are ignored.
+///
+/// # Arguments
+/// `text` : The text to parse.
+///
+/// # Returns
+/// The parsed [`Expression`]s, or an error message describing why `text` is malformed.
+pub fn parse_expression_list(text: &str) -> Result<Vec<Expression>, String> {
+    Parser { text, position: 0 }.parse_list(Parser::parse_expression)
+}
+
+/// Parses the argument to SELECT: a comma separated list of expressions, each optionally renamed
+/// with `AS`, e.g. `CityName AS Name,CityPop`. Empty entries in the list are ignored.
+///
+/// # Arguments
+/// `text` : The text to parse. The tokens around each `AS` must be separated by a single space.
+///
+/// # Returns
+/// The parsed [`SelectColumn`]s, or an error message describing why `text` is malformed.
+pub fn parse_select_list(text: &str) -> Result<Vec<SelectColumn>, String> {
+    Parser { text, position: 0 }.parse_list(Parser::parse_select_column)
+}
+
+/// Parses the argument to EXTEND: a comma separated list of column names, each followed by `=` and
+/// the expression computing the column, e.g. `PopDensity = CityPop / 1000,Name = UPPER(CityName)`.
+/// Empty entries in the list are ignored.
+///
+/// # Arguments
+/// `text` : The text to parse.
+///
+/// # Returns
+/// The names of the columns and their parsed [`Expression`]s, or an error message describing why
+/// `text` is malformed.
+pub fn parse_extend_list(text: &str) -> Result<Vec<(String, Expression)>, String> {
+    Parser { text, position: 0 }.parse_list(Parser::parse_named_expression)
+}
+
+/// Parses a single expression, e.g. the left hand side of a [`Predicate`].
+///
+/// # Arguments
+/// `text` : The text to parse.
+///
+/// # Returns
+/// The parsed [`Expression`], or an error message describing why `text` is malformed.
+pub fn parse_expression(text: &str) -> Result<Expression, String> {
+    let mut parser = Parser { text, position: 0 };
+    let expression = parser.parse_expression()?;
+    match parser.peek() {
+        None => Ok(expression),
+        Some(c) => Err(format!(
+            "Unexpected character '{}' in expression: {}",
+            c, text
+        )),
+    }
+}
+
+/// Parses the rows passed to `INSERT INTO <dataset> VALUES`: comma separated lists of values in
+/// parentheses, themselves separated by commas, e.g. `(1, 'Xi\'an', NULL), (2, Lhasa, 5)`. A value
+/// is a quoted string, a number, NULL for a missing value, or a single word standing for itself.
+///
+/// # Arguments
+/// `text` : The text to parse.
+///
+/// # Returns
+/// The values of each row, or an error message describing why `text` is malformed.
+pub fn parse_values(text: &str) -> Result<Vec<Vec<Cell>>, String> {
+    let mut parser = Parser { text, position: 0 };
+    let mut rows = Vec::new();
+    loop {
+        parser.take_while(|c| c == ' ');
+        if !parser.consume('(') {
+            return Err(format!(
+                "Expected '(' before the values of a row in: {}",
+                text
+            ));
+        }
+        let mut values = Vec::new();
+        loop {
+            parser.take_while(|c| c == ' ');
+            values.push(match parser.parse_operand()? {
+                Expression::Literal(value) => value,
+                Expression::Column(name) if name == "NULL" => Cell::Null,
+                Expression::Column(name) => Cell::String(name),
+                expression => {
+                    return Err(format!(
+                        "Expected a string, a number, or NULL instead of {} in: {}",
+                        expression, text
+                    ))
+                }
+            });
+            parser.take_while(|c| c == ' ');
+            if parser.consume(')') {
+                break;
+            }
+            if !parser.consume(',') {
+                return Err(format!(
+                    "Expected ',' or ')' after the values of a row in: {}",
+                    text
+                ));
+            }
+        }
+        rows.push(values);
+        parser.take_while(|c| c == ' ');
+        if parser.peek().is_none() {
+            return Ok(rows);
+        }
+        if !parser.consume(',') {
+            return Err(format!("Expected ',' between the rows in: {}", text));
+        }
+    }
+}
+
+#[test]
+fn test_parse_values() {
+    let rows = parse_values("(1, 'Xi\\'an', NULL), (-2,Lhasa,0.5)").unwrap();
+    assert_eq!(
+        rows.iter()
+            .map(|values| values.iter().map(Cell::to_string).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        vec![vec!["1", "Xi'an", ""], vec!["-2", "Lhasa", "0.5"]]
+    );
+    assert!(matches!(rows[0][0], Cell::Int64(1)));
+    assert!(matches!(rows[0][2], Cell::Null));
+    assert!(matches!(rows[1][2], Cell::Float64(_)));
+}
+
+#[test]
+fn test_parse_values_malformed() {
+    assert_eq!(
+        parse_values("1, 2").unwrap_err(),
+        "Expected '(' before the values of a row in: 1, 2"
+    );
+    assert_eq!(
+        parse_values("(1 2)").unwrap_err(),
+        "Expected ',' or ')' after the values of a row in: (1 2)"
+    );
+    assert_eq!(
+        parse_values("(1) (2)").unwrap_err(),
+        "Expected ',' between the rows in: (1) (2)"
+    );
+    assert_eq!(
+        parse_values("(upper(CityName))").unwrap_err(),
+        "Expected a string, a number, or NULL instead of upper(CityName) in: (upper(CityName))"
+    );
+    assert_eq!(
+        parse_values("(1,").unwrap_err(),
+        "Incomplete expression: (1,"
+    );
+}
+
+/// The comparisons supported by a [`Predicate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    /// `=`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessOrEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+    /// `IS NULL`, which holds for missing values.
+    IsNull,
+    /// `IS NOT NULL`, which holds for values that aren't missing.
+    IsNotNull,
+    /// `LIKE`, which holds for strings matching a pattern in which `%` stands for any sequence
+    /// of characters and `_` for any single character, or `ILIKE` if `ignore_case`.
+    Like { ignore_case: bool },
+    /// `CONTAINS`, which holds for strings containing the value, or `ICONTAINS` if `ignore_case`.
+    Contains { ignore_case: bool },
+    /// `STARTSWITH`, which holds for strings starting with the value, or `ISTARTSWITH` if
+    /// `ignore_case`.
+    StartsWith { ignore_case: bool },
+    /// `ENDSWITH`, which holds for strings ending with the value, or `IENDSWITH` if
+    /// `ignore_case`.
+    EndsWith { ignore_case: bool },
+    /// `MATCHES`, which holds for strings matching the value as a regular expression, or
+    /// `IMATCHES` if `ignore_case`.
+    Matches { ignore_case: bool },
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::IsNull => "IS NULL",
+            Comparison::IsNotNull => "IS NOT NULL",
+            Comparison::Like { ignore_case } => ["LIKE", "ILIKE"][*ignore_case as usize],
+            Comparison::Contains { ignore_case } => {
+                ["CONTAINS", "ICONTAINS"][*ignore_case as usize]
+            }
+            Comparison::StartsWith { ignore_case } => {
+                ["STARTSWITH", "ISTARTSWITH"][*ignore_case as usize]
+            }
+            Comparison::EndsWith { ignore_case } => {
+                ["ENDSWITH", "IENDSWITH"][*ignore_case as usize]
+            }
+            Comparison::Matches { ignore_case } => ["MATCHES", "IMATCHES"][*ignore_case as usize],
+        })
+    }
+}
+
+impl Comparison {
+    /// Returns `true` if the comparison holds for two values that compare as `ordering`.
+    fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::NotEqual => ordering != Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+            // These don't compare values by their order; see `Predicate::matches`.
+            Comparison::IsNull
+            | Comparison::IsNotNull
+            | Comparison::Like { .. }
+            | Comparison::Contains { .. }
+            | Comparison::StartsWith { .. }
+            | Comparison::EndsWith { .. }
+            | Comparison::Matches { .. } => false,
+        }
+    }
+
+    /// Returns `true` for the comparisons that only apply to strings, e.g. `LIKE`.
+    pub fn is_string_comparison(&self) -> bool {
+        matches!(
+            self,
+            Comparison::Like { .. }
+                | Comparison::Contains { .. }
+                | Comparison::StartsWith { .. }
+                | Comparison::EndsWith { .. }
+                | Comparison::Matches { .. }
+        )
+    }
+
+    /// Decides whether a string `text` matches the `pattern` of a string comparison other than
+    /// `MATCHES`, whose regular expressions are compiled by the [`ExecutionContext`].
+    ///
+    /// # Returns
+    /// Whether the comparison holds, or `None` if it isn't one of those string comparisons.
+    fn holds_for_string(&self, text: &str, pattern: &str) -> Option<bool> {
+        let (text, pattern) = match self {
+            Comparison::Like { ignore_case: true }
+            | Comparison::Contains { ignore_case: true }
+            | Comparison::StartsWith { ignore_case: true }
+            | Comparison::EndsWith { ignore_case: true } => {
+                (text.to_lowercase(), pattern.to_lowercase())
+            }
+            _ => (text.to_string(), pattern.to_string()),
+        };
+        match self {
+            Comparison::Like { .. } => Some(like(
+                &text.chars().collect::<Vec<char>>(),
+                &pattern.chars().collect::<Vec<char>>(),
+            )),
+            Comparison::Contains { .. } => Some(text.contains(&pattern)),
+            Comparison::StartsWith { .. } => Some(text.starts_with(&pattern)),
+            Comparison::EndsWith { .. } => Some(text.ends_with(&pattern)),
+            _ => None,
+        }
+    }
+}
+
+/// Helper function to match the characters of a `text` against a LIKE `pattern`, in which `%`
+/// stands for any sequence of characters and `_` for any single character.
+fn like(text: &[char], pattern: &[char]) -> bool {
+    // The position in the pattern after the last `%`, and the position in the text it was
+    // matched up to, to backtrack to when the rest of the pattern doesn't match.
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut t, mut p) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(c) if *c == '_' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((after, matched)) => {
+                    p = after;
+                    t = matched + 1;
+                    backtrack = Some((after, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '%')
+}
+
+#[test]
+fn test_like() {
+    let like = |text: &str, pattern: &str| {
+        like(
+            &text.chars().collect::<Vec<char>>(),
+            &pattern.chars().collect::<Vec<char>>(),
+        )
+    };
+    assert!(like("San Jose", "San%"));
+    assert!(like("San Jose", "%Jose"));
+    assert!(like("San Jose", "%n J%"));
+    assert!(like("San Jose", "S_n _ose"));
+    assert!(like("San Jose", "%"));
+    assert!(like("", "%%"));
+    assert!(like("Abab", "%ab"));
+    assert!(like("aXbXc", "a%b%c"));
+    assert!(!like("San Jose", "San"));
+    assert!(!like("San Jose", "%San"));
+    assert!(!like("San", "San_"));
+    assert!(!like("São Paulo", "S_o"));
+    assert!(like("São Paulo", "S_o%"));
+}
+
+#[test]
+fn test_comparison_holds_for_string() {
+    let contains = Comparison::Contains { ignore_case: false };
+    let icontains = Comparison::Contains { ignore_case: true };
+    assert_eq!(contains.holds_for_string("New York", "York"), Some(true));
+    assert_eq!(contains.holds_for_string("New York", "york"), Some(false));
+    assert_eq!(icontains.holds_for_string("New York", "york"), Some(true));
+    let starts_with = Comparison::StartsWith { ignore_case: true };
+    assert_eq!(starts_with.holds_for_string("New York", "NEW"), Some(true));
+    assert_eq!(
+        starts_with.holds_for_string("New York", "York"),
+        Some(false)
+    );
+    let ends_with = Comparison::EndsWith { ignore_case: false };
+    assert_eq!(ends_with.holds_for_string("New York", "York"), Some(true));
+    assert_eq!(ends_with.holds_for_string("New York", "New"), Some(false));
+    let ilike = Comparison::Like { ignore_case: true };
+    assert_eq!(ilike.holds_for_string("San Jose", "san%"), Some(true));
+    assert_eq!(
+        Comparison::Like { ignore_case: false }.holds_for_string("San Jose", "san%"),
+        Some(false)
+    );
+    assert_eq!(Comparison::Equal.holds_for_string("a", "a"), None);
+}
+
+/// A condition on the values of a [`Row`], used by the FILTER operator to decide which rows to
+/// keep.
+/// Example:
+/// 'Continent = Europe' will parse to
+/// ```text
+/// Predicate {
+///   left: Expression::Column("Continent"),
+///   comparison: Comparison::Equal,
+///   value: Cell::String("Europe"),
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    /// The expression whose value is compared.
+    pub left: Expression,
+    /// How the values are compared.
+    pub comparison: Comparison,
+    /// The constant value to compare against. [`Cell::Null`] for `IS NULL` and `IS NOT NULL`.
+    pub value: Cell,
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.comparison {
+            Comparison::IsNull | Comparison::IsNotNull => {
+                f.write_fmt(format_args!("{} {}", self.left, self.comparison))
+            }
+            _ => f.write_fmt(format_args!(
+                "{} {} {}",
+                self.left,
+                self.comparison,
+                format_constant(&self.value)
+            )),
+        }
+    }
+}
+
+impl Predicate {
+    /// Decides whether the predicate holds for a `row`. Integers are compared numerically and
+    /// strings lexicographically, and the string comparisons such as `LIKE` only hold for
+    /// strings. Missing values only satisfy `IS NULL`, and never a comparison with a value.
+    ///
+    /// # Arguments
+    /// `header` : The names of the columns of the table the `row` belongs to.
+    /// `row` : The row to check.
+    /// `context` : The [`ExecutionContext`] holding the user-defined functions.
+    ///
+    /// # Usage Note: The caller must guarantee that the columns referenced by the predicate exist
+    /// in the `header`.
+    ///
+    /// # Returns
+    /// On success: Whether the predicate holds.
+    /// On failure: [`OperatorError::InvalidComparison`] if a number is compared with a string,
+    /// [`OperatorError::InvalidStringComparison`] if a number is matched against a string
+    /// comparison, [`OperatorError::InvalidPattern`] if the value of `MATCHES` isn't a valid
+    /// regular expression, or [`OperatorError::FunctionError`] if a function called by the
+    /// expression fails.
+    pub fn matches(
+        &self,
+        header: &[String],
+        row: &Row,
+        context: &ExecutionContext,
+    ) -> Result<bool, OperatorError> {
+        let left = self.left.evaluate(header, row, context)?;
+        match self.comparison {
+            Comparison::IsNull => return Ok(left.is_null()),
+            Comparison::IsNotNull => return Ok(!left.is_null()),
+            _ if left.is_null() => return Ok(false),
+            _ => (),
+        }
+        if let (Comparison::Matches { ignore_case }, Cell::String(left)) = (self.comparison, &left)
+        {
+            let regex = context.regex(&self.value.to_string(), ignore_case)?;
+            return Ok(regex.is_match(left));
+        }
+        if let Some(holds) = match &left {
+            Cell::String(left) => self
+                .comparison
+                .holds_for_string(left, &self.value.to_string()),
+            _ => None,
+        } {
+            return Ok(holds);
+        }
+        if self.comparison.is_string_comparison() {
+            return Err(OperatorError::InvalidStringComparison {
+                expression: self.left.to_string(),
+                comparison: self.comparison.to_string(),
+            });
+        }
+        let ordering = match (&left, &self.value) {
+            (Cell::String(left), right) => left.as_str().cmp(right.to_string().as_str()),
+            (left, right) => match left.cmp_numeric(right) {
+                Some(ordering) => ordering,
+                None => {
+                    return Err(OperatorError::InvalidComparison {
+                        expression: self.left.to_string(),
+                        value: right.to_string(),
+                    })
+                }
+            },
+        };
+        Ok(self.comparison.holds(ordering))
+    }
+}
+
+/// Describes why a regular expression is invalid in a single line, e.g. "unclosed group", as
+/// the regex crate lays out its syntax errors over several lines, pointing at the error.
+pub(crate) fn describe_regex_error(error: &regex::Error) -> String {
+    let message = error.to_string();
+    let last = message.lines().last().unwrap_or_default();
+    last.strip_prefix("error: ").unwrap_or(last).to_string()
+}
+
+/// Parses the arguments of the FILTER operator into a [`Predicate`]. The `value` is a number if it
+/// parses as one, and a string otherwise. Strings may optionally be quoted, which is needed for
+/// strings containing spaces, e.g. `'New York'`, and keeps a string of digits a string. The value
+/// of a string comparison such as `LIKE` is always a string, and that of `MATCHES` must be a valid
+/// regular expression.
+///
+/// # Arguments
+/// `left` : The expression to compare, e.g. a column name.
+/// `comparison` : One of `=`, `!=`, `<`, `<=`, `>`, `>=`, `IS`, `IS NOT`, `LIKE`, `CONTAINS`,
+/// `STARTSWITH`, `ENDSWITH`, `MATCHES`, or one of the latter five prefixed with `I` to ignore
+/// case, e.g. `ILIKE`.
+/// `value` : The constant to compare against, which must be `NULL` after `IS` and `IS NOT`.
+///
+/// # Returns
+/// The parsed [`Predicate`], or an error message describing why the input is malformed.
+pub fn parse_predicate(left: &str, comparison: &str, value: &str) -> Result<Predicate, String> {
+    let comparison = match comparison {
+        "=" | "==" => Comparison::Equal,
+        "!=" | "<>" => Comparison::NotEqual,
+        "<" => Comparison::Less,
+        "<=" => Comparison::LessOrEqual,
+        ">" => Comparison::Greater,
+        ">=" => Comparison::GreaterOrEqual,
+        "LIKE" | "ILIKE" | "CONTAINS" | "ICONTAINS" | "STARTSWITH" | "ISTARTSWITH" | "ENDSWITH"
+        | "IENDSWITH" | "MATCHES" | "IMATCHES" => {
+            let ignore_case = comparison.starts_with('I');
+            let value = unquote(value).unwrap_or_else(|| unescape(value));
+            let comparison = match comparison.trim_start_matches('I') {
+                "LIKE" => Comparison::Like { ignore_case },
+                "CONTAINS" => Comparison::Contains { ignore_case },
+                "STARTSWITH" => Comparison::StartsWith { ignore_case },
+                "ENDSWITH" => Comparison::EndsWith { ignore_case },
+                _ => {
+                    // Report an invalid regular expression now rather than when the query runs.
+                    if let Err(error) = regex::Regex::new(&value) {
+                        return Err(format!(
+                            "Invalid regular expression {}: {}",
+                            value,
+                            describe_regex_error(&error)
+                        ));
+                    }
+                    Comparison::Matches { ignore_case }
+                }
+            };
+            return Ok(Predicate {
+                left: parse_expression(left)?,
+                comparison,
+                value: Cell::String(value),
+            });
+        }
+        "IS" | "IS NOT" => {
+            if value != "NULL" {
+                return Err(format!(
+                    "{} must be followed by NULL, e.g. Capital IS NULL.",
+                    comparison
+                ));
+            }
+            return Ok(Predicate {
+                left: parse_expression(left)?,
+                comparison: if comparison == "IS" {
+                    Comparison::IsNull
+                } else {
+                    Comparison::IsNotNull
+                },
+                value: Cell::Null,
+            });
+        }
+        other => {
+            return Err(format!(
+                "Invalid comparison: {}. Must be one of =, !=, <, <=, >, >=, IS NULL, IS NOT NULL, \
+                 LIKE, CONTAINS, STARTSWITH, ENDSWITH, or MATCHES, optionally prefixed with I to \
+                 ignore case, e.g. ILIKE.",
+                other
+            ))
+        }
+    };
+    Ok(Predicate {
+        left: parse_expression(left)?,
+        comparison,
+        value: parse_constant(value),
+    })
+}
+
+/// Parses a constant value passed to an operator, e.g. the value FILTER compares against. The
+/// `value` is a number if it parses as one, and a string otherwise. Strings may optionally be
+/// quoted, which is needed for strings containing spaces, e.g. `'New York'`, and keeps a string
+/// of digits a string.
+pub fn parse_constant(value: &str) -> Cell {
+    let is_number = value.chars().any(|c| c.is_ascii_digit());
+    match (
+        unquote(value),
+        str::parse::<i64>(value),
+        str::parse::<f64>(value),
+    ) {
+        (Some(unquoted), _, _) => Cell::String(unquoted),
+        (_, Ok(val), _) => Cell::Int64(val),
+        (_, _, Ok(val)) if is_number && val.is_finite() => Cell::Float64(val),
+        _ => Cell::String(unescape(value)),
+    }
+}
+
+/// Formats a constant value the way it is entered on the command line, so that
+/// [`parse_constant`] reads it back as the same value. Strings are quoted when they wouldn't be
+/// read back as the same value otherwise, e.g. a string of digits or one containing spaces.
+pub fn format_constant(value: &Cell) -> String {
+    match value {
+        Cell::String(value)
+            if value.is_empty()
+                || value.parse::<f64>().is_ok()
+                || value
+                    .contains(|c: char| c.is_whitespace() || matches!(c, '\'' | '"' | '\\')) =>
+        {
+            quote(value)
+        }
+        value => value.to_string(),
+    }
+}
+
+#[test]
+fn test_parse_constant() {
+    assert_eq!(parse_constant("0"), Cell::Int64(0));
+    assert_eq!(parse_constant("-1.5"), Cell::Float64(-1.5));
+    assert_eq!(parse_constant("'007'"), Cell::String("007".to_string()));
+    assert_eq!(parse_constant("n/a"), Cell::String("n/a".to_string()));
+    assert_eq!(
+        format_constant(&Cell::String("New York".to_string())),
+        "'New York'"
+    );
+    // Formatted values are read back as the same value.
+    for value in ["0", "-1.5", "'007'", "n/a", "'New York'", "''"] {
+        let value = parse_constant(value);
+        assert_eq!(parse_constant(&format_constant(&value)), value);
+    }
+}
+
+#[test]
+fn test_parse_expression_list_columns() {
+    assert_eq!(
+        parse_expression_list("CityName,CityPop"),
+        Ok(vec![
+            Expression::Column("CityName".to_string()),
+            Expression::Column("CityPop".to_string())
+        ])
+    );
+    assert_eq!(
+        parse_expression_list("CityName,,"),
+        Ok(vec![Expression::Column("CityName".to_string())])
+    );
+}
+
+#[test]
+fn test_parse_expression_list_calls() {
+    assert_eq!(
+        parse_expression_list("pad(slug(CityName),'-',-3),CityPop"),
+        Ok(vec![
+            Expression::Call {
+                function: "pad".to_string(),
+                arguments: vec![
+                    Expression::Call {
+                        function: "slug".to_string(),
+                        arguments: vec![Expression::Column("CityName".to_string())],
+                    },
+                    Expression::Literal(Cell::String("-".to_string())),
+                    Expression::Literal(Cell::Int64(-3)),
+                ],
+            },
+            Expression::Column("CityPop".to_string())
+        ])
+    );
+    assert_eq!(
+        parse_expression_list("now()"),
+        Ok(vec![Expression::Call {
+            function: "now".to_string(),
+            arguments: vec![],
+        }])
+    );
+    assert_eq!(
+        parse_expression_list("round(CityPop,-0.5)"),
+        Ok(vec![Expression::Call {
+            function: "round".to_string(),
+            arguments: vec![
+                Expression::Column("CityPop".to_string()),
+                Expression::Literal(Cell::Float64(-0.5)),
+            ],
+        }])
+    );
+}
+
+#[test]
+fn test_parse_expression_list_malformed() {
+    assert_eq!(
+        parse_expression_list("slug(CityName,"),
+        Err("Incomplete expression: slug(CityName,".to_string())
+    );
+    assert_eq!(
+        parse_expression_list("slug(CityName CityPop)"),
+        Err(
+            "Expected ',' or ')' after the arguments to slug in: slug(CityName CityPop)"
+                .to_string()
+        )
+    );
+    assert_eq!(
+        parse_expression_list("'open"),
+        Err("Unterminated string in expression: 'open".to_string())
+    );
+    assert_eq!(
+        parse_expression_list("City)"),
+        Err("Unexpected character ')' in expression: City)".to_string())
+    );
+}
+
+#[test]
+fn test_parse_select_list() {
+    let columns = parse_select_list("CityName AS Name,CityPop,slug(CityName) AS Slug").unwrap();
+    assert_eq!(
+        columns,
+        vec![
+            SelectColumn {
+                expression: Expression::Column("CityName".to_string()),
+                alias: Some("Name".to_string()),
+            },
+            Expression::Column("CityPop".to_string()).into(),
+            SelectColumn {
+                expression: Expression::Call {
+                    function: "slug".to_string(),
+                    arguments: vec![Expression::Column("CityName".to_string())],
+                },
+                alias: Some("Slug".to_string()),
+            },
+        ]
+    );
+    let names: Vec<String> = columns.iter().map(|column| column.name()).collect();
+    assert_eq!(names, vec!["Name", "CityPop", "Slug"]);
+    assert_eq!(columns[2].to_string(), "slug(CityName) AS Slug");
+}
+
+#[test]
+fn test_parse_select_list_malformed() {
+    assert_eq!(
+        parse_select_list("CityName AS"),
+        Err("Unexpected character ' ' in expression: CityName AS".to_string())
+    );
+    assert_eq!(
+        parse_select_list("CityName AS ,CityPop"),
+        Err("Expected a column name after AS in: CityName AS ,CityPop".to_string())
+    );
+    assert_eq!(
+        parse_select_list("CityName AS Name AS Other"),
+        Err("Unexpected character ' ' in expression: CityName AS Name AS Other".to_string())
+    );
+}
+
+#[test]
+fn test_parse_extend_list() {
+    assert_eq!(
+        parse_extend_list("PopDensity = CityPop / 1000,Name=UPPER(CityName)"),
+        Ok(vec![
+            (
+                "PopDensity".to_string(),
+                Expression::Arithmetic {
+                    operator: ArithmeticOperator::Divide,
+                    left: Box::new(Expression::Column("CityPop".to_string())),
+                    right: Box::new(Expression::Literal(Cell::Int64(1000))),
+                },
+            ),
+            (
+                "Name".to_string(),
+                Expression::Call {
+                    function: "UPPER".to_string(),
+                    arguments: vec![Expression::Column("CityName".to_string())],
+                },
+            ),
+        ])
+    );
+    assert_eq!(
+        parse_extend_list("PopDensity CityPop"),
+        Err("Expected '=' after PopDensity in: PopDensity CityPop".to_string())
+    );
+    assert_eq!(
+        parse_extend_list("= CityPop"),
+        Err("Expected a column name in: = CityPop".to_string())
+    );
+    assert_eq!(
+        parse_extend_list("PopDensity ="),
+        Err("Incomplete expression: PopDensity =".to_string())
+    );
+}
+
+#[test]
+fn test_expression_display() {
+    let expressions = parse_expression_list("pad(slug(CityName),'-',-3),CityPop").unwrap();
+    assert_eq!(expressions[0].to_string(), "pad(slug(CityName),'-',-3)");
+    assert_eq!(expressions[1].to_string(), "CityPop");
+}
+
+#[test]
+fn test_expression_evaluate() {
+    let mut context = ExecutionContext::default();
+    context.functions =
+        Some(crate::script::ScriptFunctions::from_source("fn add(a, b) { a + b }").unwrap());
+    let header = vec!["A".to_string(), "B".to_string()];
+    let row = Row {
+        cells: vec![Cell::Int64(1), Cell::Int64(2)],
+    };
+    let expression = &parse_expression_list("add(A,add(B,10))").unwrap()[0];
+    assert_eq!(expression.column_names(), vec!["A", "B"]);
+    assert_eq!(
+        expression.evaluate(&header, &row, &context).unwrap(),
+        Cell::Int64(13)
+    );
+
+    let expression = &parse_expression_list("sub(A,B)").unwrap()[0];
+    assert_eq!(
+        expression
+            .evaluate(&header, &row, &context)
+            .unwrap_err()
+            .to_string(),
+        "Failed to evaluate the sub function: No function called sub taking 2 argument(s) is defined."
+    );
+}
+
+#[test]
+fn test_expression_rename_columns() {
+    let expression = &parse_expression_list("add(city.CityPop,CityID*2)").unwrap()[0];
+    assert_eq!(expression.column_names(), vec!["city.CityPop", "CityID"]);
+    let renamed =
+        expression.rename_columns(&|name| name.trim_start_matches("city.").to_lowercase());
+    assert_eq!(renamed.to_string(), "add(citypop,cityid*2)");
+}
+
+#[test]
+fn test_parse_expression_arithmetic() {
+    let column = |name: &str| Box::new(Expression::Column(name.to_string()));
+    assert_eq!(
+        parse_expression("A-B*2"),
+        Ok(Expression::Arithmetic {
+            operator: ArithmeticOperator::Subtract,
+            left: column("A"),
+            right: Box::new(Expression::Arithmetic {
+                operator: ArithmeticOperator::Multiply,
+                left: column("B"),
+                right: Box::new(Expression::Literal(Cell::Int64(2))),
+            }),
+        })
+    );
+    assert_eq!(
+        parse_expression("A - B - C"),
+        Ok(Expression::Arithmetic {
+            operator: ArithmeticOperator::Subtract,
+            left: Box::new(Expression::Arithmetic {
+                operator: ArithmeticOperator::Subtract,
+                left: column("A"),
+                right: column("B"),
+            }),
+            right: column("C"),
+        })
+    );
+    // Parentheses are only kept where they change the order of evaluation.
+    for (text, display) in [
+        ("A-B*2", "A-B*2"),
+        ("(A-B)*2", "(A-B)*2"),
+        ("A-(B-C)", "A-(B-C)"),
+        ("(A*B)+(C/D)", "A*B+C/D"),
+        ("( A + B ) / LENGTH(C)", "(A+B)/LENGTH(C)"),
+        ("A*-2", "A*-2"),
+    ] {
+        assert_eq!(parse_expression(text).unwrap().to_string(), display);
+    }
+    assert_eq!(
+        parse_expression("A+B").unwrap().column_names(),
+        vec!["A", "B"]
+    );
+    assert_eq!(
+        parse_expression("(A+B"),
+        Err("Expected ')' in expression: (A+B".to_string())
+    );
+    assert_eq!(
+        parse_expression("A*"),
+        Err("Incomplete expression: A*".to_string())
+    );
+}
+
+#[test]
+fn test_arithmetic_operator_apply() {
+    let add = ArithmeticOperator::Add;
+    let divide = ArithmeticOperator::Divide;
+    assert_eq!(
+        add.apply(&Cell::Int64(1), &Cell::Int64(2)),
+        Ok(Cell::Int64(3))
+    );
+    assert_eq!(
+        ArithmeticOperator::Multiply.apply(&Cell::Int64(3), &Cell::OptInt64(Some(4))),
+        Ok(Cell::Int64(12))
+    );
+    assert_eq!(
+        ArithmeticOperator::Subtract.apply(&Cell::Float64(1.5), &Cell::Int64(2)),
+        Ok(Cell::Float64(-0.5))
+    );
+    assert_eq!(
+        divide.apply(&Cell::Int64(3), &Cell::Int64(2)),
+        Ok(Cell::Float64(1.5))
+    );
+    // Missing values and division by zero produce missing values.
+    assert_eq!(
+        divide.apply(&Cell::Int64(3), &Cell::Int64(0)),
+        Ok(Cell::Null)
+    );
+    assert_eq!(
+        divide.apply(&Cell::Float64(3.0), &Cell::Float64(0.0)),
+        Ok(Cell::Null)
+    );
+    assert_eq!(
+        add.apply(&Cell::Int64(1), &Cell::OptInt64(None)),
+        Ok(Cell::Null)
+    );
+    assert_eq!(
+        add.apply(&Cell::Null, &Cell::String("Kabul".to_string())),
+        Ok(Cell::Null)
+    );
+    assert_eq!(
+        add.apply(&Cell::String("Kabul".to_string()), &Cell::Int64(1)),
+        Err("Kabul is not a number.".to_string())
+    );
+    assert_eq!(
+        add.apply(&Cell::Int64(i64::MAX), &Cell::Int64(1)),
+        Err(format!("The result of {} + 1 is too large.", i64::MAX))
+    );
+}
+
+#[test]
+fn test_expression_evaluate_arithmetic() {
+    let header = vec!["City".to_string(), "Pop".to_string()];
+    let row = Row {
+        cells: vec![Cell::String("Kabul".to_string()), Cell::Int64(1780000)],
+    };
+    let context = ExecutionContext::default();
+    let evaluate = |text: &str| {
+        parse_expression(text)
+            .unwrap()
+            .evaluate(&header, &row, &context)
+            .map_err(|e| e.to_string())
+    };
+    assert_eq!(evaluate("Pop/1000"), Ok(Cell::Float64(1780.0)));
+    assert_eq!(evaluate("(Pop - 780000) * 2"), Ok(Cell::Int64(2000000)));
+    assert_eq!(evaluate("LENGTH(City)+1"), Ok(Cell::Int64(6)));
+    assert_eq!(
+        evaluate("City*2"),
+        Err("Failed to compute City*2: Kabul is not a number.".to_string())
+    );
+}
+
+#[test]
+fn test_expression_evaluate_builtin() {
+    let header = vec!["City".to_string(), "Missing".to_string()];
+    let row = Row {
+        cells: vec![Cell::String("Kabul".to_string()), Cell::Null],
+    };
+    let evaluate = |text: &str, context: &ExecutionContext| {
+        parse_expression(text)
+            .unwrap()
+            .evaluate(&header, &row, context)
+            .map_err(|e| e.to_string())
+    };
+    let mut context = ExecutionContext::default();
+    assert_eq!(
+        evaluate("UPPER(City)", &context),
+        Ok(Cell::String("KABUL".to_string()))
+    );
+    assert_eq!(
+        evaluate("LENGTH(LOWER(City))", &context),
+        Ok(Cell::Int64(5))
+    );
+    assert_eq!(
+        evaluate("COALESCE(Missing, 'n/a')", &context),
+        Ok(Cell::String("n/a".to_string()))
+    );
+    assert_eq!(
+        evaluate("LENGTH(City,City)", &context),
+        Err(
+            "Failed to evaluate the LENGTH function: LENGTH takes 1 argument, but 2 were given."
+                .to_string()
+        )
+    );
+
+    // A function defined in the script replaces the built-in one.
+    context.functions =
+        Some(crate::script::ScriptFunctions::from_source("fn UPPER(s) { \"up\" }").unwrap());
+    assert_eq!(
+        evaluate("UPPER(City)", &context),
+        Ok(Cell::String("up".to_string()))
+    );
+}
+
+#[test]
+fn test_parse_predicate() {
+    assert_eq!(
+        parse_predicate("Continent", "=", "Europe"),
+        Ok(Predicate {
+            left: Expression::Column("Continent".to_string()),
+            comparison: Comparison::Equal,
+            value: Cell::String("Europe".to_string()),
+        })
+    );
+    assert_eq!(
+        parse_predicate("CityPop", ">=", "1000000"),
+        Ok(Predicate {
+            left: Expression::Column("CityPop".to_string()),
+            comparison: Comparison::GreaterOrEqual,
+            value: Cell::Int64(1000000),
+        })
+    );
+    assert_eq!(
+        parse_predicate("Rating", "<", "4.5").unwrap().value,
+        Cell::Float64(4.5)
+    );
+    assert_eq!(
+        parse_predicate("Rating", "=", "inf").unwrap().value,
+        Cell::String("inf".to_string())
+    );
+    assert_eq!(
+        parse_predicate("CountryCode", "!=", "'NLD'").unwrap().value,
+        Cell::String("NLD".to_string())
+    );
+    // Quoted values may contain spaces and escaped quotes, and stay strings even if numeric.
+    assert_eq!(
+        parse_predicate("CityName", "=", "'St. John\\'s'")
+            .unwrap()
+            .value,
+        Cell::String("St. John's".to_string())
+    );
+    assert_eq!(
+        parse_predicate("Zip", "=", "\"01234\"").unwrap().value,
+        Cell::String("01234".to_string())
+    );
+    assert_eq!(
+        parse_predicate("CityPop", "~", "1").unwrap_err(),
+        "Invalid comparison: ~. Must be one of =, !=, <, <=, >, >=, IS NULL, IS NOT NULL, LIKE, CONTAINS, STARTSWITH, ENDSWITH, or MATCHES, optionally prefixed with I to ignore case, e.g. ILIKE."
+    );
+    assert_eq!(
+        parse_predicate("City,Pop", "=", "1").unwrap_err(),
+        "Unexpected character ',' in expression: City,Pop"
+    );
+}
+
+#[test]
+fn test_predicate_matches() {
+    let context = ExecutionContext::default();
+    let header = vec![
+        "Name".to_string(),
+        "Pop".to_string(),
+        "Capital".to_string(),
+        "Continent".to_string(),
+    ];
+    let row = Row {
+        cells: vec![
+            Cell::String("Haag".to_string()),
+            Cell::Int64(440900),
+            Cell::OptInt64(None),
+            Cell::Null,
+        ],
+    };
+    let matches = |left, comparison, value| {
+        parse_predicate(left, comparison, value)
+            .unwrap()
+            .matches(&header, &row, &context)
+    };
+    assert!(matches("Pop", ">", "400000").unwrap());
+    assert!(!matches("Pop", "<", "400000").unwrap());
+    assert!(matches("Pop", "<", "440900.5").unwrap());
+    assert!(matches("Pop", "=", "440900.0").unwrap());
+    assert!(matches("Name", "=", "Haag").unwrap());
+    assert!(matches("Name", "<", "Rotterdam").unwrap());
+    assert!(!matches("Capital", "=", "1").unwrap());
+    assert!(!matches("Capital", "!=", "1").unwrap());
+    assert!(!matches("Continent", "!=", "Europe").unwrap());
+    assert!(matches("Capital", "IS", "NULL").unwrap());
+    assert!(matches("Continent", "IS", "NULL").unwrap());
+    assert!(!matches("Pop", "IS", "NULL").unwrap());
+    assert!(matches("Name", "IS NOT", "NULL").unwrap());
+    assert!(!matches("Capital", "IS NOT", "NULL").unwrap());
+    assert_eq!(
+        matches("Pop", "=", "many").unwrap_err().to_string(),
+        "Cannot compare the numeric values of Pop with the string many."
+    );
+    assert!(matches("Name", "LIKE", "H_a%").unwrap());
+    assert!(!matches("Name", "LIKE", "h%").unwrap());
+    assert!(matches("Name", "ILIKE", "h%").unwrap());
+    assert!(matches("Name", "CONTAINS", "aa").unwrap());
+    assert!(matches("Name", "ISTARTSWITH", "HAA").unwrap());
+    assert!(matches("Name", "ENDSWITH", "ag").unwrap());
+    assert!(!matches("Continent", "CONTAINS", "''").unwrap());
+    assert!(matches("Name", "MATCHES", "'^H.+g$'").unwrap());
+    assert!(!matches("Name", "MATCHES", "^h").unwrap());
+    assert!(matches("Name", "IMATCHES", "^h").unwrap());
+    assert!(!matches("Continent", "MATCHES", ".*").unwrap());
+    assert_eq!(
+        matches("Pop", "LIKE", "44%").unwrap_err().to_string(),
+        "LIKE only applies to strings, but Pop has numeric values."
+    );
+}
+
+#[test]
+fn test_display_quoted() {
+    // Strings are quoted so that the text can be parsed back into the same value.
+    for (left, value, expected) in [
+        ("CityName", "'New York'", "CityName = 'New York'"),
+        ("CityName", "'O\\'Hare'", "CityName = 'O\\'Hare'"),
+        ("Zip", "'01234'", "Zip = '01234'"),
+        ("CityName", "Kabul", "CityName = Kabul"),
+    ] {
+        let predicate = parse_predicate(left, "=", value).unwrap();
+        assert_eq!(predicate.to_string(), expected);
+        let text = predicate.to_string();
+        let tokens = crate::lexer::tokenize(&text).unwrap();
+        assert_eq!(
+            parse_predicate(tokens[0], tokens[1], tokens[2]),
+            Ok(predicate)
+        );
+    }
+    let literal = parse_expression("'it\\'s'").unwrap();
+    assert_eq!(
+        literal,
+        Expression::Literal(Cell::String("it's".to_string()))
+    );
+    assert_eq!(literal.to_string(), "'it\\'s'");
+}
+
+#[test]
+fn test_parse_predicate_null() {
+    let predicate = parse_predicate("Capital", "IS", "NULL").unwrap();
+    assert_eq!(
+        predicate,
+        Predicate {
+            left: Expression::Column("Capital".to_string()),
+            comparison: Comparison::IsNull,
+            value: Cell::Null,
+        }
+    );
+    assert_eq!(predicate.to_string(), "Capital IS NULL");
+    assert_eq!(
+        parse_predicate("Capital", "IS NOT", "NULL")
+            .unwrap()
+            .to_string(),
+        "Capital IS NOT NULL"
+    );
+    assert_eq!(
+        parse_predicate("Capital", "IS", "1").unwrap_err(),
+        "IS must be followed by NULL, e.g. Capital IS NULL."
+    );
+}
+
+#[test]
+fn test_parse_predicate_string() {
+    let predicate = parse_predicate("CityName", "ILIKE", "'san%'").unwrap();
+    assert_eq!(
+        predicate,
+        Predicate {
+            left: Expression::Column("CityName".to_string()),
+            comparison: Comparison::Like { ignore_case: true },
+            value: Cell::String("san%".to_string()),
+        }
+    );
+    assert_eq!(predicate.to_string(), "CityName ILIKE san%");
+    // The value of a string comparison stays a string, even if it is a number.
+    let predicate = parse_predicate("Zip", "STARTSWITH", "01").unwrap();
+    assert_eq!(predicate.value, Cell::String("01".to_string()));
+    assert_eq!(predicate.to_string(), "Zip STARTSWITH '01'");
+    for comparison in [
+        "LIKE",
+        "CONTAINS",
+        "ICONTAINS",
+        "STARTSWITH",
+        "ISTARTSWITH",
+        "ENDSWITH",
+        "IENDSWITH",
+        "MATCHES",
+        "IMATCHES",
+    ] {
+        let predicate = parse_predicate("CityName", comparison, "New").unwrap();
+        assert!(predicate.comparison.is_string_comparison());
+        assert_eq!(predicate.comparison.to_string(), comparison);
+    }
+    assert!(parse_predicate("CityName", "IEQUALS", "New").is_err());
+    assert_eq!(
+        parse_predicate("CityName", "MATCHES", "'(New'").unwrap_err(),
+        "Invalid regular expression (New: unclosed group"
+    );
+}
@@ -0,0 +1,172 @@
+use std::fmt::Display;
+
+use crate::table::Cell;
+
+/// Functions built into the toy-query-engine that compute a value from the values of their
+/// arguments, one row at a time. They can be called in expressions like the functions defined in
+/// a script, e.g. `UPPER(CityName)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalarFunction {
+    /// The text of the value, in upper case.
+    Upper,
+    /// The text of the value, in lower case.
+    Lower,
+    /// The number of characters in the text of the value.
+    Length,
+    /// The first of the values that isn't missing, e.g. `COALESCE(Capital,0)` to replace the
+    /// missing values of a column with a default.
+    Coalesce,
+}
+
+impl Display for ScalarFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl ScalarFunction {
+    /// Returns all the built-in scalar functions.
+    pub fn all() -> Vec<ScalarFunction> {
+        vec![
+            ScalarFunction::Upper,
+            ScalarFunction::Lower,
+            ScalarFunction::Length,
+            ScalarFunction::Coalesce,
+        ]
+    }
+
+    /// Returns the keyword used to invoke the function, e.g. "UPPER".
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScalarFunction::Upper => "UPPER",
+            ScalarFunction::Lower => "LOWER",
+            ScalarFunction::Length => "LENGTH",
+            ScalarFunction::Coalesce => "COALESCE",
+        }
+    }
+
+    /// Looks up a function by the keyword used to invoke it, e.g. "UPPER".
+    pub fn from_name(name: &str) -> Option<ScalarFunction> {
+        ScalarFunction::all()
+            .into_iter()
+            .find(|function| function.name() == name)
+    }
+
+    /// Computes the value of the function for the values of its `arguments`.
+    ///
+    /// # Arguments
+    /// `arguments` : The values of the arguments. COALESCE takes one or more arguments, and the
+    /// other functions exactly one. They may be of any type; numbers are treated as their text.
+    ///
+    /// # Returns
+    /// On success: The computed value. UPPER and LOWER return a [`Cell::String`], and LENGTH a
+    /// [`Cell::Int64`]. A missing value is returned as is. COALESCE returns the first value that
+    /// isn't missing, with an optional integer returned as a [`Cell::Int64`] so that the values
+    /// can be sorted on, or [`Cell::Null`] if all of them are missing.
+    /// On failure: A message describing why the function can't be applied to the `arguments`.
+    pub fn apply(&self, arguments: &[Cell]) -> Result<Cell, String> {
+        if *self == ScalarFunction::Coalesce {
+            return match arguments.iter().find(|value| !value.is_null()) {
+                _ if arguments.is_empty() => Err(format!(
+                    "{} takes at least 1 argument, but 0 were given.",
+                    self
+                )),
+                Some(Cell::OptInt64(Some(val))) => Ok(Cell::Int64(*val)),
+                Some(value) => Ok(value.clone()),
+                None => Ok(Cell::Null),
+            };
+        }
+        let value = match arguments {
+            [value] => value,
+            _ => {
+                return Err(format!(
+                    "{} takes 1 argument, but {} were given.",
+                    self,
+                    arguments.len()
+                ))
+            }
+        };
+        if value.is_null() {
+            return Ok(value.clone());
+        }
+        let text = value.to_string();
+        Ok(match self {
+            ScalarFunction::Upper => Cell::String(text.to_uppercase()),
+            ScalarFunction::Lower => Cell::String(text.to_lowercase()),
+            ScalarFunction::Length => Cell::Int64(text.chars().count() as i64),
+            ScalarFunction::Coalesce => unreachable!(),
+        })
+    }
+}
+
+#[test]
+fn test_scalar_function_from_name() {
+    assert_eq!(
+        ScalarFunction::from_name("UPPER"),
+        Some(ScalarFunction::Upper)
+    );
+    assert_eq!(
+        ScalarFunction::from_name("LENGTH"),
+        Some(ScalarFunction::Length)
+    );
+    assert_eq!(ScalarFunction::from_name("upper"), None);
+    assert_eq!(ScalarFunction::from_name("slug"), None);
+    assert_eq!(ScalarFunction::Lower.to_string(), "LOWER");
+}
+
+#[test]
+fn test_scalar_function_apply() {
+    let city = [Cell::String("São Paulo".to_string())];
+    assert_eq!(
+        ScalarFunction::Upper.apply(&city),
+        Ok(Cell::String("SÃO PAULO".to_string()))
+    );
+    assert_eq!(
+        ScalarFunction::Lower.apply(&city),
+        Ok(Cell::String("são paulo".to_string()))
+    );
+    assert_eq!(ScalarFunction::Length.apply(&city), Ok(Cell::Int64(9)));
+    assert_eq!(
+        ScalarFunction::Length.apply(&[Cell::Int64(-120)]),
+        Ok(Cell::Int64(4))
+    );
+    assert_eq!(
+        ScalarFunction::Upper.apply(&[Cell::OptInt64(None)]),
+        Ok(Cell::OptInt64(None))
+    );
+    assert_eq!(ScalarFunction::Length.apply(&[Cell::Null]), Ok(Cell::Null));
+    assert_eq!(
+        ScalarFunction::Upper.apply(&[]),
+        Err("UPPER takes 1 argument, but 0 were given.".to_string())
+    );
+}
+
+#[test]
+fn test_scalar_function_apply_coalesce() {
+    let coalesce = |arguments: &[Cell]| ScalarFunction::Coalesce.apply(arguments);
+    assert_eq!(
+        coalesce(&[Cell::OptInt64(None), Cell::Int64(0)]),
+        Ok(Cell::Int64(0))
+    );
+    // Present optional integers become plain integers.
+    assert_eq!(
+        coalesce(&[Cell::OptInt64(Some(129)), Cell::Int64(0)]),
+        Ok(Cell::Int64(129))
+    );
+    assert_eq!(
+        coalesce(&[Cell::Null, Cell::Null, Cell::String("n/a".to_string())]),
+        Ok(Cell::String("n/a".to_string()))
+    );
+    assert_eq!(
+        coalesce(&[Cell::Null, Cell::OptInt64(None)]),
+        Ok(Cell::Null)
+    );
+    assert_eq!(
+        coalesce(&[]),
+        Err("COALESCE takes at least 1 argument, but 0 were given.".to_string())
+    );
+    assert_eq!(
+        ScalarFunction::from_name("COALESCE"),
+        Some(ScalarFunction::Coalesce)
+    );
+}
@@ -0,0 +1,153 @@
+use crate::data::{peek_columns, DatasetRegistry};
+use crate::operators::{OperatorDescriptor, OPERATORS};
+use crate::settings::SETTINGS;
+
+#[cfg(test)]
+use crate::commands::SESSION_COMMANDS;
+#[cfg(test)]
+use crate::data::Dataset;
+
+/// Builds the message printed by the `help` command from the [`OPERATORS`] and [`SETTINGS`]
+/// descriptors and the catalog of [`crate::data::Dataset`]s, so it always matches what the tool supports.
+///
+/// # Arguments
+/// `datasets` : The datasets registered in the session, which are listed after the built-in
+/// ones. Their columns are listed if they can be found without loading them, see
+/// [`peek_columns`].
+pub fn help_message(datasets: &DatasetRegistry) -> String {
+    let mut message = String::from("Available Commands:\n");
+    for operator in OPERATORS {
+        message.push_str(&format!(
+            "  {} {} - {}\n",
+            operator.name, operator.arguments, operator.summary
+        ));
+    }
+    message.push_str("Use 'help <OPERATOR>' for more details about an operator.\n");
+    message.push_str(
+        "Use 'EXPLAIN <query>' to show how a query would be evaluated without running it.\n",
+    );
+    message.push_str(
+        "Use 'EXPLAIN ANALYZE <query>' to run a query and show the rows produced and time spent by each operator.\n",
+    );
+    message.push_str(
+        "Use 'WATCH <query>' to run a query again each time a file it reads changes, until Ctrl-C is pressed.\n",
+    );
+    message.push_str(
+        "Use 'LOAD <name> FROM <path> [ENCODING <label>]' to name a CSV or Parquet file, so FROM and JOIN can refer to it, optionally reading it in another encoding than UTF-8, e.g. latin1.\n",
+    );
+    message.push_str(
+        "Use 'ATTACH <path>' to make the tables of a SQLite database datasets that FROM and JOIN can refer to by name.\n",
+    );
+    message.push_str(
+        "Use 'SET <name> = <query>' to save the result of a query, so FROM and JOIN can refer to it as @<name>.\n",
+    );
+    message.push_str(
+        "Use 'INSERT INTO <dataset> VALUES (<value>, ...)' or 'DELETE FROM <dataset> WHERE <condition>' to edit a dataset in memory, leaving its file untouched.\n",
+    );
+    message.push_str(
+        "Use 'DESCRIBE <dataset>' to list the type, missing and distinct values, range, and samples of each column.\n",
+    );
+    message.push_str(
+        "Use 'SHOW TABLES' to list the datasets and saved results FROM and JOIN can refer to, and 'SHOW COLUMNS <dataset>' to list the names and types of its columns.\n",
+    );
+    message.push_str(
+        "Use 'SAVE <dataset> [<path>]' to write a dataset, with its edits, to its CSV file or the one at <path>.\n",
+    );
+    message.push_str(
+        "Use '\\history' to list the queries of the session, and '\\rerun <number>' to run one of them again.\n",
+    );
+    message.push_str(
+        "Use '\\save <path>' to write the settings, registered datasets, and saved results of the session to a TOML file, and '\\load <path>' to restore them later.\n",
+    );
+    message.push_str(
+        "Separate queries with ';' to run several of them one after the other, e.g. 'FROM city TAKE 3; FROM country TAKE 3'.\n",
+    );
+
+    message.push_str("\nAvailable Settings:\n");
+    for setting in SETTINGS {
+        message.push_str(&format!(
+            "  \\{} {} - {}\n",
+            setting.name, setting.arguments, setting.summary
+        ));
+    }
+
+    message.push_str("\nAvailable Datasets:\n");
+    for dataset in datasets.catalog() {
+        if dataset.is_builtin() {
+            message.push_str(&format!(
+                "  <dataset> : {} (or {})\n      <column-name> : [{}]\n      <numeric-column-name> : [{}]\n",
+                dataset,
+                dataset.name(),
+                dataset.column_names().join(", "),
+                dataset.sortable_columns().join(", ")
+            ));
+        } else {
+            // Which columns are numeric is only known once the dataset is loaded.
+            message.push_str(&format!(
+                "  <dataset> : {} ({})\n      <column-name> : [{}]\n",
+                dataset.name(),
+                dataset.path().unwrap_or_default(),
+                peek_columns(&dataset).join(", ")
+            ));
+        }
+    }
+    message
+}
+
+/// Builds the message printed by the `help <OPERATOR>` command for the `operator`.
+pub fn operator_help(operator: &OperatorDescriptor) -> String {
+    format!(
+        "{} {} - {}\n{}\n",
+        operator.name, operator.arguments, operator.summary, operator.details
+    )
+}
+
+#[test]
+fn test_help_message_lists_everything() {
+    let message = help_message(&DatasetRegistry::default());
+    for operator in OPERATORS {
+        assert!(message.contains(&format!("  {} {} - ", operator.name, operator.arguments)));
+    }
+    for setting in SETTINGS {
+        assert!(message.contains(&format!("  \\{} ", setting.name)));
+    }
+    for command in SESSION_COMMANDS {
+        assert!(message.contains(&format!("'\\{}", command)));
+    }
+    for dataset in Dataset::all() {
+        assert!(message.contains(&format!(
+            "  <dataset> : {} (or {})",
+            dataset,
+            dataset.name()
+        )));
+    }
+}
+
+#[test]
+fn test_help_message_numeric_columns() {
+    assert!(help_message(&DatasetRegistry::default()).contains(
+        "  <dataset> : country.csv (or country)\n      <column-name> : [CountryCode, CountryName, Continent, CountryPop, Capital]\n      <numeric-column-name> : [CountryPop, Capital]\n"
+    ));
+}
+
+#[test]
+fn test_help_message_registered_datasets() {
+    let path = std::env::temp_dir().join("toy-query-engine-help-planets.csv");
+    std::fs::write(&path, "Planet,Moons\nEarth,1\n").unwrap();
+    let path = path.to_string_lossy().to_string();
+    let mut datasets = DatasetRegistry::default();
+    datasets.register("planets", &path).unwrap();
+    let message = help_message(&datasets);
+    assert!(message.ends_with(&format!(
+        "      <numeric-column-name> : []\n  <dataset> : planets ({})\n      <column-name> : [Planet, Moons]\n",
+        path
+    )));
+}
+
+#[test]
+fn test_operator_help() {
+    assert_eq!(
+        operator_help(&OPERATORS[3]),
+        "TAKE <number> - Returns the first <number> rows of the input table.\n<number> must be greater than or equal to 0. If the input table has fewer rows, all of them are returned.\nExample: FROM city.csv TAKE 5\n"
+    );
+}
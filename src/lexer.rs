@@ -0,0 +1,198 @@
+/// Splits a line of input into tokens at whitespace, except for whitespace that is quoted or
+/// escaped:
+/// - A single or double quote at the start of a token, or after a character other than a letter,
+///   digit, or underscore (e.g. after the `(` or `,` in `pad(CityName,' ',3)`), starts a quoted
+///   string that runs to the next matching quote. A quote inside a word, e.g. the apostrophe in
+///   `Xi'an`, is left as is.
+/// - A backslash escapes the character that follows it, inside quotes or not, e.g. `'O\'Hare'`
+///   or `New\ York`.
+///
+/// The tokens are slices of the `input`, with their quotes and escapes left in, so that the
+/// position of each token in the input can be reported. Use [`unquote`] and [`unescape`] to get
+/// the values they stand for.
+///
+/// # Returns
+/// The tokens on success, or the rest of the `input` from the quote that starts a string without
+/// a closing quote.
+pub fn tokenize(input: &str) -> Result<Vec<&str>, &str> {
+    let mut tokens = Vec::new();
+    // The offset of the first character of the token being read, if any.
+    let mut start = None;
+    // The quote that started the string being read, and its offset.
+    let mut quote: Option<(char, usize)> = None;
+    let mut previous = None;
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                start.get_or_insert(index);
+                chars.next();
+            }
+            (Some((opening, _)), c) if c == opening => quote = None,
+            (Some(_), _) => (),
+            (None, c) if c.is_whitespace() => {
+                if let Some(start) = start.take() {
+                    tokens.push(&input[start..index]);
+                }
+            }
+            (None, '\'' | '"') if start.is_none() || !previous.is_some_and(is_word_character) => {
+                start.get_or_insert(index);
+                quote = Some((c, index));
+            }
+            (None, _) => {
+                start.get_or_insert(index);
+            }
+        }
+        previous = Some(c);
+    }
+    if let Some((_, index)) = quote {
+        return Err(&input[index..]);
+    }
+    if let Some(start) = start {
+        tokens.push(&input[start..]);
+    }
+    Ok(tokens)
+}
+
+/// Splits a line of `input` into the statements separated by semicolons, e.g. to run
+/// `FROM city.csv TAKE 3; FROM country.csv TAKE 3` one query after the other. Semicolons that are
+/// quoted or escaped, as decided by [`tokenize`], don't separate statements.
+///
+/// # Returns
+/// The statements, in order, as slices of the `input` with their surrounding whitespace left in.
+/// Empty statements, e.g. after a trailing semicolon, are left out.
+pub fn split_statements(input: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    // The offset of the first character of the statement being read.
+    let mut start = 0;
+    // The quote that started the string being read, if any.
+    let mut quote = None;
+    let mut previous = None;
+    let mut chars = input.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some(opening), c) if c == opening => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"') if !previous.is_some_and(is_word_character) => quote = Some(c),
+            (None, ';') => {
+                statements.push(&input[start..index]);
+                start = index + 1;
+            }
+            (None, _) => (),
+        }
+        previous = Some(c);
+    }
+    statements.push(&input[start..]);
+    statements.retain(|statement| !statement.trim().is_empty());
+    statements
+}
+
+/// Helper function to decide whether a quote following the character `c` is part of a word.
+fn is_word_character(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Removes the backslashes escaping the characters of `text`, e.g. `O\'Hare` becomes `O'Hare`. A
+/// backslash is escaped by another backslash.
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Returns the string a `token` wholly enclosed in matching single or double quotes stands for,
+/// without the quotes and escapes, e.g. `'New York'` becomes `New York`.
+///
+/// # Returns
+/// The unquoted string, or `None` if the `token` isn't quoted.
+pub fn unquote(token: &str) -> Option<String> {
+    let quote = token.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let inner = token[1..].strip_suffix(quote)?;
+    // The closing quote must not be escaped, e.g. `'abc\'` isn't a complete string.
+    let escaped = inner.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1;
+    (!escaped).then(|| unescape(inner))
+}
+
+/// Encloses `text` in single quotes, escaping the quotes and backslashes in it, so that
+/// [`tokenize`] and [`unquote`] read it back as one token standing for `text`.
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[test]
+fn test_tokenize() {
+    assert_eq!(
+        tokenize("  FROM city.csv   TAKE 5 "),
+        Ok(vec!["FROM", "city.csv", "TAKE", "5"])
+    );
+    assert_eq!(
+        tokenize("FILTER CityName = 'New York' TAKE 1"),
+        Ok(vec!["FILTER", "CityName", "=", "'New York'", "TAKE", "1"])
+    );
+    assert_eq!(
+        tokenize("FILTER CityName = \"Washington, D.C.\""),
+        Ok(vec!["FILTER", "CityName", "=", "\"Washington, D.C.\""])
+    );
+    // Quotes following punctuation start a string too.
+    assert_eq!(
+        tokenize("SELECT pad(CityName,' ',3),CityPop"),
+        Ok(vec!["SELECT", "pad(CityName,' ',3),CityPop"])
+    );
+    assert_eq!(tokenize(""), Ok(vec![]));
+}
+
+#[test]
+fn test_tokenize_escapes() {
+    assert_eq!(
+        tokenize("= 'O\\'Hare' New\\ York"),
+        Ok(vec!["=", "'O\\'Hare'", "New\\ York"])
+    );
+    // A quote inside a word isn't the start of a string.
+    assert_eq!(
+        tokenize("= Xi'an TAKE 1"),
+        Ok(vec!["=", "Xi'an", "TAKE", "1"])
+    );
+    assert_eq!(tokenize("= 'New York"), Err("'New York"));
+    assert_eq!(tokenize("= 'New York\\'"), Err("'New York\\'"));
+}
+
+#[test]
+fn test_split_statements() {
+    assert_eq!(
+        split_statements("FROM city.csv TAKE 3; FROM country.csv TAKE 3;"),
+        vec!["FROM city.csv TAKE 3", " FROM country.csv TAKE 3"]
+    );
+    assert_eq!(split_statements("FROM city.csv"), vec!["FROM city.csv"]);
+    // Quoted and escaped semicolons are part of the statement.
+    assert_eq!(
+        split_statements("FILTER CityName = 'a;b' ; FILTER Xi'an = \\; ;"),
+        vec!["FILTER CityName = 'a;b' ", " FILTER Xi'an = \\; "]
+    );
+    assert_eq!(split_statements(" ; "), Vec::<&str>::new());
+}
+
+#[test]
+fn test_unquote() {
+    assert_eq!(unquote("'New York'"), Some("New York".to_string()));
+    assert_eq!(unquote("\"O'Hare\""), Some("O'Hare".to_string()));
+    assert_eq!(unquote("'O\\'Hare'"), Some("O'Hare".to_string()));
+    assert_eq!(unquote("''"), Some(String::new()));
+    assert_eq!(unquote("New York"), None);
+    assert_eq!(unquote("'"), None);
+    assert_eq!(unquote("'abc\\'"), None);
+    assert_eq!(unescape("New\\ York\\\\"), "New York\\");
+    for text in ["New York", "O'Hare", "C:\\data", ""] {
+        let quoted = quote(text);
+        assert_eq!(tokenize(&quoted), Ok(vec![quoted.as_str()]));
+        assert_eq!(unquote(&quoted), Some(text.to_string()));
+    }
+}
@@ -0,0 +1,48 @@
+//! The toy-query-engine as a library, for embedding it in other applications.
+//!
+//! [`QueryEngine`] is the high-level entry point: it parses and runs queries written in the same
+//! language as the REPL. The lower-level building blocks used by the REPL, e.g.
+//! [`parse_command`], [`process_operator`], and [`open_operator`], which pulls the rows of a query
+//! one at a time, are also exposed.
+//!
+//! Note: The built-in datasets are loaded from the `data` directory relative to the current
+//! working directory.
+
+pub mod aggregate;
+pub mod bench;
+pub mod cache;
+pub mod commands;
+pub mod completion;
+pub mod data;
+pub mod describe;
+pub mod engine;
+pub mod explain;
+pub mod expression;
+pub mod function;
+pub mod help;
+pub mod lexer;
+pub mod lint;
+pub mod operators;
+pub mod optimizer;
+pub mod pipeline;
+pub mod profile;
+pub mod render;
+pub mod script;
+pub mod server;
+pub mod session;
+pub mod settings;
+pub mod sql;
+pub mod table;
+pub mod watch;
+
+pub use commands::{parse_command, parse_command_with, Command, ParseError};
+pub use data::{Dataset, DatasetRegistry};
+pub use engine::{EngineError, QueryEngine, QueryResult};
+pub use operators::{open_operator, process_operator, ExecutionContext, Operator, OperatorError};
+pub use pipeline::{RowSource, RowStream};
+pub use script::ScriptFunctions;
+pub use table::{Cell, Row, Table};
+
+/// The [`arrow`] crate that [`Table`]s convert to and from record batches of, so applications can
+/// use the same version of it.
+pub use arrow;
@@ -0,0 +1,688 @@
+use std::fmt::Display;
+
+use crate::aggregate::ALL_ROWS;
+use crate::data::Dataset;
+#[cfg(test)]
+use crate::expression::Expression;
+use crate::operators::Operator;
+#[cfg(test)]
+use crate::operators::{CountByOrder, JoinKind, NullsOrder, SortOrder};
+
+/// Suspicious, but legal, patterns in an [`Operator`] chain.
+/// These never stop a query from running; they are printed as warnings before the results.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintWarning {
+    /// An ORDERBY whose ordering is thrown away by a later COUNTBY.
+    OrderDiscarded {
+        /// The column passed to the ORDERBY operator.
+        orderby_column: String,
+        /// The column passed to the COUNTBY operator.
+        countby_column: String,
+    },
+    /// A SELECT that drops a column needed by a later operator.
+    ColumnDroppedBySelect {
+        /// The column that is needed by the later operator.
+        column_name: String,
+        /// The later operator that needs the column.
+        operator: String,
+    },
+    /// A TAKE 0, which always produces an empty table.
+    TakeZero,
+    /// A JOIN on a column that doesn't uniquely identify the rows of the joined dataset, so every
+    /// row may match several rows and multiply the size of the result.
+    JoinOnNonKeyColumn {
+        /// The dataset passed to the JOIN operator.
+        dataset: Dataset,
+        /// The column passed to the JOIN operator.
+        column_name: String,
+    },
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintWarning::OrderDiscarded {
+                orderby_column,
+                countby_column,
+            } => f.write_fmt(format_args!(
+                "The ordering produced by ORDERBY {} is discarded by the later COUNTBY {}.",
+                orderby_column, countby_column
+            )),
+            LintWarning::ColumnDroppedBySelect {
+                column_name,
+                operator,
+            } => f.write_fmt(format_args!(
+                "SELECT drops the {} column needed by the later {} operator.",
+                column_name, operator
+            )),
+            LintWarning::TakeZero => f.write_str("TAKE 0 always produces an empty table."),
+            LintWarning::JoinOnNonKeyColumn {
+                dataset,
+                column_name,
+            } => f.write_fmt(format_args!(
+                "{} is not a key column of {}; each row may match several rows and multiply the size of the result.",
+                column_name, dataset
+            )),
+        }
+    }
+}
+
+/// Helper function to find the nearest SELECT in `chain` that drops `column_name`.
+/// The search stops at operators that replace the columns of their input (COUNTBY, the aggregates,
+/// GROUPBY, PIVOT, JOIN, and MAP), or add the column (RANK and EXTEND), as the column may
+/// legitimately be produced by them. Past a RENAME that produces the column, the search continues
+/// under its original name. A qualified name, e.g. `city.CityName`, also refers to the selected `CityName`.
+/// The selected names match ignoring case, as the columns are looked up.
+fn find_dropping_select(chain: &Operator, column_name: &str) -> bool {
+    let unqualified = column_name.split_once('.').map(|(_, name)| name);
+    match chain {
+        Operator::Select { columns, .. } => !columns.iter().any(|column| {
+            let name = column.name().to_lowercase();
+            name == column_name.to_lowercase() || Some(name) == unqualified.map(str::to_lowercase)
+        }),
+        Operator::SelectAll { except, .. } if except.iter().any(|name| name == column_name) => true,
+        Operator::Rename {
+            chain,
+            column,
+            new_name,
+        } if new_name == column_name => find_dropping_select(chain, column),
+        // The column was renamed away, which isn't a mistake made by a SELECT.
+        Operator::Rename { column, .. } if column == column_name => false,
+        // The rank column is named `rank`, suffixed with a number if that name is taken.
+        Operator::Rank { .. } if column_name.split('_').next() == Some("rank") => false,
+        Operator::Extend { columns, .. } if columns.iter().any(|(name, _)| name == column_name) => {
+            false
+        }
+        Operator::CountBy { .. }
+        | Operator::Aggregate { .. }
+        | Operator::GroupBy { .. }
+        | Operator::Pivot { .. }
+        | Operator::Join { .. }
+        | Operator::Map { .. } => false,
+        _ => chain
+            .chain()
+            .is_some_and(|chain| find_dropping_select(chain, column_name)),
+    }
+}
+
+/// Helper function to find an ORDERBY in `chain` whose ordering is not observed before the chain
+/// ends. Operators that depend on the order of their input (i.e., TAKE, SKIP, TAIL, TOPN, and
+/// DISTINCT on a subset of the columns, which keeps the first row of each group) stop the search.
+fn find_unobserved_orderby(chain: &Operator) -> Option<&str> {
+    match chain {
+        Operator::OrderBy { column, .. } => Some(column),
+        Operator::Take { .. }
+        | Operator::Skip { .. }
+        | Operator::Tail { .. }
+        | Operator::TopN { .. } => None,
+        Operator::Distinct { columns, .. } if !columns.is_empty() => None,
+        _ => chain.chain().and_then(find_unobserved_orderby),
+    }
+}
+
+/// Checks a single `operator` (not its chain) for suspicious patterns.
+fn lint_single(operator: &Operator) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    match operator {
+        Operator::Take { count: 0, .. } => warnings.push(LintWarning::TakeZero),
+        Operator::CountBy { chain, column, .. } => {
+            if let Some(orderby_column) = find_unobserved_orderby(chain) {
+                warnings.push(LintWarning::OrderDiscarded {
+                    orderby_column: orderby_column.to_string(),
+                    countby_column: column.clone(),
+                });
+            }
+        }
+        // The key columns of other datasets, and of sub-queries, aren't known until they are
+        // loaded.
+        Operator::Join {
+            right,
+            column,
+            right_column,
+            ..
+        } => {
+            let column = right_column.as_ref().unwrap_or(column);
+            if let Operator::From(dataset) = right.as_ref() {
+                if dataset.is_builtin() && !dataset.key_columns().contains(column) {
+                    warnings.push(LintWarning::JoinOnNonKeyColumn {
+                        dataset: dataset.clone(),
+                        column_name: column.clone(),
+                    });
+                }
+            }
+        }
+        _ => (),
+    }
+
+    let needed_columns = match operator {
+        Operator::OrderBy { column, .. }
+        | Operator::CountBy { column, .. }
+        | Operator::Aggregate { column, .. }
+        | Operator::GroupBy { column, .. }
+        | Operator::Join { column, .. }
+        | Operator::Rename { column, .. }
+        | Operator::Cast { column, .. }
+        | Operator::FillNa { column, .. } => vec![column],
+        Operator::TopN { group, column, .. } => vec![group, column],
+        Operator::Reorder { columns, .. } => columns.iter().collect(),
+        Operator::Pivot {
+            row,
+            column,
+            aggregate,
+            ..
+        } => vec![row, column, &aggregate.column],
+        Operator::Rank {
+            column, partition, ..
+        } => [Some(column), partition.as_ref()]
+            .into_iter()
+            .flatten()
+            .collect(),
+        _ => vec![],
+    };
+    if let Some(chain) = operator.chain() {
+        for column in needed_columns {
+            // COUNT * counts the rows rather than reading a column.
+            if column != ALL_ROWS && find_dropping_select(chain, column) {
+                warnings.push(LintWarning::ColumnDroppedBySelect {
+                    column_name: column.clone(),
+                    operator: operator.name().to_string(),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Checks the `operator` chain for suspicious patterns that are likely to be mistakes.
+///
+/// # Arguments
+/// `operator` : The operator chain to check.
+///
+/// # Returns
+/// The [`LintWarning`]s found, ordered from the start of the chain (FROM) to its end. The warnings
+/// for the sub-query of a JOIN or a UNION come before the ones for the operator itself.
+pub fn lint_operator(operator: &Operator) -> Vec<LintWarning> {
+    let mut warnings = match operator.chain() {
+        Some(chain) => lint_operator(chain),
+        None => Vec::new(),
+    };
+    if let Some(subquery) = operator.subquery() {
+        warnings.append(&mut lint_operator(subquery));
+    }
+    warnings.append(&mut lint_single(operator));
+    warnings
+}
+
+#[test]
+fn test_lint_operator_clean() {
+    let operator = Operator::Take {
+        chain: Box::new(Operator::OrderBy {
+            chain: Box::new(Operator::Join {
+                chain: Box::new(Operator::From(Dataset::City)),
+                right: Box::new(Operator::From(Dataset::Country)),
+                column: "CountryCode".to_string(),
+                right_column: None,
+                kind: JoinKind::Inner,
+                strategy: None,
+            }),
+            column: "CityPop".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::Last,
+        }),
+        count: 10,
+    };
+    assert_eq!(lint_operator(&operator), vec![]);
+}
+
+#[test]
+fn test_lint_operator_order_discarded() {
+    let operator = Operator::CountBy {
+        chain: Box::new(Operator::OrderBy {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CityPop".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::Last,
+        }),
+        column: "CountryCode".to_string(),
+        order: CountByOrder::Count,
+        bars: false,
+        having: None,
+    };
+    assert_eq!(
+        lint_operator(&operator),
+        vec![LintWarning::OrderDiscarded {
+            orderby_column: "CityPop".to_string(),
+            countby_column: "CountryCode".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_order_observed_by_take() {
+    let operator = Operator::CountBy {
+        chain: Box::new(Operator::Take {
+            chain: Box::new(Operator::OrderBy {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
+            }),
+            count: 100,
+        }),
+        column: "CountryCode".to_string(),
+        order: CountByOrder::Count,
+        bars: false,
+        having: None,
+    };
+    assert_eq!(lint_operator(&operator), vec![]);
+}
+
+#[test]
+fn test_lint_operator_order_observed_by_skip() {
+    let operator = Operator::CountBy {
+        chain: Box::new(Operator::Skip {
+            chain: Box::new(Operator::OrderBy {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
+            }),
+            count: 100,
+        }),
+        column: "CountryCode".to_string(),
+        order: CountByOrder::Count,
+        bars: false,
+        having: None,
+    };
+    assert_eq!(lint_operator(&operator), vec![]);
+}
+
+#[test]
+fn test_lint_operator_order_observed_by_tail() {
+    let operator = Operator::CountBy {
+        chain: Box::new(Operator::Tail {
+            chain: Box::new(Operator::OrderBy {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
+            }),
+            count: 100,
+        }),
+        column: "CountryCode".to_string(),
+        order: CountByOrder::Count,
+        bars: false,
+        having: None,
+    };
+    assert_eq!(lint_operator(&operator), vec![]);
+}
+
+#[test]
+fn test_lint_operator_order_observed_by_distinct() {
+    let orderby = Operator::OrderBy {
+        chain: Box::new(Operator::From(Dataset::City)),
+        column: "CityPop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    let countby = |columns: Vec<String>| Operator::CountBy {
+        chain: Box::new(Operator::Distinct {
+            chain: Box::new(orderby.clone()),
+            columns,
+        }),
+        column: "CountryCode".to_string(),
+        order: CountByOrder::Count,
+        bars: false,
+        having: None,
+    };
+    assert_eq!(
+        lint_operator(&countby(vec!["CountryCode".to_string()])),
+        vec![]
+    );
+    assert_eq!(lint_operator(&countby(vec![])).len(), 1);
+}
+
+#[test]
+fn test_lint_operator_column_dropped_by_select() {
+    let operator = Operator::OrderBy {
+        chain: Box::new(Operator::Take {
+            chain: Box::new(Operator::Select {
+                chain: Box::new(Operator::From(Dataset::City)),
+                columns: vec![Expression::Column("CityName".to_string()).into()],
+            }),
+            count: 10,
+        }),
+        column: "CityPop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    assert_eq!(
+        lint_operator(&operator),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "CityPop".to_string(),
+            operator: "ORDERBY".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_column_renamed_by_select() {
+    let select = |columns| Operator::Select {
+        chain: Box::new(Operator::From(Dataset::City)),
+        columns: crate::expression::parse_select_list(columns).unwrap(),
+    };
+    let orderby = |columns| Operator::OrderBy {
+        chain: Box::new(select(columns)),
+        column: "Pop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    assert_eq!(lint_operator(&orderby("CityName,CityPop AS Pop")), vec![]);
+    assert_eq!(lint_operator(&orderby("CityName AS Pop,CityPop")), vec![]);
+    assert_eq!(lint_operator(&orderby("CityPop AS Population")).len(), 1);
+}
+
+#[test]
+fn test_lint_operator_column_renamed_by_rename() {
+    let rename = |select: &str, column: &str| Operator::Rename {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: crate::expression::parse_select_list(select).unwrap(),
+        }),
+        column: column.to_string(),
+        new_name: "Population".to_string(),
+    };
+    let orderby = |chain: Operator, column: &str| Operator::OrderBy {
+        chain: Box::new(chain),
+        column: column.to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    assert_eq!(
+        lint_operator(&orderby(
+            rename("CityName,CityPop", "CityPop"),
+            "Population"
+        )),
+        vec![]
+    );
+    assert_eq!(
+        lint_operator(&orderby(rename("CityName,CityPop", "CityPop"), "CityPop")),
+        vec![]
+    );
+    assert_eq!(
+        lint_operator(&orderby(rename("CityName,CityID", "CityID"), "CityPop")),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "CityPop".to_string(),
+            operator: "ORDERBY".to_string(),
+        }]
+    );
+    assert_eq!(
+        lint_operator(&rename("CityName", "CityPop")),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "CityPop".to_string(),
+            operator: "RENAME".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_column_added_by_extend() {
+    let extend = |columns| Operator::OrderBy {
+        chain: Box::new(Operator::Extend {
+            chain: Box::new(Operator::Select {
+                chain: Box::new(Operator::From(Dataset::City)),
+                columns: vec![Expression::Column("CityPop".to_string()).into()],
+            }),
+            columns: crate::expression::parse_extend_list(columns).unwrap(),
+        }),
+        column: "Pop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    assert_eq!(lint_operator(&extend("Pop = CityPop / 1000")), vec![]);
+    assert_eq!(
+        lint_operator(&extend("Other = CityPop / 1000")),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "Pop".to_string(),
+            operator: "ORDERBY".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_cast_column_dropped_by_select() {
+    let operator = Operator::Cast {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![Expression::Column("CityName".to_string()).into()],
+        }),
+        column: "CityPop".to_string(),
+        to: crate::operators::CastType::String,
+    };
+    assert_eq!(
+        lint_operator(&operator),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "CityPop".to_string(),
+            operator: "CAST".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_count_rows() {
+    let operator = Operator::Aggregate {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![Expression::Column("CityName".to_string()).into()],
+        }),
+        function: crate::aggregate::AggregateFunction::Count,
+        column: ALL_ROWS.to_string(),
+    };
+    assert_eq!(lint_operator(&operator), vec![]);
+}
+
+#[test]
+fn test_lint_operator_topn_column_dropped_by_select() {
+    let operator = Operator::TopN {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![Expression::Column("CityName".to_string()).into()],
+        }),
+        count: 3,
+        group: "CountryCode".to_string(),
+        column: "CityPop".to_string(),
+        order: SortOrder::Descending,
+    };
+    let dropped = |column_name: &str| LintWarning::ColumnDroppedBySelect {
+        column_name: column_name.to_string(),
+        operator: "TOPN".to_string(),
+    };
+    assert_eq!(
+        lint_operator(&operator),
+        vec![dropped("CountryCode"), dropped("CityPop")]
+    );
+}
+
+#[test]
+fn test_lint_operator_rank_column_dropped_by_select() {
+    let operator = Operator::Rank {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![Expression::Column("CityPop".to_string()).into()],
+        }),
+        column: "CityPop".to_string(),
+        partition: Some("CountryCode".to_string()),
+    };
+    assert_eq!(
+        lint_operator(&operator),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "CountryCode".to_string(),
+            operator: "RANK".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_column_dropped_by_select_all() {
+    let select_all = |except: &[&str]| Operator::SelectAll {
+        chain: Box::new(Operator::From(Dataset::City)),
+        except: except.iter().map(|name| name.to_string()).collect(),
+    };
+    let orderby = |chain: Operator| Operator::OrderBy {
+        chain: Box::new(chain),
+        column: "CityPop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    assert_eq!(lint_operator(&orderby(select_all(&[]))), vec![]);
+    assert_eq!(lint_operator(&orderby(select_all(&["CityName"]))), vec![]);
+    assert_eq!(
+        lint_operator(&orderby(select_all(&["CityName", "CityPop"]))),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "CityPop".to_string(),
+            operator: "ORDERBY".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_qualified_column() {
+    // The selected CityName column keeps its qualified name.
+    let orderby = |column: &str| Operator::OrderBy {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![Expression::Column("CityName".to_string()).into()],
+        }),
+        column: column.to_string(),
+        order: SortOrder::Ascending,
+        nulls: NullsOrder::Last,
+    };
+    assert_eq!(lint_operator(&orderby("city.CityName")), vec![]);
+    // Names match ignoring case, as the columns are looked up.
+    assert_eq!(lint_operator(&orderby("cityname")), vec![]);
+    assert_eq!(lint_operator(&orderby("City.CITYNAME")), vec![]);
+    assert_eq!(
+        lint_operator(&orderby("city.CityPop")),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "city.CityPop".to_string(),
+            operator: "ORDERBY".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_rank_column() {
+    // The rank column is added by RANK rather than dropped by the SELECT before it.
+    let operator = Operator::OrderBy {
+        chain: Box::new(Operator::Rank {
+            chain: Box::new(Operator::Select {
+                chain: Box::new(Operator::From(Dataset::City)),
+                columns: vec![Expression::Column("CityPop".to_string()).into()],
+            }),
+            column: "CityPop".to_string(),
+            partition: None,
+        }),
+        column: "rank".to_string(),
+        order: SortOrder::Ascending,
+        nulls: NullsOrder::Last,
+    };
+    assert_eq!(lint_operator(&operator), vec![]);
+}
+
+#[test]
+fn test_lint_operator_groupby_column_dropped_by_select() {
+    let operator = Operator::GroupBy {
+        chain: Box::new(Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: vec![Expression::Column("CityPop".to_string()).into()],
+        }),
+        column: "CountryCode".to_string(),
+        aggregates: crate::aggregate::parse_aggregate_list("SUM(CityPop)").unwrap(),
+        having: None,
+    };
+    assert_eq!(
+        lint_operator(&operator),
+        vec![LintWarning::ColumnDroppedBySelect {
+            column_name: "CountryCode".to_string(),
+            operator: "GROUPBY".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_lint_operator_take_zero() {
+    let operator = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 0,
+    };
+    assert_eq!(lint_operator(&operator), vec![LintWarning::TakeZero]);
+}
+
+#[test]
+fn test_lint_operator_join_on_non_key_column() {
+    let operator = Operator::Join {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        right: Box::new(Operator::From(Dataset::Language)),
+        column: "CountryCode".to_string(),
+        right_column: None,
+        kind: JoinKind::Inner,
+        strategy: None,
+    };
+    let warnings = lint_operator(&operator);
+    assert_eq!(
+        warnings,
+        vec![LintWarning::JoinOnNonKeyColumn {
+            dataset: Dataset::Language,
+            column_name: "CountryCode".to_string(),
+        }]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "CountryCode is not a key column of language.csv; each row may match several rows and multiply the size of the result."
+    );
+}
+
+#[test]
+fn test_lint_operator_subquery() {
+    let operator = Operator::Join {
+        chain: Box::new(Operator::From(Dataset::City)),
+        right: Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            count: 0,
+        }),
+        column: "CountryCode".to_string(),
+        right_column: None,
+        kind: JoinKind::Inner,
+        strategy: None,
+    };
+    // The sub-query is linted, but its key columns aren't known.
+    assert_eq!(lint_operator(&operator), vec![LintWarning::TakeZero]);
+    assert_eq!(
+        operator.label(),
+        "JOIN (FROM language.csv TAKE 0) CountryCode"
+    );
+}
+
+#[test]
+fn test_lint_operator_join_on_key_column() {
+    let join = |column: &str, right_column: &str| Operator::Join {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        right: Box::new(Operator::From(Dataset::City)),
+        column: column.to_string(),
+        right_column: Some(right_column.to_string()),
+        kind: JoinKind::Inner,
+        strategy: None,
+    };
+    assert_eq!(lint_operator(&join("Capital", "CityID")), vec![]);
+    assert_eq!(
+        lint_operator(&join("Capital", "CityPop")),
+        vec![LintWarning::JoinOnNonKeyColumn {
+            dataset: Dataset::City,
+            column_name: "CityPop".to_string(),
+        }]
+    );
+    assert_eq!(
+        join("Capital", "CityID").label(),
+        "JOIN city.csv ON Capital=CityID"
+    );
+}
@@ -1,107 +1,1308 @@
-mod commands;
-mod data;
-mod operators;
-mod table;
-
-use commands::*;
-use operators::*;
-
-/// Prints an error message about the input being malformed to stdout.
-fn print_error_message(error_message: &str) {
-    println!("Malformed input. {}", error_message);
-}
-
-/// The help message to print to stdout for the `help` command.
-const C_HELP_MESSAGE: &str =
-    "Available Commands: \n
-      FROM <dataset> - Loads the `dataset`. \n
-          Maybe chained with other commands. Must always be the first command in a chain.\n
-          If no other command is specified, will print the `dataset`. \n
-      SELECT <column-name> - used to select particular columns from the specified dataset. \n
-          See the Datasets section below for a list of column-names for each dataset. \n
-      TAKE <number> - Specifies the number of rows to print from the dataset. \n
-          <number> must be greater than or equal to 0. \n
-      ORDERBY <numeric-column-name> - Sorts the loaded dataset by the column-name in descending order, if the column contains numeric values. \n
-          See the Datasets section below for a list of acceptable values for <numeric-column-name> for each dataset. \n
-      COUNTBY <column-name> - Returns the . \n
-          <number> must be greater than or equal to 0. \n
-      JOIN <dataset> <column-name> - performs a join on the current dataset and the one specified in this command on <column-name>. \n
-          See the Datasets section below for a list of available datasets and the column-names for each dataset. \n
-          The provided <column-name> must be present in both datasets. \n
-    \n
-    Available Datasets\n
-      <dataset> : city.csv\n
-          <column-name> : [CityID, CityName, CountryCode, CityPop]\n
-          <numeric-column-name> : [CityID, CityPop]\n
-      <dataset> : country.csv\n
-          <column-name> : [CountryCode, CountryName, Continent, CountryPop, Capital]\n
-          <numeric-column-name> : [CountryPop, Capital]\n
-      <dataset> : language.csv\n
-          <column-name> : [CountryCode,Language]\n
-          <numeric-column-name> : []\n";
-
-/// Main driver logic for parsing the user input and running the query.
+mod arguments;
+
+use std::error::Error;
+use std::io::{BufRead, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use arguments::{parse_arguments, Arguments, C_USAGE_MESSAGE};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+use terminal_size::Width;
+use toy_query_engine::commands::*;
+use toy_query_engine::completion::complete;
+use toy_query_engine::data::{parse_encoding, Dataset, DatasetRegistry};
+use toy_query_engine::describe::{describe_table, list_columns, list_tables};
+use toy_query_engine::engine::{run_operator, QueryEngine, QueryResult};
+use toy_query_engine::explain::{explain_analyze, explain_operator};
+use toy_query_engine::help::{help_message, operator_help};
+use toy_query_engine::lexer::{split_statements, tokenize};
+use toy_query_engine::lint::lint_operator;
+use toy_query_engine::operators::*;
+use toy_query_engine::optimizer::optimize;
+use toy_query_engine::profile::{QueryProfile, SessionProfile};
+use toy_query_engine::render::{self, query_output, render_output, row_count, QueryOutput};
+use toy_query_engine::script::ScriptFunctions;
+use toy_query_engine::server::{serve, serve_sessions};
+use toy_query_engine::session::{SavedResult, SavedSession};
+use toy_query_engine::settings::{parse_setting, Setting, Settings, Syntax};
+use toy_query_engine::sql::parse_sql_command;
+use toy_query_engine::table::format_bytes;
+use toy_query_engine::watch;
+
+/// Returns the path of the file the REPL history is kept in between sessions:
+/// `.toy_query_engine_history` in the user's home directory, or `None` if it isn't known.
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".toy_query_engine_history"))
+}
+
+#[test]
+fn test_history_path() {
+    if let Some(path) = history_path() {
+        assert!(path.ends_with(".toy_query_engine_history"));
+    }
+}
+
+/// Completes keywords, dataset names, and column names in the line being edited when Tab is
+/// pressed. See [`complete`].
+struct ReplHelper {
+    /// A copy of the datasets registered in the session, updated after every command.
+    datasets: DatasetRegistry,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(line, pos, &self.datasets))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Returns the line printed after a query when `\timing` is on, e.g. "30670 rows (2.1 MB) in
+/// 1.24s, 4079 rows scanned", with the estimated memory used by the result and the number of rows
+/// read from the datasets to compute it.
+fn timing_message(result: &QueryResult) -> String {
+    let rows = result.table.rows.len();
+    format!(
+        "{} {} ({}) in {:.2}s, {} {} scanned",
+        rows,
+        if rows == 1 { "row" } else { "rows" },
+        format_bytes(result.table.estimated_size()),
+        result.elapsed.as_secs_f64(),
+        result.rows_scanned,
+        if result.rows_scanned == 1 {
+            "row"
+        } else {
+            "rows"
+        },
+    )
+}
+
+#[test]
+fn test_timing_message() {
+    use toy_query_engine::table::{Cell, Row, Table};
+    let result = |rows: usize, rows_scanned: usize, elapsed: Duration| QueryResult {
+        table: Table::new(
+            vec!["n".to_string()],
+            vec![
+                Row {
+                    cells: vec![Cell::Int64(0)]
+                };
+                rows
+            ],
+        ),
+        rows_scanned,
+        elapsed,
+        warnings: vec![],
+    };
+    assert_eq!(
+        timing_message(&result(30670, 4079, Duration::from_millis(1240))),
+        "30670 rows (1.4 MB) in 1.24s, 4079 rows scanned"
+    );
+    assert_eq!(
+        timing_message(&result(1, 1, Duration::from_micros(1500))),
+        "1 row (194 bytes) in 0.00s, 1 row scanned"
+    );
+    assert_eq!(
+        timing_message(&result(0, 0, Duration::ZERO)),
+        "0 rows (146 bytes) in 0.00s, 0 rows scanned"
+    );
+}
+
+/// Prints the `text` of a query result a page at a time: the first `header_lines` lines, which
+/// name the columns, and the first `page_size` rows, then each following page of rows once the user
+/// presses Enter. Entering `q`, or the end of the `input`, stops the printing.
+///
+/// # Arguments
+/// `text` : The formatted result, one row per line after the header.
+/// `header_lines` : The number of lines at the start of `text` that precede the rows.
+/// `page_size` : The number of rows printed at a time.
+/// `input` : Where the user's answers are read from.
+/// `output` : Where the result and the prompts are printed to.
+///
+/// # Returns
+/// An error propagated from reading the `input` or writing the `output`.
+fn print_paged<R: BufRead, W: Write>(
+    text: &str,
+    header_lines: usize,
+    page_size: usize,
+    input: &mut R,
+    output: &mut W,
+) -> std::io::Result<()> {
+    let lines: Vec<&str> = text.lines().collect();
+    let (header, rows) = lines.split_at(header_lines.min(lines.len()));
+    for line in header {
+        writeln!(output, "{}", line)?;
+    }
+    let mut shown = 0;
+    for page in rows.chunks(page_size.max(1)) {
+        if shown > 0 {
+            write!(
+                output,
+                "-- {} of {} rows shown. Press Enter for more, or q to stop. --",
+                shown,
+                rows.len()
+            )?;
+            output.flush()?;
+            let mut answer = String::new();
+            if input.read_line(&mut answer)? == 0 {
+                // The terminal doesn't echo a new line at the end of the input.
+                return writeln!(output);
+            }
+            if answer.trim() == "q" {
+                return Ok(());
+            }
+        }
+        for line in page {
+            writeln!(output, "{}", line)?;
+        }
+        shown += page.len();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_print_paged() {
+    let text = "Name | Pop\n-----+----\na    | 1\nb    | 2\nc    | 3\nd    | 4\ne    | 5\n";
+    let page = |input: &str| {
+        let mut output = Vec::new();
+        print_paged(text, 2, 2, &mut input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    let prompt = |shown| {
+        format!(
+            "-- {} of 5 rows shown. Press Enter for more, or q to stop. --",
+            shown
+        )
+    };
+    assert_eq!(
+        page("\n\n"),
+        format!(
+            "Name | Pop\n-----+----\na    | 1\nb    | 2\n{}c    | 3\nd    | 4\n{}e    | 5\n",
+            prompt(2),
+            prompt(4)
+        )
+    );
+    assert_eq!(
+        page("q\n"),
+        format!("Name | Pop\n-----+----\na    | 1\nb    | 2\n{}", prompt(2))
+    );
+    assert_eq!(
+        page(""),
+        format!(
+            "Name | Pop\n-----+----\na    | 1\nb    | 2\n{}\n",
+            prompt(2)
+        )
+    );
+}
+
+#[test]
+fn test_print_paged_single_page() {
+    let mut output = Vec::new();
+    print_paged("a,b\n1,2\n", 1, 10, &mut "".as_bytes(), &mut output).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "a,b\n1,2\n");
+}
+
+/// Prints an error message about the input being malformed to stdout, in red if `color` is on.
+fn print_error_message(error_message: &str, color: bool) {
+    println!(
+        "{}",
+        render::error(&format!("Malformed input. {}", error_message), color)
+    );
+}
+
+/// Returns a line that marks the `span` of the `input` with carets, so that, printed below the
+/// `input` as it was entered after the `prompt`, it points at the offending token.
+fn span_marker(prompt: &str, input: &str, span: Span) -> String {
+    let width = |text: &str| text.chars().count();
+    format!(
+        "{}{}",
+        " ".repeat(width(prompt) + width(&input[..span.start])),
+        "^".repeat(width(&input[span.start..span.end]).max(1))
+    )
+}
+
+#[test]
+fn test_span_marker() {
+    let input = "FROM city.csv TAKE many";
+    assert_eq!(
+        span_marker("> ", input, Span { start: 19, end: 23 }),
+        "                     ^^^^"
+    );
+    // Multi-byte characters before the span take a single column.
+    assert_eq!(
+        span_marker("> ", "FROM São TAKE 1", Span { start: 5, end: 9 }),
+        "       ^^^"
+    );
+    assert_eq!(span_marker("> ", "", Span { start: 0, end: 0 }), "  ^");
+    assert_eq!(
+        span_marker("query> ", "FROM", Span { start: 0, end: 4 }),
+        "       ^^^^"
+    );
+}
+
+/// State kept for the duration of the REPL session.
+#[derive(Debug, Default)]
+struct Session {
+    /// The settings changed with `\<setting>` commands.
+    settings: Settings,
+    /// The context every query is processed in.
+    context: ExecutionContext,
+    /// The datasets registered with `LOAD` commands.
+    datasets: DatasetRegistry,
+    /// Where to write the execution statistics of the session's queries, if requested with
+    /// `--profile-out`.
+    profile_out: Option<String>,
+    /// The execution statistics of the queries run so far. Only collected if `profile_out` is set.
+    profile: SessionProfile,
+    /// Whether any input of the session was malformed or failed to run. Determines the exit code
+    /// when the queries come from `--command` or `--file`.
+    failed: bool,
+    /// Whether the user is typing the input and reading the results in a terminal, so long
+    /// results can be paged with `\pagesize`.
+    interactive: bool,
+    /// Whether only the results are printed, without the welcome banner and the goodbye message,
+    /// as requested by `--quiet` or when the queries are piped in.
+    quiet: bool,
+    /// Whether the results and errors are colored, i.e., they are printed to a terminal and
+    /// `NO_COLOR` isn't set.
+    color: bool,
+    /// The results saved with `SET`, as the queries producing them, in the order they were saved.
+    /// Written by `\save`.
+    results: Vec<SavedResult>,
+}
+
+impl Session {
+    /// Creates a session configured by the command line `arguments`.
+    ///
+    /// Returns
+    /// The session, or an error propagated from loading the `--script`.
+    fn new(arguments: Arguments) -> Result<Self, Box<dyn Error>> {
+        let mut context = if arguments.profile_out.is_some() {
+            ExecutionContext::with_profiling()
+        } else {
+            ExecutionContext::default()
+        };
+        if let Some(path) = &arguments.script {
+            context.functions = Some(ScriptFunctions::from_file(path)?);
+        }
+        context.encoding = arguments.encoding;
+        let settings = Settings {
+            output_format: arguments.format.unwrap_or_default(),
+            ..Settings::default()
+        };
+        Ok(Session {
+            settings,
+            context,
+            profile_out: arguments.profile_out,
+            quiet: arguments.quiet,
+            ..Session::default()
+        })
+    }
+
+    /// Records the execution statistics collected while running `operator` and rewrites the
+    /// profile file, so it is complete even if the process is killed. Does nothing if profiling is
+    /// disabled.
+    fn record_profile(&mut self, operator: &Operator, succeeded: bool, elapsed: Duration) {
+        if let (Some(path), Some(operators)) = (&self.profile_out, self.context.take_profile()) {
+            self.profile.queries.push(QueryProfile {
+                query: operator.to_string(),
+                succeeded,
+                elapsed,
+                operators,
+            });
+            if let Err(e) = self.profile.write(path) {
+                println!("Failed to write the profile to {}: {}", path, e);
+            }
+        }
+    }
+
+    /// Changes the `setting`, and updates the context queries run in to match.
+    fn apply_setting(&mut self, setting: Setting) {
+        if setting == Setting::Cache(false) {
+            self.context.clear_results();
+        }
+        self.settings.apply(setting);
+        self.context.limits = ResultLimits {
+            max_rows: self.settings.max_rows,
+            max_bytes: self.settings.max_bytes,
+        };
+        if self.context.bad_rows != self.settings.bad_rows
+            || self.context.normalization != self.settings.normalization
+        {
+            // The datasets loaded so far are read again, to handle their bad rows and
+            // normalize their values as asked.
+            self.context.bad_rows = self.settings.bad_rows;
+            self.context.normalization = self.settings.normalization;
+            self.context.clear_cache();
+        }
+    }
+
+    /// Runs the `operator` chain and saves its result as `@<name>`, remembering the query for
+    /// `\save`.
+    fn set_result(&mut self, name: &str, operator: &Operator) {
+        match process_operator(&optimize(operator), &mut self.context) {
+            Ok(out) => {
+                println!("Saved {} as @{}.", row_count(out.rows.len()), name);
+                self.context.set_variable(name, out);
+                // The previous query of the name is only kept if a later result, or this one, was
+                // computed from it, so that running the queries again in order gives the same
+                // results.
+                let query = operator.to_string();
+                if let Some(index) = self.results.iter().rposition(|saved| saved.name == name) {
+                    let reference = format!("@{}", name);
+                    let read = self.results[index + 1..]
+                        .iter()
+                        .map(|saved| saved.query.as_str())
+                        .chain([query.as_str()])
+                        .any(|query| {
+                            tokenize(query)
+                                .unwrap_or_default()
+                                .contains(&reference.as_str())
+                        });
+                    if !read {
+                        self.results.remove(index);
+                    }
+                }
+                self.results.push(SavedResult {
+                    name: name.to_string(),
+                    query,
+                });
+            }
+            Err(e) => {
+                self.report_error(e);
+            }
+        }
+    }
+
+    /// Returns the state of the session to write with `\save`.
+    fn saved(&self) -> SavedSession {
+        let mut saved = SavedSession {
+            settings: self
+                .settings
+                .arguments()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+            results: self.results.clone(),
+            ..SavedSession::default()
+        };
+        for dataset in self.datasets.registered() {
+            match dataset {
+                Dataset::Registered {
+                    name,
+                    path,
+                    encoding,
+                } => {
+                    if let Some(encoding) = encoding {
+                        saved
+                            .encodings
+                            .insert(name.clone(), encoding.name().to_string());
+                    }
+                    saved.datasets.insert(name, path);
+                }
+                // Each table of a database is attached, but the database is attached only once.
+                Dataset::Sqlite { path, .. } if !saved.attached.contains(&path) => {
+                    saved.attached.push(path)
+                }
+                _ => (),
+            }
+        }
+        saved
+    }
+
+    /// Restores the `saved` state written by `\save`: applies its settings, registers and
+    /// attaches its datasets, and runs the queries of its saved results again, in order. Each
+    /// part that can't be restored, e.g. a dataset whose file was removed, is reported and
+    /// skipped.
+    fn restore(&mut self, saved: SavedSession) {
+        for (name, value) in &saved.settings {
+            let args = tokenize(value).unwrap_or_default();
+            match parse_setting(name, &args) {
+                Ok(setting) => self.apply_setting(setting),
+                Err(message) => self.report_error(message),
+            }
+        }
+        for (name, path) in &saved.datasets {
+            let registered = saved
+                .encodings
+                .get(name)
+                .map(|label| parse_encoding(label))
+                .transpose()
+                .and_then(|encoding| self.datasets.register_with_encoding(name, path, encoding));
+            if let Err(error) = registered {
+                self.report_error(error);
+            }
+        }
+        for path in &saved.attached {
+            if let Err(error) = self.datasets.attach(path) {
+                self.report_error(error);
+            }
+        }
+        self.context.clear_results();
+        for result in &saved.results {
+            match parse_command_with(&format!("{}\n", result.query), &self.datasets) {
+                Command::Operator(operator) => self.set_result(&result.name, &operator),
+                _ => self.report_error(format!(
+                    "Could not restore @{}, as its query is malformed: {}",
+                    result.name, result.query
+                )),
+            }
+        }
+    }
+
+    /// Prints the `error` that made the input fail, in red if the output is colored, and records
+    /// the failure for the exit code.
+    fn report_error(&mut self, error: impl std::fmt::Display) {
+        println!("{}", render::error(&error.to_string(), self.color));
+        self.failed = true;
+    }
+}
+
+/// Runs the `operator` chain of a query and prints its results in the output format of the
+/// `session`, preceded by its lint warnings and followed by its timing if `\timing` is on.
+fn run_query(operator: &Operator, session: &mut Session) {
+    for warning in lint_operator(operator) {
+        println!("Warning: {}", warning);
+    }
+    let start = Instant::now();
+    let result = run_operator(operator, &mut session.context, session.settings.cache);
+    session.record_profile(operator, result.is_ok(), start.elapsed());
+    // Only the time taken to compute the results counts, not the time taken to print them.
+    let timing = match &result {
+        Ok(result) if session.settings.timing => Some(timing_message(result)),
+        _ => None,
+    };
+    let output =
+        result.map(|result| query_output(operator, result.table, session.settings.default_limit));
+    match output {
+        Ok(QueryOutput::Written(message)) => println!("{}", message),
+        Ok(QueryOutput::Rows {
+            table: out,
+            limit_message,
+        }) => {
+            let (text, header_lines) =
+                render_output(&out, session.settings.output_format, session.color);
+            match session.settings.page_size.filter(|_| session.interactive) {
+                Some(page_size) => {
+                    if let Err(e) = print_paged(
+                        &text,
+                        header_lines,
+                        page_size,
+                        &mut std::io::stdin().lock(),
+                        &mut std::io::stdout(),
+                    ) {
+                        println!("Failed to print the results: {}", e);
+                    }
+                    println!();
+                }
+                None => println!("{}", text),
+            }
+            if let Some(limit_message) = limit_message {
+                println!("{}", limit_message);
+            }
+        }
+        Err(e) => {
+            session.report_error(e);
+        }
+    }
+    if let Some(timing) = timing {
+        println!("{}", timing);
+    }
+}
+
+/// Runs the `operator` chain of a query like [`run_query`], then runs it again each time one of
+/// the files it reads is modified, until the user presses Ctrl-C. Datasets that aren't files,
+/// e.g. those downloaded from a URL or saved with SET, aren't watched.
+fn watch_query(operator: &Operator, session: &mut Session) {
+    let mut paths: Vec<String> = Vec::new();
+    for path in operator
+        .datasets()
+        .iter()
+        .filter_map(|dataset| dataset.path())
+    {
+        if !paths.iter().any(|watched| watched == path) {
+            paths.push(path.to_string());
+        }
+    }
+    if paths.is_empty() {
+        session
+            .report_error("WATCH needs a query that reads a file, e.g. WATCH FROM log.csv COUNT.");
+        return;
+    }
+    let mut modified = watch::modification_times(&paths);
+    run_query(operator, session);
+    println!(
+        "Watching {} for changes. Press Ctrl-C to stop.",
+        paths.join(", ")
+    );
+    let cancelled = session.context.cancelled.clone();
+    while let Some(changed) =
+        watch::wait_for_change(&paths, &mut modified, watch::C_WATCH_INTERVAL, &cancelled)
+    {
+        println!("{} changed, running the query again.", changed.join(", "));
+        run_query(operator, session);
+    }
+}
+
+/// Main driver logic for parsing the user input and running the queries. Several statements
+/// separated by semicolons, e.g. `FROM city.csv TAKE 3; FROM country.csv TAKE 3`, run one after
+/// the other, each reporting its own errors. In a terminal, each of them is shown as if it had
+/// been entered at the prompt before it runs.
 ///
 /// # Arguments
 /// 'input': The text entered by the user.
+/// 'session': The state of the REPL session. Updated in place by `\<setting>` commands and
+/// queries.
+/// 'history': The queries entered earlier in the session, listed by `\history` and run again by
+/// `\rerun`. Each statement of the `input` is appended to it, unless it's one of those two
+/// commands.
 ///
 /// # Returns
 /// `true` - Indicates the user entered the 'exit' command and the process should exit.
 /// `false` - Some other command was entered and the process should not exit.
-fn process_input(input: &str) -> bool {
+fn process_input(input: &str, session: &mut Session, history: &mut Vec<String>) -> bool {
+    let statements = split_statements(input.trim_end_matches('\n'));
+    if statements.len() < 2 {
+        // A single statement keeps its position in the line, for errors to be marked below it.
+        let statement = statements.first().copied().unwrap_or_default();
+        return process_statement(&format!("{}\n", statement), session, history);
+    }
+    for statement in statements {
+        let statement = statement.trim();
+        if session.interactive {
+            println!("{}{}", session.settings.prompt, statement);
+        }
+        // The statements after an `exit` aren't run.
+        if process_statement(&format!("{}\n", statement), session, history) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Parses a single statement of the user input and runs it. See [`process_input`].
+fn process_statement(input: &str, session: &mut Session, history: &mut Vec<String>) -> bool {
     let mut should_exit = false;
-    match parse_command(input) {
+    let command = match session.settings.syntax {
+        Syntax::Pipe => parse_command_with(input, &session.datasets),
+        Syntax::Sql => parse_sql_command(input, &session.datasets),
+    };
+    if !matches!(
+        command,
+        Command::History | Command::Rerun(_) | Command::Exit | Command::NoInput
+    ) {
+        // A query continued over several lines is listed on a single one.
+        history.push(input.trim().replace('\n', " "));
+    }
+    match command {
         Command::Exit => {
-            println!("Goodbye!");
+            if !session.quiet {
+                println!("Goodbye!");
+            }
             should_exit = true;
         }
-        Command::Help => println!("{}", C_HELP_MESSAGE),
-        Command::Operator(operator) => match process_operator(&operator) {
-            Ok(out) => println!("{}", out),
-            Err(e) => println!("{}", e),
+        Command::Help(None) => println!("{}", help_message(&session.datasets)),
+        Command::Help(Some(name)) => {
+            if let Some(operator) = find_operator_descriptor(&name) {
+                println!("{}", operator_help(operator));
+            }
+        }
+        Command::Operator(operator) => run_query(&operator, session),
+        Command::Explain(operator) => {
+            for warning in lint_operator(&operator) {
+                println!("Warning: {}", warning);
+            }
+            print!("{}", explain_operator(&optimize(&operator)));
+        }
+        Command::ExplainAnalyze(operator) => {
+            for warning in lint_operator(&operator) {
+                println!("Warning: {}", warning);
+            }
+            match explain_analyze(&optimize(&operator), &mut session.context) {
+                Ok(plan) => print!("{}", plan),
+                Err(e) => {
+                    session.report_error(e);
+                }
+            }
+        }
+        Command::Watch(operator) => watch_query(&operator, session),
+        Command::Set { name, operator } => {
+            for warning in lint_operator(&operator) {
+                println!("Warning: {}", warning);
+            }
+            session.set_result(&name, &operator);
+        }
+        Command::Insert { dataset, rows } => match session.context.insert_rows(&dataset, rows) {
+            Ok(count) => println!("Inserted {} into {}.", row_count(count), dataset),
+            Err(e) => {
+                session.report_error(e);
+            }
+        },
+        Command::Describe(dataset) => {
+            match process_operator(&Operator::From(dataset), &mut session.context) {
+                Ok(table) => {
+                    let description = describe_table(&table);
+                    let (text, _) =
+                        render_output(&description, session.settings.output_format, session.color);
+                    println!("{}", text);
+                }
+                Err(e) => {
+                    session.report_error(e);
+                }
+            }
+        }
+        Command::ShowTables => {
+            let datasets: Vec<Dataset> = session
+                .datasets
+                .catalog()
+                .into_iter()
+                .chain(session.context.variables())
+                .collect();
+            let (text, _) = render_output(
+                &list_tables(&datasets),
+                session.settings.output_format,
+                session.color,
+            );
+            println!("{}", text);
+        }
+        Command::ShowColumns(dataset) => {
+            match process_operator(&Operator::From(dataset), &mut session.context) {
+                Ok(table) => {
+                    let (text, _) = render_output(
+                        &list_columns(&table),
+                        session.settings.output_format,
+                        session.color,
+                    );
+                    println!("{}", text);
+                }
+                Err(e) => {
+                    session.report_error(e);
+                }
+            }
+        }
+        Command::Save { dataset, path } => match session.context.save_dataset(&dataset, &path) {
+            Ok(count) => println!("Saved {} of {} to {}.", row_count(count), dataset, path),
+            Err(e) => {
+                session.report_error(e);
+            }
+        },
+        Command::Delete { dataset, predicate } => {
+            match session.context.delete_rows(&dataset, &predicate) {
+                Ok(count) => println!("Deleted {} from {}.", row_count(count), dataset),
+                Err(e) => {
+                    session.report_error(e);
+                }
+            }
+        }
+        Command::Load {
+            name,
+            path,
+            encoding,
+        } => match session
+            .datasets
+            .register_with_encoding(&name, &path, encoding)
+        {
+            Ok(_) => {
+                println!("Registered {} for {}.", name, path);
+                session.context.clear_results();
+            }
+            Err(error) => {
+                print_error_message(&error, session.color);
+                session.failed = true;
+            }
+        },
+        Command::Attach(path) => match session.datasets.attach(&path) {
+            Ok(tables) if tables.is_empty() => println!("{} has no tables to attach.", path),
+            Ok(tables) => {
+                println!(
+                    "Attached {}: {}.",
+                    path,
+                    tables
+                        .iter()
+                        .map(|table| table.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                );
+                session.context.clear_results();
+            }
+            Err(error) => {
+                print_error_message(&error, session.color);
+                session.failed = true;
+            }
+        },
+        Command::Setting(setting) => {
+            println!("{}", setting);
+            session.apply_setting(setting);
+        }
+        Command::SaveSession(path) => match session.saved().write(&path) {
+            Ok(()) => println!("Saved the session to {}.", path),
+            Err(e) => {
+                session.report_error(format!("Failed to save the session to {}: {}", path, e))
+            }
+        },
+        Command::LoadSession(path) => match SavedSession::read(&path) {
+            Ok(saved) => {
+                session.restore(saved);
+                println!("Loaded the session from {}.", path);
+            }
+            Err(e) => {
+                session.report_error(format!("Failed to load the session from {}: {}", path, e))
+            }
+        },
+        Command::InputError(error) => {
+            // The input is only shown right above the marker in the terminal. A query entered over
+            // several lines is shown again on a single one for the marker to line up with it.
+            if session.interactive {
+                let prompt = &session.settings.prompt;
+                let input = input.trim_end_matches('\n');
+                if input.contains('\n') {
+                    println!(
+                        "{}{}",
+                        " ".repeat(prompt.chars().count()),
+                        input.replace('\n', " ")
+                    );
+                }
+                println!(
+                    "{}",
+                    span_marker(prompt, &input.replace('\n', " "), error.span())
+                );
+            }
+            print_error_message(&error.to_string(), session.color);
+            session.failed = true;
+        }
+        Command::History => {
+            for (index, query) in history.iter().enumerate() {
+                println!("{:>4}  {}", index + 1, query);
+            }
+        }
+        Command::Rerun(number) => match history.get(number - 1).cloned() {
+            Some(query) => {
+                // Shown as if it had been entered at the prompt, for errors to be marked below it.
+                println!("{}{}", session.settings.prompt, query);
+                should_exit = process_input(&format!("{}\n", query), session, history);
+            }
+            None => session.report_error(format!(
+                "There is no query {} in the history. Use \\history to list them.",
+                number
+            )),
         },
-        Command::InputError(error) => print_error_message(&error),
         Command::NoInput => (),
     }
+    for report in session.context.take_bad_rows() {
+        println!("{}", report);
+    }
     should_exit
 }
 
 #[test]
 fn test_process_input_no_input() {
-    assert_eq!(process_input("\n"), false);
+    assert!(!process_input(
+        "\n",
+        &mut Session::default(),
+        &mut Vec::new()
+    ));
 }
 
 #[test]
 fn test_process_input_exit() {
-    assert_eq!(process_input("exit\n"), true);
+    assert!(process_input(
+        "exit\n",
+        &mut Session::default(),
+        &mut Vec::new()
+    ));
 }
 
 #[test]
 fn test_process_input_help() {
-    assert_eq!(process_input("help\n"), false);
+    assert!(!process_input(
+        "help\n",
+        &mut Session::default(),
+        &mut Vec::new()
+    ));
+}
+
+#[test]
+fn test_process_input_help_operator() {
+    assert!(!process_input(
+        "help TAKE\n",
+        &mut Session::default(),
+        &mut Vec::new()
+    ));
 }
 
 #[test]
 fn test_process_input_some_command() {
-    assert_eq!(process_input("FROM language.csv\n"), false);
+    assert!(!process_input(
+        "FROM language.csv\n",
+        &mut Session::default(),
+        &mut Vec::new()
+    ));
 }
 
 #[test]
 fn test_process_input_malformed_command() {
-    assert_eq!(process_input("FRM language.csv\n"), false);
+    assert!(!process_input(
+        "FRM language.csv\n",
+        &mut Session::default(),
+        &mut Vec::new()
+    ));
+}
+
+#[test]
+fn test_process_input_failed() {
+    let mut session = Session::default();
+    process_input("FROM city.csv TAKE 1\n", &mut session, &mut Vec::new());
+    assert!(!session.failed);
+    process_input(
+        "FROM city.csv ORDERBY CityName\n",
+        &mut session,
+        &mut Vec::new(),
+    );
+    assert!(session.failed);
+
+    let mut session = Session::default();
+    process_input("FRM city.csv\n", &mut session, &mut Vec::new());
+    assert!(session.failed);
+}
+
+/// Runs the `queries`, one per line, without prompting for more, as requested by `--command` or
+/// `--file`. A line ending with a backslash continues on the next one. Stops early at an `exit`
+/// command.
+///
+/// # Returns
+/// The exit code of the process: 0 if every query succeeded, or 1 otherwise.
+fn run_queries(queries: &str, session: &mut Session) -> i32 {
+    let mut pending = String::new();
+    let mut history = Vec::new();
+    for line in queries.lines() {
+        if let Some(line) = strip_continuation(line) {
+            pending.push_str(line);
+            pending.push('\n');
+            continue;
+        }
+        pending.push_str(line);
+        // parse_command expects the trailing new line read from stdin.
+        let query = std::mem::take(&mut pending);
+        if process_input(&format!("{}\n", query), session, &mut history) {
+            return i32::from(session.failed);
+        }
+    }
+    // The last line may have been continued on a line that doesn't exist.
+    if !pending.is_empty() {
+        process_input(&format!("{}\n", pending), session, &mut history);
+    }
+    i32::from(session.failed)
+}
+
+#[test]
+fn test_run_queries() {
+    assert_eq!(
+        run_queries(
+            "FROM city.csv TAKE 1\n\nFROM country.csv TAKE 1",
+            &mut Session::default()
+        ),
+        0
+    );
+    assert_eq!(
+        run_queries(
+            "FROM city.csv TAKE 1\nFRM city.csv",
+            &mut Session::default()
+        ),
+        1
+    );
+    assert_eq!(
+        run_queries("exit\nFRM city.csv", &mut Session::default()),
+        0
+    );
+}
+
+#[test]
+fn test_run_queries_continuation() {
+    assert_eq!(
+        run_queries(
+            "FROM city.csv \\\n  JOIN country.csv CountryCode \\\n  TAKE 1",
+            &mut Session::default()
+        ),
+        0
+    );
+    // Without the backslash, each line is a query of its own.
+    assert_eq!(
+        run_queries("FROM city.csv\nTAKE 1", &mut Session::default()),
+        1
+    );
+    assert_eq!(
+        run_queries("FROM city.csv TAKE \\", &mut Session::default()),
+        1
+    );
+    let mut session = Session::default();
+    run_queries("SET big = FROM city.csv \\\nTAKE 3\nexit", &mut session);
+    assert!(!session.failed);
+    process_input("FROM @big TAKE 1\n", &mut session, &mut Vec::new());
+    assert!(!session.failed);
+}
+
+#[test]
+fn test_process_input_history() {
+    let mut session = Session::default();
+    let mut history = Vec::new();
+    process_input("FROM city.csv TAKE 1\n", &mut session, &mut history);
+    process_input("\\limit 10\n", &mut session, &mut history);
+    process_input("FRM city.csv\n", &mut session, &mut history);
+    process_input("\\history\n", &mut session, &mut history);
+    process_input("\n", &mut session, &mut history);
+    assert_eq!(
+        history,
+        vec!["FROM city.csv TAKE 1", "\\limit 10", "FRM city.csv"]
+    );
+
+    let mut session = Session::default();
+    assert!(!process_input("\\rerun 1\n", &mut session, &mut history));
+    assert!(!session.failed);
+    // The query run again is appended, like a query entered at the prompt.
+    assert_eq!(history.len(), 4);
+    assert_eq!(history[3], "FROM city.csv TAKE 1");
+    process_input("\\rerun 5\n", &mut session, &mut history);
+    assert!(session.failed);
+    assert_eq!(history.len(), 4);
+
+    let mut session = Session::default();
+    let mut history = Vec::new();
+    process_input("FROM city.csv\nTAKE 1\n", &mut session, &mut history);
+    assert_eq!(history, vec!["FROM city.csv TAKE 1"]);
+}
+
+#[test]
+fn test_process_input_attach() {
+    let path = std::env::temp_dir().join("toy-query-engine-process-input-attach.sqlite");
+    let _ = std::fs::remove_file(&path);
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE planets (Planet TEXT, Moons INTEGER);
+             INSERT INTO planets VALUES ('Earth', 1), ('Mars', 2);
+             CREATE VIEW big_planets AS SELECT * FROM planets WHERE Moons > 1;",
+        )
+        .unwrap();
+    let mut session = Session::default();
+    process_input(
+        &format!("ATTACH {}\n", path.display()),
+        &mut session,
+        &mut Vec::new(),
+    );
+    assert!(!session.failed);
+    process_input(
+        "FROM planets JOIN big_planets Planet\n",
+        &mut session,
+        &mut Vec::new(),
+    );
+    assert!(!session.failed);
+
+    process_input("ATTACH data/city.csv\n", &mut session, &mut Vec::new());
+    assert!(session.failed);
+}
+
+#[test]
+fn test_process_input_load() {
+    let mut session = Session::default();
+    assert!(!process_input(
+        "LOAD cities FROM data/city.csv\n",
+        &mut session,
+        &mut Vec::new()
+    ));
+    assert!(!session.failed);
+    assert!(session.datasets.resolve("cities").is_some());
+    process_input("FROM cities TAKE 1\n", &mut session, &mut Vec::new());
+    assert!(!session.failed);
+
+    process_input(
+        "LOAD city FROM data/city.csv\n",
+        &mut session,
+        &mut Vec::new(),
+    );
+    assert!(session.failed);
+}
+
+#[test]
+fn test_process_input_set() {
+    let mut session = Session::default();
+    process_input("FROM @big\n", &mut session, &mut Vec::new());
+    assert!(session.failed);
+
+    let mut session = Session::default();
+    process_input(
+        "SET big = FROM city.csv TAKE 3\n",
+        &mut session,
+        &mut Vec::new(),
+    );
+    process_input(
+        "FROM @big JOIN country CountryCode\n",
+        &mut session,
+        &mut Vec::new(),
+    );
+    assert!(!session.failed);
+    // A failed query doesn't replace the saved result.
+    process_input(
+        "SET big = FROM city.csv ORDERBY CityName\n",
+        &mut session,
+        &mut Vec::new(),
+    );
+    assert!(session.failed);
+    process_input("FROM @big TAKE 1\n", &mut session, &mut Vec::new());
+}
+
+#[test]
+fn test_process_input_setting() {
+    let mut session = Session::default();
+    assert!(!process_input(
+        "\\limit 10\n",
+        &mut session,
+        &mut Vec::new()
+    ));
+    assert_eq!(session.settings.default_limit, Some(10));
+    assert!(!process_input(
+        "\\format csv\n",
+        &mut session,
+        &mut Vec::new()
+    ));
+    assert_eq!(
+        session.settings.output_format,
+        toy_query_engine::settings::OutputFormat::Csv
+    );
+    assert!(!process_input(
+        "\\pagesize 20\n",
+        &mut session,
+        &mut Vec::new()
+    ));
+    assert_eq!(session.settings.page_size, Some(20));
+}
+
+/// Returns a function creating a [`QueryEngine`] configured by the command line `arguments`,
+/// i.e., whose queries can call the functions of the `--script` and read the CSV files in the
+/// `--encoding`. The script is only loaded once, however many engines are created.
+///
+/// Returns
+/// The function, or an error propagated from loading the `--script`.
+fn engine_factory(arguments: &Arguments) -> Result<impl Fn() -> QueryEngine, Box<dyn Error>> {
+    let functions = match &arguments.script {
+        Some(path) => Some(ScriptFunctions::from_file(path)?),
+        None => None,
+    };
+    let encoding = arguments.encoding;
+    Ok(move || {
+        let mut engine = match &functions {
+            Some(functions) => QueryEngine::with_functions(functions.clone()),
+            None => QueryEngine::new(),
+        };
+        if let Some(encoding) = encoding {
+            engine.set_encoding(encoding);
+        }
+        engine
+    })
+}
+
+/// Runs the server requested with `--serve` or `--listen`, until the process is stopped:
+/// - `--serve`: Answers the queries sent to `POST /query`, all run on a single [`QueryEngine`].
+/// - `--listen`: Accepts REPL sessions, each run on its own [`QueryEngine`].
+///
+/// # Arguments
+/// `address` : The address to listen on.
+/// `arguments` : The command line arguments, which configure the engines.
+///
+/// # Returns
+/// The exit code: 2 if the script can't be loaded or the address can't be listened on.
+fn run_server(address: &str, arguments: &Arguments) -> i32 {
+    let new_engine = match engine_factory(arguments) {
+        Ok(new_engine) => new_engine,
+        Err(e) => {
+            println!("Failed to load the script: {}", e);
+            return 2;
+        }
+    };
+    let listener = match TcpListener::bind(address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("Failed to listen on {}: {}", address, e);
+            return 2;
+        }
+    };
+    match arguments.listen.is_some() {
+        true => {
+            println!("Accepting sessions on {}", address);
+            serve_sessions(listener, new_engine);
+        }
+        false => {
+            println!("Listening on http://{}/query", address);
+            serve(listener, new_engine(), arguments.allow_origins.clone());
+        }
+    }
+    0
 }
 
 fn main() {
-    println!("Toy Query Engine v0.1");
-    println!("Enter your query, or 'help' for more information or 'exit' to exit.");
+    let arguments = match parse_arguments(std::env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(e) => {
+            println!("{}\n{}", e, C_USAGE_MESSAGE);
+            std::process::exit(2)
+        }
+    };
+    if let Some(address) = arguments.serve.as_ref().or(arguments.listen.as_ref()) {
+        std::process::exit(run_server(address, &arguments));
+    }
+    let queries = match (&arguments.command, &arguments.file) {
+        (Some(query), _) => Some(query.clone()),
+        (None, Some(path)) => match std::fs::read_to_string(path) {
+            Ok(queries) => Some(queries),
+            Err(e) => {
+                println!("Failed to read the queries from {}: {}", path, e);
+                std::process::exit(2)
+            }
+        },
+        (None, None) => None,
+    };
+    let mut session = match Session::new(arguments) {
+        Ok(session) => session,
+        Err(e) => {
+            println!("Failed to load the script: {}", e);
+            std::process::exit(2)
+        }
+    };
+    session.color = render::color_enabled(
+        std::io::stdout().is_terminal(),
+        std::env::var_os("NO_COLOR").as_deref(),
+    );
+    if let Some(queries) = queries {
+        std::process::exit(run_queries(&queries, &mut session));
+    }
+    session.interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    // List the candidates when a word can be completed in several ways, like a shell does.
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor: Editor<ReplHelper, DefaultHistory> = match Editor::with_config(config) {
+        Ok(editor) => editor,
+        Err(e) => {
+            println!("Failed to initialize the line editor: {}", e);
+            std::process::exit(2)
+        }
+    };
+    if session.interactive {
+        // While a line is edited, Ctrl-C is a key handled by the editor; while a query runs, it
+        // cancels the query instead of killing the process, and the prompt comes back. The
+        // handler replaces the one the editor installs when it's created, so it comes after.
+        let cancelled = session.context.cancelled.clone();
+        if let Err(e) =
+            ctrlc::set_handler(move || cancelled.store(true, std::sync::atomic::Ordering::Relaxed))
+        {
+            println!("Failed to handle Ctrl-C: {}", e);
+        }
+    }
+    editor.set_helper(Some(ReplHelper {
+        datasets: session.datasets.clone(),
+    }));
+    // Only what the user types is worth recalling, not queries piped in from a file.
+    let history = history_path().filter(|_| std::io::stdin().is_terminal());
+    if let Some(path) = &history {
+        // The file doesn't exist until the first session ends, so a failure here isn't an error.
+        let _ = editor.load_history(path);
+    }
+    // Queries piped in, e.g. `echo "FROM city.csv TAKE 3" | toy-query-engine`, only print their
+    // results, so the output can be piped to another tool.
+    session.quiet |= !std::io::stdin().is_terminal();
+    if !session.quiet {
+        println!("Toy Query Engine v0.1");
+        println!("Enter your query, or 'help' for more information or 'exit' to exit.");
+    }
+    // The queries of the session, for \history and \rerun. Unlike the editor's history, it doesn't
+    // include those of earlier sessions.
+    let mut queries = Vec::new();
+    // The lines of a query continued on the next line, entered so far.
+    let mut pending = String::new();
     loop {
-        let mut input = String::new();
-        if let Err(e) = std::io::stdin().read_line(&mut input) {
-            print_error_message(&e.to_string());
+        // The editor prints the prompt and flushes it before waiting for the line.
+        let prompt = if pending.is_empty() {
+            session.settings.prompt.clone()
+        } else {
+            session.settings.continuation_prompt()
+        };
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            // Ctrl-C discards the line being edited, and the lines of the query continued on it.
+            Err(ReadlineError::Interrupted) => {
+                pending.clear();
+                continue;
+            }
+            // Ctrl-D runs the query continued on the line first, like an empty line does.
+            Err(ReadlineError::Eof) if !pending.is_empty() => String::new(),
+            // Ctrl-D, or the end of piped input, exits like the 'exit' command.
+            Err(ReadlineError::Eof) => "exit".to_string(),
+            Err(e) => {
+                print_error_message(&e.to_string(), session.color);
+                continue;
+            }
+        };
+        if let Some(line) = strip_continuation(&line) {
+            pending.push_str(line);
+            pending.push('\n');
+            continue;
+        }
+        let submitted = line.trim().is_empty();
+        pending.push_str(&line);
+        // In the terminal, a query cut short, e.g. after a keyword missing its arguments, continues
+        // on the next line too, until it's complete or an empty line is entered.
+        if session.interactive && !submitted && is_incomplete(&pending, &session.datasets) {
+            pending.push('\n');
             continue;
         }
-        let should_exit = process_input(&input);
+        let input = std::mem::take(&mut pending);
+        if !input.trim().is_empty() {
+            // The query is recalled on a single line, to be edited as a whole.
+            let _ = editor.add_history_entry(input.replace('\n', " ").trim());
+        }
+        // The terminal may have been resized since the last query.
+        session.context.terminal_width =
+            terminal_size::terminal_size().map(|(Width(width), _)| width as usize);
+        // A Ctrl-C pressed since the last query doesn't cancel this one.
+        session
+            .context
+            .cancelled
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        // parse_command expects the trailing new line, which the editor strips.
+        let should_exit = process_input(&format!("{}\n", input), &mut session, &mut queries);
+        // LOAD and ATTACH may have registered datasets whose names can now be completed.
+        if let Some(helper) = editor.helper_mut() {
+            helper.datasets = session.datasets.clone();
+        }
         if should_exit {
+            if let Some(path) = &history {
+                if let Err(e) = editor.save_history(path) {
+                    println!("Failed to save the history to {}: {}", path.display(), e);
+                }
+            }
             std::process::exit(0)
         }
     }
@@ -1,10 +1,34 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fmt::Display;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::vec;
 
-use crate::data::{load_cities, load_countries, load_languages, City, Country, Dataset, Language};
-use crate::table::{Cell, Row, Table};
+use encoding_rs::{Encoding, UTF_8};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::aggregate::{Aggregate, AggregateFunction, ALL_ROWS};
+use crate::cache::{CachedResult, ResultCache};
+use crate::data::{
+    fetch_url, is_parquet_file, load_csv_with_options, load_parquet, load_sqlite, parse_decimal,
+    BadRows, BadRowsReport, Dataset, DatasetCache, Normalization,
+};
+#[cfg(test)]
+use crate::expression::parse_predicate;
+use crate::expression::{
+    describe_regex_error, format_constant, Expression, Predicate, SelectColumn,
+};
+use crate::pipeline::{RowSource, RowStream};
+use crate::profile::OperatorProfile;
+use crate::script::{RowTransform, ScriptFunctions};
+use crate::table::{format_bytes, Cell, ColumnSchema, DataType, Row, Table};
 
 /// Operations supported by this tool.
 /// These are constructed by parsing the user input on the toy-query-engine command line.
@@ -20,8 +44,27 @@ pub enum Operator {
         ///  Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
         /// operator.
         chain: Box<Operator>,
-        /// The name of the column to select from the input [`Table`].
-        column_names: Vec<String>,
+        /// The expressions computing the columns to select from the input [`Table`], each
+        /// optionally renamed. Usually these are just the names of columns.
+        columns: Vec<SelectColumn>,
+    },
+    /// Selects all the columns from the [`Table`] produced by the chained operator (`SELECT *`),
+    /// except the listed ones, if any (`SELECT * EXCEPT Capital`).
+    SelectAll {
+        ///  Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The names of the columns to drop. Empty to keep all of them.
+        except: Vec<String>,
+    },
+    /// Appends columns computed from the [`Table`] produced by the chained operator, keeping all of
+    /// its columns rather than replacing them like [`Operator::Select`] does.
+    Extend {
+        ///  Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The names of the appended columns, and the expressions computing their values.
+        columns: Vec<(String, Expression)>,
     },
     /// Returns the first 'count' number of rows from the [`Table`] produced by the chained
     /// operator.
@@ -32,15 +75,48 @@ pub enum Operator {
         /// The number of rows from the input [`Table`] to return.
         count: usize,
     },
-    /// Sorts the dataset in descending order by the specified column.
+    /// Drops the first 'count' number of rows from the [`Table`] produced by the chained operator,
+    /// returning the rest. Combined with [`Operator::Take`] to page through results.
+    Skip {
+        ///  Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The number of rows from the input [`Table`] to drop.
+        count: usize,
+    },
+    /// Returns the last 'count' number of rows from the [`Table`] produced by the chained
+    /// operator.
+    Tail {
+        ///  Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The number of rows from the end of the input [`Table`] to return.
+        count: usize,
+    },
+    /// Returns a uniform random subset of the rows of the [`Table`] produced by the chained
+    /// operator, in their original order.
+    Sample {
+        ///  Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// How many rows to return.
+        size: SampleSize,
+        /// The seed of the random number generator picking the rows, to pick the same rows every
+        /// time. A different subset is picked every time when `None`.
+        seed: Option<u64>,
+    },
+    /// Sorts the dataset by the specified column, in descending order unless ASC is specified.
     /// The column must contain numeric values
     OrderBy {
         ///  Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
         /// operator.
         chain: Box<Operator>,
-        /// The name of the column to reverse sort (i.e., in descending order) the input [`Table`]
-        /// by.
+        /// The name of the column to sort the input [`Table`] by.
         column: String,
+        /// Whether to sort in ascending or descending order.
+        order: SortOrder,
+        /// Whether the rows with a missing value come first or last.
+        nulls: NullsOrder,
     },
     /// Returns a histogram from the dataset for the selected column.
     CountBy {
@@ -49,855 +125,8559 @@ pub enum Operator {
         chain: Box<Operator>,
         /// The name of the column to produce the histogram for.
         column: String,
+        /// How the rows of the histogram are sorted.
+        order: CountByOrder,
+        /// Whether a `bar` column is added, drawing each count as a bar of `#`s.
+        bars: bool,
+        /// The condition the rows of the histogram must meet to be kept, e.g. `count > 10`.
+        having: Option<Predicate>,
+    },
+    /// Combines all the values of a column of the [`Table`] produced by the chained operator into
+    /// a single row, e.g. their sum.
+    Aggregate {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// How the values are combined.
+        function: AggregateFunction,
+        /// The name of the column whose values are combined.
+        column: String,
+    },
+    /// Groups the rows of the dataset by the values of the specified column and combines the
+    /// values of other columns within each group, producing one row per group.
+    GroupBy {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The name of the column to group the rows by.
+        column: String,
+        /// The aggregates to compute for each group.
+        aggregates: Vec<Aggregate>,
+        /// The condition the groups must meet to be kept, e.g. `SUM(CityPop) > 1000000`.
+        having: Option<Predicate>,
+    },
+    /// Cross-tabulates the [`Table`] produced by the chained operator: produces one row per value
+    /// of the `row` column and one column per value of the `column` column, holding the
+    /// `aggregate` of the rows that share both values.
+    Pivot {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The name of the column whose values become the rows.
+        row: String,
+        /// The name of the column whose values become the columns.
+        column: String,
+        /// The aggregate computed for each pair of values.
+        aggregate: Aggregate,
+    },
+    /// Removes duplicate rows from the dataset, keeping the first occurrence of each. If `columns`
+    /// are given, rows are duplicates when their values in those columns are equal.
+    Distinct {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The names of the columns to compare rows on. Empty to compare entire rows.
+        columns: Vec<String>,
+    },
+    /// Renames a column of the [`Table`] produced by the chained operator, e.g. to tell apart the
+    /// columns of a dataset joined with itself.
+    Rename {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The name of the column to rename.
+        column: String,
+        /// The name the column is given.
+        new_name: String,
+    },
+    /// Moves columns of the [`Table`] produced by the chained operator to the front, in the given
+    /// order, keeping the other columns after them in their original order, e.g. to lay out a
+    /// result for export.
+    Reorder {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The names of the columns to move to the front, in order.
+        columns: Vec<String>,
+    },
+    /// Converts the values of a column of the [`Table`] produced by the chained operator to
+    /// another type, e.g. a string column of digits to integers so that it can be sorted on.
+    Cast {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The name of the column to convert.
+        column: String,
+        /// The type its values are converted to.
+        to: CastType,
+    },
+    /// Replaces the missing values of a column of the [`Table`] produced by the chained operator
+    /// with a default value, e.g. so that a column of integers with missing values can be sorted
+    /// on.
+    FillNa {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The name of the column whose missing values are replaced.
+        column: String,
+        /// The value replacing them.
+        value: Cell,
+    },
+    /// Keeps the `count` rows with the largest values of a column within each group of rows that
+    /// share a value of another column, e.g. the 3 most populous cities of each country.
+    TopN {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The number of rows kept from each group.
+        count: usize,
+        /// The name of the column to group the rows by.
+        group: String,
+        /// The name of the numeric column the rows of each group are ranked by.
+        column: String,
+        /// Whether the rows with the largest values (descending) or the smallest values
+        /// (ascending) are kept.
+        order: SortOrder,
+    },
+    /// Adds a `rank` column to the [`Table`] produced by the chained operator, ranking each row by
+    /// a numeric column from the largest value down, optionally within the groups of rows that
+    /// share a value of another column.
+    Rank {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The name of the numeric column the rows are ranked by.
+        column: String,
+        /// The name of the column whose values partition the rows into groups ranked separately,
+        /// or `None` to rank all of the rows together.
+        partition: Option<String>,
+    },
+    /// Writes the dataset to a file as CSV or Arrow. Must be the last operator of a query.
+    Into {
+        /// Chain of [`Operator`]s that must be executed to produce the [`Table`] to write.
+        chain: Box<Operator>,
+        /// The path of the file to write. Overwritten if it already exists.
+        path: String,
+        /// The format the file is written in.
+        format: ExportFormat,
     },
     /// Peforms a Merge of the chained and right data sets on the specified column.
     Join {
         /// Chain of [`Operator`]s that must be executed to produce the `left` [`Table`] for this
         /// operator.
         chain: Box<Operator>,
-        /// The [`Dataset`] to load as the `right` [`Table`] for the join.
-        right: Dataset,
-        /// The name of the column to join the `left` and `right` tables on.
+        /// The [`Operator`] chain producing the `right` [`Table`] for the join: a FROM of the
+        /// [`Dataset`] to join with, or a parenthesized sub-query.
+        right: Box<Operator>,
+        /// The name of the column to join the `left` and `right` tables on. Unless `right_column`
+        /// is given, it must be in both tables.
         column: String,
+        /// The name of the column of the `right` table to join on, if it differs from `column`,
+        /// i.e., `ON <column>=<right_column>`. Both columns are kept in the result; otherwise the
+        /// `right` table's copy of `column` is dropped.
+        right_column: Option<String>,
+        /// Which rows without a match in the other table are kept.
+        kind: JoinKind,
+        /// The algorithm to match the rows with, if picked with `USING` or by the
+        /// [`crate::optimizer`]. [`JoinStrategy::Hash`] is used otherwise.
+        strategy: Option<JoinStrategy>,
+    },
+    /// Appends the rows of the `right` [`Table`] to the rows of the [`Table`] produced by the
+    /// chained operator, removing the duplicate rows unless `all` is set. The columns of the
+    /// tables are matched by their position, and keep the names of the chained operator's.
+    Union {
+        /// Chain of [`Operator`]s that must be executed to produce the first rows for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The [`Operator`] chain producing the rows appended to them.
+        right: Box<Operator>,
+        /// Whether the duplicate rows are kept, i.e., `UNION ALL`.
+        all: bool,
+    },
+    /// Keeps only the rows of the [`Table`] produced by the chained operator that satisfy the
+    /// `predicate`.
+    Filter {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The condition a row must satisfy to be kept.
+        predicate: Predicate,
+    },
+    /// Runs the `query` after processing the named sub-queries (common table expressions) in
+    /// `ctes`, so FROM and JOIN in the `query` can refer to their results as [`Dataset::Cte`].
+    With {
+        /// The names and definitions of the sub-queries, in the order they were defined. Each
+        /// definition may refer to the sub-queries defined before it.
+        ctes: Vec<(String, Operator)>,
+        /// The query to run.
+        query: Box<Operator>,
+    },
+    /// Transforms every row of the [`Table`] produced by the chained operator by running a
+    /// user-provided script on it. See [`RowTransform`].
+    Map {
+        /// Chain of [`Operator`]s that must be executed to produce the input [`Table`] for this
+        /// operator.
+        chain: Box<Operator>,
+        /// The path of the rhai script to run on each row.
+        script: String,
     },
 }
 
-impl Display for Operator {
+/// The order the [`Operator::OrderBy`] operator sorts its input in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SortOrder {
+    /// Smallest value first. Selected with `ASC`.
+    Ascending,
+    /// Largest value first. Selected with `DESC`, or by not specifying an order.
+    #[default]
+    Descending,
+}
+
+/// Where the [`Operator::OrderBy`] operator places the rows with a missing value in the column it
+/// sorts by, whatever the [`SortOrder`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NullsOrder {
+    /// Before the other rows. Selected with `NULLS FIRST`.
+    First,
+    /// After the other rows. Selected with `NULLS LAST`, or by not specifying where.
+    #[default]
+    Last,
+}
+
+/// How the [`Operator::CountBy`] operator sorts the histogram it produces.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CountByOrder {
+    /// Most frequent value first. Values that appear equally often are listed in the order they
+    /// first appear in the input. Selected with `ORDER count`, or by not specifying an order.
+    #[default]
+    Count,
+    /// Smallest value first, as sorted by [`Cell::cmp_values`]. Selected with `ORDER value`.
+    Value,
+}
+
+impl CountByOrder {
+    /// Looks up an order by the name used to select it after `ORDER`, e.g. "count". The name is
+    /// case-insensitive.
+    pub fn from_name(name: &str) -> Option<CountByOrder> {
+        match name.to_ascii_lowercase().as_str() {
+            "count" => Some(CountByOrder::Count),
+            "value" => Some(CountByOrder::Value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for CountByOrder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Operator::From(dataset) => f.write_fmt(format_args!("FROM {}", dataset)),
-            Operator::Select {
-                chain,
-                column_names,
-            } => f.write_fmt(format_args!("{} SELECT {}", *chain, column_names.join(","))),
-            Operator::Take { chain, count } => {
-                f.write_fmt(format_args!("{} TAKE {}", *chain, count))
-            }
-            Operator::OrderBy { chain, column } => {
-                f.write_fmt(format_args!("{} ORDERBY {}", *chain, column))
-            }
-            Operator::CountBy { chain, column } => {
-                f.write_fmt(format_args!("{} COUNTBY {}", *chain, column))
-            }
-            Operator::Join {
-                chain,
-                right,
-                column,
-            } => f.write_fmt(format_args!("{} JOIN {} {}", *chain, right, column)),
+            CountByOrder::Count => f.write_str("count"),
+            CountByOrder::Value => f.write_str("value"),
         }
     }
 }
 
-/// The set of errors that can be returned when processing the [`Operator`]s.
-/// This is primarily used to display an error message when processing fails.
-#[derive(Debug)]
-pub enum OperatorError {
-    /// Encountered an error while trying to load the dataset from disk while processing the FROM
-    /// or JOIN operators.
-    CSVError {
-        /// The name of the dataset that was passed to the FROM command.
-        dataset: Dataset,
-        /// The error returned from the [`serde`] or [`csv`] crates.
-        error: Box<dyn Error>,
-        /// The operator that was being processed when this error occurred.
-        operator: String,
-    },
-    /// The `column_name` provided to the `operator` does not exist in its input [`Table`].
-    NoSuchColumn {
-        /// The operator that was being processed when this error was thrown
-        operator: String,
-        /// The operator chain where this error was thrown.
-        chain: Box<Operator>,
-        /// Name of the column that was specified as an argument to the operator.
-        column_name: String,
-    },
-    /// Indicates that the `column_name` passed to the ORDERBY command is illegal as its values are
-    /// non-numeric.
-    OrderByColumnNotNumeric {
-        /// Name of the column that was specified as an argument to the ORDERBY command.
-        column_name: String,
-    },
+#[test]
+fn test_countby_order_from_name() {
+    assert_eq!(CountByOrder::from_name("count"), Some(CountByOrder::Count));
+    assert_eq!(CountByOrder::from_name("VALUE"), Some(CountByOrder::Value));
+    assert_eq!(CountByOrder::from_name("name"), None);
 }
 
-impl Display for OperatorError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            OperatorError::CSVError {
-                dataset,
-                error,
-                operator,
-            } => f.write_fmt(format_args!(
-                "Failed to load the {} dataset while processing the {} command. Error encountered: {}",
-                dataset, operator, error
-            )),
-            OperatorError::NoSuchColumn {
-                operator,
-                chain,
-                column_name,
-            } => f.write_fmt(format_args!(
-                "Could not find the {} column to {} on the table produced by this operator chain: {}",
-                column_name, operator, chain,
-            )),
-            OperatorError::OrderByColumnNotNumeric { column_name } => f.write_fmt(format_args!(
-                "You attempted to ORDERBY the {} column whose type is not numeric.",
-                column_name
-            )),
+/// The types the [`Operator::Cast`] operator converts values to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CastType {
+    /// Integers, i.e., [`Cell::Int64`]. Decimal numbers are truncated towards zero. Selected with
+    /// `INT`.
+    Int,
+    /// Decimal numbers, i.e., [`Cell::Float64`]. Selected with `FLOAT`.
+    Float,
+    /// Strings, i.e., [`Cell::String`], as the values are printed. Selected with `STRING`.
+    String,
+}
+
+impl CastType {
+    /// Looks up a type by the name used to select it after `AS`, e.g. "INT". The name is
+    /// case-insensitive.
+    pub fn from_name(name: &str) -> Option<CastType> {
+        match name.to_ascii_uppercase().as_str() {
+            "INT" => Some(CastType::Int),
+            "FLOAT" => Some(CastType::Float),
+            "STRING" => Some(CastType::String),
+            _ => None,
         }
     }
-}
 
-/// Common helper function to load the requested [`Dataset`] from disk.
-///
-/// # Arguments:
-/// `dataset`: the [`Dataset`] to be laoded.
-/// `operator`: the name of the operator that called this function. Used for error reporting.
-///
-/// # Returns:
-/// On success: The loaded dataset as a [`Table`].
-/// On failure: [`OperatorError::CSVError`] or other [`OperatorError`] from processing the
-/// chained operators.
-fn load_dataset(dataset: &Dataset, operator: &str) -> Result<Table, OperatorError> {
-    match dataset {
-        Dataset::City => match load_cities() {
-            Ok(cities) => Ok(Table {
-                header: City::column_names(),
-                rows: cities
-                    .into_iter()
-                    .map(|city| -> Row { city.into() })
-                    .collect(),
-                numeric_columns: City::numeric_columns(),
-            }),
-            Err(e) => Err(OperatorError::CSVError {
-                dataset: dataset.clone(),
-                error: e,
-                operator: operator.to_string(),
-            }),
-        },
-        Dataset::Country => match load_countries() {
-            Ok(countries) => Ok(Table {
-                header: Country::column_names(),
-                rows: countries
-                    .into_iter()
-                    .map(|country| -> Row { country.into() })
-                    .collect(),
-                numeric_columns: Country::numeric_columns(),
-            }),
-            Err(e) => Err(OperatorError::CSVError {
-                dataset: dataset.clone(),
-                error: e,
-                operator: operator.to_string(),
-            }),
-        },
-        Dataset::Language => match load_languages() {
-            Ok(languages) => Ok(Table {
-                header: Language::column_names(),
-                rows: languages
-                    .into_iter()
-                    .map(|language| -> Row { language.into() })
-                    .collect(),
-                numeric_columns: Language::numeric_columns(),
-            }),
-            Err(e) => Err(OperatorError::CSVError {
-                dataset: dataset.clone(),
-                error: e,
-                operator: operator.to_string(),
-            }),
-        },
+    /// Converts a `value` to this type. Missing values stay missing.
+    ///
+    /// # Returns
+    /// The converted value, or `None` if the `value` can't be converted, e.g. the string `abc` to
+    /// an integer.
+    pub fn convert(&self, value: &Cell) -> Option<Cell> {
+        match (self, value) {
+            (_, Cell::OptInt64(None) | Cell::Null) => Some(Cell::Null),
+            (CastType::Int, Cell::Int64(val) | Cell::OptInt64(Some(val))) => {
+                Some(Cell::Int64(*val))
+            }
+            // Decimal numbers out of the range of integers can't be converted.
+            (CastType::Int, Cell::Float64(val)) => {
+                let val = val.trunc();
+                (val >= i64::MIN as f64 && val < i64::MAX as f64).then_some(Cell::Int64(val as i64))
+            }
+            (CastType::Int, Cell::String(val)) => val.trim().parse::<i64>().ok().map(Cell::Int64),
+            (CastType::Float, Cell::String(val)) => parse_decimal(val.trim()).map(Cell::Float64),
+            (CastType::Float, value) => value.as_f64().map(Cell::Float64),
+            (CastType::String, value) => Some(Cell::String(value.to_string())),
+        }
     }
 }
 
-/// Handles the [`Operator::From`] operator by loading the requested [`Dataset`] from disk.
-/// This is just a shim around the [`load_dataset`] function.
-///
-/// # Arguments:
-/// `dataset`: the [`Dataset`] to be laoded.
-///
-/// # Returns:
-/// On success: The loaded dataset as a [`Table`].
-/// On failure: [`OperatorError::CSVError`] or other [`OperatorError`] from processing the
-/// chained operators.
-fn process_from(dataset: &Dataset) -> Result<Table, OperatorError> {
-    load_dataset(dataset, "FROM")
+impl Display for CastType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CastType::Int => f.write_str("INT"),
+            CastType::Float => f.write_str("FLOAT"),
+            CastType::String => f.write_str("STRING"),
+        }
+    }
 }
 
 #[test]
-fn test_process_from_city() {
-    let result = process_from(&Dataset::City);
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 4079);
-    assert_eq!(result.rows[0].cells.len(), 4);
+fn test_cast_type_convert() {
+    assert_eq!(CastType::from_name("int"), Some(CastType::Int));
+    assert_eq!(CastType::from_name("STRING"), Some(CastType::String));
+    assert_eq!(CastType::from_name("DATE"), None);
+    let string = |value: &str| Cell::String(value.to_string());
+    assert_eq!(
+        CastType::Int.convert(&string(" 042 ")),
+        Some(Cell::Int64(42))
+    );
+    assert_eq!(CastType::Int.convert(&string("4.2")), None);
+    assert_eq!(CastType::Int.convert(&string("abc")), None);
+    assert_eq!(
+        CastType::Int.convert(&Cell::Float64(-4.7)),
+        Some(Cell::Int64(-4))
+    );
+    assert_eq!(CastType::Int.convert(&Cell::Float64(1e20)), None);
+    assert_eq!(
+        CastType::Int.convert(&Cell::OptInt64(Some(7))),
+        Some(Cell::Int64(7))
+    );
+    assert_eq!(
+        CastType::Float.convert(&string("4.25")),
+        Some(Cell::Float64(4.25))
+    );
+    assert_eq!(CastType::Float.convert(&string("NaN")), None);
+    assert_eq!(
+        CastType::Float.convert(&Cell::Int64(3)),
+        Some(Cell::Float64(3.0))
+    );
+    assert_eq!(CastType::String.convert(&Cell::Int64(3)), Some(string("3")));
+    assert_eq!(
+        CastType::String.convert(&Cell::Float64(0.5)),
+        Some(string("0.5"))
+    );
+    for to in [CastType::Int, CastType::Float, CastType::String] {
+        assert_eq!(to.convert(&Cell::OptInt64(None)), Some(Cell::Null));
+        assert_eq!(to.convert(&Cell::Null), Some(Cell::Null));
+    }
 }
 
-#[test]
-fn test_process_from_country() {
-    let result = process_from(&Dataset::Country);
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 239);
-    assert_eq!(result.rows[0].cells.len(), 5);
+impl Display for JoinKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.keyword())
+    }
 }
 
-#[test]
-fn test_process_from_language() {
-    let result = process_from(&Dataset::Language);
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 984);
-    assert_eq!(result.rows[0].cells.len(), 2);
+/// The algorithms [`Operator::Join`] can use to find the matching rows of its tables. Both find
+/// the same matches; they differ in how fast they find them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum JoinStrategy {
+    /// Index the rows of the smaller table by their join column and look up the rows of the other
+    /// one. Suits tables in any order. Selected with `USING HASH`.
+    #[default]
+    Hash,
+    /// Sort the rows of both tables by their join column and walk through them side by side.
+    /// Suits tables already sorted on the join column, e.g. by ORDERBY, which are cheap to sort
+    /// again. Selected with `USING MERGE`.
+    Merge,
 }
 
-/// Helper function to find the index that corresponds to the first occurrence of 'name' in `table`.
-///
-/// # Arguments:
-/// 'table' : The table to find the column in.
-/// 'name' : The name of the column whose index is to be returned.
-/// 'chain' : The chain on operators that produced this table (used to construct the error message
-/// if the column doesn't exist in the table).
-/// 'current_operator': The operator calling this function.
-///
-/// # Returns:
-/// Ok([`usize`]) for the index of the first occurrence of `name` in the `table`.
-/// Err([`OperatorError::NoSuchColumn`]) if `name` is not found in the `header` field.
-fn find_column_index(
-    table: &Table,
-    name: &str,
-    chain: &Box<Operator>,
-    current_operator: &str,
-) -> Result<usize, OperatorError> {
-    match table.find_column_index_by_name(name) {
-        Some(index) => Ok(index),
-        None => {
-            // The requested column doesn't exist in the table.
-            Err(OperatorError::NoSuchColumn {
-                operator: current_operator.to_string(),
-                chain: chain.clone(),
-                column_name: name.to_string(),
-            })
+impl JoinStrategy {
+    /// Returns the keyword following `USING` to pick this strategy.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            JoinStrategy::Hash => "HASH",
+            JoinStrategy::Merge => "MERGE",
         }
     }
-}
-
-/// Test find_column_index for names that do exist in the table.
-#[test]
-fn test_find_column_index_exists() {
-    let table = Table {
-        header: vec![
-            "H1".to_string(),
-            "H2".to_string(),
-            "H3".to_string(),
-            "H4".to_string(),
-        ],
-        numeric_columns: vec![],
-        rows: vec![],
-    };
 
-    let operator = Box::new(Operator::From(Dataset::Language));
-    assert!(find_column_index(&table, "H1", &operator, "TEST").is_ok());
-    assert!(find_column_index(&table, "H2", &operator, "TEST").is_ok());
-    assert!(find_column_index(&table, "H3", &operator, "TEST").is_ok());
-    assert!(find_column_index(&table, "H4", &operator, "TEST").is_ok());
+    /// Looks up a strategy by the keyword following `USING`, e.g. "MERGE".
+    pub fn from_keyword(keyword: &str) -> Option<JoinStrategy> {
+        [JoinStrategy::Hash, JoinStrategy::Merge]
+            .into_iter()
+            .find(|strategy| strategy.keyword() == keyword)
+    }
 }
 
-/// Test find_column_index_by_name for names that do not exist in the table.
-#[test]
-fn test_find_column_index_does_not_exist() {
-    let table = Table {
-        header: vec![
-            "H1".to_string(),
-            "H2".to_string(),
-            "H3".to_string(),
-            "H4".to_string(),
-        ],
-        numeric_columns: vec![],
-        rows: vec![],
-    };
-    let operator = Box::new(Operator::From(Dataset::Language));
-    assert!(find_column_index(&table, "H", &operator, "TEST").is_err());
-    assert!(find_column_index(&table, "H12", &operator, "TEST").is_err());
-    assert!(find_column_index(&table, "H31", &operator, "TEST").is_err());
-    assert!(find_column_index(&table, "H42", &operator, "TEST").is_err());
+impl Display for JoinStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.keyword())
+    }
 }
 
-/// Test find_column_index_by_name for names that do not exist in the table.
-#[test]
-fn test_find_column_index_empty_table() {
-    let table = Table {
-        header: vec![],
-        numeric_columns: vec![],
-        rows: vec![],
-    };
-    let operator = Box::new(Operator::From(Dataset::Language));
-    assert!(find_column_index(&table, "H", &operator, "TEST").is_err());
-    assert!(find_column_index(&table, "H12", &operator, "TEST").is_err());
-    assert!(find_column_index(&table, "H31", &operator, "TEST").is_err());
-    assert!(find_column_index(&table, "H42", &operator, "TEST").is_err());
+/// The file formats [`Operator::Into`] can write.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ExportFormat {
+    /// Comma-separated values, with the header on the first line. Written by `INTO <path>`,
+    /// `EXPORT <path>`, or `EXPORT CSV <path>`.
+    #[default]
+    Csv,
+    /// An Arrow IPC file, also known as Feather v2, which keeps the types of the columns. Written
+    /// by `EXPORT ARROW <path>`.
+    Arrow,
 }
 
-/// Handles the [`Operator::Select`] operator by processing the [`Operator`] chain and selecting the
+impl ExportFormat {
+    /// Returns the keyword following `EXPORT` to pick this format.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Arrow => "ARROW",
+        }
+    }
+
+    /// Looks up a format by the keyword following `EXPORT`, e.g. "ARROW".
+    pub fn from_keyword(keyword: &str) -> Option<ExportFormat> {
+        [ExportFormat::Csv, ExportFormat::Arrow]
+            .into_iter()
+            .find(|format| format.keyword() == keyword)
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.keyword())
+    }
+}
+
+/// How many rows the [`Operator::Sample`] operator returns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    /// A number of rows, e.g. `SAMPLE 10`. All the rows are returned if there are fewer.
+    Rows(usize),
+    /// A percentage of the rows, between 0 and 100, e.g. `SAMPLE 5%`. The number of rows is
+    /// rounded to the nearest whole number.
+    Percent(f64),
+}
+
+impl SampleSize {
+    /// Parses the argument of SAMPLE: a number of rows, or a percentage ending with `%`.
+    ///
+    /// # Returns
+    /// The size, or `None` if `text` is neither a number nor a percentage between 0 and 100.
+    pub fn parse(text: &str) -> Option<SampleSize> {
+        match text.strip_suffix('%') {
+            Some(percent) => str::parse::<f64>(percent)
+                .ok()
+                .filter(|percent| (0.0..=100.0).contains(percent))
+                .map(SampleSize::Percent),
+            None => str::parse::<usize>(text).ok().map(SampleSize::Rows),
+        }
+    }
+
+    /// Returns the number of rows to sample out of `total` rows.
+    pub fn rows_of(&self, total: usize) -> usize {
+        match self {
+            SampleSize::Rows(count) => (*count).min(total),
+            SampleSize::Percent(percent) => (total as f64 * percent / 100.0).round() as usize,
+        }
+    }
+}
+
+impl Display for SampleSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SampleSize::Rows(count) => f.write_fmt(format_args!("{}", count)),
+            SampleSize::Percent(percent) => f.write_fmt(format_args!("{}%", percent)),
+        }
+    }
+}
+
+#[test]
+fn test_sample_size() {
+    assert_eq!(SampleSize::parse("10"), Some(SampleSize::Rows(10)));
+    assert_eq!(SampleSize::parse("2.5%"), Some(SampleSize::Percent(2.5)));
+    assert_eq!(SampleSize::parse("100%"), Some(SampleSize::Percent(100.0)));
+    for malformed in ["-1", "1.5", "ten", "101%", "-5%", "%", "NaN%"] {
+        assert_eq!(SampleSize::parse(malformed), None, "{}", malformed);
+    }
+    assert_eq!(SampleSize::Rows(10).rows_of(4), 4);
+    assert_eq!(SampleSize::Rows(10).rows_of(4079), 10);
+    assert_eq!(SampleSize::Percent(10.0).rows_of(4079), 408);
+    assert_eq!(SampleSize::Percent(0.0).rows_of(4079), 0);
+    assert_eq!(SampleSize::Percent(100.0).rows_of(4079), 4079);
+    assert_eq!(SampleSize::Percent(2.5).to_string(), "2.5%");
+    assert_eq!(SampleSize::Rows(10).to_string(), "10");
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortOrder::Ascending => f.write_str("ASC"),
+            SortOrder::Descending => f.write_str("DESC"),
+        }
+    }
+}
+
+impl Display for NullsOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NullsOrder::First => f.write_str("NULLS FIRST"),
+            NullsOrder::Last => f.write_str("NULLS LAST"),
+        }
+    }
+}
+
+/// The kinds of [`Operator::Join`], which differ in what happens to the rows of one table that
+/// don't match any row of the other table.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum JoinKind {
+    /// Only rows with a match in both tables are kept. Selected with `JOIN`.
+    #[default]
+    Inner,
+    /// Unmatched rows of the `left` table are kept. Selected with `LEFTJOIN`.
+    Left,
+    /// Unmatched rows of the `right` table are kept. Selected with `RIGHTJOIN`.
+    Right,
+    /// Unmatched rows of both tables are kept. Selected with `OUTERJOIN`.
+    Full,
+}
+
+impl JoinKind {
+    /// Returns the keyword used to invoke this kind of join.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "JOIN",
+            JoinKind::Left => "LEFTJOIN",
+            JoinKind::Right => "RIGHTJOIN",
+            JoinKind::Full => "OUTERJOIN",
+        }
+    }
+
+    /// Looks up a kind of join by the keyword used to invoke it, e.g. "LEFTJOIN".
+    pub fn from_keyword(keyword: &str) -> Option<JoinKind> {
+        [
+            JoinKind::Inner,
+            JoinKind::Left,
+            JoinKind::Right,
+            JoinKind::Full,
+        ]
+        .into_iter()
+        .find(|kind| kind.keyword() == keyword)
+    }
+
+    /// Returns `true` if rows of the `left` table without a match are kept.
+    fn keeps_unmatched_left(&self) -> bool {
+        matches!(self, JoinKind::Left | JoinKind::Full)
+    }
+
+    /// Returns `true` if rows of the `right` table without a match are kept.
+    fn keeps_unmatched_right(&self) -> bool {
+        matches!(self, JoinKind::Right | JoinKind::Full)
+    }
+}
+
+#[test]
+fn test_join_kind_from_keyword() {
+    assert_eq!(JoinKind::from_keyword("JOIN"), Some(JoinKind::Inner));
+    assert_eq!(JoinKind::from_keyword("OUTERJOIN"), Some(JoinKind::Full));
+    assert_eq!(JoinKind::from_keyword("leftjoin"), None);
+    assert_eq!(JoinKind::Right.keyword(), "RIGHTJOIN");
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The WITH clause comes before the query it applies to.
+        if let Operator::With { query, .. } = self {
+            return f.write_fmt(format_args!("{} {}", self.label(), query));
+        }
+        match self.chain() {
+            Some(chain) => f.write_fmt(format_args!("{} {}", chain, self.label())),
+            None => f.write_str(&self.label()),
+        }
+    }
+}
+
+/// Describes an [`Operator`] for the `help` command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorDescriptor {
+    /// The keyword used to invoke the operator, e.g. "TAKE".
+    pub name: &'static str,
+    /// The arguments the operator takes, e.g. `<number>`.
+    pub arguments: &'static str,
+    /// A one line description of the operator.
+    pub summary: &'static str,
+    /// Further details about the operator's arguments and behavior.
+    pub details: &'static str,
+}
+
+/// Descriptors for every [`Operator`] supported by this tool, in the order they are listed by
+/// `help`.
+pub const OPERATORS: &[OperatorDescriptor] = &[
+    OperatorDescriptor {
+        name: "FROM",
+        arguments: "<dataset>",
+        summary: "Loads the <dataset>.",
+        details: "Must always be the first operator in a chain, and may be followed by any of the \
+            other operators. If no other operator is specified, prints the <dataset>. The \
+            <dataset> can also be the path of any other CSV file with a header row, or of a \
+            Parquet file; the types of its columns are inferred from their values.\n\
+            Example: FROM city.csv or FROM ./path/to/any.csv or FROM ./path/to/any.parquet",
+    },
+    OperatorDescriptor {
+        name: "SELECT",
+        arguments: "<column-name>[,<column-name>...] | * [EXCEPT <column-name>[,<column-name>...]]",
+        summary: "Selects particular columns from the input table.",
+        details: "Takes a comma separated list of column names, without spaces. The columns are \
+            output in the order they are listed. A column can also be computed by calling one of \
+            the built-in functions UPPER, LOWER, LENGTH, and COALESCE, e.g. UPPER(CityName), or \
+            a function defined in the --script file, e.g. slug(CityName). Values can be combined with +, -, \
+            *, and /, e.g. CountryPop - CityPop; dividing always produces a decimal number, and \
+            dividing by zero produces a missing value. A column can be renamed with AS; later \
+            operators then refer to it by its new name. * selects all the columns of the input \
+            table, and * EXCEPT all of them but the listed ones.\n\
+            Example: FROM city.csv SELECT UPPER(CityName) AS Name,CityPop\n\
+            Example: FROM country.csv SELECT * EXCEPT Capital",
+    },
+    OperatorDescriptor {
+        name: "EXTEND",
+        arguments: "<name> = <expression>[,<name> = <expression>...]",
+        summary: "Appends columns computed from the columns of the input table.",
+        details: "Each column is computed by an expression like those SELECT takes, and given \
+            the <name> before the =. Unlike SELECT, all the columns of the input table are kept, \
+            and the new ones are added after them. The expressions can only refer to the columns \
+            of the input table, and the names of the new columns must not be taken.\n\
+            Example: FROM city.csv EXTEND PopDensity = CityPop / 1000,Name = UPPER(CityName)",
+    },
+    OperatorDescriptor {
+        name: "TAKE",
+        arguments: "<number>",
+        summary: "Returns the first <number> rows of the input table.",
+        details: "<number> must be greater than or equal to 0. If the input table has fewer rows, \
+            all of them are returned.\n\
+            Example: FROM city.csv TAKE 5",
+    },
+    OperatorDescriptor {
+        name: "SKIP",
+        arguments: "<number>",
+        summary: "Drops the first <number> rows of the input table and returns the rest.",
+        details: "<number> must be greater than or equal to 0. If the input table has fewer rows, \
+            the result is empty. Use SKIP before TAKE to page through the results.\n\
+            Example: FROM city.csv ORDERBY CityPop SKIP 10 TAKE 10",
+    },
+    OperatorDescriptor {
+        name: "TAIL",
+        arguments: "<number>",
+        summary: "Returns the last <number> rows of the input table.",
+        details: "<number> must be greater than or equal to 0. If the input table has fewer rows, \
+            all of them are returned. Unlike TAKE, TAIL reads its whole input before returning a \
+            row, but only holds <number> rows in memory. Use it after ORDERBY to get the rows \
+            at the other end of the order.\n\
+            Example: FROM city.csv ORDERBY CityPop TAIL 5",
+    },
+    OperatorDescriptor {
+        name: "SAMPLE",
+        arguments: "<number>|<percent>% [SEED <number>]",
+        summary: "Returns a random subset of the rows of the input table.",
+        details: "Every row is equally likely to be picked, unlike TAKE, which returns the first \
+            rows. The picked rows keep their order. <percent> is between 0 and 100, e.g. 10% \
+            returns a tenth of the rows. A different subset is picked every time, unless SEED is \
+            given, which picks the same rows for the same seed and input table.\n\
+            Example: FROM city.csv JOIN country.csv CountryCode SAMPLE 5\n\
+            Example: FROM language.csv SAMPLE 1% SEED 42",
+    },
+    OperatorDescriptor {
+        name: "ORDERBY",
+        arguments: "<numeric-column-name> [ASC|DESC] [NULLS FIRST|LAST]",
+        summary: "Sorts the input table by the column, in descending order unless ASC is given.",
+        details: "The column must contain numeric values. Rows with equal values keep their \
+            relative order, and rows with a missing value come last, unless NULLS FIRST is given.\n\
+            Example: FROM country.csv ORDERBY Capital ASC NULLS FIRST",
+    },
+    OperatorDescriptor {
+        name: "COUNTBY",
+        arguments: "<column-name> [ORDER count|value] [BARS] [HAVING <condition>]",
+        summary: "Counts the number of times each value of the column appears in the input table.",
+        details: "Produces a table with two columns: the distinct values of <column-name> and \
+            their `count`. The most frequent values are listed first, unless ORDER value is given, \
+            which lists the values from smallest to largest instead. Values that appear equally \
+            often are listed in the order they first appear in the input table. BARS adds a `bar` \
+            column drawing each count as a bar of #s, the longest filling the width of the \
+            terminal, to show the distribution at a glance. HAVING keeps only the rows meeting the \
+            condition, written as for FILTER, e.g. count > 10.\n\
+            Example: FROM language.csv COUNTBY Language\n\
+            Example: FROM city.csv COUNTBY CountryCode ORDER value\n\
+            Example: FROM language.csv COUNTBY CountryCode BARS TAKE 10\n\
+            Example: FROM language.csv COUNTBY Language HAVING count > 10",
+    },
+    OperatorDescriptor {
+        name: "SUM",
+        arguments: "<column-name>",
+        summary: "Adds up the values of the column, producing a single row.",
+        details: "The column must contain numbers. Missing values are ignored, and the result \
+            is missing if there are no values to add up.\n\
+            Example: FROM city.csv SUM CityPop",
+    },
+    OperatorDescriptor {
+        name: "AVG",
+        arguments: "<column-name>",
+        summary: "Averages the values of the column, producing a single row.",
+        details: "The column must contain numbers. Missing values are ignored, and the result \
+            is missing if there are no values to average.\n\
+            Example: FROM country.csv AVG CountryPop",
+    },
+    OperatorDescriptor {
+        name: "MIN",
+        arguments: "<column-name>",
+        summary: "Finds the smallest value of the column, producing a single row.",
+        details: "The column must contain numbers. Missing values are ignored, and the result \
+            is missing if there are no values.\n\
+            Example: FROM city.csv MIN CityPop",
+    },
+    OperatorDescriptor {
+        name: "MAX",
+        arguments: "<column-name>",
+        summary: "Finds the largest value of the column, producing a single row.",
+        details: "The column must contain numbers. Missing values are ignored, and the result \
+            is missing if there are no values.\n\
+            Example: FROM city.csv MAX CityPop",
+    },
+    OperatorDescriptor {
+        name: "COUNT",
+        arguments: "[<column-name>|*]",
+        summary: "Counts the values of the column, or the rows if no column is given, producing a single row.",
+        details: "Missing values are not counted. COUNT on its own, or COUNT *, counts the rows of \
+            the input table instead, producing the column COUNT(*).\n\
+            Example: FROM country.csv COUNT Capital\n\
+            Example: FROM city.csv FILTER CityPop > 1000000 COUNT",
+
+    },
+    OperatorDescriptor {
+        name: "GROUPBY",
+        arguments: "<column-name> <AGG(column-name)>[,<AGG(column-name)>...] [HAVING <condition>]",
+        summary: "Produces one row per value of the column, with the aggregates of each group.",
+        details: "AGG is one of SUM, AVG, MIN, MAX, or COUNT. The groups are listed in the order \
+            their values first appear in the input table. HAVING keeps only the groups meeting the \
+            condition, written as for FILTER, which may compare an aggregate by its name.\n\
+            Example: FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID)\n\
+            Example: FROM city.csv GROUPBY CountryCode SUM(CityPop) HAVING SUM(CityPop) > 50000000",
+    },
+    OperatorDescriptor {
+        name: "PIVOT",
+        arguments: "<row-column-name> <column-column-name> <AGG(column-name)>",
+        summary: "Cross-tabulates the input table, with the aggregate of each pair of values.",
+        details: "Produces one row per value of <row-column-name> and, after it, one column per \
+            value of <column-column-name>, named after the value. Each cell holds the aggregate of \
+            the rows sharing its row's and its column's values, e.g. how many there are with \
+            COUNT. AGG is one of SUM, AVG, MIN, MAX, or COUNT. The rows and columns are listed in \
+            the order their values first appear in the input table. A pair of values without any \
+            rows is missing, or 0 for COUNT.\n\
+            Example: FROM language.csv JOIN country.csv CountryCode PIVOT Language Continent \
+            COUNT(CountryCode)",
+    },
+    OperatorDescriptor {
+        name: "JOIN",
+        arguments: "<dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE]",
+        summary: "Joins the input table with the <dataset> on the column.",
+        details:
+            "The <column-name> must be present in both the input table and the <dataset>. \
+            To join columns with different names, use ON with the column of the input table \
+            before the = and the column of the <dataset> after it; both columns are kept. \
+            Only rows with a matching value in both tables are kept. Other columns of the \
+            <dataset> named like a column of the input table get a numbered suffix, e.g. \
+            CityName_2, so a dataset can be joined with itself. Columns can also be referred \
+            to by their qualified name, e.g. city.CityName, which is ambiguous for a dataset \
+            joined with itself. The <dataset> may also be a \
+            query in parentheses, whose result is joined like a dataset. The rows are matched \
+            with a hash join, or with a merge join when the input table is sorted on the column \
+            by ORDERBY; USING HASH or USING MERGE picks one instead.\n\
+            Example: FROM city.csv JOIN country.csv CountryCode\n\
+            Example: FROM country.csv JOIN city.csv ON Capital=CityID\n\
+            Example: FROM city.csv JOIN city.csv CountryCode SELECT CityName,CityName_2\n\
+            Example: FROM city.csv JOIN country.csv CountryCode SELECT city.CityName,country.CountryName\n\
+            Example: FROM city.csv JOIN (FROM country.csv SELECT CountryCode,Continent) CountryCode",
+    },
+    OperatorDescriptor {
+        name: "LEFTJOIN",
+        arguments: "<dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE]",
+        summary: "Like JOIN, but also keeps the rows of the input table without a match.",
+        details: "The columns from the <dataset> are left empty (NULL) for the rows of the input \
+            table without a matching row in the <dataset>.\n\
+            Example: FROM country.csv LEFTJOIN city.csv CountryCode",
+    },
+    OperatorDescriptor {
+        name: "RIGHTJOIN",
+        arguments: "<dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE]",
+        summary: "Like JOIN, but also keeps the rows of the <dataset> without a match.",
+        details: "The columns from the input table are left empty (NULL) for the rows of the \
+            <dataset> without a matching row in the input table. They are listed after the \
+            matched rows.\n\
+            Example: FROM city.csv RIGHTJOIN country.csv CountryCode",
+    },
+    OperatorDescriptor {
+        name: "OUTERJOIN",
+        arguments: "<dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE]",
+        summary: "Like JOIN, but also keeps the rows of both tables without a match.",
+        details: "Combines LEFTJOIN and RIGHTJOIN: the missing columns of unmatched rows from \
+            either table are left empty (NULL).\n\
+            Example: FROM city.csv OUTERJOIN country.csv CountryCode",
+    },
+    OperatorDescriptor {
+        name: "UNION",
+        arguments: "[ALL] (<query>)",
+        summary: "Appends the rows of the <query> to the input table, removing duplicate rows unless ALL is given.",
+        details: "Both tables must have the same number of columns. Their columns are matched by \
+            position, must hold the same type of values, e.g. both numbers or both strings, and \
+            keep the names of the input table's. The <query> must be in parentheses, and the \
+            operators after it apply to the combined rows, e.g. UNION (FROM language.csv SELECT \
+            CountryCode) TAKE 5 keeps 5 of them.\n\
+            Example: FROM city.csv SELECT CountryCode UNION (FROM language.csv SELECT CountryCode)",
+    },
+    OperatorDescriptor {
+        name: "FILTER",
+        arguments: "<column-name> <comparison> <value>",
+        summary: "Keeps the rows of the input table for which the comparison holds.",
+        details: "<comparison> is one of =, !=, <, <=, >, >=. Numbers are compared numerically \
+            and strings alphabetically. The string comparisons LIKE, CONTAINS, STARTSWITH, and \
+            ENDSWITH match strings against the value, where a LIKE pattern uses % for any \
+            sequence of characters and _ for any single character, e.g. CityName LIKE 'San%'. \
+            MATCHES matches strings against a regular expression, e.g. CityName MATCHES \
+            '^New_.*'. Prefix them with I to ignore case, e.g. ICONTAINS. Rows with a missing value are never kept, unless the \
+            comparison is IS NULL; IS NOT NULL keeps the rows whose value isn't missing. WHERE \
+            can be used instead of FILTER. Values containing spaces are written in single or \
+            double quotes, e.g. 'New York', and a quote inside them is escaped with a \
+            backslash, e.g. 'O\\'Hare'.\n\
+            Example: FROM country.csv FILTER Continent = Europe",
+    },
+    OperatorDescriptor {
+        name: "DISTINCT",
+        arguments: "[<comma-separated-column-names>]",
+        summary: "Removes duplicate rows from the input table, keeping the first of each.",
+        details: "Without columns, rows are duplicates if all their values are equal. With \
+            columns, rows are duplicates if their values in those columns are equal, and the \
+            first such row is kept whole.\n\
+            Example: FROM language.csv SELECT Language DISTINCT\n\
+            Example: FROM city.csv ORDERBY CityPop DISTINCT CountryCode",
+    },
+    OperatorDescriptor {
+        name: "RENAME",
+        arguments: "<column-name> <new-column-name>",
+        summary: "Renames a column of the input table.",
+        details: "Later operators refer to the column by its new name. The new name must not be \
+            the name of another column of the input table. Useful to tell apart the columns of a \
+            dataset joined with itself.\n\
+            Example: FROM city.csv RENAME CityPop Population ORDERBY Population",
+    },
+    OperatorDescriptor {
+        name: "REORDER",
+        arguments: "<comma-separated-column-names>",
+        summary: "Moves columns of the input table to the front, in the given order.",
+        details: "The other columns follow in their original order, so unlike SELECT no column \
+            is dropped or has to be listed. A column listed more than once is only moved once. \
+            Useful to lay out a result before exporting it.\n\
+            Example: FROM city.csv REORDER CityName,CityPop EXPORT csv cities.csv",
+    },
+    OperatorDescriptor {
+        name: "CAST",
+        arguments: "<column-name> AS INT|FLOAT|STRING",
+        summary: "Converts the values of a column of the input table to another type.",
+        details: "INT converts strings of digits and truncates decimal numbers towards zero, \
+            FLOAT converts strings holding numbers, and STRING converts any value to its text. \
+            Missing values stay missing, and a value that can't be converted is an error. Useful \
+            to sort on a string column of digits, or to join it with an integer column.\n\
+            Example: FROM zips.csv CAST Zip AS INT ORDERBY Zip",
+    },
+    OperatorDescriptor {
+        name: "FILLNA",
+        arguments: "<column-name> <value>",
+        summary: "Replaces the missing values of a column of the input table with the <value>.",
+        details: "The <value> is a number if it parses as one, and a string otherwise; quote it \
+            if it contains spaces. Once its missing values are replaced with a number, a column \
+            of integers such as Capital can be sorted on and aggregated. The built-in function \
+            COALESCE does the same in expressions, returning its first argument that isn't \
+            missing, e.g. SELECT COALESCE(Capital, 0) AS Capital.\n\
+            Example: FROM country.csv FILLNA Capital 0 ORDERBY Capital",
+    },
+    OperatorDescriptor {
+        name: "TOPN",
+        arguments: "<number> BY <column-name> ORDER <numeric-column-name> [ASC|DESC]",
+        summary: "Keeps the top rows of each group of rows sharing a value of the BY column.",
+        details: "Keeps the <number> rows with the largest values of the ORDER column within \
+            each group, or the smallest ones if ASC is given. The groups are listed in the order \
+            they first appear in the input table, and the rows of each group from first to last \
+            place. Rows with equal values keep their relative order, and rows with a missing \
+            value come last.\n\
+            Example: FROM city.csv TOPN 3 BY CountryCode ORDER CityPop",
+    },
+    OperatorDescriptor {
+        name: "RANK",
+        arguments: "BY <numeric-column-name> [PER <column-name>]",
+        summary: "Adds a `rank` column ranking the rows of the input table by the column.",
+        details: "The row with the largest value is ranked 1. Rows with equal values share a \
+            rank, and the rank after them skips the shared places, e.g. 1, 2, 2, 4. Rows with a \
+            missing value are ranked last. PER ranks the rows of each group sharing a value of \
+            <column-name> separately. The rows keep their order.\n\
+            Example: FROM city.csv RANK BY CityPop PER CountryCode FILTER rank <= 3",
+    },
+    OperatorDescriptor {
+        name: "WITH",
+        arguments: "<name> AS (<query>)[, <name> AS (<query>)...] <query>",
+        summary: "Names the results of sub-queries so FROM and JOIN in the <query> can use them.",
+        details: "Each sub-query is run once, before the <query>. Sub-queries may refer to the \
+            ones defined before them. The names are only valid within the query.\n\
+            Example: WITH euro AS (FROM country.csv FILTER Continent = Europe) FROM city.csv \
+            JOIN euro CountryCode",
+    },
+    OperatorDescriptor {
+        name: "MAP",
+        arguments: "<script>",
+        summary: "Transforms every row of the input table with the rhai <script>.",
+        details: "<script> is the path of a rhai script that is run once per row. The row is \
+            available to the script as a map from column name to value called `row`, and the \
+            script must evaluate to the modified map. Columns can be changed, added, or removed, \
+            but every row must end up with the same columns.\n\
+            Example: FROM city.csv MAP clean.rhai",
+    },
+    OperatorDescriptor {
+        name: "INTO",
+        arguments: "<path>",
+        summary: "Writes the input table to the file at <path> as CSV instead of printing it.",
+        details: "Must be the last operator of the query. The file is overwritten if it exists, \
+            and can be loaded back with FROM. EXPORT can be used instead of INTO. \
+            EXPORT ARROW <path> writes an Arrow IPC (Feather) file instead, which keeps the types \
+            of the columns, e.g. to read the results with pandas or polars.\n\
+            Example: FROM city.csv ORDERBY CityPop TAKE 10 INTO results.csv\n\
+            Example: FROM city.csv JOIN country.csv CountryCode EXPORT ARROW cities.arrow",
+    },
+];
+
+/// Looks up the [`OperatorDescriptor`] for the operator invoked by `name`, ignoring case.
+pub fn find_operator_descriptor(name: &str) -> Option<&'static OperatorDescriptor> {
+    OPERATORS
+        .iter()
+        .find(|descriptor| descriptor.name.eq_ignore_ascii_case(name))
+}
+
+#[test]
+fn test_find_operator_descriptor() {
+    assert_eq!(find_operator_descriptor("TAKE").unwrap().name, "TAKE");
+    assert_eq!(find_operator_descriptor("orderby").unwrap().name, "ORDERBY");
+    assert!(find_operator_descriptor("ORDER").is_none());
+}
+
+impl Operator {
+    /// Returns the keyword used to invoke this operator on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operator::From(_) => "FROM",
+            Operator::Select { .. } | Operator::SelectAll { .. } => "SELECT",
+            Operator::Extend { .. } => "EXTEND",
+            Operator::Take { .. } => "TAKE",
+            Operator::Skip { .. } => "SKIP",
+            Operator::Tail { .. } => "TAIL",
+            Operator::Sample { .. } => "SAMPLE",
+            Operator::OrderBy { .. } => "ORDERBY",
+            Operator::CountBy { .. } => "COUNTBY",
+            Operator::Aggregate { function, .. } => function.name(),
+            Operator::GroupBy { .. } => "GROUPBY",
+            Operator::Join { kind, .. } => kind.keyword(),
+            Operator::Union { .. } => "UNION",
+            Operator::Filter { .. } => "FILTER",
+            Operator::With { .. } => "WITH",
+            Operator::Map { .. } => "MAP",
+            Operator::Distinct { .. } => "DISTINCT",
+            Operator::Rename { .. } => "RENAME",
+            Operator::Reorder { .. } => "REORDER",
+            Operator::Cast { .. } => "CAST",
+            Operator::FillNa { .. } => "FILLNA",
+            Operator::TopN { .. } => "TOPN",
+            Operator::Pivot { .. } => "PIVOT",
+            Operator::Rank { .. } => "RANK",
+            Operator::Into { .. } => "INTO",
+        }
+    }
+
+    /// Returns this operator and its arguments as entered on the command line, without the chain
+    /// of operators that precede it.
+    pub fn label(&self) -> String {
+        match self {
+            Operator::From(dataset) => format!("FROM {}", dataset),
+            Operator::Select { columns, .. } => format!(
+                "SELECT {}",
+                columns
+                    .iter()
+                    .map(|column| column.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            Operator::SelectAll { except, .. } if except.is_empty() => "SELECT *".to_string(),
+            Operator::SelectAll { except, .. } => format!("SELECT * EXCEPT {}", except.join(",")),
+            Operator::Extend { columns, .. } => format!(
+                "EXTEND {}",
+                columns
+                    .iter()
+                    .map(|(name, expression)| format!("{} = {}", name, expression))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            Operator::Take { count, .. } => format!("TAKE {}", count),
+            Operator::Skip { count, .. } => format!("SKIP {}", count),
+            Operator::Tail { count, .. } => format!("TAIL {}", count),
+            Operator::Sample {
+                size,
+                seed: Some(seed),
+                ..
+            } => format!("SAMPLE {} SEED {}", size, seed),
+            Operator::Sample { size, .. } => format!("SAMPLE {}", size),
+            // Descending and NULLS LAST are the defaults, so they are left out to keep the label
+            // short.
+            Operator::OrderBy {
+                column,
+                order,
+                nulls,
+                ..
+            } => {
+                let mut label = format!("ORDERBY {}", column);
+                if *order == SortOrder::Ascending {
+                    label.push_str(&format!(" {}", order));
+                }
+                if *nulls == NullsOrder::First {
+                    label.push_str(&format!(" {}", nulls));
+                }
+                label
+            }
+            Operator::CountBy {
+                column,
+                order,
+                bars,
+                having,
+                ..
+            } => {
+                let mut label = format!("COUNTBY {}", column);
+                // Ordering by count is the default, so it is left out to keep the label short.
+                if *order != CountByOrder::Count {
+                    label.push_str(&format!(" ORDER {}", order));
+                }
+                if *bars {
+                    label.push_str(" BARS");
+                }
+                if let Some(having) = having {
+                    label.push_str(&format!(" HAVING {}", having));
+                }
+                label
+            }
+            Operator::Aggregate {
+                function, column, ..
+            } => format!("{} {}", function, column),
+            Operator::GroupBy {
+                column,
+                aggregates,
+                having,
+                ..
+            } => {
+                let label = format!(
+                    "GROUPBY {} {}",
+                    column,
+                    aggregates
+                        .iter()
+                        .map(|aggregate| aggregate.to_string())
+                        .collect::<Vec<String>>()
+                        .join(",")
+                );
+                match having {
+                    Some(having) => format!("{} HAVING {}", label, having),
+                    None => label,
+                }
+            }
+            Operator::Join {
+                right,
+                column,
+                right_column,
+                kind,
+                strategy,
+                ..
+            } => {
+                // A dataset is named as is; a sub-query is parenthesized.
+                let right = match right.as_ref() {
+                    Operator::From(dataset) => dataset.to_string(),
+                    subquery => format!("({})", subquery),
+                };
+                let label = match right_column {
+                    None => format!("{} {} {}", kind, right, column),
+                    Some(right_column) => {
+                        format!("{} {} ON {}={}", kind, right, column, right_column)
+                    }
+                };
+                match strategy {
+                    Some(strategy) => format!("{} USING {}", label, strategy),
+                    None => label,
+                }
+            }
+            // The query after UNION is parenthesized, so the operators following it aren't read
+            // as part of it.
+            Operator::Union {
+                right, all: true, ..
+            } => format!("UNION ALL ({})", right),
+            Operator::Union { right, .. } => format!("UNION ({})", right),
+            Operator::Filter { predicate, .. } => format!("FILTER {}", predicate),
+            Operator::With { ctes, .. } => format!(
+                "WITH {}",
+                ctes.iter()
+                    .map(|(name, cte)| format!("{} AS ({})", name, cte))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Operator::Map { script, .. } => format!("MAP {}", script),
+            Operator::Into {
+                path,
+                format: ExportFormat::Csv,
+                ..
+            } => format!("INTO {}", path),
+            Operator::Into { path, format, .. } => format!("EXPORT {} {}", format, path),
+            Operator::Distinct { columns, .. } if columns.is_empty() => "DISTINCT".to_string(),
+            Operator::Distinct { columns, .. } => format!("DISTINCT {}", columns.join(",")),
+            Operator::Rename {
+                column, new_name, ..
+            } => format!("RENAME {} {}", column, new_name),
+            Operator::Reorder { columns, .. } => format!("REORDER {}", columns.join(",")),
+            Operator::Cast { column, to, .. } => format!("CAST {} AS {}", column, to),
+            Operator::FillNa { column, value, .. } => {
+                format!("FILLNA {} {}", column, format_constant(value))
+            }
+            // Descending is the default, so it is left out to keep the label short.
+            Operator::TopN {
+                count,
+                group,
+                column,
+                order: SortOrder::Descending,
+                ..
+            } => format!("TOPN {} BY {} ORDER {}", count, group, column),
+            Operator::TopN {
+                count,
+                group,
+                column,
+                order,
+                ..
+            } => format!("TOPN {} BY {} ORDER {} {}", count, group, column, order),
+            Operator::Rank {
+                column,
+                partition: Some(partition),
+                ..
+            } => format!("RANK BY {} PER {}", column, partition),
+            Operator::Rank { column, .. } => format!("RANK BY {}", column),
+            Operator::Pivot {
+                row,
+                column,
+                aggregate,
+                ..
+            } => format!("PIVOT {} {} {}", row, column, aggregate),
+        }
+    }
+
+    /// Returns the chain of [`Operator`]s that produces the input [`Table`] for this operator, or
+    /// `None` if this operator is the start of the chain (i.e., FROM). For WITH, this is the query
+    /// the sub-queries apply to.
+    pub fn chain(&self) -> Option<&Operator> {
+        match self {
+            Operator::From(_) => None,
+            Operator::Select { chain, .. }
+            | Operator::SelectAll { chain, .. }
+            | Operator::Extend { chain, .. }
+            | Operator::Take { chain, .. }
+            | Operator::Skip { chain, .. }
+            | Operator::Tail { chain, .. }
+            | Operator::Sample { chain, .. }
+            | Operator::OrderBy { chain, .. }
+            | Operator::CountBy { chain, .. }
+            | Operator::Aggregate { chain, .. }
+            | Operator::GroupBy { chain, .. }
+            | Operator::Join { chain, .. }
+            | Operator::Union { chain, .. }
+            | Operator::Filter { chain, .. }
+            | Operator::Map { chain, .. }
+            | Operator::Into { chain, .. }
+            | Operator::Distinct { chain, .. }
+            | Operator::Rename { chain, .. }
+            | Operator::Reorder { chain, .. }
+            | Operator::Cast { chain, .. }
+            | Operator::FillNa { chain, .. }
+            | Operator::TopN { chain, .. }
+            | Operator::Rank { chain, .. }
+            | Operator::Pivot { chain, .. } => Some(chain),
+            Operator::With { query, .. } => Some(query),
+        }
+    }
+
+    /// Returns the sub-query producing the right table of a JOIN or a UNION, or `None` if this
+    /// operator is neither or joins with a dataset, which is loaded directly.
+    pub fn subquery(&self) -> Option<&Operator> {
+        match self {
+            Operator::Join { right, .. } if !matches!(right.as_ref(), Operator::From(_)) => {
+                Some(right)
+            }
+            Operator::Union { right, .. } => Some(right),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this operator, the last of its query, is a TAKE, a TAIL, or a SAMPLE.
+    /// One earlier in the chain doesn't count, as the operators after it may produce more rows,
+    /// e.g. `FROM city TAKE 5 JOIN city CountryCode`.
+    pub fn ends_with_take(&self) -> bool {
+        match self {
+            Operator::Take { .. } | Operator::Tail { .. } | Operator::Sample { .. } => true,
+            Operator::With { query, .. } => query.ends_with_take(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if processing this operator again gives the same result as long as the
+    /// datasets it reads are unchanged, i.e., neither it nor any operator in its chain or its
+    /// sub-queries writes the result with INTO or samples rows without a seed.
+    pub fn is_repeatable(&self) -> bool {
+        match self {
+            Operator::Into { .. } | Operator::Sample { seed: None, .. } => false,
+            Operator::With { ctes, query } => {
+                ctes.iter().all(|(_, cte)| cte.is_repeatable()) && query.is_repeatable()
+            }
+            _ => {
+                self.subquery().is_none_or(Operator::is_repeatable)
+                    && self.chain().is_none_or(Operator::is_repeatable)
+            }
+        }
+    }
+
+    /// Returns the datasets this operator reads, those of its chain first, then those of its
+    /// sub-queries and of the right side of a JOIN. A dataset read more than once is listed each
+    /// time.
+    pub fn datasets(&self) -> Vec<&Dataset> {
+        match self {
+            Operator::From(dataset) => vec![dataset],
+            Operator::With { ctes, query } => ctes
+                .iter()
+                .flat_map(|(_, cte)| cte.datasets())
+                .chain(query.datasets())
+                .collect(),
+            Operator::Join { chain, right, .. } | Operator::Union { chain, right, .. } => {
+                let mut datasets = chain.datasets();
+                datasets.extend(right.datasets());
+                datasets
+            }
+            _ => self.chain().map(Operator::datasets).unwrap_or_default(),
+        }
+    }
+
+    /// Returns `true` if this operator produces its rows one at a time, as they are pulled from
+    /// it, or `false` if it processes its whole input when it is opened. See [`open_operator`].
+    /// WITH processes its sub-queries when it is opened, but streams the rows of its query.
+    pub fn is_streaming(&self) -> bool {
+        match self {
+            Operator::From(_)
+            | Operator::Select { .. }
+            | Operator::SelectAll { .. }
+            | Operator::Extend { .. }
+            | Operator::Take { .. }
+            | Operator::Skip { .. }
+            | Operator::Filter { .. }
+            | Operator::Distinct { .. }
+            | Operator::Rename { .. }
+            | Operator::Reorder { .. }
+            | Operator::Cast { .. }
+            | Operator::FillNa { .. }
+            | Operator::Union { .. }
+            | Operator::With { .. } => true,
+            Operator::Tail { .. }
+            | Operator::Sample { .. }
+            | Operator::OrderBy { .. }
+            | Operator::TopN { .. }
+            | Operator::Rank { .. }
+            | Operator::Pivot { .. }
+            | Operator::CountBy { .. }
+            | Operator::Aggregate { .. }
+            | Operator::GroupBy { .. }
+            | Operator::Join { .. }
+            | Operator::Map { .. }
+            | Operator::Into { .. } => false,
+        }
+    }
+}
+
+#[test]
+fn test_operator_ends_with_take() {
+    assert!(!Operator::From(Dataset::City).ends_with_take());
+    let take = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 5,
+    };
+    assert!(take.ends_with_take());
+    assert!(Operator::With {
+        ctes: vec![("big".to_string(), Operator::From(Dataset::City))],
+        query: Box::new(take.clone()),
+    }
+    .ends_with_take());
+    assert!(!Operator::OrderBy {
+        chain: Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 5,
+        }),
+        column: "CityPop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    }
+    .ends_with_take());
+    assert!(!Operator::Select {
+        chain: Box::new(Operator::From(Dataset::City)),
+        columns: vec![Expression::Column("CityPop".to_string()).into()],
+    }
+    .ends_with_take());
+}
+
+#[test]
+fn test_operator_is_streaming() {
+    let from = Operator::From(Dataset::City);
+    assert!(from.is_streaming());
+    let take = Operator::Take {
+        chain: Box::new(from.clone()),
+        count: 5,
+    };
+    assert!(take.is_streaming());
+    let orderby = Operator::OrderBy {
+        chain: Box::new(take),
+        column: "CityPop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    assert!(!orderby.is_streaming());
+    assert!(!Operator::CountBy {
+        chain: Box::new(from),
+        column: "CountryCode".to_string(),
+        order: CountByOrder::Count,
+        bars: false,
+        having: None,
+    }
+    .is_streaming());
+}
+
+#[test]
+fn test_operator_datasets() {
+    let operator = |query: &str| match crate::commands::parse_command(&format!("{}\n", query)) {
+        crate::commands::Command::Operator(operator) => operator,
+        command => panic!("{:?}", command),
+    };
+    assert_eq!(
+        operator("FROM city TAKE 3").datasets(),
+        vec![&Dataset::City]
+    );
+    assert_eq!(
+        operator("FROM city JOIN country CountryCode UNION (FROM city) COUNT").datasets(),
+        vec![&Dataset::City, &Dataset::Country, &Dataset::City]
+    );
+    assert_eq!(
+        operator("WITH big AS (FROM language) FROM big JOIN (FROM country) CountryCode").datasets(),
+        vec![
+            &Dataset::Language,
+            &Dataset::Cte("big".to_string()),
+            &Dataset::Country
+        ]
+    );
+}
+
+/// Limits on the size of the tables held in memory while a query is processed, e.g. to stop a
+/// JOIN whose result wouldn't fit in memory. No limit applies when a field is `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResultLimits {
+    /// The maximum number of rows of a table.
+    pub max_rows: Option<usize>,
+    /// The maximum estimated number of bytes used by the rows of a table. See
+    /// [`Row::estimated_size`].
+    pub max_bytes: Option<usize>,
+}
+
+impl ResultLimits {
+    /// Checks a table of `rows` rows, using an estimated `bytes` bytes, against the limits.
+    ///
+    /// # Arguments
+    /// `operator` : Names the operator producing the table, for the error message. Only called if
+    /// a limit is exceeded.
+    ///
+    /// # Returns
+    /// [`OperatorError::ResultTooLarge`] if the table exceeds a limit.
+    pub fn check(
+        &self,
+        rows: usize,
+        bytes: usize,
+        operator: impl FnOnce() -> String,
+    ) -> Result<(), OperatorError> {
+        let limit = match (self.max_rows, self.max_bytes) {
+            (Some(max_rows), _) if rows > max_rows => format!("{} rows", max_rows),
+            (_, Some(max_bytes)) if bytes > max_bytes => format_bytes(max_bytes),
+            _ => return Ok(()),
+        };
+        Err(OperatorError::ResultTooLarge {
+            operator: operator(),
+            limit,
+        })
+    }
+}
+
+#[test]
+fn test_result_limits_check() {
+    let limits = ResultLimits {
+        max_rows: Some(10),
+        max_bytes: Some(2048),
+    };
+    assert!(limits.check(10, 2048, || "JOIN".to_string()).is_ok());
+    assert_eq!(
+        limits.check(11, 0, || "JOIN".to_string()).unwrap_err().to_string(),
+        "The result of JOIN grew past the limit of 10 rows, so the query was stopped. Narrow the query down, e.g. with FILTER, or raise the limit."
+    );
+    assert!(matches!(
+        limits.check(1, 2049, || "JOIN".to_string()),
+        Err(OperatorError::ResultTooLarge { limit, .. }) if limit == "2.0 KB"
+    ));
+    assert!(ResultLimits::default()
+        .check(usize::MAX, usize::MAX, || "JOIN".to_string())
+        .is_ok());
+}
+
+/// State shared by the [`Operator`]s while a query is processed.
+#[derive(Debug, Default)]
+pub struct ExecutionContext {
+    /// Execution statistics for every operator processed so far, with every operator after the
+    /// operators it chains.
+    /// Only collected when profiling is enabled, i.e., when this is `Some`.
+    pub profile: Option<Vec<OperatorProfile>>,
+    /// The user-defined functions that can be called from expressions, if a script was loaded.
+    pub functions: Option<ScriptFunctions>,
+    /// The query-scoped catalog of tables defined by the WITH clause of the query being
+    /// processed, by name. Consulted by FROM and JOIN for [`Dataset::Cte`]s.
+    catalog: HashMap<String, Arc<Table>>,
+    /// The results saved with `SET <name> = <query>`, by name. Consulted by FROM and JOIN for
+    /// [`Dataset::Variable`]s. Unlike the catalog, they are kept until the context is dropped.
+    variables: HashMap<String, Arc<Table>>,
+    /// The tables loaded from disk so far, reused by later queries processed in this context.
+    cache: DatasetCache,
+    /// The datasets modified with INSERT and DELETE, which are consulted before the cache. The
+    /// files on disk are left untouched, so the edits are lost when the context is dropped.
+    edited: HashMap<Dataset, Arc<Table>>,
+    /// How deeply nested the operator currently being processed is. The outermost operator of a
+    /// query has a depth of 0.
+    depth: usize,
+    /// The encoding the CSV files are read in, e.g. windows-1252. UTF-8 when `None`. The built-in
+    /// datasets are always UTF-8, and the files registered with an encoding of their own (see
+    /// [`Dataset::Registered`]) are read in it. Set it before the first query, as the datasets
+    /// already in the cache aren't read again.
+    pub encoding: Option<&'static Encoding>,
+    /// The number of columns of the terminal the results are printed to, which the bars drawn by
+    /// `COUNTBY ... BARS` are scaled to. [`C_DEFAULT_TERMINAL_WIDTH`] is used when `None`, e.g.
+    /// when the results aren't printed to a terminal.
+    pub terminal_width: Option<usize>,
+    /// The regular expressions compiled for `MATCHES` comparisons, by pattern, so that they are
+    /// compiled once per query rather than for every row. Cleared when the next query starts.
+    regexes: Mutex<HashMap<String, Regex>>,
+    /// How the rows of CSV files with too few or too many values are handled. Clear the cache
+    /// after changing it, as the datasets already in the cache aren't read again.
+    pub bad_rows: BadRows,
+    /// How the values of CSV files are cleaned up while loading them. Clear the cache after
+    /// changing it, as the datasets already in the cache aren't read again.
+    pub normalization: Normalization,
+    /// The bad rows skipped or filled in while loading datasets, since they were last taken with
+    /// [`ExecutionContext::take_bad_rows`].
+    bad_rows_reports: Vec<BadRowsReport>,
+    /// Limits on the size of the tables materialized while processing a query, i.e., the results
+    /// of the operators, and of JOIN as it is computed.
+    pub limits: ResultLimits,
+    /// The results of the latest queries processed with [`process_cached`].
+    results: ResultCache,
+    /// The hash indexes built by JOIN over the join columns of the datasets it joined, so that
+    /// joining a dataset on the same column again reuses them.
+    join_indexes: JoinIndexCache,
+    /// The datasets loaded by the query being processed with [`process_cached`], and the tables
+    /// they held. Only recorded when this is `Some`.
+    inputs: Option<Vec<(Dataset, Arc<Table>)>>,
+    /// Set, e.g. by a Ctrl-C handler running on another thread, to stop the query being
+    /// processed. Checked as rows are produced and joined, so even a long JOIN stops soon after.
+    /// Clear it before the next query, or it is cancelled too.
+    pub cancelled: Arc<AtomicBool>,
+    /// The number of rows read from the datasets, and the saved results, since it was last taken
+    /// with [`ExecutionContext::take_rows_scanned`].
+    rows_scanned: usize,
+}
+
+impl ExecutionContext {
+    /// Creates a context that collects an [`OperatorProfile`] for every processed operator.
+    pub fn with_profiling() -> Self {
+        ExecutionContext {
+            profile: Some(Vec::new()),
+            ..ExecutionContext::default()
+        }
+    }
+
+    /// Returns the statistics collected so far and resets the collection for the next query.
+    /// Returns `None` if profiling is disabled.
+    pub fn take_profile(&mut self) -> Option<Vec<OperatorProfile>> {
+        self.profile.as_mut().map(std::mem::take)
+    }
+
+    /// Returns [`OperatorError::Cancelled`] if the query being processed was cancelled, i.e.,
+    /// [`ExecutionContext::cancelled`] is set, to stop processing it.
+    pub fn check_cancelled(&self) -> Result<(), OperatorError> {
+        match self.cancelled.load(std::sync::atomic::Ordering::Relaxed) {
+            true => Err(OperatorError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Counts `rows` more rows read from a dataset or a saved result.
+    pub(crate) fn record_scanned(&mut self, rows: usize) {
+        self.rows_scanned += rows;
+    }
+
+    /// Returns the number of rows read from the datasets, and the saved results, by the queries
+    /// processed since the last call, and resets the count for the next query. Rows are only
+    /// counted as they are read, so a dataset cut short by TAKE counts only the rows it produced,
+    /// and a result reused by [`process_cached`] counts none.
+    pub fn take_rows_scanned(&mut self) -> usize {
+        std::mem::take(&mut self.rows_scanned)
+    }
+
+    /// Returns the bad rows skipped or filled in while loading the datasets of the queries
+    /// processed since the last call, to report them after the query.
+    pub fn take_bad_rows(&mut self) -> Vec<BadRowsReport> {
+        std::mem::take(&mut self.bad_rows_reports)
+    }
+
+    /// Saves the `table` under the `name`, so later queries processed in this context can refer to
+    /// it as `@<name>` (i.e., [`Dataset::Variable`]). Saving a name again replaces its table.
+    pub fn set_variable(&mut self, name: &str, table: Table) {
+        let table = Arc::new(table);
+        self.join_indexes
+            .forget_stale(&Dataset::Variable(name.to_string()), &table);
+        self.variables.insert(name.to_string(), table);
+    }
+
+    /// Returns the results saved with [`ExecutionContext::set_variable`], as the
+    /// [`Dataset::Variable`]s referring to them, ordered by name.
+    pub fn variables(&self) -> Vec<Dataset> {
+        let mut names: Vec<&String> = self.variables.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| Dataset::Variable(name.clone()))
+            .collect()
+    }
+
+    /// Returns the compiled regular expression for a `MATCHES` `pattern`, compiling it on first use
+    /// in the query being processed.
+    ///
+    /// # Arguments
+    /// `pattern` : The regular expression, in the syntax of the regex crate.
+    /// `ignore_case` : Whether letters match regardless of their case, as for `IMATCHES`.
+    ///
+    /// # Returns
+    /// On success: The compiled [`Regex`].
+    /// On failure: [`OperatorError::InvalidPattern`] if the `pattern` isn't a valid regular
+    /// expression.
+    pub fn regex(&self, pattern: &str, ignore_case: bool) -> Result<Regex, OperatorError> {
+        let pattern = match ignore_case {
+            true => format!("(?i){}", pattern),
+            false => pattern.to_string(),
+        };
+        if let Some(regex) = self.regexes.lock().unwrap().get(&pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(&pattern).map_err(|error| OperatorError::InvalidPattern {
+            pattern: pattern.clone(),
+            message: describe_regex_error(&error),
+        })?;
+        self.regexes.lock().unwrap().insert(pattern, regex.clone());
+        Ok(regex)
+    }
+
+    /// Drops the cached tables, so the next query reads every dataset from disk again. Datasets
+    /// modified with INSERT and DELETE keep their edits. The cached query results are dropped too.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+        self.results.clear();
+        self.join_indexes.clear();
+    }
+
+    /// Drops the cached query results, so the next queries are processed again. See
+    /// [`process_cached`].
+    pub fn clear_results(&mut self) {
+        self.results.clear();
+    }
+
+    /// Appends the `rows` of values to the `dataset`, as if it had been read from disk with them.
+    /// Only the copy of the dataset held by this context is modified, not the file on disk.
+    ///
+    /// # Arguments
+    /// `dataset` : The dataset to modify. A [`Dataset::Variable`] modifies the saved result.
+    /// `rows` : The values of each new row, one per column of the dataset. Integers are stored as
+    /// [`Cell::OptInt64`] in columns holding those, e.g. `Capital`, and the values of text columns
+    /// are stored as strings.
+    ///
+    /// # Returns
+    /// On success: The number of rows inserted.
+    /// On failure: [`OperatorError::WrongNumberOfValues`] if a row doesn't have one value per
+    /// column, [`OperatorError::InvalidValue`] if a value doesn't fit the type of its column, or
+    /// the [`OperatorError`] from loading the dataset. No rows are inserted on failure.
+    pub fn insert_rows(
+        &mut self,
+        dataset: &Dataset,
+        rows: Vec<Vec<Cell>>,
+    ) -> Result<usize, OperatorError> {
+        let mut table = Arc::unwrap_or_clone(load_dataset(dataset, "INSERT", self)?);
+        let count = rows.len();
+        for values in rows {
+            if values.len() != table.header.len() {
+                return Err(OperatorError::WrongNumberOfValues {
+                    dataset: dataset.clone(),
+                    expected: table.header.len(),
+                    found: values.len(),
+                });
+            }
+            let cells = values
+                .into_iter()
+                .enumerate()
+                .map(|(index, value)| convert_value(&table, index, value))
+                .collect::<Result<Vec<Cell>, OperatorError>>()?;
+            for (column, cell) in table.schema.iter_mut().zip(&cells) {
+                column.observe(cell);
+            }
+            table.rows.push(Row { cells });
+        }
+        self.replace_dataset(dataset, table);
+        Ok(count)
+    }
+
+    /// Removes the rows of the `dataset` for which the `predicate` holds. Only the copy of the
+    /// dataset held by this context is modified, not the file on disk.
+    ///
+    /// # Arguments
+    /// `dataset` : The dataset to modify. A [`Dataset::Variable`] modifies the saved result.
+    /// `predicate` : The condition the rows to remove satisfy.
+    ///
+    /// # Returns
+    /// On success: The number of rows removed.
+    /// On failure: [`OperatorError::NoSuchColumn`] if the `predicate` refers to a column the
+    /// dataset doesn't have, or the [`OperatorError`] from loading the dataset or evaluating the
+    /// `predicate`. No rows are removed on failure.
+    pub fn delete_rows(
+        &mut self,
+        dataset: &Dataset,
+        predicate: &Predicate,
+    ) -> Result<usize, OperatorError> {
+        let mut table = Arc::unwrap_or_clone(load_dataset(dataset, "DELETE", self)?);
+        for name in predicate.left.column_names() {
+            // This can throw the [`OperatorError::NoSuchColumn`] error.
+            find_column_index(
+                &table.header,
+                &table.qualified_names,
+                name,
+                &Operator::From(dataset.clone()),
+                "DELETE",
+            )?;
+        }
+        let count = table.rows.len();
+        let mut kept = Vec::with_capacity(count);
+        for row in table.rows {
+            if !predicate.matches(&table.header, &row, self)? {
+                kept.push(row);
+            }
+        }
+        let count = count - kept.len();
+        table.rows = kept;
+        self.replace_dataset(dataset, table);
+        Ok(count)
+    }
+
+    /// Writes the `dataset` as loaded by FROM, including the edits made with INSERT and DELETE, to
+    /// the CSV file at `path`. When the `path` is the dataset's own file, the edits are dropped
+    /// once written, so that the dataset is read from the file again.
+    ///
+    /// # Returns
+    /// On success: The number of rows written.
+    /// On failure: [`OperatorError::ExportError`] if the file can't be written, or the
+    /// [`OperatorError`] from loading the dataset.
+    pub fn save_dataset(&mut self, dataset: &Dataset, path: &str) -> Result<usize, OperatorError> {
+        let table = load_dataset(dataset, "SAVE", self)?;
+        table
+            .write_csv(path)
+            .map_err(|e| OperatorError::ExportError {
+                path: path.to_string(),
+                error: Box::new(e),
+            })?;
+        if dataset.path() == Some(path) {
+            self.edited.remove(dataset);
+            self.join_indexes.forget(dataset);
+        }
+        Ok(table.rows.len())
+    }
+
+    /// Helper function to make the `table` the contents of the `dataset` for the later queries
+    /// processed in this context.
+    fn replace_dataset(&mut self, dataset: &Dataset, table: Table) {
+        match dataset {
+            Dataset::Variable(name) => self.set_variable(name, table),
+            _ => {
+                let table = Arc::new(table);
+                self.join_indexes.forget_stale(dataset, &table);
+                self.edited.insert(dataset.clone(), table);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_execution_context_regex() {
+    let mut context = ExecutionContext::default();
+    let regex = context.regex("^New_", false).unwrap();
+    assert!(regex.is_match("New_York"));
+    assert!(!context.regex("^new_", false).unwrap().is_match("New_York"));
+    assert!(context.regex("^new_", true).unwrap().is_match("New_York"));
+    assert_eq!(context.regexes.lock().unwrap().len(), 3);
+    // Compiling the same pattern again reuses the cached regex.
+    context.regex("^New_", false).unwrap();
+    assert_eq!(context.regexes.lock().unwrap().len(), 3);
+    assert_eq!(
+        context.regex("(New", false).unwrap_err().to_string(),
+        "Invalid regular expression (New: unclosed group"
+    );
+    // The next query starts with an empty cache.
+    process_operator(&Operator::From(Dataset::Language), &mut context).unwrap();
+    assert!(context.regexes.lock().unwrap().is_empty());
+}
+
+/// Helper function to convert a `value` passed to INSERT to the type of the column of the `table`
+/// at `index`.
+///
+/// # Returns
+/// On success: The value to store in the column.
+/// On failure: [`OperatorError::InvalidValue`] if the column holds numbers and the `value` isn't
+/// one.
+fn convert_value(table: &Table, index: usize, value: Cell) -> Result<Cell, OperatorError> {
+    let column = &table.header[index];
+    let optional = table
+        .rows
+        .iter()
+        .any(|row| matches!(row.cells[index], Cell::OptInt64(_)));
+    let invalid = |value: &Cell, expected: &str| OperatorError::InvalidValue {
+        column: column.clone(),
+        value: value.to_string(),
+        expected: expected.to_string(),
+    };
+    match value {
+        value if table.schema[index].is_numeric() => match value {
+            Cell::Int64(_) | Cell::Float64(_) => Ok(value),
+            value => Err(invalid(&value, "numbers")),
+        },
+        value if optional => match value {
+            Cell::Int64(val) => Ok(Cell::OptInt64(Some(val))),
+            Cell::Null => Ok(Cell::OptInt64(None)),
+            value => Err(invalid(&value, "whole numbers")),
+        },
+        Cell::String(_) | Cell::Null => Ok(value),
+        value => Ok(Cell::String(value.to_string())),
+    }
+}
+
+/// The set of errors that can be returned when processing the [`Operator`]s.
+/// This is primarily used to display an error message when processing fails.
+#[derive(Debug)]
+pub enum OperatorError {
+    /// Encountered an error while trying to load the dataset from disk while processing the FROM
+    /// or JOIN operators.
+    CSVError {
+        /// The name of the dataset that was passed to the FROM command.
+        dataset: Dataset,
+        /// The error returned from the [`csv`] or [`parquet`] crates.
+        error: Box<dyn Error>,
+        /// The operator that was being processed when this error occurred.
+        operator: String,
+    },
+    /// The `column_name` provided to the `operator` does not exist in its input [`Table`].
+    NoSuchColumn {
+        /// The operator that was being processed when this error was thrown
+        operator: String,
+        /// The operator chain where this error was thrown.
+        chain: Box<Operator>,
+        /// Name of the column that was specified as an argument to the operator.
+        column_name: String,
+        /// The columns of the input whose names are closest to `column_name`, e.g. `CityPop` for
+        /// a misspelled `CityPpo`. See [`suggest_columns`].
+        suggestions: Vec<String>,
+    },
+    /// The `column_name` provided to the `operator` may refer to several columns of its input
+    /// [`Table`], e.g. `city.CityName` after joining the city dataset with itself.
+    AmbiguousColumn {
+        /// The operator that was being processed when this error was thrown
+        operator: String,
+        /// The operator chain where this error was thrown.
+        chain: Box<Operator>,
+        /// Name of the column that was specified as an argument to the operator.
+        column_name: String,
+        /// The names of the columns it may refer to.
+        candidates: Vec<String>,
+    },
+    /// A function called from an [`Expression`] doesn't exist or failed.
+    FunctionError {
+        /// The name of the function.
+        function: String,
+        /// Describes why the function failed.
+        message: String,
+    },
+    /// The values of an arithmetic [`Expression`] couldn't be combined, e.g. because one of them
+    /// is a string.
+    ArithmeticError {
+        /// The arithmetic expression, e.g. "CityPop/1000".
+        expression: String,
+        /// Describes why the values couldn't be combined.
+        message: String,
+    },
+    /// An aggregate function couldn't combine the values of a column.
+    AggregateError {
+        /// The aggregate being computed, e.g. "SUM(CityPop)".
+        aggregate: String,
+        /// Describes why the values couldn't be combined.
+        message: String,
+    },
+    /// A [`Dataset::Cte`] was referenced outside of the query that defines it.
+    NoSuchDataset {
+        /// The name of the dataset.
+        name: String,
+    },
+    /// A [`Dataset::Variable`] was referenced before a result was saved under its name.
+    NoSuchVariable {
+        /// The name of the variable, without the leading `@`.
+        name: String,
+    },
+    /// The FILTER operator compared a number with a string.
+    InvalidComparison {
+        /// The expression whose numeric values were compared.
+        expression: String,
+        /// The string they were compared with.
+        value: String,
+    },
+    /// The FILTER operator matched a number against a string comparison such as LIKE.
+    InvalidStringComparison {
+        /// The expression whose numeric values were matched.
+        expression: String,
+        /// The string comparison, e.g. "LIKE".
+        comparison: String,
+    },
+    /// The pattern of a MATCHES comparison isn't a valid regular expression.
+    InvalidPattern {
+        /// The pattern.
+        pattern: String,
+        /// Why the pattern is invalid.
+        message: String,
+    },
+    /// The script passed to the MAP operator couldn't be loaded, or failed on a row.
+    ScriptError {
+        /// The path of the script.
+        script: String,
+        /// Describes why the script failed.
+        message: String,
+    },
+    /// The INTO operator couldn't write the results to disk.
+    ExportError {
+        /// The path of the file that was being written.
+        path: String,
+        /// The error returned from the [`csv`] or [`arrow`] crate.
+        error: Box<dyn Error>,
+    },
+    /// The RENAME operator was asked to give a column the name of another column of its input.
+    ColumnExists {
+        /// The name of the column being renamed.
+        column_name: String,
+        /// The name of the other column.
+        new_name: String,
+    },
+    /// The EXTEND operator was asked to add a column with the name of another column.
+    ExtendColumnExists {
+        /// The name of the added column.
+        column_name: String,
+    },
+    /// The CAST operator met a value it can't convert to the requested type.
+    InvalidCast {
+        /// The name of the column being converted.
+        column_name: String,
+        /// The value that can't be converted.
+        value: String,
+        /// The type it was converted to.
+        to: CastType,
+    },
+    /// INSERT was given a row without exactly one value per column of the dataset.
+    WrongNumberOfValues {
+        /// The dataset the row was inserted into.
+        dataset: Dataset,
+        /// The number of columns of the dataset.
+        expected: usize,
+        /// The number of values in the row.
+        found: usize,
+    },
+    /// INSERT was given a value that doesn't fit the type of its column, e.g. a string for a
+    /// column of numbers.
+    InvalidValue {
+        /// The name of the column.
+        column: String,
+        /// The value, as it would be printed.
+        value: String,
+        /// Describes the values the column holds, e.g. "numbers".
+        expected: String,
+    },
+    /// The tables combined by UNION don't have the same number of columns, or a pair of their
+    /// columns hold different types of values, e.g. numbers and strings.
+    IncompatibleUnion {
+        /// The columns of the table produced by the chained operators.
+        left: Vec<String>,
+        /// The columns of the table produced by the query after UNION.
+        right: Vec<String>,
+        /// The position of the first pair of columns holding different types of values, with the
+        /// type of the left one and of the right one, or `None` if the tables don't have the same
+        /// number of columns.
+        mismatch: Option<(usize, DataType, DataType)>,
+    },
+    /// Indicates that the `column_name` passed to the ORDERBY or TOPN command is illegal as its
+    /// values are non-numeric.
+    OrderByColumnNotNumeric {
+        /// The operator that was being processed when this error was thrown.
+        operator: String,
+        /// Name of the column that was specified as an argument to the command.
+        column_name: String,
+    },
+    /// The table produced by the `operator` grew past one of the [`ResultLimits`], so the query
+    /// was stopped rather than running out of memory.
+    ResultTooLarge {
+        /// The operator whose result was too large, e.g. "JOIN country.csv CountryCode".
+        operator: String,
+        /// The limit that was exceeded, e.g. "1000 rows" or "1.0 MB".
+        limit: String,
+    },
+    /// The query was cancelled while it was processed, e.g. with Ctrl-C. See
+    /// [`ExecutionContext::cancelled`].
+    Cancelled,
+}
+
+impl Error for OperatorError {}
+
+impl Display for OperatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            OperatorError::CSVError {
+                dataset,
+                error,
+                operator,
+            } => f.write_fmt(format_args!(
+                "Failed to load the {} dataset while processing the {} command. Error encountered: {}",
+                dataset, operator, error
+            )),
+            OperatorError::NoSuchColumn {
+                operator,
+                chain,
+                column_name,
+                suggestions,
+            } => {
+                f.write_fmt(format_args!(
+                    "Could not find the {} column to {} on the table produced by this operator chain: {}",
+                    column_name, operator, chain,
+                ))?;
+                match suggestions.is_empty() {
+                    true => Ok(()),
+                    false => f.write_fmt(format_args!(
+                        ". Did you mean {}?",
+                        suggestions.join(" or ")
+                    )),
+                }
+            }
+            OperatorError::AmbiguousColumn {
+                operator,
+                chain,
+                column_name,
+                candidates,
+            } => f.write_fmt(format_args!(
+                "The {} column to {} is ambiguous, as it may refer to any of the columns [{}] of the table produced by this operator chain: {}",
+                column_name,
+                operator,
+                candidates.join(", "),
+                chain,
+            )),
+            OperatorError::FunctionError { function, message } => f.write_fmt(format_args!(
+                "Failed to evaluate the {} function: {}",
+                function, message
+            )),
+            OperatorError::ArithmeticError {
+                expression,
+                message,
+            } => f.write_fmt(format_args!("Failed to compute {}: {}", expression, message)),
+            OperatorError::AggregateError { aggregate, message } => f.write_fmt(format_args!(
+                "Failed to compute {}: {}",
+                aggregate, message
+            )),
+            OperatorError::NoSuchDataset { name } => f.write_fmt(format_args!(
+                "The {} dataset is not defined. Use WITH {} AS (<query>) to define it.",
+                name, name
+            )),
+            OperatorError::NoSuchVariable { name } => f.write_fmt(format_args!(
+                "No result is saved as @{}. Use SET {} = <query> to save one.",
+                name, name
+            )),
+            OperatorError::InvalidComparison { expression, value } => f.write_fmt(format_args!(
+                "Cannot compare the numeric values of {} with the string {}.",
+                expression, value
+            )),
+            OperatorError::InvalidStringComparison {
+                expression,
+                comparison,
+            } => f.write_fmt(format_args!(
+                "{} only applies to strings, but {} has numeric values.",
+                comparison, expression
+            )),
+            OperatorError::InvalidPattern { pattern, message } => f.write_fmt(format_args!(
+                "Invalid regular expression {}: {}",
+                pattern, message
+            )),
+            OperatorError::ScriptError { script, message } => f.write_fmt(format_args!(
+                "Failed to run the {} script: {}",
+                script, message
+            )),
+            OperatorError::ExportError { path, error } => f.write_fmt(format_args!(
+                "Failed to write the results to {}: {}",
+                path, error
+            )),
+            OperatorError::ColumnExists {
+                column_name,
+                new_name,
+            } => f.write_fmt(format_args!(
+                "Cannot RENAME {} to {}, as the table already has a {} column.",
+                column_name, new_name, new_name
+            )),
+            OperatorError::ExtendColumnExists { column_name } => f.write_fmt(format_args!(
+                "Cannot EXTEND the table with a {} column, as it already has one.",
+                column_name
+            )),
+            OperatorError::InvalidCast {
+                column_name,
+                value,
+                to,
+            } => f.write_fmt(format_args!(
+                "Cannot CAST the value {} of the {} column to {}.",
+                value, column_name, to
+            )),
+            OperatorError::WrongNumberOfValues {
+                dataset,
+                expected,
+                found,
+            } => f.write_fmt(format_args!(
+                "Cannot INSERT a row of {} values into {}, as it has {} columns.",
+                found, dataset, expected
+            )),
+            OperatorError::InvalidValue {
+                column,
+                value,
+                expected,
+            } => f.write_fmt(format_args!(
+                "Cannot INSERT {:?} into the {} column, as it holds {}.",
+                value, column, expected
+            )),
+            OperatorError::IncompatibleUnion {
+                left,
+                right,
+                mismatch: Some((index, left_type, right_type)),
+            } => f.write_fmt(format_args!(
+                "UNION can't combine the {} column, which holds {} values, with the {} column, which holds {} values. The columns are matched by their position, and must hold the same type of values.",
+                left[*index], left_type, right[*index], right_type
+            )),
+            OperatorError::IncompatibleUnion { left, right, .. } => f.write_fmt(format_args!(
+                "UNION can't combine the columns [{}] with the columns [{}]. Both tables must have the same number of columns.",
+                left.join(", "),
+                right.join(", ")
+            )),
+            OperatorError::OrderByColumnNotNumeric {
+                operator,
+                column_name,
+            } => f.write_fmt(format_args!(
+                "You attempted to {} the {} column whose type is not numeric.",
+                operator, column_name
+            )),
+            OperatorError::ResultTooLarge { operator, limit } => f.write_fmt(format_args!(
+                "The result of {} grew past the limit of {}, so the query was stopped. Narrow the query down, e.g. with FILTER, or raise the limit.",
+                operator, limit
+            )),
+            OperatorError::Cancelled => f.write_str("The query was cancelled."),
+        }
+    }
+}
+
+/// Helper function to read the requested [`Dataset`] from disk, bypassing the cache.
+///
+/// # Arguments:
+/// `dataset`: the [`Dataset`] to be read. Must not be a [`Dataset::Cte`] or a
+/// [`Dataset::Variable`].
+/// `encoding`: The encoding CSV files are written in, unless they were registered with another
+/// one. Parquet files and the built-in datasets are always UTF-8.
+/// `bad_rows`: How the rows of CSV files with too few or too many values are handled.
+/// `normalization`: How the values of CSV files are cleaned up.
+///
+/// # Returns:
+/// On success: The dataset as a [`Table`], and the line numbers of the bad rows that were skipped
+/// or filled in.
+/// On failure: The error returned from the [`csv`], [`parquet`], [`rusqlite`], or [`reqwest`]
+/// crates.
+fn read_dataset(
+    dataset: &Dataset,
+    encoding: &'static Encoding,
+    bad_rows: BadRows,
+    normalization: Normalization,
+) -> Result<(Table, Vec<u64>), Box<dyn Error>> {
+    Ok(match dataset {
+        Dataset::Parquet(path) => (load_parquet(path)?, vec![]),
+        Dataset::Registered { path, .. } if is_parquet_file(path) => (load_parquet(path)?, vec![]),
+        Dataset::Registered {
+            path,
+            encoding: Some(encoding),
+            ..
+        } => load_csv_with_options(path, encoding, bad_rows, normalization)?,
+        Dataset::Csv(path) | Dataset::Registered { path, .. } => {
+            load_csv_with_options(path, encoding, bad_rows, normalization)?
+        }
+        Dataset::Sqlite { path, table } => (load_sqlite(path, table)?, vec![]),
+        Dataset::Url(url) => {
+            let path = fetch_url(url)?;
+            match is_parquet_file(&path) {
+                true => (load_parquet(&path)?, vec![]),
+                false => load_csv_with_options(&path, encoding, bad_rows, normalization)?,
+            }
+        }
+        Dataset::Cte(_) | Dataset::Variable(_) => {
+            return Err(format!("{} is not stored on disk.", dataset).into())
+        }
+        // The built-in datasets are loaded like any other CSV file; the types inferred for their
+        // columns match the ones listed by `Dataset::numeric_columns`. They are UTF-8 files, so
+        // they are read as such whatever the encoding of the other files.
+        Dataset::City | Dataset::Country | Dataset::Language => {
+            load_csv_with_options(dataset.path().unwrap(), UTF_8, bad_rows, normalization)?
+        }
+    })
+}
+
+/// Common helper function to load the requested [`Dataset`] from the [`DatasetCache`], reading it
+/// from disk if needed, from the query-scoped catalog for a [`Dataset::Cte`], or from the saved
+/// results for a [`Dataset::Variable`]. A dataset modified with INSERT or DELETE is loaded as
+/// modified.
+///
+/// # Arguments:
+/// `dataset`: the [`Dataset`] to be laoded.
+/// `operator`: the name of the operator that called this function. Used for error reporting.
+/// `context`: The [`ExecutionContext`] holding the cache and the catalog.
+///
+/// # Returns:
+/// On success: The loaded dataset as a [`Table`], shared with the cache or the catalog.
+/// On failure: [`OperatorError::CSVError`], [`OperatorError::NoSuchDataset`],
+/// [`OperatorError::NoSuchVariable`], or other [`OperatorError`] from processing the chained
+/// operators.
+fn load_dataset(
+    dataset: &Dataset,
+    operator: &str,
+    context: &mut ExecutionContext,
+) -> Result<Arc<Table>, OperatorError> {
+    let table = match dataset {
+        Dataset::Cte(name) => context
+            .catalog
+            .get(name)
+            .cloned()
+            .ok_or(OperatorError::NoSuchDataset { name: name.clone() }),
+        Dataset::Variable(name) => context
+            .variables
+            .get(name)
+            .cloned()
+            .ok_or(OperatorError::NoSuchVariable { name: name.clone() }),
+        _ if context.edited.contains_key(dataset) => Ok(context.edited[dataset].clone()),
+        _ => {
+            let (encoding, bad_rows, normalization) = (
+                context.encoding.unwrap_or(UTF_8),
+                context.bad_rows,
+                context.normalization,
+            );
+            let reports = &mut context.bad_rows_reports;
+            context
+                .cache
+                .get_or_load(dataset, |dataset| {
+                    let (table, lines) = read_dataset(dataset, encoding, bad_rows, normalization)?;
+                    if !lines.is_empty() {
+                        reports.push(BadRowsReport {
+                            dataset: dataset.clone(),
+                            handling: bad_rows,
+                            lines,
+                        });
+                    }
+                    Ok(table)
+                })
+                .map_err(|e| OperatorError::CSVError {
+                    dataset: dataset.clone(),
+                    error: e,
+                    operator: operator.to_string(),
+                })
+        }
+    }?;
+    // The indexes over a table the dataset no longer holds, e.g. as its file was modified, are of
+    // no use anymore.
+    context.join_indexes.forget_stale(dataset, &table);
+    // The sub-queries of a WITH are computed from other datasets, which are recorded instead.
+    if let (Some(inputs), false) = (&mut context.inputs, matches!(dataset, Dataset::Cte(_))) {
+        inputs.push((dataset.clone(), table.clone()));
+    }
+    Ok(table)
+}
+
+#[test]
+fn test_load_dataset_cached() {
+    let mut context = ExecutionContext::default();
+    let first = load_dataset(&Dataset::City, "FROM", &mut context).unwrap();
+    assert_eq!(context.cache.len(), 1);
+    let second = load_dataset(&Dataset::City, "JOIN", &mut context).unwrap();
+    assert_eq!(context.cache.len(), 1);
+    assert!(Arc::ptr_eq(&first, &second));
+}
+
+#[test]
+fn test_load_dataset_variable() {
+    let mut context = ExecutionContext::default();
+    let big = Dataset::Variable("big".to_string());
+    assert_eq!(
+        load_dataset(&big, "FROM", &mut context)
+            .unwrap_err()
+            .to_string(),
+        "No result is saved as @big. Use SET big = <query> to save one."
+    );
+    let table = process_operator(
+        &Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 3,
+        },
+        &mut context,
+    )
+    .unwrap();
+    context.set_variable("big", table);
+    let first = load_dataset(&big, "FROM", &mut context).unwrap();
+    assert_eq!(first.rows.len(), 3);
+    let second = load_dataset(&big, "JOIN", &mut context).unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+    // Saved results aren't read from disk, so they aren't cached.
+    assert_eq!(context.cache.len(), 1);
+}
+
+#[test]
+fn test_insert_rows() {
+    let mut context = ExecutionContext::default();
+    let inserted = context
+        .insert_rows(
+            &Dataset::Country,
+            vec![
+                vec![
+                    Cell::String("ATL".to_string()),
+                    Cell::String("Atlantis".to_string()),
+                    Cell::String("Ocean".to_string()),
+                    Cell::Int64(1000),
+                    Cell::Null,
+                ],
+                vec![
+                    Cell::String("LMU".to_string()),
+                    Cell::String("Lemuria".to_string()),
+                    Cell::Int64(5),
+                    Cell::Float64(2.5),
+                    Cell::Int64(7),
+                ],
+            ],
+        )
+        .unwrap();
+    assert_eq!(inserted, 2);
+    let table = load_dataset(&Dataset::Country, "FROM", &mut context).unwrap();
+    assert_eq!(table.rows.len(), 241);
+    assert_eq!(table.rows[239].join(), "ATL,Atlantis,Ocean,1000,");
+    assert_eq!(table.rows[240].join(), "LMU,Lemuria,5,2.5,7");
+    assert!(matches!(table.rows[239].cells[4], Cell::OptInt64(None)));
+    assert!(matches!(table.rows[240].cells[2], Cell::String(_)));
+    assert!(matches!(table.rows[240].cells[4], Cell::OptInt64(Some(7))));
+    // The schema accounts for the inserted values.
+    assert_eq!(table.schema[2].data_type, DataType::String);
+    assert_eq!(table.schema[3].data_type, DataType::Decimal);
+    assert!(table.schema[3].is_numeric());
+    // The edits survive the cache being cleared.
+    context.clear_cache();
+    let table = load_dataset(&Dataset::Country, "FROM", &mut context).unwrap();
+    assert_eq!(table.rows.len(), 241);
+}
+
+#[test]
+fn test_insert_rows_errors() {
+    let mut context = ExecutionContext::default();
+    assert_eq!(
+        context
+            .insert_rows(&Dataset::Language, vec![vec![Cell::Int64(1)]])
+            .unwrap_err()
+            .to_string(),
+        "Cannot INSERT a row of 1 values into language.csv, as it has 2 columns."
+    );
+    assert_eq!(
+        context
+            .insert_rows(
+                &Dataset::City,
+                vec![vec![
+                    Cell::Int64(1),
+                    Cell::String("Atlantis".to_string()),
+                    Cell::String("ATL".to_string()),
+                    Cell::String("many".to_string()),
+                ]]
+            )
+            .unwrap_err()
+            .to_string(),
+        "Cannot INSERT \"many\" into the CityPop column, as it holds numbers."
+    );
+    assert_eq!(
+        context
+            .insert_rows(
+                &Dataset::Country,
+                vec![vec![
+                    Cell::String("ATL".to_string()),
+                    Cell::String("Atlantis".to_string()),
+                    Cell::String("Ocean".to_string()),
+                    Cell::Int64(1000),
+                    Cell::Float64(1.5),
+                ]]
+            )
+            .unwrap_err()
+            .to_string(),
+        "Cannot INSERT \"1.5\" into the Capital column, as it holds whole numbers."
+    );
+    // Nothing was inserted.
+    let table = load_dataset(&Dataset::Country, "FROM", &mut context).unwrap();
+    assert_eq!(table.rows.len(), 239);
+    assert!(context.edited.is_empty());
+}
+
+#[test]
+fn test_delete_rows() {
+    let mut context = ExecutionContext::default();
+    let predicate = parse_predicate("CityPop", "<", "1000").unwrap();
+    let deleted = context.delete_rows(&Dataset::City, &predicate).unwrap();
+    let table = load_dataset(&Dataset::City, "FROM", &mut context).unwrap();
+    assert!(deleted > 0);
+    assert_eq!(table.rows.len() + deleted, 4079);
+    assert!(table
+        .rows
+        .iter()
+        .all(|row| row.cells[3].as_f64().unwrap() >= 1000.0));
+    // Deleting again finds nothing left to delete.
+    assert_eq!(context.delete_rows(&Dataset::City, &predicate).unwrap(), 0);
+    assert_eq!(
+        context
+            .delete_rows(
+                &Dataset::City,
+                &parse_predicate("Population", "<", "1000").unwrap()
+            )
+            .unwrap_err()
+            .to_string(),
+        "Could not find the Population column to DELETE on the table produced by this operator chain: FROM city.csv"
+    );
+}
+
+#[test]
+fn test_delete_rows_variable() {
+    let mut context = ExecutionContext::default();
+    let table = process_operator(
+        &Operator::Take {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            count: 3,
+        },
+        &mut context,
+    )
+    .unwrap();
+    context.set_variable("some", table);
+    let some = Dataset::Variable("some".to_string());
+    let predicate = parse_predicate("CountryCode", "=", "ABW").unwrap();
+    assert_eq!(context.delete_rows(&some, &predicate).unwrap(), 3);
+    assert!(load_dataset(&some, "FROM", &mut context)
+        .unwrap()
+        .rows
+        .is_empty());
+    // The dataset the variable was computed from is unchanged.
+    assert!(context.edited.is_empty());
+}
+
+#[test]
+fn test_save_dataset() {
+    let path = std::env::temp_dir().join("toy-query-engine-save.csv");
+    let path = path.to_str().unwrap();
+    std::fs::write(path, "Name,Moons,Note\nEarth,1,home\n").unwrap();
+    let dataset = Dataset::Csv(path.to_string());
+    let mut context = ExecutionContext::default();
+    context
+        .insert_rows(
+            &dataset,
+            vec![
+                vec![
+                    Cell::String("Mars, the red planet".to_string()),
+                    Cell::Int64(2),
+                    Cell::Null,
+                ],
+                vec![
+                    Cell::String("Vulcan \"?\"".to_string()),
+                    Cell::Int64(0),
+                    Cell::String("hypothetical".to_string()),
+                ],
+            ],
+        )
+        .unwrap();
+    assert_eq!(context.save_dataset(&dataset, path).unwrap(), 3);
+    assert_eq!(
+        std::fs::read_to_string(path).unwrap(),
+        "Name,Moons,Note\nEarth,1,home\n\"Mars, the red planet\",2,\n\"Vulcan \"\"?\"\"\",0,hypothetical\n"
+    );
+    // The dataset is read back from the file it was saved to.
+    assert!(context.edited.is_empty());
+    let table = load_dataset(&dataset, "FROM", &mut context).unwrap();
+    assert_eq!(table.rows[1].cells[0].to_string(), "Mars, the red planet");
+    assert_eq!(table.rows[1].cells[2].to_string(), "");
+    assert_eq!(table.rows[2].cells[0].to_string(), "Vulcan \"?\"");
+
+    // Saving elsewhere keeps the edits.
+    let predicate = parse_predicate("Moons", "=", "1").unwrap();
+    context.delete_rows(&dataset, &predicate).unwrap();
+    let copy = std::env::temp_dir().join("toy-query-engine-save-copy.csv");
+    assert_eq!(
+        context
+            .save_dataset(&dataset, copy.to_str().unwrap())
+            .unwrap(),
+        2
+    );
+    assert_eq!(context.edited.len(), 1);
+    assert!(context
+        .save_dataset(&dataset, "/nonexistent/directory/out.csv")
+        .unwrap_err()
+        .to_string()
+        .starts_with("Failed to write the results to /nonexistent/directory/out.csv: "));
+}
+
+/// Handles the [`Operator::From`] operator by loading the requested [`Dataset`] from disk.
+/// This is just a shim around the [`load_dataset`] function, which also gives the columns their
+/// qualified names, e.g. `city.CityName`.
+///
+/// # Arguments:
+/// `dataset`: the [`Dataset`] to be laoded.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing the rows of the dataset. Rows are only copied out of the
+/// cached [`Table`] when they are asked for.
+/// On failure: [`OperatorError::CSVError`], [`OperatorError::NoSuchDataset`], or other
+/// [`OperatorError`] from processing the chained operators.
+fn process_from(
+    dataset: &Dataset,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'static>, OperatorError> {
+    let table = load_dataset(dataset, "FROM", context)?;
+    let qualified_names = dataset.qualified_names(&table.header);
+    Ok(RowStream::from(table).with_qualified_names(qualified_names))
+}
+
+#[test]
+fn test_process_from_city() {
+    let mut context = ExecutionContext::default();
+    let result =
+        process_from(&Dataset::City, &mut context).and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 4079);
+    assert_eq!(result.rows[0].cells.len(), 4);
+}
+
+#[test]
+fn test_process_from_country() {
+    let mut context = ExecutionContext::default();
+    let result = process_from(&Dataset::Country, &mut context)
+        .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 239);
+    assert_eq!(result.rows[0].cells.len(), 5);
+}
+
+#[test]
+fn test_process_from_language() {
+    let mut context = ExecutionContext::default();
+    let result = process_from(&Dataset::Language, &mut context)
+        .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 984);
+    assert_eq!(result.rows[0].cells.len(), 2);
+}
+
+#[test]
+fn test_process_from_parquet() {
+    let path = std::env::temp_dir().join("toy-query-engine-from-parquet.parquet");
+    crate::data::write_planets_parquet(&path);
+    let path = path.to_string_lossy().to_string();
+    let mut context = ExecutionContext::default();
+    for dataset in [
+        Dataset::Parquet(path.clone()),
+        Dataset::Registered {
+            name: "planets".to_string(),
+            path: path.clone(),
+            encoding: None,
+        },
+    ] {
+        let result = process_from(&dataset, &mut context)
+            .and_then(|stream| stream.collect(&mut context))
+            .unwrap();
+        assert_eq!(result.rows.len(), 3);
+        assert_eq!(result.numeric_columns(), vec!["Moons", "Radius"]);
+        assert_eq!(result.rows[1].join(), "Earth,1,6371,");
+    }
+}
+
+#[test]
+fn test_process_from_bad_rows() {
+    let path = std::env::temp_dir().join("toy-query-engine-from-bad-rows.csv");
+    std::fs::write(&path, "Name,Pop\nAruba,103000\nAnguilla\nAngola,12878000\n").unwrap();
+    let dataset = Dataset::Csv(path.to_string_lossy().to_string());
+    let mut context = ExecutionContext::default();
+    assert!(process_operator(&Operator::From(dataset.clone()), &mut context).is_err());
+    assert!(context.take_bad_rows().is_empty());
+
+    context.bad_rows = BadRows::Skip;
+    let result = process_operator(&Operator::From(dataset.clone()), &mut context).unwrap();
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(
+        context.take_bad_rows(),
+        vec![BadRowsReport {
+            dataset: dataset.clone(),
+            handling: BadRows::Skip,
+            lines: vec![3],
+        }]
+    );
+    // A cached dataset isn't reported again.
+    process_operator(&Operator::From(dataset.clone()), &mut context).unwrap();
+    assert!(context.take_bad_rows().is_empty());
+
+    context.bad_rows = BadRows::Null;
+    context.clear_cache();
+    let result = process_operator(&Operator::From(dataset), &mut context).unwrap();
+    assert_eq!(result.rows[1].join(), "Anguilla,");
+    assert_eq!(context.take_bad_rows()[0].handling, BadRows::Null);
+}
+
+#[test]
+fn test_process_from_url() {
+    let url = crate::data::serve_once("planets.csv", "200 OK", "Planet,Moons\nEarth,1\nMars,2\n");
+    let dataset = Dataset::Url(url);
+    let mut context = ExecutionContext::default();
+    let result = process_operator(&Operator::From(dataset.clone()), &mut context).unwrap();
+    assert_eq!(result.numeric_columns(), vec!["Moons"]);
+    assert_eq!(result.rows[1].join(), "Mars,2");
+    // The server only answers once, so the second query must be served from the cache.
+    let result = process_operator(&Operator::From(dataset), &mut context).unwrap();
+    assert_eq!(result.rows.len(), 2);
+}
+
+/// Helper function to compute the edit distance between two column names, i.e., the number of
+/// characters to insert, delete or replace to turn one into the other, ignoring case.
+fn edit_distance(left: &str, right: &str) -> usize {
+    let left: Vec<char> = left.to_lowercase().chars().collect();
+    let right: Vec<char> = right.to_lowercase().chars().collect();
+    // The distances between the prefixes of `left` seen so far and each prefix of `right`.
+    let mut distances: Vec<usize> = (0..=right.len()).collect();
+    for (i, left_char) in left.iter().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+        for (j, right_char) in right.iter().enumerate() {
+            let replaced = previous + usize::from(left_char != right_char);
+            previous = distances[j + 1];
+            distances[j + 1] = replaced.min(previous + 1).min(distances[j] + 1);
+        }
+    }
+    distances[right.len()]
+}
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("CityPop", "CityPop"), 0);
+    assert_eq!(edit_distance("CityPop", "citypop"), 0);
+    assert_eq!(edit_distance("CityPpo", "CityPop"), 2);
+    assert_eq!(edit_distance("CtyPop", "CityPop"), 1);
+    assert_eq!(edit_distance("CityPops", "CityPop"), 1);
+    assert_eq!(edit_distance("", "Pop"), 3);
+    assert_eq!(edit_distance("Capital", "Continent"), 7);
+}
+
+/// Helper function to suggest the columns of the `header` that a `name` not found in it may have
+/// meant, e.g. because it is misspelled.
+///
+/// # Returns
+/// The names closest to `name` by [`edit_distance`], in the order of the `header`, if they are
+/// close enough: within a third of the length of `name`, and at least one character. Empty if
+/// none are.
+fn suggest_columns(header: &[String], name: &str) -> Vec<String> {
+    let limit = (name.chars().count() / 3).max(1);
+    let distances: Vec<usize> = header
+        .iter()
+        .map(|column| edit_distance(column, name))
+        .collect();
+    let Some(closest) = distances.iter().copied().min().filter(|min| *min <= limit) else {
+        return vec![];
+    };
+    let mut suggestions: Vec<String> = Vec::new();
+    for (column, distance) in header.iter().zip(distances) {
+        if distance == closest && !suggestions.contains(column) {
+            suggestions.push(column.clone());
+        }
+    }
+    suggestions
+}
+
+#[test]
+fn test_suggest_columns() {
+    let header = ["CityID", "CityName", "CountryCode", "CityPop"].map(str::to_string);
+    assert_eq!(suggest_columns(&header, "CityPpo"), vec!["CityPop"]);
+    assert_eq!(suggest_columns(&header, "countrycod"), vec!["CountryCode"]);
+    assert_eq!(suggest_columns(&header, "CityIDs"), vec!["CityID"]);
+    assert_eq!(suggest_columns(&header, "Continent"), Vec::<String>::new());
+    assert_eq!(suggest_columns(&header, "Pop"), Vec::<String>::new());
+    // Names that are as close as each other are all suggested.
+    let header = ["Pop_1", "Pop_2", "Pop_1"].map(str::to_string);
+    assert_eq!(suggest_columns(&header, "Pop_"), vec!["Pop_1", "Pop_2"]);
+    assert!(suggest_columns(&[], "Pop").is_empty());
+}
+
+/// Helper function to find the index of the column called `name` in the `header` of a table, or
+/// whose qualified name is `name`, e.g. `city.CityName`. Only if there is neither, a column whose
+/// name only differs from `name` by case is found instead, e.g. `CityPop` for `citypop`.
+///
+/// # Arguments:
+/// 'header' : The names of the columns of the table to find the column in.
+/// 'qualified_names' : The qualified names of the columns of the table. See
+/// [`Table::qualified_names`].
+/// 'name' : The name, or the qualified name, of the column whose index is to be returned.
+/// 'chain' : The chain on operators that produced this table (used to construct the error message
+/// if the column doesn't exist in the table).
+/// 'current_operator': The operator calling this function.
+///
+/// # Returns:
+/// Ok([`usize`]) for the index of the column named `name` in the `header`. Only if there is none,
+/// the index of the column whose qualified name is `name`, and then the index of the column whose
+/// name is `name` in a different case.
+/// Err([`OperatorError::AmbiguousColumn`]) if several columns are named `name`, e.g. two columns
+/// of a dataset joined with itself share the qualified name.
+/// Err([`OperatorError::NoSuchColumn`]) if `name` is not found in the `header`, with the columns
+/// it may have meant as suggestions.
+fn find_column_index(
+    header: &[String],
+    qualified_names: &[Option<String>],
+    name: &str,
+    chain: &Operator,
+    current_operator: &str,
+) -> Result<usize, OperatorError> {
+    let mut matches: Vec<usize> = (0..header.len())
+        .filter(|index| header[*index] == name)
+        .collect();
+    if matches.is_empty() {
+        matches = (0..qualified_names.len())
+            .filter(|index| qualified_names[*index].as_deref() == Some(name))
+            .collect();
+    }
+    if matches.is_empty() {
+        matches = (0..header.len())
+            .filter(|index| header[*index].to_lowercase() == name.to_lowercase())
+            .collect();
+    }
+    match matches[..] {
+        [index] => Ok(index),
+        [] => {
+            // The requested column doesn't exist in the table.
+            Err(OperatorError::NoSuchColumn {
+                operator: current_operator.to_string(),
+                chain: Box::new(chain.clone()),
+                column_name: name.to_string(),
+                suggestions: suggest_columns(header, name),
+            })
+        }
+        _ => Err(OperatorError::AmbiguousColumn {
+            operator: current_operator.to_string(),
+            chain: Box::new(chain.clone()),
+            column_name: name.to_string(),
+            candidates: matches.iter().map(|index| header[*index].clone()).collect(),
+        }),
+    }
+}
+
+/// Helper function to return the name an output column refers to the column at `index` of the
+/// `header` by: the name of that column if `name` only differs from it in case, e.g. `CityPop`
+/// for `citypop`, and `name` itself otherwise, e.g. a qualified name like `city.CityPop`.
+fn resolved_name<'a>(header: &'a [String], index: usize, name: &'a str) -> &'a str {
+    match header[index].to_lowercase() == name.to_lowercase() {
+        true => &header[index],
+        false => name,
+    }
+}
+
+/// Test find_column_index for names that do exist in the table.
+#[test]
+fn test_find_column_index_exists() {
+    let table = Table::new(
+        vec![
+            "H1".to_string(),
+            "H2".to_string(),
+            "H3".to_string(),
+            "H4".to_string(),
+        ],
+        vec![],
+    );
+
+    let operator = Box::new(Operator::From(Dataset::Language));
+    assert!(find_column_index(&table.header, &[], "H1", &operator, "TEST").is_ok());
+    assert!(find_column_index(&table.header, &[], "H2", &operator, "TEST").is_ok());
+    assert!(find_column_index(&table.header, &[], "H3", &operator, "TEST").is_ok());
+    assert!(find_column_index(&table.header, &[], "H4", &operator, "TEST").is_ok());
+}
+
+/// Test find_column_index_by_name for names that do not exist in the table.
+#[test]
+fn test_find_column_index_does_not_exist() {
+    let table = Table::new(
+        vec![
+            "H1".to_string(),
+            "H2".to_string(),
+            "H3".to_string(),
+            "H4".to_string(),
+        ],
+        vec![],
+    );
+    let operator = Box::new(Operator::From(Dataset::Language));
+    assert!(find_column_index(&table.header, &[], "H", &operator, "TEST").is_err());
+    assert!(find_column_index(&table.header, &[], "H12", &operator, "TEST").is_err());
+    assert!(find_column_index(&table.header, &[], "H31", &operator, "TEST").is_err());
+    assert!(find_column_index(&table.header, &[], "H42", &operator, "TEST").is_err());
+}
+
+/// Test find_column_index_by_name for names that do not exist in the table.
+#[test]
+fn test_find_column_index_empty_table() {
+    let table = Table::new(vec![], vec![]);
+    let operator = Box::new(Operator::From(Dataset::Language));
+    assert!(find_column_index(&table.header, &[], "H", &operator, "TEST").is_err());
+    assert!(find_column_index(&table.header, &[], "H12", &operator, "TEST").is_err());
+    assert!(find_column_index(&table.header, &[], "H31", &operator, "TEST").is_err());
+    assert!(find_column_index(&table.header, &[], "H42", &operator, "TEST").is_err());
+}
+
+/// Test find_column_index for qualified names, and for names of several columns.
+#[test]
+fn test_find_column_index_qualified() {
+    let header = ["CityName", "CityPop", "CityName_2", "CityPop"].map(str::to_string);
+    let qualified_names = ["city.CityName", "city.CityPop", "city.CityName"]
+        .map(|name| Some(name.to_string()))
+        .to_vec();
+    let operator = Operator::From(Dataset::City);
+    let find = |name| find_column_index(&header, &qualified_names, name, &operator, "TEST");
+    assert_eq!(find("CityName").unwrap(), 0);
+    assert_eq!(find("CityName_2").unwrap(), 2);
+    assert_eq!(find("city.CityPop").unwrap(), 1);
+    assert_eq!(
+        find("city.CityName").unwrap_err().to_string(),
+        "The city.CityName column to TEST is ambiguous, as it may refer to any of the columns [CityName, CityName_2] of the table produced by this operator chain: FROM city.csv"
+    );
+    assert_eq!(
+        find("CityPop").unwrap_err().to_string(),
+        "The CityPop column to TEST is ambiguous, as it may refer to any of the columns [CityPop, CityPop] of the table produced by this operator chain: FROM city.csv"
+    );
+    assert!(matches!(
+        find("country.CityName"),
+        Err(OperatorError::NoSuchColumn { .. })
+    ));
+}
+
+/// Test find_column_index for names that only differ from a column's by case, and the columns
+/// suggested for names that aren't found.
+#[test]
+fn test_find_column_index_case_insensitive() {
+    let header = ["CityName", "CityPop", "citypop", "Pop"].map(str::to_string);
+    let operator = Operator::From(Dataset::City);
+    let find = |name| find_column_index(&header, &[], name, &operator, "TEST");
+    assert_eq!(find("cityname").unwrap(), 0);
+    assert_eq!(find("CITYNAME").unwrap(), 0);
+    // An exact match is preferred.
+    assert_eq!(find("citypop").unwrap(), 2);
+    assert_eq!(
+        find("CITYPOP").unwrap_err().to_string(),
+        "The CITYPOP column to TEST is ambiguous, as it may refer to any of the columns [CityPop, citypop] of the table produced by this operator chain: FROM city.csv"
+    );
+    assert_eq!(
+        find("CityNme").unwrap_err().to_string(),
+        "Could not find the CityNme column to TEST on the table produced by this operator chain: FROM city.csv. Did you mean CityName?"
+    );
+    assert_eq!(
+        find("CtyPop").unwrap_err().to_string(),
+        "Could not find the CtyPop column to TEST on the table produced by this operator chain: FROM city.csv. Did you mean CityPop or citypop?"
+    );
+    assert_eq!(
+        find("Region").unwrap_err().to_string(),
+        "Could not find the Region column to TEST on the table produced by this operator chain: FROM city.csv"
+    );
+}
+
+#[test]
+fn test_resolved_name() {
+    let header = ["CityName", "CityPop"].map(str::to_string);
+    assert_eq!(resolved_name(&header, 1, "citypop"), "CityPop");
+    assert_eq!(resolved_name(&header, 1, "CityPop"), "CityPop");
+    assert_eq!(resolved_name(&header, 0, "city.cityName"), "city.cityName");
+}
+
+/// Handles the [`Operator::Select`] operator by processing the [`Operator`] chain and selecting the
 /// requested column(s) from the resulting [`Table`].
 ///
 /// # Arguments:
 /// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
 /// this operator.
-/// `column_names`: Names of one or more columns to select from the output of the `chain`.
+/// `columns`: One or more expressions computing the columns to select from the output of the
+/// `chain`, each optionally renamed with `AS`. Usually these are just the names of columns.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing only the requested columns of each input row.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::FunctionError`], or other
+/// [`OperatorError`] from processing the chained operators.
+fn process_select<'a>(
+    chain: &'a Operator,
+    columns: &'a [SelectColumn],
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
+
+    // Make sure all the columns referenced by the `columns` exist, and refer to them by their
+    // names in the input, rather than by their qualified names.
+    let mut expressions = Vec::with_capacity(columns.len());
+    let mut header = Vec::with_capacity(columns.len());
+    let mut qualified_names = Vec::with_capacity(columns.len());
+    for column in columns {
+        let mut indices = HashMap::new();
+        for name in column.expression.column_names() {
+            // This can throw the [`OperatorError::NoSuchColumn`] error.
+            let index =
+                find_column_index(&input.header, &input.qualified_names, name, chain, "Select")?;
+            indices.insert(name.to_string(), index);
+        }
+        expressions.push(
+            column
+                .expression
+                .rename_columns(&|name| input.header[indices[name]].clone()),
+        );
+        // Unless aliased, the columns are named after their expression, with the names of the
+        // columns it reads spelled as in the input.
+        header.push(match &column.alias {
+            Some(alias) => alias.clone(),
+            None => column
+                .expression
+                .rename_columns(&|name| {
+                    resolved_name(&input.header, indices[name], name).to_string()
+                })
+                .to_string(),
+        });
+        // Selected columns keep their qualified names.
+        qualified_names.push(match &column.expression {
+            Expression::Column(name) => input.qualified_names.get(indices[name]).cloned().flatten(),
+            _ => None,
+        });
+    }
+
+    Ok(RowStream::new(
+        header.clone(),
+        SelectRows {
+            input,
+            computed: header
+                .iter()
+                .map(|name| ColumnSchema::empty(name))
+                .collect(),
+            expressions,
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
+
+/// Produces the rows of the [`Operator::Select`] operator by computing the selected columns of
+/// each input row as it is pulled.
+struct SelectRows<'a> {
+    input: RowStream<'a>,
+    /// The expressions of the `columns`, referring to the columns by their names in the input.
+    expressions: Vec<Expression>,
+    /// The schema of each of the `columns`, inferred from the values computed so far.
+    computed: Vec<ColumnSchema>,
+}
+
+impl RowSource for SelectRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        let Some(row) = self.input.next_row(context)? else {
+            return Ok(None);
+        };
+        let cells = self
+            .expressions
+            .iter()
+            .map(|expression| expression.evaluate(&self.input.header, &row, context))
+            .collect::<Result<Vec<Cell>, OperatorError>>()?;
+        for (schema, cell) in self.computed.iter_mut().zip(&cells) {
+            schema.observe(cell);
+        }
+        Ok(Some(Row { cells }))
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        // Selected columns keep their schema in the input table, while the schema of computed
+        // columns is inferred from their values.
+        let input_schema = self.input.schema();
+        self.expressions
+            .iter()
+            .zip(&self.computed)
+            .map(|(expression, computed)| match expression {
+                Expression::Column(name) => input_schema
+                    .iter()
+                    .find(|column| column.name == *name)
+                    .map_or_else(|| computed.clone(), |column| column.renamed(&computed.name)),
+                _ => computed.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Handles the [`Operator::SelectAll`] operator by processing the [`Operator`] chain and dropping
+/// the `except` columns from the resulting [`Table`], if any.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `except`: The names of the columns to drop. Empty to keep all of them.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing the other columns of each input row, in their order.
+/// On failure: [`OperatorError::NoSuchColumn`] if a column to drop isn't found, or other
+/// [`OperatorError`] from processing the chained operators.
+fn process_select_all<'a>(
+    chain: &'a Operator,
+    except: &[String],
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
+    if except.is_empty() {
+        return Ok(input);
+    }
+
+    // Make sure all the columns to drop exist.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let dropped = except
+        .iter()
+        .map(|name| find_column_index(&input.header, &input.qualified_names, name, chain, "Select"))
+        .collect::<Result<Vec<usize>, OperatorError>>()?;
+    let kept: Vec<usize> = (0..input.header.len())
+        .filter(|index| !dropped.contains(index))
+        .collect();
+
+    let qualified_names = kept
+        .iter()
+        .map(|index| input.qualified_names.get(*index).cloned().flatten())
+        .collect();
+    Ok(RowStream::new(
+        kept.iter()
+            .map(|index| input.header[*index].clone())
+            .collect(),
+        ExceptRows { input, kept },
+    )
+    .with_qualified_names(qualified_names))
+}
+
+/// Produces the rows of the [`Operator::SelectAll`] operator, without the dropped columns.
+struct ExceptRows<'a> {
+    input: RowStream<'a>,
+    /// The indices of the kept columns in the input.
+    kept: Vec<usize>,
+}
+
+impl RowSource for ExceptRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        let Some(mut row) = self.input.next_row(context)? else {
+            return Ok(None);
+        };
+        let mut index = 0;
+        row.cells.retain(|_| {
+            index += 1;
+            self.kept.contains(&(index - 1))
+        });
+        Ok(Some(row))
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        let schema = self.input.schema();
+        self.kept
+            .iter()
+            .map(|index| schema[*index].clone())
+            .collect()
+    }
+}
+
+#[test]
+fn test_process_select_all() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        count: 2,
+    };
+    let result = process_select_all(&chain, &[], &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.header.len(), 5);
+    assert_eq!(result.rows[0].join(), "ABW,Aruba,North_America,103000,129");
+
+    let except = ["Capital".to_string(), "CountryName".to_string()];
+    let result = process_select_all(&chain, &except, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(
+        result.header,
+        vec!["CountryCode", "Continent", "CountryPop"]
+    );
+    assert_eq!(result.numeric_columns(), vec!["CountryPop"]);
+    assert_eq!(result.rows[1].join(), "AFG,Asia,22720000");
+
+    assert_eq!(
+        process_select_all(&chain, &["Region".to_string()], &mut context)
+            .err()
+            .unwrap()
+            .to_string(),
+        "Could not find the Region column to Select on the table produced by this operator chain: FROM country.csv TAKE 2"
+    );
+}
+
+#[test]
+fn test_process_select_single() {
+    let mut context = ExecutionContext::default();
+    let result = process_select(
+        &Box::new(Operator::From(Dataset::Language)),
+        &[Expression::Column("Language".to_string()).into()],
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 984);
+    assert_eq!(result.header.len(), 1);
+    assert_eq!(result.header[0], "Language".to_string());
+    assert_eq!(result.rows[0].cells.len(), 1);
+}
+
+#[test]
+fn test_process_select_single_non_existant_col() {
+    let mut context = ExecutionContext::default();
+    let result = process_select(
+        &Box::new(Operator::From(Dataset::Language)),
+        &[Expression::Column("Capital".to_string()).into()],
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_err());
+    let err = result.unwrap_err();
+    assert_eq!(err.to_string(), "Could not find the Capital column to Select on the table produced by this operator chain: FROM language.csv".to_string())
+}
+
+#[test]
+fn test_process_select_multiple() {
+    let mut context = ExecutionContext::default();
+    let result = process_select(
+        &Box::new(Operator::From(Dataset::City)),
+        &[
+            Expression::Column("CityID".to_string()).into(),
+            Expression::Column("CityName".to_string()).into(),
+        ],
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 4079);
+    assert_eq!(result.header.len(), 2);
+    assert_eq!(
+        result.header,
+        vec!["CityID".to_string(), "CityName".to_string()]
+    );
+    assert_eq!(result.rows[0].cells.len(), 2);
+}
+
+#[test]
+fn test_process_select_alias() {
+    let mut context = ExecutionContext::default();
+    let result = process_select(
+        &Box::new(Operator::From(Dataset::City)),
+        &crate::expression::parse_select_list("CityName AS Name,CityPop AS Population").unwrap(),
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context))
+    .unwrap();
+    assert_eq!(result.header, vec!["Name", "Population"]);
+    assert_eq!(result.numeric_columns(), vec!["Population"]);
+
+    // Later operators refer to the columns by their aliases.
+    let select = Operator::Select {
+        chain: Box::new(Operator::From(Dataset::City)),
+        columns: crate::expression::parse_select_list("CityPop AS Population").unwrap(),
+    };
+    let result = process_orderby(
+        &select,
+        "Population".to_string(),
+        SortOrder::Descending,
+        NullsOrder::Last,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    assert_eq!(result.rows[0].cells, vec![Cell::Int64(10500000)]);
+}
+
+/// Handles the [`Operator::Extend`] operator by processing the [`Operator`] chain and appending
+/// the computed columns to each row of the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `columns`: The names of the columns to append, and the expressions computing their values from
+/// the output of the `chain`. The expressions are evaluated like those of [`Operator::Select`].
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing all the columns of each input row, followed by the
+/// computed ones.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::ExtendColumnExists`] if a new
+/// column is named like another column, [`OperatorError::FunctionError`], or other
+/// [`OperatorError`] from processing the chained operators.
+fn process_extend<'a>(
+    chain: &'a Operator,
+    columns: &'a [(String, Expression)],
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
+
+    // Make sure all the columns referenced by the expressions exist, and refer to them by their
+    // names in the input, rather than by their qualified names.
+    let mut header = input.header.clone();
+    let mut expressions = Vec::with_capacity(columns.len());
+    for (name, expression) in columns {
+        let mut indices = HashMap::new();
+        for column in expression.column_names() {
+            // This can throw the [`OperatorError::NoSuchColumn`] error.
+            let index = find_column_index(
+                &input.header,
+                &input.qualified_names,
+                column,
+                chain,
+                "EXTEND",
+            )?;
+            indices.insert(column.to_string(), index);
+        }
+        if header.contains(name) {
+            return Err(OperatorError::ExtendColumnExists {
+                column_name: name.clone(),
+            });
+        }
+        header.push(name.clone());
+        expressions
+            .push(expression.rename_columns(&|column| input.header[indices[column]].clone()));
+    }
+
+    // The appended columns have no qualified names.
+    let qualified_names = input.qualified_names.clone();
+    Ok(RowStream::new(
+        header,
+        ExtendRows {
+            input,
+            computed: columns
+                .iter()
+                .map(|(name, _)| ColumnSchema::empty(name))
+                .collect(),
+            expressions,
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
+
+/// Produces the rows of the [`Operator::Extend`] operator by appending the computed columns to
+/// each input row as it is pulled.
+struct ExtendRows<'a> {
+    input: RowStream<'a>,
+    /// The expressions of the computed columns, referring to the columns by their names in the
+    /// input.
+    expressions: Vec<Expression>,
+    /// The schema of each of the computed columns, inferred from the values computed so far.
+    computed: Vec<ColumnSchema>,
+}
+
+impl RowSource for ExtendRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        let Some(mut row) = self.input.next_row(context)? else {
+            return Ok(None);
+        };
+        for (expression, schema) in self.expressions.iter().zip(self.computed.iter_mut()) {
+            let cell = expression.evaluate(&self.input.header, &row, context)?;
+            schema.observe(&cell);
+            row.cells.push(cell);
+        }
+        Ok(Some(row))
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        // The schema of the computed columns is like that of SELECT: a copied column keeps its
+        // schema in the input table, and the schema of any other is inferred from its values.
+        let input_schema = self.input.schema();
+        let computed = self
+            .expressions
+            .iter()
+            .zip(&self.computed)
+            .map(|(expression, computed)| match expression {
+                Expression::Column(name) => input_schema
+                    .iter()
+                    .find(|column| column.name == *name)
+                    .map_or_else(|| computed.clone(), |column| column.renamed(&computed.name)),
+                _ => computed.clone(),
+            })
+            .collect::<Vec<ColumnSchema>>();
+        input_schema.into_iter().chain(computed).collect()
+    }
+}
+
+#[test]
+fn test_process_extend() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 2,
+    };
+    let columns = crate::expression::parse_extend_list(
+        "PopDensity = CityPop / 1000,Name = UPPER(CityName),Pop = CityPop",
+    )
+    .unwrap();
+    let result = process_extend(&chain, &columns, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(
+        result.header,
+        vec![
+            "CityID",
+            "CityName",
+            "CountryCode",
+            "CityPop",
+            "PopDensity",
+            "Name",
+            "Pop"
+        ]
+    );
+    assert_eq!(
+        result.numeric_columns(),
+        vec!["CityID", "CityPop", "PopDensity", "Pop"]
+    );
+    assert_eq!(
+        result.rows[0].join(),
+        "1,Kabul,AFG,1780000,1780,KABUL,1780000"
+    );
+    assert_eq!(result.rows.len(), 2);
+}
+
+#[test]
+fn test_process_extend_errors() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::From(Dataset::City);
+    let mut error = |text: &str| {
+        let columns = crate::expression::parse_extend_list(text).unwrap();
+        process_extend(&chain, &columns, &mut context)
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        error("CityPop = CityPop * 2"),
+        "Cannot EXTEND the table with a CityPop column, as it already has one."
+    );
+    assert_eq!(
+        error("A = 1,A = 2"),
+        "Cannot EXTEND the table with a A column, as it already has one."
+    );
+    // The expressions can't refer to the other new columns.
+    assert_eq!(
+        error("A = CityPop,B = A"),
+        "Could not find the A column to EXTEND on the table produced by this operator chain: FROM city.csv"
+    );
+}
+
+/// Handles the [`Operator::Take`] operator by processing the [`Operator`] chain and selecting the
+/// first `count` column(s) from the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `count`: Number of rows to retain in the output. If `count` is greater than the number of rows
+/// in the input table, all rows in the input table will be returned.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing only the requested number of rows. Once it has produced
+/// them, no more rows are pulled from the chained operators.
+/// On failure: [`OperatorError`] from processing the chained operators.
+fn process_take<'a>(
+    chain: &'a Operator,
+    count: usize,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
+
+    let qualified_names = input.qualified_names.clone();
+    Ok(RowStream::new(
+        input.header.clone(),
+        TakeRows {
+            input,
+            remaining: count,
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
+
+/// Produces the rows of the [`Operator::Take`] operator.
+struct TakeRows<'a> {
+    input: RowStream<'a>,
+    /// The number of rows still to be produced.
+    remaining: usize,
+}
+
+impl RowSource for TakeRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        // Stop pulling rows from the input once there are enough.
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        let row = self.input.next_row(context)?;
+        if row.is_some() {
+            self.remaining -= 1;
+        }
+        Ok(row)
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.input.schema()
+    }
+}
+
+#[test]
+fn test_process_take() {
+    let mut context = ExecutionContext::default();
+    let result = process_take(
+        &Box::new(Operator::From(Dataset::Language)),
+        5,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 5);
+    assert_eq!(result.header.len(), 2);
+    assert_eq!(
+        result.header,
+        vec!["CountryCode".to_string(), "Language".to_string()]
+    );
+    assert_eq!(result.numeric_columns().len(), 0);
+}
+
+#[test]
+fn test_process_take_from_empty_table() {
+    let mut context = ExecutionContext::default();
+    let result = process_take(
+        &Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            count: 0,
+        }),
+        5,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 0);
+    assert_eq!(result.header.len(), 2);
+    assert_eq!(
+        result.header,
+        vec!["CountryCode".to_string(), "Language".to_string()]
+    );
+    assert_eq!(result.numeric_columns().len(), 0);
+}
+
+#[test]
+fn test_process_take_more_than_rows_in_data() {
+    let mut context = ExecutionContext::default();
+    let result = process_take(
+        &Box::new(Operator::From(Dataset::Language)),
+        10000,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 984);
+    assert_eq!(result.header.len(), 2);
+    assert_eq!(
+        result.header,
+        vec!["CountryCode".to_string(), "Language".to_string()]
+    );
+    assert_eq!(result.numeric_columns().len(), 0);
+}
+
+/// Handles the [`Operator::Tail`] operator by processing the [`Operator`] chain and keeping the
+/// last `count` rows of the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `count`: Number of rows to keep from the end of the input. If `count` is greater than the
+/// number of rows in the input table, all of them will be returned.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] containing the last `count` rows, in their original order.
+/// On failure: [`OperatorError`] from processing the chained operators.
+fn process_tail(
+    chain: &Operator,
+    count: usize,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let mut input = open_operator(chain, context)?;
+
+    // Only the last `count` rows pulled so far are kept, so the input is never held in memory.
+    let mut rows = VecDeque::with_capacity(count);
+    while let Some(row) = input.next_row(context)? {
+        if count == 0 {
+            continue;
+        }
+        if rows.len() == count {
+            rows.pop_front();
+        }
+        rows.push_back(row);
+    }
+
+    Ok(Table {
+        schema: input.schema(),
+        header: input.header,
+        rows: rows.into(),
+        qualified_names: input.qualified_names,
+    })
+}
+
+/// Handles the [`Operator::Sample`] operator by processing the [`Operator`] chain and keeping a
+/// uniform random subset of the rows of the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `size`: How many rows to keep. See [`SampleSize`].
+/// `seed`: The seed of the random number generator, to keep the same rows for the same input. The
+/// generator is seeded from the operating system when `None`.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] containing the sampled rows, in their original order.
+/// On failure: [`OperatorError`] from processing the chained operators.
+fn process_sample(
+    chain: &Operator,
+    size: SampleSize,
+    seed: Option<u64>,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+    let total = table.rows.len();
+    let mut picked = rand::seq::index::sample(&mut rng, total, size.rows_of(total)).into_vec();
+    picked.sort_unstable();
+
+    // Move the picked rows out of the table, in order, rather than cloning them.
+    let mut picked = picked.into_iter().peekable();
+    let rows = table
+        .rows
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, row)| picked.next_if_eq(&index).map(|_| row))
+        .collect();
+    Ok(Table {
+        header: table.header,
+        schema: table.schema,
+        rows,
+        qualified_names: table.qualified_names,
+    })
+}
+
+#[test]
+fn test_process_sample() {
+    let mut context = ExecutionContext::default();
+    let all: Vec<String> = process_operator(&Operator::From(Dataset::City), &mut context)
+        .unwrap()
+        .rows
+        .iter()
+        .map(|row| row.join())
+        .collect();
+    let sample = |size, seed, context: &mut ExecutionContext| -> Vec<String> {
+        let result = process_sample(&Operator::From(Dataset::City), size, seed, context).unwrap();
+        assert_eq!(result.header.len(), 4);
+        result.rows.iter().map(|row| row.join()).collect()
+    };
+
+    let result = sample(SampleSize::Rows(10), Some(42), &mut context);
+    assert_eq!(result.len(), 10);
+    // The rows are taken from the input, in their original order.
+    let positions: Vec<usize> = result
+        .iter()
+        .map(|row| all.iter().position(|other| other == row).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    // The same seed picks the same rows.
+    assert_eq!(sample(SampleSize::Rows(10), Some(42), &mut context), result);
+    assert_ne!(sample(SampleSize::Rows(10), Some(7), &mut context), result);
+
+    assert_eq!(
+        sample(SampleSize::Percent(10.0), None, &mut context).len(),
+        408
+    );
+    assert_eq!(sample(SampleSize::Rows(5000), None, &mut context), all);
+    assert!(sample(SampleSize::Rows(0), None, &mut context).is_empty());
+}
+
+#[test]
+fn test_process_tail() {
+    let mut context = ExecutionContext::default();
+    let result = process_tail(&Operator::From(Dataset::Language), 3, &mut context).unwrap();
+    let all = process_operator(&Operator::From(Dataset::Language), &mut context).unwrap();
+    assert_eq!(
+        result.header,
+        vec!["CountryCode".to_string(), "Language".to_string()]
+    );
+    let tail: Vec<String> = result.rows.iter().map(|row| row.join()).collect();
+    let expected: Vec<String> = all.rows[981..].iter().map(|row| row.join()).collect();
+    assert_eq!(tail, expected);
+
+    // The rows at the other end of an ORDERBY are the smallest ones, smallest last.
+    let result = process_tail(
+        &Operator::OrderBy {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CityPop".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::Last,
+        },
+        2,
+        &mut context,
+    )
+    .unwrap();
+    let smallest = process_take(
+        &Operator::OrderBy {
+            chain: Box::new(Operator::From(Dataset::City)),
+            column: "CityPop".to_string(),
+            order: SortOrder::Ascending,
+            nulls: NullsOrder::Last,
+        },
+        2,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context))
+    .unwrap();
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[0].join(), smallest.rows[1].join());
+    assert_eq!(result.numeric_columns(), smallest.numeric_columns());
+}
+
+#[test]
+fn test_process_tail_more_than_rows_in_data() {
+    let mut context = ExecutionContext::default();
+    let result = process_tail(&Operator::From(Dataset::Language), 10000, &mut context).unwrap();
+    assert_eq!(result.rows.len(), 984);
+    let result = process_tail(&Operator::From(Dataset::Language), 0, &mut context).unwrap();
+    assert!(result.rows.is_empty());
+    assert_eq!(result.header.len(), 2);
+}
+
+/// Handles the [`Operator::Skip`] operator by processing the [`Operator`] chain and dropping the
+/// first `count` rows from the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `count`: Number of rows to drop from the start of the input. If `count` is greater than the
+/// number of rows in the input table, no rows will be returned.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing the rows after the first `count`.
+/// On failure: [`OperatorError`] from processing the chained operators.
+fn process_skip<'a>(
+    chain: &'a Operator,
+    count: usize,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
+
+    let qualified_names = input.qualified_names.clone();
+    Ok(RowStream::new(
+        input.header.clone(),
+        SkipRows {
+            input,
+            to_skip: count,
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
+
+/// Produces the rows of the [`Operator::Skip`] operator.
+struct SkipRows<'a> {
+    input: RowStream<'a>,
+    /// The number of rows still to be dropped before rows are produced.
+    to_skip: usize,
+}
+
+impl RowSource for SkipRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        while self.to_skip > 0 {
+            self.to_skip -= 1;
+            if self.input.next_row(context)?.is_none() {
+                return Ok(None);
+            }
+        }
+        self.input.next_row(context)
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.input.schema()
+    }
+}
+
+#[test]
+fn test_process_skip() {
+    let mut context = ExecutionContext::default();
+    let result = process_skip(
+        &Box::new(Operator::From(Dataset::Language)),
+        980,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context))
+    .unwrap();
+    assert_eq!(result.rows.len(), 4);
+    assert_eq!(
+        result.header,
+        vec!["CountryCode".to_string(), "Language".to_string()]
+    );
+
+    // Skipping then taking returns the next page of rows.
+    let all = process_take(&Box::new(Operator::From(Dataset::City)), 6, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    let page = process_take(
+        &Box::new(Operator::Skip {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 3,
+        }),
+        3,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context))
+    .unwrap();
+    let page: Vec<String> = page.rows.iter().map(|row| row.join()).collect();
+    let expected: Vec<String> = all.rows[3..].iter().map(|row| row.join()).collect();
+    assert_eq!(page, expected);
+}
+
+#[test]
+fn test_process_skip_more_than_rows_in_data() {
+    let mut context = ExecutionContext::default();
+    let result = process_skip(
+        &Box::new(Operator::From(Dataset::Language)),
+        10000,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context))
+    .unwrap();
+    assert!(result.rows.is_empty());
+    assert_eq!(result.header.len(), 2);
+}
+
+/// Helper function to check that the rows of the `table` can be sorted on the `col_index` column,
+/// i.e., that its values are all numbers or missing. Both the type of the column and its values
+/// are checked, so a value the type doesn't account for fails the query instead of the sort.
+///
+/// # Arguments
+/// `table` : The table to sort.
+/// `col_index` : The index of the column to sort on.
+/// `operator` : The operator sorting the table, e.g. "ORDERBY", for the error.
+/// `column` : The name of the column to sort on, as given to the operator, for the error.
+///
+/// # Returns
+/// [`OperatorError::OrderByColumnNotNumeric`] if the column holds a value that isn't a number.
+fn check_sortable(
+    table: &Table,
+    col_index: usize,
+    operator: &str,
+    column: &str,
+) -> Result<(), OperatorError> {
+    let sortable = table.schema[col_index].is_sortable()
+        && table.rows.iter().all(|row| {
+            let value = &row.cells[col_index];
+            value.is_null() || value.as_f64().is_some()
+        });
+    match sortable {
+        true => Ok(()),
+        false => Err(OperatorError::OrderByColumnNotNumeric {
+            operator: operator.to_string(),
+            column_name: column.to_string(),
+        }),
+    }
+}
+
+#[test]
+fn test_check_sortable() {
+    let table = Table::new(
+        vec!["Capital".to_string()],
+        vec![
+            Row {
+                cells: vec![Cell::OptInt64(Some(129))],
+            },
+            Row {
+                cells: vec![Cell::OptInt64(None)],
+            },
+            Row {
+                cells: vec![Cell::Float64(1.5)],
+            },
+        ],
+    );
+    assert!(check_sortable(&table, 0, "ORDERBY", "Capital").is_ok());
+
+    // A string the schema doesn't account for is an error rather than a panic while sorting.
+    let mut mislabeled = table.clone();
+    mislabeled.rows.push(Row {
+        cells: vec![Cell::String("Oranjestad".to_string())],
+    });
+    assert_eq!(
+        check_sortable(&mislabeled, 0, "TOPN", "Capital")
+            .unwrap_err()
+            .to_string(),
+        "You attempted to TOPN the Capital column whose type is not numeric."
+    );
+}
+
+/// Helper function to compare two values of a numeric column in the given `order`, placing missing
+/// values first or last in either order, as `nulls` says.
+/// # Usage Note: The caller must check the values with [`check_sortable`] first.
+fn cmp_sorted(a: &Cell, b: &Cell, order: SortOrder, nulls: NullsOrder) -> Ordering {
+    let missing_first = match nulls {
+        NullsOrder::First => Ordering::Less,
+        NullsOrder::Last => Ordering::Greater,
+    };
+    match (a.is_null(), b.is_null()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return missing_first,
+        (false, true) => return missing_first.reverse(),
+        (false, false) => (),
+    }
+    // Numbers always compare, with or without missing values ([`Cell::OptInt64`]);
+    // `check_sortable` has rejected the columns holding anything else.
+    let ordering = a.cmp_numeric(b).unwrap_or(Ordering::Equal);
+    match order {
+        SortOrder::Ascending => ordering,
+        SortOrder::Descending => ordering.reverse(),
+    }
+}
+
+/// Helper function to sort the input 'rows' on the `col_index` column in the given `order`, with
+/// the rows missing a value where `nulls` says. The sort is stable, and runs on all the available
+/// cores.
+/// # Usage Note: The caller must guarantee that the col_index exists in the table, and check its
+/// values with [`check_sortable`].
+fn sort_table(rows: &mut [Row], col_index: usize, order: SortOrder, nulls: NullsOrder) {
+    rows.par_sort_by(|a: &Row, b: &Row| {
+        cmp_sorted(&a.cells[col_index], &b.cells[col_index], order, nulls)
+    });
+}
+
+#[test]
+fn test_sort_table_stable() {
+    // Large enough for the sort to be split across threads; rows with equal values must keep
+    // their original order.
+    let mut rows: Vec<Row> = (0..50000)
+        .map(|id| Row {
+            cells: vec![Cell::Int64(id), Cell::Int64(id % 7)],
+        })
+        .collect();
+    sort_table(&mut rows, 1, SortOrder::Descending, NullsOrder::Last);
+    for pair in rows.windows(2) {
+        let (a, b) = (&pair[0].cells, &pair[1].cells);
+        assert!(a[1]
+            .cmp_numeric(&b[1])
+            .is_some_and(std::cmp::Ordering::is_ge));
+        if a[1] == b[1] {
+            assert!(a[0]
+                .cmp_numeric(&b[0])
+                .is_some_and(std::cmp::Ordering::is_lt));
+        }
+    }
+    assert_eq!(rows[0].cells, vec![Cell::Int64(6), Cell::Int64(6)]);
+}
+
+/// Handles the [`Operator::OrderBy`] operator by processing the [`Operator`] chain and sorting the
+/// rows of the resulting [`Table`] by the `column`.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `column`: Name of the column to sort by. Must be a `numeric` column, i.e., the values in the
+/// column must be numeric or missing.
+/// `order`: Whether to sort in ascending or descending order.
+/// `nulls`: Whether the rows with missing values are placed first or last.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] containing only the sorted rows.
+/// On failure: [`OperatorError::OrderByColumnNotNumeric`] if the input column is not a numeric
+/// column, or  [`OperatorError::NoSuchColumn`] if the input column is not found, or any
+/// other [`OperatorError`] produced on processing the operator chain.
+fn process_orderby(
+    chain: &Operator,
+    column: String,
+    order: SortOrder,
+    nulls: NullsOrder,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let mut table = process_operator(chain, context)?;
+
+    // Find the index corresponding to the `column`.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let col_index = find_column_index(
+        &table.header,
+        &table.qualified_names,
+        &column,
+        chain,
+        "ORDERBY",
+    )?;
+
+    // Ensure the `column` to sort by is a numeric column.
+    check_sortable(&table, col_index, "ORDERBY", &column)?;
+
+    // Do the actual sort
+    sort_table(&mut table.rows, col_index, order, nulls);
+
+    Ok(table)
+}
+
+#[test]
+fn test_process_orderby_numeric() {
+    let result = process_orderby(
+        &Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 10,
+        }),
+        "CityPop".to_string(),
+        SortOrder::Descending,
+        NullsOrder::Last,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 10);
+    assert_eq!(result.header.len(), 4);
+    assert!(result.rows[0].cells[3]
+        .cmp_numeric(&result.rows[1].cells[3])
+        .is_some_and(std::cmp::Ordering::is_ge));
+    assert!(result.rows[1].cells[3]
+        .cmp_numeric(&result.rows[2].cells[3])
+        .is_some_and(std::cmp::Ordering::is_ge));
+    assert!(result.rows[2].cells[3]
+        .cmp_numeric(&result.rows[3].cells[3])
+        .is_some_and(std::cmp::Ordering::is_ge));
+}
+
+#[test]
+fn test_process_orderby_ascending() {
+    let result = process_orderby(
+        &Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 10,
+        }),
+        "CityPop".to_string(),
+        SortOrder::Ascending,
+        NullsOrder::Last,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 10);
+    for i in 1..result.rows.len() {
+        assert!(result.rows[i - 1].cells[3]
+            .cmp_numeric(&result.rows[i].cells[3])
+            .is_some_and(std::cmp::Ordering::is_le));
+    }
+}
+
+#[test]
+fn test_process_orderby_non_numeric() {
+    let result = process_orderby(
+        &Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 10,
+        }),
+        "CityName".to_string(),
+        SortOrder::Descending,
+        NullsOrder::Last,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_err());
+    let err = result.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "You attempted to ORDERBY the CityName column whose type is not numeric.".to_string()
+    );
+}
+
+#[test]
+fn test_process_orderby_nulls_last() {
+    for order in [SortOrder::Ascending, SortOrder::Descending] {
+        let result = process_orderby(
+            &Operator::From(Dataset::Country),
+            "Capital".to_string(),
+            order,
+            NullsOrder::Last,
+            &mut ExecutionContext::default(),
+        )
+        .unwrap();
+        let capitals: Vec<&Cell> = result.rows.iter().map(|row| &row.cells[4]).collect();
+        let missing = capitals.iter().filter(|capital| capital.is_null()).count();
+        assert_eq!(missing, 7);
+        assert!(capitals[capitals.len() - missing..]
+            .iter()
+            .all(|capital| capital.is_null()));
+        for pair in capitals[..capitals.len() - missing].windows(2) {
+            let ordering = pair[0].cmp_numeric(pair[1]).unwrap();
+            assert!(match order {
+                SortOrder::Ascending => ordering.is_le(),
+                SortOrder::Descending => ordering.is_ge(),
+            });
+        }
+    }
+}
+
+#[test]
+fn test_process_orderby_nulls_first() {
+    for order in [SortOrder::Ascending, SortOrder::Descending] {
+        let result = process_orderby(
+            &Operator::From(Dataset::Country),
+            "Capital".to_string(),
+            order,
+            NullsOrder::First,
+            &mut ExecutionContext::default(),
+        )
+        .unwrap();
+        let capitals: Vec<&Cell> = result.rows.iter().map(|row| &row.cells[4]).collect();
+        assert!(capitals[..7].iter().all(|capital| capital.is_null()));
+        for pair in capitals[7..].windows(2) {
+            let ordering = pair[0].cmp_numeric(pair[1]).unwrap();
+            assert!(match order {
+                SortOrder::Ascending => ordering.is_le(),
+                SortOrder::Descending => ordering.is_ge(),
+            });
+        }
+    }
+}
+
+/// The number of columns the bars drawn by `COUNTBY ... BARS` are scaled to when the width of the
+/// terminal isn't known.
+pub const C_DEFAULT_TERMINAL_WIDTH: usize = 80;
+
+/// Helper function to draw the `counts` as bars of `#`s, the longest of them `width` long. Every
+/// non-zero count gets at least one `#`, so rare values don't look absent.
+fn draw_bars(counts: &[i64], width: usize) -> Vec<String> {
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|count| {
+            let length = (*count as f64 / max as f64 * width as f64).round() as usize;
+            "#".repeat(if *count > 0 { length.max(1) } else { 0 })
+        })
+        .collect()
+}
+
+#[test]
+fn test_draw_bars() {
+    assert_eq!(draw_bars(&[10, 5, 1], 10), vec!["##########", "#####", "#"]);
+    assert_eq!(
+        draw_bars(&[1000, 1], 20),
+        vec!["#".repeat(20), "#".to_string()]
+    );
+    assert!(draw_bars(&[], 20).is_empty());
+}
+
+/// Helper function to apply the HAVING condition of [`Operator::CountBy`] and
+/// [`Operator::GroupBy`] to the rows they produce.
+///
+/// # Arguments
+/// `header` : The names of the columns of the `rows`.
+/// `rows` : The rows of the histogram or the groups.
+/// `having` : The condition the rows must meet to be kept.
+/// `operator` : Builds the operator that produced the `rows`, for the error naming its chain.
+/// `context` : The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns
+/// On success: The `rows` the condition holds for, in order.
+/// On failure: [`OperatorError::NoSuchColumn`] if the condition reads a column that isn't in the
+/// `header`, even in a different case, or any other [`OperatorError`] from evaluating it.
+fn retain_having(
+    header: &[String],
+    rows: Vec<Row>,
+    having: &Predicate,
+    operator: impl FnOnce() -> Operator,
+    context: &ExecutionContext,
+) -> Result<Vec<Row>, OperatorError> {
+    // The columns are named as in the header, e.g. `SUM(CityPop)`, even if the aggregate was
+    // given as `SUM(citypop)`.
+    let mut columns = HashMap::new();
+    for name in having.left.column_names() {
+        let column = header.iter().find(|column| *column == name).or_else(|| {
+            header
+                .iter()
+                .find(|column| column.to_lowercase() == name.to_lowercase())
+        });
+        let Some(column) = column else {
+            return Err(OperatorError::NoSuchColumn {
+                operator: "HAVING".to_string(),
+                chain: Box::new(operator()),
+                column_name: name.to_string(),
+                suggestions: suggest_columns(header, name),
+            });
+        };
+        columns.insert(name.to_string(), column.clone());
+    }
+    let having = Predicate {
+        left: having.left.rename_columns(&|name| columns[name].clone()),
+        ..having.clone()
+    };
+    let mut kept = Vec::with_capacity(rows.len());
+    for row in rows {
+        if having.matches(header, &row, context)? {
+            kept.push(row);
+        }
+    }
+    Ok(kept)
+}
+
+/// Handles the [`Operator::CountBy`] operator by processing the [`Operator`] chain and produces a
+/// [`Table`] containing only two columns: the first contains the values of the specified `column`,
+/// and the second `count` column contains the number of times that value appears in the dataset.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `column`: Name of the column to create the histogram for.
+/// `order`: How the rows of the histogram are sorted. See [`CountByOrder`].
+/// `bars`: Whether to add a third `bar` column, drawing each count as a bar of `#`s. The bars are
+/// scaled so that the table fits the [`ExecutionContext::terminal_width`] when printed.
+/// `having`: The condition the rows of the histogram must meet to be kept, if any. It is applied
+/// before the bars are drawn, so the bars are scaled to the counts that are kept.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] containing the two columns described above, and the `bar` column if
+/// `bars` is set.
+/// On failure: [`OperatorError::NoSuchColumn`] if the input column, or a column read by `having`,
+/// is not found, or any other [`OperatorError`] produced on processing the operator chain.
+fn process_countby(
+    chain: &Operator,
+    column: String,
+    order: CountByOrder,
+    bars: bool,
+    having: Option<&Predicate>,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+
+    // Find the index corresponding to the `column`.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let col_index = find_column_index(
+        &table.header,
+        &table.qualified_names,
+        &column,
+        chain,
+        "COUNTBY",
+    )?;
+
+    // Count the number of times each value in the selected column occurs in the input table,
+    // keeping the values in the order they are first seen.
+    let mut counts: Vec<(&Cell, i64)> = Vec::new();
+    let mut value_indices: HashMap<&Cell, usize> = HashMap::new();
+    for row in &table.rows {
+        let value = &row.cells[col_index];
+        let index = *value_indices.entry(value).or_insert_with(|| {
+            counts.push((value, 0));
+            counts.len() - 1
+        });
+        counts[index].1 += 1;
+    }
+
+    // The sorts are stable, so values that compare equal stay in the order they were first seen.
+    match order {
+        CountByOrder::Count => counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count)),
+        CountByOrder::Value => counts.sort_by(|a, b| a.0.cmp_values(b.0)),
+    }
+    let mut histogram: Vec<Row> = counts
+        .iter()
+        .map(|(value, count)| Row {
+            cells: vec![(*value).clone(), Cell::Int64(*count)],
+        })
+        .collect();
+    let mut header = vec![
+        resolved_name(&table.header, col_index, &column).to_string(),
+        String::from("count"),
+    ];
+
+    if let Some(having) = having {
+        let countby = || Operator::CountBy {
+            chain: Box::new(chain.clone()),
+            column: column.clone(),
+            order,
+            bars: false,
+            having: None,
+        };
+        histogram = retain_having(&header, histogram, having, countby, context)?;
+    }
+
+    if bars {
+        // The bars take up the width left over by the other two columns once the table is
+        // printed, i.e., the widest value and count, and the two " | " separators.
+        let widest = |index: usize| {
+            histogram
+                .iter()
+                .map(|row| row.cells[index].to_string().chars().count())
+                .chain([header[index].chars().count()])
+                .max()
+                .unwrap_or(0)
+        };
+        let width = context
+            .terminal_width
+            .unwrap_or(C_DEFAULT_TERMINAL_WIDTH)
+            .saturating_sub(widest(0) + widest(1) + 6)
+            // Keep the bars long enough to compare, even if the table then wraps.
+            .max(10);
+        let counts: Vec<i64> = histogram
+            .iter()
+            .map(|row| match row.cells[1] {
+                Cell::Int64(count) => count,
+                _ => 0,
+            })
+            .collect();
+        for (row, bar) in histogram.iter_mut().zip(draw_bars(&counts, width)) {
+            row.cells.push(Cell::String(bar));
+        }
+        header.push(String::from("bar"));
+    }
+
+    // The values keep the schema of the column they were counted in.
+    let mut result = Table::new(header, histogram);
+    result.schema[0] = table.schema[col_index].renamed(&result.header[0]);
+    Ok(result)
+}
+
+#[test]
+fn test_process_countby() {
+    let result = process_countby(
+        &Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            count: 100,
+        }),
+        "Language".to_string(),
+        CountByOrder::Count,
+        false,
+        None,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 70);
+    assert_eq!(result.header.len(), 2);
+    assert_eq!(
+        result.rows[0].cells,
+        vec![Cell::String("English".to_string()), Cell::Int64(7)],
+    );
+    assert_eq!(
+        result.rows[1].cells,
+        vec![Cell::String("Arabic".to_string()), Cell::Int64(4)],
+    );
+}
+
+#[test]
+fn test_process_countby_order() {
+    let countby = |column: &str, order| {
+        process_countby(
+            &Operator::From(Dataset::Language),
+            column.to_string(),
+            order,
+            false,
+            None,
+            &mut ExecutionContext::default(),
+        )
+        .unwrap()
+    };
+    // Counting a column other than the second one used to sort on the wrong column.
+    let by_count = countby("CountryCode", CountByOrder::Count);
+    assert!(by_count.rows.windows(2).all(|pair| pair[0].cells[1]
+        .cmp_numeric(&pair[1].cells[1])
+        .is_some_and(|order| order.is_ge())));
+    // Ties are listed in the order the values first appear in language.csv.
+    let first_with_one = by_count
+        .rows
+        .iter()
+        .find(|row| row.cells[1] == Cell::Int64(1))
+        .unwrap();
+    assert_eq!(first_with_one.cells[0], Cell::String("AIA".to_string()));
+
+    let by_value = countby("CountryCode", CountByOrder::Value);
+    assert_eq!(by_value.rows.len(), by_count.rows.len());
+    assert!(by_value
+        .rows
+        .windows(2)
+        .all(|pair| pair[0].cells[0].cmp_values(&pair[1].cells[0]).is_lt()));
+    assert_eq!(by_value.rows[0].cells[0], Cell::String("ABW".to_string()));
+}
+
+#[test]
+fn test_process_countby_bars() {
+    let mut context = ExecutionContext {
+        terminal_width: Some(60),
+        ..ExecutionContext::default()
+    };
+    let result = process_countby(
+        &Operator::From(Dataset::Language),
+        "Language".to_string(),
+        CountByOrder::Count,
+        true,
+        None,
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(
+        result.header,
+        vec![
+            "Language".to_string(),
+            "count".to_string(),
+            "bar".to_string()
+        ]
+    );
+    assert_eq!(result.numeric_columns(), vec!["count".to_string()]);
+    // The longest bar fills the width left over by the other columns, so the table is 60 wide.
+    let widest = result
+        .rows
+        .iter()
+        .map(|row| row.cells[0].to_string().len())
+        .max()
+        .unwrap();
+    assert_eq!(
+        result.rows[0].cells[2],
+        Cell::String("#".repeat(60 - widest - "count".len() - 6))
+    );
+    let last = result.rows.last().unwrap();
+    assert_eq!(last.cells[1], Cell::Int64(1));
+    assert_eq!(last.cells[2], Cell::String("#".to_string()));
+}
+
+#[test]
+fn test_process_countby_having() {
+    let mut context = ExecutionContext {
+        terminal_width: Some(60),
+        ..ExecutionContext::default()
+    };
+    let result = process_countby(
+        &Operator::From(Dataset::Language),
+        "Language".to_string(),
+        CountByOrder::Count,
+        true,
+        Some(&parse_predicate("count", ">", "10").unwrap()),
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(result.rows.len(), 13);
+    assert_eq!(
+        result.rows[0].join(),
+        format!("English,60,{}", "#".repeat(35))
+    );
+    // The bars are scaled to the counts that are kept.
+    assert_eq!(
+        result.rows[12].join(),
+        format!("Ukrainian,12,{}", "#".repeat(7))
+    );
+
+    let result = process_countby(
+        &Operator::From(Dataset::Language),
+        "Language".to_string(),
+        CountByOrder::Count,
+        false,
+        Some(&parse_predicate("Language", "=", "Ful").unwrap()),
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(result.rows.len(), 1);
+
+    let result = process_countby(
+        &Operator::From(Dataset::Language),
+        "Language".to_string(),
+        CountByOrder::Value,
+        false,
+        Some(&parse_predicate("total", ">", "10").unwrap()),
+        &mut context,
+    );
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Could not find the total column to HAVING on the table produced by this operator chain: FROM language.csv COUNTBY Language ORDER value"
+    );
+}
+
+#[test]
+fn test_process_countby_empty() {
+    let result = process_countby(
+        &Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            count: 0,
+        }),
+        "Language".to_string(),
+        CountByOrder::Count,
+        false,
+        None,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 0);
+    assert_eq!(result.header.len(), 2);
+}
+
+#[test]
+fn test_process_countby_no_such_column() {
+    let result = process_countby(
+        &Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            count: 100,
+        }),
+        "CityPop".to_string(),
+        CountByOrder::Count,
+        false,
+        None,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_err());
+    let result = result.unwrap_err();
+    assert_eq!(result.to_string(), "Could not find the CityPop column to COUNTBY on the table produced by this operator chain: FROM language.csv TAKE 100".to_string());
+}
+
+/// Handles the [`Operator::Aggregate`] operator by processing the [`Operator`] chain and combining
+/// the values of the `column` of the resulting [`Table`] with the aggregate `function`.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `function`: How the values are combined.
+/// `column`: Name of the column whose values are combined. The values must be numbers, except for
+/// COUNT, which counts the rows of the input table instead if the column is [`ALL_ROWS`].
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] with a single row and a single column, named after the aggregate, e.g.
+/// `SUM(CityPop)`.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::AggregateError`] if the values
+/// can't be combined, or other [`OperatorError`] from processing the chained operators.
+fn process_aggregate(
+    chain: &Operator,
+    function: AggregateFunction,
+    column: &str,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+
+    let (name, value) = if function == AggregateFunction::Count && column == ALL_ROWS {
+        (
+            format!("{}({})", function, column),
+            Cell::Int64(table.rows.len() as i64),
+        )
+    } else {
+        // Find the index corresponding to the `column`.
+        // This can throw the [`OperatorError::NoSuchColumn`] error.
+        let col_index = find_column_index(
+            &table.header,
+            &table.qualified_names,
+            column,
+            chain,
+            function.name(),
+        )?;
+        let name = format!(
+            "{}({})",
+            function,
+            resolved_name(&table.header, col_index, column)
+        );
+        let value = function
+            .apply(table.rows.iter().map(|row| &row.cells[col_index]))
+            .map_err(|message| OperatorError::AggregateError {
+                aggregate: name.clone(),
+                message,
+            })?;
+        (name, value)
+    };
+
+    Ok(Table::new(vec![name], vec![Row { cells: vec![value] }]))
+}
+
+#[test]
+fn test_process_aggregate() {
+    let aggregate = |function, dataset, column| {
+        process_aggregate(
+            &Operator::From(dataset),
+            function,
+            column,
+            &mut ExecutionContext::default(),
+        )
+    };
+    let result = aggregate(AggregateFunction::Sum, Dataset::Country, "CountryPop").unwrap();
+    assert_eq!(result.header, vec!["SUM(CountryPop)"]);
+    assert_eq!(result.numeric_columns(), vec!["SUM(CountryPop)"]);
+    assert_eq!(result.rows.len(), 1);
+    assert_eq!(result.rows[0].cells, vec![Cell::Int64(6078749450)]);
+
+    let result = aggregate(AggregateFunction::Max, Dataset::City, "CityPop").unwrap();
+    assert_eq!(result.rows[0].cells, vec![Cell::Int64(10500000)]);
+
+    // The missing capitals are ignored.
+    let result = aggregate(AggregateFunction::Min, Dataset::Country, "Capital").unwrap();
+    assert_eq!(result.rows[0].cells, vec![Cell::Int64(1)]);
+    let result = aggregate(AggregateFunction::Count, Dataset::Country, "Capital").unwrap();
+    assert_eq!(result.rows[0].cells, vec![Cell::Int64(232)]);
+    // COUNT * counts the rows, including those with missing values.
+    let result = aggregate(AggregateFunction::Count, Dataset::Country, ALL_ROWS).unwrap();
+    assert_eq!(result.header, vec!["COUNT(*)"]);
+    assert_eq!(result.numeric_columns(), vec!["COUNT(*)"]);
+    assert_eq!(result.rows[0].cells, vec![Cell::Int64(239)]);
+
+    let result = aggregate(AggregateFunction::Avg, Dataset::Country, "CountryPop").unwrap();
+    assert_eq!(result.numeric_columns(), vec!["AVG(CountryPop)"]);
+    assert!(result.rows[0].join().starts_with("25434098.11"));
+}
+
+#[test]
+fn test_process_aggregate_errors() {
+    let result = process_aggregate(
+        &Operator::From(Dataset::City),
+        AggregateFunction::Sum,
+        "CityName",
+        &mut ExecutionContext::default(),
+    );
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Failed to compute SUM(CityName): Kabul is not a number."
+    );
+    let result = process_aggregate(
+        &Operator::From(Dataset::City),
+        AggregateFunction::Sum,
+        "Capital",
+        &mut ExecutionContext::default(),
+    );
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Could not find the Capital column to SUM on the table produced by this operator chain: FROM city.csv"
+    );
+}
+
+/// Handles the [`Operator::GroupBy`] operator by processing the [`Operator`] chain, grouping the
+/// rows of the resulting [`Table`] by the values of the `column`, and computing the `aggregates`
+/// for each group.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `column`: Name of the column to group the rows by.
+/// `aggregates`: The aggregates to compute for each group. `COUNT(*)` counts the rows of the
+/// group.
+/// `having`: The condition the groups must meet to be kept, if any, which may read the
+/// aggregates by name, e.g. `SUM(CityPop) > 1000000`.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] with one row per distinct value of the `column` whose group meets the
+/// `having` condition, in the order the values first appear in the input. The first column holds
+/// the value, and is followed by one column per aggregate, named after it, e.g. `SUM(CityPop)`.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::AggregateError`] if the values
+/// of a group can't be combined, or other [`OperatorError`] from processing the chained operators.
+fn process_groupby(
+    chain: &Operator,
+    column: &str,
+    aggregates: &[Aggregate],
+    having: Option<&Predicate>,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+
+    // Find the indices of the grouping column and the aggregated columns.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let col_index = find_column_index(
+        &table.header,
+        &table.qualified_names,
+        column,
+        chain,
+        "GROUPBY",
+    )?;
+    // Counting the rows of a group doesn't read any column.
+    let aggregate_indices = aggregates
+        .iter()
+        .map(|aggregate| match aggregate.column == ALL_ROWS {
+            true if aggregate.function == AggregateFunction::Count => Ok(None),
+            _ => find_column_index(
+                &table.header,
+                &table.qualified_names,
+                &aggregate.column,
+                chain,
+                "GROUPBY",
+            )
+            .map(Some),
+        })
+        .collect::<Result<Vec<Option<usize>>, OperatorError>>()?;
+
+    // Collect the rows of each group, keeping the groups in the order they are first seen.
+    let mut groups: Vec<(&Cell, Vec<&Row>)> = Vec::new();
+    let mut group_indices: HashMap<&Cell, usize> = HashMap::new();
+    for row in &table.rows {
+        let key = &row.cells[col_index];
+        let index = *group_indices.entry(key).or_insert_with(|| {
+            groups.push((key, Vec::new()));
+            groups.len() - 1
+        });
+        groups[index].1.push(row);
+    }
+
+    let mut rows = Vec::with_capacity(groups.len());
+    for (key, group) in &groups {
+        let mut cells = vec![(*key).clone()];
+        for (aggregate, index) in aggregates.iter().zip(aggregate_indices.iter()) {
+            let value = match index {
+                Some(index) => aggregate
+                    .function
+                    .apply(group.iter().map(|row| &row.cells[*index]))
+                    .map_err(|message| OperatorError::AggregateError {
+                        aggregate: aggregate.to_string(),
+                        message,
+                    })?,
+                None => Cell::Int64(group.len() as i64),
+            };
+            cells.push(value);
+        }
+        rows.push(Row { cells });
+    }
+
+    let mut header = vec![resolved_name(&table.header, col_index, column).to_string()];
+    header.extend(
+        aggregates
+            .iter()
+            .zip(&aggregate_indices)
+            .map(|(aggregate, index)| match index {
+                Some(index) => Aggregate {
+                    function: aggregate.function,
+                    column: resolved_name(&table.header, *index, &aggregate.column).to_string(),
+                }
+                .to_string(),
+                None => aggregate.to_string(),
+            }),
+    );
+    if let Some(having) = having {
+        let groupby = || Operator::GroupBy {
+            chain: Box::new(chain.clone()),
+            column: column.to_string(),
+            aggregates: aggregates.to_vec(),
+            having: None,
+        };
+        rows = retain_having(&header, rows, having, groupby, context)?;
+    }
+    // The groups keep the schema of the column they were made from, while the schema of each
+    // aggregate is inferred from the values it produced.
+    let mut result = Table::new(header, rows);
+    result.schema[0] = table.schema[col_index].renamed(&result.header[0]);
+    Ok(result)
+}
+
+#[test]
+fn test_process_groupby() {
+    let mut context = ExecutionContext::default();
+    let aggregates = crate::aggregate::parse_aggregate_list("SUM(CityPop),COUNT(CityID)").unwrap();
+    let result = process_groupby(
+        &Operator::From(Dataset::City),
+        "CountryCode",
+        &aggregates,
+        None,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        result.header,
+        vec!["CountryCode", "SUM(CityPop)", "COUNT(CityID)"]
+    );
+    assert_eq!(
+        result.numeric_columns(),
+        vec!["SUM(CityPop)", "COUNT(CityID)"]
+    );
+    // The groups are in the order they first appear in the input.
+    assert_eq!(result.rows[0].cells[0], Cell::String("AFG".to_string()));
+    let nld = result
+        .rows
+        .iter()
+        .find(|row| row.cells[0] == Cell::String("NLD".to_string()))
+        .unwrap();
+    assert_eq!(nld.cells.len(), 3);
+    let input = process_from(&Dataset::City, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    let distinct_countries = input
+        .rows
+        .iter()
+        .map(|row| &row.cells[2])
+        .collect::<std::collections::HashSet<&Cell>>()
+        .len();
+    assert_eq!(result.rows.len(), distinct_countries);
+}
+
+#[test]
+fn test_process_groupby_count_rows() {
+    let aggregates = crate::aggregate::parse_aggregate_list("COUNT(*),COUNT(Capital)").unwrap();
+    let result = process_groupby(
+        &Operator::From(Dataset::Country),
+        "Continent",
+        &aggregates,
+        None,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        result.header,
+        vec!["Continent", "COUNT(*)", "COUNT(Capital)"]
+    );
+    // Unlike COUNT(Capital), COUNT(*) also counts the countries without a capital.
+    let antarctica = result
+        .rows
+        .iter()
+        .find(|row| row.cells[0] == Cell::String("Antarctica".to_string()))
+        .unwrap();
+    assert_eq!(antarctica.cells[1], Cell::Int64(5));
+    assert_eq!(antarctica.cells[2], Cell::Int64(0));
+}
+
+#[test]
+fn test_process_groupby_sums_match_aggregate() {
+    let aggregates = crate::aggregate::parse_aggregate_list("SUM(CountryPop)").unwrap();
+    let result = process_groupby(
+        &Operator::From(Dataset::Country),
+        "Continent",
+        &aggregates,
+        None,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    let total: i64 = result
+        .rows
+        .iter()
+        .map(|row| match row.cells[1] {
+            Cell::Int64(val) => val,
+            _ => panic!("SUM should produce integers"),
+        })
+        .sum();
+    assert_eq!(total, 6078749450);
+}
+
+#[test]
+fn test_process_groupby_having() {
+    let aggregates = crate::aggregate::parse_aggregate_list("SUM(CityPop),COUNT(CityID)").unwrap();
+    let result = process_groupby(
+        &Operator::From(Dataset::City),
+        "CountryCode",
+        &aggregates,
+        Some(&parse_predicate("SUM(CityPop)", ">=", "50000000").unwrap()),
+        &mut ExecutionContext::default(),
+    )
+    .unwrap_err();
+    // Parsed on its own, the aggregate is a call to a function on the CityPop column, which the
+    // groups don't have; see `parse_having`.
+    assert_eq!(
+        result.to_string(),
+        "Could not find the CityPop column to HAVING on the table produced by this operator chain: FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID)"
+    );
+
+    let having = Predicate {
+        left: Expression::Column("SUM(CityPop)".to_string()),
+        ..parse_predicate("total", ">=", "50000000").unwrap()
+    };
+    let result = process_groupby(
+        &Operator::From(Dataset::City),
+        "CountryCode",
+        &aggregates,
+        Some(&having),
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        result
+            .rows
+            .iter()
+            .map(|row| row.cells[0].to_string())
+            .collect::<Vec<String>>(),
+        vec!["BRA", "IND", "JPN", "CHN", "MEX", "RUS", "USA"]
+    );
+    assert_eq!(
+        result.numeric_columns(),
+        vec!["SUM(CityPop)".to_string(), "COUNT(CityID)".to_string()]
+    );
+}
+
+#[test]
+fn test_process_groupby_errors() {
+    let groupby = |column: &str, aggregates: &str| {
+        process_groupby(
+            &Operator::From(Dataset::City),
+            column,
+            &crate::aggregate::parse_aggregate_list(aggregates).unwrap(),
+            None,
+            &mut ExecutionContext::default(),
+        )
+        .unwrap_err()
+        .to_string()
+    };
+    assert_eq!(
+        groupby("Continent", "SUM(CityPop)"),
+        "Could not find the Continent column to GROUPBY on the table produced by this operator chain: FROM city.csv"
+    );
+    assert_eq!(
+        groupby("CountryCode", "SUM(Capital)"),
+        "Could not find the Capital column to GROUPBY on the table produced by this operator chain: FROM city.csv"
+    );
+    assert_eq!(
+        groupby("CountryCode", "MAX(CityName)"),
+        "Failed to compute MAX(CityName): Kabul is not a number."
+    );
+}
+
+/// Handles the [`Operator::Pivot`] operator by processing the [`Operator`] chain and
+/// cross-tabulating the resulting [`Table`]: its rows are grouped by the values of the `row` column
+/// and, within those, by the values of the `column` column, and the `aggregate` is computed for
+/// each group.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `row`: Name of the column whose values become the rows.
+/// `column`: Name of the column whose values become the columns.
+/// `aggregate`: The aggregate to compute for each pair of values.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] with one row per distinct value of the `row` column, in the order the
+/// values first appear in the input. The first column holds the value, and is followed by one
+/// column per distinct value of the `column` column, in the same order, named after the value
+/// (`NULL` for a missing value, and suffixed with a number if the name is taken). A pair of values
+/// that no row shares gets the aggregate of no values, i.e., a missing value, or 0 for COUNT.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::AggregateError`] if the values
+/// of a group can't be combined, or other [`OperatorError`] from processing the chained operators.
+fn process_pivot(
+    chain: &Operator,
+    row: &str,
+    column: &str,
+    aggregate: &Aggregate,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+
+    // Find the indices of the grouping columns and the aggregated column.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let row_index = find_column_index(&table.header, &table.qualified_names, row, chain, "PIVOT")?;
+    let col_index = find_column_index(
+        &table.header,
+        &table.qualified_names,
+        column,
+        chain,
+        "PIVOT",
+    )?;
+    let value_index = find_column_index(
+        &table.header,
+        &table.qualified_names,
+        &aggregate.column,
+        chain,
+        "PIVOT",
+    )?;
+
+    // The values of both columns, in the order they are first seen.
+    let mut row_keys: Vec<&Cell> = Vec::new();
+    let mut row_indices: HashMap<&Cell, usize> = HashMap::new();
+    let mut col_keys: Vec<&Cell> = Vec::new();
+    let mut col_indices: HashMap<&Cell, usize> = HashMap::new();
+    // The values to aggregate of each pair of values, by the index of their row and column.
+    let mut groups: HashMap<(usize, usize), Vec<&Cell>> = HashMap::new();
+    for input_row in &table.rows {
+        let key = &input_row.cells[row_index];
+        let row_key = *row_indices.entry(key).or_insert_with(|| {
+            row_keys.push(key);
+            row_keys.len() - 1
+        });
+        let key = &input_row.cells[col_index];
+        let col_key = *col_indices.entry(key).or_insert_with(|| {
+            col_keys.push(key);
+            col_keys.len() - 1
+        });
+        groups
+            .entry((row_key, col_key))
+            .or_default()
+            .push(&input_row.cells[value_index]);
+    }
+
+    let mut rows = Vec::with_capacity(row_keys.len());
+    for (row_key, key) in row_keys.iter().enumerate() {
+        let mut cells = vec![(*key).clone()];
+        for col_key in 0..col_keys.len() {
+            let values = groups
+                .get(&(row_key, col_key))
+                .map_or(&[][..], Vec::as_slice);
+            let value = aggregate
+                .function
+                .apply(values.iter().copied())
+                .map_err(|message| OperatorError::AggregateError {
+                    aggregate: aggregate.to_string(),
+                    message,
+                })?;
+            cells.push(value);
+        }
+        rows.push(Row { cells });
+    }
+
+    let mut header = vec![resolved_name(&table.header, row_index, row).to_string()];
+    for key in &col_keys {
+        let name = match key.is_null() {
+            true => "NULL".to_string(),
+            false => key.to_string(),
+        };
+        header.push(unique_column_name(&name, &header));
+    }
+    // The rows keep the schema of the column they were made from, while the schema of each
+    // pivoted column is inferred from its values.
+    let mut result = Table::new(header, rows);
+    result.schema[0] = table.schema[row_index].renamed(&result.header[0]);
+    Ok(result)
+}
+
+#[test]
+fn test_process_pivot() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        count: 4,
+    };
+    let count = crate::aggregate::parse_aggregate_list("COUNT(CountryCode)").unwrap();
+    let result = process_pivot(&chain, "Continent", "Capital", &count[0], &mut context).unwrap();
+    assert_eq!(result.header, vec!["Continent", "129", "1", "56", "62"]);
+    let result = process_pivot(
+        &Operator::From(Dataset::Country),
+        "Continent",
+        "Continent",
+        &count[0],
+        &mut context,
+    )
+    .unwrap();
+    // The columns are named after the values, and the name of the row column is taken.
+    assert_eq!(result.header[0], "Continent");
+    assert_eq!(result.header[1], "North_America");
+    assert_eq!(result.rows[0].join(), "North_America,37,0,0,0,0,0,0");
+    assert_eq!(result.rows.len(), 7);
+    assert_eq!(result.numeric_columns().len(), 7);
+
+    let sum = crate::aggregate::parse_aggregate_list("SUM(CountryPop)").unwrap();
+    let result = process_pivot(&chain, "Continent", "Continent", &sum[0], &mut context).unwrap();
+    assert_eq!(
+        result.header,
+        vec!["Continent", "North_America", "Asia", "Africa"]
+    );
+    assert_eq!(
+        result
+            .rows
+            .iter()
+            .map(|row| row.join())
+            .collect::<Vec<String>>(),
+        vec![
+            "North_America,111000,,",
+            "Asia,,22720000,",
+            "Africa,,,12878000"
+        ]
+    );
+    // The columns with missing values aren't numeric.
+    assert!(result.numeric_columns().is_empty());
+}
+
+#[test]
+fn test_process_pivot_errors() {
+    let pivot = |row: &str, column: &str, aggregate: &str| {
+        process_pivot(
+            &Operator::From(Dataset::City),
+            row,
+            column,
+            &crate::aggregate::parse_aggregate_list(aggregate).unwrap()[0],
+            &mut ExecutionContext::default(),
+        )
+        .unwrap_err()
+        .to_string()
+    };
+    assert_eq!(
+        pivot("Continent", "CountryCode", "SUM(CityPop)"),
+        "Could not find the Continent column to PIVOT on the table produced by this operator chain: FROM city.csv"
+    );
+    assert_eq!(
+        pivot("CountryCode", "CityID", "SUM(Capital)"),
+        "Could not find the Capital column to PIVOT on the table produced by this operator chain: FROM city.csv"
+    );
+    assert_eq!(
+        pivot("CountryCode", "CityID", "MAX(CityName)"),
+        "Failed to compute MAX(CityName): Kabul is not a number."
+    );
+}
+
+/// The value a row is joined on. Integers are compared by their value, whether or not their column
+/// may have missing values, so e.g. the Capital column of country.csv ([`Cell::OptInt64`]) can be
+/// joined with the CityID column of city.csv ([`Cell::Int64`]).
+#[derive(PartialEq, Eq, Hash)]
+enum JoinKey<'a> {
+    Integer(i64),
+    Other(&'a Cell),
+}
+
+impl<'a> From<&'a Cell> for JoinKey<'a> {
+    fn from(cell: &'a Cell) -> Self {
+        match cell {
+            Cell::Int64(val) | Cell::OptInt64(Some(val)) => JoinKey::Integer(*val),
+            _ => JoinKey::Other(cell),
+        }
+    }
+}
+
+impl Ord for JoinKey<'_> {
+    /// Orders the keys consistently with their equality: integers first, by value, then strings,
+    /// and then decimal numbers, by their bits as [`f64::total_cmp`] does.
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Helper to rank the kinds of keys relative to each other.
+        fn rank(key: &JoinKey) -> u8 {
+            match key {
+                JoinKey::Integer(_) => 0,
+                JoinKey::Other(Cell::String(_)) => 1,
+                JoinKey::Other(_) => 2,
+            }
+        }
+        match (self, other) {
+            (JoinKey::Integer(left), JoinKey::Integer(right)) => left.cmp(right),
+            (JoinKey::Other(Cell::String(left)), JoinKey::Other(Cell::String(right))) => {
+                left.cmp(right)
+            }
+            (JoinKey::Other(Cell::Float64(left)), JoinKey::Other(Cell::Float64(right))) => {
+                left.total_cmp(right)
+            }
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+impl PartialOrd for JoinKey<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Helper function to normalize a value to look up in a [`JoinIndex`], so that integers are
+/// compared by their value, whether or not their column may have missing values, as for
+/// [`JoinKey`].
+fn index_key(cell: &Cell) -> Cow<'_, Cell> {
+    match cell {
+        Cell::OptInt64(Some(val)) => Cow::Owned(Cell::Int64(*val)),
+        _ => Cow::Borrowed(cell),
+    }
+}
+
+/// A hash index over the join column of a dataset, kept by the [`ExecutionContext`] so that
+/// joining the dataset on the same column again, e.g. `JOIN country CountryCode` in query after
+/// query while exploring the data, doesn't build it again.
+#[derive(Debug)]
+struct JoinIndex {
+    /// The table the index was built over. The index is only used while the dataset still holds
+    /// it, i.e., it wasn't reloaded, modified, or saved again since.
+    table: Arc<Table>,
+    /// The indices of the rows of the table, in order, by their value in the join column (see
+    /// [`index_key`]). Missing values never match, so their rows are left out.
+    rows: HashMap<Cell, Vec<usize>>,
+}
+
+impl JoinIndex {
+    /// Indexes the rows of the `table` by their value in the column at `col_index`.
+    fn build(table: Arc<Table>, col_index: usize) -> Self {
+        let mut rows: HashMap<Cell, Vec<usize>> = HashMap::new();
+        for (row_index, row) in table.rows.iter().enumerate() {
+            if row.cells[col_index].is_null() {
+                continue;
+            }
+            rows.entry(index_key(&row.cells[col_index]).into_owned())
+                .or_default()
+                .push(row_index);
+        }
+        JoinIndex { table, rows }
+    }
+
+    /// Finds the indexed rows matching each of the probing `rows`, in parallel on all the
+    /// available cores. Stops looking once `cancelled` is set, like [`hash_join_matches`].
+    ///
+    /// # Returns
+    /// The indices of the matching `(probing, indexed)` rows, ordered by the probing row and then
+    /// by the indexed row, like [`hash_join_matches`].
+    fn probe(&self, rows: &[Row], col_index: usize, cancelled: &AtomicBool) -> Vec<(usize, usize)> {
+        rows.par_iter()
+            .enumerate()
+            .filter(|_| !cancelled.load(std::sync::atomic::Ordering::Relaxed))
+            .flat_map_iter(|(row_index, row)| {
+                self.rows
+                    .get(index_key(&row.cells[col_index]).as_ref())
+                    .into_iter()
+                    .flatten()
+                    .map(move |indexed_row| (row_index, *indexed_row))
+            })
+            .collect()
+    }
+}
+
+/// The number of [`JoinIndex`]es kept by a [`JoinIndexCache`].
+pub const C_JOIN_INDEX_CACHE_SIZE: usize = 8;
+
+/// The [`JoinIndex`]es over the join columns of the datasets joined by the latest queries. Only
+/// the [`C_JOIN_INDEX_CACHE_SIZE`] most recently used indexes are kept, and an index is dropped as
+/// soon as its dataset holds another table, so that replaced tables aren't kept alive by it.
+#[derive(Debug, Default)]
+struct JoinIndexCache {
+    /// The indexes by dataset and column index, least recently used first.
+    entries: VecDeque<((Dataset, usize), JoinIndex)>,
+}
+
+impl JoinIndexCache {
+    /// Returns the index over the column at `col_index` of the `table` the `dataset` holds,
+    /// building it unless an earlier join built it over the same table, and marks it as the most
+    /// recently used. The least recently used index is dropped if the cache is full.
+    fn get_or_build(
+        &mut self,
+        dataset: &Dataset,
+        table: &Arc<Table>,
+        col_index: usize,
+    ) -> &JoinIndex {
+        let key = (dataset.clone(), col_index);
+        let index = match self.entries.iter().position(|(entry, _)| *entry == key) {
+            Some(position) => self.entries.remove(position).map(|(_, index)| index),
+            None => None,
+        };
+        let index = match index {
+            Some(index) if Arc::ptr_eq(&index.table, table) => index,
+            _ => JoinIndex::build(table.clone(), col_index),
+        };
+        if self.entries.len() == C_JOIN_INDEX_CACHE_SIZE {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, index));
+        &self.entries[self.entries.len() - 1].1
+    }
+
+    /// Drops the indexes over the tables the `dataset` held before it was made to hold `table`.
+    fn forget_stale(&mut self, dataset: &Dataset, table: &Arc<Table>) {
+        self.entries
+            .retain(|((entry, _), index)| entry != dataset || Arc::ptr_eq(&index.table, table));
+    }
+
+    /// Drops the indexes over the `dataset`.
+    fn forget(&mut self, dataset: &Dataset) {
+        self.entries.retain(|((entry, _), _)| entry != dataset);
+    }
+
+    /// Returns the index over the column at `col_index` of the `dataset`, if it is cached.
+    #[cfg(test)]
+    fn get(&self, dataset: &Dataset, col_index: usize) -> Option<&JoinIndex> {
+        self.entries
+            .iter()
+            .find(|((entry, index), _)| entry == dataset && *index == col_index)
+            .map(|(_, index)| index)
+    }
+
+    /// Returns the number of cached indexes.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no indexes are cached.
+    #[cfg(test)]
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drops all the cached indexes.
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Helper function to find the dataset whose rows the `right` input of a JOIN holds, in order, to
+/// look up the index over its join column. That's the case when it is read with FROM, possibly
+/// narrowed to some of its columns, e.g. by the projection pushdown of the optimizer. Columns
+/// renamed with AS would be looked up under the wrong name in the dataset, so a SELECT renaming
+/// any isn't looked through. The datasets only defined for a single query by a WITH aren't
+/// indexed.
+fn indexed_dataset(right: &Operator) -> Option<&Dataset> {
+    match right {
+        Operator::From(Dataset::Cte(_)) => None,
+        Operator::From(dataset) => Some(dataset),
+        Operator::Select { chain, columns }
+            if columns.iter().all(|column| {
+                column.alias.is_none() && matches!(column.expression, Expression::Column(_))
+            }) =>
+        {
+            indexed_dataset(chain)
+        }
+        _ => None,
+    }
+}
+
+/// Helper function to find the pairs of rows in the `left` and `right` tables whose values in the
+/// join columns are equal. A hash index is built over the join column of the smaller table and
+/// probed with the rows of the other one, in parallel on all the available cores. Missing values
+/// never match, not even each other. See [`JoinKey`] for how values are compared.
+///
+/// # Arguments:
+/// `left`: The rows of the left table.
+/// `left_index`: The index of the join column in the `left` rows.
+/// `right`: The rows of the right table.
+/// `right_index`: The index of the join column in the `right` rows.
+/// `cancelled`: Set when the query is cancelled (see [`ExecutionContext::cancelled`]), to stop
+/// looking for matches, as there may be far more of them than rows in either table.
+///
+/// # Returns:
+/// The indices of the matching `(left, right)` rows, ordered by the left row and then by the
+/// right row, i.e., in the same order a nested loop over `left` and then `right` would find them.
+/// Only some of them if the query was cancelled.
+fn hash_join_matches(
+    left: &[Row],
+    left_index: usize,
+    right: &[Row],
+    right_index: usize,
+    cancelled: &AtomicBool,
+) -> Vec<(usize, usize)> {
+    // Helper to index the rows of a table by the value in their join column.
+    fn build_index(rows: &[Row], col_index: usize) -> HashMap<JoinKey<'_>, Vec<usize>> {
+        let mut index: HashMap<JoinKey<'_>, Vec<usize>> = HashMap::new();
+        for (row_index, row) in rows.iter().enumerate() {
+            if row.cells[col_index].is_null() {
+                continue;
+            }
+            index
+                .entry(JoinKey::from(&row.cells[col_index]))
+                .or_default()
+                .push(row_index);
+        }
+        index
+    }
+
+    // Helper to find the rows of the indexed table matching each of the probing `rows`. The
+    // matches are collected in the order of the probing rows, however the work is split up.
+    fn probe(
+        index: &HashMap<JoinKey<'_>, Vec<usize>>,
+        rows: &[Row],
+        col_index: usize,
+        cancelled: &AtomicBool,
+    ) -> Vec<(usize, usize)> {
+        rows.par_iter()
+            .enumerate()
+            .filter(|_| !cancelled.load(std::sync::atomic::Ordering::Relaxed))
+            .flat_map_iter(|(row_index, row)| {
+                index
+                    .get(&JoinKey::from(&row.cells[col_index]))
+                    .into_iter()
+                    .flatten()
+                    .map(move |indexed_row| (row_index, *indexed_row))
+            })
+            .collect()
+    }
+
+    if right.len() <= left.len() {
+        // Probing in the order of the left rows produces the matches in the right order.
+        probe(
+            &build_index(right, right_index),
+            left,
+            left_index,
+            cancelled,
+        )
+    } else {
+        let mut matches: Vec<(usize, usize)> = probe(
+            &build_index(left, left_index),
+            right,
+            right_index,
+            cancelled,
+        )
+        .into_iter()
+        .map(|(right_row, left_row)| (left_row, right_row))
+        .collect();
+        matches.par_sort_unstable();
+        matches
+    }
+}
+
+/// Helper function to find the same pairs of rows as [`hash_join_matches`] with the "sort-merge
+/// join" algorithm: the rows of both tables are sorted by their join column, keeping the order of
+/// rows with equal values, and walked through side by side to pair up the rows with equal values.
+/// Sorting is cheap for a table that is already sorted, e.g. by ORDERBY, and the matches then come
+/// out nearly in order.
+///
+/// # Arguments:
+/// `left`: The rows of the left table.
+/// `left_index`: The index of the join column in the `left` rows.
+/// `right`: The rows of the right table.
+/// `right_index`: The index of the join column in the `right` rows.
+/// `cancelled`: Set when the query is cancelled, to stop looking for matches.
+///
+/// # Returns:
+/// The indices of the matching `(left, right)` rows, ordered by the left row and then by the
+/// right row. Only some of them if the query was cancelled.
+fn merge_join_matches(
+    left: &[Row],
+    left_index: usize,
+    right: &[Row],
+    right_index: usize,
+    cancelled: &AtomicBool,
+) -> Vec<(usize, usize)> {
+    // Helper to sort the rows of a table by the value in their join column. Missing values never
+    // match, so they are left out.
+    fn sort_keys(rows: &[Row], col_index: usize) -> Vec<(JoinKey<'_>, usize)> {
+        let mut keys: Vec<(JoinKey<'_>, usize)> = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !row.cells[col_index].is_null())
+            .map(|(row_index, row)| (JoinKey::from(&row.cells[col_index]), row_index))
+            .collect();
+        keys.par_sort_by(|(left, _), (right, _)| left.cmp(right));
+        keys
+    }
+
+    let left_keys = sort_keys(left, left_index);
+    let right_keys = sort_keys(right, right_index);
+    let mut matches = Vec::new();
+    let (mut l, mut r) = (0, 0);
+    while l < left_keys.len()
+        && r < right_keys.len()
+        && !cancelled.load(std::sync::atomic::Ordering::Relaxed)
+    {
+        match left_keys[l].0.cmp(&right_keys[r].0) {
+            Ordering::Less => l += 1,
+            Ordering::Greater => r += 1,
+            Ordering::Equal => {
+                // Pair up every row of the run of equal values in each table.
+                let key = &left_keys[l].0;
+                let left_end = l + left_keys[l..].partition_point(|(other, _)| other == key);
+                let right_end = r + right_keys[r..].partition_point(|(other, _)| other == key);
+                for (_, left_row) in &left_keys[l..left_end] {
+                    for (_, right_row) in &right_keys[r..right_end] {
+                        matches.push((*left_row, *right_row));
+                    }
+                }
+                (l, r) = (left_end, right_end);
+            }
+        }
+    }
+    matches.par_sort_unstable();
+    matches
+}
+
+#[test]
+fn test_join_key_ord() {
+    let cells = [
+        Cell::Float64(1.5),
+        Cell::String("b".to_string()),
+        Cell::OptInt64(Some(2)),
+        Cell::String("a".to_string()),
+        Cell::Int64(-1),
+        Cell::Int64(2),
+    ];
+    let mut keys: Vec<JoinKey> = cells.iter().map(JoinKey::from).collect();
+    keys.sort();
+    assert_eq!(
+        keys.iter()
+            .map(|key| match key {
+                JoinKey::Integer(val) => val.to_string(),
+                JoinKey::Other(cell) => cell.to_string(),
+            })
+            .collect::<Vec<_>>(),
+        vec!["-1", "2", "2", "a", "b", "1.5"]
+    );
+    assert_eq!(
+        JoinKey::from(&Cell::OptInt64(Some(2))).cmp(&JoinKey::from(&Cell::Int64(2))),
+        Ordering::Equal
+    );
+}
+
+#[test]
+fn test_merge_join_matches() {
+    let rows = |values: &[Cell]| -> Vec<Row> {
+        values
+            .iter()
+            .map(|value| Row {
+                cells: vec![value.clone()],
+            })
+            .collect()
+    };
+    let left = rows(&[
+        Cell::Int64(3),
+        Cell::Int64(1),
+        Cell::Null,
+        Cell::Int64(3),
+        Cell::String("3".to_string()),
+        Cell::OptInt64(Some(1)),
+    ]);
+    let right = rows(&[
+        Cell::OptInt64(Some(3)),
+        Cell::OptInt64(None),
+        Cell::String("3".to_string()),
+        Cell::Int64(1),
+        Cell::Int64(3),
+        Cell::Int64(7),
+    ]);
+    let expected = hash_join_matches(&left, 0, &right, 0, &AtomicBool::default());
+    assert_eq!(
+        expected,
+        vec![(0, 0), (0, 4), (1, 3), (3, 0), (3, 4), (4, 2), (5, 3)]
+    );
+    assert_eq!(
+        merge_join_matches(&left, 0, &right, 0, &AtomicBool::default()),
+        expected
+    );
+    assert_eq!(
+        merge_join_matches(&right, 0, &left, 0, &AtomicBool::default()),
+        hash_join_matches(&right, 0, &left, 0, &AtomicBool::default())
+    );
+    assert_eq!(
+        merge_join_matches(&left, 0, &rows(&[]), 0, &AtomicBool::default()),
+        vec![]
+    );
+}
+
+#[test]
+fn test_hash_join_matches() {
+    let rows = |values: &[i64]| -> Vec<Row> {
+        values
+            .iter()
+            .map(|value| Row {
+                cells: vec![Cell::Int64(*value)],
+            })
+            .collect()
+    };
+    let small = rows(&[2, 1, 2]);
+    let large = rows(&[1, 2, 3, 2, 1]);
+    // The output order doesn't depend on which table is indexed.
+    assert_eq!(
+        hash_join_matches(&small, 0, &large, 0, &AtomicBool::default()),
+        vec![(0, 1), (0, 3), (1, 0), (1, 4), (2, 1), (2, 3)]
+    );
+    assert_eq!(
+        hash_join_matches(&large, 0, &small, 0, &AtomicBool::default()),
+        vec![(0, 1), (1, 0), (1, 2), (3, 0), (3, 2), (4, 1)]
+    );
+    assert_eq!(
+        hash_join_matches(&small, 0, &rows(&[]), 0, &AtomicBool::default()),
+        vec![]
+    );
+    let nulls = vec![
+        Row {
+            cells: vec![Cell::Null],
+        },
+        Row {
+            cells: vec![Cell::OptInt64(None)],
+        },
+    ];
+    assert_eq!(
+        hash_join_matches(&nulls, 0, &nulls, 0, &AtomicBool::default()),
+        vec![]
+    );
+}
+
+#[test]
+fn test_hash_join_matches_parallel() {
+    // Large enough for the probe to be split across threads; the order must still match a
+    // nested loop.
+    let rows = |count: i64, modulus: i64| -> Vec<Row> {
+        (0..count)
+            .map(|value| Row {
+                cells: vec![Cell::Int64(value % modulus)],
+            })
+            .collect()
+    };
+    let left = rows(20000, 101);
+    let right = rows(300, 97);
+    let expected: Vec<(usize, usize)> = (0..left.len())
+        .flat_map(|l| (0..right.len()).map(move |r| (l, r)))
+        .filter(|(l, r)| left[*l].cells[0] == right[*r].cells[0])
+        .collect();
+    assert_eq!(
+        hash_join_matches(&left, 0, &right, 0, &AtomicBool::default()),
+        expected
+    );
+    let mut swapped: Vec<(usize, usize)> = expected.iter().map(|(l, r)| (*r, *l)).collect();
+    swapped.sort_unstable();
+    assert_eq!(
+        hash_join_matches(&right, 0, &left, 0, &AtomicBool::default()),
+        swapped
+    );
+}
+
+#[test]
+fn test_hash_join_matches_mixed_integers() {
+    let left = vec![
+        Row {
+            cells: vec![Cell::OptInt64(Some(2))],
+        },
+        Row {
+            cells: vec![Cell::OptInt64(None)],
+        },
+    ];
+    let right = vec![
+        Row {
+            cells: vec![Cell::Int64(1)],
+        },
+        Row {
+            cells: vec![Cell::Int64(2)],
+        },
+        Row {
+            cells: vec![Cell::String("2".to_string())],
+        },
+    ];
+    assert_eq!(
+        hash_join_matches(&left, 0, &right, 0, &AtomicBool::default()),
+        vec![(0, 1)]
+    );
+    assert_eq!(
+        hash_join_matches(&right, 0, &left, 0, &AtomicBool::default()),
+        vec![(1, 0)]
+    );
+}
+
+/// Helper function to name a column added to a table whose columns are named `taken`: the column
+/// keeps its `name` if it is free, and is otherwise suffixed with the smallest number that makes
+/// it unique, starting from 2, e.g. `CityName_2`.
+pub(crate) fn unique_column_name(name: &str, taken: &[String]) -> String {
+    if !taken.iter().any(|column| column == name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|suffix| format!("{}_{}", name, suffix))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap()
+}
+
+#[test]
+fn test_unique_column_name() {
+    let taken = vec![
+        "CityName".to_string(),
+        "CityPop".to_string(),
+        "CityPop_2".to_string(),
+    ];
+    assert_eq!(unique_column_name("Continent", &taken), "Continent");
+    assert_eq!(unique_column_name("CityName", &taken), "CityName_2");
+    assert_eq!(unique_column_name("CityPop", &taken), "CityPop_3");
+}
+
+/// Handles the [`Operator::Join`] operator by processing the [`Operator`] chain to produce the
+/// 'left' table and the `right` chain to produce the 'right' table and joining them on the input
+/// `column`.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the 'left' [`Table`] to join on.
+/// `right`: The FROM of the dataset, or the sub-query, that produces the 'right' [`Table`] to join
+/// on. It is processed after the `chain`.
+/// `column`: Name of the column to perform the join on. This column must be in both the 'left'
+/// and 'right' tables, unless `right_column` is given.
+/// `right_column`: Name of the column of the 'right' table to join with the `column` of the 'left'
+/// table, if they are named differently. Both columns are kept in the result; otherwise only the
+/// 'left' table's copy of `column` is. Other columns of the 'right' table named like a column of
+/// the 'left' table are renamed by [`unique_column_name`], e.g. `CityName_2`.
+/// `kind`: Which rows without a match in the other table are kept. Their columns from the other
+/// table are filled with [`Cell::Null`].
+/// `strategy`: The algorithm that finds the matching rows, which doesn't affect the result.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] containing the joined rows, in the order of the 'left' table. Kept
+/// unmatched 'right' rows come last, in their original order.
+/// On failure: [`OperatorError::NoSuchColumn`] if the input column is not found, or any
+/// other [`OperatorError`] produced on processing the operator chain.
+fn process_join(
+    chain: &Operator,
+    right: &Operator,
+    column: String,
+    right_column: Option<&str>,
+    kind: JoinKind,
+    strategy: JoinStrategy,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let left = process_operator(chain, context)?;
+    let right_label = match right {
+        Operator::From(dataset) => dataset.to_string(),
+        subquery => format!("({})", subquery),
+    };
+    // The index over the join column of a dataset is kept for later joins, including when the
+    // dataset was narrowed to the columns used by the query.
+    let indexed_dataset = indexed_dataset(right).cloned();
+
+    // Load the right table. A dataset is shared with the cache instead of being copied row by row.
+    // This can throw [`OperatorError::CSVError`].
+    let (right, right_qualified_names) = match right {
+        Operator::From(dataset) => {
+            let table = load_dataset(dataset, kind.keyword(), context)?;
+            context.record_scanned(table.rows.len());
+            let qualified_names = dataset.qualified_names(&table.header);
+            (table, qualified_names)
+        }
+        subquery => {
+            let table = process_operator(subquery, context)?;
+            let qualified_names = table.qualified_names.clone();
+            (Arc::new(table), qualified_names)
+        }
+    };
+
+    // Make sure the columns to join on are in the 'left' and 'right' tables.
+    let right_name = right_column.unwrap_or(&column);
+    let (Some(left_index), Some(right_index)) = (
+        left.find_column_index_by_name(&column),
+        right.find_column_index_by_name(right_name),
+    ) else {
+        let (column_name, header) = if left.header.contains(&column) {
+            (right_name.to_string(), &right.header)
+        } else {
+            (column, &left.header)
+        };
+        return Err(OperatorError::NoSuchColumn {
+            operator: kind.keyword().to_string(),
+            chain: Box::new(chain.clone()),
+            suggestions: suggest_columns(header, &column_name),
+            column_name,
+        });
+    };
+    // The 'right' table's join column duplicates the 'left' one when they share a name.
+    let dropped_index = right_column.is_none().then_some(right_index);
+
+    // Construct the new header by concatenating the headers of the 'left' and 'right' tables,
+    // taking care to remove the common column from the 'right' table. The other columns of the
+    // 'right' table that share a name with a column of the 'left' one, e.g. all of them when a
+    // table is joined with itself, are renamed so every column can be referred to.
+    // The columns keep their qualified names, so the renamed ones can be referred to by them too.
+    let mut header = left.header.clone();
+    let mut qualified_names = left.qualified_names.clone();
+    qualified_names.resize(left.header.len(), None);
+    let mut schema = left.schema.clone();
+    for (index, name) in right.header.iter().enumerate() {
+        if Some(index) != dropped_index {
+            header.push(unique_column_name(name, &header));
+            qualified_names.push(right_qualified_names.get(index).cloned().flatten());
+            schema.push(right.schema[index].renamed(&header[header.len() - 1]));
+        }
+    }
+
+    // Perform the actual join using the "hash join" or "sort-merge join" algorithm.
+    // The index is only used when the 'right' table is the smaller one, as building a hash index
+    // over the larger table, even once, costs more than hashing the smaller one for every join.
+    let cancelled = context.cancelled.clone();
+    let indexed = match indexed_dataset {
+        Some(dataset) if strategy == JoinStrategy::Hash && right.rows.len() <= left.rows.len() => {
+            let table = load_dataset(&dataset, kind.keyword(), context)?;
+            // The index is over the whole dataset, whose rows are those of the 'right' table.
+            table
+                .find_column_index_by_name(right_name)
+                .filter(|_| table.rows.len() == right.rows.len())
+                .map(|col_index| (dataset, table, col_index))
+        }
+        _ => None,
+    };
+    let matches = match (strategy, indexed) {
+        (JoinStrategy::Hash, Some((dataset, table, col_index))) => context
+            .join_indexes
+            .get_or_build(&dataset, &table, col_index)
+            .probe(&left.rows, left_index, &cancelled),
+        (JoinStrategy::Hash, None) => {
+            hash_join_matches(&left.rows, left_index, &right.rows, right_index, &cancelled)
+        }
+        (JoinStrategy::Merge, _) => {
+            merge_join_matches(&left.rows, left_index, &right.rows, right_index, &cancelled)
+        }
+    };
+
+    // Helper to append the cells of a 'right' row, or NULLs if there is none, to the `cells` of a
+    // 'left' row.
+    let append_right = |mut cells: Vec<Cell>, right_row: Option<&Row>| {
+        for index in (0..right.header.len()).filter(|index| Some(*index) != dropped_index) {
+            cells.push(right_row.map_or(Cell::Null, |row| row.cells[index].clone()));
+        }
+        Row { cells }
+    };
+
+    // The joined rows are checked against the limits as they are added, as the result of a join
+    // can be much larger than its inputs.
+    // They are also where a cancelled join stops.
+    let limits = context.limits;
+    let context = &*context;
+    let mut bytes = 0;
+    let mut push = |rows: &mut Vec<Row>, row: Row| {
+        context.check_cancelled()?;
+        bytes += row.estimated_size();
+        rows.push(row);
+        limits.check(rows.len(), bytes, || {
+            format!("{} {}", kind.keyword(), right_label)
+        })
+    };
+
+    let mut rows: Vec<Row> = Vec::with_capacity(matches.len());
+    let mut right_matched = vec![false; right.rows.len()];
+    // Whether the columns of the 'left' or the 'right' table were filled with NULLs for a row.
+    let (mut padded_left, mut padded_right) = (false, false);
+    let mut matches = matches.into_iter().peekable();
+    for (left_row, row) in left.rows.iter().enumerate() {
+        let mut matched = false;
+        while let Some((_, right_row)) = matches.next_if(|(l, _)| *l == left_row) {
+            push(
+                &mut rows,
+                append_right(row.cells.clone(), Some(&right.rows[right_row])),
+            )?;
+            right_matched[right_row] = true;
+            matched = true;
+        }
+        if !matched && kind.keeps_unmatched_left() {
+            push(&mut rows, append_right(row.cells.clone(), None))?;
+            padded_right = true;
+        }
+    }
+    if kind.keeps_unmatched_right() {
+        for (row, _) in right
+            .rows
+            .iter()
+            .zip(right_matched)
+            .filter(|(_, matched)| !matched)
+        {
+            // The shared join column comes from the 'right' row, so it isn't missing.
+            let mut cells = vec![Cell::Null; left.header.len()];
+            if dropped_index.is_some() {
+                cells[left_index] = row.cells[right_index].clone();
+            }
+            push(&mut rows, append_right(cells, Some(row)))?;
+            padded_left = true;
+        }
+    }
+
+    // The columns of a table are filled with NULLs for the unmatched rows of the other one, so
+    // they may be missing values if there are any.
+    for (index, column) in schema.iter_mut().enumerate() {
+        column.nullable |= match index < left.header.len() {
+            true => padded_left && (dropped_index.is_none() || index != left_index),
+            false => padded_right,
+        };
+    }
+
+    Ok(Table {
+        header,
+        schema,
+        rows,
+        qualified_names,
+    })
+}
+
+#[test]
+fn test_process_join_simple() {
+    let result = process_join(
+        &Box::new(Operator::From(Dataset::City)),
+        &Operator::From(Dataset::Country),
+        "CountryCode".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 4079);
+    assert_eq!(
+        result.rows[4066].cells,
+        vec![
+            Cell::Int64(4067),
+            Cell::String("Charlotte_Amalie".to_string()),
+            Cell::String("VIR".to_string()),
+            Cell::Int64(13000),
+            Cell::String("Virgin_Islands_U.S.".to_string()),
+            Cell::String("North_America".to_string()),
+            Cell::Int64(93000),
+            Cell::OptInt64(Some(4067))
+        ]
+    )
+}
+
+#[test]
+fn test_process_join_reuses_index() {
+    let join = |context: &mut ExecutionContext| {
+        process_join(
+            &Operator::From(Dataset::City),
+            &Operator::From(Dataset::Country),
+            "CountryCode".to_string(),
+            None,
+            JoinKind::Inner,
+            JoinStrategy::Hash,
+            context,
+        )
+        .unwrap()
+    };
+    let mut context = ExecutionContext::default();
+    let indexed_rows = |context: &ExecutionContext| {
+        context.join_indexes.get(&Dataset::Country, 0).unwrap().rows
+            [&Cell::String("NLD".to_string())]
+            .as_ptr()
+    };
+    let first = join(&mut context);
+    let index = indexed_rows(&context);
+    // Joining the same dataset on the same column again reuses the index.
+    assert_eq!(join(&mut context), first);
+    assert_eq!(indexed_rows(&context), index);
+    assert_eq!(context.join_indexes.len(), 1);
+
+    // Modifying the dataset rebuilds the index over the modified table.
+    context
+        .insert_rows(
+            &Dataset::Country,
+            vec![vec![
+                Cell::String("ATL".to_string()),
+                Cell::String("Atlantis".to_string()),
+                Cell::String("Atlantic".to_string()),
+                Cell::Int64(5),
+                Cell::Int64(1),
+            ]],
+        )
+        .unwrap();
+    assert_eq!(join(&mut context).rows.len(), first.rows.len());
+    assert_ne!(indexed_rows(&context), index);
+    assert!(context
+        .join_indexes
+        .get(&Dataset::Country, 0)
+        .unwrap()
+        .rows
+        .contains_key(&Cell::String("ATL".to_string())));
+    assert_eq!(context.join_indexes.len(), 1);
+
+    // Saving the dataset to its file drops the index, which is over the modified table.
+    let path = std::env::temp_dir().join("toy-query-engine-join-index.csv");
+    let path = path.to_string_lossy().to_string();
+    context.save_dataset(&Dataset::Country, &path).unwrap();
+    assert_eq!(context.join_indexes.len(), 1);
+    let saved = Dataset::Csv(path.clone());
+    let table = load_dataset(&saved, "JOIN", &mut context).unwrap();
+    context.join_indexes.get_or_build(&saved, &table, 0);
+    assert_eq!(context.join_indexes.len(), 2);
+    context.save_dataset(&saved, &path).unwrap();
+    assert_eq!(context.join_indexes.len(), 1);
+    std::fs::remove_file(&path).unwrap();
+
+    // The datasets of a WITH are only defined for a single query, so they aren't indexed.
+    let with =
+        crate::commands::parse_command("WITH c AS (FROM country) FROM city JOIN c CountryCode\n");
+    let crate::commands::Command::Operator(operator) = with else {
+        panic!("Unexpected command: {:?}", with);
+    };
+    assert_eq!(
+        process_operator(&operator, &mut context)
+            .unwrap()
+            .rows
+            .len(),
+        first.rows.len()
+    );
+    assert_eq!(context.join_indexes.len(), 1);
+
+    context.clear_cache();
+    assert!(context.join_indexes.is_empty());
+}
+
+#[test]
+fn test_process_join_reuses_index_after_pushdown() {
+    // The optimizer narrows country to the columns used by the query, which still reuses the
+    // index over the whole dataset.
+    let command = crate::commands::parse_command(
+        "FROM city JOIN country CountryCode SELECT CityName,Continent
+",
+    );
+    let crate::commands::Command::Operator(operator) = command else {
+        panic!("Unexpected command: {:?}", command);
+    };
+    let operator = crate::optimizer::optimize(&operator);
+    let Operator::Select { chain, .. } = &operator else {
+        panic!("Unexpected operator: {:?}", operator);
+    };
+    let Operator::Join { right, .. } = chain.as_ref() else {
+        panic!("Unexpected operator: {:?}", chain);
+    };
+    assert!(matches!(right.as_ref(), Operator::Select { .. }));
+
+    let mut context = ExecutionContext::default();
+    let first = process_operator(&operator, &mut context).unwrap();
+    let index = context.join_indexes.get(&Dataset::Country, 0).unwrap().rows
+        [&Cell::String("NLD".to_string())]
+        .as_ptr();
+    assert_eq!(process_operator(&operator, &mut context).unwrap(), first);
+    assert_eq!(
+        context.join_indexes.get(&Dataset::Country, 0).unwrap().rows
+            [&Cell::String("NLD".to_string())]
+            .as_ptr(),
+        index
+    );
+    assert_eq!(context.join_indexes.len(), 1);
+    assert_eq!(
+        first.rows[0].cells,
+        vec![
+            Cell::String("Kabul".to_string()),
+            Cell::String("Asia".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_process_join_index_aliased_subquery() {
+    // The sub-query renames CityName to CountryCode, so the index over the CountryCode column of
+    // city.csv doesn't hold its rows. The hash join finds the same (no) matches as the merge join.
+    let count = |query: &str| {
+        let command = crate::commands::parse_command(query);
+        let crate::commands::Command::Operator(operator) = command else {
+            panic!("Unexpected command: {:?}", command);
+        };
+        process_operator(&operator, &mut ExecutionContext::default())
+            .unwrap()
+            .rows[0]
+            .join()
+    };
+    let query = "FROM city JOIN (FROM city SELECT CityName AS CountryCode) CountryCode";
+    assert_eq!(count(&format!("{} COUNT\n", query)), "0");
+    assert_eq!(count(&format!("{} USING MERGE COUNT\n", query)), "0");
+    assert_eq!(
+        indexed_dataset(&Operator::Select {
+            chain: Box::new(Operator::From(Dataset::City)),
+            columns: crate::expression::parse_select_list("CityName AS CountryCode").unwrap(),
+        }),
+        None
+    );
+}
+
+#[test]
+fn test_process_join_indexes_smaller_side() {
+    // The 'right' table is the larger one, so the smaller 'left' one is hashed instead of building
+    // an index over it.
+    let mut context = ExecutionContext::default();
+    let result = process_join(
+        &Operator::From(Dataset::Country),
+        &Operator::From(Dataset::City),
+        "CountryCode".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut context,
+    )
+    .unwrap();
+    assert!(!result.rows.is_empty());
+    assert!(context.join_indexes.is_empty());
+}
+
+#[test]
+fn test_join_index_cache() {
+    let table = |rows: i64| {
+        Arc::new(Table::new(
+            vec!["n".to_string()],
+            (0..rows)
+                .map(|value| Row {
+                    cells: vec![Cell::Int64(value)],
+                })
+                .collect(),
+        ))
+    };
+    let mut cache = JoinIndexCache::default();
+    let tables: Vec<Arc<Table>> = (0..C_JOIN_INDEX_CACHE_SIZE as i64).map(table).collect();
+    for (index, table) in tables.iter().enumerate() {
+        cache.get_or_build(&Dataset::Variable(index.to_string()), table, 0);
+    }
+    assert_eq!(cache.len(), C_JOIN_INDEX_CACHE_SIZE);
+
+    // Using an index marks it as the most recently used, so the next one is dropped to make room.
+    cache.get_or_build(&Dataset::Variable("0".to_string()), &tables[0], 0);
+    cache.get_or_build(&Dataset::City, &table(1), 0);
+    assert_eq!(cache.len(), C_JOIN_INDEX_CACHE_SIZE);
+    assert!(cache.get(&Dataset::Variable("0".to_string()), 0).is_some());
+    assert!(cache.get(&Dataset::Variable("1".to_string()), 0).is_none());
+
+    // The indexes over a table the dataset no longer holds are dropped.
+    let variable = Dataset::Variable("2".to_string());
+    cache.forget_stale(&variable, &tables[2]);
+    assert!(cache.get(&variable, 0).is_some());
+    cache.forget_stale(&variable, &table(2));
+    assert!(cache.get(&variable, 0).is_none());
+
+    cache.forget(&Dataset::City);
+    assert_eq!(cache.len(), C_JOIN_INDEX_CACHE_SIZE - 2);
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_process_join_complex() {
+    let result = process_join(
+        &Box::new(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::City)),
+            right: Box::new(Operator::From(Dataset::Country)),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        }),
+        &Operator::From(Dataset::Language),
+        "CountryCode".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 30670);
+    assert_eq!(
+        result.rows[30668].cells,
+        vec![
+            Cell::Int64(4079),
+            Cell::String("Rafah".to_string()),
+            Cell::String("PSE".to_string()),
+            Cell::Int64(92020),
+            Cell::String("Palestine".to_string()),
+            Cell::String("Asia".to_string()),
+            Cell::Int64(3101000),
+            Cell::OptInt64(Some(4074)),
+            Cell::String("Arabic".to_string()),
+        ]
+    )
+}
+
+#[test]
+fn test_process_join_no_such_column_left() {
+    let result = process_join(
+        &Box::new(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::City)),
+            right: Box::new(Operator::From(Dataset::Country)),
+            column: "Language".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        }),
+        &Operator::From(Dataset::Language),
+        "CountryCode".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_err());
+    let result = result.unwrap_err();
+    assert_eq!(result.to_string(), "Could not find the Language column to JOIN on the table produced by this operator chain: FROM city.csv".to_string());
+}
+
+#[test]
+fn test_process_join_no_such_column_right() {
+    let result = process_join(
+        &Box::new(Operator::Join {
+            chain: Box::new(Operator::From(Dataset::City)),
+            right: Box::new(Operator::From(Dataset::Country)),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        }),
+        &Operator::From(Dataset::Language),
+        "Capital".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_err());
+    let result = result.unwrap_err();
+    assert_eq!(result.to_string(), "Could not find the Capital column to JOIN on the table produced by this operator chain: FROM city.csv JOIN country.csv CountryCode".to_string());
+}
+
+#[test]
+fn test_process_join_left() {
+    let join = |kind| {
+        process_join(
+            &Operator::From(Dataset::Country),
+            &Operator::From(Dataset::City),
+            "CountryCode".to_string(),
+            None,
+            kind,
+            JoinStrategy::Hash,
+            &mut ExecutionContext::default(),
+        )
+        .unwrap()
+    };
+    let inner = join(JoinKind::Inner);
+    let left = join(JoinKind::Left);
+    // Antarctica has no cities, so it is only kept by the LEFTJOIN, with NULL city columns.
+    let antarctica = left
+        .rows
+        .iter()
+        .find(|row| row.cells[0] == Cell::String("ATA".to_string()))
+        .unwrap();
+    assert_eq!(antarctica.cells[5..], [Cell::Null, Cell::Null, Cell::Null]);
+    assert!(!inner
+        .rows
+        .iter()
+        .any(|row| row.cells[0] == Cell::String("ATA".to_string())));
+    assert!(left.rows.len() > inner.rows.len());
+    // The city columns now have missing values, so they are no longer numeric.
+    assert_eq!(left.numeric_columns(), vec!["CountryPop"]);
+    assert!(left.schema[5..].iter().all(|column| column.nullable));
+    assert_eq!(left.schema[6].data_type, DataType::String);
+    assert!(!left.schema[0].nullable);
+    assert_eq!(
+        inner.numeric_columns(),
+        vec!["CountryPop", "CityID", "CityPop"]
+    );
+}
+
+#[test]
+fn test_process_join_merge() {
+    let join = |column: &str, right_column: Option<&str>, kind, strategy| {
+        process_join(
+            &Operator::From(Dataset::Country),
+            &Operator::From(Dataset::City),
+            column.to_string(),
+            right_column,
+            kind,
+            strategy,
+            &mut ExecutionContext::default(),
+        )
+        .unwrap()
+        .to_string()
+    };
+    // Both strategies produce the same rows, in the same order.
+    for kind in [
+        JoinKind::Inner,
+        JoinKind::Left,
+        JoinKind::Right,
+        JoinKind::Full,
+    ] {
+        assert_eq!(
+            join("CountryCode", None, kind, JoinStrategy::Merge),
+            join("CountryCode", None, kind, JoinStrategy::Hash)
+        );
+        assert_eq!(
+            join("Capital", Some("CityID"), kind, JoinStrategy::Merge),
+            join("Capital", Some("CityID"), kind, JoinStrategy::Hash)
+        );
+    }
+}
+
+#[test]
+fn test_process_join_right_and_full() {
+    let join = |kind| {
+        process_join(
+            &Operator::From(Dataset::City),
+            &Operator::From(Dataset::Country),
+            "CountryCode".to_string(),
+            None,
+            kind,
+            JoinStrategy::Hash,
+            &mut ExecutionContext::default(),
+        )
+        .unwrap()
+    };
+    let inner = join(JoinKind::Inner);
+    let right = join(JoinKind::Right);
+    let full = join(JoinKind::Full);
+    // Every city has a country, so the unmatched countries are appended after the matched rows,
+    // with their CountryCode filled in and NULL city columns.
+    assert!(inner
+        .rows
+        .iter()
+        .zip(&right.rows)
+        .all(|(inner, right)| inner.cells == right.cells));
+    let unmatched = &right.rows[inner.rows.len()..];
+    assert!(!unmatched.is_empty());
+    assert!(unmatched
+        .iter()
+        .all(|row| row.cells[0] == Cell::Null && !row.cells[2].is_null()));
+    assert_eq!(full.rows.len(), right.rows.len());
+}
+
+#[test]
+fn test_process_join_self() {
+    let mut context = ExecutionContext::default();
+    // The first 4 cities are Afghan, and the next 16 Dutch.
+    let cities = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 20,
+    };
+    let result = process_join(
+        &cities,
+        &Operator::From(Dataset::City),
+        "CountryCode".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(
+        result.header,
+        vec![
+            "CityID",
+            "CityName",
+            "CountryCode",
+            "CityPop",
+            "CityID_2",
+            "CityName_2",
+            "CityPop_2"
+        ]
+    );
+    assert_eq!(
+        result.numeric_columns(),
+        vec!["CityID", "CityPop", "CityID_2", "CityPop_2"]
+    );
+    // Every city is paired with each city of its country, including itself.
+    assert!(result
+        .rows
+        .iter()
+        .any(|row| row.cells[0] == row.cells[4] && row.cells[1] == row.cells[5]));
+    assert!(result.rows.iter().any(|row| row.cells[0] != row.cells[4]));
+
+    // The renamed columns can be used by later operators.
+    let result = process_operator(
+        &Operator::OrderBy {
+            chain: Box::new(Operator::Join {
+                chain: Box::new(cities.clone()),
+                right: Box::new(Operator::From(Dataset::City)),
+                column: "CountryCode".to_string(),
+                right_column: None,
+                kind: JoinKind::Inner,
+                strategy: None,
+            }),
+            column: "CityPop_2".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::Last,
+        },
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(result.rows[0].cells[5], Cell::String("Kabul".to_string()));
+}
+
+#[test]
+fn test_process_join_qualified_names() {
+    let mut context = ExecutionContext::default();
+    let join = |right: Operator| Operator::Join {
+        chain: Box::new(Operator::From(Dataset::City)),
+        right: Box::new(right),
+        column: "CountryCode".to_string(),
+        right_column: None,
+        kind: JoinKind::Inner,
+        strategy: None,
+    };
+    let table = process_operator(&join(Operator::From(Dataset::Country)), &mut context).unwrap();
+    assert_eq!(
+        table.qualified_names,
+        [
+            "city.CityID",
+            "city.CityName",
+            "city.CountryCode",
+            "city.CityPop",
+            "country.CountryName",
+            "country.Continent",
+            "country.CountryPop",
+            "country.Capital"
+        ]
+        .map(|name| Some(name.to_string()))
+    );
+
+    // The qualified names can be used by later operators, and are kept by those passing the
+    // columns through.
+    let result = process_operator(
+        &Operator::Select {
+            chain: Box::new(Operator::Filter {
+                chain: Box::new(join(Operator::From(Dataset::Country))),
+                predicate: parse_predicate("country.Continent", "=", "Oceania").unwrap(),
+            }),
+            columns: crate::expression::parse_select_list(
+                "city.CityName,country.CountryName AS Country",
+            )
+            .unwrap(),
+        },
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(result.header, vec!["city.CityName", "Country"]);
+    assert_eq!(
+        result.qualified_names,
+        vec![
+            Some("city.CityName".to_string()),
+            Some("country.CountryName".to_string())
+        ]
+    );
+    assert_eq!(result.rows[0].join(), "Tafuna,American_Samoa");
+
+    // A dataset joined with itself gives both copies of a column the same qualified name.
+    let error = process_operator(
+        &Operator::OrderBy {
+            chain: Box::new(join(Operator::From(Dataset::City))),
+            column: "city.CityPop".to_string(),
+            order: SortOrder::Descending,
+            nulls: NullsOrder::Last,
+        },
+        &mut context,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "The city.CityPop column to ORDERBY is ambiguous, as it may refer to any of the columns [CityPop, CityPop_2] of the table produced by this operator chain: FROM city.csv JOIN city.csv CountryCode"
+    );
+}
+
+#[test]
+fn test_process_join_subquery() {
+    let europe = Operator::Select {
+        chain: Box::new(Operator::Filter {
+            chain: Box::new(Operator::From(Dataset::Country)),
+            predicate: parse_predicate("Continent", "=", "Europe").unwrap(),
+        }),
+        columns: vec![
+            Expression::Column("CountryCode".to_string()).into(),
+            Expression::Column("CountryName".to_string()).into(),
+        ],
+    };
+    let table = process_join(
+        &Operator::From(Dataset::City),
+        &europe,
+        "CountryCode".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    // Only the columns selected by the sub-query are added.
+    assert_eq!(
+        table.header,
+        vec![
+            "CityID",
+            "CityName",
+            "CountryCode",
+            "CityPop",
+            "CountryName"
+        ]
+    );
+    assert_eq!(table.numeric_columns(), vec!["CityID", "CityPop"]);
+    assert!(!table.rows.is_empty());
+    assert!(table
+        .rows
+        .iter()
+        .all(|row| row.cells[4] != Cell::String("Afghanistan".to_string())));
+
+    // Errors in the sub-query are reported as is.
+    let error = process_join(
+        &Operator::From(Dataset::City),
+        &Operator::Take {
+            chain: Box::new(Operator::OrderBy {
+                chain: Box::new(Operator::From(Dataset::Country)),
+                column: "CountryName".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
+            }),
+            count: 1,
+        },
+        "CountryCode".to_string(),
+        None,
+        JoinKind::Inner,
+        JoinStrategy::Hash,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "You attempted to ORDERBY the CountryName column whose type is not numeric."
+    );
+}
+
+#[test]
+fn test_process_join_limits() {
+    let mut context = ExecutionContext::default();
+    let join = Operator::Join {
+        chain: Box::new(Operator::From(Dataset::City)),
+        right: Box::new(Operator::From(Dataset::Country)),
+        column: "CountryCode".to_string(),
+        right_column: None,
+        kind: JoinKind::Inner,
+        strategy: None,
+    };
+    context.limits.max_rows = Some(4079);
+    assert_eq!(
+        process_operator(&join, &mut context).unwrap().rows.len(),
+        4079
+    );
+
+    // The join is stopped as soon as its result grows past a limit. The first 20 cities are
+    // paired with the 272 cities of their countries.
+    context.limits.max_rows = Some(100);
+    let self_join = Operator::Join {
+        chain: Box::new(Operator::Take {
+            chain: Box::new(Operator::From(Dataset::City)),
+            count: 20,
+        }),
+        right: Box::new(Operator::From(Dataset::City)),
+        column: "CountryCode".to_string(),
+        right_column: None,
+        kind: JoinKind::Inner,
+        strategy: None,
+    };
+    assert_eq!(
+        process_operator(&self_join, &mut context).unwrap_err().to_string(),
+        "The result of JOIN city.csv grew past the limit of 100 rows, so the query was stopped. Narrow the query down, e.g. with FILTER, or raise the limit."
+    );
+    context.limits = ResultLimits {
+        max_rows: None,
+        max_bytes: Some(768 * 1024),
+    };
+    assert!(matches!(
+        process_operator(&join, &mut context),
+        Err(OperatorError::ResultTooLarge { operator, limit })
+            if operator == "JOIN country.csv" && limit == "768.0 KB"
+    ));
+
+    // The results of the other operators are limited too.
+    context.limits.max_bytes = None;
+    context.limits.max_rows = Some(10);
+    assert!(matches!(
+        process_operator(&Operator::From(Dataset::Country), &mut context),
+        Err(OperatorError::ResultTooLarge { operator, .. }) if operator == "FROM country.csv"
+    ));
+    let take = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        count: 10,
+    };
+    assert!(process_operator(&take, &mut context).is_ok());
+}
+
+#[test]
+fn test_process_cancelled() {
+    use std::sync::atomic::Ordering::Relaxed;
+    let mut context = ExecutionContext::default();
+    let join = Operator::Join {
+        chain: Box::new(Operator::From(Dataset::City)),
+        right: Box::new(Operator::From(Dataset::City)),
+        column: "CountryCode".to_string(),
+        right_column: None,
+        kind: JoinKind::Inner,
+        strategy: None,
+    };
+    context.cancelled.store(true, Relaxed);
+    assert!(matches!(
+        process_operator(&join, &mut context),
+        Err(OperatorError::Cancelled)
+    ));
+    assert_eq!(
+        process_operator(&Operator::From(Dataset::Country), &mut context)
+            .unwrap_err()
+            .to_string(),
+        "The query was cancelled."
+    );
+
+    // A cancelled join stops looking for matches, which may far outnumber the rows.
+    let cities = process_operator(
+        &Operator::From(Dataset::City),
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    assert!(hash_join_matches(&cities.rows, 2, &cities.rows, 2, &context.cancelled).is_empty());
+    assert!(merge_join_matches(&cities.rows, 2, &cities.rows, 2, &context.cancelled).is_empty());
+
+    // The next query runs once the flag is cleared.
+    context.cancelled.store(false, Relaxed);
+    assert_eq!(
+        process_operator(&join, &mut context).unwrap().rows.len(),
+        613619
+    );
+}
+
+#[test]
+fn test_process_join_on() {
+    let join = |right_column, kind| {
+        process_join(
+            &Operator::From(Dataset::Country),
+            &Operator::From(Dataset::City),
+            "Capital".to_string(),
+            Some(right_column),
+            kind,
+            JoinStrategy::Hash,
+            &mut ExecutionContext::default(),
+        )
+    };
+    let capitals = join("CityID", JoinKind::Inner).unwrap();
+    // Both join columns are kept, and city's CountryCode is renamed as country's is kept as is.
+    assert_eq!(
+        capitals.header,
+        vec![
+            "CountryCode",
+            "CountryName",
+            "Continent",
+            "CountryPop",
+            "Capital",
+            "CityID",
+            "CityName",
+            "CountryCode_2",
+            "CityPop"
+        ]
+    );
+    let aruba = &capitals.rows[0];
+    assert_eq!(aruba.cells[1], Cell::String("Aruba".to_string()));
+    assert_eq!(aruba.cells[5], Cell::Int64(129));
+    assert_eq!(aruba.cells[6], Cell::String("Oranjestad".to_string()));
+    assert!(capitals.rows.iter().all(|row| row.cells[0] == row.cells[7]));
+
+    // The Capital of the cities that aren't capitals is left missing.
+    let cities = join("CityID", JoinKind::Right).unwrap();
+    let unmatched = &cities.rows[capitals.rows.len()..];
+    assert_eq!(capitals.rows.len() + unmatched.len(), 4079);
+    assert!(unmatched.iter().all(|row| row.cells[4].is_null()));
+
+    assert_eq!(
+        join("ID", JoinKind::Inner).unwrap_err().to_string(),
+        "Could not find the ID column to JOIN on the table produced by this operator chain: FROM country.csv"
+    );
+}
+
+/// Handles the [`Operator::Union`] operator by processing the [`Operator`] chain and the `right`
+/// one, and appending the rows of the second resulting [`Table`] to the rows of the first.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `right`: The chain of [`Operator`]s producing the rows to append.
+/// `all`: Whether the duplicate rows are kept. Otherwise, only the first of each is produced.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] with the columns of the input, producing its rows followed by the
+/// rows of the `right` table.
+/// On failure: [`OperatorError::IncompatibleUnion`] if the tables don't have the same number of
+/// columns, or a pair of their columns hold different types of values (see [`union_mismatch`]),
+/// or other [`OperatorError`] from processing the chained operators. The types of the columns
+/// computed one row at a time, e.g. by SELECT, are only known from the rows produced so far, so
+/// such a mismatch may only be found once some rows have been produced.
+fn process_union<'a>(
+    chain: &'a Operator,
+    right: &'a Operator,
+    all: bool,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open both chains of operators to stream the rows for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let left = open_operator(chain, context)?;
+    let right = open_operator(right, context)?;
+
+    // The columns are matched by position, so their number must match.
+    if left.header.len() != right.header.len() {
+        return Err(OperatorError::IncompatibleUnion {
+            left: left.header,
+            right: right.header,
+            mismatch: None,
+        });
+    }
+    let left_types: Vec<DataType> = left
+        .schema()
+        .iter()
+        .map(|column| column.data_type)
+        .collect();
+    let right_types: Vec<DataType> = right
+        .schema()
+        .iter()
+        .map(|column| column.data_type)
+        .collect();
+    if let Some(mismatch) = union_mismatch(&left_types, &right_types) {
+        return Err(OperatorError::IncompatibleUnion {
+            left: left.header,
+            right: right.header,
+            mismatch: Some(mismatch),
+        });
+    }
+
+    let qualified_names = left.qualified_names.clone();
+    Ok(RowStream::new(
+        left.header.clone(),
+        UnionRows {
+            left,
+            right,
+            left_done: false,
+            seen: (!all).then(HashSet::new),
+            left_types,
+            right_types,
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
+
+/// Helper function to find the first pair of columns combined by UNION that hold different types
+/// of values, given the types of the columns of each table. Integers and decimal numbers go
+/// together, and a column without values, or already holding values of several types, goes with
+/// any other.
+///
+/// # Returns
+/// The position of the pair of columns, with the type of the left one and of the right one, or
+/// `None` if every pair can be combined.
+fn union_mismatch(left: &[DataType], right: &[DataType]) -> Option<(usize, DataType, DataType)> {
+    left.iter()
+        .zip(right)
+        .position(|(left, right)| {
+            left.merge(*right) == DataType::Mixed
+                && *left != DataType::Mixed
+                && *right != DataType::Mixed
+        })
+        .map(|index| (index, left[index], right[index]))
+}
+
+/// Produces the rows of the [`Operator::Union`] operator.
+struct UnionRows<'a> {
+    left: RowStream<'a>,
+    right: RowStream<'a>,
+    /// Whether all the rows of the `left` input have been produced.
+    left_done: bool,
+    /// The rows produced so far, to skip their duplicates. `None` for UNION ALL, which keeps them.
+    seen: Option<HashSet<Vec<Cell>>>,
+    /// The types of the columns of the `left` input, accounting for the rows it produced so far.
+    left_types: Vec<DataType>,
+    /// The types of the columns of the `right` input, accounting for the rows it produced so far.
+    right_types: Vec<DataType>,
+}
+
+impl UnionRows<'_> {
+    /// Accounts for the values of a `row` of the `left` input, or of the `right` one, in the
+    /// types of its columns.
+    ///
+    /// # Returns
+    /// [`OperatorError::IncompatibleUnion`] if a column of the row now holds a different type of
+    /// values than the matching column of the other input.
+    fn observe(&mut self, row: &Row, left: bool) -> Result<(), OperatorError> {
+        let types = match left {
+            true => &mut self.left_types,
+            false => &mut self.right_types,
+        };
+        for (data_type, cell) in types.iter_mut().zip(&row.cells) {
+            *data_type = data_type.merge(DataType::of(cell));
+        }
+        match union_mismatch(&self.left_types, &self.right_types) {
+            Some(mismatch) => Err(OperatorError::IncompatibleUnion {
+                left: self.left.header.clone(),
+                right: self.right.header.clone(),
+                mismatch: Some(mismatch),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RowSource for UnionRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        loop {
+            let row = if self.left_done {
+                match self.right.next_row(context)? {
+                    Some(row) => {
+                        self.observe(&row, false)?;
+                        row
+                    }
+                    None => return Ok(None),
+                }
+            } else {
+                match self.left.next_row(context)? {
+                    Some(row) => {
+                        self.observe(&row, true)?;
+                        row
+                    }
+                    None => {
+                        self.left_done = true;
+                        continue;
+                    }
+                }
+            };
+            if let Some(seen) = &mut self.seen {
+                if !seen.insert(row.cells.clone()) {
+                    continue;
+                }
+            }
+            return Ok(Some(row));
+        }
+    }
+
+    /// A column holds the values of both inputs, and is named like in the left one.
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.left
+            .schema()
+            .into_iter()
+            .zip(self.right.schema())
+            .map(|(left, right)| ColumnSchema {
+                data_type: left.data_type.merge(right.data_type),
+                nullable: left.nullable || right.nullable,
+                ..left
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_process_union() {
+    let mut context = ExecutionContext::default();
+    let codes = |dataset: Dataset| Operator::Select {
+        chain: Box::new(Operator::From(dataset)),
+        columns: vec![Expression::Column("CountryCode".to_string()).into()],
+    };
+    let (countries, languages) = (codes(Dataset::Country), codes(Dataset::Language));
+    let result = process_union(&countries, &languages, true, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.header, vec!["CountryCode"]);
+    assert_eq!(result.rows.len(), 239 + 984);
+    // The rows of the input come first.
+    assert_eq!(result.rows[0].join(), "ABW");
+    assert_eq!(result.rows[239].join(), "ABW");
+
+    // Every country has a code, so the languages don't add any.
+    let result = process_union(&countries, &languages, false, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.rows.len(), 239);
+    let mut codes: Vec<String> = result.rows.iter().map(Row::join).collect();
+    codes.dedup();
+    assert_eq!(codes.len(), 239);
+}
+
+#[test]
+fn test_process_union_columns() {
+    let mut context = ExecutionContext::default();
+    let select = |dataset: Dataset, columns: &[&str]| Operator::Select {
+        chain: Box::new(Operator::From(dataset)),
+        columns: columns
+            .iter()
+            .map(|column| Expression::Column(column.to_string()).into())
+            .collect(),
+    };
+    // The columns are matched by position, and named after the input's.
+    let result = process_union(
+        &select(Dataset::City, &["CityName", "CityPop"]),
+        &select(Dataset::Country, &["CountryName", "CountryPop"]),
+        true,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context))
+    .unwrap();
+    assert_eq!(result.header, vec!["CityName", "CityPop"]);
+    assert_eq!(result.numeric_columns(), vec!["CityPop"]);
+    assert_eq!(result.rows.len(), 4079 + 239);
+    assert_eq!(result.schema[0].data_type, DataType::String);
+    // Numbers and strings can't be combined.
+    let result = process_union(
+        &select(Dataset::City, &["CityName", "CityPop"]),
+        &select(Dataset::Country, &["CountryPop", "CountryName"]),
+        true,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "UNION can't combine the CityName column, which holds string values, with the CountryPop column, which holds integer values. The columns are matched by their position, and must hold the same type of values."
+    );
+
+    let result = process_union(
+        &select(Dataset::City, &["CityName", "CityPop"]),
+        &select(Dataset::Country, &["CountryName"]),
+        false,
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "UNION can't combine the columns [CityName, CityPop] with the columns [CountryName]. Both tables must have the same number of columns."
+    );
+}
+
+#[test]
+fn test_union_mismatch() {
+    use DataType::*;
+    assert_eq!(union_mismatch(&[String, Integer], &[String, Decimal]), None);
+    assert_eq!(union_mismatch(&[Empty, Mixed], &[Integer, String]), None);
+    assert_eq!(
+        union_mismatch(&[String, Integer, String], &[String, String, Integer]),
+        Some((1, Integer, String))
+    );
+}
+
+#[test]
+fn test_process_union_types() {
+    let mut context = ExecutionContext::default();
+    let mut run = |query: &str| match crate::commands::parse_command(&format!("{}\n", query)) {
+        crate::commands::Command::Operator(operator) => {
+            process_operator(&operator, &mut context).map_err(|error| error.to_string())
+        }
+        command => panic!("{:?}", command),
+    };
+    assert_eq!(
+        run("FROM city.csv SELECT CityID UNION (FROM country.csv SELECT CountryCode)"),
+        Err("UNION can't combine the CityID column, which holds integer values, with the CountryCode column, which holds string values. The columns are matched by their position, and must hold the same type of values.".to_string())
+    );
+    assert_eq!(
+        run("FROM language.csv SELECT CountryCode UNION (FROM language.csv SELECT CountryCode COUNT)"),
+        Err("UNION can't combine the CountryCode column, which holds string values, with the COUNT(*) column, which holds integer values. The columns are matched by their position, and must hold the same type of values.".to_string())
+    );
+    // The type of a computed column is found from its values, even once some rows are produced.
+    assert_eq!(
+        run("FROM city SELECT CityName UNION ALL (FROM city EXTEND Double = CityPop * 2 SELECT Double)"),
+        Err("UNION can't combine the CityName column, which holds string values, with the Double column, which holds integer values. The columns are matched by their position, and must hold the same type of values.".to_string())
+    );
+    // Missing values go with any type.
+    assert_eq!(
+        run("FROM country SELECT Capital UNION ALL (FROM city SELECT CityID)")
+            .unwrap()
+            .rows
+            .len(),
+        239 + 4079
+    );
+}
+
+/// Handles the [`Operator::Filter`] operator by processing the [`Operator`] chain and keeping
+/// only the rows of the resulting [`Table`] that satisfy the `predicate`.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `predicate`: The condition a row must satisfy to be kept.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing only the matching rows.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::InvalidComparison`], or other
+/// [`OperatorError`] from processing the chained operators.
+fn process_filter<'a>(
+    chain: &'a Operator,
+    predicate: &'a Predicate,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
+
+    // Make sure all the columns referenced by the `predicate` exist, and refer to them by their
+    // names in the input, rather than by their qualified names.
+    let mut indices = HashMap::new();
+    for name in predicate.left.column_names() {
+        // This can throw the [`OperatorError::NoSuchColumn`] error.
+        let index =
+            find_column_index(&input.header, &input.qualified_names, name, chain, "FILTER")?;
+        indices.insert(name.to_string(), index);
+    }
+    let predicate = Predicate {
+        left: predicate
+            .left
+            .rename_columns(&|name| input.header[indices[name]].clone()),
+        ..predicate.clone()
+    };
+    let qualified_names = input.qualified_names.clone();
+
+    Ok(
+        RowStream::new(input.header.clone(), FilterRows { input, predicate })
+            .with_qualified_names(qualified_names),
+    )
+}
+
+/// Produces the rows of the [`Operator::Filter`] operator.
+struct FilterRows<'a> {
+    input: RowStream<'a>,
+    /// The predicate, referring to the columns by their names in the input.
+    predicate: Predicate,
+}
+
+impl RowSource for FilterRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        while let Some(row) = self.input.next_row(context)? {
+            if self.predicate.matches(&self.input.header, &row, context)? {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.input.schema()
+    }
+}
+
+#[test]
+fn test_process_filter() {
+    let mut context = ExecutionContext::default();
+    let result = process_filter(
+        &Operator::From(Dataset::Country),
+        &parse_predicate("Continent", "=", "Europe").unwrap(),
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 46);
+    assert_eq!(result.header.len(), 5);
+
+    let result = process_filter(
+        &Operator::From(Dataset::City),
+        &parse_predicate("CityPop", ">", "9000000").unwrap(),
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert_eq!(result.unwrap().rows.len(), 6);
+}
+
+#[test]
+fn test_process_filter_errors() {
+    let mut context = ExecutionContext::default();
+    let result = process_filter(
+        &Operator::From(Dataset::Country),
+        &parse_predicate("CityPop", ">", "1").unwrap(),
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert_eq!(result.unwrap_err().to_string(), "Could not find the CityPop column to FILTER on the table produced by this operator chain: FROM country.csv");
+
+    let result = process_filter(
+        &Operator::From(Dataset::Country),
+        &parse_predicate("CountryPop", ">", "many").unwrap(),
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Cannot compare the numeric values of CountryPop with the string many."
+    );
+}
+
+/// Handles the [`Operator::With`] operator by processing each of the sub-queries in `ctes`,
+/// registering their results in the query-scoped catalog, and then processing the `query`.
+///
+/// # Arguments:
+/// `ctes`: The names and definitions of the sub-queries, in the order they were defined.
+/// `query`: The query to run once the sub-queries are registered.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: The [`RowStream`] of the `query`.
+/// On failure: [`OperatorError`] from processing the sub-queries or the `query`.
+fn process_with<'a>(
+    ctes: &[(String, Operator)],
+    query: &'a Operator,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    let mut shadowed = Vec::new();
+    let mut result = Ok(());
+    for (name, cte) in ctes {
+        match process_operator(cte, context) {
+            Ok(table) => {
+                let table = Arc::new(table);
+                shadowed.push((name, context.catalog.insert(name.clone(), table)));
+            }
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+    }
+    // The FROM and JOIN operators of the `query` take their tables from the catalog when they are
+    // opened, so the names can be restored before any of its rows are produced.
+    let result = result.and_then(|_| open_operator(query, context));
+
+    // The names are only valid within this query, so restore whatever they referred to before.
+    for (name, previous) in shadowed.into_iter().rev() {
+        match previous {
+            Some(table) => context.catalog.insert(name.clone(), table),
+            None => context.catalog.remove(name),
+        };
+    }
+    result
+}
+
+#[test]
+fn test_process_with() {
+    let euro = Operator::Filter {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        predicate: parse_predicate("Continent", "=", "Europe").unwrap(),
+    };
+    let mut context = ExecutionContext::default();
+    let result = process_with(
+        &[("euro".to_string(), euro)],
+        &Operator::Join {
+            chain: Box::new(Operator::From(Dataset::City)),
+            right: Box::new(Operator::From(Dataset::Cte("euro".to_string()))),
+            column: "CountryCode".to_string(),
+            right_column: None,
+            kind: JoinKind::Inner,
+            strategy: None,
+        },
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 841);
+    assert_eq!(result.header.len(), 8);
+    // The catalog is query-scoped.
+    assert!(context.catalog.is_empty());
+}
+
+#[test]
+fn test_process_with_undefined() {
+    let result = process_operator(
+        &Operator::From(Dataset::Cte("euro".to_string())),
+        &mut ExecutionContext::default(),
+    );
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "The euro dataset is not defined. Use WITH euro AS (<query>) to define it."
+    );
+}
+
+/// Handles the [`Operator::Map`] operator by processing the [`Operator`] chain and running the
+/// `script` on every row of the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `script`: The path of the rhai script to run. See [`RowTransform`].
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] containing the transformed rows. The columns of the output are the
+/// columns returned by the script for the first row.
+/// On failure: [`OperatorError::ScriptError`] or other [`OperatorError`] from processing the
+/// chained operators.
+fn process_map(
+    chain: &Operator,
+    script: &str,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    let script_error = |message: String| OperatorError::ScriptError {
+        script: script.to_string(),
+        message,
+    };
+    // Load the script before running the chain, so a typo in the path fails fast.
+    let transform = RowTransform::from_file(script).map_err(|e| script_error(e.to_string()))?;
+
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+
+    // An empty table has nothing to transform, so its columns are passed through as is.
+    if table.rows.is_empty() {
+        return Ok(table);
+    }
+
+    let mut header: Option<Vec<String>> = None;
+    let mut rows = Vec::with_capacity(table.rows.len());
+    for (index, row) in table.rows.iter().enumerate() {
+        let columns = transform
+            .apply(&table.header, row)
+            .map_err(|message| script_error(format!("{} (on row {})", message, index + 1)))?;
+        let (names, cells): (Vec<String>, Vec<Cell>) = columns.into_iter().unzip();
+        // The first row decides the columns of the output; every other row must match them.
+        match &header {
+            None => header = Some(names),
+            Some(header) if *header != names => {
+                return Err(script_error(format!(
+                    "Row {} has the columns [{}], but the first row has [{}]. Every row must have the same columns.",
+                    index + 1,
+                    names.join(", "),
+                    header.join(", ")
+                )));
+            }
+            Some(_) => (),
+        }
+        rows.push(Row { cells });
+    }
+    let header = header.unwrap_or_default();
+
+    // The schema of the columns is inferred from their values.
+    Ok(Table::new(header, rows))
+}
+
+/// Helper function to write a MAP script for the tests below to a temporary file.
+#[cfg(test)]
+fn write_test_script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("toy-query-engine-{}.rhai", name));
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+#[test]
+fn test_process_map() {
+    let script = write_test_script(
+        "map",
+        "row.Language = row.Language.to_upper(); row.Length = row.Language.len(); row",
+    );
+    let result = process_map(
+        &Operator::From(Dataset::Language),
+        &script,
+        &mut ExecutionContext::default(),
+    );
+    assert!(result.is_ok());
+    let result = result.unwrap();
+    assert_eq!(result.rows.len(), 984);
+    assert_eq!(result.header, vec!["CountryCode", "Language", "Length"]);
+    assert_eq!(result.numeric_columns(), vec!["Length"]);
+    assert_eq!(result.rows[0].join(), "ABW,DUTCH,5");
+}
+
+#[test]
+fn test_process_map_mismatched_columns() {
+    let script = write_test_script(
+        "map-mismatched",
+        "if row.Language == \"English\" { row.remove(\"CountryCode\"); } row",
+    );
+    let result = process_map(
+        &Operator::From(Dataset::Language),
+        &script,
+        &mut ExecutionContext::default(),
+    );
+    assert_eq!(result.unwrap_err().to_string(), format!("Failed to run the {} script: Row 2 has the columns [Language], but the first row has [CountryCode, Language]. Every row must have the same columns.", script));
+}
+
+#[test]
+fn test_process_map_no_such_script() {
+    let result = process_map(
+        &Operator::From(Dataset::Language),
+        "no-such-script.rhai",
+        &mut ExecutionContext::default(),
+    );
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .starts_with("Failed to run the no-such-script.rhai script: "));
+}
+
+/// Handles the [`Operator::Distinct`] operator by processing the [`Operator`] chain and removing
+/// the duplicate rows from the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `columns`: The names of the columns to compare rows on. If empty, all the columns are compared.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`RowStream`] with the same columns as the input, producing the first row of each
+/// set of duplicates, in their original order.
+/// On failure: [`OperatorError::NoSuchColumn`] or other [`OperatorError`] from processing the
+/// chained operators.
+fn process_distinct<'a>(
+    chain: &'a Operator,
+    columns: &[String],
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
+
+    // Find the indices of the columns to compare rows on.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let col_indices = if columns.is_empty() {
+        (0..input.header.len()).collect()
+    } else {
+        columns
+            .iter()
+            .map(|column| {
+                find_column_index(
+                    &input.header,
+                    &input.qualified_names,
+                    column,
+                    chain,
+                    "DISTINCT",
+                )
+            })
+            .collect::<Result<Vec<usize>, OperatorError>>()?
+    };
+
+    let qualified_names = input.qualified_names.clone();
+    Ok(RowStream::new(
+        input.header.clone(),
+        DistinctRows {
+            input,
+            col_indices,
+            seen: HashSet::new(),
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
+
+/// Produces the rows of the [`Operator::Distinct`] operator.
+struct DistinctRows<'a> {
+    input: RowStream<'a>,
+    /// The indices of the columns to compare rows on.
+    col_indices: Vec<usize>,
+    /// The values in the compared columns of the rows produced so far.
+    seen: HashSet<Vec<Cell>>,
+}
+
+impl RowSource for DistinctRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        // Produce a row only if its values in the compared columns haven't been seen before.
+        while let Some(row) = self.input.next_row(context)? {
+            let key = self
+                .col_indices
+                .iter()
+                .map(|i| row.cells[*i].clone())
+                .collect();
+            if self.seen.insert(key) {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.input.schema()
+    }
+}
+
+#[test]
+fn test_process_distinct() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Select {
+        chain: Box::new(Operator::From(Dataset::Language)),
+        columns: vec![Expression::Column("Language".to_string()).into()],
+    };
+    let result = process_distinct(&chain, &[], &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.header, vec!["Language"]);
+    // The first occurrences are kept, in their original order.
+    assert_eq!(result.rows[0].join(), "Dutch");
+    assert_eq!(result.rows[1].join(), "English");
+    let mut languages: Vec<String> = result.rows.iter().map(Row::join).collect();
+    languages.sort();
+    languages.dedup();
+    assert_eq!(languages.len(), result.rows.len());
+}
+
+#[test]
+fn test_process_distinct_columns() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::OrderBy {
+        chain: Box::new(Operator::From(Dataset::City)),
+        column: "CityPop".to_string(),
+        order: SortOrder::Descending,
+        nulls: NullsOrder::Last,
+    };
+    let result = process_distinct(&chain, &["CountryCode".to_string()], &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    // Whole rows are kept, and the first city of each country is its largest.
+    assert_eq!(result.header.len(), 4);
+    assert_eq!(result.rows[0].join(), "1024,Mumbai_(Bombay),IND,10500000");
+    assert_eq!(result.rows[1].join(), "2331,Seoul,KOR,9981619");
+    assert!(!result
+        .rows
+        .iter()
+        .any(|row| row.join() == "1025,Delhi,IND,7206704"));
+
+    let result = process_distinct(
+        &Operator::From(Dataset::City),
+        &["Continent".to_string()],
+        &mut context,
+    )
+    .and_then(|stream| stream.collect(&mut context));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Could not find the Continent column to DISTINCT on the table produced by this operator chain: FROM city.csv"
+    );
+}
+
+/// Handles the [`Operator::Rename`] operator by processing the [`Operator`] chain and renaming
+/// the `column` of the resulting [`Table`].
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `column`: The name of the column to rename.
+/// `new_name`: The name the column is given.
+/// `context`: The [`ExecutionContext`] the query is processed in.
 ///
 /// # Returns:
-/// On success: A [`Table`] containing only the requested columns.
-/// On failure: [`OperatorError::NoSuchColumn`] or other [`OperatorError`] from processing the
-/// chained operators.
-fn process_select(
-    chain: &Box<Operator>,
-    column_names: &Vec<String>,
-) -> Result<Table, OperatorError> {
-    // Run the chained operators to produce the input for this operator.
-    // Will terminate this function and return the produced error if the processing fails.
-    let table = process_operator(&**chain)?;
+/// On success: A [`RowStream`] producing the rows of the input unchanged, with the `column` named
+/// `new_name`.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::ColumnExists`] if the input
+/// already has a column named `new_name`, or other [`OperatorError`] from processing the chained
+/// operators.
+fn process_rename<'a>(
+    chain: &'a Operator,
+    column: &str,
+    new_name: &str,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
 
-    // Find the indices corresponding to the input `column_names`.
-    let mut col_indices = Vec::<usize>::new();
-    for name in column_names {
-        // This can throw the [`OperatorError::NoSuchColumn`] error.
-        let index = find_column_index(&table, &name, chain, "Select")?;
-        col_indices.push(index);
+    // Find the index corresponding to the `column`.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let col_index = find_column_index(
+        &input.header,
+        &input.qualified_names,
+        column,
+        chain,
+        "RENAME",
+    )?;
+    if input.header[col_index] != new_name && input.header.iter().any(|name| name == new_name) {
+        return Err(OperatorError::ColumnExists {
+            column_name: column.to_string(),
+            new_name: new_name.to_string(),
+        });
     }
 
-    // Construct the output using the col_indices previously calculated.
-    Ok(Table {
-        header: column_names.clone(),
-        rows: table
-            .rows
-            .iter()
-            .map(|row| Row {
-                // Extract the cells at the previously computed col_indices into a new Row.
-                cells: col_indices
-                    .iter()
-                    .map(|index| row.cells[*index].clone())
-                    .collect(),
-            })
-            .collect(),
-        // Extract only those numeric_columns in the input table that are in the `column_names`.
-        numeric_columns: column_names
-            .iter()
-            .filter(|name| table.numeric_columns.contains(name))
-            .map(|name| name.clone())
-            .collect(),
-    })
+    let mut header = input.header.clone();
+    header[col_index] = new_name.to_string();
+    let qualified_names = input.qualified_names.clone();
+    Ok(RowStream::new(
+        header,
+        RenameRows {
+            column: input.header[col_index].clone(),
+            input,
+            new_name: new_name.to_string(),
+        },
+    )
+    .with_qualified_names(qualified_names))
 }
 
-#[test]
-fn test_process_select_single() {
-    let result = process_select(
-        &Box::new(Operator::From(Dataset::Language)),
-        &vec!["Language".to_string()],
-    );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 984);
-    assert_eq!(result.header.len(), 1);
-    assert_eq!(result.header[0], "Language".to_string());
-    assert_eq!(result.rows[0].cells.len(), 1);
+/// Produces the rows of the [`Operator::Rename`] operator.
+struct RenameRows<'a> {
+    input: RowStream<'a>,
+    /// The name of the renamed column in the input.
+    column: String,
+    /// The name of the renamed column in the output.
+    new_name: String,
+}
+
+impl RowSource for RenameRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        self.input.next_row(context)
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.input
+            .schema()
+            .into_iter()
+            .map(|column| match column.name == self.column {
+                true => column.renamed(&self.new_name),
+                false => column,
+            })
+            .collect()
+    }
 }
 
 #[test]
-fn test_process_select_single_non_existant_col() {
-    let result = process_select(
-        &Box::new(Operator::From(Dataset::Language)),
-        &vec!["Capital".to_string()],
+fn test_process_rename() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 2,
+    };
+    let result = process_rename(&chain, "CityPop", "Population", &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(
+        result.header,
+        vec!["CityID", "CityName", "CountryCode", "Population"]
     );
-    assert!(result.is_err());
-    let err = result.unwrap_err();
-    assert_eq!(err.to_string(), "Could not find the Capital column to Select on the table produced by this operator chain: FROM language.csv".to_string())
+    assert_eq!(result.numeric_columns(), vec!["CityID", "Population"]);
+    assert_eq!(result.rows[0].join(), "1,Kabul,AFG,1780000");
+    assert_eq!(result.rows.len(), 2);
+
+    // Renaming a column to its own name changes nothing.
+    let result = process_rename(&chain, "CityPop", "CityPop", &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.numeric_columns(), vec!["CityID", "CityPop"]);
 }
 
 #[test]
-fn test_process_select_multiple() {
-    let result = process_select(
-        &Box::new(Operator::From(Dataset::City)),
-        &vec!["CityID".to_string(), "CityName".to_string()],
+fn test_process_rename_errors() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::From(Dataset::City);
+    let error = |column, new_name, context: &mut ExecutionContext| {
+        process_rename(&chain, column, new_name, context)
+            .err()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        error("CityName", "CityPop", &mut context),
+        "Cannot RENAME CityName to CityPop, as the table already has a CityPop column."
     );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 4079);
-    assert_eq!(result.header.len(), 2);
     assert_eq!(
-        result.header,
-        vec!["CityID".to_string(), "CityName".to_string()]
+        error("Continent", "Region", &mut context),
+        "Could not find the Continent column to RENAME on the table produced by this operator chain: FROM city.csv"
     );
-    assert_eq!(result.rows[0].cells.len(), 2);
 }
 
-/// Handles the [`Operator::Take`] operator by processing the [`Operator`] chain and selecting the
-/// first `count` column(s) from the resulting [`Table`].
+/// Handles the [`Operator::Reorder`] operator by processing the [`Operator`] chain and moving the
+/// `columns` of the resulting [`Table`] to the front.
 ///
 /// # Arguments:
 /// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
 /// this operator.
-/// `count`: Number of rows to retain in the output. If `count` is greater than the number of rows
-/// in the input table, all rows in the input table will be returned.
+/// `columns`: The names of the columns to move to the front, in order.
+/// `context`: The [`ExecutionContext`] the query is processed in.
 ///
 /// # Returns:
-/// On success: A [`Table`] containing only the requested number of rows.
-/// On failure: [`OperatorError`] from processing the chained operators.
-fn process_take(chain: &Box<Operator>, count: usize) -> Result<Table, OperatorError> {
-    // Run the chained operators to produce the input for this operator.
-    // Will terminate this function and return the produced error if the processing fails.
-    let table = process_operator(&**chain)?;
+/// On success: A [`RowStream`] producing the rows with the `columns` first, followed by the other
+/// columns in their original order.
+/// On failure: [`OperatorError::NoSuchColumn`], or other [`OperatorError`] from processing the
+/// chained operators.
+fn process_reorder<'a>(
+    chain: &'a Operator,
+    columns: &[String],
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
 
-    Ok(Table {
-        header: table.header,
-        rows: table
-            .rows
+    // Find the indices corresponding to the `columns`, dropping repeated ones.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let mut order: Vec<usize> = Vec::new();
+    for column in columns {
+        let index = find_column_index(
+            &input.header,
+            &input.qualified_names,
+            column,
+            chain,
+            "REORDER",
+        )?;
+        if !order.contains(&index) {
+            order.push(index);
+        }
+    }
+    let rest: Vec<usize> = (0..input.header.len())
+        .filter(|index| !order.contains(index))
+        .collect();
+    order.extend(rest);
+
+    let qualified_names = order
+        .iter()
+        .map(|index| input.qualified_names.get(*index).cloned().flatten())
+        .collect();
+    Ok(RowStream::new(
+        order
             .iter()
-            .take(count)
-            .map(|row| row.clone())
+            .map(|index| input.header[*index].clone())
             .collect(),
-        numeric_columns: table.numeric_columns,
-    })
+        ReorderRows { input, order },
+    )
+    .with_qualified_names(qualified_names))
 }
 
-#[test]
-fn test_process_take() {
-    let result = process_take(&Box::new(Operator::From(Dataset::Language)), 5);
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 5);
-    assert_eq!(result.header.len(), 2);
-    assert_eq!(
-        result.header,
-        vec!["CountryCode".to_string(), "Language".to_string()]
-    );
-    assert_eq!(result.numeric_columns.len(), 0);
+/// Produces the rows of the [`Operator::Reorder`] operator.
+struct ReorderRows<'a> {
+    input: RowStream<'a>,
+    /// The indices of the input columns, in the order they are produced.
+    order: Vec<usize>,
 }
 
-#[test]
-fn test_process_take_from_empty_table() {
-    let result = process_take(
-        &Box::new(Operator::Take {
-            chain: Box::new(Operator::From(Dataset::Language)),
-            count: 0,
-        }),
-        5,
-    );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 0);
-    assert_eq!(result.header.len(), 2);
-    assert_eq!(
-        result.header,
-        vec!["CountryCode".to_string(), "Language".to_string()]
-    );
-    assert_eq!(result.numeric_columns.len(), 0);
+impl RowSource for ReorderRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        let Some(row) = self.input.next_row(context)? else {
+            return Ok(None);
+        };
+        Ok(Some(Row {
+            cells: self
+                .order
+                .iter()
+                .map(|index| row.cells[*index].clone())
+                .collect(),
+        }))
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        let schema = self.input.schema();
+        self.order
+            .iter()
+            .map(|index| schema[*index].clone())
+            .collect()
+    }
 }
 
 #[test]
-fn test_process_take_more_than_rows_in_data() {
-    let result = process_take(&Box::new(Operator::From(Dataset::Language)), 10000);
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 984);
-    assert_eq!(result.header.len(), 2);
+fn test_process_reorder() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 2,
+    };
+    let columns = [
+        "CityPop".to_string(),
+        "CityName".to_string(),
+        "CityPop".to_string(),
+    ];
+    let result = process_reorder(&chain, &columns, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
     assert_eq!(
         result.header,
-        vec!["CountryCode".to_string(), "Language".to_string()]
+        vec!["CityPop", "CityName", "CityID", "CountryCode"]
     );
-    assert_eq!(result.numeric_columns.len(), 0);
-}
+    assert_eq!(result.numeric_columns(), vec!["CityPop", "CityID"]);
+    assert_eq!(result.rows[0].join(), "1780000,Kabul,1,AFG");
 
-/// Helper function to sort the input 'rows' on the `col_index` column.
-/// # Usage Note: The caller must guarantee that the col_index exists in the table and is numeric.
-fn sort_table(rows: &mut Vec<Row>, col_index: usize) {
-    rows.sort_by(|a: &Row, b: &Row| {
-        let b_val = match b.cells[col_index] {
-            Cell::Int64(val) => val,
-            // This is unreachable because we would have returned
-            // OperatorError::OrderByColumnNotNumeric in the check above if this column was not
-            // numeric.
-            _ => unreachable!(),
-        };
-        let a_val = match a.cells[col_index] {
-            Cell::Int64(val) => val,
-            // This is unreachable because we would have returned
-            // OperatorError::OrderByColumnNotNumeric in the check above if this column was not
-            // numeric.
-            _ => unreachable!(),
-        };
-        b_val.cmp(&a_val)
-    });
+    assert_eq!(
+        process_reorder(&chain, &["Continent".to_string()], &mut context)
+            .err()
+            .unwrap()
+            .to_string(),
+        "Could not find the Continent column to REORDER on the table produced by this operator chain: FROM city.csv TAKE 2"
+    );
 }
 
-/// Handles the [`Operator::OrderBy`] operator by processing the [`Operator`] chain and reverse
-/// sorting (descending order) the rows of the resulting [`Table`] by the `column`.
+/// Handles the [`Operator::Cast`] operator by processing the [`Operator`] chain and converting
+/// the values of the `column` to the type `to`.
 ///
 /// # Arguments:
 /// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
 /// this operator.
-/// `column`: Name of the column to reverse sort by. Must be a `numeric` column, i.e., the values in
-/// the column must be numeric.
+/// `column`: The name of the column to convert.
+/// `to`: The type its values are converted to.
+/// `context`: The [`ExecutionContext`] the query is processed in.
 ///
 /// # Returns:
-/// On success: A [`Table`] containing only the sorted rows.
-/// On failure: [`OperatorError::OrderByColumnNotNumeric`] if the input column is not a numeric
-/// column, or  [`OperatorError::NoSuchColumn`] if the input column is not found, or any
-/// other [`OperatorError`] produced on processing the operator chain.
-fn process_orderby(chain: &Box<Operator>, column: String) -> Result<Table, OperatorError> {
-    // Run the chained operators to produce the input for this operator.
-    // Will terminate this function and return the produced error if the processing fails.
-    let mut table = process_operator(&**chain)?;
-
-    // Ensure the `column` to sort by is a numeric column.
-    if !table.numeric_columns.contains(&column) {
-        return Err(OperatorError::OrderByColumnNotNumeric {
-            column_name: column,
-        });
-    }
+/// On success: A [`RowStream`] producing the rows with their `column` converted. The `column` is
+/// numeric when converted to INT or FLOAT, and isn't when converted to STRING.
+/// On failure: [`OperatorError::NoSuchColumn`], [`OperatorError::InvalidCast`] once a value
+/// that can't be converted is reached, or other [`OperatorError`] from processing the chained
+/// operators.
+fn process_cast<'a>(
+    chain: &'a Operator,
+    column: &str,
+    to: CastType,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
 
     // Find the index corresponding to the `column`.
     // This can throw the [`OperatorError::NoSuchColumn`] error.
-    let col_index = find_column_index(&table, &column, chain, "ORDERBY")?;
+    let col_index =
+        find_column_index(&input.header, &input.qualified_names, column, chain, "CAST")?;
 
-    // Do the actual sort
-    sort_table(&mut table.rows, col_index);
+    let qualified_names = input.qualified_names.clone();
+    Ok(RowStream::new(
+        input.header.clone(),
+        CastRows {
+            converted: ColumnSchema {
+                data_type: match to {
+                    CastType::Int => DataType::Integer,
+                    CastType::Float => DataType::Decimal,
+                    CastType::String => DataType::String,
+                },
+                ..ColumnSchema::empty(&input.header[col_index])
+            },
+            input,
+            col_index,
+            to,
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
 
-    Ok(table)
+/// Produces the rows of the [`Operator::Cast`] operator.
+struct CastRows<'a> {
+    input: RowStream<'a>,
+    /// The index of the converted column.
+    col_index: usize,
+    /// The type its values are converted to.
+    to: CastType,
+    /// The schema of the converted column, accounting for the values converted so far.
+    converted: ColumnSchema,
+}
+
+impl RowSource for CastRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        let Some(mut row) = self.input.next_row(context)? else {
+            return Ok(None);
+        };
+        let value = &row.cells[self.col_index];
+        row.cells[self.col_index] =
+            self.to
+                .convert(value)
+                .ok_or_else(|| OperatorError::InvalidCast {
+                    column_name: self.input.header[self.col_index].clone(),
+                    value: value.to_string(),
+                    to: self.to,
+                })?;
+        self.converted.observe(&row.cells[self.col_index]);
+        Ok(Some(row))
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        let mut schema = self.input.schema();
+        schema[self.col_index] = self.converted.clone();
+        schema
+    }
 }
 
 #[test]
-fn test_process_orderby_numeric() {
-    let result = process_orderby(
-        &Box::new(Operator::Take {
-            chain: Box::new(Operator::From(Dataset::City)),
-            count: 10,
-        }),
-        "CityPop".to_string(),
-    );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 10);
-    assert_eq!(result.header.len(), 4);
-    assert!(result.rows[0].cells[3] >= result.rows[1].cells[3]);
-    assert!(result.rows[1].cells[3] >= result.rows[2].cells[3]);
-    assert!(result.rows[2].cells[3] >= result.rows[3].cells[3]);
+fn test_process_cast() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 2,
+    };
+    let result = process_cast(&chain, "CityPop", CastType::String, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.numeric_columns(), vec!["CityID"]);
+    assert_eq!(result.rows[0].cells[3], Cell::String("1780000".to_string()));
+
+    let chain = Operator::Cast {
+        chain: Box::new(chain),
+        column: "CityPop".to_string(),
+        to: CastType::String,
+    };
+    let result = process_cast(&chain, "CityPop", CastType::Float, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.numeric_columns(), vec!["CityID", "CityPop"]);
+    assert_eq!(result.rows[1].cells[3], Cell::Float64(237500.0));
+    assert_eq!(result.rows[1].join(), "2,Qandahar,AFG,237500");
 }
 
 #[test]
-fn test_process_orderby_non_numeric() {
-    let result = process_orderby(
-        &Box::new(Operator::Take {
-            chain: Box::new(Operator::From(Dataset::City)),
-            count: 10,
-        }),
-        "CityName".to_string(),
+fn test_process_cast_errors() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::From(Dataset::City);
+    let result = process_cast(&chain, "CityName", CastType::Int, &mut context)
+        .and_then(|stream| stream.collect(&mut context));
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Cannot CAST the value Kabul of the CityName column to INT."
     );
-    assert!(result.is_err());
-    let err = result.unwrap_err();
     assert_eq!(
-        err.to_string(),
-        "You attempted to ORDERBY the CityName column whose type is not numeric.".to_string()
+        process_cast(&chain, "Continent", CastType::Int, &mut context)
+            .err()
+            .unwrap()
+            .to_string(),
+        "Could not find the Continent column to CAST on the table produced by this operator chain: FROM city.csv"
     );
 }
 
-/// Handles the [`Operator::CountBy`] operator by processing the [`Operator`] chain and produces a
-/// [`Table`] containing only two columns: the first contains the values of the specified `column`,
-/// and the second `count` column contains the number of times that value appears in the dataset.
+/// Handles the [`Operator::FillNa`] operator by processing the [`Operator`] chain and replacing
+/// the missing values of the `column` of the resulting [`Table`] with the `value`.
 ///
 /// # Arguments:
 /// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
 /// this operator.
-/// `column`: Name of the column to create the histogram for.
+/// `column`: The name of the column whose missing values are replaced.
+/// `value`: The value replacing them.
+/// `context`: The [`ExecutionContext`] the query is processed in.
 ///
 /// # Returns:
-/// On success: A [`Table`] containing the two columns described above.
-/// On failure: [`OperatorError::NoSuchColumn`] if the input column is not found, or any
-/// other [`OperatorError`] produced on processing the operator chain.
-fn process_countby(chain: &Box<Operator>, column: String) -> Result<Table, OperatorError> {
-    // Run the chained operators to produce the input for this operator.
-    // Will terminate this function and return the produced error if the processing fails.
-    let table = process_operator(&**chain)?;
+/// On success: A [`RowStream`] producing the rows with the missing values of their `column`
+/// replaced. The other values of a column of integers with missing values become plain integers,
+/// so the `column` is numeric if all its values end up being numbers.
+/// On failure: [`OperatorError::NoSuchColumn`], or other [`OperatorError`] from processing the
+/// chained operators.
+fn process_fillna<'a>(
+    chain: &'a Operator,
+    column: &str,
+    value: &'a Cell,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    // Open the chained operators to stream the input for this operator.
+    // Will terminate this function and return the produced error if opening them fails.
+    let input = open_operator(chain, context)?;
 
     // Find the index corresponding to the `column`.
     // This can throw the [`OperatorError::NoSuchColumn`] error.
-    let col_index = find_column_index(&table, &column, chain, "COUNTBY")?;
+    let col_index = find_column_index(
+        &input.header,
+        &input.qualified_names,
+        column,
+        chain,
+        "FILLNA",
+    )?;
 
-    let mut histogram: Vec<Row> = table
-        .rows
-        .into_iter()
-        // Count the number of times each `value` in the selected column occurs in the input table
-        // using a hashmap with Key = `value` and Value = count.
-        .fold(HashMap::<Cell, usize>::new(), |mut m, x| {
-            *m.entry(x.cells[col_index].clone()).or_default() += 1;
-            m
-        })
-        .into_iter()
-        // Output each (Key, Value) in the resulting hashamp as a Row.
-        .map(|(cell, count)| Row {
-            cells: vec![cell, Cell::Int64(count as i64)],
-        })
-        .collect();
+    // The column holds the values of the input and the `value`, without missing values unless
+    // the `value` is missing too.
+    let mut filled = ColumnSchema {
+        nullable: false,
+        ..input.schema().swap_remove(col_index)
+    };
+    filled.observe(value);
+    let qualified_names = input.qualified_names.clone();
+    Ok(RowStream::new(
+        input.header.clone(),
+        FillNaRows {
+            input,
+            col_index,
+            value,
+            filled,
+        },
+    )
+    .with_qualified_names(qualified_names))
+}
 
-    // sort the histogram on the 'count' column for stable ordering in the output.
-    sort_table(&mut histogram, col_index);
+/// Produces the rows of the [`Operator::FillNa`] operator.
+struct FillNaRows<'a> {
+    input: RowStream<'a>,
+    /// The index of the column whose missing values are replaced.
+    col_index: usize,
+    /// The value replacing them.
+    value: &'a Cell,
+    /// The schema of the column, accounting for the values produced so far.
+    filled: ColumnSchema,
+}
 
-    Ok(Table {
-        header: vec![column.clone(), String::from("count")],
-        numeric_columns: if table.numeric_columns.contains(&column) {
-            vec![column.clone(), String::from("count")]
-        } else {
-            vec![String::from("count")]
-        },
-        rows: histogram,
-    })
+impl RowSource for FillNaRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        let Some(mut row) = self.input.next_row(context)? else {
+            return Ok(None);
+        };
+        let cell = &mut row.cells[self.col_index];
+        if cell.is_null() {
+            *cell = self.value.clone();
+        } else if let Cell::OptInt64(Some(val)) = cell {
+            *cell = Cell::Int64(*val);
+        }
+        self.filled.observe(cell);
+        Ok(Some(row))
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        let mut schema = self.input.schema();
+        schema[self.col_index] = self.filled.clone();
+        schema
+    }
 }
 
 #[test]
-fn test_process_countby() {
-    let result = process_countby(
-        &Box::new(Operator::Take {
-            chain: Box::new(Operator::From(Dataset::Language)),
-            count: 100,
-        }),
-        "Language".to_string(),
-    );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 70);
-    assert_eq!(result.header.len(), 2);
+fn test_process_fillna() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::From(Dataset::Country);
+    let result = process_fillna(&chain, "Capital", &Cell::Int64(0), &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.numeric_columns(), vec!["CountryPop", "Capital"]);
+    let capitals: Vec<&Cell> = result.rows.iter().map(|row| &row.cells[4]).collect();
+    assert_eq!(capitals[0], &Cell::Int64(129));
     assert_eq!(
-        result.rows[0].cells,
-        vec![Cell::String("English".to_string()), Cell::Int64(7)],
+        capitals
+            .iter()
+            .filter(|capital| ***capital == Cell::Int64(0))
+            .count(),
+        7
     );
+
+    // A column holding strings isn't numeric.
+    let value = Cell::String("none".to_string());
+    let result = process_fillna(&chain, "Capital", &value, &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(result.numeric_columns(), vec!["CountryPop"]);
+    assert!(result.rows.iter().any(|row| row.cells[4] == value));
+
     assert_eq!(
-        result.rows[1].cells,
-        vec![Cell::String("Arabic".to_string()), Cell::Int64(4)],
+        process_fillna(&chain, "Region", &value, &mut context)
+            .err()
+            .unwrap()
+            .to_string(),
+        "Could not find the Region column to FILLNA on the table produced by this operator chain: FROM country.csv"
     );
 }
 
-#[test]
-fn test_process_countby_empty() {
-    let result = process_countby(
-        &Box::new(Operator::Take {
-            chain: Box::new(Operator::From(Dataset::Language)),
-            count: 0,
-        }),
-        "Language".to_string(),
-    );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 0);
-    assert_eq!(result.header.len(), 2);
+/// Handles the [`Operator::TopN`] operator by processing the [`Operator`] chain and keeping the
+/// top `count` rows of each group of rows that share a value of the `group` column.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `count`: The number of rows kept from each group.
+/// `group`: The name of the column to group the rows by.
+/// `column`: The name of the column the rows of each group are ranked by. Must be a `numeric`
+/// column. Rows with missing values are ranked last.
+/// `order`: Whether the rows with the largest (descending) or smallest (ascending) values of the
+/// `column` are kept.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: A [`Table`] with the columns of the input, holding the groups in the order they
+/// first appear in it, and the kept rows of each group in ranked order.
+/// On failure: [`OperatorError::OrderByColumnNotNumeric`] if the `column` is not a numeric
+/// column, [`OperatorError::NoSuchColumn`] if either column is not found, or any other
+/// [`OperatorError`] produced on processing the operator chain.
+fn process_topn(
+    chain: &Operator,
+    count: usize,
+    group: &str,
+    column: &str,
+    order: SortOrder,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+
+    // Find the indices corresponding to the columns.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let group_index =
+        find_column_index(&table.header, &table.qualified_names, group, chain, "TOPN")?;
+    let col_index =
+        find_column_index(&table.header, &table.qualified_names, column, chain, "TOPN")?;
+    check_sortable(&table, col_index, "TOPN", column)?;
+
+    // Split the rows into their groups, in the order the groups first appear.
+    let mut groups: Vec<Vec<Row>> = Vec::new();
+    let mut group_indices: HashMap<Cell, usize> = HashMap::new();
+    for row in table.rows {
+        let index = *group_indices
+            .entry(row.cells[group_index].clone())
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[index].push(row);
+    }
+
+    let mut rows = Vec::new();
+    for mut group_rows in groups {
+        sort_table(&mut group_rows, col_index, order, NullsOrder::Last);
+        group_rows.truncate(count);
+        rows.append(&mut group_rows);
+    }
+    Ok(Table {
+        header: table.header,
+        schema: table.schema,
+        rows,
+        qualified_names: table.qualified_names,
+    })
+}
+
+#[test]
+fn test_process_topn() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 11,
+    };
+    let result = process_topn(
+        &chain,
+        2,
+        "CountryCode",
+        "CityPop",
+        SortOrder::Descending,
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(result.header.len(), 4);
+    let names: Vec<String> = result
+        .rows
+        .iter()
+        .map(|row| row.cells[1].to_string())
+        .collect();
+    assert_eq!(names, vec!["Kabul", "Qandahar", "Amsterdam", "Rotterdam"]);
+
+    let result = process_topn(
+        &chain,
+        1,
+        "CountryCode",
+        "CityPop",
+        SortOrder::Ascending,
+        &mut context,
+    )
+    .unwrap();
+    let names: Vec<String> = result
+        .rows
+        .iter()
+        .map(|row| row.cells[1].to_string())
+        .collect();
+    assert_eq!(names, vec!["Mazar-e-Sharif", "Groningen"]);
+
+    // Groups with fewer rows than the count are kept whole.
+    let result = process_topn(
+        &chain,
+        100,
+        "CountryCode",
+        "CityPop",
+        SortOrder::Descending,
+        &mut context,
+    )
+    .unwrap();
+    assert_eq!(result.rows.len(), 11);
 }
 
 #[test]
-fn test_process_countby_no_such_column() {
-    let result = process_countby(
-        &Box::new(Operator::Take {
-            chain: Box::new(Operator::From(Dataset::Language)),
-            count: 100,
-        }),
-        "CityPop".to_string(),
+fn test_process_topn_errors() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::From(Dataset::City);
+    assert_eq!(
+        process_topn(
+            &chain,
+            3,
+            "CountryCode",
+            "CityName",
+            SortOrder::Descending,
+            &mut context
+        )
+        .unwrap_err()
+        .to_string(),
+        "You attempted to TOPN the CityName column whose type is not numeric."
+    );
+    assert_eq!(
+        process_topn(
+            &chain,
+            3,
+            "Continent",
+            "CityPop",
+            SortOrder::Descending,
+            &mut context
+        )
+        .unwrap_err()
+        .to_string(),
+        "Could not find the Continent column to TOPN on the table produced by this operator chain: FROM city.csv"
     );
-    assert!(result.is_err());
-    let result = result.unwrap_err();
-    assert_eq!(result.to_string(), "Could not find the CityPop column to COUNTBY on the table produced by this operator chain: FROM language.csv TAKE 100".to_string());
 }
 
-/// Handles the [`Operator::Join`] operator by processing the [`Operator`] chain to produce the
-/// 'left' table and loading the `dataset` as the 'right' table and performing a left-join on them
-/// on the input `column`.
+/// Handles the [`Operator::Rank`] operator by processing the [`Operator`] chain and adding a column
+/// ranking its rows by the `column`.
 ///
 /// # Arguments:
-/// `chain`: A chain of one or more [`Operator`]s that produce the 'left' [`Table`] to join on.
-/// `dataset`: The dataset to load for the 'right' table to join on.
-/// `column`: Name of the column to perform the left-join on. This column must be in both the 'left'
-/// and 'right' tables.
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] that is the input for
+/// this operator.
+/// `column`: The name of the column to rank the rows by. Must be a `numeric` column. The largest
+/// value is ranked 1, and rows with missing values are ranked last.
+/// `partition`: The name of the column whose values split the rows into groups that are ranked
+/// separately, or `None` to rank all the rows together.
+/// `context`: The [`ExecutionContext`] the query is processed in.
 ///
 /// # Returns:
-/// On success: A [`Table`] containing the joined rows.
-/// On failure: [`OperatorError::NoSuchColumn`] if the input column is not found, or any
-/// other [`OperatorError`] produced on processing the operator chain.
-fn process_join(
-    chain: &Box<Operator>,
-    dataset: &Dataset,
-    column: String,
+/// On success: A [`Table`] with the rows of the input in the same order, and a numeric `rank`
+/// column added after the others (suffixed with a number if the input has a `rank` column
+/// already). Rows with equal values share a rank, and the next rank skips the shared places.
+/// On failure: [`OperatorError::OrderByColumnNotNumeric`] if the `column` is not a numeric
+/// column, [`OperatorError::NoSuchColumn`] if either column is not found, or any other
+/// [`OperatorError`] produced on processing the operator chain.
+fn process_rank(
+    chain: &Operator,
+    column: &str,
+    partition: Option<&str>,
+    context: &mut ExecutionContext,
 ) -> Result<Table, OperatorError> {
     // Run the chained operators to produce the input for this operator.
     // Will terminate this function and return the produced error if the processing fails.
-    let left = process_operator(&**chain)?;
+    let mut table = process_operator(chain, context)?;
 
-    // Load the right table.
-    // This can throw [`OperatorError::CSVError`].
-    let right = load_dataset(dataset, "JOIN")?;
+    // Find the indices corresponding to the columns.
+    // This can throw the [`OperatorError::NoSuchColumn`] error.
+    let col_index =
+        find_column_index(&table.header, &table.qualified_names, column, chain, "RANK")?;
+    let partition_index = partition
+        .map(|partition| {
+            find_column_index(
+                &table.header,
+                &table.qualified_names,
+                partition,
+                chain,
+                "RANK",
+            )
+        })
+        .transpose()?;
+    check_sortable(&table, col_index, "RANK", column)?;
 
-    // Make sure the column to join on is in both the 'left' and 'right' tables.
-    if !(left.header.contains(&column) && right.header.contains(&column)) {
-        return Err(OperatorError::NoSuchColumn {
-            operator: String::from("JOIN"),
-            chain: chain.clone(),
-            column_name: column,
-        });
+    // Split the indices of the rows into their groups; all the rows are in one without a
+    // partition.
+    let mut groups: HashMap<Option<&Cell>, Vec<usize>> = HashMap::new();
+    for (index, row) in table.rows.iter().enumerate() {
+        let key = partition_index.map(|partition_index| &row.cells[partition_index]);
+        groups.entry(key).or_default().push(index);
     }
 
-    // Construct the new header by concatenating the headers of the 'left' and 'right' tables,
-    // taking care to remove the common column from the 'right' table.
-    let header = {
-        let mut header = left.header.clone();
-        for name in &right.header {
-            if *name != column {
-                header.push(name.clone());
-            }
-        }
-        header
-    };
-
-    // Construct the new numeric_columns by concatenating the numeric_columns of the 'left' and
-    // 'right' tables, taking care to remove the common column from the 'right' table.
-    let numeric_columns = {
-        let mut numeric_columns = left.numeric_columns.clone();
-        for name in &right.numeric_columns {
-            if *name != column {
-                numeric_columns.push(name.clone());
-            }
-        }
-        numeric_columns
-    };
-
-    // Perform the actual join using the "nested-loop" algorithm.
-    let rows: Vec<Row> = {
-        let mut rows: Vec<Row> = Vec::new();
-        let left_index = left.find_column_index_by_name(&column).unwrap();
-        let right_index = right.find_column_index_by_name(&column).unwrap();
-        for left_row in &left.rows {
-            for right_row in &right.rows {
-                if left_row.cells[left_index] == right_row.cells[right_index] {
-                    let mut row = left_row.clone();
-                    for (index, cell) in right_row.cells.iter().enumerate() {
-                        if index != right_index {
-                            row.cells.push(cell.clone());
-                        }
-                    }
-                    rows.push(row);
+    let mut ranks = vec![0; table.rows.len()];
+    for mut indices in groups.into_values() {
+        let value = |index: usize| &table.rows[index].cells[col_index];
+        indices.sort_by(|a, b| {
+            cmp_sorted(
+                value(*a),
+                value(*b),
+                SortOrder::Descending,
+                NullsOrder::Last,
+            )
+        });
+        for (place, index) in indices.iter().enumerate() {
+            // A row ties with the one ranked before it if their values are equal.
+            ranks[*index] = match place.checked_sub(1).map(|previous| indices[previous]) {
+                Some(previous)
+                    if cmp_sorted(
+                        value(previous),
+                        value(*index),
+                        SortOrder::Descending,
+                        NullsOrder::Last,
+                    ) == Ordering::Equal =>
+                {
+                    ranks[previous]
                 }
-            }
+                _ => place as i64 + 1,
+            };
         }
-        rows
-    };
+    }
 
-    Ok(Table {
-        header,
-        numeric_columns,
-        rows,
-    })
+    let rank_column = unique_column_name("rank", &table.header);
+    for (row, rank) in table.rows.iter_mut().zip(ranks) {
+        row.cells.push(Cell::Int64(rank));
+    }
+    table.schema.push(ColumnSchema {
+        data_type: DataType::Integer,
+        ..ColumnSchema::empty(&rank_column)
+    });
+    table.header.push(rank_column);
+    Ok(table)
 }
 
 #[test]
-fn test_process_join_simple() {
-    let result = process_join(
-        &Box::new(Operator::From(Dataset::City)),
-        &Dataset::Country,
-        "CountryCode".to_string(),
-    );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 4079);
+fn test_process_rank() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 7,
+    };
+    // The ranks are in the last column.
+    let ranks_of = |table: Table| -> Vec<String> {
+        table
+            .rows
+            .iter()
+            .map(|row| row.cells.last().unwrap().to_string())
+            .collect()
+    };
+    let result = process_rank(&chain, "CityPop", None, &mut context).unwrap();
     assert_eq!(
-        result.rows[4066].cells,
-        vec![
-            Cell::Int64(4067),
-            Cell::String("Charlotte_Amalie".to_string()),
-            Cell::String("VIR".to_string()),
-            Cell::Int64(13000),
-            Cell::String("Virgin_Islands_U.S.".to_string()),
-            Cell::String("North_America".to_string()),
-            Cell::Int64(93000),
-            Cell::OptInt64(Some(4067))
+        result.header,
+        vec!["CityID", "CityName", "CountryCode", "CityPop", "rank"]
+    );
+    assert_eq!(result.numeric_columns(), vec!["CityID", "CityPop", "rank"]);
+    assert_eq!(ranks_of(result), vec!["1", "5", "6", "7", "2", "3", "4"]);
+
+    let result = process_rank(&chain, "CityPop", Some("CountryCode"), &mut context).unwrap();
+    assert_eq!(ranks_of(result), vec!["1", "2", "3", "4", "1", "2", "3"]);
+
+    // Equal values share a rank, and missing values are ranked last.
+    let table = Table::new(
+        vec!["Name".to_string(), "Pop".to_string()],
+        [
+            Cell::Int64(5),
+            Cell::OptInt64(None),
+            Cell::Int64(7),
+            Cell::Int64(5),
+            Cell::Int64(1),
         ]
-    )
+        .into_iter()
+        .map(|pop| Row {
+            cells: vec![Cell::String("x".to_string()), pop],
+        })
+        .collect(),
+    );
+    context.set_variable("pops", table);
+    let chain = Operator::From(Dataset::Variable("pops".to_string()));
+    let result = process_rank(&chain, "Pop", None, &mut context).unwrap();
+    assert_eq!(ranks_of(result), vec!["2", "5", "1", "2", "4"]);
 }
 
 #[test]
-fn test_process_join_complex() {
-    let result = process_join(
-        &Box::new(Operator::Join {
-            chain: Box::new(Operator::From(Dataset::City)),
-            right: Dataset::Country,
-            column: "CountryCode".to_string(),
-        }),
-        &Dataset::Language,
-        "CountryCode".to_string(),
+fn test_process_rank_errors() {
+    let mut context = ExecutionContext::default();
+    let chain = Operator::From(Dataset::City);
+    assert_eq!(
+        process_rank(&chain, "CityName", None, &mut context)
+            .unwrap_err()
+            .to_string(),
+        "You attempted to RANK the CityName column whose type is not numeric."
     );
-    assert!(result.is_ok());
-    let result = result.unwrap();
-    assert_eq!(result.rows.len(), 30670);
     assert_eq!(
-        result.rows[30668].cells,
-        vec![
-            Cell::Int64(4079),
-            Cell::String("Rafah".to_string()),
-            Cell::String("PSE".to_string()),
-            Cell::Int64(92020),
-            Cell::String("Palestine".to_string()),
-            Cell::String("Asia".to_string()),
-            Cell::Int64(3101000),
-            Cell::OptInt64(Some(4074)),
-            Cell::String("Arabic".to_string()),
-        ]
+        process_rank(&chain, "CityPop", Some("Continent"), &mut context)
+            .unwrap_err()
+            .to_string(),
+        "Could not find the Continent column to RANK on the table produced by this operator chain: FROM city.csv"
+    );
+}
+
+/// Handles the [`Operator::Into`] operator by processing the [`Operator`] chain and writing the
+/// resulting [`Table`] to the file at `path` as CSV or Arrow.
+///
+/// # Arguments:
+/// `chain`: A chain of one or more [`Operator`]s that produce the [`Table`] to write.
+/// `path`: The path of the file to write.
+/// `format`: The format to write the file in.
+/// `context`: The [`ExecutionContext`] the query is processed in.
+///
+/// # Returns:
+/// On success: The [`Table`] that was written, unchanged.
+/// On failure: [`OperatorError::ExportError`] if the file can't be written, or other
+/// [`OperatorError`] from processing the chained operators.
+fn process_into(
+    chain: &Operator,
+    path: &str,
+    format: ExportFormat,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    // Run the chained operators to produce the input for this operator.
+    // Will terminate this function and return the produced error if the processing fails.
+    let table = process_operator(chain, context)?;
+    let written: Result<(), Box<dyn Error>> = match format {
+        ExportFormat::Csv => table.write_csv(path).map_err(Box::from),
+        ExportFormat::Arrow => table.write_arrow(path).map_err(Box::from),
+    };
+    written.map_err(|error| OperatorError::ExportError {
+        path: path.to_string(),
+        error,
+    })?;
+    Ok(table)
+}
+
+#[test]
+fn test_process_into() {
+    let mut context = ExecutionContext::default();
+    let path = std::env::temp_dir().join("toy-query-engine-into.csv");
+    let path = path.to_str().unwrap();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::Country)),
+        count: 3,
+    };
+    let result = process_into(
+        &chain,
+        path,
+        ExportFormat::Csv,
+        &mut ExecutionContext::default(),
     )
+    .unwrap();
+    assert_eq!(result.rows.len(), 3);
+
+    // The file can be loaded back, with the same columns and values.
+    let loaded = process_from(&Dataset::Csv(path.to_string()), &mut context)
+        .and_then(|stream| stream.collect(&mut context))
+        .unwrap();
+    assert_eq!(loaded.header, result.header);
+    assert_eq!(loaded.rows.len(), 3);
+    assert_eq!(loaded.rows[0].join(), result.rows[0].join());
 }
 
 #[test]
-fn test_process_join_no_such_column_left() {
-    let result = process_join(
-        &Box::new(Operator::Join {
-            chain: Box::new(Operator::From(Dataset::City)),
-            right: Dataset::Country,
-            column: "Language".to_string(),
-        }),
-        &Dataset::Language,
-        "CountryCode".to_string(),
+fn test_process_into_error() {
+    let result = process_into(
+        &Operator::From(Dataset::Language),
+        "/nonexistent/directory/out.csv",
+        ExportFormat::Csv,
+        &mut ExecutionContext::default(),
     );
-    assert!(result.is_err());
-    let result = result.unwrap_err();
-    assert_eq!(result.to_string(), "Could not find the Language column to JOIN on the table produced by this operator chain: FROM city.csv".to_string());
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .starts_with("Failed to write the results to /nonexistent/directory/out.csv: "));
 }
 
 #[test]
-fn test_process_join_no_such_column_right() {
-    let result = process_join(
-        &Box::new(Operator::Join {
-            chain: Box::new(Operator::From(Dataset::City)),
-            right: Dataset::Country,
-            column: "CountryCode".to_string(),
-        }),
-        &Dataset::Language,
-        "Capital".to_string(),
+fn test_process_into_arrow() {
+    let path = std::env::temp_dir().join("toy-query-engine-into.arrow");
+    let path = path.to_str().unwrap();
+    let chain = Operator::Take {
+        chain: Box::new(Operator::From(Dataset::City)),
+        count: 5,
+    };
+    let result = process_into(
+        &chain,
+        path,
+        ExportFormat::Arrow,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap();
+    let file = std::fs::File::open(path).unwrap();
+    let batches: Vec<arrow::record_batch::RecordBatch> =
+        arrow::ipc::reader::FileReader::try_new(file, None)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].num_rows(), result.rows.len());
+    assert_eq!(
+        batches[0]
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect::<Vec<&str>>(),
+        result.header
     );
-    assert!(result.is_err());
-    let result = result.unwrap_err();
-    assert_eq!(result.to_string(), "Could not find the Capital column to JOIN on the table produced by this operator chain: FROM city.csv JOIN country.csv CountryCode".to_string());
+    assert!(process_into(
+        &chain,
+        "/nonexistent/directory/out.arrow",
+        ExportFormat::Arrow,
+        &mut ExecutionContext::default(),
+    )
+    .unwrap_err()
+    .to_string()
+    .starts_with("Failed to write the results to /nonexistent/directory/out.arrow: "));
+}
+
+/// Handles the input [`Operator`] by opening its chain and producing all of its rows.
+///
+/// # Arguments:
+/// `operator`: The operator chain to process.
+/// `context`: The [`ExecutionContext`] the query is processed in. If profiling is enabled, an
+/// [`OperatorProfile`] is recorded in it for each operator in the chain.
+///
+/// # Returns:
+/// On success: A [`Table`] containing the rows obtained by processing the operator chain.
+/// On failure: [`OperatorError::ResultTooLarge`] if the table, or one produced by an operator in
+/// the chain, grows past the [`ExecutionContext::limits`], or another [`OperatorError`].
+pub fn process_operator(
+    operator: &Operator,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    context.check_cancelled()?;
+    let limits = context.limits;
+    let table =
+        open_operator(operator, context)?.collect_within(&limits, || operator.label(), context)?;
+    // An operator cancelled midway, e.g. a JOIN, may have produced only some of its rows.
+    context.check_cancelled()?;
+    Ok(table)
 }
 
-/// Handles the input [`Operator`] by delegating to the functions above.
+/// Handles the input [`Operator`] like [`process_operator`], but returns the previous result if the
+/// same operator chain was processed recently and the datasets it read are unchanged since, e.g.
+/// not modified with INSERT or DELETE, saved again with SET, or edited on disk. Queries that
+/// aren't repeatable (see [`Operator::is_repeatable`]), and queries processed while profiling,
+/// are always processed.
 ///
 /// # Arguments:
 /// `operator`: The operator chain to process.
+/// `context`: The [`ExecutionContext`] the query is processed in, which caches the results.
 ///
 /// # Returns:
 /// On success: A [`Table`] containing the rows obtained by processing the operator chain.
+/// On failure: [`OperatorError`]. Failed queries aren't cached.
+pub fn process_cached(
+    operator: &Operator,
+    context: &mut ExecutionContext,
+) -> Result<Table, OperatorError> {
+    if !operator.is_repeatable() || context.profile.is_some() {
+        return process_operator(operator, context);
+    }
+    // The debug representation tells apart everything the result depends on, e.g. the path of a
+    // dataset registered with LOAD, which its name in the query doesn't.
+    let key = format!("{:?}", operator);
+    if let Some(cached) = context.results.take(&key) {
+        let current = cached.inputs.iter().all(|(dataset, table)| {
+            load_dataset(dataset, "FROM", context).is_ok_and(|current| Arc::ptr_eq(table, &current))
+        });
+        if current {
+            let table = (*cached.table).clone();
+            context.results.insert(&key, cached);
+            return Ok(table);
+        }
+    }
+
+    context.inputs = Some(Vec::new());
+    let result = process_operator(operator, context);
+    let inputs = context.inputs.take().unwrap_or_default();
+    let table = result?;
+    context.results.insert(
+        &key,
+        CachedResult {
+            table: Arc::new(table.clone()),
+            inputs,
+        },
+    );
+    Ok(table)
+}
+
+#[test]
+fn test_process_cached() {
+    let mut context = ExecutionContext::default();
+    let operator = |query: &str| match crate::commands::parse_command(&format!("{}\n", query)) {
+        crate::commands::Command::Operator(operator) => operator,
+        command => panic!("{:?}", command),
+    };
+    let query = operator("FROM city JOIN country CountryCode FILTER Continent = Oceania");
+    let first = process_cached(&query, &mut context).unwrap();
+    assert_eq!(context.results.len(), 1);
+    assert_eq!(process_cached(&query, &mut context).unwrap(), first);
+    assert_eq!(context.results.len(), 1);
+
+    // Modifying a dataset read by the query processes it again.
+    context
+        .insert_rows(
+            &Dataset::City,
+            vec![vec![
+                Cell::Int64(5000),
+                Cell::String("Atlantis".to_string()),
+                Cell::String("AUS".to_string()),
+                Cell::Int64(1),
+            ]],
+        )
+        .unwrap();
+    let second = process_cached(&query, &mut context).unwrap();
+    assert_eq!(second.rows.len(), first.rows.len() + 1);
+
+    // So does saving a result again under the name of a variable read by the query.
+    context.set_variable("cities", first.clone());
+    let query = operator("FROM @cities COUNT");
+    assert_eq!(
+        process_cached(&query, &mut context).unwrap().rows[0].join(),
+        first.rows.len().to_string()
+    );
+    context.set_variable("cities", second);
+    assert_eq!(
+        process_cached(&query, &mut context).unwrap().rows[0].join(),
+        (first.rows.len() + 1).to_string()
+    );
+
+    // Queries that aren't repeatable, and failed queries, aren't cached.
+    context.clear_results();
+    process_cached(&operator("FROM city SAMPLE 5"), &mut context).unwrap();
+    process_cached(&operator("FROM city ORDERBY CityName"), &mut context).unwrap_err();
+    assert!(context.results.is_empty());
+    process_cached(&operator("FROM city SAMPLE 5 SEED 1"), &mut context).unwrap();
+    assert_eq!(context.results.len(), 1);
+}
+
+/// Opens the input [`Operator`] by delegating to the functions above. Operators that work on one
+/// row at a time (FROM, SELECT, TAKE, SKIP, FILTER, DISTINCT, RENAME, and UNION) produce their rows only when
+/// they are pulled from the returned [`RowStream`]; the others process their whole input here.
+///
+/// # Arguments:
+/// `operator`: The operator chain to open.
+/// `context`: The [`ExecutionContext`] the query is processed in. If profiling is enabled, an
+/// [`OperatorProfile`] is recorded in it for each operator in the chain, and kept up to date as
+/// rows are pulled.
+///
+/// # Returns:
+/// On success: A [`RowStream`] producing the rows of the operator chain.
 /// On failure: [`OperatorError`].
-pub fn process_operator(operator: &Operator) -> Result<Table, OperatorError> {
-    match operator {
-        Operator::From(dataset) => process_from(dataset),
-        Operator::Select {
+pub fn open_operator<'a>(
+    operator: &'a Operator,
+    context: &mut ExecutionContext,
+) -> Result<RowStream<'a>, OperatorError> {
+    let start = Instant::now();
+    let first_child = context.profile.as_ref().map_or(0, Vec::len);
+    if context.depth == 0 {
+        // A new query starts, so the regular expressions of the previous one are dropped.
+        context.regexes.get_mut().unwrap().clear();
+    }
+    context.depth += 1;
+    let result = match operator {
+        Operator::From(dataset) => process_from(dataset, context),
+        Operator::Select { chain, columns } => process_select(chain, columns, context),
+        Operator::SelectAll { chain, except } => process_select_all(chain, except, context),
+        Operator::Extend { chain, columns } => process_extend(chain, columns, context),
+        Operator::Take { chain, count } => process_take(chain, *count, context),
+        Operator::Skip { chain, count } => process_skip(chain, *count, context),
+        Operator::Tail { chain, count } => {
+            process_tail(chain, *count, context).map(RowStream::from)
+        }
+        Operator::Sample { chain, size, seed } => {
+            process_sample(chain, *size, *seed, context).map(RowStream::from)
+        }
+        Operator::OrderBy {
+            chain,
+            column,
+            order,
+            nulls,
+        } => process_orderby(chain, column.clone(), *order, *nulls, context).map(RowStream::from),
+        Operator::CountBy {
+            chain,
+            column,
+            order,
+            bars,
+            having,
+        } => process_countby(
+            chain,
+            column.clone(),
+            *order,
+            *bars,
+            having.as_ref(),
+            context,
+        )
+        .map(RowStream::from),
+        Operator::Aggregate {
+            chain,
+            function,
+            column,
+        } => process_aggregate(chain, *function, column, context).map(RowStream::from),
+        Operator::GroupBy {
+            chain,
+            column,
+            aggregates,
+            having,
+        } => process_groupby(chain, column, aggregates, having.as_ref(), context)
+            .map(RowStream::from),
+        Operator::Pivot {
             chain,
-            column_names,
-        } => process_select(chain, column_names),
-        Operator::Take { chain, count } => process_take(chain, *count),
-        Operator::OrderBy { chain, column } => process_orderby(chain, column.clone()),
-        Operator::CountBy { chain, column } => process_countby(chain, column.clone()),
+            row,
+            column,
+            aggregate,
+        } => process_pivot(chain, row, column, aggregate, context).map(RowStream::from),
         Operator::Join {
             chain,
             right,
             column,
-        } => process_join(chain, right, column.clone()),
+            right_column,
+            kind,
+            strategy,
+        } => process_join(
+            chain,
+            right,
+            column.clone(),
+            right_column.as_deref(),
+            *kind,
+            strategy.unwrap_or_default(),
+            context,
+        )
+        .map(RowStream::from),
+        Operator::Union { chain, right, all } => process_union(chain, right, *all, context),
+        Operator::Filter { chain, predicate } => process_filter(chain, predicate, context),
+        Operator::With { ctes, query } => process_with(ctes, query, context),
+        Operator::Map { chain, script } => process_map(chain, script, context).map(RowStream::from),
+        Operator::Into {
+            chain,
+            path,
+            format,
+        } => process_into(chain, path, *format, context).map(RowStream::from),
+        Operator::Distinct { chain, columns } => process_distinct(chain, columns, context),
+        Operator::Rename {
+            chain,
+            column,
+            new_name,
+        } => process_rename(chain, column, new_name, context),
+        Operator::Reorder { chain, columns } => process_reorder(chain, columns, context),
+        Operator::Cast { chain, column, to } => process_cast(chain, column, *to, context),
+        Operator::FillNa {
+            chain,
+            column,
+            value,
+        } => process_fillna(chain, column, value, context),
+        Operator::TopN {
+            chain,
+            count,
+            group,
+            column,
+            order,
+        } => process_topn(chain, *count, group, column, *order, context).map(RowStream::from),
+        Operator::Rank {
+            chain,
+            column,
+            partition,
+        } => process_rank(chain, column, partition.as_deref(), context).map(RowStream::from),
+    };
+    context.depth -= 1;
+    let stream = result?;
+
+    // Record the time spent opening this operator. The rows it produces, and the time spent
+    // producing them, are added as they are pulled.
+    let Some(profile) = context.profile.as_mut() else {
+        return Ok(stream);
+    };
+    profile.push(OperatorProfile {
+        operator: operator.label(),
+        depth: context.depth,
+        rows: 0,
+        elapsed: start.elapsed(),
+        self_elapsed: Duration::ZERO,
+        estimated_bytes: std::mem::size_of::<Table>()
+            + stream
+                .header
+                .iter()
+                .map(|name| std::mem::size_of::<String>() + name.len())
+                .sum::<usize>(),
+    });
+    let source = ProfiledRows {
+        input: stream.source,
+        index: profile.len() - 1,
+        first_child,
+    };
+    source.update_self_elapsed(profile);
+    Ok(RowStream {
+        header: stream.header,
+        source: Box::new(source),
+        qualified_names: stream.qualified_names,
+    })
+}
+
+/// Produces the rows of an operator unchanged, while recording them in the operator's
+/// [`OperatorProfile`].
+struct ProfiledRows<'a> {
+    input: Box<dyn RowSource + 'a>,
+    /// The index of the operator's [`OperatorProfile`] in the collected profile.
+    index: usize,
+    /// The index in the collected profile of the first operator chained by this one.
+    first_child: usize,
+}
+
+impl ProfiledRows<'_> {
+    /// Updates how long this operator took, excluding the time spent in the operators it chains.
+    fn update_self_elapsed(&self, profile: &mut [OperatorProfile]) {
+        let depth = profile[self.index].depth;
+        let children_elapsed: Duration = profile[self.first_child..self.index]
+            .iter()
+            .filter(|child| child.depth == depth + 1)
+            .map(|child| child.elapsed)
+            .sum();
+        let entry = &mut profile[self.index];
+        entry.self_elapsed = entry.elapsed.saturating_sub(children_elapsed);
+    }
+}
+
+impl RowSource for ProfiledRows<'_> {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        let start = Instant::now();
+        let row = self.input.next_row(context)?;
+        // The profile is only missing if it was taken before all the rows were pulled.
+        if let Some(profile) = context
+            .profile
+            .as_mut()
+            .filter(|profile| self.index < profile.len())
+        {
+            let entry = &mut profile[self.index];
+            entry.elapsed += start.elapsed();
+            if let Some(row) = &row {
+                entry.rows += 1;
+                entry.estimated_bytes += row.estimated_size();
+            }
+            self.update_self_elapsed(profile);
+        }
+        Ok(row)
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.input.schema()
     }
 }
+
+#[test]
+fn test_process_operator_profile() {
+    let mut context = ExecutionContext::with_profiling();
+    let result = process_operator(
+        &Operator::Take {
+            chain: Box::new(Operator::From(Dataset::Language)),
+            count: 5,
+        },
+        &mut context,
+    );
+    assert!(result.is_ok());
+    let profile = context.take_profile().unwrap();
+    assert_eq!(profile.len(), 2);
+    assert_eq!(profile[0].operator, "FROM language.csv");
+    assert_eq!(profile[0].depth, 1);
+    // TAKE stops pulling rows from FROM once it has enough.
+    assert_eq!(profile[0].rows, 5);
+    assert_eq!(profile[1].operator, "TAKE 5");
+    assert_eq!(profile[1].depth, 0);
+    assert_eq!(profile[1].rows, 5);
+    assert_eq!(profile[1].estimated_bytes, profile[0].estimated_bytes);
+    assert!(profile[1].elapsed >= profile[0].elapsed);
+    assert!(profile[1].self_elapsed <= profile[1].elapsed);
+    // The collected profile is reset for the next query.
+    assert_eq!(context.take_profile(), Some(vec![]));
+}
+
+#[test]
+fn test_process_operator_profile_blocking() {
+    let mut context = ExecutionContext::with_profiling();
+    let result = process_operator(
+        &Operator::Take {
+            chain: Box::new(Operator::OrderBy {
+                chain: Box::new(Operator::From(Dataset::City)),
+                column: "CityPop".to_string(),
+                order: SortOrder::Descending,
+                nulls: NullsOrder::Last,
+            }),
+            count: 3,
+        },
+        &mut context,
+    );
+    assert_eq!(result.unwrap().rows.len(), 3);
+    let profile = context.take_profile().unwrap();
+    let rows: Vec<(&str, usize, usize)> = profile
+        .iter()
+        .map(|entry| (entry.operator.as_str(), entry.depth, entry.rows))
+        .collect();
+    // ORDERBY needs all of its input, but only produces the rows TAKE asks for.
+    assert_eq!(
+        rows,
+        vec![
+            ("FROM city.csv", 2, 4079),
+            ("ORDERBY CityPop", 1, 3),
+            ("TAKE 3", 0, 3)
+        ]
+    );
+}
+
+#[test]
+fn test_process_operator_no_profile() {
+    let mut context = ExecutionContext::default();
+    let result = process_operator(&Operator::From(Dataset::Language), &mut context);
+    assert!(result.is_ok());
+    assert_eq!(context.take_profile(), None);
+}
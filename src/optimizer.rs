@@ -0,0 +1,743 @@
+use crate::aggregate::ALL_ROWS;
+use crate::expression::{Expression, SelectColumn};
+use crate::operators::{unique_column_name, JoinKind, JoinStrategy, Operator};
+
+/// Helper function to work out the names of the columns the `operator` chain produces without
+/// running it.
+///
+/// # Returns
+/// The names of the columns, or `None` if they are only known once the chain runs, e.g. when it
+/// reads a file other than a built-in dataset or runs a script.
+fn output_columns(operator: &Operator) -> Option<Vec<String>> {
+    match operator {
+        Operator::From(dataset) if dataset.is_builtin() => Some(dataset.column_names()),
+        Operator::Select { columns, .. } => Some(columns.iter().map(SelectColumn::name).collect()),
+        Operator::SelectAll { chain, except } => output_columns(chain).map(|columns| {
+            columns
+                .into_iter()
+                .filter(|name| !except.contains(name))
+                .collect()
+        }),
+        Operator::Take { chain, .. }
+        | Operator::Skip { chain, .. }
+        | Operator::Tail { chain, .. }
+        | Operator::Sample { chain, .. }
+        | Operator::OrderBy { chain, .. }
+        | Operator::TopN { chain, .. }
+        | Operator::Filter { chain, .. }
+        | Operator::Distinct { chain, .. }
+        | Operator::Union { chain, .. }
+        | Operator::Cast { chain, .. }
+        | Operator::FillNa { chain, .. }
+        | Operator::Into { chain, .. } => output_columns(chain),
+        Operator::Rename {
+            chain,
+            column,
+            new_name,
+        } => output_columns(chain).map(|columns| {
+            columns
+                .into_iter()
+                .map(|name| {
+                    if name == *column {
+                        new_name.clone()
+                    } else {
+                        name
+                    }
+                })
+                .collect()
+        }),
+        // The columns are only known if all the moved columns exist.
+        Operator::Reorder { chain, columns } => output_columns(chain)
+            .filter(|header| columns.iter().all(|name| header.contains(name)))
+            .map(|header| {
+                let mut reordered = with_columns(vec![], columns.iter().map(String::as_str));
+                reordered.extend(header.into_iter().filter(|name| !columns.contains(name)));
+                reordered
+            }),
+        Operator::Extend { chain, columns } => output_columns(chain).map(|mut header| {
+            header.extend(columns.iter().map(|(name, _)| name.clone()));
+            header
+        }),
+        Operator::Rank { chain, .. } => output_columns(chain).map(|mut columns| {
+            columns.push(unique_column_name("rank", &columns));
+            columns
+        }),
+        Operator::CountBy { column, bars, .. } => {
+            let mut header = vec![column.clone(), "count".to_string()];
+            if *bars {
+                header.push("bar".to_string());
+            }
+            Some(header)
+        }
+        Operator::Join {
+            chain,
+            right,
+            column,
+            right_column,
+            ..
+        } => {
+            let mut header = output_columns(chain)?;
+            for name in output_columns(right)? {
+                // The right join column is dropped when both join columns share a name.
+                if right_column.is_some() || name != *column {
+                    header.push(unique_column_name(&name, &header));
+                }
+            }
+            Some(header)
+        }
+        _ => None,
+    }
+}
+
+/// Helper function to work out whether the rows produced by the `operator` chain are sorted on the
+/// `column` by an ORDERBY, i.e., whether the operators following the ORDERBY keep its order and
+/// the `column`.
+fn sorted_on(operator: &Operator, column: &str) -> bool {
+    match operator {
+        Operator::OrderBy {
+            column: sorted_column,
+            ..
+        } => sorted_column == column,
+        Operator::Take { chain, .. }
+        | Operator::Skip { chain, .. }
+        | Operator::Tail { chain, .. }
+        | Operator::Sample { chain, .. }
+        | Operator::Filter { chain, .. }
+        | Operator::Distinct { chain, .. }
+        | Operator::Reorder { chain, .. }
+        | Operator::Extend { chain, .. }
+        | Operator::Rank { chain, .. } => sorted_on(chain, column),
+        // The column must be selected as is, keeping its name.
+        Operator::Select { chain, columns } => {
+            columns.iter().any(|selected| {
+                selected.alias.is_none()
+                    && selected.expression == Expression::Column(column.to_string())
+            }) && sorted_on(chain, column)
+        }
+        Operator::SelectAll { chain, except } => {
+            !except.iter().any(|name| name == column) && sorted_on(chain, column)
+        }
+        Operator::Rename {
+            chain,
+            column: old_name,
+            new_name,
+        } if new_name == column => sorted_on(chain, old_name),
+        Operator::Rename {
+            chain,
+            column: old_name,
+            ..
+        } => old_name != column && sorted_on(chain, column),
+        // Converting the values of the column may change their order, e.g. to strings.
+        Operator::Cast {
+            chain,
+            column: cast_column,
+            ..
+        } => cast_column != column && sorted_on(chain, column),
+        // The missing values are replaced by a value that may sort anywhere.
+        Operator::FillNa {
+            chain,
+            column: filled_column,
+            ..
+        } => filled_column != column && sorted_on(chain, column),
+        // The joins keep the order of their input, unless unmatched rows of the other table are
+        // added after it.
+        Operator::Join {
+            chain,
+            kind: JoinKind::Inner | JoinKind::Left,
+            ..
+        } => sorted_on(chain, column),
+        _ => false,
+    }
+}
+
+/// Helper function to add the `names` missing from the `columns`, keeping their order.
+fn with_columns<'a>(
+    mut columns: Vec<String>,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Vec<String> {
+    for name in names {
+        if !columns.iter().any(|column| column == name) {
+            columns.push(name.to_string());
+        }
+    }
+    columns
+}
+
+/// Helper function to narrow the `chain` down to the `required` columns, by pruning it and then
+/// selecting only those columns from it. The columns keep the order they have in the `chain`.
+/// If the columns of the `chain` aren't known, or it doesn't produce all the `required` columns,
+/// it is only pruned as a whole, so that running it reports the same errors as before.
+fn project(chain: &Operator, required: &[String]) -> Operator {
+    let columns = match output_columns(chain) {
+        Some(columns) if required.iter().all(|name| columns.contains(name)) => columns,
+        _ => return prune(chain, None),
+    };
+    let kept: Vec<String> = columns
+        .iter()
+        .filter(|name| required.contains(name))
+        .cloned()
+        .collect();
+    let pruned = prune(chain, Some(&kept));
+    if kept.len() == columns.len() {
+        return pruned;
+    }
+    Operator::Select {
+        chain: Box::new(pruned),
+        columns: kept
+            .into_iter()
+            .map(|name| Expression::Column(name).into())
+            .collect(),
+    }
+}
+
+/// Helper function to rewrite the `operator` chain so that JOIN and ORDERBY, which hold their
+/// whole input in memory, only receive the columns needed by the operators they feed.
+///
+/// # Arguments
+/// `operator` : The operator chain to rewrite.
+/// `required` : The columns of the `operator`'s output used by the operators it feeds, or `None`
+/// if all of them are.
+fn prune(operator: &Operator, required: Option<&[String]>) -> Operator {
+    match operator {
+        Operator::Select { chain, columns } => {
+            let used = with_columns(
+                vec![],
+                columns
+                    .iter()
+                    .flat_map(|column| column.expression.column_names()),
+            );
+            Operator::Select {
+                chain: Box::new(prune(chain, Some(&used))),
+                columns: columns.clone(),
+            }
+        }
+        // The dropped columns are kept, so that running the chain reports the same errors if they
+        // don't exist.
+        Operator::SelectAll { chain, except } => {
+            let required = required
+                .map(|required| with_columns(required.to_vec(), except.iter().map(String::as_str)));
+            Operator::SelectAll {
+                chain: Box::new(prune(chain, required.as_deref())),
+                except: except.clone(),
+            }
+        }
+        // The appended columns aren't required from the input, only the columns they are computed
+        // from.
+        Operator::Extend { chain, columns } => {
+            let required = required.map(|required| {
+                with_columns(
+                    required
+                        .iter()
+                        .filter(|name| !columns.iter().any(|(column, _)| column == *name))
+                        .cloned()
+                        .collect(),
+                    columns
+                        .iter()
+                        .flat_map(|(_, expression)| expression.column_names()),
+                )
+            });
+            Operator::Extend {
+                chain: Box::new(prune(chain, required.as_deref())),
+                columns: columns.clone(),
+            }
+        }
+        Operator::Take { chain, count } => Operator::Take {
+            chain: Box::new(prune(chain, required)),
+            count: *count,
+        },
+        Operator::Skip { chain, count } => Operator::Skip {
+            chain: Box::new(prune(chain, required)),
+            count: *count,
+        },
+        Operator::Tail { chain, count } => Operator::Tail {
+            chain: Box::new(prune(chain, required)),
+            count: *count,
+        },
+        Operator::Sample { chain, size, seed } => Operator::Sample {
+            chain: Box::new(prune(chain, required)),
+            size: *size,
+            seed: *seed,
+        },
+        Operator::Into {
+            chain,
+            path,
+            format,
+        } => Operator::Into {
+            chain: Box::new(prune(chain, required)),
+            path: path.clone(),
+            format: *format,
+        },
+        Operator::Filter { chain, predicate } => {
+            let required = required
+                .map(|required| with_columns(required.to_vec(), predicate.left.column_names()));
+            Operator::Filter {
+                chain: Box::new(prune(chain, required.as_deref())),
+                predicate: predicate.clone(),
+            }
+        }
+        Operator::OrderBy {
+            chain,
+            column,
+            order,
+            nulls,
+        } => Operator::OrderBy {
+            chain: Box::new(match required {
+                Some(required) => {
+                    project(chain, &with_columns(required.to_vec(), [column.as_str()]))
+                }
+                None => prune(chain, None),
+            }),
+            column: column.clone(),
+            order: *order,
+            nulls: *nulls,
+        },
+        Operator::TopN {
+            chain,
+            count,
+            group,
+            column,
+            order,
+        } => Operator::TopN {
+            chain: Box::new(match required {
+                Some(required) => project(
+                    chain,
+                    &with_columns(required.to_vec(), [group.as_str(), column.as_str()]),
+                ),
+                None => prune(chain, None),
+            }),
+            count: *count,
+            group: group.clone(),
+            column: column.clone(),
+            order: *order,
+        },
+        // The rank column is added by RANK, so it isn't required from the input. It is named after
+        // the columns of the input, so those are only narrowed when they are known.
+        Operator::Rank {
+            chain,
+            column,
+            partition,
+        } => Operator::Rank {
+            chain: Box::new(match (required, output_columns(chain)) {
+                (Some(required), Some(columns)) => {
+                    let rank_column = unique_column_name("rank", &columns);
+                    let required = with_columns(
+                        required
+                            .iter()
+                            .filter(|name| **name != rank_column)
+                            .cloned()
+                            .collect(),
+                        [Some(column), partition.as_ref()]
+                            .into_iter()
+                            .flatten()
+                            .map(String::as_str),
+                    );
+                    project(chain, &required)
+                }
+                _ => prune(chain, None),
+            }),
+            column: column.clone(),
+            partition: partition.clone(),
+        },
+        // Removing columns would change which rows are duplicates when all of them are compared.
+        Operator::Distinct { chain, columns } => {
+            let required = required.filter(|_| !columns.is_empty()).map(|required| {
+                with_columns(required.to_vec(), columns.iter().map(String::as_str))
+            });
+            Operator::Distinct {
+                chain: Box::new(prune(chain, required.as_deref())),
+                columns: columns.clone(),
+            }
+        }
+        Operator::Rename {
+            chain,
+            column,
+            new_name,
+        } => {
+            let required = required.map(|required| {
+                required
+                    .iter()
+                    .map(|name| {
+                        if name == new_name {
+                            column.clone()
+                        } else {
+                            name.clone()
+                        }
+                    })
+                    .collect::<Vec<String>>()
+            });
+            Operator::Rename {
+                chain: Box::new(prune(chain, required.as_deref())),
+                column: column.clone(),
+                new_name: new_name.clone(),
+            }
+        }
+        // The moved columns are kept, so that running the chain reports the same errors if they
+        // don't exist.
+        Operator::Reorder { chain, columns } => {
+            let required = required.map(|required| {
+                with_columns(required.to_vec(), columns.iter().map(String::as_str))
+            });
+            Operator::Reorder {
+                chain: Box::new(prune(chain, required.as_deref())),
+                columns: columns.clone(),
+            }
+        }
+        Operator::Cast { chain, column, to } => {
+            let required =
+                required.map(|required| with_columns(required.to_vec(), [column.as_str()]));
+            Operator::Cast {
+                chain: Box::new(prune(chain, required.as_deref())),
+                column: column.clone(),
+                to: *to,
+            }
+        }
+        Operator::FillNa {
+            chain,
+            column,
+            value,
+        } => {
+            let required =
+                required.map(|required| with_columns(required.to_vec(), [column.as_str()]));
+            Operator::FillNa {
+                chain: Box::new(prune(chain, required.as_deref())),
+                column: column.clone(),
+                value: value.clone(),
+            }
+        }
+        // The operators that reduce their input only need the columns they read.
+        Operator::CountBy {
+            chain,
+            column,
+            order,
+            bars,
+            having,
+        } => Operator::CountBy {
+            chain: Box::new(prune(chain, Some(std::slice::from_ref(column)))),
+            column: column.clone(),
+            order: *order,
+            bars: *bars,
+            having: having.clone(),
+        },
+        Operator::Aggregate {
+            chain,
+            function,
+            column,
+        } => Operator::Aggregate {
+            // Counting the rows doesn't read any column, but the rows are kept whole to be counted.
+            chain: Box::new(prune(
+                chain,
+                Some(std::slice::from_ref(column)).filter(|_| column != ALL_ROWS),
+            )),
+            function: *function,
+            column: column.clone(),
+        },
+        Operator::GroupBy {
+            chain,
+            column,
+            aggregates,
+            having,
+        } => {
+            // COUNT(*) counts the rows of each group rather than reading a column.
+            let used = with_columns(
+                vec![column.clone()],
+                aggregates
+                    .iter()
+                    .map(|aggregate| aggregate.column.as_str())
+                    .filter(|column| *column != ALL_ROWS),
+            );
+            Operator::GroupBy {
+                chain: Box::new(prune(chain, Some(&used))),
+                column: column.clone(),
+                aggregates: aggregates.clone(),
+                having: having.clone(),
+            }
+        }
+        Operator::Pivot {
+            chain,
+            row,
+            column,
+            aggregate,
+        } => {
+            let used = with_columns(
+                vec![row.clone()],
+                [column.as_str(), aggregate.column.as_str()],
+            );
+            Operator::Pivot {
+                chain: Box::new(prune(chain, Some(&used))),
+                row: row.clone(),
+                column: column.clone(),
+                aggregate: aggregate.clone(),
+            }
+        }
+        Operator::Join {
+            chain,
+            right,
+            column,
+            right_column,
+            kind,
+            strategy,
+        } => {
+            let right_name = right_column.as_ref().unwrap_or(column);
+            let (chain, right) = match (required, output_columns(chain), output_columns(right)) {
+                // Columns renamed to avoid a clash would be named differently if the columns they
+                // clash with were removed, so the inputs are only narrowed without clashes.
+                (Some(required), Some(left_columns), Some(right_columns))
+                    if !right_columns.iter().any(|name| {
+                        left_columns.contains(name) && (right_column.is_some() || name != column)
+                    }) && required.iter().all(|name| {
+                        left_columns.contains(name) || right_columns.contains(name)
+                    }) =>
+                {
+                    // Each input keeps the required columns it produces, and its join column.
+                    let side = |columns: &[String], join_column: &str| {
+                        let required = required
+                            .iter()
+                            .map(String::as_str)
+                            .filter(|name| columns.iter().any(|column| column == name));
+                        with_columns(vec![join_column.to_string()], required)
+                    };
+                    (
+                        project(chain, &side(&left_columns, column)),
+                        project(right, &side(&right_columns, right_name)),
+                    )
+                }
+                _ => (prune(chain, None), prune(right, None)),
+            };
+            // A merge join is cheaper than a hash join when the input is already sorted on the
+            // join column, unless a strategy was picked with USING.
+            let strategy =
+                strategy.or_else(|| sorted_on(&chain, column).then_some(JoinStrategy::Merge));
+            Operator::Join {
+                chain: Box::new(chain),
+                right: Box::new(right),
+                column: column.clone(),
+                right_column: right_column.clone(),
+                kind: *kind,
+                strategy,
+            }
+        }
+        // The columns of the tables are matched by position, so neither can be narrowed.
+        Operator::Union { chain, right, all } => Operator::Union {
+            chain: Box::new(prune(chain, None)),
+            right: Box::new(prune(right, None)),
+            all: *all,
+        },
+        Operator::Map { chain, script } => Operator::Map {
+            chain: Box::new(prune(chain, None)),
+            script: script.clone(),
+        },
+        Operator::With { ctes, query } => Operator::With {
+            ctes: ctes
+                .iter()
+                .map(|(name, cte)| (name.clone(), prune(cte, None)))
+                .collect(),
+            query: Box::new(prune(query, required)),
+        },
+        Operator::From(_) => operator.clone(),
+    }
+}
+
+/// Rewrites the `operator` chain before it runs, so that it uses less memory without changing its
+/// result: the columns that aren't needed by the rest of the chain, e.g. those not picked by a
+/// final SELECT, are removed before JOIN and ORDERBY, which hold their whole input in memory.
+/// A JOIN against a dataset then joins against a sub-query selecting the needed columns of it.
+/// A JOIN whose input is sorted on the join column by ORDERBY is made a merge join, unless a
+/// strategy was picked with USING.
+///
+/// The columns are only removed where they are known before running the chain, i.e., for the
+/// built-in datasets, and never where it would rename the columns of a JOIN.
+///
+/// # Arguments
+/// `operator` : The operator chain to rewrite.
+///
+/// # Returns
+/// The rewritten operator chain.
+pub fn optimize(operator: &Operator) -> Operator {
+    prune(operator, None)
+}
+
+#[cfg(test)]
+fn parse_query(query: &str) -> Operator {
+    match crate::commands::parse_command(&format!("{}\n", query)) {
+        crate::commands::Command::Operator(operator) => operator,
+        command => panic!("Not a query: {:?}", command),
+    }
+}
+
+/// Helper function to check that the optimized `query` produces the same result as the query.
+#[cfg(test)]
+fn assert_same_result(query: &str) {
+    let mut context = crate::operators::ExecutionContext::default();
+    let operator = parse_query(query);
+    let expected = crate::operators::process_operator(&operator, &mut context);
+    let optimized = crate::operators::process_operator(&optimize(&operator), &mut context);
+    assert_eq!(
+        optimized
+            .map(|table| table.to_string())
+            .map_err(|error| error.to_string()),
+        expected
+            .map(|table| table.to_string())
+            .map_err(|error| error.to_string())
+    );
+}
+
+#[test]
+fn test_optimize_join() {
+    let query = "FROM city.csv JOIN country.csv CountryCode SELECT CityName,Continent";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv SELECT CityName,CountryCode JOIN (FROM country.csv SELECT CountryCode,Continent) CountryCode SELECT CityName,Continent"
+    );
+    assert_same_result(query);
+
+    // The columns read by FILTER are kept, and an input needing all its columns is left as is.
+    let query = "FROM language.csv LEFTJOIN country.csv CountryCode FILTER CountryPop > 100000000 SELECT Language,CountryName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM language.csv LEFTJOIN (FROM country.csv SELECT CountryCode,CountryName,CountryPop) CountryCode FILTER CountryPop > 100000000 SELECT Language,CountryName"
+    );
+    assert_same_result(query);
+}
+
+#[test]
+fn test_optimize_orderby() {
+    let query = "FROM city.csv ORDERBY CityPop TAKE 3 SELECT CityName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv SELECT CityName,CityPop ORDERBY CityPop TAKE 3 SELECT CityName"
+    );
+    assert_same_result(query);
+
+    // The columns of a RENAME are followed to the input.
+    let query = "FROM city.csv RENAME CityPop Population ORDERBY Population ASC SELECT CityName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv RENAME CityPop Population SELECT CityName,Population ORDERBY Population ASC SELECT CityName"
+    );
+    assert_same_result(query);
+
+    // The column converted by CAST is kept.
+    let query = "FROM city.csv CAST CountryCode AS STRING ORDERBY CityPop SELECT CityName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv CAST CountryCode AS STRING SELECT CityName,CityPop ORDERBY CityPop SELECT CityName"
+    );
+    assert_same_result(query);
+
+    // The columns dropped by SELECT * EXCEPT are dropped before ORDERBY, along with the unused ones.
+    let query = "FROM country.csv SELECT * EXCEPT Capital ORDERBY CountryPop SELECT CountryName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM country.csv SELECT * EXCEPT Capital SELECT CountryName,CountryPop ORDERBY CountryPop SELECT CountryName"
+    );
+    assert_same_result(query);
+
+    // The columns are narrowed after a REORDER, in the order it gives them.
+    let query = "FROM city.csv REORDER CityPop ORDERBY CityPop SELECT CityName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv REORDER CityPop SELECT CityPop,CityName ORDERBY CityPop SELECT CityName"
+    );
+    assert_same_result(query);
+
+    // TOPN keeps the columns it groups and ranks the rows by.
+    let query = "FROM city.csv TOPN 3 BY CountryCode ORDER CityPop SELECT CityName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv SELECT CityName,CountryCode,CityPop TOPN 3 BY CountryCode ORDER CityPop SELECT CityName"
+    );
+    assert_same_result(query);
+
+    // RANK keeps the columns it ranks the rows by, and adds the rank column.
+    let query = "FROM city.csv RANK BY CityPop PER CountryCode ORDERBY rank SELECT CityName,rank";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv SELECT CityName,CountryCode,CityPop RANK BY CityPop PER CountryCode SELECT CityName,rank ORDERBY rank SELECT CityName,rank"
+    );
+    assert_same_result(query);
+
+    // EXTEND adds its columns, and needs the ones they are computed from.
+    let query = "FROM city.csv TAKE 10 EXTEND Pop = CityPop / 1000 ORDERBY Pop SELECT CityName";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv TAKE 10 EXTEND Pop = CityPop/1000 SELECT CityName,Pop ORDERBY Pop SELECT CityName"
+    );
+    assert_same_result(query);
+
+    // The operators that reduce their input only need the columns they read.
+    let query = "FROM city.csv ORDERBY CityPop TAKE 100 COUNTBY CountryCode";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv SELECT CountryCode,CityPop ORDERBY CityPop TAKE 100 COUNTBY CountryCode"
+    );
+    assert_same_result(query);
+    let query = "FROM city.csv ORDERBY CityPop TAKE 100 PIVOT CountryCode CityID COUNT(CityID)";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv SELECT CityID,CountryCode,CityPop ORDERBY CityPop TAKE 100 PIVOT CountryCode CityID COUNT(CityID)"
+    );
+    assert_same_result(query);
+    let query = "FROM city.csv ORDERBY CityPop TAKE 100 COUNT";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv ORDERBY CityPop TAKE 100 COUNT *"
+    );
+    assert_same_result(query);
+}
+
+#[test]
+fn test_optimize_join_strategy() {
+    let query = "FROM city.csv ORDERBY CountryCode ASC TAKE 500 JOIN country.csv CountryCode";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv ORDERBY CountryCode ASC TAKE 500 JOIN country.csv CountryCode USING MERGE"
+    );
+    assert_same_result(query);
+
+    // The order is followed through SELECT and RENAME, and joins keeping the order of their input.
+    let query = "FROM city.csv ORDERBY CityID RENAME CityID Capital JOIN country.csv Capital SELECT CountryName,Capital JOIN city.csv ON Capital=CityID";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv ORDERBY CityID RENAME CityID Capital JOIN country.csv Capital USING MERGE SELECT CountryName,Capital JOIN city.csv ON Capital=CityID USING MERGE"
+    );
+    assert_same_result(query);
+
+    // A hint isn't overridden, and an input sorted on another column is hash joined.
+    for query in [
+        "FROM city.csv ORDERBY CountryCode JOIN country.csv CountryCode USING HASH",
+        "FROM city.csv ORDERBY CityPop JOIN country.csv CountryCode",
+        "FROM city.csv ORDERBY CountryCode ASC CAST CountryCode AS STRING JOIN country.csv CountryCode",
+    ] {
+        let operator = parse_query(query);
+        assert_eq!(optimize(&operator), operator, "{}", query);
+    }
+    // Unmatched rows of the joined dataset come after the sorted ones.
+    let query = "FROM city.csv ORDERBY CountryCode RIGHTJOIN country.csv CountryCode JOIN language.csv CountryCode";
+    assert_eq!(
+        optimize(&parse_query(query)).to_string(),
+        "FROM city.csv ORDERBY CountryCode RIGHTJOIN country.csv CountryCode USING MERGE JOIN language.csv CountryCode"
+    );
+    assert_same_result(query);
+    assert_same_result("FROM city.csv JOIN country.csv CountryCode USING MERGE");
+}
+
+#[test]
+fn test_optimize_unchanged() {
+    for query in [
+        // All the columns are needed.
+        "FROM city.csv JOIN country.csv CountryCode ORDERBY CityPop TAKE 5",
+        "FROM city.csv ORDERBY CityPop SELECT CityID,CityName,CountryCode,CityPop",
+        // Removing columns would change the names given to the clashing columns.
+        "FROM city.csv TAKE 20 JOIN city.csv CountryCode SELECT CityName,CityName_2",
+        "FROM country.csv LEFTJOIN city.csv ON Capital=CityID SELECT CountryName,CityName",
+        // Removing columns would change which rows are duplicates.
+        "FROM language.csv ORDERBY CountryCode DISTINCT SELECT CountryCode",
+        // The query fails the same way.
+        "FROM city.csv ORDERBY CityPop SELECT Name",
+        "FROM city.csv JOIN country.csv Capital SELECT CityName",
+    ] {
+        let operator = parse_query(query);
+        assert_eq!(optimize(&operator), operator, "{}", query);
+        assert_same_result(query);
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use crate::operators::{ExecutionContext, OperatorError, ResultLimits};
+use crate::table::{ColumnSchema, Row, Table};
+
+/// A source of [`Row`]s that are produced one at a time, only when the consumer asks for the next
+/// one (i.e., a Volcano-style iterator). Operators that can work on one row at a time, like TAKE
+/// and FILTER, pull their input from the [`RowSource`] of the operator they chain, so e.g. a TAKE
+/// stops the operators before it once it has enough rows.
+pub trait RowSource {
+    /// Produces the next row.
+    ///
+    /// # Arguments
+    /// `context` : The [`crate::operators::ExecutionContext`] the query is processed in.
+    ///
+    /// # Returns
+    /// On success: The next row, or `None` once all the rows have been produced.
+    /// On failure: The [`OperatorError`] that stopped the row from being produced.
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError>;
+
+    /// Returns the schema of each column. For columns computed from the rows themselves, e.g. by
+    /// SELECT, this only accounts for the rows produced so far.
+    fn schema(&self) -> Vec<ColumnSchema>;
+}
+
+/// The output of an operator: the names of its columns, which are known up front, and the
+/// [`RowSource`] producing its rows on demand.
+pub struct RowStream<'a> {
+    /// The names of the columns in each row.
+    pub header: Vec<String>,
+    /// The qualified names of the columns, e.g. `city.CityName`. See [`Table::qualified_names`].
+    pub qualified_names: Vec<Option<String>>,
+    /// Produces the rows.
+    pub source: Box<dyn RowSource + 'a>,
+}
+
+impl<'a> RowStream<'a> {
+    /// Creates a stream of the rows produced by `source`, whose columns are named by `header`.
+    /// The columns have no qualified names; see [`RowStream::with_qualified_names`].
+    pub fn new<S: RowSource + 'a>(header: Vec<String>, source: S) -> Self {
+        RowStream {
+            header,
+            qualified_names: Vec::new(),
+            source: Box::new(source),
+        }
+    }
+
+    /// Gives the columns of the stream their `qualified_names`, e.g. those of the columns of the
+    /// input of an operator that passes them through unchanged.
+    pub fn with_qualified_names(mut self, qualified_names: Vec<Option<String>>) -> Self {
+        self.qualified_names = qualified_names;
+        self
+    }
+
+    /// Produces the next row. See [`RowSource::next_row`].
+    pub fn next_row(
+        &mut self,
+        context: &mut ExecutionContext,
+    ) -> Result<Option<Row>, OperatorError> {
+        self.source.next_row(context)
+    }
+
+    /// Returns the schema of each column. See [`RowSource::schema`].
+    pub fn schema(&self) -> Vec<ColumnSchema> {
+        self.source.schema()
+    }
+
+    /// Produces all the remaining rows and collects them into a [`Table`].
+    ///
+    /// # Returns
+    /// On success: The [`Table`] holding the rows.
+    /// On failure: The [`OperatorError`] that stopped a row from being produced.
+    pub fn collect(self, context: &mut ExecutionContext) -> Result<Table, OperatorError> {
+        self.collect_within(&ResultLimits::default(), String::new, context)
+    }
+
+    /// Like [`RowStream::collect`], but stops as soon as the collected rows exceed the `limits`.
+    ///
+    /// # Arguments
+    /// `limits` : The limits on the number of rows and their estimated size.
+    /// `operator` : Names the operator producing the rows, for the error message.
+    /// `context` : The [`ExecutionContext`] the query is processed in.
+    ///
+    /// # Returns
+    /// On success: The [`Table`] holding the rows.
+    /// On failure: [`OperatorError::ResultTooLarge`], [`OperatorError::Cancelled`] if the query
+    /// is cancelled, or the [`OperatorError`] that stopped a row from being produced.
+    pub fn collect_within(
+        mut self,
+        limits: &ResultLimits,
+        operator: impl Fn() -> String,
+        context: &mut ExecutionContext,
+    ) -> Result<Table, OperatorError> {
+        let mut rows = Vec::new();
+        let mut bytes = 0;
+        while let Some(row) = self.source.next_row(context)? {
+            context.check_cancelled()?;
+            bytes += row.estimated_size();
+            rows.push(row);
+            limits.check(rows.len(), bytes, &operator)?;
+        }
+        Ok(Table {
+            header: self.header,
+            schema: self.source.schema(),
+            rows,
+            qualified_names: self.qualified_names,
+        })
+    }
+}
+
+/// Produces the rows of a [`Table`] owned by the stream, e.g. the output of an operator like
+/// ORDERBY that needs all of its input before it can produce a row.
+struct OwnedRows {
+    rows: std::vec::IntoIter<Row>,
+    schema: Vec<ColumnSchema>,
+}
+
+impl RowSource for OwnedRows {
+    fn next_row(&mut self, _: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        Ok(self.rows.next())
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.schema.clone()
+    }
+}
+
+impl From<Table> for RowStream<'_> {
+    fn from(table: Table) -> Self {
+        RowStream::new(
+            table.header,
+            OwnedRows {
+                rows: table.rows.into_iter(),
+                schema: table.schema,
+            },
+        )
+        .with_qualified_names(table.qualified_names)
+    }
+}
+
+/// Produces copies of the rows of a [`Table`] shared with others, e.g. the
+/// [`crate::data::DatasetCache`]. Rows are only copied when they are asked for, so rows that are
+/// never asked for are never copied.
+struct SharedRows {
+    table: Arc<Table>,
+    /// The index of the next row to produce.
+    position: usize,
+}
+
+impl RowSource for SharedRows {
+    fn next_row(&mut self, context: &mut ExecutionContext) -> Result<Option<Row>, OperatorError> {
+        // Scans are where a query that filters out most rows spends its time, so they stop too.
+        context.check_cancelled()?;
+        let row = self.table.rows.get(self.position).cloned();
+        self.position += 1;
+        if row.is_some() {
+            context.record_scanned(1);
+        }
+        Ok(row)
+    }
+
+    fn schema(&self) -> Vec<ColumnSchema> {
+        self.table.schema.clone()
+    }
+}
+
+impl From<Arc<Table>> for RowStream<'_> {
+    fn from(table: Arc<Table>) -> Self {
+        let qualified_names = table.qualified_names.clone();
+        RowStream::new(table.header.clone(), SharedRows { table, position: 0 })
+            .with_qualified_names(qualified_names)
+    }
+}
+
+#[test]
+fn test_row_stream_from_table() {
+    let table = Table::new(
+        vec!["Planet".to_string(), "Moons".to_string()],
+        vec![
+            Row {
+                cells: vec![
+                    crate::table::Cell::String("Earth".to_string()),
+                    crate::table::Cell::Int64(1),
+                ],
+            },
+            Row {
+                cells: vec![
+                    crate::table::Cell::String("Mars".to_string()),
+                    crate::table::Cell::Int64(2),
+                ],
+            },
+        ],
+    );
+    let mut context = ExecutionContext::default();
+
+    let mut stream = RowStream::from(table.clone());
+    assert_eq!(stream.header, table.header);
+    assert_eq!(
+        stream.next_row(&mut context).unwrap().unwrap().join(),
+        "Earth,1"
+    );
+    let rest = stream.collect(&mut context).unwrap();
+    assert_eq!(rest.schema, table.schema);
+    assert_eq!(rest.rows.len(), 1);
+    assert_eq!(rest.rows[0].join(), "Mars,2");
+
+    let shared = Arc::new(table);
+    let mut stream = RowStream::from(shared.clone());
+    assert_eq!(
+        stream.next_row(&mut context).unwrap().unwrap().join(),
+        "Earth,1"
+    );
+    assert_eq!(
+        stream.next_row(&mut context).unwrap().unwrap().join(),
+        "Mars,2"
+    );
+    assert!(stream.next_row(&mut context).unwrap().is_none());
+    assert!(stream.next_row(&mut context).unwrap().is_none());
+    assert_eq!(stream.schema(), shared.schema);
+    // The shared table itself is left untouched.
+    assert_eq!(shared.rows.len(), 2);
+}
@@ -0,0 +1,89 @@
+use std::error::Error;
+use std::fs::File;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+/// Helper function to serialize a [`Duration`] as a (fractional) number of milliseconds, which is
+/// easier to graph than serde's default `{ secs, nanos }` representation.
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+/// Execution statistics for a single operator, collected while processing a query.
+/// See [`crate::operators::ExecutionContext::with_profiling`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OperatorProfile {
+    /// The operator and its arguments, e.g. "ORDERBY CityPop".
+    pub operator: String,
+    /// How deeply the operator is nested in the query. The outermost operator has a depth of 0.
+    pub depth: usize,
+    /// The number of rows produced by the operator. Operators only produce the rows pulled by the
+    /// operator that chains them, e.g. a FROM followed by TAKE 5 produces 5 rows.
+    pub rows: usize,
+    /// Time spent processing the operator, including the operators it chains.
+    #[serde(rename = "elapsed_ms", serialize_with = "serialize_millis")]
+    pub elapsed: Duration,
+    /// Time spent processing the operator, excluding the operators it chains.
+    #[serde(rename = "self_elapsed_ms", serialize_with = "serialize_millis")]
+    pub self_elapsed: Duration,
+    /// Estimated memory used by the rows produced by the operator.
+    pub estimated_bytes: usize,
+}
+
+/// Execution statistics for a single query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryProfile {
+    /// The query as it was parsed.
+    pub query: String,
+    /// Whether the query completed successfully.
+    pub succeeded: bool,
+    /// Total time spent processing the query.
+    #[serde(rename = "elapsed_ms", serialize_with = "serialize_millis")]
+    pub elapsed: Duration,
+    /// Statistics for each operator in the query, with every operator after the operators it
+    /// chains.
+    pub operators: Vec<OperatorProfile>,
+}
+
+/// The execution statistics of every query run in a session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionProfile {
+    /// One entry per query, in the order they were run.
+    pub queries: Vec<QueryProfile>,
+}
+
+impl SessionProfile {
+    /// Writes the profile to `path` as JSON, replacing the file if it already exists.
+    ///
+    /// Returns
+    /// An error propagated from creating the file or serializing the profile.
+    pub fn write(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_session_profile_serialize() {
+    let profile = SessionProfile {
+        queries: vec![QueryProfile {
+            query: "FROM city.csv".to_string(),
+            succeeded: true,
+            elapsed: Duration::from_micros(2500),
+            operators: vec![OperatorProfile {
+                operator: "FROM city.csv".to_string(),
+                depth: 0,
+                rows: 4079,
+                elapsed: Duration::from_millis(2),
+                self_elapsed: Duration::from_millis(2),
+                estimated_bytes: 1024,
+            }],
+        }],
+    };
+    assert_eq!(
+        serde_json::to_string(&profile).unwrap(),
+        "{\"queries\":[{\"query\":\"FROM city.csv\",\"succeeded\":true,\"elapsed_ms\":2.5,\"operators\":[{\"operator\":\"FROM city.csv\",\"depth\":0,\"rows\":4079,\"elapsed_ms\":2.0,\"self_elapsed_ms\":2.0,\"estimated_bytes\":1024}]}]}"
+    );
+}
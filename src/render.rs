@@ -0,0 +1,514 @@
+use std::ffi::OsStr;
+
+use crate::operators::Operator;
+use crate::settings::OutputFormat;
+use crate::table::{Cell, Table};
+
+/// The ANSI escape code that starts bold text, used for the header row.
+const C_BOLD: &str = "\x1b[1m";
+/// The ANSI escape code that starts dimmed text, used for missing values.
+const C_DIM: &str = "\x1b[2m";
+/// The ANSI escape code that starts red text, used for error messages.
+const C_RED: &str = "\x1b[31m";
+/// The ANSI escape code that starts cyan text, used for numbers.
+const C_CYAN: &str = "\x1b[36m";
+/// The ANSI escape code that ends the styles started by the others.
+const C_RESET: &str = "\x1b[0m";
+
+/// Decides whether the output is colored: only when it goes to a terminal, and the user hasn't
+/// opted out by setting the `NO_COLOR` environment variable to a non-empty value (see
+/// <https://no-color.org>).
+///
+/// # Arguments
+/// `is_terminal` : Whether the output goes to a terminal rather than a file or a pipe.
+/// `no_color` : The value of the `NO_COLOR` environment variable, if it is set.
+pub fn color_enabled(is_terminal: bool, no_color: Option<&OsStr>) -> bool {
+    is_terminal && no_color.is_none_or(OsStr::is_empty)
+}
+
+/// Helper function to wrap `text` in the ANSI escape codes of the `style`, if any, if `color` is
+/// on.
+fn paint(text: &str, style: Option<&str>, color: bool) -> String {
+    match style {
+        Some(style) if color && !text.is_empty() => format!("{}{}{}", style, text, C_RESET),
+        _ => text.to_string(),
+    }
+}
+
+/// Renders an error `message`, in red if `color` is on.
+pub fn error(message: &str, color: bool) -> String {
+    paint(message, Some(C_RED), color)
+}
+
+/// Renders the `table` as text with aligned columns, for display in a terminal. Each column is
+/// padded to the width of its widest value, the columns of numbers are right-aligned even if
+/// some of their values are missing, and the header is separated from the rows by a line of
+/// dashes.
+///
+/// # Arguments
+/// `table` : The table to render.
+/// `color` : Whether to style the table with ANSI escape codes: the header in bold, numbers in
+/// cyan, and missing values as a dimmed `NULL` rather than left empty.
+///
+/// # Returns
+/// The rendered table, with one line for the header, one for the separator, and one per row.
+/// Every line ends with a newline.
+pub fn render_table(table: &Table, color: bool) -> String {
+    // The text of each value, and the style it is painted in.
+    let rows: Vec<Vec<(String, Option<&str>)>> = table
+        .rows
+        .iter()
+        .map(|row| {
+            row.cells
+                .iter()
+                .map(|cell| match cell {
+                    cell if cell.is_null() && color => ("NULL".to_string(), Some(C_DIM)),
+                    Cell::Int64(_) | Cell::OptInt64(_) | Cell::Float64(_) => {
+                        (cell.to_string(), Some(C_CYAN))
+                    }
+                    cell => (cell.to_string(), None),
+                })
+                .collect()
+        })
+        .collect();
+    let header: Vec<(String, Option<&str>)> = table
+        .header
+        .iter()
+        .map(|name| (name.clone(), Some(C_BOLD)))
+        .collect();
+    let mut widths: Vec<usize> = header
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .collect();
+    for row in &rows {
+        for (width, (value, _)) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let right_aligned: Vec<bool> = table
+        .schema
+        .iter()
+        .map(|column| column.data_type.is_numeric())
+        .collect();
+
+    // The padding is left outside the escape codes, so that the trailing spaces can be trimmed.
+    let format_line = |values: &[(String, Option<&str>)]| {
+        let line = values
+            .iter()
+            .zip(widths.iter().zip(&right_aligned))
+            .map(|((value, style), (width, right_aligned))| {
+                let padding = " ".repeat(width - value.chars().count());
+                match right_aligned {
+                    true => format!("{}{}", padding, paint(value, *style, color)),
+                    false => format!("{}{}", paint(value, *style, color), padding),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" | ");
+        format!("{}\n", line.trim_end())
+    };
+
+    let mut output = format_line(&header);
+    output.push_str(
+        &widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<String>>()
+            .join("-+-"),
+    );
+    output.push('\n');
+    for row in &rows {
+        output.push_str(&format_line(row));
+    }
+    output
+}
+
+/// Renders the `table` as one JSON object per line, mapping each column name to its value in the
+/// row, in the order of the columns. Missing values are `null`.
+///
+/// # Arguments
+/// `table` : The table to render.
+///
+/// # Returns
+/// The rendered rows, each on its own line ending with a newline. A table without rows renders as
+/// an empty string.
+pub fn render_jsonl(table: &Table) -> String {
+    let names: Vec<String> = table
+        .header
+        .iter()
+        .map(|name| serde_json::to_string(name).unwrap_or_default())
+        .collect();
+    table
+        .rows
+        .iter()
+        .map(|row| {
+            let fields = names
+                .iter()
+                .zip(&row.cells)
+                .map(|(name, cell)| {
+                    format!(
+                        "{}:{}",
+                        name,
+                        serde_json::to_string(cell).unwrap_or_default()
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(",");
+            format!("{{{}}}\n", fields)
+        })
+        .collect()
+}
+
+/// Helper function to escape the `text` of a Markdown table cell, so that a `|` doesn't end the
+/// cell and a line break doesn't end the row.
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Renders the `table` as a GitHub-flavored Markdown table, with the columns of numbers
+/// right-aligned.
+///
+/// # Arguments
+/// `table` : The table to render.
+///
+/// # Returns
+/// The rendered table, with one line for the header, one for the alignment row, and one per row.
+/// Every line ends with a newline.
+pub fn render_markdown(table: &Table) -> String {
+    let format_line = |values: Vec<String>| {
+        let cells: String = values
+            .iter()
+            .map(|value| match value.is_empty() {
+                true => " |".to_string(),
+                false => format!(" {} |", value),
+            })
+            .collect();
+        format!("|{}\n", cells)
+    };
+    let mut output = format_line(
+        table
+            .header
+            .iter()
+            .map(|name| escape_markdown(name))
+            .collect(),
+    );
+    output.push_str(&format_line(
+        table
+            .schema
+            .iter()
+            .map(|column| match column.data_type.is_numeric() {
+                true => "---:".to_string(),
+                false => "---".to_string(),
+            })
+            .collect(),
+    ));
+    for row in &table.rows {
+        output.push_str(&format_line(
+            row.cells
+                .iter()
+                .map(|cell| escape_markdown(&cell.to_string()))
+                .collect(),
+        ));
+    }
+    output
+}
+
+/// Renders the `table` in the output `format`, for printing a query result.
+///
+/// # Arguments
+/// `table` : The table to render.
+/// `format` : The format to render it in.
+/// `color` : Whether to style a [`OutputFormat::Table`] with ANSI escape codes. The other formats
+/// are meant to be read by other tools, so they are never styled.
+///
+/// # Returns
+/// The rendered table, and the number of lines at its start that name the columns, e.g. to repeat
+/// them on each page of a long result.
+pub fn render_output(table: &Table, format: OutputFormat, color: bool) -> (String, usize) {
+    match format {
+        OutputFormat::Table => (render_table(table, color), 2),
+        OutputFormat::Csv => (table.to_string(), 1),
+        OutputFormat::Jsonl => (render_jsonl(table), 0),
+        OutputFormat::Markdown => (render_markdown(table), 2),
+    }
+}
+
+/// Formats a number of rows for a message, e.g. `1 row` or `5 rows`.
+pub fn row_count(count: usize) -> String {
+    match count {
+        1 => "1 row".to_string(),
+        _ => format!("{} rows", count),
+    }
+}
+
+/// What is shown for the result of a query, by the REPL and by the clients of the server alike.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryOutput {
+    /// The query wrote its whole result to a file with INTO, so only this message is shown, e.g.
+    /// `Wrote 5 rows to out.csv.`
+    Written(String),
+    /// The rows to show, and the message shown after them if they were cut short by the default
+    /// limit.
+    Rows {
+        table: Table,
+        limit_message: Option<String>,
+    },
+}
+
+/// Prepares the result `table` of a query for output. A result written to a file with INTO is
+/// only described, and the rows of a result are cut short at the `default_limit`, unless the
+/// query asks for a specific number of rows with TAKE, to guard against printing huge tables by
+/// accident.
+///
+/// # Arguments
+/// `operator` : The operator chain of the query.
+/// `table` : The result of the query.
+/// `default_limit` : The number of rows shown of results of queries that don't end with a TAKE,
+/// or `None` for all of them.
+pub fn query_output(
+    operator: &Operator,
+    mut table: Table,
+    default_limit: Option<usize>,
+) -> QueryOutput {
+    if let Operator::Into { path, .. } = operator {
+        return QueryOutput::Written(format!(
+            "Wrote {} to {}.",
+            row_count(table.rows.len()),
+            path
+        ));
+    }
+    let total_rows = table.rows.len();
+    let limit = default_limit.filter(|limit| !operator.ends_with_take() && total_rows > *limit);
+    if let Some(limit) = limit {
+        table.rows.truncate(limit);
+    }
+    QueryOutput::Rows {
+        table,
+        limit_message: limit.map(|limit| {
+            format!(
+                "Showing the first {} of {} rows. Add TAKE <number> to the query, or use '\\limit <number>' or '\\limit off' to change this limit.",
+                limit, total_rows
+            )
+        }),
+    }
+}
+
+#[test]
+fn test_color_enabled() {
+    assert!(color_enabled(true, None));
+    assert!(color_enabled(true, Some(OsStr::new(""))));
+    assert!(!color_enabled(true, Some(OsStr::new("1"))));
+    assert!(!color_enabled(false, None));
+}
+
+#[test]
+fn test_error() {
+    assert_eq!(error("No such column", false), "No such column");
+    assert_eq!(
+        error("No such column", true),
+        "\x1b[31mNo such column\x1b[0m"
+    );
+}
+
+#[test]
+fn test_render_table() {
+    use crate::table::Row;
+    let table = Table::new(
+        vec!["Name".to_string(), "Pop".to_string(), "Capital".to_string()],
+        vec![
+            Row {
+                cells: vec![
+                    Cell::String("Aruba".to_string()),
+                    Cell::Int64(103000),
+                    Cell::OptInt64(Some(129)),
+                ],
+            },
+            Row {
+                cells: vec![
+                    Cell::String("Atlantis".to_string()),
+                    Cell::Int64(5),
+                    Cell::OptInt64(None),
+                ],
+            },
+        ],
+    );
+    // The columns of numbers are right-aligned, even those with missing values.
+    assert_eq!(
+        render_table(&table, false),
+        "Name     |    Pop | Capital\n\
+         ---------+--------+--------\n\
+         Aruba    | 103000 |     129\n\
+         Atlantis |      5 |\n"
+    );
+    // The header is bold, numbers are cyan, and missing values are a dimmed NULL.
+    assert_eq!(
+        render_table(&table, true),
+        "\x1b[1mName\x1b[0m     |    \x1b[1mPop\x1b[0m | \x1b[1mCapital\x1b[0m\n\
+         ---------+--------+--------\n\
+         Aruba    | \x1b[36m103000\x1b[0m |     \x1b[36m129\x1b[0m\n\
+         Atlantis |      \x1b[36m5\x1b[0m |    \x1b[2mNULL\x1b[0m\n"
+    );
+
+    // A dimmed NULL is right-aligned with the decimal numbers of its column.
+    let share = Table::new(
+        vec!["Share".to_string()],
+        vec![
+            Row {
+                cells: vec![Cell::Float64(0.5)],
+            },
+            Row {
+                cells: vec![Cell::Null],
+            },
+        ],
+    );
+    assert_eq!(render_table(&share, false), "Share\n-----\n  0.5\n\n");
+    assert_eq!(
+        render_table(&share, true),
+        "\x1b[1mShare\x1b[0m\n-----\n  \x1b[36m0.5\x1b[0m\n \x1b[2mNULL\x1b[0m\n"
+    );
+}
+
+#[test]
+fn test_render_jsonl() {
+    use crate::table::Row;
+    let table = Table::new(
+        vec!["Name".to_string(), "Pop".to_string(), "Share".to_string()],
+        vec![
+            Row {
+                cells: vec![
+                    Cell::String("Aruba \"ABW\"".to_string()),
+                    Cell::Int64(103000),
+                    Cell::Float64(0.5),
+                ],
+            },
+            Row {
+                cells: vec![
+                    Cell::String("Atlantis".to_string()),
+                    Cell::OptInt64(None),
+                    Cell::Null,
+                ],
+            },
+        ],
+    );
+    assert_eq!(
+        render_jsonl(&table),
+        "{\"Name\":\"Aruba \\\"ABW\\\"\",\"Pop\":103000,\"Share\":0.5}\n\
+         {\"Name\":\"Atlantis\",\"Pop\":null,\"Share\":null}\n"
+    );
+    let empty = Table {
+        rows: vec![],
+        ..table
+    };
+    assert_eq!(render_jsonl(&empty), "");
+}
+
+#[test]
+fn test_render_markdown() {
+    use crate::table::Row;
+    let table = Table::new(
+        vec!["Name".to_string(), "Pop".to_string()],
+        vec![
+            Row {
+                cells: vec![
+                    Cell::String("A|B".to_string()),
+                    Cell::OptInt64(Some(103000)),
+                ],
+            },
+            Row {
+                cells: vec![Cell::String("Atlantis".to_string()), Cell::OptInt64(None)],
+            },
+        ],
+    );
+    assert_eq!(
+        render_markdown(&table),
+        "| Name | Pop |\n| --- | ---: |\n| A\\|B | 103000 |\n| Atlantis | |\n"
+    );
+    let numeric = Table::new(
+        vec!["Pop".to_string()],
+        vec![Row {
+            cells: vec![Cell::Int64(103000)],
+        }],
+    );
+    assert_eq!(render_markdown(&numeric), "| Pop |\n| ---: |\n| 103000 |\n");
+    assert_eq!(
+        render_output(&table, OutputFormat::Markdown, true),
+        (render_markdown(&table), 2)
+    );
+    assert_eq!(
+        render_output(&table, OutputFormat::Csv, true),
+        ("Name,Pop\nA|B,103000\nAtlantis,\n".to_string(), 1)
+    );
+}
+
+#[test]
+fn test_row_count() {
+    assert_eq!(row_count(0), "0 rows");
+    assert_eq!(row_count(1), "1 row");
+    assert_eq!(row_count(2), "2 rows");
+}
+
+#[test]
+fn test_query_output() {
+    let table = Table::new(
+        vec!["n".to_string()],
+        (0..5)
+            .map(|value| crate::table::Row {
+                cells: vec![Cell::Int64(value)],
+            })
+            .collect(),
+    );
+    let from = Operator::From(crate::data::Dataset::City);
+    let QueryOutput::Rows {
+        table: shown,
+        limit_message,
+    } = query_output(&from, table.clone(), Some(3))
+    else {
+        panic!("Expected the rows to be shown");
+    };
+    assert_eq!(shown.rows.len(), 3);
+    assert_eq!(
+        limit_message.as_deref(),
+        Some("Showing the first 3 of 5 rows. Add TAKE <number> to the query, or use '\\limit <number>' or '\\limit off' to change this limit.")
+    );
+
+    // Results no larger than the limit, and those of queries ending with a TAKE, are shown
+    // whole.
+    let whole = QueryOutput::Rows {
+        table: table.clone(),
+        limit_message: None,
+    };
+    assert_eq!(query_output(&from, table.clone(), Some(5)), whole);
+    assert_eq!(query_output(&from, table.clone(), None), whole);
+    let take = Operator::Take {
+        chain: Box::new(from.clone()),
+        count: 5,
+    };
+    assert_eq!(query_output(&take, table.clone(), Some(3)), whole);
+    // A TAKE followed by other operators doesn't bound the rows they produce.
+    let take_then_select = Operator::Select {
+        chain: Box::new(take.clone()),
+        columns: vec![crate::expression::Expression::Column("n".to_string()).into()],
+    };
+    assert_eq!(
+        query_output(&take_then_select, table.clone(), Some(3)),
+        query_output(&from, table.clone(), Some(3))
+    );
+
+    let into = Operator::Into {
+        chain: Box::new(from),
+        path: "out.csv".to_string(),
+        format: crate::operators::ExportFormat::Csv,
+    };
+    assert_eq!(
+        query_output(&into, table.clone(), Some(3)),
+        QueryOutput::Written("Wrote 5 rows to out.csv.".to_string())
+    );
+    let one = Table {
+        rows: table.rows[..1].to_vec(),
+        ..table
+    };
+    assert_eq!(
+        query_output(&into, one, Some(3)),
+        QueryOutput::Written("Wrote 1 row to out.csv.".to_string())
+    );
+}
@@ -0,0 +1,277 @@
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+
+use crate::table::{Cell, Row};
+
+/// User-defined scalar functions, written in the [`rhai`] scripting language and loaded from a
+/// startup script. The functions can be called from SELECT expressions.
+///
+/// Example script:
+/// ```text
+/// fn slug(s) { let t = s.to_lower(); t.replace(" ", "-"); t }
+/// ```
+///
+/// Cloning is cheap, as the clones share the compiled script, so that the sessions of a
+/// `--listen` server can each call the functions without compiling the script again.
+#[derive(Clone)]
+pub struct ScriptFunctions {
+    /// The engine used to run the functions.
+    engine: Arc<Engine>,
+    /// The compiled script holding the function definitions.
+    ast: AST,
+}
+
+impl Debug for ScriptFunctions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptFunctions")
+            .field(
+                "functions",
+                &self
+                    .ast
+                    .iter_functions()
+                    .map(|function| function.name)
+                    .collect::<Vec<&str>>(),
+            )
+            .finish()
+    }
+}
+
+impl ScriptFunctions {
+    /// Compiles the script at `path`.
+    ///
+    /// Returns
+    /// The functions defined in the script, or an error propagated from reading or compiling it.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        ScriptFunctions::from_source(&std::fs::read_to_string(path)?)
+    }
+
+    /// Compiles the script in `source`.
+    ///
+    /// Returns
+    /// The functions defined in the script, or an error propagated from compiling it.
+    pub fn from_source(source: &str) -> Result<Self, Box<dyn Error>> {
+        let engine = Engine::new();
+        let ast = engine.compile(source)?;
+        Ok(ScriptFunctions {
+            engine: Arc::new(engine),
+            ast,
+        })
+    }
+
+    /// Returns `true` if the script defines a function called `name` taking `arity` arguments.
+    pub fn has_function(&self, name: &str, arity: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == arity)
+    }
+
+    /// Calls the function `name` with the `arguments`.
+    ///
+    /// # Returns
+    /// The value returned by the function, or a message describing why the call failed.
+    pub fn call(&self, name: &str, arguments: Vec<Cell>) -> Result<Cell, String> {
+        let arguments: Vec<Dynamic> = arguments.into_iter().map(cell_to_dynamic).collect();
+        match self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, name, arguments)
+        {
+            Ok(value) => dynamic_to_cell(value),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// A row-level transform, written in the [`rhai`] scripting language and run by the MAP operator.
+/// The script is run once per row, with the row available as an object map from column name to
+/// value called `row`, and must evaluate to the modified map. Columns can be changed, added, or
+/// removed.
+///
+/// Example script:
+/// ```text
+/// row.CityName = row.CityName.to_upper();
+/// row.Big = row.CityPop > 1000000;
+/// row
+/// ```
+pub struct RowTransform {
+    /// The engine used to run the script.
+    engine: Engine,
+    /// The compiled script.
+    ast: AST,
+}
+
+impl RowTransform {
+    /// Compiles the script at `path`.
+    ///
+    /// Returns
+    /// The transform, or an error propagated from reading or compiling the script.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        RowTransform::from_source(&std::fs::read_to_string(path)?)
+    }
+
+    /// Compiles the script in `source`.
+    ///
+    /// Returns
+    /// The transform, or an error propagated from compiling the script.
+    pub fn from_source(source: &str) -> Result<Self, Box<dyn Error>> {
+        let engine = Engine::new();
+        let ast = engine.compile(source)?;
+        Ok(RowTransform { engine, ast })
+    }
+
+    /// Runs the script on a single `row`.
+    ///
+    /// # Arguments
+    /// `header` : The names of the columns of the table the `row` belongs to.
+    /// `row` : The row to transform.
+    ///
+    /// # Returns
+    /// The columns of the transformed row and their values. Columns present in the `header` are
+    /// returned first, in the same order, followed by new columns in alphabetical order.
+    /// On failure: A message describing why the script failed.
+    pub fn apply(&self, header: &[String], row: &Row) -> Result<Vec<(String, Cell)>, String> {
+        let mut map = Map::new();
+        for (name, cell) in header.iter().zip(row.cells.iter()) {
+            map.insert(name.as_str().into(), cell_to_dynamic(cell.clone()));
+        }
+        let mut scope = Scope::new();
+        scope.push("row", map);
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast)
+            .map_err(|e| e.to_string())?;
+        let type_name = result.type_name();
+        let mut map = result.try_cast::<Map>().ok_or(format!(
+            "The script must evaluate to the row as a map, not {}",
+            type_name
+        ))?;
+
+        let mut columns = Vec::new();
+        for name in header {
+            if let Some(value) = map.remove(name.as_str()) {
+                columns.push((name.clone(), dynamic_to_cell(value)?));
+            }
+        }
+        for (name, value) in map {
+            columns.push((name.to_string(), dynamic_to_cell(value)?));
+        }
+        Ok(columns)
+    }
+}
+
+/// Converts a [`Cell`] into the value passed to a script function. Missing values are passed as
+/// rhai's unit value `()`.
+fn cell_to_dynamic(cell: Cell) -> Dynamic {
+    match cell {
+        Cell::String(val) => Dynamic::from(val),
+        Cell::Int64(val) => Dynamic::from(val),
+        Cell::OptInt64(Some(val)) => Dynamic::from(val),
+        Cell::Float64(val) => Dynamic::from(val),
+        Cell::OptInt64(None) | Cell::Null => Dynamic::UNIT,
+    }
+}
+
+/// Converts the value returned by a script function into a [`Cell`]. The unit value `()` is
+/// converted to a missing value.
+fn dynamic_to_cell(value: Dynamic) -> Result<Cell, String> {
+    if value.is_unit() {
+        Ok(Cell::OptInt64(None))
+    } else if value.is_int() {
+        Ok(Cell::Int64(value.as_int()?))
+    } else if value.is_float() {
+        Ok(Cell::Float64(value.as_float()?))
+    } else if value.is_string() || value.is_char() || value.is_bool() {
+        Ok(Cell::String(value.to_string()))
+    } else {
+        Err(format!(
+            "Scripts must produce a string, a number, or (), not {}",
+            value.type_name()
+        ))
+    }
+}
+
+#[test]
+fn test_script_functions_call() {
+    let functions =
+        ScriptFunctions::from_source("fn slug(s) { s.to_lower() } fn double(n) { n * 2 }").unwrap();
+    assert!(functions.has_function("slug", 1));
+    assert!(!functions.has_function("slug", 2));
+    assert!(!functions.has_function("upper", 1));
+    assert_eq!(
+        functions.call("slug", vec![Cell::String("New_York".to_string())]),
+        Ok(Cell::String("new_york".to_string()))
+    );
+    assert_eq!(
+        functions.call("double", vec![Cell::OptInt64(Some(21))]),
+        Ok(Cell::Int64(42))
+    );
+    assert_eq!(
+        functions.call("double", vec![Cell::Float64(1.25)]),
+        Ok(Cell::Float64(2.5))
+    );
+}
+
+#[test]
+fn test_script_functions_missing_values() {
+    let functions =
+        ScriptFunctions::from_source("fn or_zero(n) { if n == () { 0 } else { n } }").unwrap();
+    assert_eq!(
+        functions.call("or_zero", vec![Cell::OptInt64(None)]),
+        Ok(Cell::Int64(0))
+    );
+}
+
+#[test]
+fn test_script_functions_errors() {
+    assert!(ScriptFunctions::from_source("fn broken(s) {").is_err());
+    let functions = ScriptFunctions::from_source("fn pair(n) { [n, n] }").unwrap();
+    assert_eq!(
+        functions.call("pair", vec![Cell::Int64(3)]),
+        Err("Scripts must produce a string, a number, or (), not array".to_string())
+    );
+}
+
+#[test]
+fn test_row_transform_apply() {
+    let transform = RowTransform::from_source(
+        "row.Name = row.Name.to_upper(); row.Double = row.Pop * 2; row.remove(\"Code\"); row",
+    )
+    .unwrap();
+    let header = vec!["Code".to_string(), "Name".to_string(), "Pop".to_string()];
+    let row = Row {
+        cells: vec![
+            Cell::String("NLD".to_string()),
+            Cell::String("Haag".to_string()),
+            Cell::Int64(440900),
+        ],
+    };
+    assert_eq!(
+        transform.apply(&header, &row),
+        Ok(vec![
+            ("Name".to_string(), Cell::String("HAAG".to_string())),
+            ("Pop".to_string(), Cell::Int64(440900)),
+            ("Double".to_string(), Cell::Int64(881800)),
+        ])
+    );
+}
+
+#[test]
+fn test_row_transform_errors() {
+    assert!(RowTransform::from_source("row.Name = ").is_err());
+    let header = vec!["Name".to_string()];
+    let row = Row {
+        cells: vec![Cell::String("Haag".to_string())],
+    };
+    let transform = RowTransform::from_source("row.Name").unwrap();
+    assert_eq!(
+        transform.apply(&header, &row),
+        Err("The script must evaluate to the row as a map, not string".to_string())
+    );
+    let transform = RowTransform::from_source("row.Name = [1.5]; row").unwrap();
+    assert_eq!(
+        transform.apply(&header, &row),
+        Err("Scripts must produce a string, a number, or (), not array".to_string())
+    );
+}
@@ -0,0 +1,990 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::commands::{parse_command, parse_command_with, Command};
+use crate::data::{Dataset, DatasetRegistry};
+use crate::engine::{QueryEngine, QueryResult};
+use crate::explain::explain_operator;
+use crate::help::{help_message, operator_help};
+use crate::operators::{find_operator_descriptor, Operator, ResultLimits};
+use crate::optimizer::optimize;
+use crate::render::{query_output, render_output, QueryOutput};
+use crate::settings::{Setting, Settings, Syntax};
+use crate::sql::parse_sql_command;
+
+/// The largest query accepted by the server, in bytes. Larger requests are rejected rather than
+/// read into memory.
+pub const C_MAX_QUERY_SIZE: usize = 1 << 20;
+
+/// How long the server waits for a client to send its request, or to take the response, before
+/// giving up on the connection.
+pub const C_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request read from a client of the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    /// The method of the request, e.g. `POST`.
+    pub method: String,
+    /// The path of the request, without its query string, e.g. `/query`.
+    pub path: String,
+    /// The body of the request, i.e., the text of the query for `POST /query`.
+    pub body: String,
+    /// The value of the `Origin` header, which browsers send with the requests of a web page to
+    /// another site, e.g. `https://dashboard.example.com`.
+    pub origin: Option<String>,
+}
+
+/// A response sent back to a client of the server. The body is always JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpResponse {
+    /// The HTTP status code, e.g. 200.
+    pub status: u16,
+    /// The JSON body of the response.
+    pub body: String,
+    /// The origin of the web page allowed to read the response, sent back as the
+    /// `Access-Control-Allow-Origin` header, if any.
+    pub allow_origin: Option<String>,
+}
+
+impl HttpResponse {
+    /// Creates a response with the given `status` whose body is `{"error": <message>}`.
+    fn error(status: u16, message: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            body: serde_json::json!({ "error": message }).to_string(),
+            allow_origin: None,
+        }
+    }
+
+    /// Returns the reason phrase of the status code, e.g. `OK` for 200.
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Content Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+
+    /// Writes the response to the `stream` as HTTP/1.1. The connection is closed after each
+    /// response.
+    fn write_to(&self, stream: &mut impl Write) -> std::io::Result<()> {
+        let allow_origin = match &self.allow_origin {
+            Some(origin) => format!(
+                "Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n",
+                origin
+            ),
+            None => String::new(),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+            self.status,
+            self.reason(),
+            self.body.len(),
+            allow_origin,
+            self.body
+        )?;
+        stream.flush()
+    }
+}
+
+/// Reads an HTTP/1.1 request from the `stream`: its request line, its headers, and as many bytes
+/// of body as its `Content-Length` header gives.
+///
+/// # Returns
+/// The [`HttpRequest`], or an error response if the request is malformed or its body is larger
+/// than [`C_MAX_QUERY_SIZE`].
+pub fn read_request(stream: &mut impl BufRead) -> Result<HttpRequest, HttpResponse> {
+    let malformed = || HttpResponse::error(400, "Malformed HTTP request.");
+    let mut line = String::new();
+    stream.read_line(&mut line).map_err(|_| malformed())?;
+    let (method, path) = match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        [method, target, _version] => (
+            method.to_string(),
+            target.split('?').next().unwrap_or_default().to_string(),
+        ),
+        _ => return Err(malformed()),
+    };
+
+    let mut length = 0;
+    let mut origin = None;
+    loop {
+        line.clear();
+        stream.read_line(&mut line).map_err(|_| malformed())?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().map_err(|_| malformed())?;
+            } else if name.eq_ignore_ascii_case("Origin") {
+                origin = Some(value.trim().to_string());
+            }
+        }
+    }
+    if length > C_MAX_QUERY_SIZE {
+        return Err(HttpResponse::error(
+            413,
+            &format!("The query is larger than {} bytes.", C_MAX_QUERY_SIZE),
+        ));
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).map_err(|_| malformed())?;
+    Ok(HttpRequest {
+        method,
+        path,
+        body: String::from_utf8(body).map_err(|_| malformed())?,
+        origin,
+    })
+}
+
+/// Helper function to tell whether `path` is the file of a built-in dataset, e.g.
+/// `data/city.csv`.
+fn is_builtin_path(path: &str) -> bool {
+    Dataset::all()
+        .iter()
+        .any(|dataset| dataset.path() == Some(path))
+}
+
+/// Helper function to tell whether a client of the server may read the `dataset`: only the
+/// built-in datasets, possibly registered under another name with LOAD, the sub-queries of a
+/// WITH, and the results saved with SET, not any other file or URL on the machine of the server.
+fn is_served(dataset: &Dataset) -> bool {
+    dataset.is_builtin()
+        || matches!(dataset, Dataset::Cte(_) | Dataset::Variable(_))
+        || matches!(dataset, Dataset::Registered { path, .. } if is_builtin_path(path))
+}
+
+/// Helper function to find an operator of the `operator` chain, or of its sub-queries, that a
+/// client of the server isn't allowed to run. See [`refused_command`].
+fn refused_operator(operator: &Operator) -> Option<String> {
+    match operator {
+        Operator::From(dataset) if !is_served(dataset) => Some(format!(
+            "{} can't be read by the server, which only reads the built-in datasets, WITH sub-queries, and results saved with SET.",
+            dataset
+        )),
+        Operator::From(_) => None,
+        Operator::Into { .. } => {
+            Some("INTO and EXPORT aren't allowed by the server, as they write files.".to_string())
+        }
+        Operator::Map { .. } => Some(
+            "MAP isn't allowed by the server, as it runs a script read from a file.".to_string(),
+        ),
+        Operator::With { ctes, query } => ctes
+            .iter()
+            .find_map(|(_, cte)| refused_operator(cte))
+            .or_else(|| refused_operator(query)),
+        Operator::Join { chain, right, .. } | Operator::Union { chain, right, .. } => {
+            refused_operator(chain).or_else(|| refused_operator(right))
+        }
+        _ => operator.chain().and_then(refused_operator),
+    }
+}
+
+/// Tells why the server refuses to run the `command` sent by a client, with a `POST /query`
+/// request or in a `--listen` session, if it does. The server may be reached by anyone on the
+/// network, so its clients can't write files, run scripts, read or register files or URLs other
+/// than the built-in datasets, or edit the datasets shared by all its clients.
+///
+/// # Returns
+/// The message explaining why the `command` is refused, or `None` if it may run.
+pub fn refused_command(command: &Command) -> Option<String> {
+    match command {
+        Command::Operator(operator)
+        | Command::Set { operator, .. }
+        | Command::ExplainAnalyze(operator)
+        | Command::Watch(operator) => refused_operator(operator),
+        Command::Load { path, .. } if !is_builtin_path(path) => Some(format!(
+            "{} can't be loaded by the server, which only reads the built-in datasets.",
+            path
+        )),
+        Command::Attach(path) => Some(format!(
+            "{} can't be attached by the server, which only reads the built-in datasets.",
+            path
+        )),
+        Command::Describe(dataset) | Command::ShowColumns(dataset) if !is_served(dataset) => {
+            refused_operator(&Operator::From(dataset.clone()))
+        }
+        Command::Save { .. } => {
+            Some("SAVE isn't allowed by the server, as it writes files.".to_string())
+        }
+        Command::Insert { .. } | Command::Delete { .. } => Some(
+            "INSERT and DELETE aren't allowed by the server, as they edit the datasets shared by all its clients."
+                .to_string(),
+        ),
+        _ => None,
+    }
+}
+
+/// Runs the query sent with a `POST /query` request on the `engine`.
+///
+/// # Arguments
+/// `request` : The request to handle.
+/// `engine` : The engine the queries run on. Datasets loaded and results saved with `SET` by one
+/// request are visible to the later ones.
+/// `allowed_origins` : The origins of the web pages allowed to send queries, as given with
+/// `--allow-origin`. Requests sent by a web page from any other origin are refused, so that a
+/// page the user happens to visit can't query the server. Requests without an `Origin` header,
+/// e.g. from `curl`, are always answered.
+///
+/// # Returns
+/// A 200 response whose body is `{"columns": [<name>, ...], "rows": [[<value>, ...], ...]}` if the
+/// query succeeded, with missing values as `null`. Otherwise a response whose body is
+/// `{"error": <message>}`: 400 if the query is malformed or failed, 403 if the server refuses to
+/// run it (see [`refused_command`]) or its origin isn't allowed, 404 for another path, and 405
+/// for another method. The response to an allowed origin may be read by its web page.
+pub fn handle_request(
+    request: &HttpRequest,
+    engine: &mut QueryEngine,
+    allowed_origins: &[String],
+) -> HttpResponse {
+    match &request.origin {
+        Some(origin) if allowed_origins.contains(origin) => HttpResponse {
+            allow_origin: Some(origin.clone()),
+            ..answer_request(request, engine)
+        },
+        Some(origin) => HttpResponse::error(
+            403,
+            &format!(
+                "Requests from {} aren't allowed. Start the server with --allow-origin {} to allow them.",
+                origin, origin
+            ),
+        ),
+        None => answer_request(request, engine),
+    }
+}
+
+/// Helper function to answer a `request` whose origin is allowed. See [`handle_request`].
+fn answer_request(request: &HttpRequest, engine: &mut QueryEngine) -> HttpResponse {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/query") => {
+            // Parsed as QueryEngine::execute does, so the command can be checked before it runs.
+            let command = parse_command(&format!("{}\n", request.body.trim_end_matches('\n')));
+            if let Some(reason) = refused_command(&command) {
+                return HttpResponse::error(403, &reason);
+            }
+            match engine.execute_command(command, &request.body) {
+                Ok(QueryResult { table, .. }) => HttpResponse {
+                    status: 200,
+                    body: serde_json::json!({ "columns": table.header, "rows": table.rows })
+                        .to_string(),
+                    allow_origin: None,
+                },
+                Err(error) => HttpResponse::error(400, &error.to_string()),
+            }
+        }
+        (_, "/query") => HttpResponse::error(405, "Send the query with POST /query."),
+        (_, path) => HttpResponse::error(404, &format!("Not found: {}", path)),
+    }
+}
+
+/// Helper function to answer a single connection: read its request, run it, and write the
+/// response. A client that doesn't send its request, or doesn't take the response, within
+/// [`C_REQUEST_TIMEOUT`] is hung up on.
+fn handle_connection(
+    stream: TcpStream,
+    engine: &Mutex<QueryEngine>,
+    allowed_origins: &[String],
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(C_REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(C_REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    // The engine is only locked once the whole request is read, so that a slow client doesn't
+    // hold up the others. A query that panicked leaves the engine usable.
+    let response = match read_request(&mut reader) {
+        Ok(request) => handle_request(
+            &request,
+            &mut engine.lock().unwrap_or_else(PoisonError::into_inner),
+            allowed_origins,
+        ),
+        Err(response) => response,
+    };
+    response.write_to(&mut &stream)
+}
+
+/// Answers the requests to the `listener` until the process is stopped. Each connection is
+/// handled on its own thread, as in [`serve_sessions`], and the queries run one at a time on the
+/// shared `engine`. See [`handle_request`] for the requests it answers.
+///
+/// # Arguments
+/// `listener` : The socket the requests arrive on.
+/// `engine` : The engine the queries run on.
+/// `allowed_origins` : The origins of the web pages allowed to send queries.
+pub fn serve(listener: TcpListener, engine: QueryEngine, allowed_origins: Vec<String>) {
+    let engine = Arc::new(Mutex::new(engine));
+    let allowed_origins = Arc::new(allowed_origins);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let engine = engine.clone();
+        let allowed_origins = allowed_origins.clone();
+        std::thread::spawn(move || {
+            // A client that hung up doesn't stop the server.
+            let _ = handle_connection(stream, &engine, &allowed_origins);
+        });
+    }
+}
+
+/// The state of a client connected to a `--listen` server, kept until it disconnects. Every client
+/// has its own, so the results saved with `SET`, the datasets registered with `LOAD` and `ATTACH`,
+/// and the settings of one client aren't seen by the others.
+#[derive(Debug)]
+pub struct ClientSession {
+    /// The engine the client's queries run on.
+    engine: QueryEngine,
+    /// The datasets registered with `LOAD` and `ATTACH` commands.
+    datasets: DatasetRegistry,
+    /// The settings changed with `\<setting>` commands.
+    settings: Settings,
+}
+
+impl ClientSession {
+    /// Creates a session whose queries run on the `engine`.
+    pub fn new(engine: QueryEngine) -> Self {
+        ClientSession {
+            engine,
+            datasets: DatasetRegistry::default(),
+            settings: Settings::default(),
+        }
+    }
+
+    /// Returns the prompt to send before each line the client enters, as set with `\prompt`.
+    pub fn prompt(&self) -> &str {
+        &self.settings.prompt
+    }
+
+    /// Runs a line of `input` entered by the client, like the REPL does.
+    ///
+    /// # Arguments
+    /// `input` : The line entered by the client, ending with a newline.
+    ///
+    /// # Returns
+    /// The text to send back to the client, i.e., the results or the error message, or `None` if
+    /// the client entered `exit`. `\timing`, `\pagesize`, `\cache`, `\badrows`, and `\normalize`
+    /// only apply to the REPL in a terminal, and are refused, as are the commands the server
+    /// doesn't run for its clients (see [`refused_command`]).
+    pub fn process_input(&mut self, input: &str) -> Option<String> {
+        let command = match self.settings.syntax {
+            Syntax::Pipe => parse_command_with(input, &self.datasets),
+            Syntax::Sql => parse_sql_command(input, &self.datasets),
+        };
+        if let Some(reason) = refused_command(&command) {
+            return Some(format!("{}\n", reason));
+        }
+        let output = match command {
+            Command::Exit => return None,
+            Command::NoInput => String::new(),
+            Command::Help(None) => format!("{}\n", help_message(&self.datasets)),
+            Command::Help(Some(name)) => find_operator_descriptor(&name)
+                .map(|operator| format!("{}\n", operator_help(operator)))
+                .unwrap_or_default(),
+            Command::Explain(operator) => explain_operator(&optimize(&operator)),
+            Command::Load {
+                name,
+                path,
+                encoding,
+            } => match self.datasets.register_with_encoding(&name, &path, encoding) {
+                Ok(_) => format!("Registered {} for {}.\n", name, path),
+                Err(error) => format!("{}\n", error),
+            },
+            Command::Attach(path) => match self.datasets.attach(&path) {
+                Ok(tables) if tables.is_empty() => format!("{} has no tables to attach.\n", path),
+                Ok(tables) => format!(
+                    "Attached {}: {}.\n",
+                    path,
+                    tables
+                        .iter()
+                        .map(|table| table.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+                Err(error) => format!("{}\n", error),
+            },
+            Command::Setting(
+                Setting::Timing(_)
+                | Setting::PageSize(_)
+                | Setting::Cache(_)
+                | Setting::BadRows(_)
+                | Setting::Normalize(_),
+            ) => "This setting is only available in the REPL.\n".to_string(),
+            Command::Setting(setting) => {
+                let message = format!("{}\n", setting);
+                self.settings.apply(setting);
+                self.engine.set_limits(ResultLimits {
+                    max_rows: self.settings.max_rows,
+                    max_bytes: self.settings.max_bytes,
+                });
+                message
+            }
+            command => self.run_query(command, input),
+        };
+        Some(output)
+    }
+
+    /// Helper function to run a query `command` parsed from the `input` on the engine, and render
+    /// its result in the output format of the session.
+    fn run_query(&mut self, command: Command, input: &str) -> String {
+        let operator = match &command {
+            Command::Operator(operator) => Some(operator.clone()),
+            _ => None,
+        };
+        let result = match self.engine.execute_command(command, input.trim()) {
+            Ok(result) => result,
+            Err(error) => return format!("{}\n", error),
+        };
+        // As in the REPL, the warnings come before the results.
+        let warnings: String = result
+            .warnings
+            .iter()
+            .map(|warning| format!("Warning: {}\n", warning))
+            .collect();
+        // The results are shown as in the REPL, except for the results of other commands, e.g.
+        // SHOW TABLES, which are never cut short.
+        let output = match &operator {
+            Some(operator) => query_output(operator, result.table, self.settings.default_limit),
+            None => QueryOutput::Rows {
+                table: result.table,
+                limit_message: None,
+            },
+        };
+        match output {
+            QueryOutput::Written(message) => format!("{}{}\n", warnings, message),
+            QueryOutput::Rows {
+                table,
+                limit_message,
+            } => {
+                let (text, _) = render_output(&table, self.settings.output_format, false);
+                let limit_message = limit_message
+                    .map(|message| format!("{}\n", message))
+                    .unwrap_or_default();
+                format!("{}{}\n{}", warnings, text, limit_message)
+            }
+        }
+    }
+}
+
+/// Runs the REPL session of a client: greets it, then sends the prompt, reads a line, and sends
+/// back its results, until the client enters `exit` or disconnects.
+///
+/// # Arguments
+/// `reader` : The lines entered by the client.
+/// `writer` : Where the prompts and results are sent.
+/// `session` : The state of the session.
+pub fn run_session(
+    reader: &mut impl BufRead,
+    writer: &mut impl Write,
+    mut session: ClientSession,
+) -> std::io::Result<()> {
+    writeln!(writer, "Toy Query Engine v0.1")?;
+    writeln!(
+        writer,
+        "Enter your query, or 'help' for more information or 'exit' to exit."
+    )?;
+    let mut line = String::new();
+    loop {
+        write!(writer, "{}", session.prompt())?;
+        writer.flush()?;
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        // Clients such as telnet end their lines with \r\n.
+        let input = format!("{}\n", line.trim_end_matches(['\r', '\n']));
+        match session.process_input(&input) {
+            Some(output) => write!(writer, "{}", output)?,
+            None => {
+                writeln!(writer, "Goodbye!")?;
+                return writer.flush();
+            }
+        }
+    }
+}
+
+/// Accepts REPL sessions on the `listener`, e.g. from `nc localhost 9000`, until the process is
+/// stopped. Each client is served on its own thread, so a long query of one client doesn't hold
+/// up the others, with its own [`ClientSession`].
+///
+/// # Arguments
+/// `listener` : The socket the clients connect to.
+/// `engine` : Creates the engine of each new client, e.g. with the functions of the `--script`.
+pub fn serve_sessions(listener: TcpListener, engine: impl Fn() -> QueryEngine) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let session = ClientSession::new(engine());
+        std::thread::spawn(move || {
+            // A client that hung up doesn't stop the server.
+            let _ = stream
+                .try_clone()
+                .and_then(|reader| run_session(&mut BufReader::new(reader), &mut &stream, session));
+        });
+    }
+}
+
+#[test]
+fn test_read_request() {
+    let mut input =
+        "POST /query?pretty HTTP/1.1\r\nHost: localhost\r\ncontent-length: 17\r\n\r\nFROM city TAKE 1".as_bytes();
+    assert_eq!(
+        read_request(&mut input),
+        Err(HttpResponse::error(400, "Malformed HTTP request."))
+    );
+    let mut input =
+        "POST /query?pretty HTTP/1.1\r\nHost: localhost\r\ncontent-length: 16\r\n\r\nFROM city TAKE 1".as_bytes();
+    assert_eq!(
+        read_request(&mut input),
+        Ok(HttpRequest {
+            method: "POST".to_string(),
+            path: "/query".to_string(),
+            body: "FROM city TAKE 1".to_string(),
+            origin: None,
+        })
+    );
+    let mut input = "POST /query HTTP/1.1\r\nOrigin: http://localhost:3000\r\n\r\n".as_bytes();
+    assert_eq!(
+        read_request(&mut input).unwrap().origin.as_deref(),
+        Some("http://localhost:3000")
+    );
+    let mut input = "GET / HTTP/1.1\r\n\r\n".as_bytes();
+    assert_eq!(read_request(&mut input).unwrap().body, "");
+    let mut input = "POST /query HTTP/1.1\r\nContent-Length: 2000000\r\n\r\n".as_bytes();
+    assert_eq!(read_request(&mut input).unwrap_err().status, 413);
+    let mut input = "hello\r\n\r\n".as_bytes();
+    assert_eq!(read_request(&mut input).unwrap_err().status, 400);
+}
+
+#[test]
+fn test_handle_request() {
+    let mut engine = QueryEngine::new();
+    let request = |method: &str, path: &str, body: &str| HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        body: body.to_string(),
+        origin: None,
+    };
+    assert_eq!(
+        handle_request(
+            &request(
+                "POST",
+                "/query",
+                "FROM country FILTER Continent = Antarctica SELECT CountryName,Capital TAKE 1"
+            ),
+            &mut engine,
+            &[]
+        ),
+        HttpResponse {
+            status: 200,
+            body: r#"{"columns":["CountryName","Capital"],"rows":[["Antarctica",null]]}"#
+                .to_string(),
+            allow_origin: None,
+        }
+    );
+    assert_eq!(
+        handle_request(&request("POST", "/query", "FROM town"), &mut engine, &[]),
+        HttpResponse::error(400, "Malformed input. Invalid dataset passed to FROM: town")
+    );
+    assert_eq!(
+        handle_request(&request("GET", "/query", ""), &mut engine, &[]).status,
+        405
+    );
+    assert_eq!(
+        handle_request(&request("POST", "/", ""), &mut engine, &[]),
+        HttpResponse::error(404, "Not found: /")
+    );
+}
+
+#[test]
+fn test_handle_request_origin() {
+    let mut engine = QueryEngine::new();
+    let allowed = ["http://localhost:3000".to_string()];
+    let request = |origin: Option<&str>| HttpRequest {
+        method: "POST".to_string(),
+        path: "/query".to_string(),
+        body: "FROM city TAKE 1 SELECT CityName".to_string(),
+        origin: origin.map(str::to_string),
+    };
+    // Only the allowed web pages may query the server and read the response.
+    let response = handle_request(
+        &request(Some("http://localhost:3000")),
+        &mut engine,
+        &allowed,
+    );
+    assert_eq!(response.status, 200);
+    assert_eq!(
+        response.allow_origin.as_deref(),
+        Some("http://localhost:3000")
+    );
+    assert_eq!(
+        handle_request(&request(Some("https://evil.example.com")), &mut engine, &allowed),
+        HttpResponse::error(403, "Requests from https://evil.example.com aren't allowed. Start the server with --allow-origin https://evil.example.com to allow them.")
+    );
+    assert_eq!(
+        handle_request(&request(Some("http://localhost:3000")), &mut engine, &[]).status,
+        403
+    );
+    // Requests from outside a browser, e.g. curl, don't send an origin.
+    let response = handle_request(&request(None), &mut engine, &[]);
+    assert_eq!(response.status, 200);
+    assert_eq!(response.allow_origin, None);
+    let mut output = Vec::new();
+    response.write_to(&mut output).unwrap();
+    assert!(!String::from_utf8(output)
+        .unwrap()
+        .contains("Access-Control-Allow-Origin"));
+}
+
+#[test]
+fn test_handle_request_refused() {
+    let mut engine = QueryEngine::new();
+    let path = std::env::temp_dir().join("toy-query-engine-served.csv");
+    std::fs::write(&path, "CountryCode,Secret\nABW,1\n").unwrap();
+    let path = path.to_string_lossy();
+    let output = std::env::temp_dir().join("toy-query-engine-served-output.csv");
+    let output = output.to_string_lossy();
+    let script = std::env::temp_dir().join("toy-query-engine-served.rhai");
+    std::fs::write(&script, "row").unwrap();
+    let script = script.to_string_lossy();
+    let refused = |query: &str, engine: &mut QueryEngine| {
+        let response = handle_request(
+            &HttpRequest {
+                method: "POST".to_string(),
+                path: "/query".to_string(),
+                body: query.to_string(),
+                origin: None,
+            },
+            engine,
+            &[],
+        );
+        assert_eq!(response.status, 403, "{}: {}", query, response.body);
+        serde_json::from_str::<serde_json::Value>(&response.body).unwrap()["error"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let unserved = format!("{} can't be read by the server, which only reads the built-in datasets, WITH sub-queries, and results saved with SET.", path);
+    assert_eq!(refused(&format!("FROM {}", path), &mut engine), unserved);
+    assert_eq!(
+        refused(
+            &format!("FROM country JOIN {} CountryCode", path),
+            &mut engine
+        ),
+        unserved
+    );
+    assert_eq!(
+        refused(
+            &format!("FROM country JOIN (FROM {}) CountryCode", path),
+            &mut engine
+        ),
+        unserved
+    );
+    assert_eq!(
+        refused(
+            &format!(
+                "FROM country SELECT CountryCode UNION (FROM {} SELECT CountryCode)",
+                path
+            ),
+            &mut engine
+        ),
+        unserved
+    );
+    assert_eq!(
+        refused(&format!("WITH t AS (FROM {}) FROM t", path), &mut engine),
+        unserved
+    );
+    assert_eq!(
+        refused(&format!("SET t = FROM {}", path), &mut engine),
+        unserved
+    );
+    assert_eq!(
+        refused(&format!("DESCRIBE {}", path), &mut engine),
+        unserved
+    );
+    assert_eq!(
+        refused(&format!("SHOW COLUMNS {}", path), &mut engine),
+        unserved
+    );
+    assert_eq!(
+        refused("FROM https://example.com/cities.csv", &mut engine),
+        "https://example.com/cities.csv can't be read by the server, which only reads the built-in datasets, WITH sub-queries, and results saved with SET."
+    );
+    for query in [
+        format!("FROM city TAKE 1 INTO {}", output),
+        format!("FROM city TAKE 1 EXPORT ARROW {}", output),
+    ] {
+        assert_eq!(
+            refused(&query, &mut engine),
+            "INTO and EXPORT aren't allowed by the server, as they write files."
+        );
+    }
+    assert!(!std::path::Path::new(output.as_ref()).exists());
+    assert_eq!(
+        refused(&format!("FROM city MAP {}", script), &mut engine),
+        "MAP isn't allowed by the server, as it runs a script read from a file."
+    );
+    assert_eq!(
+        refused(&format!("SAVE city {}", output), &mut engine),
+        "SAVE isn't allowed by the server, as it writes files."
+    );
+    for query in [
+        "INSERT INTO language VALUES (ABW, Papiamento)",
+        "DELETE FROM language WHERE CountryCode = ABW",
+    ] {
+        assert_eq!(
+            refused(query, &mut engine),
+            "INSERT and DELETE aren't allowed by the server, as they edit the datasets shared by all its clients."
+        );
+    }
+    assert_eq!(
+        engine.execute("FROM language COUNT").unwrap().table.rows[0].cells[0],
+        crate::table::Cell::Int64(984)
+    );
+    std::fs::remove_file(path.as_ref()).unwrap();
+    std::fs::remove_file(script.as_ref()).unwrap();
+
+    // The built-in datasets, sub-queries, and saved results can still be read.
+    for query in [
+        "SET big = FROM city FILTER CityPop > 9000000",
+        "WITH t AS (FROM @big) FROM t JOIN country CountryCode COUNT",
+        "DESCRIBE city",
+    ] {
+        assert_eq!(
+            handle_request(
+                &HttpRequest {
+                    method: "POST".to_string(),
+                    path: "/query".to_string(),
+                    body: query.to_string(),
+                    origin: None,
+                },
+                &mut engine,
+                &[]
+            )
+            .status,
+            200,
+            "{}",
+            query
+        );
+    }
+}
+
+#[test]
+fn test_serve() {
+    use std::io::Read;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || serve(listener, QueryEngine::new(), vec![]));
+    // A client that connects without sending its request doesn't hold up the others.
+    let _idle = TcpStream::connect(address).unwrap();
+    let query = "FROM city ORDERBY CityPop TAKE 1 SELECT CityName";
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "POST /query HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+        query.len(),
+        query
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with(r#"{"columns":["CityName"],"rows":[["Mumbai_(Bombay)"]]}"#));
+}
+
+#[test]
+fn test_client_session() {
+    let mut session = ClientSession::new(QueryEngine::new());
+    assert_eq!(
+        session.process_input("SET big = FROM city ORDERBY CityPop SELECT CityName TAKE 1\n"),
+        Some("CityName\n---------------\nMumbai_(Bombay)\n\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("\\format csv\n"),
+        Some("Output format set to csv.\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("FROM @big\n"),
+        Some("CityName\nMumbai_(Bombay)\n\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("\\syntax sql\n"),
+        Some("Query syntax set to sql.\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("SELECT CountryName FROM country WHERE CountryCode = ABW;\n"),
+        Some("CountryName\nAruba\n\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("LOAD cities FROM data/city.csv\n"),
+        Some("Registered cities for data/city.csv.\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("FROM cities TAKE 1 SELECT CityName\n"),
+        Some("CityName\nKabul\n\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("\\timing on\n"),
+        Some("This setting is only available in the REPL.\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("FROM town\n"),
+        Some("Malformed input. Invalid dataset passed to FROM: town\n".to_string())
+    );
+    assert_eq!(session.process_input("\n"), Some(String::new()));
+    assert_eq!(session.process_input("exit\n"), None);
+}
+
+#[test]
+fn test_client_session_refused() {
+    let path = std::env::temp_dir().join("toy-query-engine-client-session-refused.csv");
+    let _ = std::fs::remove_file(&path);
+    let path = path.to_string_lossy().to_string();
+    let secret = std::env::temp_dir().join("toy-query-engine-client-session-secret.csv");
+    std::fs::write(&secret, "Password\nhunter2\n").unwrap();
+    let secret = secret.to_string_lossy().to_string();
+    let mut session = ClientSession::new(QueryEngine::new());
+    for (input, reason) in [
+        (
+            format!("FROM city TAKE 1 INTO {}\n", path),
+            "INTO and EXPORT aren't allowed by the server, as they write files.".to_string(),
+        ),
+        (
+            format!("EXPLAIN ANALYZE FROM city TAKE 1 INTO {}\n", path),
+            "INTO and EXPORT aren't allowed by the server, as they write files.".to_string(),
+        ),
+        (
+            format!("SAVE city {}\n", path),
+            "SAVE isn't allowed by the server, as it writes files.".to_string(),
+        ),
+        (
+            format!("FROM {}\n", secret),
+            format!("{} can't be read by the server, which only reads the built-in datasets, WITH sub-queries, and results saved with SET.", secret),
+        ),
+        (
+            "LOAD passwords FROM /etc/passwd\n".to_string(),
+            "/etc/passwd can't be loaded by the server, which only reads the built-in datasets."
+                .to_string(),
+        ),
+        (
+            "ATTACH my.db\n".to_string(),
+            "my.db can't be attached by the server, which only reads the built-in datasets."
+                .to_string(),
+        ),
+    ] {
+        assert_eq!(
+            session.process_input(&input),
+            Some(format!("{}\n", reason)),
+            "{}",
+            input
+        );
+    }
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn test_client_session_default_limit() {
+    let mut session = ClientSession::new(QueryEngine::new());
+    session.process_input("\\format csv\n");
+    session.process_input("\\limit 2\n");
+    assert_eq!(
+        session.process_input("FROM country SELECT CountryCode\n"),
+        Some("CountryCode\nABW\nAFG\n\nShowing the first 2 of 239 rows. Add TAKE <number> to the query, or use '\\limit <number>' or '\\limit off' to change this limit.\n".to_string())
+    );
+    assert_eq!(
+        session.process_input("FROM country SELECT CountryCode TAKE 3\n"),
+        Some("CountryCode\nABW\nAFG\nAGO\n\n".to_string())
+    );
+    // The warnings about the query come before its results, as in the REPL.
+    assert_eq!(
+        session.process_input("FROM country JOIN city CountryCode COUNT\n"),
+        Some("Warning: CountryCode is not a key column of city.csv; each row may match several rows and multiply the size of the result.\nCOUNT(*)\n4079\n\n".to_string())
+    );
+}
+
+#[test]
+fn test_run_session() {
+    let mut input =
+        "FROM country TAKE 1 SELECT CountryName\r\n\\prompt sql> \nexit\nFROM city\n".as_bytes();
+    let mut output = Vec::new();
+    run_session(
+        &mut input,
+        &mut output,
+        ClientSession::new(QueryEngine::new()),
+    )
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "Toy Query Engine v0.1\n\
+         Enter your query, or 'help' for more information or 'exit' to exit.\n\
+         query> CountryName\n-----------\nAruba\n\n\
+         query> Prompt set to 'sql> '.\n\
+         sql> Goodbye!\n"
+    );
+}
+
+#[test]
+fn test_serve_sessions() {
+    use std::io::Read;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || serve_sessions(listener, QueryEngine::new));
+    let first = TcpStream::connect(address).unwrap();
+    let second = TcpStream::connect(address).unwrap();
+    let mut first_lines = BufReader::new(first.try_clone().unwrap());
+    let mut line = String::new();
+    // The first client saves a result, which the second one doesn't see.
+    write!(
+        &first,
+        "\\format csv\nSET n = FROM country TAKE 1 SELECT CountryCode\n"
+    )
+    .unwrap();
+    for _ in 0..4 {
+        line.clear();
+        first_lines.read_line(&mut line).unwrap();
+    }
+    assert_eq!(line, "query> CountryCode\n");
+    write!(&second, "FROM @n\nexit\n").unwrap();
+    let mut response = String::new();
+    (&second).read_to_string(&mut response).unwrap();
+    assert!(
+        response.contains("query> No result is saved as @n. Use SET n = <query> to save one.\n"),
+        "{}",
+        response
+    );
+    write!(&first, "FROM @n\nexit\n").unwrap();
+    let mut response = String::new();
+    first_lines.read_to_string(&mut response).unwrap();
+    assert_eq!(
+        response,
+        "ABW\n\nquery> CountryCode\nABW\n\nquery> Goodbye!\n"
+    );
+}
+
+#[test]
+fn test_serve_sessions_refused() {
+    use std::io::Read;
+    let path = std::env::temp_dir().join("toy-query-engine-serve-sessions-refused.csv");
+    let _ = std::fs::remove_file(&path);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || serve_sessions(listener, QueryEngine::new));
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "FROM city TAKE 1 INTO {}\nexit\n", path.display()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(
+        response.contains(
+            "query> INTO and EXPORT aren't allowed by the server, as they write files.\n"
+        ),
+        "{}",
+        response
+    );
+    assert!(!path.exists());
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+/// A result saved with `SET`, kept as the query that produces it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedResult {
+    /// The name the result is saved as, without the leading `@`.
+    pub name: String,
+    /// The query producing the result, as a chain of operators, e.g. "FROM city.csv TAKE 3".
+    pub query: String,
+}
+
+/// The state of a REPL session, written to a TOML file by `\save <path>` and read back by
+/// `\load <path>`, so that an analysis can be resumed later.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedSession {
+    /// The paths of the SQLite databases attached with `ATTACH`.
+    #[serde(default)]
+    pub attached: Vec<String>,
+    /// The value of each setting, as passed to its `\<setting>` command, by name. See
+    /// [`crate::settings::Settings::arguments`].
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+    /// The path of each dataset registered with `LOAD`, by name.
+    #[serde(default)]
+    pub datasets: BTreeMap<String, String>,
+    /// The encoding given with `LOAD ... ENCODING` to the datasets registered with one, by name,
+    /// e.g. `windows-1252`.
+    #[serde(default)]
+    pub encodings: BTreeMap<String, String>,
+    /// The results saved with `SET`, in the order they were saved, so that a result can be
+    /// computed from the ones before it. Only the queries are kept, and they are run again when
+    /// the session is loaded. A name saved again is listed again if a result in between was
+    /// computed from its earlier value.
+    #[serde(default)]
+    pub results: Vec<SavedResult>,
+}
+
+impl SavedSession {
+    /// Writes the session to `path` as TOML, replacing the file if it already exists.
+    ///
+    /// Returns
+    /// An error propagated from serializing the session or writing the file.
+    pub fn write(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Reads a session written by [`SavedSession::write`] from `path`. Sections missing from the
+    /// file are empty.
+    ///
+    /// Returns
+    /// The session, or an error propagated from reading the file or parsing it.
+    pub fn read(path: &str) -> Result<SavedSession, Box<dyn Error>> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+#[test]
+fn test_saved_session() {
+    let session = SavedSession {
+        attached: vec!["my.db".to_string()],
+        settings: BTreeMap::from([
+            ("format".to_string(), "csv".to_string()),
+            ("prompt".to_string(), "'sql> '".to_string()),
+        ]),
+        datasets: BTreeMap::from([("planets".to_string(), "data/planets.csv".to_string())]),
+        encodings: BTreeMap::from([("planets".to_string(), "windows-1252".to_string())]),
+        results: vec![SavedResult {
+            name: "big".to_string(),
+            query: "FROM city.csv FILTER CityPop > 9000000".to_string(),
+        }],
+    };
+    let path = std::env::temp_dir().join("toy-query-engine-saved-session.toml");
+    let path = path.to_string_lossy().to_string();
+    session.write(&path).unwrap();
+    assert_eq!(SavedSession::read(&path).unwrap(), session);
+
+    // Sections left out are empty.
+    std::fs::write(&path, "[settings]\nlimit = \"off\"\n").unwrap();
+    assert_eq!(
+        SavedSession::read(&path).unwrap(),
+        SavedSession {
+            settings: BTreeMap::from([("limit".to_string(), "off".to_string())]),
+            ..SavedSession::default()
+        }
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(SavedSession::read(&path).is_err());
+}
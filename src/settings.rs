@@ -0,0 +1,786 @@
+use std::fmt::Display;
+
+use crate::data::{BadRows, Normalization};
+use crate::lexer::{quote, unquote};
+use crate::table::format_bytes;
+
+/// The number of rows printed for a query that doesn't specify an explicit TAKE, unless the user
+/// changes it with the `\limit` command.
+pub const DEFAULT_ROW_LIMIT: usize = 1000;
+
+/// The prompt printed before each line of input, unless the user changes it with the `\prompt`
+/// command.
+pub const DEFAULT_PROMPT: &str = "query> ";
+
+/// User-configurable settings that control how queries are run and how their results are
+/// displayed. These live for the duration of the REPL session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// The maximum number of rows printed for a query that doesn't contain an explicit TAKE.
+    /// Guards against accidentally dumping tens of thousands of rows into the terminal.
+    /// `None` disables the safeguard.
+    pub default_limit: Option<usize>,
+    /// How query results are printed.
+    pub output_format: OutputFormat,
+    /// Whether the number of rows and the time taken are printed after each query.
+    pub timing: bool,
+    /// The number of rows printed at a time, waiting for the user to press Enter before printing
+    /// more. `None` prints all the rows at once.
+    pub page_size: Option<usize>,
+    /// How the rows of CSV files with too few or too many values are handled.
+    pub bad_rows: BadRows,
+    /// The prompt printed before each line of input in the terminal.
+    pub prompt: String,
+    /// Whether running a query again returns its previous result, as long as the datasets it
+    /// reads are unchanged.
+    pub cache: bool,
+    /// The maximum number of rows of a table held in memory while a query runs, e.g. the result
+    /// of a JOIN. `None` disables the limit.
+    pub max_rows: Option<usize>,
+    /// The maximum estimated number of bytes of a table held in memory while a query runs.
+    /// `None` disables the limit.
+    pub max_bytes: Option<usize>,
+    /// The language queries are written in.
+    pub syntax: Syntax,
+    /// How the values of CSV files are cleaned up while loading them.
+    pub normalization: Normalization,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            default_limit: Some(DEFAULT_ROW_LIMIT),
+            output_format: OutputFormat::default(),
+            timing: false,
+            page_size: None,
+            bad_rows: BadRows::default(),
+            prompt: DEFAULT_PROMPT.to_string(),
+            cache: true,
+            max_rows: None,
+            max_bytes: None,
+            syntax: Syntax::default(),
+            normalization: Normalization::default(),
+        }
+    }
+}
+
+/// The ways query results can be printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    /// A table with aligned columns and a separator line under the header.
+    #[default]
+    Table,
+    /// Comma-separated values, with the header on the first line.
+    Csv,
+    /// One JSON object per row, mapping the column names to the values.
+    Jsonl,
+    /// A GitHub-flavored Markdown table, e.g. to paste into an issue or a document.
+    Markdown,
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Table => f.write_str("table"),
+            OutputFormat::Csv => f.write_str("csv"),
+            OutputFormat::Jsonl => f.write_str("jsonl"),
+            OutputFormat::Markdown => f.write_str("markdown"),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Parses the `name` of an output format, as used by `\format` and `--format`.
+    ///
+    /// # Returns
+    /// The [`OutputFormat`], or an error message listing the valid names.
+    pub fn parse(name: &str) -> Result<OutputFormat, String> {
+        match name {
+            "table" => Ok(OutputFormat::Table),
+            "csv" => Ok(OutputFormat::Csv),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            "markdown" => Ok(OutputFormat::Markdown),
+            _ => Err(format!(
+                "Unknown output format: {}. Must be 'table', 'csv', 'jsonl', or 'markdown'.",
+                name
+            )),
+        }
+    }
+}
+
+/// The languages queries can be written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Syntax {
+    /// A chain of operators starting with FROM, e.g. `FROM city ORDERBY CityPop TAKE 7`.
+    #[default]
+    Pipe,
+    /// Standard SQL, e.g. `SELECT CityName FROM city ORDER BY CityPop DESC LIMIT 7`, which is
+    /// translated into the same chain of operators. See [`crate::sql`].
+    Sql,
+}
+
+impl Display for Syntax {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Syntax::Pipe => f.write_str("pipe"),
+            Syntax::Sql => f.write_str("sql"),
+        }
+    }
+}
+
+impl Settings {
+    /// Updates the settings with the value carried by `setting`.
+    pub fn apply(&mut self, setting: Setting) {
+        match setting {
+            Setting::DefaultLimit(limit) => self.default_limit = limit,
+            Setting::OutputFormat(format) => self.output_format = format,
+            Setting::Timing(timing) => self.timing = timing,
+            Setting::PageSize(page_size) => self.page_size = page_size,
+            Setting::BadRows(bad_rows) => self.bad_rows = bad_rows,
+            Setting::Normalize(normalization) => self.normalization = normalization,
+            Setting::Prompt(prompt) => self.prompt = prompt,
+            Setting::Cache(cache) => self.cache = cache,
+            Setting::MaxRows(max_rows) => self.max_rows = max_rows,
+            Setting::MaxBytes(max_bytes) => self.max_bytes = max_bytes,
+            Setting::Syntax(syntax) => self.syntax = syntax,
+        }
+    }
+
+    /// Returns the value of each setting as it is passed to its `\<setting>` command, by name and
+    /// in the order of [`SETTINGS`], e.g. `("limit", "1000")`, so that the settings can be saved
+    /// and applied again with [`parse_setting`].
+    pub fn arguments(&self) -> Vec<(&'static str, String)> {
+        let on_off = |value: bool| match value {
+            true => "on".to_string(),
+            false => "off".to_string(),
+        };
+        let number =
+            |value: Option<usize>| value.map_or("off".to_string(), |value| value.to_string());
+        vec![
+            ("limit", number(self.default_limit)),
+            ("format", self.output_format.to_string()),
+            ("timing", on_off(self.timing)),
+            ("pagesize", number(self.page_size)),
+            ("badrows", self.bad_rows.to_string()),
+            ("normalize", self.normalization.to_string()),
+            ("prompt", quote(&self.prompt)),
+            ("cache", on_off(self.cache)),
+            ("maxrows", number(self.max_rows)),
+            ("maxbytes", number(self.max_bytes)),
+            ("syntax", self.syntax.to_string()),
+        ]
+    }
+
+    /// Returns the prompt printed while the rest of a query continued on the next line is
+    /// entered: `...> `, right-aligned under the [`Settings::prompt`] so that the lines of the
+    /// query line up, e.g. `  ...> ` under `query> `.
+    pub fn continuation_prompt(&self) -> String {
+        format!("{:>1$}", "...> ", self.prompt.chars().count())
+    }
+}
+
+/// A single change to the [`Settings`], parsed from a `\<setting> <value>` command.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Setting {
+    /// `\limit <number>` or `\limit off`.
+    DefaultLimit(Option<usize>),
+    /// `\format table`, `\format csv`, `\format jsonl`, or `\format markdown`.
+    OutputFormat(OutputFormat),
+    /// `\timing on` or `\timing off`.
+    Timing(bool),
+    /// `\pagesize <number>` or `\pagesize off`.
+    PageSize(Option<usize>),
+    /// `\badrows error`, `\badrows skip`, or `\badrows null`.
+    BadRows(BadRows),
+    /// Clean up the values of CSV files as they are loaded.
+    Normalize(Normalization),
+    /// `\prompt <text>`, e.g. `\prompt 'sql> '`.
+    Prompt(String),
+    /// `\cache on` or `\cache off`.
+    Cache(bool),
+    /// `\maxrows <number>` or `\maxrows off`.
+    MaxRows(Option<usize>),
+    /// `\maxbytes <size>` or `\maxbytes off`, e.g. `\maxbytes 500MB`.
+    MaxBytes(Option<usize>),
+    /// `\syntax pipe` or `\syntax sql`.
+    Syntax(Syntax),
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Setting::DefaultLimit(Some(limit)) => {
+                f.write_fmt(format_args!("Default row limit set to {}.", limit))
+            }
+            Setting::DefaultLimit(None) => f.write_str("Default row limit disabled."),
+            Setting::OutputFormat(format) => {
+                f.write_fmt(format_args!("Output format set to {}.", format))
+            }
+            Setting::Timing(true) => f.write_str("Timing is on."),
+            Setting::Timing(false) => f.write_str("Timing is off."),
+            Setting::PageSize(Some(page_size)) => {
+                f.write_fmt(format_args!("Page size set to {} rows.", page_size))
+            }
+            Setting::PageSize(None) => f.write_str("Paging disabled."),
+            Setting::BadRows(BadRows::Error) => f.write_str("Bad rows fail the query."),
+            Setting::BadRows(BadRows::Skip) => f.write_str("Bad rows are skipped."),
+            Setting::BadRows(BadRows::Null) => {
+                f.write_str("Bad rows are filled with missing values.")
+            }
+            Setting::Normalize(normalization) if *normalization == Normalization::default() => {
+                f.write_str("Values are loaded as they are.")
+            }
+            Setting::Normalize(normalization) => f.write_fmt(format_args!(
+                "Values are normalized while loading: {}.",
+                normalization
+            )),
+            Setting::Prompt(prompt) => {
+                f.write_fmt(format_args!("Prompt set to {}.", quote(prompt)))
+            }
+            Setting::Cache(true) => f.write_str("Result cache is on."),
+            Setting::Cache(false) => f.write_str("Result cache is off."),
+            Setting::MaxRows(Some(max_rows)) => {
+                f.write_fmt(format_args!("Results are limited to {} rows.", max_rows))
+            }
+            Setting::MaxRows(None) => f.write_str("Results are not limited in rows."),
+            Setting::MaxBytes(Some(max_bytes)) => f.write_fmt(format_args!(
+                "Results are limited to {} of memory.",
+                format_bytes(*max_bytes)
+            )),
+            Setting::MaxBytes(None) => f.write_str("Results are not limited in memory."),
+            Setting::Syntax(syntax) => f.write_fmt(format_args!("Query syntax set to {}.", syntax)),
+        }
+    }
+}
+
+/// Describes a [`Setting`] for the `help` command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingDescriptor {
+    /// The name of the setting, without the leading backslash, e.g. "limit".
+    pub name: &'static str,
+    /// The values the setting accepts, e.g. `<number>|off`.
+    pub arguments: &'static str,
+    /// A one line description of the setting.
+    pub summary: &'static str,
+}
+
+/// Descriptors for every [`Setting`], in the order they are listed by `help`.
+pub const SETTINGS: &[SettingDescriptor] = &[
+    SettingDescriptor {
+        name: "limit",
+        arguments: "<number>|off",
+        summary: "The maximum number of rows printed for a query that doesn't end with a TAKE. Defaults to 1000.",
+    },
+    SettingDescriptor {
+        name: "format",
+        arguments: "table|csv|jsonl|markdown",
+        summary: "How query results are printed: a table with aligned columns, CSV, one JSON object per row, or a Markdown table. Defaults to table.",
+    },
+    SettingDescriptor {
+        name: "timing",
+        arguments: "on|off",
+        summary: "Whether the number of rows and the time taken are printed after each query. Defaults to off.",
+    },
+    SettingDescriptor {
+        name: "pagesize",
+        arguments: "<number>|off",
+        summary: "The number of rows shown at a time in the terminal, waiting for Enter before showing more. Defaults to off.",
+    },
+    SettingDescriptor {
+        name: "badrows",
+        arguments: "error|skip|null",
+        summary: "Whether CSV rows with too few or too many values fail the query, are skipped, or are filled with missing values. Defaults to error.",
+    },
+    SettingDescriptor {
+        name: "normalize",
+        arguments: "trim|underscores|nfc[,...]|off",
+        summary: "How CSV values are cleaned up while loading, so values written differently compare equal: trimming the whitespace around them, replacing underscores with spaces, and composing Unicode characters (NFC). Defaults to off.",
+    },
+    SettingDescriptor {
+        name: "prompt",
+        arguments: "<text>",
+        summary: "The prompt printed before each line of input in the terminal, quoted to control its spacing. Defaults to 'query> '.",
+    },
+    SettingDescriptor {
+        name: "cache",
+        arguments: "on|off",
+        summary: "Whether running a query again returns its previous result while the datasets it reads are unchanged. Defaults to on.",
+    },
+    SettingDescriptor {
+        name: "maxrows",
+        arguments: "<number>|off",
+        summary: "The maximum number of rows of a table held in memory while a query runs, e.g. the result of a JOIN. Larger ones stop the query. Defaults to off.",
+    },
+    SettingDescriptor {
+        name: "maxbytes",
+        arguments: "<number>[KB|MB|GB]|off",
+        summary: "The maximum estimated memory used by a table held in memory while a query runs. Larger ones stop the query. Defaults to off.",
+    },
+    SettingDescriptor {
+        name: "syntax",
+        arguments: "pipe|sql",
+        summary: "Whether queries are chains of operators, e.g. FROM city ORDERBY CityPop, or standard SQL, e.g. SELECT * FROM city ORDER BY CityPop DESC. Defaults to pipe.",
+    },
+];
+
+/// Parses the arguments of a `\<setting>` command into a [`Setting`].
+///
+/// # Arguments
+/// `name` : The name of the setting, without the leading backslash.
+/// `args` : The whitespace separated arguments that followed the name.
+///
+/// # Returns
+/// The parsed [`Setting`], or an error message describing why the input is invalid.
+pub fn parse_setting(name: &str, args: &[&str]) -> Result<Setting, String> {
+    match name {
+        "limit" => match args {
+            ["off"] => Ok(Setting::DefaultLimit(None)),
+            [limit] => match str::parse::<usize>(limit) {
+                Ok(0) => Err("Invalid value passed to \\limit: 0. Must be a positive integer or 'off'.".to_string()),
+                Ok(limit) => Ok(Setting::DefaultLimit(Some(limit))),
+                Err(e) => Err(format!(
+                    "Invalid value passed to \\limit: {}. Must be a positive integer or 'off'.\n Full error message: {}",
+                    limit, e
+                )),
+            },
+            _ => Err("\\limit must be followed by the number of rows or 'off'.".to_string()),
+        },
+        "format" => match args {
+            [format] => OutputFormat::parse(format).map(Setting::OutputFormat),
+            _ => Err(
+                "\\format must be followed by 'table', 'csv', 'jsonl', or 'markdown'.".to_string(),
+            ),
+        },
+        "timing" => match args {
+            ["on"] => Ok(Setting::Timing(true)),
+            ["off"] => Ok(Setting::Timing(false)),
+            _ => Err("\\timing must be followed by 'on' or 'off'.".to_string()),
+        },
+        "pagesize" => match args {
+            ["off"] => Ok(Setting::PageSize(None)),
+            [page_size] => match str::parse::<usize>(page_size) {
+                Ok(0) => Err("Invalid value passed to \\pagesize: 0. Must be a positive integer or 'off'.".to_string()),
+                Ok(page_size) => Ok(Setting::PageSize(Some(page_size))),
+                Err(e) => Err(format!(
+                    "Invalid value passed to \\pagesize: {}. Must be a positive integer or 'off'.\n Full error message: {}",
+                    page_size, e
+                )),
+            },
+            _ => Err("\\pagesize must be followed by the number of rows or 'off'.".to_string()),
+        },
+        "badrows" => match args {
+            [name] if BadRows::from_name(name).is_some() => {
+                Ok(Setting::BadRows(BadRows::from_name(name).unwrap()))
+            }
+            _ => Err("\\badrows must be followed by 'error', 'skip', or 'null'.".to_string()),
+        },
+        "normalize" => match Normalization::from_names(args) {
+            Some(normalization) => Ok(Setting::Normalize(normalization)),
+            None => Err(
+                "\\normalize must be followed by 'trim', 'underscores', or 'nfc', separated by commas, or 'off'."
+                    .to_string(),
+            ),
+        },
+        "prompt" => match args {
+            [] => Err("\\prompt must be followed by the text of the prompt, e.g. 'sql> '.".to_string()),
+            args => {
+                // An unquoted prompt is separated from the input by a space.
+                let text = args.join(" ");
+                Ok(Setting::Prompt(unquote(&text).unwrap_or(format!("{} ", text))))
+            }
+        },
+        "cache" => match args {
+            ["on"] => Ok(Setting::Cache(true)),
+            ["off"] => Ok(Setting::Cache(false)),
+            _ => Err("\\cache must be followed by 'on' or 'off'.".to_string()),
+        },
+        "maxrows" => match args {
+            ["off"] => Ok(Setting::MaxRows(None)),
+            [max_rows] => match str::parse::<usize>(max_rows) {
+                Ok(max_rows) => Ok(Setting::MaxRows(Some(max_rows))),
+                Err(e) => Err(format!(
+                    "Invalid value passed to \\maxrows: {}. Must be a positive integer or 'off'.\n Full error message: {}",
+                    max_rows, e
+                )),
+            },
+            _ => Err("\\maxrows must be followed by the number of rows or 'off'.".to_string()),
+        },
+        "maxbytes" => match args {
+            ["off"] => Ok(Setting::MaxBytes(None)),
+            [max_bytes] => match parse_size(max_bytes) {
+                Some(max_bytes) => Ok(Setting::MaxBytes(Some(max_bytes))),
+                None => Err(format!(
+                    "Invalid value passed to \\maxbytes: {}. Must be a number of bytes, optionally followed by KB, MB, or GB, or 'off'.",
+                    max_bytes
+                )),
+            },
+            _ => Err("\\maxbytes must be followed by a size, e.g. 500MB, or 'off'.".to_string()),
+        },
+        "syntax" => match args {
+            ["pipe"] => Ok(Setting::Syntax(Syntax::Pipe)),
+            ["sql"] => Ok(Setting::Syntax(Syntax::Sql)),
+            _ => Err("\\syntax must be followed by 'pipe' or 'sql'.".to_string()),
+        },
+        _ => Err(format!("Unknown setting: \\{}", name)),
+    }
+}
+
+/// Helper function to parse a size in bytes, e.g. `1024`, or in kilobytes, megabytes or gigabytes,
+/// e.g. `500MB`, where a KB is 1024 bytes. The unit is case-insensitive.
+fn parse_size(text: &str) -> Option<usize> {
+    let upper = text.to_ascii_uppercase();
+    let (number, multiplier) = [("KB", 1 << 10), ("MB", 1 << 20), ("GB", 1 << 30)]
+        .into_iter()
+        .find_map(|(unit, multiplier)| Some((upper.strip_suffix(unit)?, multiplier)))
+        .unwrap_or((&upper, 1));
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("1024"), Some(1024));
+    assert_eq!(parse_size("2KB"), Some(2048));
+    assert_eq!(parse_size("500mb"), Some(500 * 1024 * 1024));
+    assert_eq!(parse_size("1GB"), Some(1 << 30));
+    assert_eq!(parse_size("1.5GB"), None);
+    assert_eq!(parse_size("MB"), None);
+    assert_eq!(parse_size("-1"), None);
+}
+
+#[test]
+fn test_settings_default() {
+    assert_eq!(Settings::default().default_limit, Some(DEFAULT_ROW_LIMIT));
+    assert_eq!(Settings::default().output_format, OutputFormat::Table);
+    assert!(!Settings::default().timing);
+    assert_eq!(Settings::default().page_size, None);
+}
+
+#[test]
+fn test_settings_arguments() {
+    let mut settings = Settings::default();
+    for setting in [
+        Setting::DefaultLimit(None),
+        Setting::OutputFormat(OutputFormat::Markdown),
+        Setting::PageSize(Some(20)),
+        Setting::Normalize(Normalization::from_names(&["trim,nfc"]).unwrap()),
+        Setting::Prompt("it's> ".to_string()),
+        Setting::MaxBytes(Some(1536)),
+        Setting::Syntax(Syntax::Sql),
+    ] {
+        settings.apply(setting);
+    }
+    let arguments = settings.arguments();
+    assert_eq!(
+        arguments
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>(),
+        SETTINGS
+            .iter()
+            .map(|setting| setting.name)
+            .collect::<Vec<&str>>()
+    );
+    assert_eq!(arguments[6], ("prompt", "'it\\'s> '".to_string()));
+    // Parsing the arguments gives back the same settings.
+    let mut parsed = Settings::default();
+    for (name, value) in arguments {
+        let args = crate::lexer::tokenize(&value).unwrap();
+        parsed.apply(parse_setting(name, &args).unwrap());
+    }
+    assert_eq!(parsed, settings);
+}
+
+#[test]
+fn test_settings_apply_default_limit() {
+    let mut settings = Settings::default();
+    settings.apply(Setting::DefaultLimit(Some(5)));
+    assert_eq!(settings.default_limit, Some(5));
+    settings.apply(Setting::DefaultLimit(None));
+    assert_eq!(settings.default_limit, None);
+}
+
+#[test]
+fn test_parse_setting_limit() {
+    assert_eq!(
+        parse_setting("limit", &["20"]),
+        Ok(Setting::DefaultLimit(Some(20)))
+    );
+    assert_eq!(
+        parse_setting("limit", &["off"]),
+        Ok(Setting::DefaultLimit(None))
+    );
+}
+
+#[test]
+fn test_parse_setting_limit_malformed() {
+    assert_eq!(
+        parse_setting("limit", &[]),
+        Err("\\limit must be followed by the number of rows or 'off'.".to_string())
+    );
+    assert_eq!(
+        parse_setting("limit", &["-1"]),
+        Err("Invalid value passed to \\limit: -1. Must be a positive integer or 'off'.\n Full error message: invalid digit found in string".to_string())
+    );
+    assert_eq!(
+        parse_setting("limit", &["0"]),
+        Err("Invalid value passed to \\limit: 0. Must be a positive integer or 'off'.".to_string())
+    );
+}
+
+#[test]
+fn test_settings_apply_output_format() {
+    let mut settings = Settings::default();
+    settings.apply(Setting::OutputFormat(OutputFormat::Csv));
+    assert_eq!(settings.output_format, OutputFormat::Csv);
+}
+
+#[test]
+fn test_parse_setting_format() {
+    assert_eq!(
+        parse_setting("format", &["csv"]),
+        Ok(Setting::OutputFormat(OutputFormat::Csv))
+    );
+    assert_eq!(
+        parse_setting("format", &["table"]),
+        Ok(Setting::OutputFormat(OutputFormat::Table))
+    );
+    assert_eq!(
+        parse_setting("format", &["jsonl"]),
+        Ok(Setting::OutputFormat(OutputFormat::Jsonl))
+    );
+    assert_eq!(
+        parse_setting("format", &["markdown"]),
+        Ok(Setting::OutputFormat(OutputFormat::Markdown))
+    );
+    assert_eq!(
+        parse_setting("format", &["json"]),
+        Err(
+            "Unknown output format: json. Must be 'table', 'csv', 'jsonl', or 'markdown'."
+                .to_string()
+        )
+    );
+    assert_eq!(
+        parse_setting("format", &[]),
+        Err("\\format must be followed by 'table', 'csv', 'jsonl', or 'markdown'.".to_string())
+    );
+}
+
+#[test]
+fn test_parse_setting_timing() {
+    assert_eq!(parse_setting("timing", &["on"]), Ok(Setting::Timing(true)));
+    assert_eq!(
+        parse_setting("timing", &["off"]),
+        Ok(Setting::Timing(false))
+    );
+    assert_eq!(
+        parse_setting("timing", &[]),
+        Err("\\timing must be followed by 'on' or 'off'.".to_string())
+    );
+
+    let mut settings = Settings::default();
+    settings.apply(Setting::Timing(true));
+    assert!(settings.timing);
+    assert_eq!(Setting::Timing(true).to_string(), "Timing is on.");
+}
+
+#[test]
+fn test_parse_setting_pagesize() {
+    assert_eq!(
+        parse_setting("pagesize", &["20"]),
+        Ok(Setting::PageSize(Some(20)))
+    );
+    assert_eq!(
+        parse_setting("pagesize", &["off"]),
+        Ok(Setting::PageSize(None))
+    );
+    assert_eq!(
+        parse_setting("pagesize", &["0"]),
+        Err(
+            "Invalid value passed to \\pagesize: 0. Must be a positive integer or 'off'."
+                .to_string()
+        )
+    );
+    assert_eq!(
+        parse_setting("pagesize", &[]),
+        Err("\\pagesize must be followed by the number of rows or 'off'.".to_string())
+    );
+
+    let mut settings = Settings::default();
+    settings.apply(Setting::PageSize(Some(20)));
+    assert_eq!(settings.page_size, Some(20));
+    assert_eq!(
+        Setting::PageSize(Some(20)).to_string(),
+        "Page size set to 20 rows."
+    );
+    assert_eq!(Setting::PageSize(None).to_string(), "Paging disabled.");
+}
+
+#[test]
+fn test_parse_setting_prompt() {
+    assert_eq!(
+        parse_setting("prompt", &["'sql>", "'"]),
+        Ok(Setting::Prompt("sql> ".to_string()))
+    );
+    assert_eq!(
+        parse_setting("prompt", &["sql>"]),
+        Ok(Setting::Prompt("sql> ".to_string()))
+    );
+    assert_eq!(
+        parse_setting("prompt", &[]),
+        Err("\\prompt must be followed by the text of the prompt, e.g. 'sql> '.".to_string())
+    );
+    assert_eq!(
+        Setting::Prompt("sql> ".to_string()).to_string(),
+        "Prompt set to 'sql> '."
+    );
+
+    let mut settings = Settings::default();
+    assert_eq!(settings.prompt, "query> ");
+    assert_eq!(settings.continuation_prompt(), "  ...> ");
+    settings.apply(Setting::Prompt("> ".to_string()));
+    assert_eq!(settings.prompt, "> ");
+    // The continuation prompt is never shorter than `...> `.
+    assert_eq!(settings.continuation_prompt(), "...> ");
+}
+
+#[test]
+fn test_parse_setting_badrows() {
+    assert_eq!(
+        parse_setting("badrows", &["skip"]),
+        Ok(Setting::BadRows(BadRows::Skip))
+    );
+    assert_eq!(
+        parse_setting("badrows", &["fill"]),
+        Err("\\badrows must be followed by 'error', 'skip', or 'null'.".to_string())
+    );
+
+    let mut settings = Settings::default();
+    assert_eq!(settings.bad_rows, BadRows::Error);
+    settings.apply(Setting::BadRows(BadRows::Null));
+    assert_eq!(settings.bad_rows, BadRows::Null);
+    assert_eq!(
+        Setting::BadRows(BadRows::Skip).to_string(),
+        "Bad rows are skipped."
+    );
+}
+
+#[test]
+fn test_parse_setting_cache() {
+    assert_eq!(parse_setting("cache", &["off"]), Ok(Setting::Cache(false)));
+    assert_eq!(
+        parse_setting("cache", &["clear"]),
+        Err("\\cache must be followed by 'on' or 'off'.".to_string())
+    );
+
+    let mut settings = Settings::default();
+    assert!(settings.cache);
+    settings.apply(Setting::Cache(false));
+    assert!(!settings.cache);
+    assert_eq!(Setting::Cache(false).to_string(), "Result cache is off.");
+}
+
+#[test]
+fn test_parse_setting_limits() {
+    assert_eq!(
+        parse_setting("maxrows", &["1000000"]),
+        Ok(Setting::MaxRows(Some(1000000)))
+    );
+    assert_eq!(
+        parse_setting("maxrows", &["off"]),
+        Ok(Setting::MaxRows(None))
+    );
+    assert_eq!(
+        parse_setting("maxrows", &[]),
+        Err("\\maxrows must be followed by the number of rows or 'off'.".to_string())
+    );
+    assert_eq!(
+        parse_setting("maxbytes", &["500MB"]),
+        Ok(Setting::MaxBytes(Some(500 * 1024 * 1024)))
+    );
+    assert_eq!(
+        parse_setting("maxbytes", &["lots"]),
+        Err("Invalid value passed to \\maxbytes: lots. Must be a number of bytes, optionally followed by KB, MB, or GB, or 'off'.".to_string())
+    );
+
+    let mut settings = Settings::default();
+    assert_eq!((settings.max_rows, settings.max_bytes), (None, None));
+    settings.apply(Setting::MaxRows(Some(10)));
+    settings.apply(Setting::MaxBytes(Some(1 << 20)));
+    assert_eq!(
+        (settings.max_rows, settings.max_bytes),
+        (Some(10), Some(1 << 20))
+    );
+    assert_eq!(
+        Setting::MaxBytes(Some(1 << 20)).to_string(),
+        "Results are limited to 1.0 MB of memory."
+    );
+    assert_eq!(
+        Setting::MaxRows(Some(10)).to_string(),
+        "Results are limited to 10 rows."
+    );
+}
+
+#[test]
+fn test_parse_setting_syntax() {
+    assert_eq!(
+        parse_setting("syntax", &["sql"]),
+        Ok(Setting::Syntax(Syntax::Sql))
+    );
+    assert_eq!(
+        parse_setting("syntax", &["pipe"]),
+        Ok(Setting::Syntax(Syntax::Pipe))
+    );
+    assert_eq!(
+        parse_setting("syntax", &["postgres"]),
+        Err("\\syntax must be followed by 'pipe' or 'sql'.".to_string())
+    );
+
+    let mut settings = Settings::default();
+    assert_eq!(settings.syntax, Syntax::Pipe);
+    settings.apply(Setting::Syntax(Syntax::Sql));
+    assert_eq!(settings.syntax, Syntax::Sql);
+    assert_eq!(
+        Setting::Syntax(Syntax::Sql).to_string(),
+        "Query syntax set to sql."
+    );
+}
+
+#[test]
+fn test_parse_setting_normalize() {
+    let trim = Normalization {
+        trim: true,
+        ..Normalization::default()
+    };
+    assert_eq!(
+        parse_setting("normalize", &["trim"]),
+        Ok(Setting::Normalize(trim))
+    );
+    assert_eq!(
+        parse_setting("normalize", &["off"]),
+        Ok(Setting::Normalize(Normalization::default()))
+    );
+    assert_eq!(
+        parse_setting("normalize", &[]),
+        Err("\\normalize must be followed by 'trim', 'underscores', or 'nfc', separated by commas, or 'off'.".to_string())
+    );
+
+    let mut settings = Settings::default();
+    settings.apply(Setting::Normalize(trim));
+    assert_eq!(settings.normalization, trim);
+    assert_eq!(
+        Setting::Normalize(Normalization::from_names(&["trim,underscores"]).unwrap()).to_string(),
+        "Values are normalized while loading: trim,underscores."
+    );
+    assert_eq!(
+        Setting::Normalize(Normalization::default()).to_string(),
+        "Values are loaded as they are."
+    );
+}
+
+#[test]
+fn test_parse_setting_unknown() {
+    assert_eq!(
+        parse_setting("colour", &["on"]),
+        Err("Unknown setting: \\colour".to_string())
+    );
+}
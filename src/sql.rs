@@ -0,0 +1,1064 @@
+use crate::aggregate::{Aggregate, AggregateFunction};
+use crate::commands::{parse_command_with, resolve_dataset, Command, ParseError, Span};
+use crate::data::{is_identifier, DatasetRegistry};
+use crate::expression::{parse_predicate, parse_select_list, Expression, Predicate, SelectColumn};
+use crate::lexer::tokenize;
+use crate::operators::{JoinKind, NullsOrder, Operator, SortOrder};
+
+/// The clauses of a SQL query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Clause {
+    Select,
+    From,
+    Join(JoinKind),
+    Where,
+    GroupBy,
+    Having,
+    OrderBy,
+    Limit,
+    Offset,
+}
+
+impl Clause {
+    /// Returns the position of the clause in a query. The clauses must appear in increasing
+    /// order; LIMIT and OFFSET may appear in either order.
+    fn rank(self) -> usize {
+        match self {
+            Clause::Select => 0,
+            Clause::From => 1,
+            Clause::Join(_) => 2,
+            Clause::Where => 3,
+            Clause::GroupBy => 4,
+            Clause::Having => 5,
+            Clause::OrderBy => 6,
+            Clause::Limit | Clause::Offset => 7,
+        }
+    }
+
+    /// Describes what follows the keyword of the clause, for the errors about a clause without
+    /// one.
+    fn expected(self) -> &'static str {
+        match self {
+            Clause::Select => "the columns to select, or *",
+            Clause::From => "the dataset to load",
+            Clause::Join(_) => {
+                "the dataset, ON, and the columns to join on, e.g. JOIN country ON Capital = CityID"
+            }
+            Clause::Where | Clause::Having => {
+                "a column name, a comparison, and a value, e.g. CityPop > 1000000"
+            }
+            Clause::GroupBy => "the name of the column to group by",
+            Clause::OrderBy => "the name of the column to order by",
+            Clause::Limit => "the number of rows to return",
+            Clause::Offset => "the number of rows to skip",
+        }
+    }
+}
+
+/// A clause of a SQL query: its keyword, and the tokens up to the next clause.
+#[derive(Debug)]
+struct SqlClause<'a> {
+    clause: Clause,
+    /// The keyword of the clause in upper case, e.g. `ORDER BY`, for the errors.
+    name: String,
+    /// The span of the keyword in the query.
+    span: Span,
+    /// The tokens following the keyword.
+    body: Vec<&'a str>,
+}
+
+/// A column of the SELECT list of a SQL query.
+#[derive(Debug)]
+enum SelectItem {
+    /// A column computed from each row, e.g. `CityName AS City` or `CityPop / 1000`.
+    Column(SelectColumn),
+    /// An aggregate of the values of a column, e.g. `SUM(CityPop)`, and the name given to it with
+    /// `AS`, if any.
+    Aggregate(Aggregate, Option<String>),
+}
+
+/// Helper function to recognize the keyword of a clause at the start of `tokens`, e.g. `ORDER BY`
+/// or `LEFT OUTER JOIN`. Keywords are case-insensitive.
+///
+/// # Returns
+/// The clause and the number of tokens its keyword takes, or `None` if `tokens` don't start with
+/// a keyword.
+fn clause_keyword(tokens: &[&str]) -> Option<(Clause, usize)> {
+    let upper: Vec<String> = tokens
+        .iter()
+        .take(3)
+        .map(|token| token.to_ascii_uppercase())
+        .collect();
+    let upper: Vec<&str> = upper.iter().map(String::as_str).collect();
+    let side = |name: &str| match name {
+        "LEFT" => JoinKind::Left,
+        "RIGHT" => JoinKind::Right,
+        _ => JoinKind::Full,
+    };
+    match upper.as_slice() {
+        ["SELECT", ..] => Some((Clause::Select, 1)),
+        ["FROM", ..] => Some((Clause::From, 1)),
+        ["JOIN", ..] => Some((Clause::Join(JoinKind::Inner), 1)),
+        ["INNER", "JOIN", ..] => Some((Clause::Join(JoinKind::Inner), 2)),
+        [name @ ("LEFT" | "RIGHT" | "FULL"), "JOIN", ..] => Some((Clause::Join(side(name)), 2)),
+        [name @ ("LEFT" | "RIGHT" | "FULL"), "OUTER", "JOIN", ..] => {
+            Some((Clause::Join(side(name)), 3))
+        }
+        ["WHERE", ..] => Some((Clause::Where, 1)),
+        ["GROUP", "BY", ..] => Some((Clause::GroupBy, 2)),
+        ["HAVING", ..] => Some((Clause::Having, 1)),
+        ["ORDER", "BY", ..] => Some((Clause::OrderBy, 2)),
+        ["LIMIT", ..] => Some((Clause::Limit, 1)),
+        ["OFFSET", ..] => Some((Clause::Offset, 1)),
+        _ => None,
+    }
+}
+
+/// Helper function to return how many more parentheses the `token` opens than it closes. Quoted
+/// strings don't count.
+fn depth_change(token: &str) -> i64 {
+    match token.starts_with(['\'', '"']) {
+        true => 0,
+        false => token.matches('(').count() as i64 - token.matches(')').count() as i64,
+    }
+}
+
+/// Helper function to split the `tokens` of a query into its clauses. Keywords inside
+/// parentheses, e.g. of a sub-query, don't start a clause.
+///
+/// # Arguments
+/// `tokens` : The tokens of the query, starting with SELECT.
+/// `input` : The line the `tokens` were taken from, which the spans are relative to.
+fn split_clauses<'a>(tokens: &[&'a str], input: &str) -> Vec<SqlClause<'a>> {
+    let mut clauses: Vec<SqlClause> = Vec::new();
+    let mut depth = 0;
+    let mut index = 0;
+    while index < tokens.len() {
+        match clause_keyword(&tokens[index..]).filter(|_| depth == 0) {
+            Some((clause, length)) => {
+                let keyword = &tokens[index..index + length];
+                clauses.push(SqlClause {
+                    clause,
+                    name: keyword.join(" ").to_ascii_uppercase(),
+                    span: Span::between(keyword[0], keyword[length - 1], input),
+                    body: Vec::new(),
+                });
+                index += length;
+            }
+            None => {
+                depth += depth_change(tokens[index]);
+                if let Some(clause) = clauses.last_mut() {
+                    clause.body.push(tokens[index]);
+                }
+                index += 1;
+            }
+        }
+    }
+    clauses
+}
+
+/// Helper function to find the position of the `keyword` among the `tokens`, outside of
+/// parentheses. The keyword is case-insensitive.
+fn find_keyword(tokens: &[&str], keyword: &str) -> Option<usize> {
+    let mut depth = 0;
+    tokens.iter().position(|token| {
+        let found = depth == 0 && token.eq_ignore_ascii_case(keyword);
+        depth += depth_change(token);
+        found
+    })
+}
+
+/// Helper function to split the `text` of a list at its commas, except those inside parentheses or
+/// quoted strings, e.g. the comma of `COALESCE(Capital, 0)`.
+fn split_list(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(opening), c) if c == opening => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                items.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => (),
+        }
+    }
+    items.push(&text[start..]);
+    items
+}
+
+/// Helper function to parse an aggregate of the SELECT list, e.g. `SUM(CityPop)` or `count(*)`.
+/// Unlike in GROUPBY, the name of the function is case-insensitive.
+///
+/// # Returns
+/// The [`Aggregate`], or `None` if `text` isn't a call to an aggregate function.
+fn parse_aggregate(text: &str) -> Option<Aggregate> {
+    let (name, rest) = text.split_once('(')?;
+    let function = AggregateFunction::from_name(&name.trim().to_ascii_uppercase())?;
+    let column = rest.strip_suffix(')')?.trim();
+    (!column.is_empty() && !column.contains(['(', ')', ','])).then(|| Aggregate {
+        function,
+        column: column.to_string(),
+    })
+}
+
+/// Helper function to parse the SELECT list of a query into its columns and aggregates.
+///
+/// # Arguments
+/// `tokens` : The tokens of the list, without DISTINCT.
+/// `input` : The line the `tokens` were taken from, which the spans of errors are relative to.
+///
+/// # Returns
+/// The [`SelectItem`]s, or [`ParseError::InvalidExpression`] if one of them is malformed.
+fn parse_select_items(tokens: &[&str], input: &str) -> Result<Vec<SelectItem>, ParseError> {
+    let invalid = |message: String| ParseError::InvalidExpression {
+        message,
+        span: Span::between(tokens[0], tokens[tokens.len() - 1], input),
+    };
+    // The expression parser expects AS in upper case, separated by single spaces.
+    let text = tokens
+        .iter()
+        .map(|token| match token.eq_ignore_ascii_case("AS") {
+            true => "AS",
+            false => token,
+        })
+        .collect::<Vec<&str>>()
+        .join(" ");
+    split_list(&text)
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (expression, alias) = match item.rsplit_once(" AS ") {
+                Some((expression, alias)) if is_identifier(alias) => {
+                    (expression.trim(), Some(alias.to_string()))
+                }
+                _ => (item, None),
+            };
+            if let Some(aggregate) = parse_aggregate(expression) {
+                return Ok(SelectItem::Aggregate(aggregate, alias));
+            }
+            match parse_select_list(item).map_err(invalid)?.as_slice() {
+                [column] => Ok(SelectItem::Column(column.clone())),
+                _ => Err(invalid(format!("Invalid column: {}", item))),
+            }
+        })
+        .collect()
+}
+
+/// Helper function to parse a condition of WHERE or HAVING, e.g. `CityPop > 1000000` or
+/// `Capital IS NOT NULL`. The comparison is case-insensitive.
+///
+/// # Arguments
+/// `tokens` : The tokens of the condition.
+/// `clause` : The clause the condition belongs to.
+/// `input` : The line the `tokens` were taken from, which the spans of errors are relative to.
+/// `resolve` : Maps the left side of the condition to the name of the column it reads, if it
+/// isn't an expression, e.g. `SUM(CityPop)` to the column GROUPBY computes it in.
+///
+/// # Returns
+/// The [`Predicate`], or [`ParseError::InvalidExpression`] if the condition is malformed.
+fn parse_condition(
+    tokens: &[&str],
+    clause: &SqlClause,
+    input: &str,
+    resolve: &dyn Fn(&str) -> Option<String>,
+) -> Result<Predicate, ParseError> {
+    let span = match tokens {
+        [] => clause.span,
+        [first, .., last] => Span::between(first, last, input),
+        [only] => Span::between(only, only, input),
+    };
+    let (left, comparison, value) = match tokens {
+        [left, is, null] if is.eq_ignore_ascii_case("IS") => (*left, "IS".to_string(), *null),
+        [left, is, not, null]
+            if is.eq_ignore_ascii_case("IS") && not.eq_ignore_ascii_case("NOT") =>
+        {
+            (*left, "IS NOT".to_string(), *null)
+        }
+        [left, comparison, value] => (*left, comparison.to_ascii_uppercase(), *value),
+        _ => {
+            return Err(ParseError::InvalidExpression {
+                message: format!(
+                    "{} must be followed by {}, optionally combined with AND.",
+                    clause.name,
+                    clause.clause.expected()
+                ),
+                span,
+            })
+        }
+    };
+    let value = match value.eq_ignore_ascii_case("NULL") {
+        true => "NULL",
+        false => value,
+    };
+    // A column that isn't an expression is read by its name, e.g. `SUM(CityPop)`, which would
+    // otherwise be parsed as a call to a function, so the condition is parsed on the value first.
+    let predicate = match resolve(left) {
+        Some(column) => parse_predicate("_", &comparison, value).map(|predicate| Predicate {
+            left: Expression::Column(column),
+            ..predicate
+        }),
+        None => parse_predicate(left, &comparison, value),
+    };
+    predicate.map_err(|message| ParseError::InvalidExpression { message, span })
+}
+
+/// Helper function to parse the conditions of a WHERE or HAVING clause, combined with AND.
+///
+/// # Arguments
+/// `clause` : The clause whose conditions to parse.
+/// `input` : The line the clause was taken from, which the spans of errors are relative to.
+/// `resolve` : As passed to [`parse_condition`].
+///
+/// # Returns
+/// The [`Predicate`]s, all of which a row must satisfy, or the [`ParseError`] describing why a
+/// condition is malformed.
+fn parse_conditions(
+    clause: &SqlClause,
+    input: &str,
+    resolve: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<Predicate>, ParseError> {
+    if let Some(index) = find_keyword(&clause.body, "OR") {
+        return Err(ParseError::InvalidExpression {
+            message: format!(
+                "The conditions of {} can only be combined with AND, not OR.",
+                clause.name
+            ),
+            span: Span::between(clause.body[index], clause.body[index], input),
+        });
+    }
+    clause
+        .body
+        .split(|token| token.eq_ignore_ascii_case("AND"))
+        .map(|tokens| parse_condition(tokens, clause, input, resolve))
+        .collect()
+}
+
+/// Helper function to parse the dataset of a FROM or JOIN clause: the name of a dataset, or a
+/// parenthesized sub-query, e.g. `(SELECT * FROM country WHERE Continent = Europe)`.
+///
+/// # Arguments
+/// `clause` : The clause the dataset belongs to.
+/// `tokens` : The tokens naming the dataset.
+/// `input`, `datasets` : As passed to [`parse_sql`].
+///
+/// # Returns
+/// An [`Operator::From`] of the dataset, the [`Operator`] chain of the sub-query, or the
+/// [`ParseError`] describing why they are malformed.
+fn parse_source(
+    clause: &SqlClause,
+    tokens: &[&str],
+    input: &str,
+    datasets: &DatasetRegistry,
+) -> Result<Operator, ParseError> {
+    match tokens {
+        [] => Err(ParseError::MissingArgument {
+            keyword: clause.name.clone(),
+            expected: clause.clause.expected().to_string(),
+            span: clause.span,
+        }),
+        [first, ..] if first.starts_with('(') => {
+            let subquery = format!("sub-query to {}", clause.name);
+            let mut body = tokens.to_vec();
+            let last = body.len() - 1;
+            body[last] =
+                body[last]
+                    .strip_suffix(')')
+                    .ok_or_else(|| ParseError::MissingParenthesis {
+                        after: format!("the {}", subquery),
+                        span: Span::of(first, input),
+                    })?;
+            body[0] = &body[0][1..];
+            body.retain(|token| !token.is_empty());
+            match body.first() {
+                None => Err(ParseError::EmptySubquery {
+                    subquery,
+                    span: Span::of(first, input),
+                }),
+                Some(select) if select.eq_ignore_ascii_case("SELECT") => {
+                    parse_sql(&body, input, datasets)
+                }
+                Some(token) => Err(ParseError::UnexpectedToken {
+                    token: token.to_string(),
+                    span: Span::of(token, input),
+                }),
+            }
+        }
+        [name] => match resolve_dataset(name, &[], datasets) {
+            Some(dataset) => Ok(Operator::From(dataset)),
+            None => Err(ParseError::InvalidDataset {
+                keyword: clause.name.clone(),
+                name: name.to_string(),
+                span: Span::of(name, input),
+            }),
+        },
+        [_, extra, ..] => Err(ParseError::UnexpectedToken {
+            token: extra.to_string(),
+            span: Span::of(extra, input),
+        }),
+    }
+}
+
+/// Helper function to parse a JOIN clause, e.g. `JOIN country ON city.CountryCode =
+/// country.CountryCode`, and join its dataset to the `chain`. The columns may be qualified by the
+/// name of their dataset, in either order.
+///
+/// # Arguments
+/// `chain` : The [`Operator`] chain producing the left table.
+/// `kind` : Which unmatched rows the join keeps.
+/// `clause` : The JOIN clause.
+/// `input`, `datasets` : As passed to [`parse_sql`].
+///
+/// # Returns
+/// The [`Operator::Join`], or the [`ParseError`] describing why the clause is malformed.
+fn parse_join(
+    chain: Operator,
+    kind: JoinKind,
+    clause: &SqlClause,
+    input: &str,
+    datasets: &DatasetRegistry,
+) -> Result<Operator, ParseError> {
+    let on = find_keyword(&clause.body, "ON").ok_or_else(|| ParseError::MissingArgument {
+        keyword: clause.name.clone(),
+        expected: clause.clause.expected().to_string(),
+        span: clause.span,
+    })?;
+    let right = parse_source(clause, &clause.body[..on], input, datasets)?;
+    let condition = clause.body[on + 1..].concat();
+    let (left_column, right_column) = condition
+        .split_once('=')
+        .filter(|(left, right)| !left.is_empty() && !right.is_empty())
+        .ok_or_else(|| ParseError::InvalidExpression {
+            message: format!(
+                "Invalid join condition: {}. Must be two columns compared with =, e.g. Capital = CityID.",
+                condition
+            ),
+            span: Span::between(clause.body[on], clause.body[clause.body.len() - 1], input),
+        })?;
+
+    // The qualifiers only tell which column belongs to which dataset.
+    let unqualified = |column: &str| match column.split_once('.') {
+        Some((qualifier, name)) => (resolve_dataset(qualifier, &[], datasets), name.to_string()),
+        None => (None, column.to_string()),
+    };
+    let (left_dataset, mut left_column) = unqualified(left_column);
+    let (_, mut right_column) = unqualified(right_column);
+    if matches!((&left_dataset, &right), (Some(dataset), Operator::From(right)) if dataset == right)
+    {
+        std::mem::swap(&mut left_column, &mut right_column);
+    }
+    Ok(Operator::Join {
+        chain: Box::new(chain),
+        right: Box::new(right),
+        right_column: (right_column != left_column).then_some(right_column),
+        column: left_column,
+        kind,
+        strategy: None,
+    })
+}
+
+/// Helper function to parse the single number following LIMIT or OFFSET.
+fn parse_count(clause: &SqlClause, input: &str) -> Result<usize, ParseError> {
+    match clause.body.as_slice() {
+        [count] => str::parse::<usize>(count).map_err(|error| ParseError::InvalidNumber {
+            keyword: clause.name.clone(),
+            value: count.to_string(),
+            error,
+            span: Span::of(count, input),
+        }),
+        [_, extra, ..] => Err(ParseError::UnexpectedToken {
+            token: extra.to_string(),
+            span: Span::of(extra, input),
+        }),
+        [] => Err(ParseError::MissingArgument {
+            keyword: clause.name.clone(),
+            expected: clause.clause.expected().to_string(),
+            span: clause.span,
+        }),
+    }
+}
+
+/// Parses a query written in standard SQL, e.g. `SELECT CityName FROM city ORDER BY CityPop DESC
+/// LIMIT 7`, into the same [`Operator`] chain as the equivalent pipe query, e.g.
+/// `FROM city ORDERBY CityPop DESC SELECT CityName TAKE 7`.
+///
+/// The clauses are `SELECT [DISTINCT] <columns>|* FROM <dataset> [[INNER|LEFT|RIGHT|FULL [OUTER]]
+/// JOIN <dataset> ON <column> = <column> ...] [WHERE <condition> [AND <condition> ...]]
+/// [GROUP BY <column> [HAVING <condition> [AND ...]]] [ORDER BY <column> [ASC|DESC]
+/// [NULLS FIRST|LAST]] [LIMIT <number>] [OFFSET <number>]`, whose keywords are case-insensitive. A
+/// dataset may also be a parenthesized sub-query. As in SQL, ORDER BY sorts in ascending order
+/// unless DESC is given.
+///
+/// # Arguments
+/// `tokens` : The tokens of the query, starting with SELECT.
+/// `input` : The line the `tokens` were taken from, which the spans of errors are relative to.
+/// `datasets` : The datasets registered with `LOAD`.
+///
+/// # Returns
+/// The [`Operator`] chain, or the [`ParseError`] describing why the query is malformed or uses
+/// something the operators can't express, e.g. conditions combined with OR.
+pub fn parse_sql(
+    tokens: &[&str],
+    input: &str,
+    datasets: &DatasetRegistry,
+) -> Result<Operator, ParseError> {
+    let mut clauses = split_clauses(tokens, input);
+    for (index, clause) in clauses.iter().enumerate() {
+        let misplaced = clauses[..index].iter().any(|previous| {
+            previous.clause.rank() > clause.clause.rank()
+                || (previous.clause == clause.clause && !matches!(clause.clause, Clause::Join(_)))
+        });
+        if misplaced {
+            return Err(ParseError::UnexpectedToken {
+                token: clause.name.clone(),
+                span: clause.span,
+            });
+        }
+        if clause.body.is_empty() {
+            return Err(ParseError::MissingArgument {
+                keyword: clause.name.clone(),
+                expected: clause.clause.expected().to_string(),
+                span: clause.span,
+            });
+        }
+    }
+    let find = |kind: Clause| clauses.iter().find(|clause| clause.clause == kind);
+    let select = &clauses[0];
+    let Some(from) = find(Clause::From) else {
+        return Err(ParseError::MissingArgument {
+            keyword: select.name.clone(),
+            expected:
+                "the columns to select, FROM, and the dataset, e.g. SELECT CityName FROM city"
+                    .to_string(),
+            span: select.span,
+        });
+    };
+
+    // FROM, JOIN and WHERE produce the rows the columns are selected from.
+    let mut chain = parse_source(from, &from.body, input, datasets)?;
+    for clause in &clauses {
+        if let Clause::Join(kind) = clause.clause {
+            chain = parse_join(chain, kind, clause, input, datasets)?;
+        }
+    }
+    if let Some(clause) = find(Clause::Where) {
+        for predicate in parse_conditions(clause, input, &|_| None)? {
+            chain = Operator::Filter {
+                chain: Box::new(chain),
+                predicate,
+            };
+        }
+    }
+
+    let distinct = select.body[0].eq_ignore_ascii_case("DISTINCT");
+    let columns = &select.body[distinct as usize..];
+    let items = match columns {
+        [] => {
+            return Err(ParseError::MissingArgument {
+                keyword: "SELECT DISTINCT".to_string(),
+                expected: Clause::Select.expected().to_string(),
+                span: select.span,
+            })
+        }
+        ["*"] => None,
+        columns => Some(parse_select_items(columns, input)?),
+    };
+    let invalid_select = |message: String| ParseError::InvalidExpression {
+        message,
+        span: Span::between(columns[0], columns[columns.len() - 1], input),
+    };
+    let having = find(Clause::Having);
+    if let (None, Some(having)) = (find(Clause::GroupBy), having) {
+        return Err(ParseError::UnexpectedToken {
+            token: having.name.clone(),
+            span: having.span,
+        });
+    }
+
+    // The columns of the result, named as in the table they are selected from, or `None` to keep
+    // all of them.
+    let projection = match (find(Clause::GroupBy), items) {
+        (Some(group_by), items) => {
+            let group = match group_by.body.as_slice() {
+                [group] => group.to_string(),
+                _ => {
+                    return Err(ParseError::InvalidExpression {
+                        message: format!(
+                            "GROUP BY groups the rows by a single column, not {}.",
+                            group_by.body.join(" ")
+                        ),
+                        span: Span::between(
+                            group_by.body[0],
+                            group_by.body[group_by.body.len() - 1],
+                            input,
+                        ),
+                    })
+                }
+            };
+            let Some(items) = items else {
+                return Err(invalid_select(format!(
+                    "SELECT * can't be grouped; select {} and the aggregates to compute instead.",
+                    group
+                )));
+            };
+            let mut aggregates: Vec<Aggregate> = Vec::new();
+            let mut columns = Vec::new();
+            for item in items {
+                match item {
+                    SelectItem::Column(SelectColumn {
+                        expression: Expression::Column(name),
+                        alias,
+                    }) if name == group => columns.push(SelectColumn {
+                        expression: Expression::Column(name),
+                        alias,
+                    }),
+                    SelectItem::Column(column) => {
+                        return Err(invalid_select(format!(
+                        "{} must be the GROUP BY column, {}, or an aggregate, e.g. SUM(CityPop).",
+                        column.expression, group
+                    )))
+                    }
+                    SelectItem::Aggregate(aggregate, alias) => {
+                        if !aggregates.contains(&aggregate) {
+                            aggregates.push(aggregate.clone());
+                        }
+                        columns.push(SelectColumn {
+                            expression: Expression::Column(aggregate.to_string()),
+                            alias,
+                        });
+                    }
+                }
+            }
+
+            // HAVING reads the aggregates by name or alias, and may read ones that aren't
+            // selected, which are computed too.
+            let mut conditions = match having {
+                Some(having) => {
+                    let mut needed = Vec::new();
+                    let resolve = |left: &str| match parse_aggregate(left) {
+                        Some(aggregate) => Some(aggregate.to_string()),
+                        None => columns
+                            .iter()
+                            .find(|column| column.alias.as_deref() == Some(left))
+                            .map(|column| column.expression.to_string()),
+                    };
+                    let conditions = parse_conditions(having, input, &resolve)?;
+                    for token in &having.body {
+                        needed.extend(parse_aggregate(token));
+                    }
+                    for aggregate in needed {
+                        if !aggregates.contains(&aggregate) {
+                            aggregates.push(aggregate);
+                        }
+                    }
+                    conditions
+                }
+                None => Vec::new(),
+            };
+            let identity: Vec<SelectColumn> = std::iter::once(group.clone())
+                .chain(aggregates.iter().map(Aggregate::to_string))
+                .map(|name| SelectColumn {
+                    expression: Expression::Column(name),
+                    alias: None,
+                })
+                .collect();
+            chain = Operator::GroupBy {
+                chain: Box::new(chain),
+                column: group,
+                aggregates,
+                having: match conditions.is_empty() {
+                    true => None,
+                    false => Some(conditions.remove(0)),
+                },
+            };
+            for predicate in conditions {
+                chain = Operator::Filter {
+                    chain: Box::new(chain),
+                    predicate,
+                };
+            }
+            (columns != identity).then_some(columns)
+        }
+        (None, Some(items))
+            if items
+                .iter()
+                .any(|item| matches!(item, SelectItem::Aggregate(..))) =>
+        {
+            let [SelectItem::Aggregate(aggregate, alias)] = items.as_slice() else {
+                return Err(invalid_select(
+                    "Without GROUP BY, a query selects a single aggregate, e.g. SELECT SUM(CityPop) FROM city."
+                        .to_string(),
+                ));
+            };
+            chain = Operator::Aggregate {
+                chain: Box::new(chain),
+                function: aggregate.function,
+                column: aggregate.column.clone(),
+            };
+            alias.clone().map(|alias| {
+                vec![SelectColumn {
+                    expression: Expression::Column(aggregate.to_string()),
+                    alias: Some(alias),
+                }]
+            })
+        }
+        (None, items) => items.map(|items| {
+            items
+                .into_iter()
+                .filter_map(|item| match item {
+                    SelectItem::Column(column) => Some(column),
+                    SelectItem::Aggregate(..) => None,
+                })
+                .collect()
+        }),
+    };
+
+    // ORDER BY may sort on a column that isn't selected, so it is sorted before the columns are
+    // selected, unless it sorts on one of them by the name it is given.
+    let order_by = match find(Clause::OrderBy) {
+        Some(clause) => {
+            // The rows with a missing value come last unless the clause ends with NULLS FIRST.
+            let is = |token: &str, keyword: &str| token.eq_ignore_ascii_case(keyword);
+            let (body, nulls) = match clause.body.as_slice() {
+                [body @ .., nulls, place] if is(nulls, "NULLS") && is(place, "FIRST") => {
+                    (body, NullsOrder::First)
+                }
+                [body @ .., nulls, place] if is(nulls, "NULLS") && is(place, "LAST") => {
+                    (body, NullsOrder::Last)
+                }
+                body => (body, NullsOrder::Last),
+            };
+            let (column, order) = match body {
+                [column] => (*column, SortOrder::Ascending),
+                [column, order] if order.eq_ignore_ascii_case("ASC") => {
+                    (*column, SortOrder::Ascending)
+                }
+                [column, order] if order.eq_ignore_ascii_case("DESC") => {
+                    (*column, SortOrder::Descending)
+                }
+                _ => {
+                    let body = &clause.body;
+                    return Err(ParseError::InvalidExpression {
+                        message: format!(
+                            "ORDER BY sorts on a single column, optionally followed by ASC or DESC and NULLS FIRST or LAST, not {}.",
+                            body.join(" ")
+                        ),
+                        span: Span::between(body[0], body[body.len() - 1], input),
+                    });
+                }
+            };
+            let column = parse_aggregate(column)
+                .map_or(column.to_string(), |aggregate| aggregate.to_string());
+            Some((column, order, nulls))
+        }
+        None => None,
+    };
+    let is_selected = |column: &str, columns: &[SelectColumn]| {
+        columns.iter().any(|selected| match &selected.alias {
+            Some(alias) => alias == column,
+            None => selected.expression.to_string() == column,
+        })
+    };
+    let sort = |chain: Operator, (column, order, nulls): (String, SortOrder, NullsOrder)| {
+        Operator::OrderBy {
+            chain: Box::new(chain),
+            column,
+            order,
+            nulls,
+        }
+    };
+    match (projection, order_by) {
+        (Some(columns), Some(order_by)) if !is_selected(&order_by.0, &columns) => {
+            chain = Operator::Select {
+                chain: Box::new(sort(chain, order_by)),
+                columns,
+            };
+        }
+        (projection, order_by) => {
+            if let Some(columns) = projection {
+                chain = Operator::Select {
+                    chain: Box::new(chain),
+                    columns,
+                };
+            }
+            if let Some(order_by) = order_by {
+                chain = sort(chain, order_by);
+            }
+        }
+    }
+    if distinct {
+        chain = Operator::Distinct {
+            chain: Box::new(chain),
+            columns: Vec::new(),
+        };
+    }
+
+    clauses.retain(|clause| matches!(clause.clause, Clause::Limit | Clause::Offset));
+    if let Some(offset) = clauses
+        .iter()
+        .find(|clause| clause.clause == Clause::Offset)
+    {
+        chain = Operator::Skip {
+            chain: Box::new(chain),
+            count: parse_count(offset, input)?,
+        };
+    }
+    if let Some(limit) = clauses.iter().find(|clause| clause.clause == Clause::Limit) {
+        chain = Operator::Take {
+            chain: Box::new(chain),
+            count: parse_count(limit, input)?,
+        };
+    }
+    Ok(chain)
+}
+
+/// Parses the command entered on the CLI into a [`Command`] while `\syntax sql` is set: queries
+/// starting with SELECT, optionally after EXPLAIN or EXPLAIN ANALYZE, are parsed as SQL by
+/// [`parse_sql`], and all other input as by [`parse_command_with`], e.g. settings and `LOAD`.
+///
+/// # Arguments
+/// `input` : the input string to be processed.
+/// `datasets` : The datasets registered so far.
+///
+/// # Returns
+/// A [`Command`] that represents the parsed input.
+pub fn parse_sql_command(input: &str, datasets: &DatasetRegistry) -> Command {
+    let Some(val) = input.strip_suffix("\n") else {
+        return parse_command_with(input, datasets);
+    };
+    // Unterminated strings are reported by parse_command_with.
+    let Ok(mut tokens) = tokenize(val) else {
+        return parse_command_with(input, datasets);
+    };
+    // A query may end with a semicolon, as is customary in SQL.
+    if let Some(last) = tokens.last_mut() {
+        *last = last.strip_suffix(';').unwrap_or(last);
+    }
+    tokens.retain(|token| !token.is_empty());
+
+    let is_select = |tokens: &[&str]| {
+        tokens
+            .first()
+            .is_some_and(|token| token.eq_ignore_ascii_case("SELECT"))
+    };
+    let is_keyword = |token: &str, keyword: &str| token.eq_ignore_ascii_case(keyword);
+    let (query, wrap): (&[&str], fn(Operator) -> Command) = match tokens.as_slice() {
+        query if is_select(query) => (query, Command::Operator),
+        [explain, analyze, query @ ..]
+            if is_keyword(explain, "EXPLAIN")
+                && is_keyword(analyze, "ANALYZE")
+                && is_select(query) =>
+        {
+            (query, Command::ExplainAnalyze)
+        }
+        [explain, query @ ..] if is_keyword(explain, "EXPLAIN") && is_select(query) => {
+            (query, Command::Explain)
+        }
+        _ => return parse_command_with(input, datasets),
+    };
+    match parse_sql(query, val, datasets) {
+        Ok(operator) => wrap(operator),
+        Err(error) => Command::InputError(error),
+    }
+}
+
+/// Helper function to parse the SQL `query` and the equivalent `pipe` query, which must both be
+/// well-formed, into their [`Command`]s.
+#[cfg(test)]
+fn parse_both(query: &str, pipe: &str) -> (Command, Command) {
+    let datasets = DatasetRegistry::default();
+    (
+        parse_sql_command(&format!("{}\n", query), &datasets),
+        parse_command_with(&format!("{}\n", pipe), &datasets),
+    )
+}
+
+/// Helper function to parse the SQL `query`, which must be malformed, into its [`ParseError`].
+#[cfg(test)]
+fn sql_error(query: &str) -> ParseError {
+    match parse_sql_command(&format!("{}\n", query), &DatasetRegistry::default()) {
+        Command::InputError(error) => error,
+        command => panic!("Not malformed: {:?}", command),
+    }
+}
+
+#[test]
+fn test_parse_sql() {
+    for (query, pipe) in [
+        (
+            "SELECT CityName FROM city ORDER BY CityPop DESC LIMIT 7",
+            "FROM city ORDERBY CityPop DESC SELECT CityName TAKE 7",
+        ),
+        ("select * from city limit 5;", "FROM city TAKE 5"),
+        (
+            "SELECT CityName, CityPop / 1000 AS PopK FROM city ORDER BY PopK",
+            "FROM city SELECT CityName,CityPop / 1000 AS PopK ORDERBY PopK ASC",
+        ),
+        (
+            "SELECT * FROM country ORDER BY Capital DESC nulls first",
+            "FROM country ORDERBY Capital DESC NULLS FIRST",
+        ),
+        (
+            "SELECT DISTINCT Language FROM language LIMIT 3 OFFSET 2",
+            "FROM language SELECT Language DISTINCT SKIP 2 TAKE 3",
+        ),
+        (
+            "SELECT CityName FROM city WHERE CountryCode = 'NLD' AND Capital is not null",
+            "FROM city FILTER CountryCode = 'NLD' FILTER Capital IS NOT NULL SELECT CityName",
+        ),
+        (
+            "SELECT * FROM (SELECT CityName FROM city) LIMIT 1",
+            "FROM city SELECT CityName TAKE 1",
+        ),
+    ] {
+        let (sql, pipe) = parse_both(query, pipe);
+        assert_eq!(sql, pipe, "{}", query);
+    }
+}
+
+#[test]
+fn test_parse_sql_join() {
+    for (query, pipe) in [
+        (
+            "SELECT * FROM city JOIN country ON city.CountryCode = country.CountryCode",
+            "FROM city JOIN country CountryCode",
+        ),
+        (
+            "SELECT * FROM country LEFT OUTER JOIN city ON Capital = CityID",
+            "FROM country LEFTJOIN city ON Capital=CityID",
+        ),
+        // The qualifiers tell which column belongs to the joined dataset.
+        (
+            "SELECT * FROM country FULL JOIN city ON city.CityID=country.Capital",
+            "FROM country OUTERJOIN city ON Capital=CityID",
+        ),
+    ] {
+        let (sql, pipe) = parse_both(query, pipe);
+        assert_eq!(sql, pipe, "{}", query);
+    }
+}
+
+#[test]
+fn test_parse_sql_aggregates() {
+    for (query, pipe) in [
+        ("SELECT COUNT(*) FROM city", "FROM city COUNT"),
+        (
+            "SELECT CountryCode, SUM(CityPop) FROM city GROUP BY CountryCode ORDER BY SUM(CityPop) DESC",
+            "FROM city GROUPBY CountryCode SUM(CityPop) ORDERBY SUM(CityPop) DESC",
+        ),
+        (
+            "SELECT Continent, COUNT(Capital) FROM country GROUP BY Continent HAVING count(Capital) > 30",
+            "FROM country GROUPBY Continent COUNT(Capital) HAVING COUNT(Capital) > 30",
+        ),
+    ] {
+        let (sql, pipe) = parse_both(query, pipe);
+        assert_eq!(sql, pipe, "{}", query);
+    }
+
+    let (sql, _) = parse_both("SELECT sum(CityPop) AS Total FROM city", "FROM city");
+    let Command::Operator(operator) = sql else {
+        panic!("Not a query: {:?}", sql);
+    };
+    assert_eq!(
+        operator.to_string(),
+        "FROM city.csv SUM CityPop SELECT SUM(CityPop) AS Total"
+    );
+
+    // The aggregates are selected by name when they are reordered or renamed, and HAVING may
+    // read an aggregate that isn't selected.
+    let (sql, _) = parse_both(
+        "SELECT SUM(CityPop) AS Total, CountryCode FROM city GROUP BY CountryCode HAVING COUNT(*) > 10",
+        "FROM city",
+    );
+    let Command::Operator(Operator::Select { chain, columns }) = sql else {
+        panic!("Not a SELECT: {:?}", sql);
+    };
+    assert_eq!(
+        columns
+            .iter()
+            .map(SelectColumn::to_string)
+            .collect::<Vec<String>>(),
+        vec!["SUM(CityPop) AS Total", "CountryCode"]
+    );
+    assert_eq!(
+        chain.to_string(),
+        "FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(*) HAVING COUNT(*) > 10"
+    );
+}
+
+#[test]
+fn test_parse_sql_command() {
+    let datasets = DatasetRegistry::default();
+    let (sql, pipe) = parse_both(
+        "EXPLAIN SELECT * FROM city LIMIT 1",
+        "EXPLAIN FROM city TAKE 1",
+    );
+    assert_eq!(sql, pipe);
+    assert!(matches!(sql, Command::Explain(_)));
+    let (sql, pipe) = parse_both(
+        "explain analyze select * from city limit 1",
+        "EXPLAIN ANALYZE FROM city TAKE 1",
+    );
+    assert_eq!(sql, pipe);
+    // The other commands are parsed as usual.
+    for input in ["\\syntax pipe\n", "DESCRIBE city\n", "help\n", "\n"] {
+        assert_eq!(
+            parse_sql_command(input, &datasets),
+            parse_command_with(input, &datasets)
+        );
+    }
+}
+
+#[test]
+fn test_parse_sql_malformed() {
+    assert_eq!(
+        sql_error("SELECT CityName WHERE CityPop > 1").to_string(),
+        "SELECT must be followed by the columns to select, FROM, and the dataset, e.g. SELECT CityName FROM city."
+    );
+    assert_eq!(
+        sql_error("SELECT CityName FROM city LIMIT 1 WHERE CityPop > 1"),
+        ParseError::UnexpectedToken {
+            token: "WHERE".to_string(),
+            span: Span { start: 34, end: 39 },
+        }
+    );
+    assert_eq!(
+        sql_error("SELECT CityName FROM city ORDER BY").to_string(),
+        "ORDER BY must be followed by the name of the column to order by."
+    );
+    assert_eq!(
+        sql_error("SELECT CityName FROM town").to_string(),
+        "Invalid dataset passed to FROM: town"
+    );
+    assert_eq!(
+        sql_error("SELECT CityName FROM city WHERE CityPop > 1 OR CityPop < 0").to_string(),
+        "The conditions of WHERE can only be combined with AND, not OR."
+    );
+    assert_eq!(
+        sql_error("SELECT CityName, COUNT(*) FROM city GROUP BY CountryCode").to_string(),
+        "CityName must be the GROUP BY column, CountryCode, or an aggregate, e.g. SUM(CityPop)."
+    );
+    assert_eq!(
+        sql_error("SELECT CityName, COUNT(*) FROM city").to_string(),
+        "Without GROUP BY, a query selects a single aggregate, e.g. SELECT SUM(CityPop) FROM city."
+    );
+    assert_eq!(
+        sql_error("SELECT * FROM city ORDER BY CityPop, CityID").to_string(),
+        "ORDER BY sorts on a single column, optionally followed by ASC or DESC and NULLS FIRST or LAST, not CityPop, CityID."
+    );
+    assert_eq!(
+        sql_error("SELECT * FROM city JOIN country CountryCode").to_string(),
+        "JOIN must be followed by the dataset, ON, and the columns to join on, e.g. JOIN country ON Capital = CityID."
+    );
+    assert_eq!(
+        sql_error("SELECT * FROM city LIMIT lots").to_string(),
+        "Invalid value passed to LIMIT operator: lots. Must be a positive integer.\n Full error message: invalid digit found in string"
+    );
+}
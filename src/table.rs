@@ -1,8 +1,23 @@
+use std::cmp::Ordering;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, AsArray, Float64Array, Int64Array, StringArray};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{DataType as ArrowType, Field, Float64Type, Int64Type, Schema};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::data::typed_table;
 
 /// Type used to hold data in the Table. All data must be wrapped in one of these variants.
 /// Cells correspond to the columns of a row.
-#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd)]
+#[derive(Clone, Debug)]
 pub enum Cell {
     /// The value in the cell is a String.
     String(String),
@@ -19,6 +34,164 @@ pub enum Cell {
     ///                                              ^--- No capital.
     /// ATG,Antigua_and_Barbuda,North_America,68000,63
     OptInt64(Option<i64>),
+    /// The value in the Cell is a decimal number, e.g. `3.75`.
+    Float64(f64),
+    /// The value is missing from a column of any type. Produced for the columns of unmatched rows
+    /// by the outer joins.
+    Null,
+}
+
+impl Cell {
+    /// Returns `true` if the value is missing, i.e., it is [`Cell::Null`] or an empty
+    /// [`Cell::OptInt64`].
+    pub fn is_null(&self) -> bool {
+        matches!(self, Cell::Null | Cell::OptInt64(None))
+    }
+
+    /// Returns `true` if the value is a number that can be sorted on, i.e., it is a
+    /// [`Cell::Int64`] or a [`Cell::Float64`].
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Cell::Int64(_) | Cell::Float64(_))
+    }
+
+    /// Returns the value as a decimal number, or `None` if it isn't a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Cell::Int64(val) | Cell::OptInt64(Some(val)) => Some(*val as f64),
+            Cell::Float64(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Compares the numeric values of two cells. Integers are compared exactly; if either value
+    /// is a decimal number, both are compared as decimal numbers.
+    ///
+    /// # Returns
+    /// The ordering of the values, or `None` if either of them isn't a number.
+    pub fn cmp_numeric(&self, other: &Cell) -> Option<Ordering> {
+        match (self, other) {
+            (
+                Cell::Int64(left) | Cell::OptInt64(Some(left)),
+                Cell::Int64(right) | Cell::OptInt64(Some(right)),
+            ) => Some(left.cmp(right)),
+            _ => Some(self.as_f64()?.total_cmp(&other.as_f64()?)),
+        }
+    }
+
+    /// Compares two cells of any type, so values can be sorted even if they aren't all numbers:
+    /// numbers come first, ordered by their value, then strings, ordered lexicographically, and
+    /// missing values last.
+    pub fn cmp_values(&self, other: &Cell) -> Ordering {
+        // Helper to rank the kinds of values relative to each other.
+        fn rank(cell: &Cell) -> u8 {
+            match cell {
+                _ if cell.is_null() => 2,
+                Cell::String(_) => 1,
+                _ => 0,
+            }
+        }
+        match (self, other) {
+            (Cell::String(left), Cell::String(right)) => left.cmp(right),
+            _ => self
+                .cmp_numeric(other)
+                .unwrap_or_else(|| rank(self).cmp(&rank(other))),
+        }
+    }
+}
+
+impl PartialEq for Cell {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Cell::String(left), Cell::String(right)) => left == right,
+            (Cell::Int64(left), Cell::Int64(right)) => left == right,
+            (Cell::OptInt64(left), Cell::OptInt64(right)) => left == right,
+            // Decimal numbers are compared by their bits, so equality is consistent with hashing
+            // and cells can be used as keys by COUNTBY, GROUPBY, DISTINCT, and JOIN.
+            (Cell::Float64(left), Cell::Float64(right)) => left.to_bits() == right.to_bits(),
+            (Cell::Null, Cell::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Cell {}
+
+impl Hash for Cell {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Cell::String(val) => val.hash(state),
+            Cell::Int64(val) => val.hash(state),
+            Cell::OptInt64(val) => val.hash(state),
+            Cell::Float64(val) => val.to_bits().hash(state),
+            Cell::Null => (),
+        }
+    }
+}
+
+/// Test Cell::cmp_numeric for integers, decimal numbers, and non-numeric values.
+#[test]
+fn test_cell_cmp_numeric() {
+    assert_eq!(
+        Cell::Int64(2).cmp_numeric(&Cell::OptInt64(Some(10))),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        Cell::Float64(2.5).cmp_numeric(&Cell::Int64(2)),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        Cell::Float64(-0.5).cmp_numeric(&Cell::Float64(-0.5)),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(Cell::Int64(1).cmp_numeric(&Cell::Null), None);
+    assert_eq!(
+        Cell::String("1".to_string()).cmp_numeric(&Cell::Int64(1)),
+        None
+    );
+}
+
+/// Test Cell::cmp_values for values of every type.
+#[test]
+fn test_cell_cmp_values() {
+    let mut cells = [
+        Cell::Null,
+        Cell::String("b".to_string()),
+        Cell::Int64(3),
+        Cell::OptInt64(None),
+        Cell::String("a".to_string()),
+        Cell::Float64(2.5),
+        Cell::OptInt64(Some(-1)),
+    ];
+    cells.sort_by(Cell::cmp_values);
+    assert_eq!(
+        cells
+            .iter()
+            .map(|cell| cell.to_string())
+            .collect::<Vec<_>>(),
+        vec!["-1", "2.5", "3", "a", "b", "", ""]
+    );
+    assert_eq!(
+        Cell::Null.cmp_values(&Cell::OptInt64(None)),
+        Ordering::Equal
+    );
+}
+
+/// Test that equal cells hash equally, including decimal numbers.
+#[test]
+fn test_cell_eq_and_hash() {
+    let cells: std::collections::HashSet<Cell> = [
+        Cell::Float64(1.5),
+        Cell::Float64(1.5),
+        Cell::Float64(f64::NAN),
+        Cell::Float64(f64::NAN),
+        Cell::Int64(1),
+        Cell::OptInt64(Some(1)),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(cells.len(), 4);
+    assert_ne!(Cell::Float64(1.0), Cell::Int64(1));
 }
 
 impl Display for Cell {
@@ -26,19 +199,106 @@ impl Display for Cell {
         match self {
             Cell::String(val) => f.write_fmt(format_args!("{}", val)),
             Cell::Int64(val) => f.write_fmt(format_args!("{}", val)),
-            Cell::OptInt64(val) => {
-                if val.is_some() {
-                    f.write_fmt(format_args!("{}", val.unwrap()))
-                } else {
-                    f.write_fmt(format_args!("{}", String::new()))
-                }
-            }
+            Cell::OptInt64(val) => match val {
+                Some(val) => f.write_fmt(format_args!("{}", val)),
+                None => f.write_fmt(format_args!("{}", String::new())),
+            },
+            Cell::Float64(val) => f.write_fmt(format_args!("{}", val)),
+            Cell::Null => Ok(()),
         }
     }
 }
 
-/// Type used to represent a row of data in the data being processed.
-#[derive(Clone, Debug)]
+/// Cells are serialized as plain values rather than tagged with their variant, e.g. `42`, `"Kabul"`,
+/// or `null`, so that serialized results are easy to read from other tools.
+impl Serialize for Cell {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Cell::String(val) => serializer.serialize_str(val),
+            Cell::Int64(val) | Cell::OptInt64(Some(val)) => serializer.serialize_i64(*val),
+            Cell::Float64(val) => serializer.serialize_f64(*val),
+            Cell::OptInt64(None) | Cell::Null => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Helper to read a [`Cell`] back from the plain value it is serialized as.
+struct CellVisitor;
+
+impl<'de> Visitor<'de> for CellVisitor {
+    type Value = Cell;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a string, a number, or null")
+    }
+
+    fn visit_str<E: de::Error>(self, val: &str) -> Result<Cell, E> {
+        Ok(Cell::String(val.to_string()))
+    }
+
+    fn visit_i64<E: de::Error>(self, val: i64) -> Result<Cell, E> {
+        Ok(Cell::Int64(val))
+    }
+
+    fn visit_u64<E: de::Error>(self, val: u64) -> Result<Cell, E> {
+        i64::try_from(val)
+            .map(Cell::Int64)
+            .map_err(|_| E::custom(format!("{} doesn't fit in a 64-bit integer", val)))
+    }
+
+    fn visit_f64<E: de::Error>(self, val: f64) -> Result<Cell, E> {
+        Ok(Cell::Float64(val))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Cell, E> {
+        Ok(Cell::Null)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Cell, E> {
+        Ok(Cell::Null)
+    }
+}
+
+/// The variant of a [`Cell`] isn't serialized, so integers are read back as [`Cell::Int64`] and
+/// missing values as [`Cell::Null`].
+impl<'de> Deserialize<'de> for Cell {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Cell, D::Error> {
+        deserializer.deserialize_any(CellVisitor)
+    }
+}
+
+/// Test that cells are serialized as plain values, and read back.
+#[test]
+fn test_cell_serde() {
+    let cells = vec![
+        Cell::String("Kabul".to_string()),
+        Cell::Int64(-3),
+        Cell::OptInt64(Some(7)),
+        Cell::OptInt64(None),
+        Cell::Float64(2.5),
+        Cell::Null,
+    ];
+    let json = serde_json::to_string(&cells).unwrap();
+    assert_eq!(json, r#"["Kabul",-3,7,null,2.5,null]"#);
+    assert_eq!(
+        serde_json::from_str::<Vec<Cell>>(&json).unwrap(),
+        vec![
+            Cell::String("Kabul".to_string()),
+            Cell::Int64(-3),
+            Cell::Int64(7),
+            Cell::Null,
+            Cell::Float64(2.5),
+            Cell::Null,
+        ]
+    );
+    assert!(serde_json::from_str::<Cell>("[1]").is_err());
+    assert!(serde_json::from_str::<Cell>("18446744073709551615").is_err());
+}
+
+/// Type used to represent a row of data in the data being processed. Serialized as the list of
+/// its cells.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Row {
     pub cells: Vec<Cell>,
 }
@@ -52,6 +312,20 @@ impl Row {
             .collect::<Vec<String>>()
             .join(",")
     }
+
+    /// Returns a rough estimate of the number of bytes of memory used by the row, counting the
+    /// [`Cell`]s themselves plus the contents of any strings they hold.
+    pub fn estimated_size(&self) -> usize {
+        std::mem::size_of::<Row>()
+            + self
+                .cells
+                .iter()
+                .map(|cell| match cell {
+                    Cell::String(val) => std::mem::size_of::<Cell>() + val.len(),
+                    _ => std::mem::size_of::<Cell>(),
+                })
+                .sum::<usize>()
+    }
 }
 
 /// Test Row::join for a row with an OptInt64 Col.
@@ -82,9 +356,10 @@ fn test_row_join_with_opt() {
             Cell::OptInt64(Some(15)),
             Cell::OptInt64(Some(-15)),
             Cell::OptInt64(None),
+            Cell::Null,
         ],
     };
-    assert_eq!(row.join(), String::from("Hello,World,15,-15,15,-15,"))
+    assert_eq!(row.join(), String::from("Hello,World,15,-15,15,-15,,"))
 }
 
 impl Display for Row {
@@ -93,17 +368,220 @@ impl Display for Row {
     }
 }
 
+/// The type of the values of a column of a [`Table`], not counting its missing values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataType {
+    /// The column has no values, e.g. all of them are missing or the table has no rows.
+    Empty,
+    /// Every value is an integer, i.e., a [`Cell::Int64`] or a [`Cell::OptInt64`].
+    Integer,
+    /// Every value is a number, and some are decimal numbers, i.e., [`Cell::Float64`].
+    Decimal,
+    /// Every value is a [`Cell::String`].
+    String,
+    /// The values are of several of the other types, e.g. numbers and strings.
+    Mixed,
+}
+
+impl Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DataType::Empty => "empty",
+            DataType::Integer => "integer",
+            DataType::Decimal => "decimal",
+            DataType::String => "string",
+            DataType::Mixed => "mixed",
+        })
+    }
+}
+
+impl DataType {
+    /// Returns the type of a single `value`, which is [`DataType::Empty`] if it is missing.
+    pub fn of(value: &Cell) -> DataType {
+        match value {
+            Cell::OptInt64(None) | Cell::Null => DataType::Empty,
+            Cell::Int64(_) | Cell::OptInt64(Some(_)) => DataType::Integer,
+            Cell::Float64(_) => DataType::Decimal,
+            Cell::String(_) => DataType::String,
+        }
+    }
+
+    /// Returns the type of a column holding the values of both types, e.g. a decimal column for
+    /// integers and decimal numbers.
+    pub fn merge(self, other: DataType) -> DataType {
+        match (self, other) {
+            (DataType::Empty, other) | (other, DataType::Empty) => other,
+            (left, right) if left == right => left,
+            (DataType::Integer | DataType::Decimal, DataType::Integer | DataType::Decimal) => {
+                DataType::Decimal
+            }
+            _ => DataType::Mixed,
+        }
+    }
+
+    /// Returns `true` if the values are numbers, i.e., the type is [`DataType::Integer`] or
+    /// [`DataType::Decimal`].
+    pub fn is_numeric(self) -> bool {
+        matches!(self, DataType::Integer | DataType::Decimal)
+    }
+}
+
+/// Describes a column of a [`Table`]: its name and the type of its values.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    /// The name of the column, as in the `header` of the table.
+    pub name: String,
+    /// The type of the values of the column.
+    pub data_type: DataType,
+    /// Whether the column may hold missing values. Columns of [`Cell::OptInt64`]s, like the
+    /// `Capital` column of `country.csv`, always may, even if none of their values is missing.
+    pub nullable: bool,
+}
+
+impl ColumnSchema {
+    /// Creates the schema of a column named `name` without any values yet, e.g. to account for
+    /// the values of a column computed one row at a time with [`ColumnSchema::observe`].
+    pub fn empty(name: &str) -> ColumnSchema {
+        ColumnSchema {
+            name: name.to_string(),
+            data_type: DataType::Empty,
+            nullable: false,
+        }
+    }
+
+    /// Infers the schema of a column named `name` from its `values`.
+    pub fn infer<'a>(name: &str, values: impl IntoIterator<Item = &'a Cell>) -> ColumnSchema {
+        let mut schema = ColumnSchema::empty(name);
+        for value in values {
+            schema.observe(value);
+        }
+        schema
+    }
+
+    /// Accounts for one more `value` of the column in its type.
+    pub fn observe(&mut self, value: &Cell) {
+        self.data_type = self.data_type.merge(DataType::of(value));
+        self.nullable |= matches!(value, Cell::OptInt64(_) | Cell::Null);
+    }
+
+    /// Returns the schema of the column renamed to `name`.
+    pub fn renamed(&self, name: &str) -> ColumnSchema {
+        ColumnSchema {
+            name: name.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns `true` if the column is numeric, i.e., all its values are numbers and none is
+    /// missing. Only numeric columns can be aggregated by SUM and AVG.
+    pub fn is_numeric(&self) -> bool {
+        self.data_type.is_numeric() && !self.nullable
+    }
+
+    /// Returns `true` if the rows can be sorted on the column by ORDERBY, i.e., all its values
+    /// are numbers or missing. Unlike [`ColumnSchema::is_numeric`], this holds for columns of
+    /// numbers with missing values, like the `Capital` column of `country.csv`.
+    pub fn is_sortable(&self) -> bool {
+        matches!(
+            self.data_type,
+            DataType::Empty | DataType::Integer | DataType::Decimal
+        )
+    }
+}
+
+#[test]
+fn test_column_schema_infer() {
+    let infer = |values: &[Cell]| {
+        let schema = ColumnSchema::infer("c", values);
+        (schema.data_type, schema.nullable)
+    };
+    assert_eq!(
+        infer(&[Cell::Int64(1), Cell::Int64(2)]),
+        (DataType::Integer, false)
+    );
+    assert_eq!(
+        infer(&[Cell::OptInt64(Some(1)), Cell::Float64(1.5)]),
+        (DataType::Decimal, true)
+    );
+    assert_eq!(
+        infer(&[Cell::String("a".to_string()), Cell::Null]),
+        (DataType::String, true)
+    );
+    assert_eq!(
+        infer(&[Cell::String("a".to_string()), Cell::Int64(1)]),
+        (DataType::Mixed, false)
+    );
+    assert_eq!(infer(&[Cell::Null]), (DataType::Empty, true));
+    assert_eq!(infer(&[]), (DataType::Empty, false));
+
+    let capital = ColumnSchema::infer("Capital", &[Cell::OptInt64(Some(1))]);
+    assert!(!capital.is_numeric());
+    assert!(capital.is_sortable());
+    assert_eq!(capital.renamed("Cap").name, "Cap");
+    assert!(ColumnSchema::infer("Pop", &[Cell::Float64(0.5)]).is_numeric());
+    assert!(!ColumnSchema::infer("Name", &[Cell::String("a".to_string())]).is_sortable());
+    assert_eq!(DataType::Mixed.to_string(), "mixed");
+}
+
 /// Type used to represent the data being queried.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Table {
     /// The names of the columns in each row.
     pub header: Vec<String>,
-    /// Extra book keeping to remember which rows in the table contain numeric values.
-    /// Primarily used to quickly figure out which rows in a table the ORDERBY operation can be
-    /// performed on.
-    pub numeric_columns: Vec<String>,
+    /// The schema of each column in the `header`, in the same order. Used e.g. to check that
+    /// ORDERBY sorts on a column of numbers, and to right-align the columns of numbers when
+    /// printed.
+    pub schema: Vec<ColumnSchema>,
     /// The actual data in the column. Each [`Row`] has 1 [`Cell`] per entry in the `header`.
     pub rows: Vec<Row>,
+    /// The qualified name of each column in the `header`, i.e., the name of the dataset it was read
+    /// from and its name there, e.g. `city.CityName`, if it was read from one. Lets the columns of
+    /// joined datasets be told apart, even once JOIN renamed them. Columns past the end of the
+    /// list, e.g. of a table computed by GROUPBY, have none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub qualified_names: Vec<Option<String>>,
+}
+
+/// Test that rows can be told apart and hashed by their cells, and that tables compare equal if
+/// all their fields do.
+#[test]
+fn test_row_and_table_eq() {
+    let row = |cells: &[i64]| Row {
+        cells: cells.iter().copied().map(Cell::Int64).collect(),
+    };
+    let rows: std::collections::HashSet<Row> = [row(&[1, 2]), row(&[1, 2]), row(&[2, 1])]
+        .into_iter()
+        .collect();
+    assert_eq!(rows.len(), 2);
+    let table = Table::new(vec!["a".to_string(), "b".to_string()], vec![row(&[1, 2])]);
+    assert_eq!(table.numeric_columns(), vec!["a", "b"]);
+    assert_eq!(table, table.clone());
+    assert_ne!(
+        table,
+        Table {
+            schema: vec![],
+            ..table.clone()
+        }
+    );
+}
+
+/// Test that a table is serialized with its header, its schema and the list of the cells of each
+/// row.
+#[test]
+fn test_table_serde() {
+    let table = Table::new(
+        vec!["Planet".to_string(), "Moons".to_string()],
+        vec![Row {
+            cells: vec![Cell::String("Mars".to_string()), Cell::Int64(2)],
+        }],
+    );
+    let json = serde_json::to_string(&table).unwrap();
+    assert_eq!(
+        json,
+        r#"{"header":["Planet","Moons"],"schema":[{"name":"Planet","data_type":"string","nullable":false},{"name":"Moons","data_type":"integer","nullable":false}],"rows":[["Mars",2]]}"#
+    );
+    assert_eq!(serde_json::from_str::<Table>(&json).unwrap(), table);
 }
 
 impl Display for Table {
@@ -119,6 +597,80 @@ impl Display for Table {
 }
 
 impl Table {
+    /// Creates a table holding the `rows`, whose columns are named by the `header`, inferring the
+    /// schema of each column from its values. The columns have no qualified names.
+    pub fn new(header: Vec<String>, rows: Vec<Row>) -> Table {
+        let schema = header
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                ColumnSchema::infer(name, rows.iter().map(|row| &row.cells[index]))
+            })
+            .collect();
+        Table {
+            header,
+            schema,
+            rows,
+            qualified_names: vec![],
+        }
+    }
+
+    /// Returns the names of the numeric columns, in order. See [`ColumnSchema::is_numeric`].
+    pub fn numeric_columns(&self) -> Vec<String> {
+        self.schema
+            .iter()
+            .filter(|column| column.is_numeric())
+            .map(|column| column.name.clone())
+            .collect()
+    }
+
+    /// Returns a rough estimate of the number of bytes of memory used by the table, counting the
+    /// [`Row`]s and [`Cell`]s themselves plus the contents of any strings they hold.
+    pub fn estimated_size(&self) -> usize {
+        let header_size: usize = self
+            .header
+            .iter()
+            .chain(self.schema.iter().map(|column| &column.name))
+            .map(|name| std::mem::size_of::<String>() + name.len())
+            .sum();
+        let rows_size: usize = self.rows.iter().map(Row::estimated_size).sum();
+        std::mem::size_of::<Table>() + header_size + rows_size
+    }
+
+    /// Writes the table to the file at `path` as CSV, with the header on the first line. Values
+    /// are quoted where needed, so the file can be loaded back with FROM.
+    ///
+    /// # Returns:
+    /// An error propagated from the [`csv`] crate if the file can't be created or written.
+    pub fn write_csv(&self, path: &str) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(&self.header)?;
+        for row in &self.rows {
+            writer.write_record(row.cells.iter().map(|cell| cell.to_string()))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the table to the file at `path` in the Arrow IPC file format, also known as Feather
+    /// v2, so it can be read by tools such as pandas or polars with the column types intact. See
+    /// [`RecordBatch::try_from`] for how the columns are typed.
+    ///
+    /// # Returns:
+    /// An error propagated from the [`arrow`] crate if the file can't be created or written.
+    pub fn write_arrow(&self, path: &str) -> Result<(), ArrowError> {
+        let batch = RecordBatch::try_from(self)?;
+        let mut writer = FileWriter::try_new(std::fs::File::create(path)?, &batch.schema())?;
+        writer.write(&batch)?;
+        writer.finish()
+    }
+
+    /// Renders the table as text with aligned columns, without colors. See
+    /// [`crate::render::render_table`].
+    pub fn to_aligned_string(&self) -> String {
+        crate::render::render_table(self, false)
+    }
+
     /// Returns the index into the `header` field that corresponds to the first occurrence of
     /// 'name'.
     ///
@@ -126,34 +678,29 @@ impl Table {
     /// 'name' : The name of the column whose index is to be returned.
     ///
     /// # Returns:
-    /// [`Some(usize)`] for the index of the first occurrence of `name` in the `header` field.
+    /// `Some(usize)` for the index of the first occurrence of `name` in the `header` field.
     /// [`None`] if `name` is not found in the `header` field.
     pub fn find_column_index_by_name(&self, name: &str) -> Option<usize> {
-        match self
-            .header
+        self.header
             .iter()
             .enumerate()
             .find(|(_, col_name)| *col_name == name)
-        {
-            Some((index, _)) => Some(index),
-            None => None,
-        }
+            .map(|(index, _)| index)
     }
 }
 
 /// Test find_column_index_by_name for names that do exist in the table.
 #[test]
 fn test_find_column_index_by_name_exists() {
-    let table = Table {
-        header: vec![
+    let table = Table::new(
+        vec![
             "H1".to_string(),
             "H2".to_string(),
             "H3".to_string(),
             "H4".to_string(),
         ],
-        numeric_columns: vec![],
-        rows: vec![],
-    };
+        vec![],
+    );
     assert_eq!(table.find_column_index_by_name("H1"), Some(0));
     assert_eq!(table.find_column_index_by_name("H2"), Some(1));
     assert_eq!(table.find_column_index_by_name("H3"), Some(2));
@@ -163,16 +710,15 @@ fn test_find_column_index_by_name_exists() {
 /// Test find_column_index_by_name for names that do not exist in the table.
 #[test]
 fn test_find_column_index_by_name_does_not_exist() {
-    let table = Table {
-        header: vec![
+    let table = Table::new(
+        vec![
             "H1".to_string(),
             "H2".to_string(),
             "H3".to_string(),
             "H4".to_string(),
         ],
-        numeric_columns: vec![],
-        rows: vec![],
-    };
+        vec![],
+    );
     assert_eq!(table.find_column_index_by_name("H"), None);
     assert_eq!(table.find_column_index_by_name("H12"), None);
     assert_eq!(table.find_column_index_by_name("H31"), None);
@@ -182,18 +728,371 @@ fn test_find_column_index_by_name_does_not_exist() {
 /// Test find_column_index_by_name for names that exist in a table with duplicate header entries.
 #[test]
 fn test_find_column_index_by_name_duplicates() {
-    let table = Table {
-        header: vec![
+    let table = Table::new(
+        vec![
             "H1".to_string(),
             "H2".to_string(),
             "H1".to_string(),
             "H2".to_string(),
         ],
-        numeric_columns: vec![],
-        rows: vec![],
-    };
+        vec![],
+    );
     assert_eq!(table.find_column_index_by_name("H1"), Some(0));
     assert_eq!(table.find_column_index_by_name("H2"), Some(1));
     assert_eq!(table.find_column_index_by_name("H1"), Some(0));
     assert_eq!(table.find_column_index_by_name("H2"), Some(1));
 }
+
+/// Test Table::estimated_size grows with the number of rows and the length of strings.
+#[test]
+fn test_table_estimated_size() {
+    let empty = Table::new(vec!["H1".to_string()], vec![]);
+    let short = Table {
+        rows: vec![Row {
+            cells: vec![Cell::String("a".to_string())],
+        }],
+        ..empty.clone()
+    };
+    let long = Table {
+        rows: vec![Row {
+            cells: vec![Cell::String("abcdefghij".to_string())],
+        }],
+        ..empty.clone()
+    };
+    assert!(empty.estimated_size() < short.estimated_size());
+    assert_eq!(short.estimated_size() + 9, long.estimated_size());
+}
+
+/// Formats a number of `bytes` for display, e.g. "512 bytes" or "1.5 MB", where a KB is 1024
+/// bytes.
+pub fn format_bytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let units = ["KB", "MB", "GB"];
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(format_bytes(0), "0 bytes");
+    assert_eq!(format_bytes(1023), "1023 bytes");
+    assert_eq!(format_bytes(1536), "1.5 KB");
+    assert_eq!(format_bytes(100 * 1024 * 1024), "100.0 MB");
+    assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 * 1024), "3072.0 GB");
+}
+
+/// Test Table::to_aligned_string pads every column to its widest value.
+#[test]
+fn test_table_to_aligned_string() {
+    let table = Table::new(
+        vec![
+            "CityName".to_string(),
+            "CityPop".to_string(),
+            "Capital".to_string(),
+        ],
+        vec![
+            Row {
+                cells: vec![
+                    Cell::String("Den_Haag".to_string()),
+                    Cell::Int64(440900),
+                    Cell::OptInt64(None),
+                ],
+            },
+            Row {
+                cells: vec![
+                    Cell::String("Amsterdam".to_string()),
+                    Cell::Int64(731200),
+                    Cell::String("Yes".to_string()),
+                ],
+            },
+        ],
+    );
+    assert_eq!(
+        table.to_aligned_string(),
+        "CityName  | CityPop | Capital\n\
+         ----------+---------+--------\n\
+         Den_Haag  |  440900 |\n\
+         Amsterdam |  731200 | Yes\n"
+    );
+}
+
+/// Test Table::to_aligned_string for a table without rows.
+#[test]
+fn test_table_to_aligned_string_empty() {
+    let table = Table::new(vec!["H1".to_string(), "Header2".to_string()], vec![]);
+    assert_eq!(table.to_aligned_string(), "H1 | Header2\n---+--------\n");
+}
+
+/// Test Table::write_csv quotes values that contain commas and writes missing values as empty.
+#[test]
+fn test_table_write_csv() {
+    let path = std::env::temp_dir().join("toy-query-engine-write-csv.csv");
+    let table = Table::new(
+        vec!["Name".to_string(), "Capital".to_string()],
+        vec![
+            Row {
+                cells: vec![
+                    Cell::String("Korea, South".to_string()),
+                    Cell::OptInt64(Some(2331)),
+                ],
+            },
+            Row {
+                cells: vec![Cell::String("Antarctica".to_string()), Cell::OptInt64(None)],
+            },
+        ],
+    );
+    table.write_csv(path.to_str().unwrap()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "Name,Capital\n\"Korea, South\",2331\nAntarctica,\n"
+    );
+    assert!(table.write_csv("/nonexistent/directory/out.csv").is_err());
+}
+
+/// Converts a table to an Arrow [`RecordBatch`] with one nullable array per column. A column of
+/// integers becomes an `Int64` array, a column of numbers with some decimals a `Float64` array, and
+/// any other column, including one mixing strings and numbers, a `Utf8` array of the values as
+/// they are printed. Missing values become nulls.
+impl TryFrom<&Table> for RecordBatch {
+    /// An error propagated from the [`arrow`] crate if the arrays don't form a valid batch.
+    type Error = ArrowError;
+
+    fn try_from(table: &Table) -> Result<RecordBatch, ArrowError> {
+        let (fields, columns): (Vec<Field>, Vec<ArrayRef>) = table
+            .header
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let cells = || table.rows.iter().map(move |row| &row.cells[index]);
+                let column: ArrayRef = match table.schema[index].data_type {
+                    DataType::Integer => {
+                        Arc::new(Int64Array::from_iter(cells().map(|cell| match cell {
+                            Cell::Int64(val) | Cell::OptInt64(Some(val)) => Some(*val),
+                            _ => None,
+                        })))
+                    }
+                    DataType::Decimal => {
+                        Arc::new(Float64Array::from_iter(cells().map(Cell::as_f64)))
+                    }
+                    DataType::Empty | DataType::String | DataType::Mixed => Arc::new(
+                        StringArray::from_iter(cells().map(|cell| match cell.is_null() {
+                            true => None,
+                            false => Some(cell.to_string()),
+                        })),
+                    ),
+                };
+                (Field::new(name, column.data_type().clone(), true), column)
+            })
+            .unzip();
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+    }
+}
+
+/// Converts an Arrow [`RecordBatch`] to a table, with the columns typed like those of a Parquet
+/// file (see [`crate::data::load_parquet`]). Arrays of integers become integer columns, arrays of
+/// floating point or decimal numbers become decimal columns, and arrays of any other type that
+/// can be printed, e.g. strings, booleans, dates or lists, become string columns. Nulls become missing
+/// values.
+impl TryFrom<&RecordBatch> for Table {
+    /// An error propagated from the [`arrow`] crate if an array can't be converted, e.g. it holds
+    /// an unsigned integer too large for a [`Cell::Int64`].
+    type Error = ArrowError;
+
+    fn try_from(batch: &RecordBatch) -> Result<Table, ArrowError> {
+        let options = CastOptions {
+            safe: false,
+            ..CastOptions::default()
+        };
+        let mut records: Vec<Vec<Option<Cell>>> = vec![vec![]; batch.num_rows()];
+        for column in batch.columns() {
+            let data_type = column.data_type();
+            if data_type.is_integer() {
+                let column = cast_with_options(column, &ArrowType::Int64, &options)?;
+                for (record, value) in records.iter_mut().zip(column.as_primitive::<Int64Type>()) {
+                    record.push(value.map(Cell::Int64));
+                }
+            } else if data_type.is_floating() || matches!(data_type, ArrowType::Decimal128(..)) {
+                let column = cast_with_options(column, &ArrowType::Float64, &options)?;
+                for (record, value) in records.iter_mut().zip(column.as_primitive::<Float64Type>())
+                {
+                    record.push(value.map(Cell::Float64));
+                }
+            } else if data_type.is_null() {
+                records.iter_mut().for_each(|record| record.push(None));
+            } else {
+                let column = cast_with_options(column, &ArrowType::Utf8, &options)?;
+                for (record, value) in records.iter_mut().zip(column.as_string::<i32>()) {
+                    record.push(value.map(|value| Cell::String(value.to_string())));
+                }
+            }
+        }
+        let header = batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect();
+        Ok(typed_table(header, records))
+    }
+}
+
+#[test]
+fn test_write_arrow() {
+    use arrow::array::Array;
+    use arrow::ipc::reader::FileReader;
+
+    let table = Table::new(
+        ["Name", "Pop", "Share", "Code"]
+            .map(str::to_string)
+            .to_vec(),
+        vec![
+            Row {
+                cells: vec![
+                    Cell::String("Aruba".to_string()),
+                    Cell::Int64(103000),
+                    Cell::Int64(1),
+                    Cell::Int64(533),
+                ],
+            },
+            Row {
+                cells: vec![
+                    Cell::Null,
+                    Cell::OptInt64(None),
+                    Cell::Float64(0.5),
+                    Cell::String("ABW".to_string()),
+                ],
+            },
+        ],
+    );
+    let path = std::env::temp_dir().join("toy-query-engine-write.arrow");
+    let path = path.to_str().unwrap();
+    table.write_arrow(path).unwrap();
+
+    let mut reader = FileReader::try_new(std::fs::File::open(path).unwrap(), None).unwrap();
+    let batch = reader.next().unwrap().unwrap();
+    assert!(reader.next().is_none());
+    let types: Vec<&ArrowType> = batch
+        .schema_ref()
+        .fields()
+        .iter()
+        .map(|field| field.data_type())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            &ArrowType::Utf8,
+            &ArrowType::Int64,
+            &ArrowType::Float64,
+            &ArrowType::Utf8
+        ]
+    );
+    assert_eq!(batch.num_rows(), 2);
+    let pop = batch
+        .column(1)
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap();
+    assert_eq!((pop.value(0), pop.is_null(1)), (103000, true));
+    let share = batch
+        .column(2)
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert_eq!((share.value(0), share.value(1)), (1.0, 0.5));
+    let code = batch
+        .column(3)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!((code.value(0), code.value(1)), ("533", "ABW"));
+    assert!(batch.column(0).is_null(1));
+
+    assert!(table
+        .write_arrow("/nonexistent/directory/out.arrow")
+        .is_err());
+}
+
+#[test]
+fn test_record_batch_round_trip() {
+    let table = crate::data::load_csv("data/country.csv").unwrap();
+    let batch = RecordBatch::try_from(&table).unwrap();
+    assert_eq!(batch.num_rows(), table.rows.len());
+    assert_eq!(batch.num_columns(), table.header.len());
+    let converted = Table::try_from(&batch).unwrap();
+    assert_eq!(converted.header, table.header);
+    assert_eq!(converted.schema, table.schema);
+    assert_eq!(converted.rows, table.rows);
+}
+
+#[test]
+fn test_table_from_record_batch() {
+    use arrow::array::{BooleanArray, Decimal128Array, ListArray, UInt64Array};
+    use arrow::datatypes::Int32Type;
+
+    let columns: Vec<(&str, ArrayRef)> = vec![
+        (
+            "small",
+            Arc::new(arrow::array::Int8Array::from(vec![Some(1), None])),
+        ),
+        ("big", Arc::new(UInt64Array::from(vec![7, 8]))),
+        (
+            "share",
+            Arc::new(arrow::array::Float32Array::from(vec![0.5, 1.0])),
+        ),
+        (
+            "price",
+            Arc::new(
+                Decimal128Array::from(vec![1250, 99])
+                    .with_precision_and_scale(10, 2)
+                    .unwrap(),
+            ),
+        ),
+        ("flag", Arc::new(BooleanArray::from(vec![Some(true), None]))),
+    ];
+    let batch = RecordBatch::try_from_iter(columns).unwrap();
+    let table = Table::try_from(&batch).unwrap();
+    assert_eq!(table.header, vec!["small", "big", "share", "price", "flag"]);
+    assert_eq!(table.numeric_columns(), vec!["big", "share", "price"]);
+    assert_eq!(
+        table.rows[0].cells,
+        vec![
+            Cell::OptInt64(Some(1)),
+            Cell::Int64(7),
+            Cell::Float64(0.5),
+            Cell::Float64(12.5),
+            Cell::String("true".to_string()),
+        ]
+    );
+    assert_eq!(
+        table.rows[1].cells,
+        vec![
+            Cell::OptInt64(None),
+            Cell::Int64(8),
+            Cell::Float64(1.0),
+            Cell::Float64(0.99),
+            Cell::Null,
+        ]
+    );
+
+    // Unsigned integers too large for a Cell::Int64 can't be converted.
+    let too_big = RecordBatch::try_from_iter(vec![(
+        "big",
+        Arc::new(UInt64Array::from(vec![u64::MAX])) as ArrayRef,
+    )])
+    .unwrap();
+    assert!(Table::try_from(&too_big).is_err());
+    // Nested arrays are printed like strings.
+    let list = ListArray::from_iter_primitive::<Int32Type, _, _>(vec![Some(vec![Some(1), None])]);
+    let nested = RecordBatch::try_from_iter(vec![("list", Arc::new(list) as ArrayRef)]).unwrap();
+    assert_eq!(
+        Table::try_from(&nested).unwrap().rows[0].cells,
+        vec![Cell::String("[1, ]".to_string())]
+    );
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// How often `WATCH` checks whether the files read by its query changed.
+pub const C_WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Returns the time each file in `paths` was last modified, or `None` for a file that doesn't
+/// exist or can't be read, e.g. while it is being replaced.
+pub fn modification_times(paths: &[String]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// Waits until at least one of the files in `paths` is modified, checking their modification
+/// times every `interval`. A file that appears or disappears counts as modified.
+///
+/// # Arguments
+/// `paths` : The files to watch.
+/// `modified` : The modification times of the files when they were last checked, as returned by
+/// [`modification_times`]. Updated to the new times once a change is found.
+/// `interval` : How long to wait between two checks.
+/// `cancelled` : The flag set when the user presses Ctrl-C, which stops waiting.
+///
+/// # Returns
+/// The paths of the files that changed, in the order of `paths`, or `None` if waiting was
+/// cancelled first.
+pub fn wait_for_change(
+    paths: &[String],
+    modified: &mut Vec<Option<SystemTime>>,
+    interval: Duration,
+    cancelled: &AtomicBool,
+) -> Option<Vec<String>> {
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let current = modification_times(paths);
+        let changed: Vec<String> = paths
+            .iter()
+            .zip(current.iter().zip(modified.iter()))
+            .filter(|(_, (current, previous))| current != previous)
+            .map(|(path, _)| path.clone())
+            .collect();
+        if !changed.is_empty() {
+            *modified = current;
+            return Some(changed);
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+#[test]
+fn test_wait_for_change() {
+    let path = std::env::temp_dir().join("toy-query-engine-watch.csv");
+    std::fs::write(&path, "Status\nok\n").unwrap();
+    let paths = vec![
+        path.to_string_lossy().to_string(),
+        "no/such/file.csv".to_string(),
+    ];
+    let mut modified = modification_times(&paths);
+    assert!(modified[0].is_some());
+    assert_eq!(modified[1], None);
+
+    // Nothing changed, so only cancelling stops waiting.
+    let cancelled = AtomicBool::new(true);
+    assert_eq!(
+        wait_for_change(&paths, &mut modified, Duration::ZERO, &cancelled),
+        None
+    );
+
+    let file = std::fs::File::options().append(true).open(&path).unwrap();
+    let later = modified[0].unwrap() + Duration::from_secs(60);
+    file.set_modified(later).unwrap();
+    let cancelled = AtomicBool::new(false);
+    assert_eq!(
+        wait_for_change(&paths, &mut modified, Duration::ZERO, &cancelled),
+        Some(vec![paths[0].clone()])
+    );
+    assert_eq!(modified, vec![Some(later), None]);
+    std::fs::remove_file(&path).unwrap();
+}
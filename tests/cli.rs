@@ -6,7 +6,7 @@ fn test_exit_cmd() {
         .unwrap()
         .write_stdin("exit\n")
         .assert()
-        .stdout("Toy Query Engine v0.1\nEnter your query, or 'help' for more information or 'exit' to exit.\nGoodbye!\n");
+        .stdout("");
 }
 
 #[test]
@@ -15,7 +15,7 @@ fn test_help_cmd() {
         .unwrap()
         .write_stdin("help\nexit\n")
         .assert()
-        .stdout("Toy Query Engine v0.1\nEnter your query, or \'help\' for more information or \'exit\' to exit.\nAvailable Commands: \n\n      FROM <dataset> - Loads the `dataset`. \n\n          Maybe chained with other commands. Must always be the first command in a chain.\n\n          If no other command is specified, will print the `dataset`. \n\n      SELECT <column-name> - used to select particular columns from the specified dataset. \n\n          See the Datasets section below for a list of column-names for each dataset. \n\n      TAKE <number> - Specifies the number of rows to print from the dataset. \n\n          <number> must be greater than or equal to 0. \n\n      ORDERBY <numeric-column-name> - Sorts the loaded dataset by the column-name in descending order, if the column contains numeric values. \n\n          See the Datasets section below for a list of acceptable values for <numeric-column-name> for each dataset. \n\n      COUNTBY <column-name> - Returns the . \n\n          <number> must be greater than or equal to 0. \n\n      JOIN <dataset> <column-name> - performs a join on the current dataset and the one specified in this command on <column-name>. \n\n          See the Datasets section below for a list of available datasets and the column-names for each dataset. \n\n          The provided <column-name> must be present in both datasets. \n\n    \n\n    Available Datasets\n\n      <dataset> : city.csv\n\n          <column-name> : [CityID, CityName, CountryCode, CityPop]\n\n          <numeric-column-name> : [CityID, CityPop]\n\n      <dataset> : country.csv\n\n          <column-name> : [CountryCode, CountryName, Continent, CountryPop, Capital]\n\n          <numeric-column-name> : [CountryPop, Capital]\n\n      <dataset> : language.csv\n\n          <column-name> : [CountryCode,Language]\n\n          <numeric-column-name> : []\n\nGoodbye!\n");
+        .stdout("Available Commands:\n  FROM <dataset> - Loads the <dataset>.\n  SELECT <column-name>[,<column-name>...] | * [EXCEPT <column-name>[,<column-name>...]] - Selects particular columns from the input table.\n  EXTEND <name> = <expression>[,<name> = <expression>...] - Appends columns computed from the columns of the input table.\n  TAKE <number> - Returns the first <number> rows of the input table.\n  SKIP <number> - Drops the first <number> rows of the input table and returns the rest.\n  TAIL <number> - Returns the last <number> rows of the input table.\n  SAMPLE <number>|<percent>% [SEED <number>] - Returns a random subset of the rows of the input table.\n  ORDERBY <numeric-column-name> [ASC|DESC] [NULLS FIRST|LAST] - Sorts the input table by the column, in descending order unless ASC is given.\n  COUNTBY <column-name> [ORDER count|value] [BARS] [HAVING <condition>] - Counts the number of times each value of the column appears in the input table.\n  SUM <column-name> - Adds up the values of the column, producing a single row.\n  AVG <column-name> - Averages the values of the column, producing a single row.\n  MIN <column-name> - Finds the smallest value of the column, producing a single row.\n  MAX <column-name> - Finds the largest value of the column, producing a single row.\n  COUNT [<column-name>|*] - Counts the values of the column, or the rows if no column is given, producing a single row.\n  GROUPBY <column-name> <AGG(column-name)>[,<AGG(column-name)>...] [HAVING <condition>] - Produces one row per value of the column, with the aggregates of each group.\n  PIVOT <row-column-name> <column-column-name> <AGG(column-name)> - Cross-tabulates the input table, with the aggregate of each pair of values.\n  JOIN <dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE] - Joins the input table with the <dataset> on the column.\n  LEFTJOIN <dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE] - Like JOIN, but also keeps the rows of the input table without a match.\n  RIGHTJOIN <dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE] - Like JOIN, but also keeps the rows of the <dataset> without a match.\n  OUTERJOIN <dataset> [ON <column-name>=]<column-name> [USING HASH|MERGE] - Like JOIN, but also keeps the rows of both tables without a match.\n  UNION [ALL] (<query>) - Appends the rows of the <query> to the input table, removing duplicate rows unless ALL is given.\n  FILTER <column-name> <comparison> <value> - Keeps the rows of the input table for which the comparison holds.\n  DISTINCT [<comma-separated-column-names>] - Removes duplicate rows from the input table, keeping the first of each.\n  RENAME <column-name> <new-column-name> - Renames a column of the input table.\n  REORDER <comma-separated-column-names> - Moves columns of the input table to the front, in the given order.\n  CAST <column-name> AS INT|FLOAT|STRING - Converts the values of a column of the input table to another type.\n  FILLNA <column-name> <value> - Replaces the missing values of a column of the input table with the <value>.\n  TOPN <number> BY <column-name> ORDER <numeric-column-name> [ASC|DESC] - Keeps the top rows of each group of rows sharing a value of the BY column.\n  RANK BY <numeric-column-name> [PER <column-name>] - Adds a `rank` column ranking the rows of the input table by the column.\n  WITH <name> AS (<query>)[, <name> AS (<query>)...] <query> - Names the results of sub-queries so FROM and JOIN in the <query> can use them.\n  MAP <script> - Transforms every row of the input table with the rhai <script>.\n  INTO <path> - Writes the input table to the file at <path> as CSV instead of printing it.\nUse 'help <OPERATOR>' for more details about an operator.\nUse 'EXPLAIN <query>' to show how a query would be evaluated without running it.\nUse 'EXPLAIN ANALYZE <query>' to run a query and show the rows produced and time spent by each operator.\nUse 'WATCH <query>' to run a query again each time a file it reads changes, until Ctrl-C is pressed.\nUse 'LOAD <name> FROM <path> [ENCODING <label>]' to name a CSV or Parquet file, so FROM and JOIN can refer to it, optionally reading it in another encoding than UTF-8, e.g. latin1.\nUse 'ATTACH <path>' to make the tables of a SQLite database datasets that FROM and JOIN can refer to by name.\nUse 'SET <name> = <query>' to save the result of a query, so FROM and JOIN can refer to it as @<name>.\nUse 'INSERT INTO <dataset> VALUES (<value>, ...)' or 'DELETE FROM <dataset> WHERE <condition>' to edit a dataset in memory, leaving its file untouched.\nUse 'DESCRIBE <dataset>' to list the type, missing and distinct values, range, and samples of each column.\nUse 'SHOW TABLES' to list the datasets and saved results FROM and JOIN can refer to, and 'SHOW COLUMNS <dataset>' to list the names and types of its columns.\nUse 'SAVE <dataset> [<path>]' to write a dataset, with its edits, to its CSV file or the one at <path>.\nUse '\\history' to list the queries of the session, and '\\rerun <number>' to run one of them again.\nUse '\\save <path>' to write the settings, registered datasets, and saved results of the session to a TOML file, and '\\load <path>' to restore them later.\nSeparate queries with ';' to run several of them one after the other, e.g. 'FROM city TAKE 3; FROM country TAKE 3'.\n\nAvailable Settings:\n  \\limit <number>|off - The maximum number of rows printed for a query that doesn't end with a TAKE. Defaults to 1000.\n  \\format table|csv|jsonl|markdown - How query results are printed: a table with aligned columns, CSV, one JSON object per row, or a Markdown table. Defaults to table.\n  \\timing on|off - Whether the number of rows and the time taken are printed after each query. Defaults to off.\n  \\pagesize <number>|off - The number of rows shown at a time in the terminal, waiting for Enter before showing more. Defaults to off.\n  \\badrows error|skip|null - Whether CSV rows with too few or too many values fail the query, are skipped, or are filled with missing values. Defaults to error.\n  \\normalize trim|underscores|nfc[,...]|off - How CSV values are cleaned up while loading, so values written differently compare equal: trimming the whitespace around them, replacing underscores with spaces, and composing Unicode characters (NFC). Defaults to off.\n  \\prompt <text> - The prompt printed before each line of input in the terminal, quoted to control its spacing. Defaults to 'query> '.\n  \\cache on|off - Whether running a query again returns its previous result while the datasets it reads are unchanged. Defaults to on.\n  \\maxrows <number>|off - The maximum number of rows of a table held in memory while a query runs, e.g. the result of a JOIN. Larger ones stop the query. Defaults to off.\n  \\maxbytes <number>[KB|MB|GB]|off - The maximum estimated memory used by a table held in memory while a query runs. Larger ones stop the query. Defaults to off.\n  \\syntax pipe|sql - Whether queries are chains of operators, e.g. FROM city ORDERBY CityPop, or standard SQL, e.g. SELECT * FROM city ORDER BY CityPop DESC. Defaults to pipe.\n\nAvailable Datasets:\n  <dataset> : city.csv (or city)\n      <column-name> : [CityID, CityName, CountryCode, CityPop]\n      <numeric-column-name> : [CityID, CityPop]\n  <dataset> : country.csv (or country)\n      <column-name> : [CountryCode, CountryName, Continent, CountryPop, Capital]\n      <numeric-column-name> : [CountryPop, Capital]\n  <dataset> : language.csv (or language)\n      <column-name> : [CountryCode, Language]\n      <numeric-column-name> : []\n\n");
 }
 
 #[test]
@@ -24,7 +24,7 @@ fn test_from_take_5_cmd() {
         .unwrap()
         .write_stdin("FROM language.csv TAKE 5\nexit\n")
         .assert()
-        .stdout("Toy Query Engine v0.1\nEnter your query, or \'help\' for more information or \'exit\' to exit.\nCountryCode,Language\nABW,Dutch\nABW,English\nABW,Papiamento\nABW,Spanish\nAFG,Balochi\n\nGoodbye!\n");
+        .stdout("CountryCode | Language\n------------+-----------\nABW         | Dutch\nABW         | English\nABW         | Papiamento\nABW         | Spanish\nAFG         | Balochi\n\n");
 }
 
 #[test]
@@ -33,7 +33,7 @@ fn test_from_take_10_cmd() {
         .unwrap()
         .write_stdin("FROM language.csv TAKE 10\nexit\n")
         .assert()
-        .stdout("Toy Query Engine v0.1\nEnter your query, or \'help\' for more information or \'exit\' to exit.\nCountryCode,Language\nABW,Dutch\nABW,English\nABW,Papiamento\nABW,Spanish\nAFG,Balochi\nAFG,Dari\nAFG,Pashto\nAFG,Turkmenian\nAFG,Uzbek\nAGO,Ambo\n\nGoodbye!\n");
+        .stdout("CountryCode | Language\n------------+-----------\nABW         | Dutch\nABW         | English\nABW         | Papiamento\nABW         | Spanish\nAFG         | Balochi\nAFG         | Dari\nAFG         | Pashto\nAFG         | Turkmenian\nAFG         | Uzbek\nAGO         | Ambo\n\n");
 }
 
 #[test]
@@ -42,7 +42,7 @@ fn test_from_countby_cmd() {
         .unwrap()
         .write_stdin("FROM city.csv ORDERBY CityPop TAKE 10\nexit\n")
         .assert()
-        .stdout("Toy Query Engine v0.1\nEnter your query, or \'help\' for more information or \'exit\' to exit.\nCityID,CityName,CountryCode,CityPop\n1024,Mumbai_(Bombay),IND,10500000\n2331,Seoul,KOR,9981619\n206,Sâ€žo_Paulo,BRA,9968485\n1890,Shanghai,CHN,9696300\n939,Jakarta,IDN,9604900\n2822,Karachi,PAK,9269265\n3357,Istanbul,TUR,8787958\n2515,Ciudad_de_MÃˆxico,MEX,8591309\n3580,Moscow,RUS,8389200\n3793,New_York,USA,8008278\n\nGoodbye!\n");
+        .stdout("CityID | CityName          | CountryCode |  CityPop\n-------+-------------------+-------------+---------\n  1024 | Mumbai_(Bombay)   | IND         | 10500000\n  2331 | Seoul             | KOR         |  9981619\n   206 | Sâ€žo_Paulo       | BRA         |  9968485\n  1890 | Shanghai          | CHN         |  9696300\n   939 | Jakarta           | IDN         |  9604900\n  2822 | Karachi           | PAK         |  9269265\n  3357 | Istanbul          | TUR         |  8787958\n  2515 | Ciudad_de_MÃˆxico | MEX         |  8591309\n  3580 | Moscow            | RUS         |  8389200\n  3793 | New_York          | USA         |  8008278\n\n");
 }
 
 #[test]
@@ -51,5 +51,1060 @@ fn test_join_cmd() {
         .unwrap()
         .write_stdin("FROM city.csv JOIN country.csv CountryCode TAKE 10\nexit\n")
         .assert()
-        .stdout("Toy Query Engine v0.1\nEnter your query, or \'help\' for more information or \'exit\' to exit.\nCityID,CityName,CountryCode,CityPop,CountryName,Continent,CountryPop,Capital\n1,Kabul,AFG,1780000,Afghanistan,Asia,22720000,1\n2,Qandahar,AFG,237500,Afghanistan,Asia,22720000,1\n3,Herat,AFG,186800,Afghanistan,Asia,22720000,1\n4,Mazar-e-Sharif,AFG,127800,Afghanistan,Asia,22720000,1\n5,Amsterdam,NLD,731200,Netherlands,Europe,15864000,5\n6,Rotterdam,NLD,593321,Netherlands,Europe,15864000,5\n7,Haag,NLD,440900,Netherlands,Europe,15864000,5\n8,Utrecht,NLD,234323,Netherlands,Europe,15864000,5\n9,Eindhoven,NLD,201843,Netherlands,Europe,15864000,5\n10,Tilburg,NLD,193238,Netherlands,Europe,15864000,5\n\nGoodbye!\n");
+        .stdout("CityID | CityName       | CountryCode | CityPop | CountryName | Continent | CountryPop | Capital\n-------+----------------+-------------+---------+-------------+-----------+------------+--------\n     1 | Kabul          | AFG         | 1780000 | Afghanistan | Asia      |   22720000 |       1\n     2 | Qandahar       | AFG         |  237500 | Afghanistan | Asia      |   22720000 |       1\n     3 | Herat          | AFG         |  186800 | Afghanistan | Asia      |   22720000 |       1\n     4 | Mazar-e-Sharif | AFG         |  127800 | Afghanistan | Asia      |   22720000 |       1\n     5 | Amsterdam      | NLD         |  731200 | Netherlands | Europe    |   15864000 |       5\n     6 | Rotterdam      | NLD         |  593321 | Netherlands | Europe    |   15864000 |       5\n     7 | Haag           | NLD         |  440900 | Netherlands | Europe    |   15864000 |       5\n     8 | Utrecht        | NLD         |  234323 | Netherlands | Europe    |   15864000 |       5\n     9 | Eindhoven      | NLD         |  201843 | Netherlands | Europe    |   15864000 |       5\n    10 | Tilburg        | NLD         |  193238 | Netherlands | Europe    |   15864000 |       5\n\n");
+}
+
+#[test]
+fn test_default_limit_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("\\limit 3\nFROM language.csv\nexit\n")
+        .assert()
+        .stdout("Default row limit set to 3.\nCountryCode | Language\n------------+-----------\nABW         | Dutch\nABW         | English\nABW         | Papiamento\n\nShowing the first 3 of 984 rows. Add TAKE <number> to the query, or use '\\limit <number>' or '\\limit off' to change this limit.\n");
+}
+
+#[test]
+fn test_map_cmd() {
+    let script = std::env::temp_dir().join("toy-query-engine-cli-map.rhai");
+    std::fs::write(
+        &script,
+        "row.Language = row.Language.to_upper(); row.remove(\"CountryCode\"); row",
+    )
+    .unwrap();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "FROM language.csv MAP {} TAKE 3\nexit\n",
+            script.display()
+        ))
+        .assert()
+        .stdout("Language\n----------\nDUTCH\nENGLISH\nPAPIAMENTO\n\n");
+}
+
+#[test]
+fn test_with_filter_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("WITH euro AS (FROM country.csv FILTER Continent = Europe) FROM city.csv JOIN euro CountryCode WHERE CityPop > 3000000\nexit\n")
+        .assert()
+        .stdout("CityID | CityName      | CountryCode | CityPop | CountryName        | Continent | CountryPop | Capital\n-------+---------------+-------------+---------+--------------------+-----------+------------+--------\n   456 | London        | GBR         | 7285000 | United_Kingdom     | Europe    |   59623400 |     456\n  3068 | Berlin        | DEU         | 3386667 | Germany            | Europe    |   82164700 |    3068\n  3580 | Moscow        | RUS         | 8389200 | Russian_Federation | Europe    |  146934000 |    3580\n  3581 | St_Petersburg | RUS         | 4694000 | Russian_Federation | Europe    |  146934000 |    3580\n\n");
+}
+
+#[test]
+fn test_from_csv_path_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-from.csv");
+    std::fs::write(
+        &path,
+        "Planet,Moons\nMercury,0\nEarth,1\nMars,2\nJupiter,95\n",
+    )
+    .unwrap();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "FROM {} ORDERBY Moons TAKE 2\nexit\n",
+            path.display()
+        ))
+        .assert()
+        .stdout("Planet  | Moons\n--------+------\nJupiter |    95\nMars    |     2\n\n");
+}
+
+#[test]
+fn test_decimal_column_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-decimal.csv");
+    std::fs::write(
+        &path,
+        "Planet,Gravity\nMercury,3.7\nEarth,9.81\nMars,3.72\nJupiter,24.79\n",
+    )
+    .unwrap();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "FROM {} FILTER Gravity < 10 ORDERBY Gravity ASC\nFROM {} MAX Gravity\nexit\n",
+            path.display(),
+            path.display()
+        ))
+        .assert()
+        .stdout("Planet  | Gravity\n--------+--------\nMercury |     3.7\nMars    |    3.72\nEarth   |    9.81\n\nMAX(Gravity)\n------------\n       24.79\n\n");
+}
+
+#[test]
+fn test_select_alias_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv SELECT CityName AS Name,CityPop AS Population ORDERBY Population TAKE 2\nexit\n")
+        .assert()
+        .stdout("Name            | Population\n----------------+-----------\nMumbai_(Bombay) |   10500000\nSeoul           |    9981619\n\n");
+}
+
+#[test]
+fn test_select_string_functions_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv ORDERBY CityPop TAKE 2 SELECT UPPER(CityName),LENGTH(CityName) AS Letters,CityPop\nexit\n")
+        .assert()
+        .stdout("UPPER(CityName) | Letters |  CityPop\n----------------+---------+---------\nMUMBAI_(BOMBAY) |      15 | 10500000\nSEOUL           |       5 |  9981619\n\n");
+}
+
+#[test]
+fn test_select_arithmetic_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv ORDERBY CityPop TAKE 2 SELECT CityName,CityPop/1000 AS PopK,CityPop - 9000000\nexit\n")
+        .assert()
+        .stdout("CityName        |     PopK | CityPop-9000000\n----------------+----------+----------------\nMumbai_(Bombay) |    10500 |         1500000\nSeoul           | 9981.619 |          981619\n\n");
+}
+
+#[test]
+fn test_null_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv WHERE Capital IS NULL SELECT CountryName TAKE 2\nFROM country.csv ORDERBY Capital ASC SKIP 230 SELECT CountryName,Capital TAKE 4\nFROM country.csv WHERE Capital IS NOT NULL COUNT CountryCode\nexit\n")
+        .assert()
+        .stdout("CountryName\n---------------------------\nAntarctica\nFrench_Southern_territories\n\nCountryName                 | Capital\n----------------------------+--------\nZimbabwe                    |    4068\nPalestine                   |    4074\nAntarctica                  |\nFrench_Southern_territories |\n\nCOUNT(CountryCode)\n------------------\n               232\n\n");
+}
+
+#[test]
+fn test_orderby_nulls_first_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv ORDERBY Capital ASC NULLS FIRST SELECT CountryName,Capital TAKE 2\nFROM country.csv ORDERBY Capital NULLS SELECT CountryName\nexit\n")
+        .assert()
+        .stdout("CountryName                 | Capital\n----------------------------+--------\nAntarctica                  |\nFrench_Southern_territories |\n\nMalformed input. ORDERBY ... NULLS must be followed by FIRST or LAST.\n");
+}
+
+#[test]
+fn test_orderby_joined_capital_cmd() {
+    // The Capital column may hold missing values after a JOIN; sorting on it must not panic.
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city JOIN country CountryCode ORDERBY Capital SELECT CityName,Capital TAKE 2\nexit\n")
+        .assert()
+        .success()
+        .stdout("CityName   | Capital\n-----------+--------\nGaza       |    4074\nKhan_Yunis |    4074\n\n");
+}
+
+#[test]
+fn test_pagesize_cmd() {
+    // Results are only paged in a terminal, so piped input is never mistaken for an answer to the
+    // pager.
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("\\pagesize 2\nFROM city.csv SELECT CityName TAKE 3\n\\pagesize off\nexit\n")
+        .assert()
+        .stdout("Page size set to 2 rows.\nCityName\n--------\nKabul\nQandahar\nHerat\n\nPaging disabled.\n");
+}
+
+#[test]
+fn test_rename_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("WITH big AS (FROM city.csv RENAME CityName BigCity RENAME CityPop BigPop RENAME CityID BigID FILTER BigPop > 9000000) FROM city.csv JOIN big CountryCode SELECT CityName,BigCity TAKE 2\nFROM city.csv RENAME CityName CityPop\nexit\n")
+        .assert()
+        .stdout("CityName       | BigCity\n---------------+------------\nSâ€žo_Paulo    | Sâ€žo_Paulo\nRio_de_Janeiro | Sâ€žo_Paulo\n\nCannot RENAME CityName to CityPop, as the table already has a CityPop column.\n");
+}
+
+#[test]
+fn test_reorder_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv REORDER CityPop,CityName TAKE 2\nFROM city.csv REORDER Region\nexit\n")
+        .assert()
+        .stdout("CityPop | CityName | CityID | CountryCode\n--------+----------+--------+------------\n1780000 | Kabul    |      1 | AFG\n 237500 | Qandahar |      2 | AFG\n\nCould not find the Region column to REORDER on the table produced by this operator chain: FROM city.csv\n");
+}
+
+#[test]
+fn test_self_join_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv TAKE 2 JOIN city.csv CountryCode SELECT CityName,CityName_2,CityPop_2 TAKE 3\nexit\n")
+        .assert()
+        .stdout("Warning: CountryCode is not a key column of city.csv; each row may match several rows and multiply the size of the result.\nCityName | CityName_2 | CityPop_2\n---------+------------+----------\nKabul    | Kabul      |   1780000\nKabul    | Qandahar   |    237500\nKabul    | Herat      |    186800\n\n");
+}
+
+#[test]
+fn test_qualified_names_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city JOIN country CountryCode SELECT city.CityName,country.CountryName FILTER country.CountryName = Aruba\nFROM city RENAME CityName Name SELECT city.CityName TAKE 1\nFROM city TAKE 2 JOIN city CountryCode SELECT city.CityName\nexit\n")
+        .assert()
+        .stdout("city.CityName | country.CountryName\n--------------+--------------------\nOranjestad    | Aruba\n\ncity.CityName\n-------------\nKabul\n\nWarning: CountryCode is not a key column of city.csv; each row may match several rows and multiply the size of the result.\nThe city.CityName column to Select is ambiguous, as it may refer to any of the columns [CityName, CityName_2] of the table produced by this operator chain: FROM city.csv TAKE 2 JOIN city.csv CountryCode\n");
+}
+
+#[test]
+fn test_join_subquery_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv JOIN (FROM country.csv SELECT CountryCode,Continent) CountryCode SELECT CityName,Continent TAKE 3\nexit\n")
+        .assert()
+        .stdout("CityName | Continent\n---------+----------\nKabul    | Asia\nQandahar | Asia\nHerat    | Asia\n\n");
+}
+
+#[test]
+fn test_explain_optimized_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("EXPLAIN FROM city.csv JOIN country.csv CountryCode SELECT CityName,Continent\nexit\n")
+        .assert()
+        .stdout("Operator tree:\n  SELECT CityName,Continent\n    JOIN (subquery) CountryCode\n      (subquery)\n        SELECT CountryCode,Continent\n          FROM country.csv\n      SELECT CityName,CountryCode\n        FROM city.csv\nEvaluation order:\n  1. FROM city.csv - streams its rows\n  2. SELECT CityName,CountryCode - streams its rows\n  3. FROM country.csv - streams its rows\n  4. SELECT CountryCode,Continent - streams its rows\n  5. JOIN (subquery) CountryCode - processes its whole input at once\n  6. SELECT CityName,Continent - streams its rows\n");
+}
+
+#[test]
+fn test_tail_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv ORDERBY CityPop TAIL 3 SELECT CityName,CityPop\nexit\n")
+        .assert()
+        .stdout("CityName    | CityPop\n------------+--------\nFakaofo     |     300\nWest_Island |     167\nAdamstown   |      42\n\n");
+}
+
+#[test]
+fn test_countby_bars_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM language.csv COUNTBY Language BARS TAKE 4\nexit\n")
+        .assert()
+        .stdout("Language | count | bar\n---------+-------+---------------------------------------------\nEnglish  |    60 | ############################################\nArabic   |    33 | ########################\nSpanish  |    28 | #####################\nFrench   |    25 | ##################\n\n");
+}
+
+#[test]
+fn test_quoted_value_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-quoted.csv");
+    std::fs::write(
+        &path,
+        "Station,Riders\nGrand Central,67000\n\"Washington, D.C.\",41000\nO'Hare,12000\n",
+    )
+    .unwrap();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "FROM {0} FILTER Station = 'Grand Central'\nFROM {0} WHERE Station != \"Washington, D.C.\" SELECT Station,'n/a, yet' AS Note\nFROM {0} FILTER Station = 'O\\'Hare' SELECT Riders\nFROM {0} FILTER Station = 'Grand\nexit\n",
+            path.display()
+        ))
+        .assert()
+        .stdout("Station       | Riders\n--------------+-------\nGrand Central |  67000\n\nStation       | Note\n--------------+---------\nGrand Central | n/a, yet\nO'Hare        | n/a, yet\n\nRiders\n------\n 12000\n\nMalformed input. Missing closing quote: 'Grand\n");
+}
+
+#[test]
+fn test_skip_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv ORDERBY CityPop SKIP 2 TAKE 2 SELECT CityName,CityPop\nexit\n")
+        .assert()
+        .stdout("CityName    | CityPop\n------------+--------\nSâ€žo_Paulo | 9968485\nShanghai    | 9696300\n\n");
+}
+
+#[test]
+fn test_explain_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("EXPLAIN FROM city.csv ORDERBY CityPop TAKE 0\nexit\n")
+        .assert()
+        .stdout("Warning: TAKE 0 always produces an empty table.\nOperator tree:\n  TAKE 0\n    ORDERBY CityPop\n      FROM city.csv\nEvaluation order:\n  1. FROM city.csv - streams its rows\n  2. ORDERBY CityPop - processes its whole input at once\n  3. TAKE 0 - streams its rows\n");
+}
+
+#[test]
+fn test_end_of_input_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv TAKE 1 SELECT CityName")
+        .assert()
+        .success()
+        .stdout("CityName\n--------\nKabul\n\n");
+}
+
+#[test]
+fn test_join_on_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv JOIN city.csv ON Capital=CityID ORDERBY CountryPop TAKE 3 SELECT CountryName,CityName\nexit\n")
+        .assert()
+        .stdout("CountryName   | CityName\n--------------+-----------\nChina         | Peking\nIndia         | New_Delhi\nUnited_States | Washington\n\n");
+}
+
+#[test]
+fn test_countby_order_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM language.csv COUNTBY CountryCode TAKE 3\nFROM language.csv COUNTBY CountryCode ORDER value TAKE 3\nexit\n")
+        .assert()
+        .stdout("CountryCode | count\n------------+------\nCAN         |    12\nCHN         |    12\nIND         |    12\n\nCountryCode | count\n------------+------\nABW         |     4\nAFG         |     5\nAGO         |     9\n\n");
+}
+
+#[test]
+fn test_load_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("LOAD cities FROM data/city.csv\nFROM country JOIN cities ON Capital=CityID TAKE 2 SELECT CountryName,CityName\nLOAD country FROM data/city.csv\nexit\n")
+        .assert()
+        .stdout("Registered cities for data/city.csv.\nCountryName | CityName\n------------+-----------\nAruba       | Oranjestad\nAfghanistan | Kabul\n\nMalformed input. country is the name of a built-in dataset.\n");
+}
+
+#[test]
+fn test_set_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("SET big_cities = FROM city.csv WHERE CityPop > 9000000\nFROM @big_cities JOIN country CountryCode SELECT CityName,CountryName TAKE 2\nFROM @small_cities\nexit\n")
+        .assert()
+        .stdout("Saved 6 rows as @big_cities.\nCityName    | CountryName\n------------+------------\nSâ€žo_Paulo | Brazil\nJakarta     | Indonesia\n\nNo result is saved as @small_cities. Use SET small_cities = <query> to save one.\n");
+}
+
+#[test]
+fn test_insert_delete_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("INSERT INTO language VALUES (ATL, Atlantean), (ATL, 'Old Atlantean')\nDELETE FROM language WHERE Language = Atlantean\nFROM language FILTER CountryCode = ATL\nINSERT INTO city VALUES (1, Atlantis, ATL, many)\nexit\n")
+        .assert()
+        .stdout("Inserted 2 rows into language.csv.\nDeleted 1 row from language.csv.\nCountryCode | Language\n------------+--------------\nATL         | Old Atlantean\n\nCannot INSERT \"many\" into the CityPop column, as it holds numbers.\n");
+}
+
+#[test]
+fn test_delete_cmd_count() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("DELETE FROM country WHERE CountryCode = ABW\nDELETE FROM country WHERE Continent = Antarctica\nDELETE FROM country WHERE CountryCode = ABW\nexit\n")
+        .assert()
+        .stdout("Deleted 1 row from country.csv.\nDeleted 5 rows from country.csv.\nDeleted 0 rows from country.csv.\n");
+}
+
+#[test]
+fn test_save_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-save.csv");
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "INSERT INTO language VALUES (ATL, 'Old Atlantean, spoken')\nSAVE language {0}\nFROM {0} TAIL 2\nSAVE @none\nexit\n",
+            path.display()
+        ))
+        .assert()
+        .stdout(format!("Inserted 1 row into language.csv.\nSaved 985 rows of language.csv to {}.\nCountryCode | Language\n------------+----------------------\nZWE         | Shona\nATL         | Old Atlantean, spoken\n\nMalformed input. SAVE @none must be followed by the path of the CSV file to write.\n", path.display()));
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "SET aruba = FROM country TAKE 1\nSAVE @aruba {}\nexit\n",
+            path.display()
+        ))
+        .assert()
+        .stdout(format!(
+            "Saved 1 row as @aruba.\nSaved 1 row of @aruba to {}.\n",
+            path.display()
+        ));
+}
+
+#[test]
+fn test_join_strategy_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("EXPLAIN FROM city ORDERBY CountryCode JOIN country CountryCode\nFROM city ORDERBY CityID ASC TAKE 2 JOIN country.csv ON CountryCode=CountryCode USING MERGE SELECT CityName,CountryName\nexit\n")
+        .assert()
+        .stdout("Operator tree:\n  JOIN country.csv CountryCode USING MERGE\n    ORDERBY CountryCode\n      FROM city.csv\nEvaluation order:\n  1. FROM city.csv - streams its rows\n  2. ORDERBY CountryCode - processes its whole input at once\n  3. JOIN country.csv CountryCode USING MERGE - processes its whole input at once\nCityName | CountryName\n---------+------------\nKabul    | Afghanistan\nQandahar | Afghanistan\n\n");
+}
+
+#[test]
+fn test_describe_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("DESCRIBE language\nDESCRIBE @missing\nexit\n")
+        .assert()
+        .stdout("column      | type   | nulls | distinct | min | max | samples\n------------+--------+-------+----------+-----+-----+---------------------------\nCountryCode | string |     0 |      233 |     |     | ABW, AFG, AGO\nLanguage    | string |     0 |      457 |     |     | Dutch, English, Papiamento\n\nNo result is saved as @missing. Use SET missing = <query> to save one.\n");
+}
+
+#[test]
+fn test_having_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM language.csv COUNTBY Language HAVING count >= 19\nFROM city.csv GROUPBY CountryCode SUM(CityPop) HAVING SUM(CityPop) > 100000000\nFROM language.csv COUNTBY Language HAVING total > 1\nexit\n")
+        .assert()
+        .stdout("Language | count\n---------+------\nEnglish  |    60\nArabic   |    33\nSpanish  |    28\nFrench   |    25\nGerman   |    19\nChinese  |    19\n\nCountryCode | SUM(CityPop)\n------------+-------------\nIND         |    123298526\nCHN         |    175953614\n\nCould not find the total column to HAVING on the table produced by this operator chain: FROM language.csv COUNTBY Language\n");
+}
+
+#[test]
+fn test_sample_cmd() {
+    let output = Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv SAMPLE 3 SEED 42\nFROM city.csv SAMPLE 3 SEED 42\nFROM language.csv SAMPLE 1%\nexit\n")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let results: Vec<&str> = output.split("\n\n").collect();
+    // The header and its underline, and the sampled rows.
+    assert_eq!(results[0].lines().count(), 2 + 3);
+    // The same seed samples the same rows.
+    assert_eq!(results[0], results[1]);
+    assert_eq!(results[2].lines().count(), 2 + 10);
+}
+
+#[test]
+fn test_continuation_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM language.csv \\\n  TAKE 2\nexit\n")
+        .assert()
+        .success()
+        .stdout("CountryCode | Language\n------------+---------\nABW         | Dutch\nABW         | English\n\n");
+}
+
+#[test]
+fn test_statements_cmd() {
+    // Each statement reports its own errors, and those after an exit aren't run.
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("\\format csv; FROM city.csv TAKE 1; FRM country.csv;FROM language.csv FILTER Language = 'a;b' ;\nexit; FROM city.csv\n")
+        .assert()
+        .success()
+        .stdout("Output format set to csv.\nCityID,CityName,CountryCode,CityPop\n1,Kabul,AFG,1780000\n\nMalformed input. Invalid Input: FRM is not an operator.\nCountryCode,Language\n\n");
+}
+
+#[test]
+fn test_history_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(
+            "FROM language.csv TAKE 1\n\\format csv\n\\history\n\\rerun 1\n\\rerun 9\nexit\n",
+        )
+        .assert()
+        .success()
+        .stdout("CountryCode | Language\n------------+---------\nABW         | Dutch\n\nOutput format set to csv.\n   1  FROM language.csv TAKE 1\n   2  \\format csv\nquery> FROM language.csv TAKE 1\nCountryCode,Language\nABW,Dutch\n\nThere is no query 9 in the history. Use \\history to list them.\n");
+}
+
+#[test]
+fn test_piped_queries() {
+    // Without an exit, the end of the input exits, and only the results are printed.
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM language.csv TAKE 2\n")
+        .assert()
+        .success()
+        .stdout("CountryCode | Language\n------------+---------\nABW         | Dutch\nABW         | English\n\n");
+}
+
+#[test]
+fn test_quiet_arg() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["-c", "exit"])
+        .assert()
+        .success()
+        .stdout("Goodbye!\n");
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--quiet", "-c", "exit"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_union_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv FILTER Continent = Antarctica SELECT CountryCode UNION ALL (FROM country.csv FILTER CountryCode = ATA SELECT CountryCode) COUNTBY CountryCode\nFROM city.csv UNION (FROM country.csv)\nFROM country.csv SELECT CountryCode UNION ALL (FROM language.csv SELECT CountryCode) TAKE 2\nFROM city.csv UNION FROM country.csv TAKE 2\nexit\n")
+        .assert()
+        .success()
+        .stdout("CountryCode | count\n------------+------\nATA         |     2\nATF         |     1\nBVT         |     1\nHMD         |     1\nSGS         |     1\n\nUNION can't combine the columns [CityID, CityName, CountryCode, CityPop] with the columns [CountryCode, CountryName, Continent, CountryPop, Capital]. Both tables must have the same number of columns.\nCountryCode\n-----------\nABW\nAFG\n\nMalformed input. UNION must be followed by the query to combine with, in parentheses, e.g. (FROM language.csv SELECT CountryCode).\n");
+}
+
+#[test]
+fn test_string_predicates_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv WHERE CityName LIKE 'San_Jos%' SELECT CityName,CountryCode\nFROM country.csv WHERE CountryName ICONTAINS 'south' SELECT CountryName\nFROM city.csv WHERE CityPop STARTSWITH 1 TAKE 1\nexit\n")
+        .assert()
+        .success()
+        .stdout("CityName            | CountryCode\n--------------------+------------\nSan_JosÃˆ           | CRI\nSan_JosÃˆ_del_Monte | PHL\nSan_Jose            | PHL\nSan_Jose            | PHL\nSan_Jose            | USA\n\nCountryName\n--------------------------------------------\nFrench_Southern_territories\nSouth_Korea\nSouth_Georgia_and_the_South_Sandwich_Islands\nSouth_Africa\n\nSTARTSWITH only applies to strings, but CityPop has numeric values.\n");
+}
+
+#[test]
+fn test_matches_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv WHERE CityName MATCHES '^New_.*' SELECT CityName TAKE 3\nFROM country.csv WHERE CountryName IMATCHES '^(north|south)_' SELECT CountryName\nFROM city.csv WHERE CityName MATCHES '(New'\nexit\n")
+        .assert()
+        .success()
+        .stdout("CityName\n----------\nNew_Bombay\nNew_Delhi\nNew_York\n\nCountryName\n--------------------------------------------\nSouth_Korea\nNorth_Korea\nSouth_Georgia_and_the_South_Sandwich_Islands\nSouth_Africa\n\nMalformed input. Invalid regular expression (New: unclosed group\n");
+}
+
+#[test]
+fn test_cast_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv CAST CityPop AS STRING CAST CityPop AS FLOAT ORDERBY CityPop TAKE 2\nFROM country.csv FILTER Continent = Antarctica CAST Capital AS INT SELECT CountryCode,Capital TAKE 2\nFROM city.csv CAST CityName AS INT\nexit\n")
+        .assert()
+        .success()
+        .stdout("CityID | CityName        | CountryCode |  CityPop\n-------+-----------------+-------------+---------\n  1024 | Mumbai_(Bombay) | IND         | 10500000\n  2331 | Seoul           | KOR         |  9981619\n\nCountryCode | Capital\n------------+--------\nATA         |\nATF         |\n\nCannot CAST the value Kabul of the CityName column to INT.\n");
+}
+
+#[test]
+fn test_badrows_setting() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-bad-rows.csv");
+    std::fs::write(
+        &path,
+        "Name,Pop\nAruba,103000\nAnguilla\nAngola,12878000,AGO\n",
+    )
+    .unwrap();
+    let path = path.to_string_lossy();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!("\\badrows skip\nFROM {path}\n\\badrows null\nFROM {path}\n\\badrows error\nFROM {path}\nexit\n"))
+        .assert()
+        .success()
+        .stdout(format!("Bad rows are skipped.\nName  |    Pop\n------+-------\nAruba | 103000\n\n2 rows skipped (line numbers 3, 4) in {path}.\nBad rows are filled with missing values.\nName     |      Pop\n---------+---------\nAruba    |   103000\nAnguilla |\nAngola   | 12878000\n\n2 rows filled with missing values (line numbers 3, 4) in {path}.\nBad rows fail the query.\nFailed to load the {path} dataset while processing the FROM command. Error encountered: CSV error: record 2 (line: 3, byte: 22): found record with 1 fields, but the previous record has 2 fields. Use '\\badrows skip' or '\\badrows null' to load the other rows.\n"));
+}
+
+#[test]
+fn test_normalize_setting() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-normalize.csv");
+    std::fs::write(
+        &path,
+        "Name,Continent\nAruba,North_America\nAnguilla, North America \n",
+    )
+    .unwrap();
+    let path = path.to_string_lossy();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!("FROM {path} COUNTBY Continent\n\\normalize trim,underscores\nFROM {path} COUNTBY Continent\nexit\n"))
+        .assert()
+        .success()
+        .stdout("Continent       | count\n----------------+------\nNorth_America   |     1\n North America  |     1\n\nValues are normalized while loading: trim,underscores.\nContinent     | count\n--------------+------\nNorth America |     2\n\n");
+}
+
+#[test]
+fn test_gzip_cmd() {
+    use std::io::Write;
+    let path = std::env::temp_dir().join("toy-query-engine-cli-gzip.csv.gz");
+    let mut encoder = flate2::write::GzEncoder::new(
+        std::fs::File::create(&path).unwrap(),
+        flate2::Compression::default(),
+    );
+    encoder
+        .write_all(b"Name,Pop\nAruba,103000\nAnguilla,8000\n")
+        .unwrap();
+    encoder.finish().unwrap();
+    let path = path.to_string_lossy();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!("FROM {path} ORDERBY Pop\nexit\n"))
+        .assert()
+        .success()
+        .stdout("Name     |    Pop\n---------+-------\nAruba    | 103000\nAnguilla |   8000\n\n");
+}
+
+#[test]
+fn test_url_unreachable_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM http://127.0.0.1:1/cities.csv TAKE 2\nexit\n")
+        .assert()
+        .success()
+        .stdout("Failed to load the http://127.0.0.1:1/cities.csv dataset while processing the FROM command. Error encountered: error sending request for url (http://127.0.0.1:1/cities.csv)\n");
+}
+
+#[test]
+fn test_attach_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-attach.sqlite");
+    let _ = std::fs::remove_file(&path);
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE planets (Planet TEXT, Moons INTEGER, Radius REAL);
+             INSERT INTO planets VALUES ('Mercury', 0, 2439.7), ('Earth', 1, NULL), ('Saturn', 146, 58232.0);",
+        )
+        .unwrap();
+    let path = path.to_string_lossy();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!("ATTACH {path}\nFROM planets ORDERBY Moons\nATTACH\nexit\n"))
+        .assert()
+        .success()
+        .stdout(format!("Attached {path}: planets.\nPlanet  | Moons | Radius\n--------+-------+-------\nSaturn  |   146 |  58232\nEarth   |     1 |\nMercury |     0 | 2439.7\n\nMalformed input. ATTACH must be followed by the path of the SQLite database.\n"));
+}
+
+#[test]
+fn test_save_load_session_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-session.toml");
+    let path = path.to_string_lossy();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!("LOAD cities FROM data/city.csv\n\\format csv\nSET big = FROM cities FILTER CityPop > 9000000 SELECT CityName\nSET big = FROM @big TAKE 2\n\\save {path}\nexit\n"))
+        .assert()
+        .success();
+    // A new session picks up where the saved one left off.
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!("\\load {path}\nFROM @big\nFROM cities TAKE 1\n\\load missing.toml\nexit\n"))
+        .assert()
+        .success()
+        .stdout(format!("Saved 6 rows as @big.\nSaved 2 rows as @big.\nLoaded the session from {path}.\nCityName\nSâ€žo_Paulo\nJakarta\n\nCityID,CityName,CountryCode,CityPop\n1,Kabul,AFG,1780000\n\nFailed to load the session from missing.toml: No such file or directory (os error 2)\n"));
+}
+
+#[test]
+fn test_watch_cmd() {
+    use std::io::{BufRead, BufReader, Write};
+    let path = std::env::temp_dir().join("toy-query-engine-cli-watch.csv");
+    std::fs::write(&path, "Status\nok\nfail\n").unwrap();
+    let path = path.to_string_lossy().to_string();
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_toy-query-engine"))
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Watching only ends with Ctrl-C, so stdin is kept open and the process killed instead.
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "\\format csv\nWATCH FROM {path} COUNTBY Status").unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut read_lines = |count: usize| {
+        let mut lines = String::new();
+        for _ in 0..count {
+            stdout.read_line(&mut lines).unwrap();
+        }
+        lines
+    };
+    assert_eq!(
+        read_lines(6),
+        format!("Output format set to csv.\nStatus,count\nok,1\nfail,1\n\nWatching {path} for changes. Press Ctrl-C to stop.\n")
+    );
+
+    // Appending to the file runs the query again.
+    let mut file = std::fs::File::options().append(true).open(&path).unwrap();
+    writeln!(file, "ok").unwrap();
+    file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+    assert_eq!(
+        read_lines(5),
+        format!("{path} changed, running the query again.\nStatus,count\nok,2\nfail,1\n\n")
+    );
+    child.kill().unwrap();
+    child.wait().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // Only files are watched.
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("SET one = FROM city.csv TAKE 1\nWATCH FROM @one COUNT\nWATCH\nexit\n")
+        .assert()
+        .success()
+        .stdout("Saved 1 row as @one.\nWATCH needs a query that reads a file, e.g. WATCH FROM log.csv COUNT.\nMalformed input. WATCH must be followed by the query to watch.\n");
+}
+
+#[test]
+fn test_prompt_setting() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("\\prompt 'sql> '\nFROM language.csv TAKE 1\n\\rerun 2\n\\prompt\nexit\n")
+        .assert()
+        .success()
+        .stdout("Prompt set to 'sql> '.\nCountryCode | Language\n------------+---------\nABW         | Dutch\n\nsql> FROM language.csv TAKE 1\nCountryCode | Language\n------------+---------\nABW         | Dutch\n\nMalformed input. \\prompt must be followed by the text of the prompt, e.g. 'sql> '.\n");
+}
+
+#[test]
+fn test_count_rows_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(
+            "FROM city.csv FILTER CityPop > 1000000 COUNT\nFROM country.csv COUNT *\nexit\n",
+        )
+        .assert()
+        .success()
+        .stdout("COUNT(*)\n--------\n     237\n\nCOUNT(*)\n--------\n     239\n\n");
+}
+
+#[test]
+fn test_topn_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv TOPN 2 BY CountryCode ORDER CityPop TAKE 4\nFROM city.csv TOPN 1 BY CountryCode ORDER CityName\nexit\n")
+        .assert()
+        .success()
+        .stdout("CityID | CityName  | CountryCode | CityPop\n-------+-----------+-------------+--------\n     1 | Kabul     | AFG         | 1780000\n     2 | Qandahar  | AFG         |  237500\n     5 | Amsterdam | NLD         |  731200\n     6 | Rotterdam | NLD         |  593321\n\nYou attempted to TOPN the CityName column whose type is not numeric.\n");
+}
+
+#[test]
+fn test_rank_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv RANK BY CityPop PER CountryCode FILTER rank <= 2 TAKE 4\nFROM city.csv RANK BY CityName\nexit\n")
+        .assert()
+        .success()
+        .stdout("CityID | CityName  | CountryCode | CityPop | rank\n-------+-----------+-------------+---------+-----\n     1 | Kabul     | AFG         | 1780000 |    1\n     2 | Qandahar  | AFG         |  237500 |    2\n     5 | Amsterdam | NLD         |  731200 |    1\n     6 | Rotterdam | NLD         |  593321 |    2\n\nYou attempted to RANK the CityName column whose type is not numeric.\n");
+}
+
+#[test]
+fn test_pivot_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM language.csv JOIN country.csv CountryCode PIVOT Language Continent COUNT(CountryCode) TAKE 3\nexit\n")
+        .assert()
+        .success()
+        .stdout("Language   | North_America | Asia | Africa | Europe | South_America | Oceania\n-----------+---------------+------+--------+--------+---------------+--------\nDutch      |             3 |    0 |      0 |      2 |             0 |       0\nEnglish    |            19 |    8 |      5 |      8 |             1 |      19\nPapiamento |             2 |    0 |      0 |      0 |             0 |       0\n\n");
+}
+
+#[test]
+fn test_select_all_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv SELECT * EXCEPT Capital,CountryName TAKE 2\nFROM country.csv SELECT * TAKE 1\nexit\n")
+        .assert()
+        .success()
+        .stdout("CountryCode | Continent     | CountryPop\n------------+---------------+-----------\nABW         | North_America |     103000\nAFG         | Asia          |   22720000\n\nCountryCode | CountryName | Continent     | CountryPop | Capital\n------------+-------------+---------------+------------+--------\nABW         | Aruba       | North_America |     103000 |     129\n\n");
+}
+
+#[test]
+fn test_explain_analyze_cmd() {
+    let output = Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("EXPLAIN ANALYZE FROM language.csv TAKE 3\nexit\n")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "Operator tree:");
+    assert!(lines[1].starts_with("  TAKE 3 (3 rows, "));
+    assert!(lines[2].starts_with("    FROM language.csv (3 rows, "));
+    assert!(lines[3].starts_with("Produced 3 rows in "));
+    assert_eq!(lines.len(), 4);
+}
+
+#[test]
+fn test_orderby_asc_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv ORDERBY CountryPop ASC TAKE 3 SELECT CountryName,CountryPop\nexit\n")
+        .assert()
+        .stdout("CountryName                 | CountryPop\n----------------------------+-----------\nAntarctica                  |          0\nFrench_Southern_territories |          0\nBouvet_Island               |          0\n\n");
+}
+
+#[test]
+fn test_aggregate_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv SUM CityPop\nFROM country.csv MAX Capital\nexit\n")
+        .assert()
+        .stdout("SUM(CityPop)\n------------\n  1429559884\n\nMAX(Capital)\n------------\n        4074\n\n");
+}
+
+#[test]
+fn test_groupby_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv GROUPBY CountryCode SUM(CityPop),COUNT(CityID) TAKE 2\nexit\n")
+        .assert()
+        .stdout("CountryCode | SUM(CityPop) | COUNT(CityID)\n------------+--------------+--------------\nAFG         |      2332100 |             4\nNLD         |      5180049 |            28\n\n");
+}
+
+#[test]
+fn test_format_csv_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("\\format csv\nFROM language.csv TAKE 2\n\\format table\nFROM language.csv TAKE 2\nexit\n")
+        .assert()
+        .stdout("Output format set to csv.\nCountryCode,Language\nABW,Dutch\nABW,English\n\nOutput format set to table.\nCountryCode | Language\n------------+---------\nABW         | Dutch\nABW         | English\n\n");
+}
+
+#[test]
+fn test_format_jsonl_markdown_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("\\format jsonl\nFROM city.csv TAKE 2 SELECT CityName,CityPop\n\\format markdown\nFROM city.csv TAKE 2 SELECT CityName,CityPop\n\\format json\nexit\n")
+        .assert()
+        .stdout("Output format set to jsonl.\n{\"CityName\":\"Kabul\",\"CityPop\":1780000}\n{\"CityName\":\"Qandahar\",\"CityPop\":237500}\n\nOutput format set to markdown.\n| CityName | CityPop |\n| --- | ---: |\n| Kabul | 1780000 |\n| Qandahar | 237500 |\n\nMalformed input. Unknown output format: json. Must be 'table', 'csv', 'jsonl', or 'markdown'.\n");
+}
+
+#[test]
+fn test_format_argument_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--format", "csv", "-c", "FROM language.csv TAKE 1"])
+        .assert()
+        .success()
+        .stdout("CountryCode,Language\nABW,Dutch\n\n");
+}
+
+#[test]
+fn test_export_arrow_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-export.arrow");
+    let mut cmd = Command::cargo_bin("toy-query-engine").unwrap();
+    cmd.write_stdin(format!(
+        "FROM country.csv TAKE 3 EXPORT ARROW {}\nexit\n",
+        path.display()
+    ))
+    .assert()
+    .stdout(format!("Wrote 3 rows to {}.\n", path.display()));
+    // Arrow IPC files start and end with the ARROW1 magic bytes.
+    let contents = std::fs::read(&path).unwrap();
+    assert!(contents.starts_with(b"ARROW1"));
+    assert!(contents.ends_with(b"ARROW1"));
+}
+
+#[test]
+fn test_show_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("LOAD cities FROM data/city.csv\nSET big = FROM city.csv TAKE 1\nSHOW TABLES\nSHOW COLUMNS language\nSHOW COLUMNS nowhere\nexit\n")
+        .assert()
+        .stdout("Registered cities for data/city.csv.\nSaved 1 row as @big.\nname     | kind     | source\n---------+----------+------------------\ncity     | built-in | data/city.csv\ncountry  | built-in | data/country.csv\nlanguage | built-in | data/language.csv\ncities   | file     | data/city.csv\n@big     | variable |\n\ncolumn      | type   | numeric\n------------+--------+--------\nCountryCode | string | no\nLanguage    | string | no\n\nMalformed input. Invalid dataset passed to SHOW COLUMNS: nowhere\n");
+}
+
+#[test]
+fn test_help_lists_loaded_datasets_cmd() {
+    let mut cmd = Command::cargo_bin("toy-query-engine").unwrap();
+    let assert = cmd
+        .write_stdin("LOAD cities FROM data/city.csv\nhelp\nexit\n")
+        .assert()
+        .success();
+    let output = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(output.ends_with(
+        "      <numeric-column-name> : []\n  <dataset> : cities (data/city.csv)\n      <column-name> : [CityID, CityName, CountryCode, CityPop]\n\n"
+    ));
+}
+
+#[test]
+fn test_extend_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv TAKE 2 EXTEND PopDensity = CityPop / 1000,Name = UPPER(CityName)\nFROM city.csv EXTEND CityPop = 1\nexit\n")
+        .assert()
+        .success()
+        .stdout("CityID | CityName | CountryCode | CityPop | PopDensity | Name\n-------+----------+-------------+---------+------------+---------\n     1 | Kabul    | AFG         | 1780000 |       1780 | KABUL\n     2 | Qandahar | AFG         |  237500 |      237.5 | QANDAHAR\n\nCannot EXTEND the table with a CityPop column, as it already has one.\n");
+}
+
+#[test]
+fn test_fillna_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv FILLNA Capital 0 ORDERBY Capital ASC TAKE 2\nFROM country.csv FILTER Capital IS NULL SELECT CountryName,COALESCE(Capital, -1) AS Capital TAKE 2\nexit\n")
+        .assert()
+        .success()
+        .stdout("CountryCode | CountryName                 | Continent  | CountryPop | Capital\n------------+-----------------------------+------------+------------+--------\nATA         | Antarctica                  | Antarctica |          0 |       0\nATF         | French_Southern_territories | Antarctica |          0 |       0\n\nCountryName                 | Capital\n----------------------------+--------\nAntarctica                  |      -1\nFrench_Southern_territories |      -1\n\n");
+}
+
+#[test]
+fn test_column_suggestions_cmd() {
+    let mut cmd = Command::cargo_bin("toy-query-engine").unwrap();
+    cmd.write_stdin("FROM city TAKE 2 ORDERBY citypop SELECT CityName\nFROM city SELECT CityPpo\nFROM city SELECT cityname,citypop * 2 TAKE 1\nFROM city GROUPBY countrycode SUM(citypop) HAVING SUM(citypop) > 150000000\n")
+        .assert()
+        .success()
+        .stdout("CityName\n--------\nKabul\nQandahar\n\nCould not find the CityPpo column to Select on the table produced by this operator chain: FROM city.csv. Did you mean CityPop?\nCityName | CityPop*2\n---------+----------\nKabul    |   3560000\n\nCountryCode | SUM(CityPop)\n------------+-------------\nCHN         |    175953614\n\n");
+}
+
+#[test]
+fn test_sql_syntax_cmd() {
+    let mut cmd = Command::cargo_bin("toy-query-engine").unwrap();
+    cmd.write_stdin("\\syntax sql\nSELECT CityName FROM city ORDER BY CityPop DESC LIMIT 3\nSELECT Continent, COUNT(*) AS Countries FROM country GROUP BY Continent ORDER BY Countries DESC LIMIT 2\nSELECT CityName FROM city WHERE CityPop > 1 OR CityPop < 0\n")
+        .assert()
+        .success()
+        .stdout("Query syntax set to sql.\nCityName\n---------------\nMumbai_(Bombay)\nSeoul\nSâ€žo_Paulo\n\nContinent | Countries\n----------+----------\nAfrica    |        58\nAsia      |        51\n\nMalformed input. The conditions of WHERE can only be combined with AND, not OR.\n");
+}
+
+#[test]
+fn test_serve_cmd() {
+    let output = Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--serve", "nowhere"])
+        .assert()
+        .code(2)
+        .get_output()
+        .stdout
+        .clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .starts_with("Failed to listen on nowhere: "));
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--serve", "127.0.0.1:0", "-c", "FROM city"])
+        .assert()
+        .code(2)
+        .stdout(
+            "--serve can't be used together with --command or --file.\nUsage: toy-query-engine [--profile-out <path>] [--script <path>] [--encoding <label>] [--format <name>] [--quiet] [-c <query> | --file <path> | --serve <address> [--allow-origin <origin>...] | --listen <address>]\n",
+        );
+}
+
+#[test]
+fn test_listen_cmd() {
+    let output = Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--listen", "nowhere"])
+        .assert()
+        .code(2)
+        .get_output()
+        .stdout
+        .clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .starts_with("Failed to listen on nowhere: "));
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--listen", "127.0.0.1:0", "--script", "no/such/script.rhai"])
+        .assert()
+        .code(2)
+        .stdout("Failed to load the script: No such file or directory (os error 2)\n");
+}
+
+#[test]
+fn test_cache_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM language.csv FILTER CountryCode = ABW COUNT\nINSERT INTO language.csv VALUES (ABW, Spanish)\nFROM language.csv FILTER CountryCode = ABW COUNT\n\\cache off\nFROM language.csv FILTER CountryCode = ABW COUNT\n\\cache maybe\nexit\n")
+        .assert()
+        .stdout("COUNT(*)\n--------\n       4\n\nInserted 1 row into language.csv.\nCOUNT(*)\n--------\n       5\n\nResult cache is off.\nCOUNT(*)\n--------\n       5\n\nMalformed input. \\cache must be followed by 'on' or 'off'.\n");
+}
+
+#[test]
+fn test_result_limits_cmd() {
+    let mut cmd = Command::cargo_bin("toy-query-engine").unwrap();
+    let assert = cmd
+        .write_stdin(
+            "\\maxrows 100\nFROM city.csv JOIN country.csv CountryCode\n\\maxrows off\n\\maxbytes 1KB\nFROM country.csv\n\\maxbytes off\nFROM country.csv TAKE 1 SELECT CountryCode\n",
+        )
+        .assert()
+        .success();
+    let output = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(output.contains("Results are limited to 100 rows."));
+    assert!(output.contains(
+        "The result of FROM city.csv grew past the limit of 100 rows, so the query was stopped."
+    ));
+    assert!(output.contains("Results are limited to 1.0 KB of memory."));
+    assert!(output.contains("The result of FROM country.csv grew past the limit of 1.0 KB"));
+    assert!(output.contains("Results are not limited in memory."));
+    assert!(output.contains("CountryCode\n-----------\nABW\n"));
+}
+
+#[test]
+fn test_timing_cmd() {
+    let output = Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(
+            "\\timing on\nFROM language.csv TAKE 2\n\\timing off\nFROM language.csv TAKE 1\nexit\n",
+        )
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Timing is on.\nCountryCode | Language\n"));
+    assert!(output.contains("ABW         | English\n\n2 rows ("));
+    assert!(output.contains("s, 2 rows scanned\nTiming is off.\n"));
+    // The elapsed time is only printed while timing is on.
+    assert_eq!(output.matches(" in ").count(), 1);
+}
+
+#[test]
+fn test_into_cmd() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-into.csv");
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "FROM country.csv ORDERBY CountryPop TAKE 2 SELECT CountryName,CountryPop INTO {}\nexit\n",
+            path.display()
+        ))
+        .assert()
+        .stdout(format!("Wrote 2 rows to {}.\n", path.display()));
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "CountryName,CountryPop\nChina,1277558000\nIndia,1013662000\n"
+    );
+}
+
+#[test]
+fn test_distinct_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM city.csv ORDERBY CityPop DISTINCT CountryCode TAKE 3 SELECT CountryCode,CityName\nexit\n")
+        .assert()
+        .stdout("CountryCode | CityName\n------------+----------------\nIND         | Mumbai_(Bombay)\nKOR         | Seoul\nBRA         | Sâ€žo_Paulo\n\n");
+}
+
+#[test]
+fn test_leftjoin_cmd() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin("FROM country.csv FILTER Continent = Antarctica LEFTJOIN city.csv CountryCode SELECT CountryCode,CityName TAKE 2\nexit\n")
+        .assert()
+        .stdout("Warning: CountryCode is not a key column of city.csv; each row may match several rows and multiply the size of the result.\nCountryCode | CityName\n------------+---------\nATA         |\nATF         |\n\n");
+}
+
+#[test]
+fn test_command_arg() {
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["-c", "FROM city.csv TAKE 2 SELECT CityName"])
+        .assert()
+        .success()
+        .stdout("CityName\n--------\nKabul\nQandahar\n\n");
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--command", "FROM city.csv ORDERBY CityName"])
+        .assert()
+        .code(1)
+        .stdout("You attempted to ORDERBY the CityName column whose type is not numeric.\n");
+}
+
+#[test]
+fn test_encoding_arg() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-latin1.csv");
+    std::fs::write(&path, b"City,Code\nS\xe3o_Paulo,BRA\nZ\xfcrich,CHE\n").unwrap();
+    let query = format!("\\format csv\nFROM {} SELECT City", path.display());
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--encoding", "latin1", "-c", &query])
+        .assert()
+        .success()
+        .stdout("Output format set to csv.\nCity\nSão_Paulo\nZürich\n\n");
+    // An encoding given to LOAD only applies to the loaded file.
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .write_stdin(format!(
+            "LOAD cities FROM {} ENCODING latin1\n\\format csv\nFROM cities SELECT City\nFROM city.csv FILTER CityID = 206 SELECT CityName\nexit\n",
+            path.display()
+        ))
+        .assert()
+        .success()
+        .stdout(format!(
+            "Registered cities for {}.\nOutput format set to csv.\nCity\nSão_Paulo\nZürich\n\nCityName\nSâ€žo_Paulo\n\n",
+            path.display()
+        ));
+    let output = Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["-c", &query])
+        .assert()
+        .code(1)
+        .get_output()
+        .stdout
+        .clone();
+    assert!(String::from_utf8(output)
+        .unwrap()
+        .contains("is not valid UTF-8 text. Use --encoding to read it in another encoding."));
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--encoding", "klingon"])
+        .assert()
+        .code(2)
+        .stdout("Unknown encoding: klingon. Use a label such as utf-8, latin1, or windows-1252.\nUsage: toy-query-engine [--profile-out <path>] [--script <path>] [--encoding <label>] [--format <name>] [--quiet] [-c <query> | --file <path> | --serve <address> [--allow-origin <origin>...] | --listen <address>]\n");
+}
+
+#[test]
+fn test_file_arg() {
+    let path = std::env::temp_dir().join("toy-query-engine-cli-queries.txt");
+    std::fs::write(
+        &path,
+        "\\format csv\nFROM city.csv TAKE 1\nFRM city.csv\nFROM country.csv COUNT Capital\n",
+    )
+    .unwrap();
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .arg("--file")
+        .arg(&path)
+        .assert()
+        .code(1)
+        .stdout("Output format set to csv.\nCityID,CityName,CountryCode,CityPop\n1,Kabul,AFG,1780000\n\nMalformed input. Invalid Input: FRM is not an operator.\nCOUNT(Capital)\n232\n\n");
+    Command::cargo_bin("toy-query-engine")
+        .unwrap()
+        .args(["--file", "no-such-queries.txt"])
+        .assert()
+        .code(2);
 }
@@ -0,0 +1,107 @@
+use toy_query_engine::lint::LintWarning;
+use toy_query_engine::{Cell, Dataset, EngineError, QueryEngine, Row};
+
+#[test]
+fn test_engine_join_filter() {
+    let mut engine = QueryEngine::new();
+    let table = engine
+        .execute("FROM city.csv JOIN country.csv CountryCode FILTER CountryName = Netherlands ORDERBY CityPop TAKE 2 SELECT CityName,CityPop")
+        .unwrap()
+        .table;
+    assert_eq!(table.header, vec!["CityName", "CityPop"]);
+    assert_eq!(
+        table.rows[0].cells,
+        vec![Cell::String("Amsterdam".to_string()), Cell::Int64(731200)]
+    );
+    assert_eq!(table.rows[1].join(), "Rotterdam,593321");
+}
+
+#[test]
+fn test_engine_reuse() {
+    let mut engine = QueryEngine::new();
+    for count in 1..4 {
+        let table = engine
+            .execute(&format!("FROM language.csv TAKE {}", count))
+            .unwrap()
+            .table;
+        assert_eq!(table.rows.len(), count);
+    }
+}
+
+#[test]
+fn test_engine_results() {
+    let mut engine = QueryEngine::new();
+    let query = "FROM country.csv FILTER Continent = Antarctica SELECT CountryName,Capital TAKE 2";
+    let table = engine.execute(query).unwrap().table;
+    assert_eq!(engine.execute(query).unwrap().table, table);
+    let json = serde_json::to_string(&table.rows).unwrap();
+    assert_eq!(
+        json,
+        r#"[["Antarctica",null],["French_Southern_territories",null]]"#
+    );
+    let rows: std::collections::HashSet<Row> = serde_json::from_str(&json).unwrap();
+    assert_eq!(rows.len(), 2);
+}
+
+#[test]
+fn test_engine_errors() {
+    let mut engine = QueryEngine::new();
+    assert!(matches!(
+        engine.execute("FROM city.csv TAKE"),
+        Err(EngineError::ParseError(_))
+    ));
+    assert!(matches!(
+        engine.execute("exit"),
+        Err(EngineError::NotAQuery(_))
+    ));
+    assert!(matches!(
+        engine.execute("FROM city.csv SELECT Capital"),
+        Err(EngineError::OperatorError(_))
+    ));
+}
+
+#[test]
+fn test_engine_encoding() {
+    let path = std::env::temp_dir().join("toy-query-engine-engine-latin1.csv");
+    std::fs::write(&path, b"City,Code\nS\xe3o_Paulo,BRA\n").unwrap();
+    let query = format!("FROM {} SELECT City", path.display());
+    assert!(matches!(
+        QueryEngine::new().execute(&query),
+        Err(EngineError::OperatorError(_))
+    ));
+    let mut engine = QueryEngine::new();
+    engine.set_encoding(toy_query_engine::data::parse_encoding("latin1").unwrap());
+    let table = engine.execute(&query).unwrap().table;
+    assert_eq!(table.rows[0].join(), "São_Paulo");
+
+    // The bundled datasets are UTF-8, so they are read as such whatever the encoding. The name of
+    // São Paulo in city.csv was garbled before the file was saved, and stays as it is.
+    let table = engine
+        .execute("FROM city.csv FILTER CityID = 206 SELECT CityName")
+        .unwrap()
+        .table;
+    assert_eq!(table.rows[0].join(), "Sâ€žo_Paulo");
+}
+
+#[test]
+fn test_engine_metadata() {
+    let mut engine = QueryEngine::new();
+    // Only the rows TAKE asks for are read.
+    let result = engine.execute("FROM city.csv TAKE 3").unwrap();
+    assert_eq!(result.rows_scanned, 3);
+    assert!(result.warnings.is_empty());
+
+    let result = engine
+        .execute("FROM country.csv JOIN city.csv CountryCode COUNT")
+        .unwrap();
+    assert_eq!(result.table.rows[0].join(), "4079");
+    assert_eq!(result.rows_scanned, 239 + 4079);
+    assert_eq!(
+        result.warnings,
+        vec![LintWarning::JoinOnNonKeyColumn {
+            dataset: Dataset::City,
+            column_name: "CountryCode".to_string(),
+        }]
+    );
+    assert!(result.elapsed > std::time::Duration::ZERO);
+}